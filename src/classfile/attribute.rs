@@ -1,7 +1,6 @@
-use std::collections::HashMap;
-
 use crate::classfile::class_reader::get_utf8;
 use crate::error::Error;
+use crate::log::log;
 use crate::classfile::AttributeInfo::{*};
 use super::{class_reader::Reader, constant_pool::ConstantInfo};
 
@@ -10,6 +9,7 @@ pub struct BootstrapMethod {
     pub bootstrap_arguments: Vec<u16>
 }
 
+#[derive(Clone)]
 pub struct ExceptionTableEntry{
     pub start_pc: u16,
     pub end_pc: u16,
@@ -17,13 +17,7 @@ pub struct ExceptionTableEntry{
     pub catch_type: u16
 }
 
-pub struct InnerClassInfo{
-    pub innner_class_index: u16,
-    pub outter_class_index: u16,
-    pub inner_class_name_index: u16,
-    pub inner_class_access_flags: u16
-}
-
+#[derive(Clone)]
 pub struct LineNumberEntry {
     pub start_pc: u16,
     pub line_number:u16
@@ -37,14 +31,83 @@ pub struct LocalVariableTableEntry {
     pub index:u16,
 }
 
-pub struct LocalVariablTypeEntry {
-    pub start_pc: u16,
-    pub length:u16,
-    pub name_index:u16,
-    pub signature_index:u16,
-    pub index:u16,
+pub enum VerificationTypeInfo {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    Object {
+        cpool_index: u16
+    },
+    Uninitialized {
+        offset: u16
+    },
+}
+
+// same_frame_extended is folded into SameFrame, same_locals_1_stack_item_frame_extended
+// into SameLocals1StackItemFrame
+#[allow(clippy::enum_variant_names)]
+pub enum StackMapFrame {
+    SameFrame {
+        offset_delta: u16
+    },
+    SameLocals1StackItemFrame {
+        offset_delta: u16,
+        stack: VerificationTypeInfo
+    },
+    ChopFrame {
+        offset_delta: u16,
+        chopped: u8
+    },
+    AppendFrame {
+        offset_delta: u16,
+        locals: Vec<VerificationTypeInfo>
+    },
+    FullFrame {
+        offset_delta: u16,
+        locals: Vec<VerificationTypeInfo>,
+        stack: Vec<VerificationTypeInfo>
+    },
+}
+
+pub struct AttributeLength {
+    pub name: String,
+    pub declared: u32,
+    pub consumed: u32,
 }
 
+pub struct Annotation {
+    pub type_index: u16,
+    pub element_value_pairs: Vec<ElementValuePair>,
+}
+
+pub struct ElementValuePair {
+    pub element_name_index: u16,
+    pub value: ElementValue,
+}
+
+// the tag of a Const is one of BCDFIJSZs, its constant an integer, float, long, double or utf8 entry
+pub enum ElementValue {
+    Const {
+        tag: u8,
+        const_value_index: u16
+    },
+    Enum {
+        type_name_index: u16,
+        const_name_index: u16
+    },
+    Class {
+        class_info_index: u16
+    },
+    Annotation(Annotation),
+    Array(Vec<ElementValue>),
+}
+
+// the variants are named after the attributes they hold
+#[allow(clippy::enum_variant_names)]
 pub enum AttributeInfo {
     BootstrapMethodsAttribute{
         boostrap_methods: Vec<BootstrapMethod>
@@ -52,7 +115,6 @@ pub enum AttributeInfo {
     CodeAttribute {
         max_stacks:u16,
         max_locals:u16,
-        code_length:u32,
         code:Vec<u8>,
         exception_table:Vec<ExceptionTableEntry>,
         attributes: Vec<AttributeInfo>
@@ -60,62 +122,62 @@ pub enum AttributeInfo {
     ConstantValueAttribute{
         value_index:u16
     },
-    AttrDeprecated{
-
-    },
-    EnclosingMethodAttribute {
-        class_index:u16,
-        name_index:u16
-    },
     ExceptionsAttribute{
         index_table:Vec<u16>
     },
-    InnerClassesAttribute {
-        inner_classes: Vec<InnerClassInfo>
-    },
     LineNumberTableAttribute {
         line_number_table: Vec<LineNumberEntry>
     },
     LocalVariableTableAttribute {
         local_variable_table: Vec<LocalVariableTableEntry>
     },
-    LocalVariableTypeTableAttribute {
-        local_variable_type_table: Vec<LocalVariablTypeEntry>
-    },
     SignatureAttribute{
         signature_index:u16
     },
+    StackMapTableAttribute {
+        entries: Vec<StackMapFrame>
+    },
     SourceFileAttribute{
         source_file:u16
     },
-    UnparsedAttribute {
-       
+    // the annotation attributes keep their bytes too, which is what the JDK's reflection parses
+    RuntimeVisibleAnnotationsAttribute {
+        annotations: Vec<Annotation>,
+        raw: Vec<u8>
     },
-    DeprecatedAttribute{},
-    SyntheticAttribute{}
+    RuntimeVisibleParameterAnnotationsAttribute {
+        parameter_annotations: Vec<Vec<Annotation>>,
+        raw: Vec<u8>
+    },
+    AnnotationDefaultAttribute {
+        default_value: ElementValue,
+        raw: Vec<u8>
+    }
 }
 
 pub fn parse_attributes(reader: &Reader, constant_pool: &Vec<ConstantInfo>) -> Result<Vec<AttributeInfo>, Error> {
     let attributes_count = reader.read_u16()?;
-    println!(" attributes count = {} ", attributes_count);
+    log!(reader.logger, Parse, Trace, "{} attributes", attributes_count);
     let mut result = Vec::new();
     for _index in 0..attributes_count {
         let attr_name_index = reader.read_u16()?;
-        let name = get_utf8(&constant_pool, &attr_name_index).unwrap();
+        let name = get_utf8(constant_pool, &attr_name_index)
+            .ok_or(Error::InvalidConstantIndex(attr_name_index))?;
         let length = reader.read_u32()?;
-        println!("parse attr {:?}, length {:?}", name, length);
+        let start = reader.cursor.get();
+        log!(reader.logger, Parse, Trace, "attribute {}, {} bytes", name, length);
         match name.as_str() {
             "BootstrapMethods" => {
-                let mut bootMethods = Vec::new();
+                let mut boot_methods = Vec::new();
                 let number = reader.read_u16()?;
                 for _i in  0..number{
-                    bootMethods.push(BootstrapMethod {
+                    boot_methods.push(BootstrapMethod {
                         bootstrap_method_ref: reader.read_u16()?,
                         bootstrap_arguments: reader.read_u16s()?
                     })
                 }
                 result.push(BootstrapMethodsAttribute {
-                    boostrap_methods: bootMethods
+                    boostrap_methods: boot_methods
                 })
             },
             "Code" => {
@@ -137,34 +199,17 @@ pub fn parse_attributes(reader: &Reader, constant_pool: &Vec<ConstantInfo>) -> R
                 result.push(CodeAttribute { 
                     max_stacks,
                     max_locals,
-                    code_length,
                     code,
                     exception_table,
-                    attributes: attributes
+                    attributes
                 })
             },
             "ConstantValue" => {
                 result.push(ConstantValueAttribute { value_index: reader.read_u16()? })
             },
-            "EnclosingMethod" => {
-                result.push(EnclosingMethodAttribute { class_index: reader.read_u16()?, name_index: reader.read_u16()? })
-            },
             "Exceptions" => {
                 result.push(ExceptionsAttribute { index_table: reader.read_u16s()? })
             },
-            "InnerClasses" => {
-                let number = reader.read_u16()?;
-                let mut class_vec = Vec::new();
-                for _ in 0..number {
-                    class_vec.push(InnerClassInfo{
-                        innner_class_index: reader.read_u16()?,
-                        outter_class_index: reader.read_u16()?,
-                        inner_class_name_index: reader.read_u16()?,
-                        inner_class_access_flags: reader.read_u16()?
-                    })
-                }
-                result.push(InnerClassesAttribute { inner_classes: class_vec })
-            },
             "LineNumberTable" => {
                 let line_number = reader.read_u16()?;
                 let mut line_table = Vec::new();
@@ -190,40 +235,158 @@ pub fn parse_attributes(reader: &Reader, constant_pool: &Vec<ConstantInfo>) -> R
                 }
                 result.push(LocalVariableTableAttribute { local_variable_table: line_table })
             },
-            "LocalVariableTypeTable" => {
-                let line_number = reader.read_u16()?;
-                let mut line_table = Vec::new();
-                for _i in 0..line_number {
-                    line_table.push(LocalVariablTypeEntry {
-                        start_pc: reader.read_u16()?,
-                        length:reader.read_u16()?,
-                        name_index: reader.read_u16()?,
-                        signature_index:reader.read_u16()?,
-                        index:reader.read_u16()?
-                    })
+            "StackMapTable" => {
+                let number = reader.read_u16()?;
+                let mut entries = Vec::new();
+                for _i in 0..number {
+                    entries.push(parse_stack_map_frame(reader)?);
                 }
-                result.push(LocalVariableTypeTableAttribute { local_variable_type_table:line_table })
+                result.push(StackMapTableAttribute { entries })
             },
             "Signature" => {
                 result.push(SignatureAttribute { signature_index: reader.read_u16()? })
             },
             "SourceFile" => {
                 let source_file_index = reader.read_u16()?;
-                println!("SourceFile is {:?} ", get_utf8(constant_pool, &source_file_index));
                 result.push(SourceFileAttribute { source_file: source_file_index })
             },
-            "Synthetic" => {
-
+            "RuntimeVisibleAnnotations" => {
+                let annotations = parse_annotations(reader)?;
+                result.push(RuntimeVisibleAnnotationsAttribute { annotations, raw: raw(reader, start) })
+            },
+            "RuntimeVisibleParameterAnnotations" => {
+                let number = reader.read_u8()?;
+                let mut parameter_annotations = Vec::new();
+                for _i in 0..number {
+                    parameter_annotations.push(parse_annotations(reader)?);
+                }
+                let raw = raw(reader, start);
+                result.push(RuntimeVisibleParameterAnnotationsAttribute { parameter_annotations, raw })
             },
-            "Deprecated" => {
-                
+            "AnnotationDefault" => {
+                let default_value = parse_element_value(reader)?;
+                result.push(AnnotationDefaultAttribute { default_value, raw: raw(reader, start) })
             },
             _ => {
                 reader.read_bytes(length as usize)?;
-                println!("dont parse attr {:?}, length {:?}", name, length)
+                log!(reader.logger, Parse, Debug, "skipped attribute {}, {} bytes", name, length);
             }
         }
-       
+        reader.attribute_lengths.borrow_mut().push(AttributeLength {
+            name,
+            declared: length,
+            consumed: (reader.cursor.get() - start) as u32,
+        });
     }
-    return Ok(result);
+    Ok(result)
+}
+
+// the bytes of the attribute read from `start` on
+fn raw(reader: &Reader, start: usize) -> Vec<u8> {
+    reader.content[start..reader.cursor.get()].to_vec()
+}
+
+fn parse_annotations(reader: &Reader) -> Result<Vec<Annotation>, Error> {
+    let number = reader.read_u16()?;
+    let mut annotations = Vec::new();
+    for _i in 0..number {
+        annotations.push(parse_annotation(reader)?);
+    }
+    Ok(annotations)
+}
+
+fn parse_annotation(reader: &Reader) -> Result<Annotation, Error> {
+    let type_index = reader.read_u16()?;
+    let number = reader.read_u16()?;
+    let mut element_value_pairs = Vec::new();
+    for _i in 0..number {
+        element_value_pairs.push(ElementValuePair {
+            element_name_index: reader.read_u16()?,
+            value: parse_element_value(reader)?
+        });
+    }
+    Ok(Annotation { type_index, element_value_pairs })
+}
+
+fn parse_element_value(reader: &Reader) -> Result<ElementValue, Error> {
+    let tag = reader.read_u8()?;
+    let value = match tag {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => {
+            ElementValue::Const { tag, const_value_index: reader.read_u16()? }
+        },
+        b'e' => ElementValue::Enum { type_name_index: reader.read_u16()?, const_name_index: reader.read_u16()? },
+        b'c' => ElementValue::Class { class_info_index: reader.read_u16()? },
+        b'@' => ElementValue::Annotation(parse_annotation(reader)?),
+        b'[' => {
+            let number = reader.read_u16()?;
+            let mut values = Vec::new();
+            for _i in 0..number {
+                values.push(parse_element_value(reader)?);
+            }
+            ElementValue::Array(values)
+        },
+        _ => return Err(Error::UnknownElementValueTag(tag)),
+    };
+    Ok(value)
+}
+
+fn parse_stack_map_frame(reader: &Reader) -> Result<StackMapFrame, Error> {
+    let frame_type = reader.read_u8()?;
+    let frame = match frame_type {
+        0..=63 => StackMapFrame::SameFrame { offset_delta: frame_type as u16 },
+        64..=127 => StackMapFrame::SameLocals1StackItemFrame {
+            offset_delta: (frame_type - 64) as u16,
+            stack: parse_verification_type(reader)?
+        },
+        247 => StackMapFrame::SameLocals1StackItemFrame {
+            offset_delta: reader.read_u16()?,
+            stack: parse_verification_type(reader)?
+        },
+        248..=250 => StackMapFrame::ChopFrame {
+            offset_delta: reader.read_u16()?,
+            chopped: 251 - frame_type
+        },
+        251 => StackMapFrame::SameFrame { offset_delta: reader.read_u16()? },
+        252..=254 => {
+            let offset_delta = reader.read_u16()?;
+            let mut locals = Vec::new();
+            for _i in 0..frame_type - 251 {
+                locals.push(parse_verification_type(reader)?);
+            }
+            StackMapFrame::AppendFrame { offset_delta, locals }
+        },
+        255 => {
+            let offset_delta = reader.read_u16()?;
+            let locals_number = reader.read_u16()?;
+            let mut locals = Vec::new();
+            for _i in 0..locals_number {
+                locals.push(parse_verification_type(reader)?);
+            }
+            let stack_number = reader.read_u16()?;
+            let mut stack = Vec::new();
+            for _i in 0..stack_number {
+                stack.push(parse_verification_type(reader)?);
+            }
+            StackMapFrame::FullFrame { offset_delta, locals, stack }
+        },
+        _ => return Err(Error::UnknownStackMapFrame(frame_type)),
+    };
+    Ok(frame)
+}
+
+fn parse_verification_type(reader: &Reader) -> Result<VerificationTypeInfo, Error> {
+    let tag = reader.read_u8()?;
+    let info = match tag {
+        0 => VerificationTypeInfo::Top,
+        1 => VerificationTypeInfo::Integer,
+        2 => VerificationTypeInfo::Float,
+        3 => VerificationTypeInfo::Double,
+        4 => VerificationTypeInfo::Long,
+        5 => VerificationTypeInfo::Null,
+        6 => VerificationTypeInfo::UninitializedThis,
+        7 => VerificationTypeInfo::Object { cpool_index: reader.read_u16()? },
+        8 => VerificationTypeInfo::Uninitialized { offset: reader.read_u16()? },
+        _ => return Err(Error::UnknownVerificationType(tag)),
+    };
+    Ok(info)
 }
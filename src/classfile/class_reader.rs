@@ -1,22 +1,26 @@
-use std::cell::Cell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use byteorder::{BE, ReadBytesExt};
 use crate::classfile::attribute::{*};
 use crate::classfile::constant_pool::parse_constant_pool;
 use crate::classfile::ClassFile;
 use crate::error::Error;
+use crate::log::{log, Logger};
 
-use super::attribute::AttributeInfo::{self, *};
+use super::attribute::AttributeInfo;
 use super::constant_pool::ConstantInfo::{self, *};
 
 pub struct Reader {
     pub(crate) content: Vec<u8>,
     pub(crate) cursor: Cell<usize>,
+    pub(crate) attribute_lengths: RefCell<Vec<AttributeLength>>,
+    pub(crate) logger: Rc<Logger>,
 }
 
 
 pub struct MethodInfo {
     pub name: String,
+    pub name_index: u16,
     pub access_flag:u16,
     pub descriptor_index:u16,
     pub attribute_info:Vec<AttributeInfo>
@@ -24,60 +28,76 @@ pub struct MethodInfo {
 
 pub struct FieldInfo {
     pub name: String,
+    pub name_index: u16,
     pub access_flag:u16,
     pub descriptor_index:u16,
     pub attribute_info:Vec<AttributeInfo>
 }
 
-const ACC_PUBLIC: u32 = 0x0001;
-const ACC_FINAL: u32 = 0x0010;
-const ACC_SUPER: u32 = 0x0020;
-const ACC_INTERFACE: u32 = 0x0200;
-const ACC_ABSTRACT: u32 = 0x0400;
-const ACC_SYNTHETIC: u32 = 0x1000;
-const ACC_ANNOTATION: u32 = 0x2000;
-const ACC_ENUM: u32 = 0x4000;
+pub(crate) const ACC_PUBLIC: u16 = 0x0001;
+pub(crate) const ACC_PRIVATE: u16 = 0x0002;
+pub(crate) const ACC_PROTECTED: u16 = 0x0004;
+pub(crate) const ACC_STATIC: u16 = 0x0008;
+pub(crate) const ACC_FINAL: u16 = 0x0010;
+pub(crate) const ACC_SUPER: u16 = 0x0020;
+pub(crate) const ACC_SYNCHRONIZED: u16 = 0x0020;
+pub(crate) const ACC_VOLATILE: u16 = 0x0040;
+pub(crate) const ACC_BRIDGE: u16 = 0x0040;
+pub(crate) const ACC_TRANSIENT: u16 = 0x0080;
+pub(crate) const ACC_VARARGS: u16 = 0x0080;
+pub(crate) const ACC_NATIVE: u16 = 0x0100;
+pub(crate) const ACC_INTERFACE: u16 = 0x0200;
+pub(crate) const ACC_ABSTRACT: u16 = 0x0400;
+pub(crate) const ACC_STRICT: u16 = 0x0800;
+pub(crate) const ACC_SYNTHETIC: u16 = 0x1000;
+pub(crate) const ACC_ANNOTATION: u16 = 0x2000;
+pub(crate) const ACC_ENUM: u16 = 0x4000;
+pub(crate) const ACC_MODULE: u16 = 0x8000;
 
 impl Reader {
     pub(crate) fn parse_classfile(&self) -> Result<ClassFile, Error> {
         let magic = self.read_u32()?;
-        println!("magic number is {:#x}", magic);
-
         let minor_version = self.read_u16()?;
-        println!("minor version is {}", minor_version);
-
         let major_version = self.read_u16()?;
-        println!("major version is {}", major_version);
-
         let constant_pool_size = self.read_u16()?;
-        println!("constant_pool_size is {}", constant_pool_size);
+        log!(
+            self.logger,
+            Parse,
+            Debug,
+            "magic {:#x}, version {}.{}, {} constants",
+            magic,
+            major_version,
+            minor_version,
+            constant_pool_size
+        );
 
         let constant_pool = parse_constant_pool(self, constant_pool_size)?;
-        
-        println!("just a test {:?}", constant_pool);
         let access_flags = self.read_u16()?;
         let this_class = self.read_u16()?;
         let super_class = self.read_u16()?;
         let interface_count = self.read_u16()?;
 
         let mut interfaces = Vec::<String>::new();
+        let mut interface_indexes = Vec::<u16>::new();
         for _i in 0..interface_count {
             let class_info_index = self.read_u16()?;
-            println!(" interfaces  class_index= {}", class_info_index);
-            interfaces.push(get_class_name(&constant_pool, &class_info_index).unwrap());
+            log!(self.logger, Parse, Trace, "interface #{}", class_info_index);
+            interfaces.push(get_class_name(&constant_pool, &class_info_index).unwrap_or_default());
+            interface_indexes.push(class_info_index);
         }
 
         let fields_count = self.read_u16()?;
-        println!(" fields count = {} ", fields_count);
+        log!(self.logger, Parse, Trace, "{} fields", fields_count);
 
         let mut fields_info = Vec::<FieldInfo>::new();
-        for field_index in 0..fields_count {
+        for _field_index in 0..fields_count {
             let access_flags = self.read_u16()?;
             let name_index = self.read_u16()?;
             let descriptor_index = self.read_u16()?;
             let attributes = parse_attributes(self,&constant_pool)?;
             fields_info.push(FieldInfo {
-                name: get_utf8(&constant_pool, &name_index).unwrap(),
+                name: get_utf8(&constant_pool, &name_index).unwrap_or_default(),
+                name_index,
                 access_flag: access_flags,
                 descriptor_index,
                 attribute_info:attributes
@@ -85,15 +105,16 @@ impl Reader {
         }
 
         let methods_count = self.read_u16()?;
-        println!(" methods count = {} ", methods_count);
+        log!(self.logger, Parse, Trace, "{} methods", methods_count);
         let mut method_info = Vec::<MethodInfo>::new();
-        for method_index in 0..methods_count {
+        for _method_index in 0..methods_count {
             let access_flags = self.read_u16()?;
             let name_index = self.read_u16()?;
             let descriptor_index = self.read_u16()?;
             let attributes = parse_attributes(self, &constant_pool)?;
             method_info.push(MethodInfo {
-                name: get_utf8(&constant_pool, &name_index).unwrap(),
+                name: get_utf8(&constant_pool, &name_index).unwrap_or_default(),
+                name_index,
                 access_flag: access_flags,
                 descriptor_index,
                 attribute_info:attributes
@@ -101,82 +122,101 @@ impl Reader {
         }
 
         let attributes_info = parse_attributes(self, &constant_pool)?;
+        let trailing_bytes = self.content.len() - self.cursor.get();
 
         Ok(ClassFile {
             magic,
             minor_version,
             major_version,
-            constant_pool_count: constant_pool.len() as u16,
             constant_pool,
             access_flags,
             this_class,
             super_class,
-            interfaces_count: interface_count,
             interfaces,
-            fields_count,
+            interface_indexes,
             fields_info,
-            methods_count,
             methods_info: method_info,
-            attributes_count: attributes_info.len() as u16,
-            attributes_info
+            attributes_info,
+            attribute_lengths: self.attribute_lengths.take(),
+            trailing_bytes
         })
     }
 
+    fn next_bytes(&self, size: usize) -> Result<&[u8], Error> {
+        let start = self.cursor.get();
+        let content = self.content.get(start..start + size)
+            .ok_or(Error::TruncatedClassFile(size, start))?;
+        self.cursor.set(start + size);
+        Ok(content)
+    }
+
     pub fn read_u8(&self) -> Result<u8, Error> {
-        let content = (&self.content[self.cursor.get()..]).read_u8()?;
-        self.cursor.set(self.cursor.get() + 1);
-        return Ok(content);
+        let content = self.next_bytes(1)?.read_u8()?;
+        Ok(content)
     }
 
     pub fn read_u16(&self) -> Result<u16, Error> {
-        let content = (&self.content[self.cursor.get()..self.cursor.get() + 2]).read_u16::<BE>()?;
-        self.cursor.set(self.cursor.get() + 2);
-        return Ok(content);
+        let content = self.next_bytes(2)?.read_u16::<BE>()?;
+        Ok(content)
     }
 
     pub fn read_u16s(&self) -> Result<Vec<u16>, Error> {
-        let n = (&self.content[self.cursor.get()..self.cursor.get() + 2]).read_u16::<BE>()?;
-        self.cursor.set(self.cursor.get() + 2);
+        let n = self.read_u16()?;
         let mut content = Vec::new();
         for _i in 0..n {
             let item = self.read_u16()?;
             content.push(item);
         }
-        return Ok(content);
+        Ok(content)
     }
 
     pub fn read_u32(&self) -> Result<u32, Error> {
-        let content = (&self.content[self.cursor.get()..self.cursor.get() + 4]).read_u32::<BE>()?;
-        self.cursor.set(self.cursor.get() + 4);
-        return Ok(content);
+        let content = self.next_bytes(4)?.read_u32::<BE>()?;
+        Ok(content)
     }
 
     pub fn read_u64(&self) -> Result<u64, Error> {
-        let content = (&self.content[self.cursor.get()..self.cursor.get() + 8]).read_u64::<BE>()?;
-        self.cursor.set(self.cursor.get() + 8);
-        return Ok(content);
+        let content = self.next_bytes(8)?.read_u64::<BE>()?;
+        Ok(content)
     }
     pub fn read_bytes(&self, size: usize) -> Result<Vec<u8>, Error> {
-        let content = &self.content[self.cursor.get()..self.cursor.get() + size];
-        self.cursor.set(self.cursor.get() + size);
-        return Ok(content.to_vec());
+        let content = self.next_bytes(size)?;
+        Ok(content.to_vec())
     }
 }
 
-pub fn get_utf8(constant_pool: &Vec<ConstantInfo>, index: &u16) -> Option<String> {
-    if let ConstantUTF8 { value } = &constant_pool[*index as usize] {
-        println!("constant utf8 = {}", value);
+pub fn get_utf8(constant_pool: &[ConstantInfo], index: &u16) -> Option<String> {
+    if let Some(ConstantUTF8 { value }) = constant_pool.get(*index as usize) {
         return Some(String::from(value));
     };
-    return None;
+    None
 }
 
-pub fn get_class_name(constant_pool: &Vec<ConstantInfo>, this_class: &u16) -> Option<String> {
-    println!("this_class {}", this_class);
-    if let ConstantClass { index } = &constant_pool[*this_class as usize] {
-        if let ConstantUTF8 { value } = &constant_pool[*index as usize] {
+pub fn get_class_name(constant_pool: &[ConstantInfo], this_class: &u16) -> Option<String> {
+    if let Some(ConstantClass { index }) = constant_pool.get(*this_class as usize) {
+        if let Some(ConstantUTF8 { value }) = constant_pool.get(*index as usize) {
             return Some(String::from(value));
         }
     }
-    return None;
-}
\ No newline at end of file
+    None
+}
+pub fn get_name_and_type(constant_pool: &[ConstantInfo], index: &u16) -> Option<(String, String)> {
+    if let Some(ConstantNameAndType { name_index, descriptor_index }) = constant_pool.get(*index as usize) {
+        return Some((get_utf8(constant_pool, name_index)?, get_utf8(constant_pool, descriptor_index)?));
+    }
+    None
+}
+
+// (class name, member name, descriptor) of a field, method or interface method reference
+pub fn get_member_ref(constant_pool: &[ConstantInfo], index: &u16) -> Option<(String, String, String)> {
+    match constant_pool.get(*index as usize)? {
+        ConstantFieldReference { class_index, name_and_type_index }
+        | ConstantMethodReference { class_index, name_and_type_index }
+        | ConstantInterfaceMethodReference { class_index, name_and_type_index } => {
+            let class_name = get_class_name(constant_pool, class_index)?;
+            let (name, descriptor) = get_name_and_type(constant_pool, name_and_type_index)?;
+            Some((class_name, name, descriptor))
+        }
+        _ => None,
+    }
+}
@@ -1,8 +1,7 @@
-use std::collections::HashMap;
-
 use super::class_reader::Reader;
 use crate::classfile::ConstantInfo::{*};
 use crate::error::Error;
+use crate::log::log;
 use crate::error::Error::UnKnownConstantType;
 
 #[derive(Debug)]
@@ -81,59 +80,46 @@ pub fn parse_constant_pool(reader: &Reader, constant_pool_size: u16) -> Result<V
     let mut index = 1;
     constant_pool.push(ConstantEmpty{});
     while index < constant_pool_size {
-        println!("loop index = {}", index);
         let tag = reader.read_u8()?;
-        println!("constant tag is {}", tag);
+        let start = constant_pool.len();
 
         match tag {
             CONSTANT_INTEGER => {
                 let v = reader.read_u32()?;
                 constant_pool.push( ConstantInteger { value: v });
-                println!("value = {}", v);
             }
             CONSTANT_FLOAT => {
                 let v = reader.read_u32()?;
                 constant_pool.push( ConstantFloat { value: v });
-                println!("value = {}", v);
             }
             CONSTANT_LONG => {
                 let v = reader.read_u64()?;
                 constant_pool.push( ConstantLong { value: v });
-                println!("index before is {}", index);
-                index = index + 1;
+                index += 1;
                 constant_pool.push(ConstantEmpty{});
-                println!("index after is {}", index);
-                println!("value = {}", v);
             }
             CONSTANT_DOUBLE => {
                 let v = reader.read_u64()?;
                 constant_pool.push( ConstantDouble { value: v });
-                index = index + 1;
+                index += 1;
                 constant_pool.push(ConstantEmpty{});
-                println!("value = {}", v);
             }
             CONSTANT_UTF8 => {
                 let length = reader.read_u16()?;
+                let offset = reader.cursor.get();
                 let content = reader.read_bytes(length as usize)?;
-                let string = std::str::from_utf8(content.into_boxed_slice().as_ref())
-                    .unwrap()
-                    .to_string();
-                println!(
-                    "CONSTANT_UTF8 utf8 length = {}, content = {}",
-                    length, string
-                );
+                let string = decode_modified_utf8(&content)
+                    .ok_or(Error::MalformedUtf8(offset))?;
                 constant_pool.push( ConstantUTF8 { value: string });
             }
             CONSTANT_STRING => {
                 let str_index = reader.read_u16()?;
                 constant_pool.push( ConstantString { index: str_index });
-                println!("CONSTANT_STRING string index = {} ", str_index);
             }
 
             CONSTANT_CLASS => {
                 let class_index = reader.read_u16()?;
                 constant_pool.push( ConstantClass { index: class_index });
-                println!("CONSTANT_CLASS class index = {} ", class_index);
             }
 
             CONSTANT_NAME_AND_TYPE => {
@@ -145,10 +131,6 @@ pub fn parse_constant_pool(reader: &Reader, constant_pool_size: u16) -> Result<V
                         descriptor_index,
                     },
                 );
-                println!(
-                    "CONSTANT_NAME_AND_TYPE name_index = {},  descriptor_index= {}",
-                    name_index, descriptor_index
-                );
             }
 
             CONSTANT_FIELD_REF => {
@@ -160,19 +142,11 @@ pub fn parse_constant_pool(reader: &Reader, constant_pool_size: u16) -> Result<V
                         name_and_type_index,
                     },
                 );
-                println!(
-                    "CONSTANT_FIELD_REF class_index = {},  name_and_type_index= {}",
-                    class_index, name_and_type_index
-                );
             }
 
             CONSTANT_METHOD_REF => {
                 let class_index = reader.read_u16()?;
                 let name_and_type_index = reader.read_u16()?;
-                println!(
-                    "CONSTANT_METHOD_REF class_index = {},  name_and_type_index= {}",
-                    class_index, name_and_type_index
-                );
                 constant_pool.push(
                     ConstantMethodReference {
                         class_index,
@@ -190,19 +164,11 @@ pub fn parse_constant_pool(reader: &Reader, constant_pool_size: u16) -> Result<V
                         name_and_type_index,
                     },
                 );
-                println!(
-                    "CONSTANT_INTERFACE_METHOD_REF class_index = {},  name_and_type_index= {}",
-                    class_index, name_and_type_index
-                );
             }
 
             CONSTANT_METHOD_TYPE => {
                 let descriptor_index = reader.read_u16()?;
                 constant_pool.push( ConstantMethodType { descriptor_index });
-                println!(
-                    " CONSTANT_METHOD_TYPE  descriptor_index= {}",
-                    descriptor_index
-                );
             }
 
             CONSTANT_METHOD_HANDLE => {
@@ -214,10 +180,6 @@ pub fn parse_constant_pool(reader: &Reader, constant_pool_size: u16) -> Result<V
                         ref_kind_index,
                     },
                 );
-                println!(
-                    "CONSTANT_METHOD_HANDLE ref_kind = {}, ref_kind_index= {}",
-                    ref_kind, ref_kind_index
-                );
             }
             CONSTANT_INVOKE_DYN => {
                 let bootstrap_method_attr_index = reader.read_u16()?;
@@ -228,13 +190,68 @@ pub fn parse_constant_pool(reader: &Reader, constant_pool_size: u16) -> Result<V
                         name_and_type_index,
                     },
                 );
-                println!(" CONSTANT_INVOKE_DYN bootstrap_method_attr_index = {},  name_and_type_index= {}", bootstrap_method_attr_index, name_and_type_index);
             }
             _ => {
                 return Err(UnKnownConstantType(tag));
             }
         }
+        log!(reader.logger, Parse, Trace, "constant #{} {:?}", start, constant_pool[start]);
         index += 1;
     }
-    return Ok(constant_pool);
+    Ok(constant_pool)
+}
+
+// class files store strings as "modified UTF-8" (JVMS §4.4.7): NUL is encoded as two bytes
+// and supplementary characters as a pair of three-byte surrogates
+pub fn decode_modified_utf8(bytes: &[u8]) -> Option<String> {
+    let units = modified_utf8_chars(bytes)?;
+    Some(char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect())
+}
+
+// the UTF-16 chars of modified UTF-8 `bytes`, unpaired surrogates included
+pub fn modified_utf8_chars(bytes: &[u8]) -> Option<Vec<u16>> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i] as u16;
+        match b {
+            0x01..=0x7f => {
+                units.push(b);
+                i += 1;
+            }
+            0xc0..=0xdf => {
+                let b2 = *bytes.get(i + 1)? as u16;
+                if b2 & 0xc0 != 0x80 {
+                    return None;
+                }
+                units.push(((b & 0x1f) << 6) | (b2 & 0x3f));
+                i += 2;
+            }
+            0xe0..=0xef => {
+                let b2 = *bytes.get(i + 1)? as u16;
+                let b3 = *bytes.get(i + 2)? as u16;
+                if b2 & 0xc0 != 0x80 || b3 & 0xc0 != 0x80 {
+                    return None;
+                }
+                units.push(((b & 0x0f) << 12) | ((b2 & 0x3f) << 6) | (b3 & 0x3f));
+                i += 3;
+            }
+            // no byte may be 0 or lie in the range 0xf0 to 0xff
+            _ => return None,
+        }
+    }
+    Some(units)
+}
+
+// UTF-16 `chars` as modified UTF-8, the way JNI hands strings to native code
+pub fn encode_modified_utf8(chars: &[u16]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(chars.len());
+    for &c in chars {
+        match c {
+            0x01..=0x7f => bytes.push(c as u8),
+            0x00 | 0x80..=0x7ff => bytes.extend([0xc0 | (c >> 6) as u8, 0x80 | (c & 0x3f) as u8]),
+            _ => bytes.extend([0xe0 | (c >> 12) as u8, 0x80 | ((c >> 6) & 0x3f) as u8, 0x80 | (c & 0x3f) as u8]),
+        }
+    }
+    bytes
 }
@@ -0,0 +1,142 @@
+// field / method descriptors (JVMS §4.3) and name rules (JVMS §4.2)
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    Object(String),
+    Array(Box<FieldType>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<FieldType>,
+    // None means void
+    pub return_type: Option<FieldType>,
+}
+
+const MAX_ARRAY_DIMENSIONS: usize = 255;
+
+impl FieldType {
+    pub fn slot_size(&self) -> usize {
+        match self {
+            FieldType::Long | FieldType::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn descriptor(&self) -> String {
+        match self {
+            FieldType::Byte => String::from("B"),
+            FieldType::Char => String::from("C"),
+            FieldType::Double => String::from("D"),
+            FieldType::Float => String::from("F"),
+            FieldType::Int => String::from("I"),
+            FieldType::Long => String::from("J"),
+            FieldType::Short => String::from("S"),
+            FieldType::Boolean => String::from("Z"),
+            FieldType::Object(name) => format!("L{};", name),
+            FieldType::Array(component) => format!("[{}", component.descriptor()),
+        }
+    }
+}
+
+impl MethodDescriptor {
+    // number of local variable slots taken by the parameters, excluding `this`
+    pub fn arg_slot_count(&self) -> usize {
+        self.parameters.iter().map(|p| p.slot_size()).sum()
+    }
+}
+
+pub fn parse_field_descriptor(descriptor: &str) -> Option<FieldType> {
+    let (field_type, rest) = parse_field_type(descriptor)?;
+    if !rest.is_empty() {
+        return None;
+    }
+    Some(field_type)
+}
+
+pub fn parse_method_descriptor(descriptor: &str) -> Option<MethodDescriptor> {
+    let mut rest = descriptor.strip_prefix('(')?;
+    let mut parameters = Vec::new();
+    while !rest.starts_with(')') {
+        let (parameter, remain) = parse_field_type(rest)?;
+        parameters.push(parameter);
+        rest = remain;
+    }
+    rest = &rest[1..];
+    let return_type = if rest == "V" {
+        None
+    } else {
+        Some(parse_field_descriptor(rest)?)
+    };
+    // a method descriptor is valid only if it represents at most 255 slots
+    let descriptor = MethodDescriptor { parameters, return_type };
+    if descriptor.arg_slot_count() > 255 {
+        return None;
+    }
+    Some(descriptor)
+}
+
+fn parse_field_type(descriptor: &str) -> Option<(FieldType, &str)> {
+    let dimensions = descriptor.bytes().take_while(|b| *b == b'[').count();
+    if dimensions > MAX_ARRAY_DIMENSIONS {
+        return None;
+    }
+    let rest = &descriptor[dimensions..];
+    let (mut field_type, rest) = match rest.as_bytes().first()? {
+        b'B' => (FieldType::Byte, &rest[1..]),
+        b'C' => (FieldType::Char, &rest[1..]),
+        b'D' => (FieldType::Double, &rest[1..]),
+        b'F' => (FieldType::Float, &rest[1..]),
+        b'I' => (FieldType::Int, &rest[1..]),
+        b'J' => (FieldType::Long, &rest[1..]),
+        b'S' => (FieldType::Short, &rest[1..]),
+        b'Z' => (FieldType::Boolean, &rest[1..]),
+        b'L' => {
+            let end = rest.find(';')?;
+            let class_name = &rest[1..end];
+            if !is_binary_name(class_name) {
+                return None;
+            }
+            (FieldType::Object(String::from(class_name)), &rest[end + 1..])
+        }
+        _ => return None,
+    };
+    for _ in 0..dimensions {
+        field_type = FieldType::Array(Box::new(field_type));
+    }
+    Some((field_type, rest))
+}
+
+// unqualified names of fields, locals and formal parameters
+pub fn is_unqualified_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['.', ';', '[', '/'])
+}
+
+// method names may not contain '<' or '>' except for the special initialization methods
+pub fn is_method_name(name: &str) -> bool {
+    if name == "<init>" || name == "<clinit>" {
+        return true;
+    }
+    is_unqualified_name(name) && !name.contains(['<', '>'])
+}
+
+// binary class names in internal form, e.g. java/lang/Object
+pub fn is_binary_name(name: &str) -> bool {
+    !name.is_empty() && name.split('/').all(is_unqualified_name)
+}
+
+// a CONSTANT_Class may name either a class or an array type
+pub fn is_class_constant_name(name: &str) -> bool {
+    if name.starts_with('[') {
+        return parse_field_descriptor(name).is_some();
+    }
+    is_binary_name(name)
+}
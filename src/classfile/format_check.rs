@@ -0,0 +1,542 @@
+// format checking (JVMS §4.8): the static constraints a class file must satisfy before it is loaded
+use std::collections::HashSet;
+use std::fmt;
+
+use thiserror::Error as ThisError;
+
+use super::attribute::AttributeInfo::{self, *};
+use super::class_reader::*;
+use super::constant_pool::ConstantInfo::{self, *};
+use super::descriptor::{
+    is_class_constant_name, is_method_name, is_unqualified_name,
+    parse_field_descriptor, parse_method_descriptor, FieldType,
+};
+use super::ClassFile;
+
+pub const MAGIC: u32 = 0xCAFEBABE;
+pub const MIN_MAJOR_VERSION: u16 = 45;
+pub const MAX_MAJOR_VERSION: u16 = 61;
+// minor version 65535 marks a class file that depends on preview features
+const PREVIEW_MINOR_VERSION: u16 = 0xFFFF;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    Class,
+    ConstantPool(u16),
+    Field(String),
+    Method(String),
+    Attribute(String),
+}
+
+#[derive(ThisError, Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    #[error("bad magic number {0:#x}")]
+    BadMagic(u32),
+
+    #[error("unsupported class file version {major}.{minor}")]
+    UnsupportedVersion { major: u16, minor: u16 },
+
+    #[error("{location}: constant pool index {index} is out of range")]
+    BadConstantIndex { location: Location, index: u16 },
+
+    #[error("{location}: constant #{index} should be {expected}")]
+    WrongConstantKind { location: Location, index: u16, expected: &'static str },
+
+    #[error("{location}: {kind} constants need class file version {required} or above")]
+    ConstantNotSupported { location: Location, kind: &'static str, required: u16 },
+
+    #[error("{location}: illegal name {name:?}")]
+    IllegalName { location: Location, name: String },
+
+    #[error("{location}: illegal descriptor {descriptor:?}")]
+    IllegalDescriptor { location: Location, descriptor: String },
+
+    #[error("{location}: illegal access flags {flags:#06x}")]
+    IllegalAccessFlags { location: Location, flags: u16 },
+
+    #[error("duplicate field {name} {descriptor}")]
+    DuplicateField { name: String, descriptor: String },
+
+    #[error("duplicate method {name}{descriptor}")]
+    DuplicateMethod { name: String, descriptor: String },
+
+    #[error("{location}: {message}")]
+    BadCode { location: Location, message: String },
+
+    #[error("{location}: bad method handle kind {kind}")]
+    BadMethodHandle { location: Location, kind: u8 },
+
+    #[error("attribute {name}: declared length {declared} but {consumed} bytes were consumed")]
+    AttributeLengthMismatch { name: String, declared: u32, consumed: u32 },
+
+    #[error("{0} extra bytes at the end of the class file")]
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Class => write!(f, "class"),
+            Location::ConstantPool(index) => write!(f, "constant #{}", index),
+            Location::Field(name) => write!(f, "field {}", name),
+            Location::Method(name) => write!(f, "method {}", name),
+            Location::Attribute(name) => write!(f, "attribute {}", name),
+        }
+    }
+}
+
+pub fn check_format(classfile: &ClassFile) -> Result<(), Vec<FormatError>> {
+    let mut checker = FormatChecker {
+        classfile,
+        errors: Vec::new(),
+    };
+    checker.check_header();
+    checker.check_constant_pool();
+    checker.check_class();
+    checker.check_fields();
+    checker.check_methods();
+    checker.check_attribute_lengths();
+    if checker.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(checker.errors)
+    }
+}
+
+struct FormatChecker<'a> {
+    classfile: &'a ClassFile,
+    errors: Vec<FormatError>,
+}
+
+impl<'a> FormatChecker<'a> {
+    fn check_header(&mut self) {
+        let classfile = self.classfile;
+        if classfile.magic != MAGIC {
+            self.errors.push(FormatError::BadMagic(classfile.magic));
+        }
+        let major = classfile.major_version;
+        let minor = classfile.minor_version;
+        let supported = (MIN_MAJOR_VERSION..=MAX_MAJOR_VERSION).contains(&major)
+            && (major < 56 || minor == 0 || minor == PREVIEW_MINOR_VERSION);
+        if !supported {
+            self.errors.push(FormatError::UnsupportedVersion { major, minor });
+        }
+        if classfile.trailing_bytes > 0 {
+            self.errors.push(FormatError::TrailingBytes(classfile.trailing_bytes));
+        }
+    }
+
+    fn check_constant_pool(&mut self) {
+        let constant_pool = &self.classfile.constant_pool;
+        for (index, constant) in constant_pool.iter().enumerate().skip(1) {
+            let location = Location::ConstantPool(index as u16);
+            match constant {
+                ConstantString { index } => {
+                    self.utf8(&location, *index);
+                }
+                ConstantClass { index } => {
+                    if let Some(name) = self.utf8(&location, *index) {
+                        if !is_class_constant_name(name) {
+                            self.illegal_name(&location, name);
+                        }
+                    }
+                }
+                ConstantNameAndType { name_index, descriptor_index } => {
+                    self.utf8(&location, *name_index);
+                    self.utf8(&location, *descriptor_index);
+                }
+                ConstantFieldReference { class_index, name_and_type_index } => {
+                    self.class(&location, *class_index);
+                    if let Some((name, descriptor)) = self.name_and_type(&location, *name_and_type_index) {
+                        self.check_field_signature(&location, name, descriptor);
+                    }
+                }
+                ConstantMethodReference { class_index, name_and_type_index }
+                | ConstantInterfaceMethodReference { class_index, name_and_type_index } => {
+                    self.class(&location, *class_index);
+                    if let Some((name, descriptor)) = self.name_and_type(&location, *name_and_type_index) {
+                        self.check_method_signature(&location, name, descriptor);
+                    }
+                }
+                ConstantMethodType { descriptor_index } => {
+                    self.require_version(&location, "MethodType", 51);
+                    if let Some(descriptor) = self.utf8(&location, *descriptor_index) {
+                        if parse_method_descriptor(descriptor).is_none() {
+                            self.illegal_descriptor(&location, descriptor);
+                        }
+                    }
+                }
+                ConstantMethodHandle { ref_kind, ref_kind_index } => {
+                    self.require_version(&location, "MethodHandle", 51);
+                    self.check_method_handle(&location, *ref_kind, *ref_kind_index);
+                }
+                ConstantInvokeDynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                    self.require_version(&location, "InvokeDynamic", 51);
+                    if let Some((name, descriptor)) = self.name_and_type(&location, *name_and_type_index) {
+                        self.check_method_signature(&location, name, descriptor);
+                    }
+                    if *bootstrap_method_attr_index as usize >= self.bootstrap_method_count() {
+                        self.errors.push(FormatError::BadConstantIndex {
+                            location: Location::Attribute(String::from("BootstrapMethods")),
+                            index: *bootstrap_method_attr_index,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_method_handle(&mut self, location: &Location, kind: u8, index: u16) {
+        let constant = match self.constant(location, index) {
+            Some(constant) => constant,
+            None => return,
+        };
+        let major = self.classfile.major_version;
+        let name_and_type_index = match (kind, constant) {
+            (1..=4, ConstantFieldReference { name_and_type_index, .. }) => *name_and_type_index,
+            (5 | 8, ConstantMethodReference { name_and_type_index, .. }) => *name_and_type_index,
+            (6 | 7, ConstantMethodReference { name_and_type_index, .. }) => *name_and_type_index,
+            (6 | 7, ConstantInterfaceMethodReference { name_and_type_index, .. }) if major >= 52 => *name_and_type_index,
+            (9, ConstantInterfaceMethodReference { name_and_type_index, .. }) => *name_and_type_index,
+            (1..=9, _) => {
+                self.errors.push(FormatError::WrongConstantKind {
+                    location: location.clone(),
+                    index,
+                    expected: if kind <= 4 { "a field reference" } else { "a method reference" },
+                });
+                return;
+            }
+            _ => {
+                self.errors.push(FormatError::BadMethodHandle { location: location.clone(), kind });
+                return;
+            }
+        };
+        if let Some((name, _)) = self.name_and_type(location, name_and_type_index) {
+            let is_init = name == "<init>";
+            let is_clinit = name == "<clinit>";
+            if (kind == 8 && !is_init) || (kind >= 5 && kind != 8 && (is_init || is_clinit)) {
+                self.errors.push(FormatError::BadMethodHandle { location: location.clone(), kind });
+            }
+        }
+    }
+
+    fn check_class(&mut self) {
+        let classfile = self.classfile;
+        let location = Location::Class;
+        let flags = classfile.access_flags;
+        let is_interface = flags & ACC_INTERFACE != 0;
+        let illegal = if is_interface {
+            flags & ACC_ABSTRACT == 0 || flags & (ACC_FINAL | ACC_SUPER | ACC_ENUM | ACC_MODULE) != 0
+        } else {
+            flags & (ACC_ANNOTATION | ACC_MODULE) != 0
+                || (flags & ACC_FINAL != 0 && flags & ACC_ABSTRACT != 0)
+        };
+        if illegal {
+            self.errors.push(FormatError::IllegalAccessFlags { location: location.clone(), flags });
+        }
+
+        let this_name = self.class(&location, classfile.this_class);
+        if let Some(name) = this_name {
+            if name.starts_with('[') {
+                self.illegal_name(&location, name);
+            }
+        }
+        if classfile.super_class == 0 {
+            if this_name.is_some() && this_name != Some("java/lang/Object") {
+                self.errors.push(FormatError::BadConstantIndex { location: location.clone(), index: 0 });
+            }
+        } else if let Some(super_name) = self.class(&location, classfile.super_class) {
+            if is_interface && super_name != "java/lang/Object" {
+                self.illegal_name(&location, super_name);
+            }
+        }
+        for index in &classfile.interface_indexes {
+            self.class(&location, *index);
+        }
+    }
+
+    fn check_fields(&mut self) {
+        let classfile = self.classfile;
+        let is_interface = classfile.access_flags & ACC_INTERFACE != 0;
+        let mut seen = HashSet::new();
+        for field in &classfile.fields_info {
+            let location = Location::Field(field.name.clone());
+            let name = self.utf8(&location, field.name_index);
+            let descriptor = self.utf8(&location, field.descriptor_index);
+            let field_type = match (name, descriptor) {
+                (Some(name), Some(descriptor)) => {
+                    if !seen.insert((name, descriptor)) {
+                        self.errors.push(FormatError::DuplicateField {
+                            name: String::from(name),
+                            descriptor: String::from(descriptor),
+                        });
+                    }
+                    self.check_field_signature(&location, name, descriptor)
+                }
+                _ => None,
+            };
+
+            let flags = field.access_flag;
+            let illegal = if is_interface {
+                flags & !(ACC_SYNTHETIC) != ACC_PUBLIC | ACC_STATIC | ACC_FINAL
+            } else {
+                !at_most_one_visibility(flags) || (flags & ACC_FINAL != 0 && flags & ACC_VOLATILE != 0)
+            };
+            if illegal {
+                self.errors.push(FormatError::IllegalAccessFlags { location: location.clone(), flags });
+            }
+
+            for attribute in &field.attribute_info {
+                if let (ConstantValueAttribute { value_index }, Some(field_type)) = (attribute, &field_type) {
+                    self.check_constant_value(&location, *value_index, field_type);
+                }
+            }
+        }
+    }
+
+    fn check_constant_value(&mut self, location: &Location, index: u16, field_type: &FieldType) {
+        let constant = match self.constant(location, index) {
+            Some(constant) => constant,
+            None => return,
+        };
+        let (matches, expected) = match field_type {
+            FieldType::Long => (matches!(constant, ConstantLong { .. }), "a long constant"),
+            FieldType::Float => (matches!(constant, ConstantFloat { .. }), "a float constant"),
+            FieldType::Double => (matches!(constant, ConstantDouble { .. }), "a double constant"),
+            FieldType::Object(name) if name == "java/lang/String" => {
+                (matches!(constant, ConstantString { .. }), "a string constant")
+            }
+            FieldType::Object(_) | FieldType::Array(_) => (false, "a primitive or string field"),
+            _ => (matches!(constant, ConstantInteger { .. }), "an integer constant"),
+        };
+        if !matches {
+            self.errors.push(FormatError::WrongConstantKind { location: location.clone(), index, expected });
+        }
+    }
+
+    fn check_methods(&mut self) {
+        let classfile = self.classfile;
+        let mut seen = HashSet::new();
+        for method in &classfile.methods_info {
+            let location = Location::Method(method.name.clone());
+            let name = self.utf8(&location, method.name_index);
+            let descriptor = self.utf8(&location, method.descriptor_index);
+            if let (Some(name), Some(descriptor)) = (name, descriptor) {
+                if !seen.insert((name, descriptor)) {
+                    self.errors.push(FormatError::DuplicateMethod {
+                        name: String::from(name),
+                        descriptor: String::from(descriptor),
+                    });
+                }
+                self.check_method_signature(&location, name, descriptor);
+                if name == "<clinit>" && classfile.major_version >= 51 && descriptor != "()V" {
+                    self.illegal_descriptor(&location, descriptor);
+                }
+            }
+            if !self.method_flags_legal(method) {
+                self.errors.push(FormatError::IllegalAccessFlags {
+                    location: location.clone(),
+                    flags: method.access_flag,
+                });
+            }
+            self.check_code(&location, method);
+        }
+    }
+
+    fn method_flags_legal(&self, method: &MethodInfo) -> bool {
+        let flags = method.access_flag;
+        let major = self.classfile.major_version;
+        if method.name == "<clinit>" {
+            // everything but ACC_STATIC is ignored on class initializers
+            return major < 51 || flags & ACC_STATIC != 0;
+        }
+        if !at_most_one_visibility(flags) {
+            return false;
+        }
+        if self.classfile.access_flags & ACC_INTERFACE != 0 {
+            if major < 52 {
+                return flags & (ACC_PUBLIC | ACC_ABSTRACT) == ACC_PUBLIC | ACC_ABSTRACT
+                    && flags & !(ACC_PUBLIC | ACC_ABSTRACT | ACC_BRIDGE | ACC_VARARGS | ACC_SYNTHETIC) == 0;
+            }
+            if flags & (ACC_PROTECTED | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE) != 0 {
+                return false;
+            }
+            if (flags & ACC_PUBLIC != 0) == (flags & ACC_PRIVATE != 0) {
+                return false;
+            }
+        }
+        if flags & ACC_ABSTRACT != 0 {
+            let mut forbidden = ACC_PRIVATE | ACC_STATIC | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE;
+            if (46..61).contains(&major) {
+                forbidden |= ACC_STRICT;
+            }
+            if flags & forbidden != 0 {
+                return false;
+            }
+        }
+        if method.name == "<init>" {
+            let forbidden = ACC_STATIC | ACC_FINAL | ACC_SYNCHRONIZED | ACC_BRIDGE | ACC_NATIVE | ACC_ABSTRACT;
+            return flags & forbidden == 0;
+        }
+        true
+    }
+
+    fn check_code(&mut self, location: &Location, method: &MethodInfo) {
+        let codes: Vec<&AttributeInfo> = method.attribute_info.iter()
+            .filter(|attribute| matches!(attribute, CodeAttribute { .. }))
+            .collect();
+        let needs_code = method.access_flag & (ACC_NATIVE | ACC_ABSTRACT) == 0;
+        if needs_code && codes.len() != 1 {
+            self.bad_code(location, format!("expected one Code attribute, found {}", codes.len()));
+        } else if !needs_code && !codes.is_empty() {
+            self.bad_code(location, String::from("native and abstract methods must not have a Code attribute"));
+        }
+        for code_attribute in codes {
+            if let CodeAttribute { code, exception_table, .. } = code_attribute {
+                let code_length = code.len();
+                if code_length == 0 || code_length >= 65536 {
+                    self.bad_code(location, format!("illegal code length {}", code_length));
+                }
+                for entry in exception_table {
+                    let start = entry.start_pc as usize;
+                    let end = entry.end_pc as usize;
+                    if start >= end || end > code_length || entry.handle_pc as usize >= code_length {
+                        self.bad_code(location, format!(
+                            "illegal exception table entry [{}, {}) -> {}",
+                            entry.start_pc, entry.end_pc, entry.handle_pc
+                        ));
+                    }
+                    if entry.catch_type != 0 {
+                        self.class(location, entry.catch_type);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_attribute_lengths(&mut self) {
+        for attribute in &self.classfile.attribute_lengths {
+            if attribute.declared != attribute.consumed {
+                self.errors.push(FormatError::AttributeLengthMismatch {
+                    name: attribute.name.clone(),
+                    declared: attribute.declared,
+                    consumed: attribute.consumed,
+                });
+            }
+        }
+    }
+
+    fn check_field_signature(&mut self, location: &Location, name: &str, descriptor: &str) -> Option<FieldType> {
+        if !is_unqualified_name(name) {
+            self.illegal_name(location, name);
+        }
+        let field_type = parse_field_descriptor(descriptor);
+        if field_type.is_none() {
+            self.illegal_descriptor(location, descriptor);
+        }
+        field_type
+    }
+
+    fn check_method_signature(&mut self, location: &Location, name: &str, descriptor: &str) {
+        if !is_method_name(name) {
+            self.illegal_name(location, name);
+        }
+        match parse_method_descriptor(descriptor) {
+            Some(method_descriptor) => {
+                if name == "<init>" && method_descriptor.return_type.is_some() {
+                    self.illegal_descriptor(location, descriptor);
+                }
+            }
+            None => self.illegal_descriptor(location, descriptor),
+        }
+    }
+
+    fn require_version(&mut self, location: &Location, kind: &'static str, required: u16) {
+        if self.classfile.major_version < required {
+            self.errors.push(FormatError::ConstantNotSupported { location: location.clone(), kind, required });
+        }
+    }
+
+    fn bootstrap_method_count(&self) -> usize {
+        for attribute in &self.classfile.attributes_info {
+            if let BootstrapMethodsAttribute { boostrap_methods } = attribute {
+                return boostrap_methods.len();
+            }
+        }
+        0
+    }
+
+    // returns the constant at `index`, recording a diagnostic if it is not usable
+    fn constant(&mut self, location: &Location, index: u16) -> Option<&'a ConstantInfo> {
+        match self.classfile.constant_pool.get(index as usize) {
+            Some(ConstantEmpty {}) | None => {
+                self.errors.push(FormatError::BadConstantIndex { location: location.clone(), index });
+                None
+            }
+            Some(constant) => Some(constant),
+        }
+    }
+
+    fn utf8(&mut self, location: &Location, index: u16) -> Option<&'a str> {
+        match self.constant(location, index)? {
+            ConstantUTF8 { value } => Some(value.as_str()),
+            _ => {
+                self.wrong_kind(location, index, "a utf8 constant");
+                None
+            }
+        }
+    }
+
+    fn class(&mut self, location: &Location, index: u16) -> Option<&'a str> {
+        match self.constant(location, index)? {
+            ConstantClass { index: name_index } => match self.classfile.constant_pool.get(*name_index as usize) {
+                Some(ConstantUTF8 { value }) => Some(value.as_str()),
+                // reported when the class constant itself is checked
+                _ => None,
+            },
+            _ => {
+                self.wrong_kind(location, index, "a class constant");
+                None
+            }
+        }
+    }
+
+    fn name_and_type(&mut self, location: &Location, index: u16) -> Option<(&'a str, &'a str)> {
+        match self.constant(location, index)? {
+            ConstantNameAndType { name_index, descriptor_index } => {
+                let name = self.utf8(location, *name_index);
+                let descriptor = self.utf8(location, *descriptor_index);
+                Some((name?, descriptor?))
+            }
+            _ => {
+                self.wrong_kind(location, index, "a name and type constant");
+                None
+            }
+        }
+    }
+
+    fn wrong_kind(&mut self, location: &Location, index: u16, expected: &'static str) {
+        self.errors.push(FormatError::WrongConstantKind { location: location.clone(), index, expected });
+    }
+
+    fn illegal_name(&mut self, location: &Location, name: &str) {
+        self.errors.push(FormatError::IllegalName { location: location.clone(), name: String::from(name) });
+    }
+
+    fn illegal_descriptor(&mut self, location: &Location, descriptor: &str) {
+        self.errors.push(FormatError::IllegalDescriptor {
+            location: location.clone(),
+            descriptor: String::from(descriptor),
+        });
+    }
+
+    fn bad_code(&mut self, location: &Location, message: String) {
+        self.errors.push(FormatError::BadCode { location: location.clone(), message });
+    }
+}
+
+fn at_most_one_visibility(flags: u16) -> bool {
+    (flags & (ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED)).count_ones() <= 1
+}
+
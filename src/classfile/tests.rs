@@ -0,0 +1,205 @@
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::classpath::Classpath;
+use crate::error::Error;
+use crate::runtime::class_loader::ClassLoader;
+use crate::verifier::VerifyMode;
+
+use super::class_reader::{Reader, ACC_PUBLIC, ACC_STATIC};
+use super::constant_pool::ConstantInfo;
+use super::descriptor::{is_class_constant_name, is_method_name, parse_field_descriptor, parse_method_descriptor};
+use super::format_check::{check_format, FormatError, Location};
+use super::writer::{ClassDef, FieldDef, MethodDef, Op};
+use super::ClassFile;
+
+const RETURN: &[Op] = &[Op::Return];
+
+fn class_with<'a>(fields: &'a [FieldDef<'a>], methods: &'a [MethodDef<'a>]) -> Vec<u8> {
+    ClassDef { fields, ..ClassDef::new("Sample", methods) }.write()
+}
+
+fn sample() -> Vec<u8> {
+    let methods = [MethodDef::code("run", "()V", ACC_PUBLIC | ACC_STATIC, 0, 0, RETURN)];
+    class_with(&[FieldDef::new("count", "I", ACC_STATIC)], &methods)
+}
+
+fn parse(content: Vec<u8>) -> ClassFile {
+    let reader = Reader {
+        content,
+        cursor: Cell::new(0),
+        attribute_lengths: RefCell::new(Vec::new()),
+        logger: Rc::default(),
+    };
+    reader.parse_classfile().unwrap()
+}
+
+// the diagnostics of a class file that must fail the format check
+fn format_errors(classfile: &ClassFile) -> Vec<FormatError> {
+    check_format(classfile).expect_err("the class file passed the format check")
+}
+
+// the index of the utf8 constant `value`
+fn utf8_index(classfile: &ClassFile, value: &str) -> u16 {
+    classfile.constant_pool.iter()
+        .position(|constant| matches!(constant, ConstantInfo::ConstantUTF8 { value: v } if v == value))
+        .unwrap() as u16
+}
+
+#[test]
+fn the_sample_class_passes_the_format_check() {
+    check_format(&parse(sample())).unwrap();
+}
+
+#[test]
+fn a_bad_magic_number_is_a_class_format_error() {
+    let mut content = sample();
+    content[..4].copy_from_slice(&0xCAFED00Du32.to_be_bytes());
+    assert_eq!(format_errors(&parse(content.clone())), vec![FormatError::BadMagic(0xCAFED00D)]);
+
+    let loader = ClassLoader::new(Classpath::builtin_classpath(PathBuf::from("."), Rc::default()), VerifyMode::All);
+    match loader.define_class("Sample", content) {
+        Err(Error::ClassFormat(errors)) => assert_eq!(errors, vec![FormatError::BadMagic(0xCAFED00D)]),
+        Err(error) => panic!("expected a class format error, got {}", error),
+        Ok(_) => panic!("a class with a bad magic number was defined"),
+    }
+}
+
+#[test]
+fn unsupported_versions_are_class_format_errors() {
+    // too new, too old, and a minor version other than 0 or the preview one on Java 12 and later
+    for (major, minor) in [(62, 0), (44, 0), (56, 3)] {
+        let mut content = sample();
+        content[4..6].copy_from_slice(&u16::to_be_bytes(minor));
+        content[6..8].copy_from_slice(&u16::to_be_bytes(major));
+        assert_eq!(format_errors(&parse(content)), vec![FormatError::UnsupportedVersion { major, minor }]);
+    }
+}
+
+#[test]
+fn a_reference_to_the_wrong_kind_of_constant_is_a_class_format_error() {
+    let mut classfile = parse(sample());
+    let name = utf8_index(&classfile, "Sample");
+    classfile.this_class = name;
+    assert_eq!(format_errors(&classfile), vec![FormatError::WrongConstantKind {
+        location: Location::Class,
+        index: name,
+        expected: "a class constant",
+    }]);
+
+    let mut classfile = parse(sample());
+    let class = classfile.this_class;
+    classfile.methods_info[0].name_index = class;
+    assert_eq!(format_errors(&classfile), vec![FormatError::WrongConstantKind {
+        location: Location::Method(String::from("run")),
+        index: class,
+        expected: "a utf8 constant",
+    }]);
+
+    let mut classfile = parse(sample());
+    classfile.super_class = classfile.constant_pool.len() as u16;
+    assert_eq!(format_errors(&classfile), vec![FormatError::BadConstantIndex {
+        location: Location::Class,
+        index: classfile.super_class,
+    }]);
+}
+
+#[test]
+fn duplicate_fields_and_methods_are_class_format_errors() {
+    let field = FieldDef::new("count", "I", ACC_STATIC);
+    // the same name with another descriptor is a different member
+    let fields = [field, FieldDef::new("count", "J", ACC_STATIC), field];
+    let errors = format_errors(&parse(class_with(&fields, &[])));
+    assert_eq!(errors, vec![FormatError::DuplicateField {
+        name: String::from("count"),
+        descriptor: String::from("I"),
+    }]);
+
+    let method = MethodDef::code("run", "()V", ACC_PUBLIC | ACC_STATIC, 0, 0, RETURN);
+    let methods = [method, MethodDef::code("run", "(I)V", ACC_PUBLIC | ACC_STATIC, 0, 1, RETURN), method];
+    let errors = format_errors(&parse(class_with(&[], &methods)));
+    assert_eq!(errors, vec![FormatError::DuplicateMethod {
+        name: String::from("run"),
+        descriptor: String::from("()V"),
+    }]);
+}
+
+#[test]
+fn malformed_descriptors_are_class_format_errors() {
+    for descriptor in ["V", "[V", "Ljava/lang/Object", "L;", "II", "La//b;", ""] {
+        let errors = format_errors(&parse(class_with(&[FieldDef::new("value", descriptor, ACC_STATIC)], &[])));
+        assert_eq!(errors, vec![FormatError::IllegalDescriptor {
+            location: Location::Field(String::from("value")),
+            descriptor: String::from(descriptor),
+        }], "{}", descriptor);
+    }
+    for descriptor in ["()", "(V)V", "I", "(I", "()VV", "()[V"] {
+        let methods = [MethodDef::code("run", descriptor, ACC_PUBLIC | ACC_STATIC, 0, 2, RETURN)];
+        let errors = format_errors(&parse(class_with(&[], &methods)));
+        assert_eq!(errors, vec![FormatError::IllegalDescriptor {
+            location: Location::Method(String::from("run")),
+            descriptor: String::from(descriptor),
+        }], "{}", descriptor);
+    }
+    // constructors return void
+    let methods = [MethodDef::code("<init>", "()I", ACC_PUBLIC, 0, 1, RETURN)];
+    assert!(matches!(&format_errors(&parse(class_with(&[], &methods)))[..], [FormatError::IllegalDescriptor { .. }]));
+
+    // more than 255 slots of parameters
+    let longs = format!("({})V", "J".repeat(128));
+    assert!(parse_method_descriptor(&longs).is_none());
+    assert!(parse_method_descriptor(&format!("({})V", "J".repeat(127))).is_some());
+    assert!(parse_field_descriptor(&format!("{}I", "[".repeat(256))).is_none());
+    assert!(parse_field_descriptor(&format!("{}I", "[".repeat(255))).is_some());
+}
+
+#[test]
+fn malformed_names_are_class_format_errors() {
+    for name in ["a.b", "a;b", "a[b", "a/b", ""] {
+        let errors = format_errors(&parse(class_with(&[FieldDef::new(name, "I", ACC_STATIC)], &[])));
+        assert_eq!(errors, vec![FormatError::IllegalName {
+            location: Location::Field(String::from(name)),
+            name: String::from(name),
+        }], "{}", name);
+    }
+    for name in ["<main>", "a>", "a.b"] {
+        let methods = [MethodDef::code(name, "()V", ACC_PUBLIC | ACC_STATIC, 0, 0, RETURN)];
+        let errors = format_errors(&parse(class_with(&[], &methods)));
+        assert_eq!(errors, vec![FormatError::IllegalName {
+            location: Location::Method(String::from(name)),
+            name: String::from(name),
+        }], "{}", name);
+    }
+    assert!(is_method_name("<init>") && is_method_name("<clinit>"));
+    assert!(is_class_constant_name("[Ljava/lang/String;") && !is_class_constant_name("[java/lang/String"));
+    assert!(!is_class_constant_name("java/lang/String;") && !is_class_constant_name("java//String"));
+}
+
+#[test]
+fn an_attribute_whose_length_does_not_match_its_contents_is_a_class_format_error() {
+    let content = sample();
+    let classfile = parse(content.clone());
+    let code = classfile.attribute_lengths.iter().find(|attribute| attribute.name == "Code").unwrap().declared;
+    // the name index and length that start the Code attribute
+    let mut header = utf8_index(&classfile, "Code").to_be_bytes().to_vec();
+    header.extend(code.to_be_bytes());
+    let start = content.windows(header.len()).position(|window| window == header).unwrap();
+
+    for declared in [code + 1, code - 1] {
+        let mut content = content.clone();
+        content[start + 2..start + 6].copy_from_slice(&declared.to_be_bytes());
+        assert_eq!(format_errors(&parse(content)), vec![FormatError::AttributeLengthMismatch {
+            name: String::from("Code"),
+            declared,
+            consumed: code,
+        }]);
+    }
+}
+
+#[test]
+fn bytes_after_the_last_attribute_are_a_class_format_error() {
+    let mut content = sample();
+    content.extend([0, 0, 0]);
+    assert_eq!(format_errors(&parse(content)), vec![FormatError::TrailingBytes(3)]);
+}
@@ -0,0 +1,520 @@
+use byteorder::{WriteBytesExt, BE};
+
+use super::class_reader::{ACC_ABSTRACT, ACC_FINAL, ACC_INTERFACE, ACC_NATIVE, ACC_PUBLIC, ACC_STATIC, ACC_SUPER};
+use super::descriptor::parse_method_descriptor;
+
+// writes class files for the classes the VM makes up itself: the built-in class library and the classes
+// that link invokedynamic call sites
+#[derive(Clone, Copy)]
+pub(crate) struct ClassDef<'a> {
+    pub name: &'a str,
+    pub super_class: Option<&'a str>,
+    pub interfaces: &'a [&'a str],
+    pub access_flags: u16,
+    pub fields: &'a [FieldDef<'a>],
+    pub methods: &'a [MethodDef<'a>],
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct FieldDef<'a> {
+    pub name: &'a str,
+    pub descriptor: &'a str,
+    pub access_flags: u16,
+    pub value: Option<Constant>,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Constant {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+}
+
+// a method without code is native, unless it is abstract
+#[derive(Clone, Copy)]
+pub(crate) struct MethodDef<'a> {
+    pub name: &'a str,
+    pub descriptor: &'a str,
+    pub access_flags: u16,
+    pub code: Option<(u16, u16, &'a [Op<'a>])>,
+    pub handlers: &'a [Handler<'a>],
+    // the LineNumberTable, the line each listed op starts, and the LocalVariableTable, the name, descriptor
+    // and slot of each variable, which is in scope in all of the code; both are left out when empty
+    pub lines: &'a [(usize, u16)],
+    pub locals: &'a [(&'a str, &'a str, u16)],
+}
+
+// an exception table entry: the ops from `start` up to `end` are covered by the handler at op `target`,
+// which catches `catch_type`, or everything when None
+#[derive(Clone, Copy)]
+pub(crate) struct Handler<'a> {
+    pub start: usize,
+    pub end: usize,
+    pub target: usize,
+    pub catch_type: Option<&'a str>,
+}
+
+// the instructions the made up classes need; branch targets are indexes into the method's ops
+#[derive(Clone, Copy)]
+pub(crate) enum Op<'a> {
+    ILoad(u8),
+    LLoad(u8),
+    FLoad(u8),
+    DLoad(u8),
+    ALoad(u8),
+    // only the tests assemble arithmetic on locals
+    #[cfg(test)]
+    IStore(u8),
+    AStore(u8),
+    AConstNull,
+    LConst0,
+    Ldc(&'a str),
+    // ldc_w or ldc2_w of a numeric constant
+    LdcConstant(Constant),
+    Dup,
+    Pop,
+    Pop2,
+    AALoad,
+    AAStore,
+    #[cfg(test)]
+    IAdd,
+    I2L,
+    I2F,
+    I2D,
+    L2F,
+    L2D,
+    F2D,
+    New(&'a str),
+    ANewArray(&'a str),
+    CheckCast(&'a str),
+    GetStatic(&'a str, &'a str, &'a str),
+    GetField(&'a str, &'a str, &'a str),
+    PutField(&'a str, &'a str, &'a str),
+    PutStatic(&'a str, &'a str, &'a str),
+    InvokeVirtual(&'a str, &'a str, &'a str),
+    InvokeSpecial(&'a str, &'a str, &'a str),
+    InvokeStatic(&'a str, &'a str, &'a str),
+    InvokeInterface(&'a str, &'a str, &'a str),
+    IfNonNull(usize),
+    IReturn,
+    LReturn,
+    FReturn,
+    DReturn,
+    AReturn,
+    Return,
+    AThrow,
+}
+
+impl<'a> ClassDef<'a> {
+    // a public class extending Object with no fields, which the tests start theirs from
+    #[cfg(test)]
+    pub const fn new(name: &'a str, methods: &'a [MethodDef<'a>]) -> ClassDef<'a> {
+        ClassDef {
+            name,
+            super_class: Some("java/lang/Object"),
+            interfaces: &[],
+            access_flags: ACC_PUBLIC,
+            fields: &[],
+            methods,
+        }
+    }
+
+    pub const fn interface(name: &'a str, methods: &'a [MethodDef<'a>]) -> ClassDef<'a> {
+        ClassDef {
+            name,
+            super_class: Some("java/lang/Object"),
+            interfaces: &[],
+            access_flags: ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT,
+            fields: &[],
+            methods,
+        }
+    }
+
+    // the class file, as javac would write it for Java 5 so that the branches need no stack map frames
+    pub fn write(&self) -> Vec<u8> {
+        let mut pool = ConstantPool::default();
+        let this_class = pool.class(self.name);
+        let super_class = self.super_class.map_or(0, |name| pool.class(name));
+        let interfaces: Vec<u16> = self.interfaces.iter().map(|name| pool.class(name)).collect();
+        let mut body = Vec::new();
+        let access_flags = match self.access_flags & ACC_INTERFACE {
+            0 => self.access_flags | ACC_SUPER,
+            _ => self.access_flags,
+        };
+        put_u16(&mut body, access_flags);
+        put_u16(&mut body, this_class);
+        put_u16(&mut body, super_class);
+        put_u16(&mut body, interfaces.len() as u16);
+        interfaces.iter().for_each(|index| put_u16(&mut body, *index));
+        put_u16(&mut body, self.fields.len() as u16);
+        for field in self.fields {
+            put_u16(&mut body, field.access_flags);
+            put_u16(&mut body, pool.utf8(field.name));
+            put_u16(&mut body, pool.utf8(field.descriptor));
+            match field.value {
+                Some(value) => {
+                    put_u16(&mut body, 1);
+                    put_u16(&mut body, pool.utf8("ConstantValue"));
+                    body.write_u32::<BE>(2).unwrap();
+                    put_u16(&mut body, pool.constant(value));
+                }
+                None => put_u16(&mut body, 0),
+            }
+        }
+        // constructors cannot be native, so the ones with a native get a body that only calls super(); the native
+        // registered for one runs in place of its bytecode
+        let super_init = Op::InvokeSpecial(self.super_class.unwrap_or("java/lang/Object"), "<init>", "()V");
+        let stub = [Op::ALoad(0), super_init, Op::Return];
+        put_u16(&mut body, self.methods.len() as u16);
+        for method in self.methods {
+            let (access_flags, code) = match method.code {
+                None if method.name == "<init>" => (method.access_flags, Some((1, method.arg_slots(), &stub[..]))),
+                None if method.access_flags & ACC_ABSTRACT == 0 => (method.access_flags | ACC_NATIVE, None),
+                code => (method.access_flags, code),
+            };
+            put_u16(&mut body, access_flags);
+            put_u16(&mut body, pool.utf8(method.name));
+            put_u16(&mut body, pool.utf8(method.descriptor));
+            match code {
+                Some((max_stack, max_locals, ops)) => {
+                    let (code, offsets) = assemble(ops, &mut pool);
+                    let offset = |index: usize| offsets.get(index).map_or(code.len(), |offset| *offset) as u16;
+                    let attributes = method.debug_attributes(&mut pool, &offset, code.len() as u16);
+                    put_u16(&mut body, 1);
+                    put_u16(&mut body, pool.utf8("Code"));
+                    let length = 10 + code.len() + 8 * method.handlers.len() + attributes.len();
+                    body.write_u32::<BE>(length as u32).unwrap();
+                    put_u16(&mut body, max_stack);
+                    put_u16(&mut body, max_locals);
+                    body.write_u32::<BE>(code.len() as u32).unwrap();
+                    body.extend_from_slice(&code);
+                    put_u16(&mut body, method.handlers.len() as u16);
+                    for handler in method.handlers {
+                        put_u16(&mut body, offset(handler.start));
+                        put_u16(&mut body, offset(handler.end));
+                        put_u16(&mut body, offset(handler.target));
+                        put_u16(&mut body, handler.catch_type.map_or(0, |name| pool.class(name)));
+                    }
+                    body.extend_from_slice(&attributes);
+                }
+                None => put_u16(&mut body, 0),
+            }
+        }
+        put_u16(&mut body, 0);
+        let mut class = Vec::new();
+        class.write_u32::<BE>(0xCAFEBABE).unwrap();
+        put_u16(&mut class, 0);
+        put_u16(&mut class, 49);
+        put_u16(&mut class, pool.count);
+        class.extend_from_slice(&pool.bytes);
+        class.extend_from_slice(&body);
+        class
+    }
+}
+
+impl<'a> FieldDef<'a> {
+    pub const fn new(name: &'a str, descriptor: &'a str, access_flags: u16) -> FieldDef<'a> {
+        FieldDef { name, descriptor, access_flags, value: None }
+    }
+
+    pub const fn constant(name: &'a str, descriptor: &'a str, value: Constant) -> FieldDef<'a> {
+        FieldDef { name, descriptor, access_flags: ACC_PUBLIC | ACC_STATIC | ACC_FINAL, value: Some(value) }
+    }
+}
+
+impl<'a> MethodDef<'a> {
+    pub const fn native(name: &'a str, descriptor: &'a str) -> MethodDef<'a> {
+        MethodDef::with_flags(name, descriptor, ACC_PUBLIC)
+    }
+
+    pub const fn static_native(name: &'a str, descriptor: &'a str) -> MethodDef<'a> {
+        MethodDef::with_flags(name, descriptor, ACC_PUBLIC | ACC_STATIC)
+    }
+
+    pub const fn abstract_method(name: &'a str, descriptor: &'a str) -> MethodDef<'a> {
+        MethodDef::with_flags(name, descriptor, ACC_PUBLIC | ACC_ABSTRACT)
+    }
+
+    pub const fn with_flags(name: &'a str, descriptor: &'a str, access_flags: u16) -> MethodDef<'a> {
+        MethodDef { name, descriptor, access_flags, code: None, handlers: &[], lines: &[], locals: &[] }
+    }
+
+    pub const fn code(
+        name: &'a str,
+        descriptor: &'a str,
+        access_flags: u16,
+        max_stack: u16,
+        max_locals: u16,
+        ops: &'a [Op<'a>],
+    ) -> MethodDef<'a> {
+        let code = Some((max_stack, max_locals, ops));
+        MethodDef { name, descriptor, access_flags, code, handlers: &[], lines: &[], locals: &[] }
+    }
+
+    // the attributes of the Code attribute, starting with their count, given the offsets of the ops
+    fn debug_attributes(&self, pool: &mut ConstantPool, offset: &dyn Fn(usize) -> u16, length: u16) -> Vec<u8> {
+        let mut attributes = Vec::new();
+        let count = !self.lines.is_empty() as u16 + !self.locals.is_empty() as u16;
+        put_u16(&mut attributes, count);
+        if !self.lines.is_empty() {
+            put_u16(&mut attributes, pool.utf8("LineNumberTable"));
+            attributes.write_u32::<BE>(2 + 4 * self.lines.len() as u32).unwrap();
+            put_u16(&mut attributes, self.lines.len() as u16);
+            for (op, line) in self.lines {
+                put_u16(&mut attributes, offset(*op));
+                put_u16(&mut attributes, *line);
+            }
+        }
+        if !self.locals.is_empty() {
+            put_u16(&mut attributes, pool.utf8("LocalVariableTable"));
+            attributes.write_u32::<BE>(2 + 10 * self.locals.len() as u32).unwrap();
+            put_u16(&mut attributes, self.locals.len() as u16);
+            for (name, descriptor, slot) in self.locals {
+                put_u16(&mut attributes, 0);
+                put_u16(&mut attributes, length);
+                put_u16(&mut attributes, pool.utf8(name));
+                put_u16(&mut attributes, pool.utf8(descriptor));
+                put_u16(&mut attributes, *slot);
+            }
+        }
+        attributes
+    }
+
+    // the slots of the arguments, `this` included
+    pub fn arg_slots(&self) -> u16 {
+        let slots = parse_method_descriptor(self.descriptor).map_or(0, |descriptor| descriptor.arg_slot_count());
+        match self.access_flags & ACC_STATIC {
+            0 => slots as u16 + 1,
+            _ => slots as u16,
+        }
+    }
+}
+
+// the constant pool being written, without duplicates
+#[derive(Default)]
+struct ConstantPool {
+    bytes: Vec<u8>,
+    // the next index, which is also the constant_pool_count
+    count: u16,
+    entries: Vec<(Vec<u8>, u16)>,
+}
+
+impl ConstantPool {
+    fn utf8(&mut self, value: &str) -> u16 {
+        let mut entry = vec![1];
+        put_u16(&mut entry, value.len() as u16);
+        entry.extend_from_slice(value.as_bytes());
+        self.add(entry, 1)
+    }
+
+    fn class(&mut self, name: &str) -> u16 {
+        let name = self.utf8(name);
+        self.add_referring(7, &[name])
+    }
+
+    fn string(&mut self, value: &str) -> u16 {
+        let value = self.utf8(value);
+        self.add_referring(8, &[value])
+    }
+
+    // a Fieldref, Methodref or InterfaceMethodref, by `tag`
+    fn member(&mut self, tag: u8, class: &str, name: &str, descriptor: &str) -> u16 {
+        let class = self.class(class);
+        let (name, descriptor) = (self.utf8(name), self.utf8(descriptor));
+        let name_and_type = self.add_referring(12, &[name, descriptor]);
+        self.add_referring(tag, &[class, name_and_type])
+    }
+
+    fn constant(&mut self, value: Constant) -> u16 {
+        let mut entry = Vec::new();
+        let slots = match value {
+            Constant::Int(value) => {
+                entry.push(3);
+                entry.write_i32::<BE>(value).unwrap();
+                1
+            }
+            Constant::Float(value) => {
+                entry.push(4);
+                entry.write_u32::<BE>(value.to_bits()).unwrap();
+                1
+            }
+            Constant::Long(value) => {
+                entry.push(5);
+                entry.write_i64::<BE>(value).unwrap();
+                2
+            }
+            Constant::Double(value) => {
+                entry.push(6);
+                entry.write_u64::<BE>(value.to_bits()).unwrap();
+                2
+            }
+        };
+        self.add(entry, slots)
+    }
+
+    fn add_referring(&mut self, tag: u8, indexes: &[u16]) -> u16 {
+        let mut entry = vec![tag];
+        indexes.iter().for_each(|index| put_u16(&mut entry, *index));
+        self.add(entry, 1)
+    }
+
+    // long and double constants take two indexes
+    fn add(&mut self, entry: Vec<u8>, slots: u16) -> u16 {
+        if let Some((_, index)) = self.entries.iter().find(|(existing, _)| *existing == entry) {
+            return *index;
+        }
+        let index = self.count.max(1);
+        self.count = index + slots;
+        self.bytes.extend_from_slice(&entry);
+        self.entries.push((entry, index));
+        index
+    }
+}
+
+// the bytecode of `ops` with the offset each op starts at
+fn assemble(ops: &[Op], pool: &mut ConstantPool) -> (Vec<u8>, Vec<usize>) {
+    let offsets: Vec<usize> = ops
+        .iter()
+        .scan(0, |offset, op| {
+            let start = *offset;
+            *offset += op.length();
+            Some(start)
+        })
+        .collect();
+    let mut code = Vec::new();
+    for (op, offset) in ops.iter().zip(&offsets) {
+        match *op {
+            Op::ILoad(index) => load(&mut code, 0x1a, 0x15, index),
+            Op::LLoad(index) => load(&mut code, 0x1e, 0x16, index),
+            Op::FLoad(index) => load(&mut code, 0x22, 0x17, index),
+            Op::DLoad(index) => load(&mut code, 0x26, 0x18, index),
+            Op::ALoad(index) => load(&mut code, 0x2a, 0x19, index),
+            #[cfg(test)]
+            Op::IStore(index) => load(&mut code, 0x3b, 0x36, index),
+            Op::AStore(index) => load(&mut code, 0x4b, 0x3a, index),
+            Op::AConstNull => code.push(0x01),
+            Op::LConst0 => code.push(0x09),
+            Op::Ldc(value) => {
+                code.push(0x13);
+                put_u16(&mut code, pool.string(value));
+            }
+            Op::LdcConstant(value) => {
+                code.push(match value {
+                    Constant::Int(_) | Constant::Float(_) => 0x13,
+                    Constant::Long(_) | Constant::Double(_) => 0x14,
+                });
+                put_u16(&mut code, pool.constant(value));
+            }
+            Op::Dup => code.push(0x59),
+            Op::Pop => code.push(0x57),
+            Op::Pop2 => code.push(0x58),
+            Op::AALoad => code.push(0x32),
+            Op::AAStore => code.push(0x53),
+            #[cfg(test)]
+            Op::IAdd => code.push(0x60),
+            Op::I2L => code.push(0x85),
+            Op::I2F => code.push(0x86),
+            Op::I2D => code.push(0x87),
+            Op::L2F => code.push(0x89),
+            Op::L2D => code.push(0x8a),
+            Op::F2D => code.push(0x8d),
+            Op::New(class) => {
+                code.push(0xbb);
+                put_u16(&mut code, pool.class(class));
+            }
+            Op::ANewArray(class) => {
+                code.push(0xbd);
+                put_u16(&mut code, pool.class(class));
+            }
+            Op::CheckCast(class) => {
+                code.push(0xc0);
+                put_u16(&mut code, pool.class(class));
+            }
+            Op::GetStatic(class, name, descriptor) => {
+                code.push(0xb2);
+                put_u16(&mut code, pool.member(9, class, name, descriptor));
+            }
+            Op::GetField(class, name, descriptor) => {
+                code.push(0xb4);
+                put_u16(&mut code, pool.member(9, class, name, descriptor));
+            }
+            Op::PutField(class, name, descriptor) => {
+                code.push(0xb5);
+                put_u16(&mut code, pool.member(9, class, name, descriptor));
+            }
+            Op::PutStatic(class, name, descriptor) => {
+                code.push(0xb3);
+                put_u16(&mut code, pool.member(9, class, name, descriptor));
+            }
+            Op::InvokeVirtual(class, name, descriptor) => {
+                code.push(0xb6);
+                put_u16(&mut code, pool.member(10, class, name, descriptor));
+            }
+            Op::InvokeSpecial(class, name, descriptor) => {
+                code.push(0xb7);
+                put_u16(&mut code, pool.member(10, class, name, descriptor));
+            }
+            Op::InvokeStatic(class, name, descriptor) => {
+                code.push(0xb8);
+                put_u16(&mut code, pool.member(10, class, name, descriptor));
+            }
+            Op::InvokeInterface(class, name, descriptor) => {
+                code.push(0xb9);
+                put_u16(&mut code, pool.member(11, class, name, descriptor));
+                let count = parse_method_descriptor(descriptor).map_or(0, |descriptor| descriptor.arg_slot_count());
+                code.extend_from_slice(&[count as u8 + 1, 0]);
+            }
+            Op::IfNonNull(target) => {
+                code.push(0xc7);
+                code.write_i16::<BE>((offsets[target] as isize - *offset as isize) as i16).unwrap();
+            }
+            Op::IReturn => code.push(0xac),
+            Op::LReturn => code.push(0xad),
+            Op::FReturn => code.push(0xae),
+            Op::DReturn => code.push(0xaf),
+            Op::AReturn => code.push(0xb0),
+            Op::Return => code.push(0xb1),
+            Op::AThrow => code.push(0xbf),
+        }
+    }
+    (code, offsets)
+}
+
+// a load or store of local `index`, with the short form for the first four locals
+fn load(code: &mut Vec<u8>, short: u8, long: u8, index: u8) {
+    match index {
+        0..=3 => code.push(short + index),
+        _ => code.extend_from_slice(&[long, index]),
+    }
+}
+
+impl Op<'_> {
+    fn length(&self) -> usize {
+        match self {
+            Op::ILoad(index) | Op::LLoad(index) | Op::FLoad(index) | Op::DLoad(index) | Op::ALoad(index)
+            | Op::AStore(index) => match index {
+                0..=3 => 1,
+                _ => 2,
+            },
+            #[cfg(test)]
+            Op::IStore(index) => match index {
+                0..=3 => 1,
+                _ => 2,
+            },
+            Op::AConstNull | Op::LConst0 | Op::Dup | Op::Pop | Op::Pop2 | Op::AALoad | Op::AAStore | Op::AThrow => 1,
+            Op::I2L | Op::I2F | Op::I2D | Op::L2F | Op::L2D | Op::F2D => 1,
+            #[cfg(test)]
+            Op::IAdd => 1,
+            Op::IReturn | Op::LReturn | Op::FReturn | Op::DReturn | Op::AReturn | Op::Return => 1,
+            Op::Ldc(_) | Op::LdcConstant(_) | Op::New(_) | Op::ANewArray(_) | Op::CheckCast(_) | Op::IfNonNull(_) => 3,
+            Op::GetStatic(..) | Op::GetField(..) | Op::PutField(..) | Op::PutStatic(..) => 3,
+            Op::InvokeVirtual(..) | Op::InvokeSpecial(..) | Op::InvokeStatic(..) => 3,
+            Op::InvokeInterface(..) => 5,
+        }
+    }
+}
+
+fn put_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.write_u16::<BE>(value).unwrap();
+}
@@ -0,0 +1,1472 @@
+use crate::classfile::class_reader::{
+    ACC_ABSTRACT, ACC_BRIDGE, ACC_FINAL, ACC_PRIVATE, ACC_PROTECTED, ACC_PUBLIC, ACC_STATIC,
+    ACC_SYNTHETIC, ACC_VARARGS,
+};
+use crate::classfile::writer::{ClassDef, Constant, FieldDef, MethodDef, Op};
+use crate::log::Logger;
+
+use super::entry::Entry;
+use super::{Error, Result};
+
+// the class library the VM brings along for running without a JRE: a few java.lang and java.io classes
+// whose methods are natives in native::builtin, apart from the bridges back into Java, like
+// String.valueOf(Object) calling toString, which are written out as bytecode, along with the
+// java.lang.invoke classes bootstrap methods and method handles deal in, the java.lang.reflect classes,
+// with proxies and the annotations made of them, and the common functional interfaces.
+// The class files are made up when the class loader asks for them, so the classes go through loading like any other
+pub struct BuiltinEntry;
+
+impl Entry for BuiltinEntry {
+    fn read_class(&self, class_name: &str, _logger: &Logger) -> Result<Vec<u8>> {
+        let name = class_name.strip_suffix(".class").unwrap_or(class_name);
+        match find(name) {
+            Some(class) => Ok(class.write()),
+            None => Err(Error::ClassNotFound(String::from(class_name))),
+        }
+    }
+}
+
+// the throwables of the library with their super classes; all of them have Throwable's constructors
+pub(crate) const THROWABLES: &[(&str, &str)] = &[
+    (THROWABLE, OBJECT),
+    ("java/lang/Exception", THROWABLE),
+    ("java/lang/Error", THROWABLE),
+    ("java/lang/RuntimeException", "java/lang/Exception"),
+    ("java/lang/ArithmeticException", "java/lang/RuntimeException"),
+    ("java/lang/ArrayStoreException", "java/lang/RuntimeException"),
+    ("java/lang/ClassCastException", "java/lang/RuntimeException"),
+    ("java/lang/IllegalArgumentException", "java/lang/RuntimeException"),
+    ("java/lang/NumberFormatException", "java/lang/IllegalArgumentException"),
+    ("java/lang/IllegalMonitorStateException", "java/lang/RuntimeException"),
+    ("java/lang/IllegalStateException", "java/lang/RuntimeException"),
+    ("java/lang/IndexOutOfBoundsException", "java/lang/RuntimeException"),
+    ("java/lang/ArrayIndexOutOfBoundsException", "java/lang/IndexOutOfBoundsException"),
+    ("java/lang/StringIndexOutOfBoundsException", "java/lang/IndexOutOfBoundsException"),
+    ("java/lang/NegativeArraySizeException", "java/lang/RuntimeException"),
+    ("java/lang/NullPointerException", "java/lang/RuntimeException"),
+    ("java/lang/UnsupportedOperationException", "java/lang/RuntimeException"),
+    ("java/lang/ReflectiveOperationException", "java/lang/Exception"),
+    ("java/lang/ClassNotFoundException", "java/lang/ReflectiveOperationException"),
+    ("java/lang/IllegalAccessException", "java/lang/ReflectiveOperationException"),
+    ("java/lang/InstantiationException", "java/lang/ReflectiveOperationException"),
+    ("java/lang/NoSuchFieldException", "java/lang/ReflectiveOperationException"),
+    ("java/lang/NoSuchMethodException", "java/lang/ReflectiveOperationException"),
+    ("java/lang/reflect/InvocationTargetException", "java/lang/ReflectiveOperationException"),
+    ("java/lang/invoke/WrongMethodTypeException", "java/lang/RuntimeException"),
+    ("java/lang/annotation/IncompleteAnnotationException", "java/lang/RuntimeException"),
+    ("java/lang/CloneNotSupportedException", "java/lang/Exception"),
+    ("java/lang/InterruptedException", "java/lang/Exception"),
+    ("java/io/IOException", "java/lang/Exception"),
+    ("java/lang/LinkageError", "java/lang/Error"),
+    ("java/lang/NoClassDefFoundError", "java/lang/LinkageError"),
+    ("java/lang/ClassCircularityError", "java/lang/LinkageError"),
+    ("java/lang/ClassFormatError", "java/lang/LinkageError"),
+    ("java/lang/VerifyError", "java/lang/LinkageError"),
+    ("java/lang/ExceptionInInitializerError", "java/lang/LinkageError"),
+    ("java/lang/BootstrapMethodError", "java/lang/LinkageError"),
+    ("java/lang/UnsatisfiedLinkError", "java/lang/LinkageError"),
+    ("java/lang/IncompatibleClassChangeError", "java/lang/LinkageError"),
+    ("java/lang/AbstractMethodError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/IllegalAccessError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/InstantiationError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/NoSuchFieldError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/NoSuchMethodError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/VirtualMachineError", "java/lang/Error"),
+    ("java/lang/InternalError", "java/lang/VirtualMachineError"),
+    ("java/lang/OutOfMemoryError", "java/lang/VirtualMachineError"),
+    ("java/lang/StackOverflowError", "java/lang/VirtualMachineError"),
+];
+
+const OBJECT: &str = "java/lang/Object";
+const STRING: &str = "java/lang/String";
+const THROWABLE: &str = "java/lang/Throwable";
+const METHOD_HANDLE: &str = "java/lang/invoke/MethodHandle";
+const METHOD_TYPE: &str = "java/lang/invoke/MethodType";
+const LOOKUP: &str = "java/lang/invoke/MethodHandles$Lookup";
+const CALL_SITE: &str = "java/lang/invoke/CallSite";
+const CLASS: &str = "java/lang/Class";
+const ACCESSIBLE_OBJECT: &str = "java/lang/reflect/AccessibleObject";
+const FIELD: &str = "java/lang/reflect/Field";
+const METHOD: &str = "java/lang/reflect/Method";
+const CONSTRUCTOR: &str = "java/lang/reflect/Constructor";
+const ANNOTATED_ELEMENT: &str = "java/lang/reflect/AnnotatedElement";
+const INVOCATION_HANDLER: &str = "java/lang/reflect/InvocationHandler";
+const PROXY: &str = "java/lang/reflect/Proxy";
+const FUNCTION: &str = "java/util/function/Function";
+const BI_FUNCTION: &str = "java/util/function/BiFunction";
+const VALUE_OF_OBJECT: Op = Op::InvokeStatic(STRING, "valueOf", "(Ljava/lang/Object;)Ljava/lang/String;");
+const TO_STRING: Op = Op::InvokeVirtual(OBJECT, "toString", "()Ljava/lang/String;");
+const CONCAT: Op = Op::InvokeVirtual(STRING, "concat", "(Ljava/lang/String;)Ljava/lang/String;");
+const GET_CLASS_NAME: [Op; 2] = [
+    Op::InvokeVirtual(OBJECT, "getClass", "()Ljava/lang/Class;"),
+    Op::InvokeVirtual("java/lang/Class", "getName", "()Ljava/lang/String;"),
+];
+
+const CLASSES: &[ClassDef] = &[
+    ClassDef {
+        name: OBJECT,
+        super_class: None,
+        interfaces: &[],
+        access_flags: ACC_PUBLIC,
+        fields: &[],
+        methods: &[
+            MethodDef::code("<init>", "()V", ACC_PUBLIC, 0, 1, &[Op::Return]),
+            MethodDef::native("hashCode", "()I"),
+            MethodDef::native("equals", "(Ljava/lang/Object;)Z"),
+            // getClass().getName() + "@" + Integer.toHexString(hashCode())
+            MethodDef::code(
+                "toString",
+                "()Ljava/lang/String;",
+                ACC_PUBLIC,
+                3,
+                1,
+                &[
+                    Op::ALoad(0),
+                    GET_CLASS_NAME[0],
+                    GET_CLASS_NAME[1],
+                    Op::Ldc("@"),
+                    CONCAT,
+                    Op::ALoad(0),
+                    Op::InvokeVirtual(OBJECT, "hashCode", "()I"),
+                    Op::InvokeStatic("java/lang/Integer", "toHexString", "(I)Ljava/lang/String;"),
+                    CONCAT,
+                    Op::AReturn,
+                ],
+            ),
+            MethodDef::with_flags("getClass", "()Ljava/lang/Class;", ACC_PUBLIC | ACC_FINAL),
+            MethodDef::with_flags("clone", "()Ljava/lang/Object;", ACC_PROTECTED),
+            MethodDef::with_flags("notify", "()V", ACC_PUBLIC | ACC_FINAL),
+            MethodDef::with_flags("notifyAll", "()V", ACC_PUBLIC | ACC_FINAL),
+            MethodDef::with_flags("wait", "(J)V", ACC_PUBLIC | ACC_FINAL),
+            MethodDef::code(
+                "wait",
+                "()V",
+                ACC_PUBLIC | ACC_FINAL,
+                3,
+                1,
+                &[Op::ALoad(0), Op::LConst0, Op::InvokeVirtual(OBJECT, "wait", "(J)V"), Op::Return],
+            ),
+        ],
+    },
+    ClassDef::interface("java/lang/Cloneable", &[]),
+    ClassDef::interface("java/io/Serializable", &[]),
+    ClassDef::interface("java/lang/Comparable", &[MethodDef::abstract_method("compareTo", "(Ljava/lang/Object;)I")]),
+    ClassDef::interface(
+        "java/lang/CharSequence",
+        &[
+            MethodDef::abstract_method("length", "()I"),
+            MethodDef::abstract_method("charAt", "(I)C"),
+            MethodDef::abstract_method("toString", "()Ljava/lang/String;"),
+        ],
+    ),
+    ClassDef {
+        name: STRING,
+        super_class: Some(OBJECT),
+        interfaces: &["java/io/Serializable", "java/lang/Comparable", "java/lang/CharSequence"],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[FieldDef::new("value", "[C", ACC_PRIVATE | ACC_FINAL)],
+        methods: &[
+            MethodDef::native("<init>", "()V"),
+            MethodDef::native("<init>", "(Ljava/lang/String;)V"),
+            MethodDef::native("<init>", "([C)V"),
+            MethodDef::native("<init>", "([CII)V"),
+            MethodDef::native("length", "()I"),
+            MethodDef::native("isEmpty", "()Z"),
+            MethodDef::native("charAt", "(I)C"),
+            MethodDef::native("equals", "(Ljava/lang/Object;)Z"),
+            MethodDef::native("equalsIgnoreCase", "(Ljava/lang/String;)Z"),
+            MethodDef::native("hashCode", "()I"),
+            MethodDef::code("toString", "()Ljava/lang/String;", ACC_PUBLIC, 1, 1, &[Op::ALoad(0), Op::AReturn]),
+            MethodDef::native("compareTo", "(Ljava/lang/String;)I"),
+            MethodDef::code(
+                "compareTo",
+                "(Ljava/lang/Object;)I",
+                ACC_PUBLIC | ACC_BRIDGE | ACC_SYNTHETIC,
+                2,
+                2,
+                &[
+                    Op::ALoad(0),
+                    Op::ALoad(1),
+                    Op::CheckCast(STRING),
+                    Op::InvokeVirtual(STRING, "compareTo", "(Ljava/lang/String;)I"),
+                    Op::IReturn,
+                ],
+            ),
+            MethodDef::native("concat", "(Ljava/lang/String;)Ljava/lang/String;"),
+            MethodDef::native("substring", "(I)Ljava/lang/String;"),
+            MethodDef::native("substring", "(II)Ljava/lang/String;"),
+            MethodDef::native("indexOf", "(I)I"),
+            MethodDef::native("indexOf", "(Ljava/lang/String;)I"),
+            MethodDef::native("lastIndexOf", "(I)I"),
+            MethodDef::native("contains", "(Ljava/lang/CharSequence;)Z"),
+            MethodDef::native("startsWith", "(Ljava/lang/String;)Z"),
+            MethodDef::native("endsWith", "(Ljava/lang/String;)Z"),
+            MethodDef::native("trim", "()Ljava/lang/String;"),
+            MethodDef::native("toUpperCase", "()Ljava/lang/String;"),
+            MethodDef::native("toLowerCase", "()Ljava/lang/String;"),
+            MethodDef::native("replace", "(CC)Ljava/lang/String;"),
+            MethodDef::native("repeat", "(I)Ljava/lang/String;"),
+            MethodDef::native("toCharArray", "()[C"),
+            MethodDef::native("intern", "()Ljava/lang/String;"),
+            MethodDef::code(
+                "valueOf",
+                "(Ljava/lang/Object;)Ljava/lang/String;",
+                ACC_PUBLIC | ACC_STATIC,
+                1,
+                1,
+                &[Op::ALoad(0), Op::IfNonNull(4), Op::Ldc("null"), Op::AReturn, Op::ALoad(0), TO_STRING, Op::AReturn],
+            ),
+            MethodDef::static_native("valueOf", "(Z)Ljava/lang/String;"),
+            MethodDef::static_native("valueOf", "(C)Ljava/lang/String;"),
+            MethodDef::static_native("valueOf", "(I)Ljava/lang/String;"),
+            MethodDef::static_native("valueOf", "(J)Ljava/lang/String;"),
+            MethodDef::static_native("valueOf", "(F)Ljava/lang/String;"),
+            MethodDef::static_native("valueOf", "(D)Ljava/lang/String;"),
+            MethodDef::static_native("valueOf", "([C)Ljava/lang/String;"),
+        ],
+    },
+    ClassDef {
+        name: "java/lang/StringBuilder",
+        super_class: Some(OBJECT),
+        interfaces: &["java/io/Serializable", "java/lang/CharSequence"],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[FieldDef::new("value", "[C", ACC_PRIVATE), FieldDef::new("count", "I", ACC_PRIVATE)],
+        methods: &[
+            MethodDef::native("<init>", "()V"),
+            MethodDef::native("<init>", "(I)V"),
+            MethodDef::native("<init>", "(Ljava/lang/String;)V"),
+            MethodDef::native("append", "(Ljava/lang/String;)Ljava/lang/StringBuilder;"),
+            MethodDef::native("append", "(Z)Ljava/lang/StringBuilder;"),
+            MethodDef::native("append", "(C)Ljava/lang/StringBuilder;"),
+            MethodDef::native("append", "(I)Ljava/lang/StringBuilder;"),
+            MethodDef::native("append", "(J)Ljava/lang/StringBuilder;"),
+            MethodDef::native("append", "(F)Ljava/lang/StringBuilder;"),
+            MethodDef::native("append", "(D)Ljava/lang/StringBuilder;"),
+            MethodDef::native("append", "([C)Ljava/lang/StringBuilder;"),
+            MethodDef::code(
+                "append",
+                "(Ljava/lang/Object;)Ljava/lang/StringBuilder;",
+                ACC_PUBLIC,
+                2,
+                2,
+                &APPEND_STRING_VALUE,
+            ),
+            MethodDef::code(
+                "append",
+                "(Ljava/lang/CharSequence;)Ljava/lang/StringBuilder;",
+                ACC_PUBLIC,
+                2,
+                2,
+                &APPEND_STRING_VALUE,
+            ),
+            MethodDef::native("insert", "(ILjava/lang/String;)Ljava/lang/StringBuilder;"),
+            MethodDef::native("deleteCharAt", "(I)Ljava/lang/StringBuilder;"),
+            MethodDef::native("reverse", "()Ljava/lang/StringBuilder;"),
+            MethodDef::native("length", "()I"),
+            MethodDef::native("charAt", "(I)C"),
+            MethodDef::native("setCharAt", "(IC)V"),
+            MethodDef::native("setLength", "(I)V"),
+            MethodDef::native("indexOf", "(Ljava/lang/String;)I"),
+            MethodDef::native("toString", "()Ljava/lang/String;"),
+        ],
+    },
+    ClassDef {
+        name: CLASS,
+        super_class: Some(OBJECT),
+        interfaces: &["java/io/Serializable", ANNOTATED_ELEMENT],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[],
+        methods: &[
+            MethodDef::native("getName", "()Ljava/lang/String;"),
+            MethodDef::native("getSimpleName", "()Ljava/lang/String;"),
+            MethodDef::native("toString", "()Ljava/lang/String;"),
+            MethodDef::native("isArray", "()Z"),
+            MethodDef::native("isPrimitive", "()Z"),
+            MethodDef::native("isInterface", "()Z"),
+            MethodDef::native("isInstance", "(Ljava/lang/Object;)Z"),
+            MethodDef::native("isAssignableFrom", "(Ljava/lang/Class;)Z"),
+            MethodDef::native("getSuperclass", "()Ljava/lang/Class;"),
+            MethodDef::native("getComponentType", "()Ljava/lang/Class;"),
+            MethodDef::native("getModifiers", "()I"),
+            MethodDef::native("desiredAssertionStatus", "()Z"),
+            MethodDef::with_flags("getPrimitiveClass", "(Ljava/lang/String;)Ljava/lang/Class;", ACC_STATIC),
+            // forName0(name, true, null, null)
+            MethodDef::code(
+                "forName",
+                "(Ljava/lang/String;)Ljava/lang/Class;",
+                ACC_PUBLIC | ACC_STATIC,
+                4,
+                1,
+                &[
+                    Op::ALoad(0),
+                    Op::LdcConstant(Constant::Int(1)),
+                    Op::AConstNull,
+                    Op::AConstNull,
+                    Op::InvokeStatic(CLASS, "forName0", FOR_NAME0),
+                    Op::AReturn,
+                ],
+            ),
+            MethodDef::with_flags("forName0", FOR_NAME0, ACC_PRIVATE | ACC_STATIC),
+            MethodDef::code(
+                "getDeclaredFields",
+                "()[Ljava/lang/reflect/Field;",
+                ACC_PUBLIC,
+                2,
+                1,
+                &all_declared("getDeclaredFields0", "(Z)[Ljava/lang/reflect/Field;"),
+            ),
+            MethodDef::code(
+                "getDeclaredMethods",
+                "()[Ljava/lang/reflect/Method;",
+                ACC_PUBLIC,
+                2,
+                1,
+                &all_declared("getDeclaredMethods0", "(Z)[Ljava/lang/reflect/Method;"),
+            ),
+            MethodDef::code(
+                "getDeclaredConstructors",
+                "()[Ljava/lang/reflect/Constructor;",
+                ACC_PUBLIC,
+                2,
+                1,
+                &all_declared("getDeclaredConstructors0", "(Z)[Ljava/lang/reflect/Constructor;"),
+            ),
+            MethodDef::with_flags("getDeclaredFields0", "(Z)[Ljava/lang/reflect/Field;", ACC_PRIVATE),
+            MethodDef::with_flags("getDeclaredMethods0", "(Z)[Ljava/lang/reflect/Method;", ACC_PRIVATE),
+            MethodDef::with_flags("getDeclaredConstructors0", "(Z)[Ljava/lang/reflect/Constructor;", ACC_PRIVATE),
+            ANNOTATED_ELEMENT_METHODS[0],
+            ANNOTATED_ELEMENT_METHODS[1],
+            ANNOTATED_ELEMENT_METHODS[2],
+            ANNOTATED_ELEMENT_METHODS[3],
+        ],
+    },
+    ClassDef::interface(
+        ANNOTATED_ELEMENT,
+        &[
+            MethodDef::abstract_method("getAnnotation", "(Ljava/lang/Class;)Ljava/lang/annotation/Annotation;"),
+            MethodDef::abstract_method("isAnnotationPresent", "(Ljava/lang/Class;)Z"),
+            MethodDef::abstract_method("getAnnotations", "()[Ljava/lang/annotation/Annotation;"),
+            MethodDef::abstract_method("getDeclaredAnnotations", "()[Ljava/lang/annotation/Annotation;"),
+        ],
+    ),
+    ClassDef::interface(
+        "java/lang/annotation/Annotation",
+        &[
+            MethodDef::abstract_method("equals", "(Ljava/lang/Object;)Z"),
+            MethodDef::abstract_method("hashCode", "()I"),
+            MethodDef::abstract_method("toString", "()Ljava/lang/String;"),
+            MethodDef::abstract_method("annotationType", "()Ljava/lang/Class;"),
+        ],
+    ),
+    // the fields of Field, Method and Constructor are the JDK's, which the natives making them set
+    ClassDef {
+        name: ACCESSIBLE_OBJECT,
+        super_class: Some(OBJECT),
+        interfaces: &[ANNOTATED_ELEMENT],
+        access_flags: ACC_PUBLIC,
+        fields: &[FieldDef::new("override", "Z", ACC_PRIVATE)],
+        methods: &[
+            MethodDef::code("<init>", "()V", ACC_PROTECTED, 1, 1, &[Op::ALoad(0), OBJECT_INIT, Op::Return]),
+            // access is not checked, so this changes nothing
+            MethodDef::code(
+                "setAccessible",
+                "(Z)V",
+                ACC_PUBLIC,
+                2,
+                2,
+                &[Op::ALoad(0), Op::ILoad(1), Op::PutField(ACCESSIBLE_OBJECT, "override", "Z"), Op::Return],
+            ),
+            ANNOTATED_ELEMENT_METHODS[0],
+            ANNOTATED_ELEMENT_METHODS[1],
+            ANNOTATED_ELEMENT_METHODS[2],
+            ANNOTATED_ELEMENT_METHODS[3],
+        ],
+    },
+    ClassDef {
+        name: "java/lang/reflect/Modifier",
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC,
+        fields: &[
+            FieldDef::constant("PUBLIC", "I", Constant::Int(0x0001)),
+            FieldDef::constant("PRIVATE", "I", Constant::Int(0x0002)),
+            FieldDef::constant("PROTECTED", "I", Constant::Int(0x0004)),
+            FieldDef::constant("STATIC", "I", Constant::Int(0x0008)),
+            FieldDef::constant("FINAL", "I", Constant::Int(0x0010)),
+            FieldDef::constant("SYNCHRONIZED", "I", Constant::Int(0x0020)),
+            FieldDef::constant("VOLATILE", "I", Constant::Int(0x0040)),
+            FieldDef::constant("TRANSIENT", "I", Constant::Int(0x0080)),
+            FieldDef::constant("NATIVE", "I", Constant::Int(0x0100)),
+            FieldDef::constant("INTERFACE", "I", Constant::Int(0x0200)),
+            FieldDef::constant("ABSTRACT", "I", Constant::Int(0x0400)),
+        ],
+        methods: &[
+            MethodDef::static_native("isPublic", "(I)Z"),
+            MethodDef::static_native("isPrivate", "(I)Z"),
+            MethodDef::static_native("isProtected", "(I)Z"),
+            MethodDef::static_native("isStatic", "(I)Z"),
+            MethodDef::static_native("isFinal", "(I)Z"),
+            MethodDef::static_native("isSynchronized", "(I)Z"),
+            MethodDef::static_native("isVolatile", "(I)Z"),
+            MethodDef::static_native("isTransient", "(I)Z"),
+            MethodDef::static_native("isNative", "(I)Z"),
+            MethodDef::static_native("isInterface", "(I)Z"),
+            MethodDef::static_native("isAbstract", "(I)Z"),
+        ],
+    },
+    ClassDef {
+        name: FIELD,
+        super_class: Some(ACCESSIBLE_OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("clazz", "Ljava/lang/Class;", ACC_PRIVATE),
+            FieldDef::new("slot", "I", ACC_PRIVATE),
+            FieldDef::new("name", "Ljava/lang/String;", ACC_PRIVATE),
+            FieldDef::new("type", "Ljava/lang/Class;", ACC_PRIVATE),
+            FieldDef::new("modifiers", "I", ACC_PRIVATE),
+            FieldDef::new("trustedFinal", "Z", ACC_PRIVATE),
+            FieldDef::new("signature", "Ljava/lang/String;", ACC_PRIVATE),
+            FieldDef::new("annotations", "[B", ACC_PRIVATE),
+        ],
+        methods: &[
+            MethodDef::code("<init>", "()V", ACC_PRIVATE, 1, 1, &[Op::ALoad(0), ACCESSIBLE_OBJECT_INIT, Op::Return]),
+            MethodDef::code(
+                "getDeclaringClass",
+                "()Ljava/lang/Class;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &get_member(FIELD, "clazz", "Ljava/lang/Class;"),
+            ),
+            MethodDef::code(
+                "getName",
+                "()Ljava/lang/String;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &get_member(FIELD, "name", "Ljava/lang/String;"),
+            ),
+            MethodDef::code(
+                "getType",
+                "()Ljava/lang/Class;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &get_member(FIELD, "type", "Ljava/lang/Class;"),
+            ),
+            MethodDef::code("getModifiers", "()I", ACC_PUBLIC, 1, 1, &get_modifiers(FIELD)),
+            MethodDef::native("get", "(Ljava/lang/Object;)Ljava/lang/Object;"),
+            MethodDef::native("set", "(Ljava/lang/Object;Ljava/lang/Object;)V"),
+        ],
+    },
+    ClassDef {
+        name: METHOD,
+        super_class: Some(ACCESSIBLE_OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("clazz", "Ljava/lang/Class;", ACC_PRIVATE),
+            FieldDef::new("slot", "I", ACC_PRIVATE),
+            FieldDef::new("name", "Ljava/lang/String;", ACC_PRIVATE),
+            FieldDef::new("returnType", "Ljava/lang/Class;", ACC_PRIVATE),
+            FieldDef::new("parameterTypes", "[Ljava/lang/Class;", ACC_PRIVATE),
+            FieldDef::new("exceptionTypes", "[Ljava/lang/Class;", ACC_PRIVATE),
+            FieldDef::new("modifiers", "I", ACC_PRIVATE),
+            FieldDef::new("signature", "Ljava/lang/String;", ACC_PRIVATE),
+            FieldDef::new("annotations", "[B", ACC_PRIVATE),
+            FieldDef::new("parameterAnnotations", "[B", ACC_PRIVATE),
+            FieldDef::new("annotationDefault", "[B", ACC_PRIVATE),
+        ],
+        methods: &[
+            MethodDef::code("<init>", "()V", ACC_PRIVATE, 1, 1, &[Op::ALoad(0), ACCESSIBLE_OBJECT_INIT, Op::Return]),
+            MethodDef::code(
+                "getDeclaringClass",
+                "()Ljava/lang/Class;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &get_member(METHOD, "clazz", "Ljava/lang/Class;"),
+            ),
+            MethodDef::code(
+                "getName",
+                "()Ljava/lang/String;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &get_member(METHOD, "name", "Ljava/lang/String;"),
+            ),
+            MethodDef::code(
+                "getReturnType",
+                "()Ljava/lang/Class;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &get_member(METHOD, "returnType", "Ljava/lang/Class;"),
+            ),
+            MethodDef::code(
+                "getParameterTypes",
+                "()[Ljava/lang/Class;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &get_classes(METHOD, "parameterTypes"),
+            ),
+            MethodDef::code(
+                "getExceptionTypes",
+                "()[Ljava/lang/Class;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &get_classes(METHOD, "exceptionTypes"),
+            ),
+            MethodDef::code("getModifiers", "()I", ACC_PUBLIC, 1, 1, &get_modifiers(METHOD)),
+            MethodDef::with_flags(
+                "invoke",
+                "(Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;",
+                ACC_PUBLIC | ACC_VARARGS,
+            ),
+            MethodDef::native("getParameterAnnotations", "()[[Ljava/lang/annotation/Annotation;"),
+            MethodDef::native("getDefaultValue", "()Ljava/lang/Object;"),
+        ],
+    },
+    ClassDef {
+        name: CONSTRUCTOR,
+        super_class: Some(ACCESSIBLE_OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("clazz", "Ljava/lang/Class;", ACC_PRIVATE),
+            FieldDef::new("slot", "I", ACC_PRIVATE),
+            FieldDef::new("parameterTypes", "[Ljava/lang/Class;", ACC_PRIVATE),
+            FieldDef::new("exceptionTypes", "[Ljava/lang/Class;", ACC_PRIVATE),
+            FieldDef::new("modifiers", "I", ACC_PRIVATE),
+            FieldDef::new("signature", "Ljava/lang/String;", ACC_PRIVATE),
+            FieldDef::new("annotations", "[B", ACC_PRIVATE),
+            FieldDef::new("parameterAnnotations", "[B", ACC_PRIVATE),
+        ],
+        methods: &[
+            MethodDef::code("<init>", "()V", ACC_PRIVATE, 1, 1, &[Op::ALoad(0), ACCESSIBLE_OBJECT_INIT, Op::Return]),
+            MethodDef::code(
+                "getDeclaringClass",
+                "()Ljava/lang/Class;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &get_member(CONSTRUCTOR, "clazz", "Ljava/lang/Class;"),
+            ),
+            MethodDef::code(
+                "getParameterTypes",
+                "()[Ljava/lang/Class;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &get_classes(CONSTRUCTOR, "parameterTypes"),
+            ),
+            MethodDef::code(
+                "getExceptionTypes",
+                "()[Ljava/lang/Class;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &get_classes(CONSTRUCTOR, "exceptionTypes"),
+            ),
+            MethodDef::code("getModifiers", "()I", ACC_PUBLIC, 1, 1, &get_modifiers(CONSTRUCTOR)),
+            MethodDef::with_flags("newInstance", "([Ljava/lang/Object;)Ljava/lang/Object;", ACC_PUBLIC | ACC_VARARGS),
+            MethodDef::native("getParameterAnnotations", "()[[Ljava/lang/annotation/Annotation;"),
+        ],
+    },
+    ClassDef::interface(
+        INVOCATION_HANDLER,
+        &[MethodDef::abstract_method(
+            "invoke",
+            "(Ljava/lang/Object;Ljava/lang/reflect/Method;[Ljava/lang/Object;)Ljava/lang/Object;",
+        )],
+    ),
+    // the super class of the proxy classes the VM spins, whose constructor takes the handler
+    ClassDef {
+        name: PROXY,
+        super_class: Some(OBJECT),
+        interfaces: &["java/io/Serializable"],
+        access_flags: ACC_PUBLIC,
+        fields: &[FieldDef::new("h", "Ljava/lang/reflect/InvocationHandler;", ACC_PROTECTED)],
+        methods: &[
+            MethodDef::code(
+                "<init>",
+                "(Ljava/lang/reflect/InvocationHandler;)V",
+                ACC_PROTECTED,
+                2,
+                2,
+                &[
+                    Op::ALoad(0),
+                    OBJECT_INIT,
+                    Op::ALoad(0),
+                    Op::ALoad(1),
+                    Op::PutField(PROXY, "h", "Ljava/lang/reflect/InvocationHandler;"),
+                    Op::Return,
+                ],
+            ),
+            MethodDef::static_native(
+                "newProxyInstance",
+                "(Ljava/lang/ClassLoader;[Ljava/lang/Class;Ljava/lang/reflect/InvocationHandler;)Ljava/lang/Object;",
+            ),
+            MethodDef::static_native("isProxyClass", "(Ljava/lang/Class;)Z"),
+            MethodDef::static_native(
+                "getInvocationHandler",
+                "(Ljava/lang/Object;)Ljava/lang/reflect/InvocationHandler;",
+            ),
+        ],
+    },
+    // what the annotations are proxies of, holding the names of their members and the values
+    ClassDef {
+        name: "sun/reflect/annotation/AnnotationInvocationHandler",
+        super_class: Some(OBJECT),
+        interfaces: &[INVOCATION_HANDLER],
+        access_flags: ACC_FINAL,
+        fields: &[
+            FieldDef::new("type", "Ljava/lang/Class;", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::new("names", "[Ljava/lang/String;", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::new("values", "[Ljava/lang/Object;", ACC_PRIVATE | ACC_FINAL),
+        ],
+        methods: &[MethodDef::native(
+            "invoke",
+            "(Ljava/lang/Object;Ljava/lang/reflect/Method;[Ljava/lang/Object;)Ljava/lang/Object;",
+        )],
+    },
+    ClassDef {
+        name: "java/lang/System",
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("out", "Ljava/io/PrintStream;", ACC_PUBLIC | ACC_STATIC | ACC_FINAL),
+            FieldDef::new("err", "Ljava/io/PrintStream;", ACC_PUBLIC | ACC_STATIC | ACC_FINAL),
+        ],
+        methods: &[
+            MethodDef::static_native("currentTimeMillis", "()J"),
+            MethodDef::static_native("nanoTime", "()J"),
+            MethodDef::static_native("arraycopy", "(Ljava/lang/Object;ILjava/lang/Object;II)V"),
+            MethodDef::static_native("identityHashCode", "(Ljava/lang/Object;)I"),
+            MethodDef::static_native("getProperty", "(Ljava/lang/String;)Ljava/lang/String;"),
+            MethodDef::static_native("lineSeparator", "()Ljava/lang/String;"),
+            MethodDef::static_native("gc", "()V"),
+            MethodDef::static_native("exit", "(I)V"),
+            MethodDef::static_native("load", "(Ljava/lang/String;)V"),
+            MethodDef::static_native("loadLibrary", "(Ljava/lang/String;)V"),
+        ],
+    },
+    ClassDef {
+        name: "java/io/PrintStream",
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC,
+        fields: &[FieldDef::new("fd", "I", ACC_PRIVATE | ACC_FINAL)],
+        methods: &[
+            MethodDef::native("print", "(Ljava/lang/String;)V"),
+            MethodDef::native("print", "(Z)V"),
+            MethodDef::native("print", "(C)V"),
+            MethodDef::native("print", "(I)V"),
+            MethodDef::native("print", "(J)V"),
+            MethodDef::native("print", "(F)V"),
+            MethodDef::native("print", "(D)V"),
+            MethodDef::native("print", "([C)V"),
+            MethodDef::code("print", "(Ljava/lang/Object;)V", ACC_PUBLIC, 2, 2, &PRINT_STRING_VALUE_PRINT),
+            MethodDef::native("println", "()V"),
+            MethodDef::native("println", "(Ljava/lang/String;)V"),
+            MethodDef::native("println", "(Z)V"),
+            MethodDef::native("println", "(C)V"),
+            MethodDef::native("println", "(I)V"),
+            MethodDef::native("println", "(J)V"),
+            MethodDef::native("println", "(F)V"),
+            MethodDef::native("println", "(D)V"),
+            MethodDef::native("println", "([C)V"),
+            MethodDef::code("println", "(Ljava/lang/Object;)V", ACC_PUBLIC, 2, 2, &PRINT_STRING_VALUE_PRINTLN),
+            MethodDef::native("write", "(I)V"),
+            MethodDef::native("flush", "()V"),
+        ],
+    },
+    ClassDef {
+        name: "java/lang/Math",
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::constant("PI", "D", Constant::Double(std::f64::consts::PI)),
+            FieldDef::constant("E", "D", Constant::Double(std::f64::consts::E)),
+        ],
+        methods: &[
+            MethodDef::static_native("abs", "(I)I"),
+            MethodDef::static_native("abs", "(J)J"),
+            MethodDef::static_native("abs", "(F)F"),
+            MethodDef::static_native("abs", "(D)D"),
+            MethodDef::static_native("max", "(II)I"),
+            MethodDef::static_native("max", "(JJ)J"),
+            MethodDef::static_native("max", "(FF)F"),
+            MethodDef::static_native("max", "(DD)D"),
+            MethodDef::static_native("min", "(II)I"),
+            MethodDef::static_native("min", "(JJ)J"),
+            MethodDef::static_native("min", "(FF)F"),
+            MethodDef::static_native("min", "(DD)D"),
+            MethodDef::static_native("floorDiv", "(II)I"),
+            MethodDef::static_native("floorMod", "(II)I"),
+            MethodDef::static_native("round", "(F)I"),
+            MethodDef::static_native("round", "(D)J"),
+            MethodDef::static_native("floor", "(D)D"),
+            MethodDef::static_native("ceil", "(D)D"),
+            MethodDef::static_native("rint", "(D)D"),
+            MethodDef::static_native("signum", "(D)D"),
+            MethodDef::static_native("sqrt", "(D)D"),
+            MethodDef::static_native("cbrt", "(D)D"),
+            MethodDef::static_native("pow", "(DD)D"),
+            MethodDef::static_native("hypot", "(DD)D"),
+            MethodDef::static_native("exp", "(D)D"),
+            MethodDef::static_native("log", "(D)D"),
+            MethodDef::static_native("log10", "(D)D"),
+            MethodDef::static_native("sin", "(D)D"),
+            MethodDef::static_native("cos", "(D)D"),
+            MethodDef::static_native("tan", "(D)D"),
+            MethodDef::static_native("asin", "(D)D"),
+            MethodDef::static_native("acos", "(D)D"),
+            MethodDef::static_native("atan", "(D)D"),
+            MethodDef::static_native("atan2", "(DD)D"),
+            MethodDef::static_native("toRadians", "(D)D"),
+            MethodDef::static_native("toDegrees", "(D)D"),
+            MethodDef::static_native("random", "()D"),
+        ],
+    },
+    ClassDef {
+        name: "java/lang/Number",
+        super_class: Some(OBJECT),
+        interfaces: &["java/io/Serializable"],
+        access_flags: ACC_PUBLIC | ACC_ABSTRACT,
+        fields: &[],
+        methods: &[
+            MethodDef::code(
+                "<init>",
+                "()V",
+                ACC_PUBLIC,
+                1,
+                1,
+                &[Op::ALoad(0), Op::InvokeSpecial(OBJECT, "<init>", "()V"), Op::Return],
+            ),
+            MethodDef::abstract_method("intValue", "()I"),
+            MethodDef::abstract_method("longValue", "()J"),
+            MethodDef::abstract_method("floatValue", "()F"),
+            MethodDef::abstract_method("doubleValue", "()D"),
+        ],
+    },
+    ClassDef {
+        name: "java/lang/Integer",
+        super_class: Some("java/lang/Number"),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("value", "I", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::new("cache", "[Ljava/lang/Integer;", ACC_PRIVATE | ACC_STATIC),
+            FieldDef::constant("MIN_VALUE", "I", Constant::Int(i32::MIN)),
+            FieldDef::constant("MAX_VALUE", "I", Constant::Int(i32::MAX)),
+            TYPE_FIELD,
+        ],
+        methods: &[
+            MethodDef::code("<clinit>", "()V", ACC_STATIC, 1, 0, &set_type("java/lang/Integer", "int")),
+            MethodDef::native("<init>", "(I)V"),
+            MethodDef::static_native("valueOf", "(I)Ljava/lang/Integer;"),
+            MethodDef::static_native("parseInt", "(Ljava/lang/String;)I"),
+            MethodDef::static_native("toString", "(I)Ljava/lang/String;"),
+            MethodDef::static_native("toHexString", "(I)Ljava/lang/String;"),
+            MethodDef::static_native("toBinaryString", "(I)Ljava/lang/String;"),
+            MethodDef::static_native("compare", "(II)I"),
+            NUMBER_VALUES[0],
+            NUMBER_VALUES[1],
+            NUMBER_VALUES[2],
+            NUMBER_VALUES[3],
+            NUMBER_VALUES[4],
+            NUMBER_VALUES[5],
+            MethodDef::native("compareTo", "(Ljava/lang/Integer;)I"),
+            BOX_METHODS[0],
+            BOX_METHODS[1],
+            BOX_METHODS[2],
+        ],
+    },
+    ClassDef {
+        name: "java/lang/Long",
+        super_class: Some("java/lang/Number"),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("value", "J", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::new("cache", "[Ljava/lang/Long;", ACC_PRIVATE | ACC_STATIC),
+            FieldDef::constant("MIN_VALUE", "J", Constant::Long(i64::MIN)),
+            FieldDef::constant("MAX_VALUE", "J", Constant::Long(i64::MAX)),
+            TYPE_FIELD,
+        ],
+        methods: &[
+            MethodDef::code("<clinit>", "()V", ACC_STATIC, 1, 0, &set_type("java/lang/Long", "long")),
+            MethodDef::native("<init>", "(J)V"),
+            MethodDef::static_native("valueOf", "(J)Ljava/lang/Long;"),
+            MethodDef::static_native("parseLong", "(Ljava/lang/String;)J"),
+            MethodDef::static_native("toString", "(J)Ljava/lang/String;"),
+            MethodDef::static_native("toHexString", "(J)Ljava/lang/String;"),
+            MethodDef::static_native("toBinaryString", "(J)Ljava/lang/String;"),
+            MethodDef::static_native("compare", "(JJ)I"),
+            NUMBER_VALUES[0],
+            NUMBER_VALUES[1],
+            NUMBER_VALUES[2],
+            NUMBER_VALUES[3],
+            NUMBER_VALUES[4],
+            NUMBER_VALUES[5],
+            MethodDef::native("compareTo", "(Ljava/lang/Long;)I"),
+            BOX_METHODS[0],
+            BOX_METHODS[1],
+            BOX_METHODS[2],
+        ],
+    },
+    ClassDef {
+        name: "java/lang/Short",
+        super_class: Some("java/lang/Number"),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("value", "S", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::new("cache", "[Ljava/lang/Short;", ACC_PRIVATE | ACC_STATIC),
+            FieldDef::constant("MIN_VALUE", "S", Constant::Int(i16::MIN as i32)),
+            FieldDef::constant("MAX_VALUE", "S", Constant::Int(i16::MAX as i32)),
+            TYPE_FIELD,
+        ],
+        methods: &[
+            MethodDef::code("<clinit>", "()V", ACC_STATIC, 1, 0, &set_type("java/lang/Short", "short")),
+            MethodDef::native("<init>", "(S)V"),
+            MethodDef::static_native("valueOf", "(S)Ljava/lang/Short;"),
+            MethodDef::static_native("parseShort", "(Ljava/lang/String;)S"),
+            MethodDef::static_native("toString", "(S)Ljava/lang/String;"),
+            MethodDef::static_native("compare", "(SS)I"),
+            NUMBER_VALUES[0],
+            NUMBER_VALUES[1],
+            NUMBER_VALUES[2],
+            NUMBER_VALUES[3],
+            NUMBER_VALUES[4],
+            NUMBER_VALUES[5],
+            MethodDef::native("compareTo", "(Ljava/lang/Short;)I"),
+            BOX_METHODS[0],
+            BOX_METHODS[1],
+            BOX_METHODS[2],
+        ],
+    },
+    ClassDef {
+        name: "java/lang/Byte",
+        super_class: Some("java/lang/Number"),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("value", "B", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::new("cache", "[Ljava/lang/Byte;", ACC_PRIVATE | ACC_STATIC),
+            FieldDef::constant("MIN_VALUE", "B", Constant::Int(i8::MIN as i32)),
+            FieldDef::constant("MAX_VALUE", "B", Constant::Int(i8::MAX as i32)),
+            TYPE_FIELD,
+        ],
+        methods: &[
+            MethodDef::code("<clinit>", "()V", ACC_STATIC, 1, 0, &set_type("java/lang/Byte", "byte")),
+            MethodDef::native("<init>", "(B)V"),
+            MethodDef::static_native("valueOf", "(B)Ljava/lang/Byte;"),
+            MethodDef::static_native("parseByte", "(Ljava/lang/String;)B"),
+            MethodDef::static_native("toString", "(B)Ljava/lang/String;"),
+            MethodDef::static_native("compare", "(BB)I"),
+            NUMBER_VALUES[0],
+            NUMBER_VALUES[1],
+            NUMBER_VALUES[2],
+            NUMBER_VALUES[3],
+            NUMBER_VALUES[4],
+            NUMBER_VALUES[5],
+            MethodDef::native("compareTo", "(Ljava/lang/Byte;)I"),
+            BOX_METHODS[0],
+            BOX_METHODS[1],
+            BOX_METHODS[2],
+        ],
+    },
+    ClassDef {
+        name: "java/lang/Float",
+        super_class: Some("java/lang/Number"),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("value", "F", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::constant("MIN_VALUE", "F", Constant::Float(f32::from_bits(1))),
+            FieldDef::constant("MAX_VALUE", "F", Constant::Float(f32::MAX)),
+            FieldDef::constant("POSITIVE_INFINITY", "F", Constant::Float(f32::INFINITY)),
+            FieldDef::constant("NEGATIVE_INFINITY", "F", Constant::Float(f32::NEG_INFINITY)),
+            FieldDef::constant("NaN", "F", Constant::Float(f32::NAN)),
+            TYPE_FIELD,
+        ],
+        methods: &[
+            MethodDef::code("<clinit>", "()V", ACC_STATIC, 1, 0, &set_type("java/lang/Float", "float")),
+            MethodDef::native("<init>", "(F)V"),
+            MethodDef::static_native("valueOf", "(F)Ljava/lang/Float;"),
+            MethodDef::static_native("parseFloat", "(Ljava/lang/String;)F"),
+            MethodDef::static_native("toString", "(F)Ljava/lang/String;"),
+            MethodDef::static_native("compare", "(FF)I"),
+            MethodDef::static_native("isNaN", "(F)Z"),
+            MethodDef::static_native("isInfinite", "(F)Z"),
+            MethodDef::static_native("floatToRawIntBits", "(F)I"),
+            MethodDef::static_native("floatToIntBits", "(F)I"),
+            MethodDef::static_native("intBitsToFloat", "(I)F"),
+            NUMBER_VALUES[0],
+            NUMBER_VALUES[1],
+            NUMBER_VALUES[2],
+            NUMBER_VALUES[3],
+            NUMBER_VALUES[4],
+            NUMBER_VALUES[5],
+            MethodDef::native("compareTo", "(Ljava/lang/Float;)I"),
+            BOX_METHODS[0],
+            BOX_METHODS[1],
+            BOX_METHODS[2],
+        ],
+    },
+    ClassDef {
+        name: "java/lang/Double",
+        super_class: Some("java/lang/Number"),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("value", "D", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::constant("MIN_VALUE", "D", Constant::Double(f64::from_bits(1))),
+            FieldDef::constant("MAX_VALUE", "D", Constant::Double(f64::MAX)),
+            FieldDef::constant("POSITIVE_INFINITY", "D", Constant::Double(f64::INFINITY)),
+            FieldDef::constant("NEGATIVE_INFINITY", "D", Constant::Double(f64::NEG_INFINITY)),
+            FieldDef::constant("NaN", "D", Constant::Double(f64::NAN)),
+            TYPE_FIELD,
+        ],
+        methods: &[
+            MethodDef::code("<clinit>", "()V", ACC_STATIC, 1, 0, &set_type("java/lang/Double", "double")),
+            MethodDef::native("<init>", "(D)V"),
+            MethodDef::static_native("valueOf", "(D)Ljava/lang/Double;"),
+            MethodDef::static_native("parseDouble", "(Ljava/lang/String;)D"),
+            MethodDef::static_native("toString", "(D)Ljava/lang/String;"),
+            MethodDef::static_native("compare", "(DD)I"),
+            MethodDef::static_native("isNaN", "(D)Z"),
+            MethodDef::static_native("isInfinite", "(D)Z"),
+            MethodDef::static_native("doubleToRawLongBits", "(D)J"),
+            MethodDef::static_native("doubleToLongBits", "(D)J"),
+            MethodDef::static_native("longBitsToDouble", "(J)D"),
+            NUMBER_VALUES[0],
+            NUMBER_VALUES[1],
+            NUMBER_VALUES[2],
+            NUMBER_VALUES[3],
+            NUMBER_VALUES[4],
+            NUMBER_VALUES[5],
+            MethodDef::native("compareTo", "(Ljava/lang/Double;)I"),
+            BOX_METHODS[0],
+            BOX_METHODS[1],
+            BOX_METHODS[2],
+        ],
+    },
+    ClassDef {
+        name: "java/lang/Void",
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[TYPE_FIELD],
+        methods: &[MethodDef::code("<clinit>", "()V", ACC_STATIC, 1, 0, &set_type("java/lang/Void", "void"))],
+    },
+    ClassDef {
+        name: "java/lang/Boolean",
+        super_class: Some(OBJECT),
+        interfaces: &["java/io/Serializable"],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("value", "Z", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::new("TRUE", "Ljava/lang/Boolean;", ACC_PUBLIC | ACC_STATIC | ACC_FINAL),
+            FieldDef::new("FALSE", "Ljava/lang/Boolean;", ACC_PUBLIC | ACC_STATIC | ACC_FINAL),
+            TYPE_FIELD,
+        ],
+        methods: &[
+            MethodDef::code("<clinit>", "()V", ACC_STATIC, 3, 0, &BOOLEAN_CLINIT),
+            MethodDef::native("<init>", "(Z)V"),
+            MethodDef::static_native("valueOf", "(Z)Ljava/lang/Boolean;"),
+            MethodDef::static_native("parseBoolean", "(Ljava/lang/String;)Z"),
+            MethodDef::static_native("toString", "(Z)Ljava/lang/String;"),
+            MethodDef::static_native("compare", "(ZZ)I"),
+            MethodDef::native("booleanValue", "()Z"),
+            MethodDef::native("compareTo", "(Ljava/lang/Boolean;)I"),
+            BOX_METHODS[0],
+            BOX_METHODS[1],
+            BOX_METHODS[2],
+        ],
+    },
+    ClassDef {
+        name: "java/lang/Character",
+        super_class: Some(OBJECT),
+        interfaces: &["java/io/Serializable"],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("value", "C", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::new("cache", "[Ljava/lang/Character;", ACC_PRIVATE | ACC_STATIC),
+            FieldDef::constant("MIN_VALUE", "C", Constant::Int(0)),
+            FieldDef::constant("MAX_VALUE", "C", Constant::Int(u16::MAX as i32)),
+            TYPE_FIELD,
+        ],
+        methods: &[
+            MethodDef::code("<clinit>", "()V", ACC_STATIC, 1, 0, &set_type("java/lang/Character", "char")),
+            MethodDef::native("<init>", "(C)V"),
+            MethodDef::static_native("valueOf", "(C)Ljava/lang/Character;"),
+            MethodDef::static_native("toString", "(C)Ljava/lang/String;"),
+            MethodDef::static_native("compare", "(CC)I"),
+            MethodDef::static_native("isDigit", "(C)Z"),
+            MethodDef::static_native("isLetter", "(C)Z"),
+            MethodDef::static_native("isLetterOrDigit", "(C)Z"),
+            MethodDef::static_native("isWhitespace", "(C)Z"),
+            MethodDef::static_native("isUpperCase", "(C)Z"),
+            MethodDef::static_native("isLowerCase", "(C)Z"),
+            MethodDef::static_native("toUpperCase", "(C)C"),
+            MethodDef::static_native("toLowerCase", "(C)C"),
+            MethodDef::native("charValue", "()C"),
+            MethodDef::native("compareTo", "(Ljava/lang/Character;)I"),
+            BOX_METHODS[0],
+            BOX_METHODS[1],
+            BOX_METHODS[2],
+        ],
+    },
+    ClassDef {
+        name: METHOD_HANDLE,
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_ABSTRACT,
+        fields: &[FieldDef::new("type", "Ljava/lang/invoke/MethodType;", ACC_PRIVATE | ACC_FINAL)],
+        methods: &[
+            MethodDef::code(
+                "type",
+                "()Ljava/lang/invoke/MethodType;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &[Op::ALoad(0), Op::GetField(METHOD_HANDLE, "type", "Ljava/lang/invoke/MethodType;"), Op::AReturn],
+            ),
+            // signature polymorphic, which the interpreter calls with the descriptor of the call site
+            MethodDef::with_flags("invokeExact", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("invoke", POLYMORPHIC_OBJECT, POLYMORPHIC),
+        ],
+    },
+    ClassDef {
+        name: METHOD_TYPE,
+        super_class: Some(OBJECT),
+        interfaces: &["java/io/Serializable"],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("rtype", "Ljava/lang/Class;", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::new("ptypes", "[Ljava/lang/Class;", ACC_PRIVATE | ACC_FINAL),
+        ],
+        methods: &[
+            MethodDef::static_native("methodType", "(Ljava/lang/Class;)Ljava/lang/invoke/MethodType;"),
+            MethodDef::static_native("methodType", "(Ljava/lang/Class;Ljava/lang/Class;)Ljava/lang/invoke/MethodType;"),
+            MethodDef::static_native(
+                "methodType",
+                "(Ljava/lang/Class;[Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+            ),
+            MethodDef::with_flags(
+                "methodType",
+                "(Ljava/lang/Class;Ljava/lang/Class;[Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+                ACC_PUBLIC | ACC_STATIC | ACC_VARARGS,
+            ),
+            MethodDef::code(
+                "returnType",
+                "()Ljava/lang/Class;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &[Op::ALoad(0), Op::GetField(METHOD_TYPE, "rtype", "Ljava/lang/Class;"), Op::AReturn],
+            ),
+        ],
+    },
+    ClassDef {
+        name: "java/lang/invoke/MethodHandles",
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[],
+        methods: &[
+            MethodDef::static_native("lookup", "()Ljava/lang/invoke/MethodHandles$Lookup;"),
+            MethodDef::static_native("arrayElementVarHandle", "(Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;"),
+        ],
+    },
+    ClassDef {
+        name: LOOKUP,
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[
+            FieldDef::new("lookupClass", "Ljava/lang/Class;", ACC_PRIVATE | ACC_FINAL),
+            FieldDef::new("allowedModes", "I", ACC_PRIVATE | ACC_FINAL),
+        ],
+        methods: &[
+            MethodDef::code(
+                "lookupClass",
+                "()Ljava/lang/Class;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &[Op::ALoad(0), Op::GetField(LOOKUP, "lookupClass", "Ljava/lang/Class;"), Op::AReturn],
+            ),
+            MethodDef::native(
+                "findStatic",
+                "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/MethodHandle;",
+            ),
+            MethodDef::native(
+                "findVirtual",
+                "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/MethodHandle;",
+            ),
+            MethodDef::native(
+                "findSpecial",
+                "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/Class;)\
+                 Ljava/lang/invoke/MethodHandle;",
+            ),
+            MethodDef::native(
+                "findConstructor",
+                "(Ljava/lang/Class;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/MethodHandle;",
+            ),
+            MethodDef::native(
+                "findGetter",
+                "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+            ),
+            MethodDef::native(
+                "findSetter",
+                "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+            ),
+            MethodDef::native(
+                "findStaticGetter",
+                "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+            ),
+            MethodDef::native(
+                "findStaticSetter",
+                "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+            ),
+            MethodDef::native(
+                "findVarHandle",
+                "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;",
+            ),
+            MethodDef::native(
+                "findStaticVarHandle",
+                "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;",
+            ),
+        ],
+    },
+    ClassDef {
+        name: "java/lang/invoke/VarHandle",
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_ABSTRACT,
+        fields: &[],
+        methods: &[
+            MethodDef::with_flags("get", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("set", POLYMORPHIC_VOID, POLYMORPHIC),
+            MethodDef::with_flags("getVolatile", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("setVolatile", POLYMORPHIC_VOID, POLYMORPHIC),
+            MethodDef::with_flags("getOpaque", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("setOpaque", POLYMORPHIC_VOID, POLYMORPHIC),
+            MethodDef::with_flags("getAcquire", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("setRelease", POLYMORPHIC_VOID, POLYMORPHIC),
+            MethodDef::with_flags("compareAndSet", POLYMORPHIC_BOOLEAN, POLYMORPHIC),
+            MethodDef::with_flags("compareAndExchange", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("compareAndExchangeAcquire", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("compareAndExchangeRelease", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("weakCompareAndSetPlain", POLYMORPHIC_BOOLEAN, POLYMORPHIC),
+            MethodDef::with_flags("weakCompareAndSet", POLYMORPHIC_BOOLEAN, POLYMORPHIC),
+            MethodDef::with_flags("weakCompareAndSetAcquire", POLYMORPHIC_BOOLEAN, POLYMORPHIC),
+            MethodDef::with_flags("weakCompareAndSetRelease", POLYMORPHIC_BOOLEAN, POLYMORPHIC),
+            MethodDef::with_flags("getAndSet", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("getAndSetAcquire", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("getAndSetRelease", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("getAndAdd", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("getAndAddAcquire", POLYMORPHIC_OBJECT, POLYMORPHIC),
+            MethodDef::with_flags("getAndAddRelease", POLYMORPHIC_OBJECT, POLYMORPHIC),
+        ],
+    },
+    ClassDef {
+        name: CALL_SITE,
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_ABSTRACT,
+        fields: &[FieldDef::new("target", "Ljava/lang/invoke/MethodHandle;", 0)],
+        methods: &[
+            MethodDef::with_flags("<init>", "()V", 0),
+            MethodDef::code(
+                "getTarget",
+                "()Ljava/lang/invoke/MethodHandle;",
+                ACC_PUBLIC,
+                1,
+                1,
+                &[Op::ALoad(0), Op::GetField(CALL_SITE, "target", "Ljava/lang/invoke/MethodHandle;"), Op::AReturn],
+            ),
+        ],
+    },
+    ClassDef {
+        name: "java/lang/invoke/ConstantCallSite",
+        super_class: Some(CALL_SITE),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC,
+        fields: &[],
+        methods: &[MethodDef::code(
+            "<init>",
+            "(Ljava/lang/invoke/MethodHandle;)V",
+            ACC_PUBLIC,
+            2,
+            2,
+            &[
+                Op::ALoad(0),
+                Op::InvokeSpecial(CALL_SITE, "<init>", "()V"),
+                Op::ALoad(0),
+                Op::ALoad(1),
+                Op::PutField(CALL_SITE, "target", "Ljava/lang/invoke/MethodHandle;"),
+                Op::Return,
+            ],
+        )],
+    },
+    ClassDef {
+        name: "java/util/Objects",
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_PUBLIC | ACC_FINAL,
+        fields: &[],
+        methods: &[
+            MethodDef::static_native("requireNonNull", "(Ljava/lang/Object;)Ljava/lang/Object;"),
+            MethodDef::static_native("requireNonNull", "(Ljava/lang/Object;Ljava/lang/String;)Ljava/lang/Object;"),
+        ],
+    },
+    // the functional interfaces lambdas are most often made for
+    ClassDef::interface("java/lang/Runnable", &[MethodDef::abstract_method("run", "()V")]),
+    ClassDef::interface(
+        "java/util/Comparator",
+        &[MethodDef::abstract_method("compare", "(Ljava/lang/Object;Ljava/lang/Object;)I")],
+    ),
+    ClassDef::interface(FUNCTION, &[MethodDef::abstract_method("apply", "(Ljava/lang/Object;)Ljava/lang/Object;")]),
+    ClassDef::interface(
+        BI_FUNCTION,
+        &[MethodDef::abstract_method("apply", "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;")],
+    ),
+    ClassDef { interfaces: &[FUNCTION], ..ClassDef::interface("java/util/function/UnaryOperator", &[]) },
+    ClassDef { interfaces: &[BI_FUNCTION], ..ClassDef::interface("java/util/function/BinaryOperator", &[]) },
+    ClassDef::interface("java/util/function/Supplier", &[MethodDef::abstract_method("get", "()Ljava/lang/Object;")]),
+    ClassDef::interface(
+        "java/util/function/Consumer",
+        &[MethodDef::abstract_method("accept", "(Ljava/lang/Object;)V")],
+    ),
+    ClassDef::interface(
+        "java/util/function/BiConsumer",
+        &[MethodDef::abstract_method("accept", "(Ljava/lang/Object;Ljava/lang/Object;)V")],
+    ),
+    ClassDef::interface("java/util/function/Predicate", &[MethodDef::abstract_method("test", "(Ljava/lang/Object;)Z")]),
+    ClassDef::interface(
+        "java/util/function/BiPredicate",
+        &[MethodDef::abstract_method("test", "(Ljava/lang/Object;Ljava/lang/Object;)Z")],
+    ),
+    ClassDef::interface("java/util/function/IntBinaryOperator", &[MethodDef::abstract_method("applyAsInt", "(II)I")]),
+    ClassDef::interface("java/util/function/IntUnaryOperator", &[MethodDef::abstract_method("applyAsInt", "(I)I")]),
+    ClassDef::interface("java/util/function/IntPredicate", &[MethodDef::abstract_method("test", "(I)Z")]),
+    ClassDef::interface(
+        "java/util/function/IntFunction",
+        &[MethodDef::abstract_method("apply", "(I)Ljava/lang/Object;")],
+    ),
+    ClassDef::interface(
+        "java/util/function/ToIntFunction",
+        &[MethodDef::abstract_method("applyAsInt", "(Ljava/lang/Object;)I")],
+    ),
+    ClassDef::interface("java/util/function/IntSupplier", &[MethodDef::abstract_method("getAsInt", "()I")]),
+];
+
+// the flags and descriptors of the signature polymorphic methods of MethodHandle and VarHandle
+const POLYMORPHIC: u16 = ACC_PUBLIC | ACC_FINAL | ACC_VARARGS;
+const POLYMORPHIC_OBJECT: &str = "([Ljava/lang/Object;)Ljava/lang/Object;";
+const POLYMORPHIC_VOID: &str = "([Ljava/lang/Object;)V";
+const POLYMORPHIC_BOOLEAN: &str = "([Ljava/lang/Object;)Z";
+
+const FOR_NAME0: &str = "(Ljava/lang/String;ZLjava/lang/ClassLoader;Ljava/lang/Class;)Ljava/lang/Class;";
+const OBJECT_INIT: Op = Op::InvokeSpecial(OBJECT, "<init>", "()V");
+const ACCESSIBLE_OBJECT_INIT: Op = Op::InvokeSpecial(ACCESSIBLE_OBJECT, "<init>", "()V");
+
+// Class.getDeclaredFields() and the others, the declared members whether public or not
+const fn all_declared(native: &'static str, descriptor: &'static str) -> [Op<'static>; 4] {
+    [Op::ALoad(0), Op::LdcConstant(Constant::Int(0)), Op::InvokeVirtual(CLASS, native, descriptor), Op::AReturn]
+}
+
+// a getter of field `name` of a reflective object
+const fn get_member(class: &'static str, name: &'static str, descriptor: &'static str) -> [Op<'static>; 3] {
+    [Op::ALoad(0), Op::GetField(class, name, descriptor), Op::AReturn]
+}
+
+const fn get_modifiers(class: &'static str) -> [Op<'static>; 3] {
+    [Op::ALoad(0), Op::GetField(class, "modifiers", "I"), Op::IReturn]
+}
+
+// a getter of a Class[] field, which returns a copy
+const fn get_classes(class: &'static str, name: &'static str) -> [Op<'static>; 5] {
+    [
+        Op::ALoad(0),
+        Op::GetField(class, name, "[Ljava/lang/Class;"),
+        Op::InvokeVirtual(OBJECT, "clone", "()Ljava/lang/Object;"),
+        Op::CheckCast("[Ljava/lang/Class;"),
+        Op::AReturn,
+    ]
+}
+
+// Integer.TYPE and the others, the Class objects of the primitive types, which <clinit> sets
+const TYPE_FIELD: FieldDef = FieldDef::new("TYPE", "Ljava/lang/Class;", ACC_PUBLIC | ACC_STATIC | ACC_FINAL);
+
+const fn set_type(class: &'static str, primitive: &'static str) -> [Op<'static>; 4] {
+    [
+        Op::Ldc(primitive),
+        Op::InvokeStatic("java/lang/Class", "getPrimitiveClass", "(Ljava/lang/String;)Ljava/lang/Class;"),
+        Op::PutStatic(class, "TYPE", "Ljava/lang/Class;"),
+        Op::Return,
+    ]
+}
+
+// sets Boolean.TYPE and makes the TRUE and FALSE that valueOf gives
+const BOOLEAN_CLINIT: [Op; 14] = [
+    Op::Ldc("boolean"),
+    Op::InvokeStatic("java/lang/Class", "getPrimitiveClass", "(Ljava/lang/String;)Ljava/lang/Class;"),
+    Op::PutStatic("java/lang/Boolean", "TYPE", "Ljava/lang/Class;"),
+    Op::New("java/lang/Boolean"),
+    Op::Dup,
+    Op::LdcConstant(Constant::Int(1)),
+    Op::InvokeSpecial("java/lang/Boolean", "<init>", "(Z)V"),
+    Op::PutStatic("java/lang/Boolean", "TRUE", "Ljava/lang/Boolean;"),
+    Op::New("java/lang/Boolean"),
+    Op::Dup,
+    Op::LdcConstant(Constant::Int(0)),
+    Op::InvokeSpecial("java/lang/Boolean", "<init>", "(Z)V"),
+    Op::PutStatic("java/lang/Boolean", "FALSE", "Ljava/lang/Boolean;"),
+    Op::Return,
+];
+
+// what Class and AccessibleObject have of AnnotatedElement
+const ANNOTATED_ELEMENT_METHODS: [MethodDef; 4] = [
+    MethodDef::native("getAnnotation", "(Ljava/lang/Class;)Ljava/lang/annotation/Annotation;"),
+    MethodDef::native("isAnnotationPresent", "(Ljava/lang/Class;)Z"),
+    MethodDef::native("getAnnotations", "()[Ljava/lang/annotation/Annotation;"),
+    MethodDef::native("getDeclaredAnnotations", "()[Ljava/lang/annotation/Annotation;"),
+];
+
+// what every numeric box has of Number
+const NUMBER_VALUES: [MethodDef; 6] = [
+    MethodDef::native("byteValue", "()B"),
+    MethodDef::native("shortValue", "()S"),
+    MethodDef::native("intValue", "()I"),
+    MethodDef::native("longValue", "()J"),
+    MethodDef::native("floatValue", "()F"),
+    MethodDef::native("doubleValue", "()D"),
+];
+
+const BOX_METHODS: [MethodDef; 3] = [
+    MethodDef::native("toString", "()Ljava/lang/String;"),
+    MethodDef::native("hashCode", "()I"),
+    MethodDef::native("equals", "(Ljava/lang/Object;)Z"),
+];
+
+const THROWABLE_METHODS: &[MethodDef] = &[
+    MethodDef::native("<init>", "()V"),
+    MethodDef::native("<init>", "(Ljava/lang/String;)V"),
+    MethodDef::native("<init>", "(Ljava/lang/String;Ljava/lang/Throwable;)V"),
+    MethodDef::native("<init>", "(Ljava/lang/Throwable;)V"),
+];
+
+const THROWABLE_CLASS: ClassDef = ClassDef {
+    name: THROWABLE,
+    super_class: Some(OBJECT),
+    interfaces: &["java/io/Serializable"],
+    access_flags: ACC_PUBLIC,
+    fields: &[
+        FieldDef::new("detailMessage", "Ljava/lang/String;", ACC_PRIVATE),
+        FieldDef::new("cause", "Ljava/lang/Throwable;", ACC_PRIVATE),
+    ],
+    methods: &[
+        THROWABLE_METHODS[0],
+        THROWABLE_METHODS[1],
+        THROWABLE_METHODS[2],
+        THROWABLE_METHODS[3],
+        MethodDef::native("getMessage", "()Ljava/lang/String;"),
+        MethodDef::code(
+            "getLocalizedMessage",
+            "()Ljava/lang/String;",
+            ACC_PUBLIC,
+            1,
+            1,
+            &[Op::ALoad(0), Op::InvokeVirtual(THROWABLE, "getMessage", "()Ljava/lang/String;"), Op::AReturn],
+        ),
+        MethodDef::native("getCause", "()Ljava/lang/Throwable;"),
+        MethodDef::native("initCause", "(Ljava/lang/Throwable;)Ljava/lang/Throwable;"),
+        MethodDef::native("fillInStackTrace", "()Ljava/lang/Throwable;"),
+        MethodDef::native("printStackTrace", "()V"),
+        // getClass().getName(), followed by ": " and getLocalizedMessage() unless that is null
+        MethodDef::code(
+            "toString",
+            "()Ljava/lang/String;",
+            ACC_PUBLIC,
+            2,
+            3,
+            &[
+                Op::ALoad(0),
+                GET_CLASS_NAME[0],
+                GET_CLASS_NAME[1],
+                Op::AStore(1),
+                Op::ALoad(0),
+                Op::InvokeVirtual(THROWABLE, "getLocalizedMessage", "()Ljava/lang/String;"),
+                Op::AStore(2),
+                Op::ALoad(2),
+                Op::IfNonNull(11),
+                Op::ALoad(1),
+                Op::AReturn,
+                Op::ALoad(1),
+                Op::Ldc(": "),
+                CONCAT,
+                Op::ALoad(2),
+                CONCAT,
+                Op::AReturn,
+            ],
+        ),
+    ],
+};
+
+const APPEND_STRING_VALUE: [Op; 5] = [
+    Op::ALoad(0),
+    Op::ALoad(1),
+    VALUE_OF_OBJECT,
+    Op::InvokeVirtual("java/lang/StringBuilder", "append", "(Ljava/lang/String;)Ljava/lang/StringBuilder;"),
+    Op::AReturn,
+];
+
+const PRINT_STRING_VALUE_PRINT: [Op; 5] = [
+    Op::ALoad(0),
+    Op::ALoad(1),
+    VALUE_OF_OBJECT,
+    Op::InvokeVirtual("java/io/PrintStream", "print", "(Ljava/lang/String;)V"),
+    Op::Return,
+];
+
+const PRINT_STRING_VALUE_PRINTLN: [Op; 5] = [
+    Op::ALoad(0),
+    Op::ALoad(1),
+    VALUE_OF_OBJECT,
+    Op::InvokeVirtual("java/io/PrintStream", "println", "(Ljava/lang/String;)V"),
+    Op::Return,
+];
+
+// the names of every class in the library
+#[cfg(test)]
+pub(crate) fn class_names() -> impl Iterator<Item = &'static str> {
+    CLASSES.iter().map(|class| class.name).chain(THROWABLES.iter().map(|(name, _)| *name))
+}
+
+fn find(name: &str) -> Option<ClassDef<'static>> {
+    if let Some(class) = CLASSES.iter().find(|class| class.name == name) {
+        return Some(*class);
+    }
+    match THROWABLES.iter().find(|(throwable, _)| *throwable == name)? {
+        (THROWABLE, _) => Some(THROWABLE_CLASS),
+        (name, super_class) => Some(ClassDef {
+            name,
+            super_class: Some(super_class),
+            interfaces: &[],
+            access_flags: ACC_PUBLIC,
+            fields: &[],
+            methods: THROWABLE_METHODS,
+        }),
+    }
+}
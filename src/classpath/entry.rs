@@ -1,17 +1,17 @@
 use std::fs::File;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 use zip::ZipArchive;
 use std::result::Result as StdResult;
+use crate::log::{log, Logger};
 
 pub use crate::error::Error;
 
 pub type Result<T> = StdResult<T, Error>;
 
 pub trait Entry {
-    fn read_class(&self, class_name: &str) -> Result<Vec<u8>>;
-    fn print_path(&self);
+    fn read_class(&self, class_name: &str, logger: &Logger) -> Result<Vec<u8>>;
 }
 
 pub struct DirEntry {
@@ -22,75 +22,57 @@ pub struct ZipEntry {
     path: PathBuf,
 }
 
+#[derive(Default)]
 pub struct WildcardEntry {
     entry_list: Vec<Box<dyn Entry>>,
 }
 
 impl Entry for DirEntry {
-    fn read_class(&self, class_name: &str) -> Result<Vec<u8>> {
-        for entry in WalkDir::new(&self.path)
-            .into_iter()
-            .filter_map(StdResult::ok)
-            .filter(|e| !e.file_type().is_dir()) {
-            println!("name is {}", entry.path().display());
-            if let Some(file_name) = entry.path().to_str() {
-                if file_name.contains(class_name) {
-                    let mut data = Vec::new();
-                    File::open(file_name)?.read_to_end(&mut data)?;
-                    return Ok(data);
-                }
-            }
+    // the class is in the file its package and name make a path to, and nowhere else under the directory
+    fn read_class(&self, class_name: &str, logger: &Logger) -> Result<Vec<u8>> {
+        let path = self.path.join(class_name);
+        log!(logger, Classpath, Trace, "looking at {}", path.display());
+        if !path.is_file() {
+            return Err(Error::ClassNotFound(String::from(class_name)));
         }
-        Err(Error::ClassNotFound(String::from(class_name)))
-    }
-
-    fn print_path(&self) {
-        println!("path is {}", self.path.display())
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        Ok(data)
     }
 }
 
 impl Entry for ZipEntry {
-    fn read_class(&self, class_name: &str) -> Result<Vec<u8>> {
+    fn read_class(&self, class_name: &str, logger: &Logger) -> Result<Vec<u8>> {
         let archive1 = File::open(&self.path)?;
         let mut archive = ZipArchive::new(archive1)?;
         for idx in 0..archive.len() {
             let mut entry = archive.by_index(idx)?;
             if class_name == entry.name() {
-                println!("find id is {}", entry.name());
+                log!(logger, Classpath, Trace, "found {} in {}", entry.name(), self.path.display());
                 let mut data = Vec::new();
                 entry.read_to_end(&mut data)?;
                 return Ok(data);
             }
-            println!("name is {}", entry.name())
+            log!(logger, Classpath, Trace, "visiting {}", entry.name());
         }
         Err(Error::ClassNotFound(String::from(class_name)))
     }
-
-    fn print_path(&self) {
-        println!("path is {}", self.path.display())
-    }
 }
 
 impl Entry for WildcardEntry {
-    fn read_class(&self, class_name: &str) -> Result<Vec<u8>> {
+    fn read_class(&self, class_name: &str, logger: &Logger) -> Result<Vec<u8>> {
         for entry in &self.entry_list {
-            let class_content = entry.read_class(class_name);
+            let class_content = entry.read_class(class_name, logger);
             if class_content.is_ok() {
                 return class_content;
             }
         }
         Err(Error::ClassNotFound(String::from(class_name)))
     }
-
-    fn print_path(&self) {
-        for entry in &self.entry_list {
-            entry.print_path()
-        }
-    }
 }
 
 impl WildcardEntry {
-    pub fn new(path: &PathBuf) -> WildcardEntry {
+    pub fn new(path: &Path) -> WildcardEntry {
         let mut result = WildcardEntry {
             entry_list: Vec::new(),
         };
@@ -109,6 +91,6 @@ impl WildcardEntry {
                 }
             }
         }
-        return result;
+        result
     }
 }
\ No newline at end of file
@@ -0,0 +1,177 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use byteorder::{ByteOrder, BE, LE};
+use crate::log::{log, Logger};
+
+use super::entry::Entry;
+use super::{Error, Result};
+
+const MAGIC: u32 = 0xCAFEDADA;
+const MAJOR_VERSION: u32 = 1;
+// magic, version, flags, resource count, table length, locations size and strings size
+const HEADER_SIZE: usize = 7 * 4;
+const HASH_MULTIPLIER: u32 = 0x01000193;
+
+// the attributes of a location, by their kind
+const ATTRIBUTE_MODULE: usize = 1;
+const ATTRIBUTE_PARENT: usize = 2;
+const ATTRIBUTE_BASE: usize = 3;
+const ATTRIBUTE_EXTENSION: usize = 4;
+const ATTRIBUTE_OFFSET: usize = 5;
+const ATTRIBUTE_COMPRESSED: usize = 6;
+const ATTRIBUTE_UNCOMPRESSED: usize = 7;
+const ATTRIBUTE_COUNT: usize = 8;
+
+// the class library of a JDK 9 or later, the jimage file lib/modules: an index, a perfect hash table from resource
+// names like /java.base/java/lang/Object.class to their locations, ahead of the resources themselves. The index is
+// read once, the resources when they are asked for
+pub struct JimageEntry {
+    path: PathBuf,
+    // jimage files are in the byte order of the platform they were made for, which the magic tells
+    big_endian: bool,
+    table_length: usize,
+    index: Vec<u8>,
+}
+
+impl JimageEntry {
+    pub fn open(path: &Path, logger: &Logger) -> Result<JimageEntry> {
+        let mut file = File::open(path)?;
+        let mut header = [0; HEADER_SIZE];
+        file.read_exact(&mut header)?;
+        let big_endian = match LE::read_u32(&header) {
+            MAGIC => false,
+            magic if magic.swap_bytes() == MAGIC => true,
+            _ => return Err(Error::Jimage(path.display().to_string(), "bad magic number")),
+        };
+        let mut entry = JimageEntry { path: PathBuf::from(path), big_endian, table_length: 0, index: header.to_vec() };
+        if entry.u32_at(4) >> 16 != MAJOR_VERSION {
+            return Err(Error::Jimage(path.display().to_string(), "unsupported version"));
+        }
+        entry.table_length = entry.u32_at(16) as usize;
+        let index_size = HEADER_SIZE + entry.table_length * 8 + entry.u32_at(20) as usize + entry.u32_at(24) as usize;
+        entry.index.resize(index_size, 0);
+        file.read_exact(&mut entry.index[HEADER_SIZE..])?;
+        log!(logger, Classpath, Debug, "opened {}, {} resources", path.display(), entry.u32_at(12));
+        Ok(entry)
+    }
+
+    fn u32_at(&self, at: usize) -> u32 {
+        match self.big_endian {
+            true => BE::read_u32(&self.index[at..]),
+            false => LE::read_u32(&self.index[at..]),
+        }
+    }
+
+    fn locations_start(&self) -> usize {
+        HEADER_SIZE + self.table_length * 8
+    }
+
+    fn strings_start(&self) -> usize {
+        self.locations_start() + self.u32_at(20) as usize
+    }
+
+    // the NUL-terminated string at `offset` into the strings
+    fn string(&self, offset: u64) -> String {
+        let start = self.strings_start() + offset as usize;
+        let end = self.index[start..].iter().position(|&byte| byte == 0).map_or(self.index.len(), |end| start + end);
+        String::from_utf8_lossy(&self.index[start..end]).into_owned()
+    }
+
+    // the attributes of the resource `name`, if there is one
+    fn location(&self, name: &str) -> Option<[u64; ATTRIBUTE_COUNT]> {
+        if self.table_length == 0 {
+            return None;
+        }
+        // the redirect picks the seed that gives the name's slot, or the slot itself when it is negative
+        let redirect = self.u32_at(HEADER_SIZE + hash(name, HASH_MULTIPLIER) as usize % self.table_length * 4) as i32;
+        let slot = match redirect {
+            0 => return None,
+            redirect if redirect < 0 => (-redirect - 1) as usize,
+            seed => hash(name, seed as u32) as usize % self.table_length,
+        };
+        let offset = self.u32_at(HEADER_SIZE + (self.table_length + slot) * 4) as usize;
+        let attributes = self.attributes(self.locations_start() + offset);
+        // a name that is not in the table hashes to some other resource's slot
+        (self.name(&attributes) == name).then_some(attributes)
+    }
+
+    // attributes are a byte of their kind and length, then their value in that many big-endian bytes, up to the end
+    fn attributes(&self, mut at: usize) -> [u64; ATTRIBUTE_COUNT] {
+        let mut attributes = [0; ATTRIBUTE_COUNT];
+        while let Some(&byte) = self.index.get(at).filter(|&&byte| byte > 7) {
+            let length = (byte & 7) as usize + 1;
+            let value = self.index[at + 1..at + 1 + length].iter().fold(0, |value, &byte| value << 8 | byte as u64);
+            if let Some(attribute) = attributes.get_mut((byte >> 3) as usize) {
+                *attribute = value;
+            }
+            at += 1 + length;
+        }
+        attributes
+    }
+
+    // the full name of a resource, /module/parent/base.extension, where module, parent and extension may be left out
+    fn name(&self, attributes: &[u64; ATTRIBUTE_COUNT]) -> String {
+        let mut name = String::from("/");
+        let module = self.string(attributes[ATTRIBUTE_MODULE]);
+        if !module.is_empty() {
+            name = name + &module + "/";
+        }
+        let parent = self.string(attributes[ATTRIBUTE_PARENT]);
+        if !parent.is_empty() {
+            name = name + &parent + "/";
+        }
+        name += &self.string(attributes[ATTRIBUTE_BASE]);
+        let extension = self.string(attributes[ATTRIBUTE_EXTENSION]);
+        if !extension.is_empty() {
+            name = name + "." + &extension;
+        }
+        name
+    }
+
+    fn resource(&self, attributes: &[u64; ATTRIBUTE_COUNT]) -> Result<Vec<u8>> {
+        if attributes[ATTRIBUTE_COMPRESSED] != 0 {
+            return Err(Error::Jimage(self.path.display().to_string(), "compressed resources are not supported"));
+        }
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.index.len() as u64 + attributes[ATTRIBUTE_OFFSET]))?;
+        let mut data = vec![0; attributes[ATTRIBUTE_UNCOMPRESSED] as usize];
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    // the modules with classes in `package`, e.g. java/lang; the resource /packages/java.lang lists the modules
+    // the package is in, each as whether it is empty there and the offset of the module's name
+    fn modules(&self, package: &str) -> Result<Vec<String>> {
+        let Some(attributes) = self.location(&format!("/packages/{}", package.replace('/', "."))) else {
+            return Ok(Vec::new());
+        };
+        let modules = self.resource(&attributes)?;
+        let read_u32 = |at: usize| match self.big_endian {
+            true => BE::read_u32(&modules[at..]),
+            false => LE::read_u32(&modules[at..]),
+        };
+        Ok((0..modules.len() / 8)
+            .filter(|&pair| read_u32(pair * 8) == 0)
+            .map(|pair| self.string(read_u32(pair * 8 + 4) as u64))
+            .collect())
+    }
+}
+
+impl Entry for JimageEntry {
+    fn read_class(&self, class_name: &str, logger: &Logger) -> Result<Vec<u8>> {
+        let package = class_name.rsplit_once('/').map_or("", |(package, _)| package);
+        for module in self.modules(package)? {
+            if let Some(attributes) = self.location(&format!("/{}/{}", module, class_name)) {
+                log!(logger, Classpath, Trace, "found {} in module {} of {}", class_name, module, self.path.display());
+                return self.resource(&attributes);
+            }
+        }
+        Err(Error::ClassNotFound(String::from(class_name)))
+    }
+}
+
+// the hash of the jimage's table, FNV-1 over the bytes of the name from `seed`
+fn hash(name: &str, seed: u32) -> u32 {
+    name.bytes().fold(seed, |hash, byte| hash.wrapping_mul(HASH_MULTIPLIER) ^ byte as u32) & 0x7FFFFFFF
+}
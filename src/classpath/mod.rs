@@ -1,26 +1,51 @@
+pub(crate) mod builtin;
 mod entry;
+mod jimage;
+#[cfg(test)]
+mod tests;
 
 use std::path::PathBuf;
+use std::rc::Rc;
+use crate::classpath::builtin::BuiltinEntry;
 use crate::classpath::entry::{DirEntry, Entry, WildcardEntry};
+use crate::classpath::jimage::JimageEntry;
 use std::result::Result as StdResult;
 use crate::error::Error;
 use crate::error::Error::ClassNotFound;
+use crate::log::{log, Logger};
 
 
 pub type Result<T> = StdResult<T, Error>;
 
+// which part of the classpath a class was found in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClassSource {
+    Boot,
+    Ext,
+    User,
+}
+
 pub struct Classpath {
     boot_classpath: Box<dyn Entry>,
     ext_classpath: Box<dyn Entry>,
     user_classpath: Box<dyn Entry>,
+    // whether the boot classes are the VM's own class library instead of a JRE's
+    builtin: bool,
+    // the log of the VM the classpath is for
+    logger: Rc<Logger>,
 }
 
 impl Classpath {
-    pub fn init_classpath(jre_classpath: PathBuf, user_classpath: PathBuf) -> Classpath {
-        return Classpath {
-            boot_classpath: Box::new(
-                WildcardEntry::new(&jre_classpath.join("*"))
-            ),
+    // the classpath of the JRE whose lib directory is `jre_classpath`: the jimage lib/modules of a JDK 9 or later,
+    // or else the jars in lib and lib/ext of an older one
+    pub fn init_classpath(jre_classpath: PathBuf, user_classpath: PathBuf, logger: Rc<Logger>) -> Result<Classpath> {
+        let modules = jre_classpath.join("modules");
+        let boot_classpath: Box<dyn Entry> = match modules.is_file() {
+            true => Box::new(JimageEntry::open(&modules, &logger)?),
+            false => Box::new(WildcardEntry::new(&jre_classpath.join("*"))),
+        };
+        Ok(Classpath {
+            boot_classpath,
             ext_classpath: Box::new(
                 WildcardEntry::new(&jre_classpath.join("ext").join("*"))
             ),
@@ -29,37 +54,55 @@ impl Classpath {
                     path: user_classpath
                 }
             ),
-        };
+            builtin: false,
+            logger,
+        })
+    }
+
+    // the classpath without a JRE, whose boot classes come from the built-in class library
+    pub fn builtin_classpath(user_classpath: PathBuf, logger: Rc<Logger>) -> Classpath {
+        Classpath {
+            boot_classpath: Box::new(BuiltinEntry),
+            ext_classpath: Box::new(WildcardEntry::default()),
+            user_classpath: Box::new(DirEntry { path: user_classpath }),
+            builtin: true,
+            logger,
+        }
+    }
+
+    pub fn is_builtin(&self) -> bool {
+        self.builtin
+    }
+
+    pub(crate) fn logger(&self) -> &Rc<Logger> {
+        &self.logger
     }
 
-    pub(crate) fn load_class(&self, class_name: String) -> Result<Vec<u8>> {
+    pub(crate) fn load_class(&self, class_name: String) -> Result<(Vec<u8>, ClassSource)> {
         let real_name = class_name.replace(".", "/") + ".class";
 
-        println!("try to find {} in boot classpath", class_name);
-        let mut result = self.boot_classpath.read_class(&real_name);
-        if result.is_ok() {
-            println!("find {} in boot classpath, class content is:\n {:?}", class_name, result);
-            // fs::write("test.class", boot_result);
-            return result;
+        log!(self.logger, Classpath, Trace, "looking for {} in the boot classpath", class_name);
+        let mut result = self.boot_classpath.read_class(&real_name, &self.logger);
+        if let Ok(content) = result {
+            log!(self.logger, Classpath, Debug, "found {} in the boot classpath, {} bytes", class_name, content.len());
+            return Ok((content, ClassSource::Boot));
         }
 
-        println!("try to find {} in ext classpath", class_name);
-        result = self.ext_classpath.read_class(&real_name);
-        if result.is_ok() {
-            println!("find {} in ext classpath, class content is:\n {:?}", class_name, result);
-            // fs::write("test.class", boot_result);
-            return result;
+        log!(self.logger, Classpath, Trace, "looking for {} in the ext classpath", class_name);
+        result = self.ext_classpath.read_class(&real_name, &self.logger);
+        if let Ok(content) = result {
+            log!(self.logger, Classpath, Debug, "found {} in the ext classpath, {} bytes", class_name, content.len());
+            return Ok((content, ClassSource::Ext));
         }
 
-        println!("try to find {} in user classpath", class_name);
-        result = self.user_classpath.read_class(&real_name);
-        if result.is_ok() {
-            println!("find {} in user classpath, class content is:\n {:?}", class_name, result);
-            // fs::write("test.class", boot_result);
-            return result;
+        log!(self.logger, Classpath, Trace, "looking for {} in the user classpath", class_name);
+        result = self.user_classpath.read_class(&real_name, &self.logger);
+        if let Ok(content) = result {
+            log!(self.logger, Classpath, Debug, "found {} in the user classpath, {} bytes", class_name, content.len());
+            return Ok((content, ClassSource::User));
         }
-        println!(" class {} not found! ", class_name);
-        return Err(ClassNotFound(class_name));
+        log!(self.logger, Classpath, Debug, "{} is not on the classpath", class_name);
+        Err(ClassNotFound(class_name))
     }
 }
 
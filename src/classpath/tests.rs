@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::classfile::class_reader::{ACC_PUBLIC, ACC_STATIC};
+use crate::classfile::writer::{ClassDef, Constant, MethodDef, Op};
+use crate::native::Sink;
+use crate::vm::{JValue, TestClasspath};
+
+use super::Classpath;
+
+// the JDK of JAVA_HOME, or else one installed where Linux distributions put them, if there is one
+fn installed_jdk() -> Option<PathBuf> {
+    let installed = std::fs::read_dir("/usr/lib/jvm").into_iter().flatten().filter_map(Result::ok);
+    std::env::var_os("JAVA_HOME")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(installed.map(|entry| entry.path()))
+        .find(|java_home| java_home.join("lib").join("modules").is_file())
+}
+
+#[test]
+fn a_modular_jdk_boots_on_the_classes_of_its_jimage() {
+    let Some(java_home) = installed_jdk() else {
+        eprintln!("skipped: no JDK 9 or later installed");
+        return;
+    };
+    let classpath = Classpath::init_classpath(java_home.join("lib"), PathBuf::from("."), Rc::default()).unwrap();
+    let (object, _) = classpath.load_class(String::from("java/lang/Object")).unwrap();
+    assert_eq!(object[..4], [0xCA, 0xFE, 0xBA, 0xBE]);
+    // a class of a module other than java.base
+    assert!(classpath.load_class(String::from("java/sql/Connection")).is_ok());
+    assert!(classpath.load_class(String::from("java/lang/NoSuchClass")).is_err());
+
+    let main = [
+        Op::GetStatic("java/lang/System", "out", "Ljava/io/PrintStream;"),
+        Op::LdcConstant(Constant::Int(42)),
+        Op::InvokeVirtual("java/io/PrintStream", "println", "(I)V"),
+        Op::Return,
+    ];
+    let main = [MethodDef::code("main", "([Ljava/lang/String;)V", ACC_PUBLIC | ACC_STATIC, 2, 1, &main)];
+    let user = TestClasspath::new(&[ClassDef::new("Hello", &main)]);
+    let stdout = Sink::default();
+    let mut vm = user.builder().jre(&java_home).stdout(stdout.clone()).build().unwrap();
+    assert_eq!(vm.call_static("java/lang/Math", "max", "(II)I", &[JValue::Int(3), JValue::Int(5)]), Ok(JValue::Int(5)));
+    vm.run_main("Hello", &[]).unwrap();
+    assert_eq!(stdout.0.borrow().as_slice(), b"42\n");
+}
@@ -0,0 +1,586 @@
+// the commands of the command sets the agent answers; the others get NOT_IMPLEMENTED
+use std::rc::Rc;
+
+use crate::native::Env;
+use crate::runtime::class::{signature, Class, Field};
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{Slot, ThreadState};
+
+use super::packet::{
+    Reader, Writer, ABSENT_INFORMATION, BREAKPOINT, INVALID_FIELDID, INVALID_FRAMEID, INVALID_INDEX, INVALID_LENGTH,
+    INVALID_OBJECT, INVALID_SLOT, NOT_IMPLEMENTED,
+};
+use super::{
+    find_object, find_thread, method_of, object_id, object_tag, status, step_kind, thread_id, type_tag, Agent,
+    Modifier, Request, Step, ILLEGAL_ARGUMENT, INVALID_CLASS, THREAD_GROUP, THREAD_TAG,
+};
+
+const VIRTUAL_MACHINE: u8 = 1;
+const REFERENCE_TYPE: u8 = 2;
+const CLASS_TYPE: u8 = 3;
+const METHOD: u8 = 6;
+const OBJECT_REFERENCE: u8 = 9;
+const STRING_REFERENCE: u8 = 10;
+const THREAD_REFERENCE: u8 = 11;
+const THREAD_GROUP_REFERENCE: u8 = 12;
+const ARRAY_REFERENCE: u8 = 13;
+const EVENT_REQUEST: u8 = 15;
+const STACK_FRAME: u8 = 16;
+const CLASS_OBJECT_REFERENCE: u8 = 17;
+
+impl Agent {
+    // the data of the reply to command `command` of set `set`, or the error code
+    pub(super) fn command(&mut self, env: &mut Env, set: u8, command: u8, data: &mut Reader) -> Result<Vec<u8>, u16> {
+        let mut reply = Writer::default();
+        match set {
+            VIRTUAL_MACHINE => self.virtual_machine(env, command, data, &mut reply)?,
+            REFERENCE_TYPE => self.reference_type(env, command, data, &mut reply)?,
+            CLASS_TYPE if command == 1 => {
+                let class = self.class(data.long()?)?;
+                reply.long(class.super_class.as_ref().map_or(0, |class| self.class_id(class)));
+            }
+            METHOD => self.method(command, data, &mut reply)?,
+            OBJECT_REFERENCE => self.object_reference(env, command, data, &mut reply)?,
+            STRING_REFERENCE if command == 1 => {
+                let string = find_object(env, data.long()?)?.ok_or(INVALID_OBJECT)?;
+                reply.string(&env.rust_string(string));
+            }
+            THREAD_REFERENCE => self.thread_reference(env, command, data, &mut reply)?,
+            THREAD_GROUP_REFERENCE => match command {
+                1 => {
+                    reply.string("main");
+                }
+                2 => {
+                    reply.long(0);
+                }
+                3 => {
+                    let threads = all_threads(env);
+                    reply.int(threads.len() as i32);
+                    for thread in threads {
+                        reply.long(thread);
+                    }
+                    reply.int(0);
+                }
+                _ => return Err(NOT_IMPLEMENTED),
+            },
+            ARRAY_REFERENCE => {
+                let array = find_object(env, data.long()?)?.ok_or(INVALID_OBJECT)?;
+                let length = env.heap[array].array_length();
+                match command {
+                    1 => {
+                        reply.int(length as i32);
+                    }
+                    2 => {
+                        let (first, count) = (data.int()?, data.int()?);
+                        let first = usize::try_from(first).ok().filter(|first| *first <= length).ok_or(INVALID_INDEX)?;
+                        let count = usize::try_from(count).ok().filter(|count| first + count <= length);
+                        write_elements(&mut reply, env, array, first..first + count.ok_or(INVALID_LENGTH)?);
+                    }
+                    _ => return Err(NOT_IMPLEMENTED),
+                }
+            }
+            EVENT_REQUEST => self.event_request(env, command, data, &mut reply)?,
+            STACK_FRAME => self.stack_frame(env, command, data, &mut reply)?,
+            CLASS_OBJECT_REFERENCE if command == 1 => {
+                let object = find_object(env, data.long()?)?.ok_or(INVALID_OBJECT)?;
+                let class = env.heap[object].mirror_of.clone().ok_or(INVALID_OBJECT)?;
+                reply.byte(type_tag(&class)).long(self.class_id(&class));
+            }
+            _ => return Err(NOT_IMPLEMENTED),
+        }
+        Ok(reply.0)
+    }
+
+    fn virtual_machine(
+        &mut self,
+        env: &mut Env,
+        command: u8,
+        data: &mut Reader,
+        reply: &mut Writer,
+    ) -> Result<(), u16> {
+        match command {
+            // Version
+            1 => {
+                reply.string("LearnJVM JDWP agent").int(1).int(8).string("1.8.0").string("LearnJVM");
+            }
+            // ClassesBySignature
+            2 => {
+                let signature = data.string()?;
+                let classes: Vec<_> = self.classes.iter().filter(|class| class.descriptor() == signature).collect();
+                reply.int(classes.len() as i32);
+                for class in classes {
+                    reply.byte(type_tag(class)).long(self.class_id(class)).int(status(class));
+                }
+            }
+            // AllClasses, AllClassesWithGeneric
+            3 | 20 => {
+                reply.int(self.classes.len() as i32);
+                for class in &self.classes {
+                    reply.byte(type_tag(class)).long(self.class_id(class)).string(&class.descriptor());
+                    if command == 20 {
+                        reply.string(&generic_signature(class));
+                    }
+                    reply.int(status(class));
+                }
+            }
+            // AllThreads
+            4 => {
+                let threads = all_threads(env);
+                reply.int(threads.len() as i32);
+                for thread in threads {
+                    reply.long(thread);
+                }
+            }
+            // TopLevelThreadGroups
+            5 => {
+                reply.int(1).long(THREAD_GROUP);
+            }
+            // Dispose
+            6 => self.detach(env),
+            // IDSizes: field, method, object, reference type and frame IDs
+            7 => {
+                reply.int(8).int(8).int(8).int(8).int(8);
+            }
+            // Suspend
+            8 => self.suspended += 1,
+            // Resume
+            9 => self.suspended = self.suspended.saturating_sub(1),
+            // Exit
+            10 => self.quit = true,
+            // Capabilities, CapabilitiesNew: nothing optional is supported
+            12 => {
+                for _ in 0..7 {
+                    reply.boolean(false);
+                }
+            }
+            17 => {
+                for _ in 0..32 {
+                    reply.boolean(false);
+                }
+            }
+            // ClassPaths
+            13 => {
+                let classpath = env.properties.iter().find(|(key, _)| key == "java.class.path");
+                let classpath: Vec<&str> = classpath.map_or(Vec::new(), |(_, value)| value.split(':').collect());
+                let directory = std::env::current_dir().unwrap_or_default();
+                reply.string(&directory.to_string_lossy()).int(classpath.len() as i32);
+                for path in classpath {
+                    reply.string(path);
+                }
+                reply.int(0);
+            }
+            // DisposeObjects, HoldEvents, ReleaseEvents: objects are not kept alive for the debugger, and events
+            // are only sent between instructions
+            14..=16 => {}
+            _ => return Err(NOT_IMPLEMENTED),
+        }
+        Ok(())
+    }
+
+    fn reference_type(&mut self, env: &mut Env, command: u8, data: &mut Reader, reply: &mut Writer) -> Result<(), u16> {
+        let class = self.class(data.long()?)?.clone();
+        match command {
+            // Signature, SignatureWithGeneric
+            1 | 13 => {
+                reply.string(&class.descriptor());
+                if command == 13 {
+                    reply.string(&generic_signature(&class));
+                }
+            }
+            // ClassLoader: there is only the bootstrap one
+            2 => {
+                reply.long(0);
+            }
+            // Modifiers
+            3 => {
+                reply.int(class.classfile.access_flags as i32);
+            }
+            // Fields, FieldsWithGeneric
+            4 | 14 => {
+                let class_id = self.class_id(&class);
+                reply.int(class.fields.len() as i32);
+                for (index, field) in class.fields.iter().enumerate() {
+                    reply.long(field_id(class_id, index)).string(&field.name).string(&field.descriptor);
+                    if command == 14 {
+                        reply.string(field.signature.as_deref().unwrap_or_default());
+                    }
+                    reply.int(field.access_flags as i32);
+                }
+            }
+            // Methods, MethodsWithGeneric
+            5 | 15 => {
+                reply.int(class.methods.len() as i32);
+                for (index, method) in class.methods.iter().enumerate() {
+                    reply.long(index as u64 + 1).string(&method.name).string(&method.descriptor);
+                    if command == 15 {
+                        reply.string(method.signature.as_deref().unwrap_or_default());
+                    }
+                    reply.int(method.access_flags as i32);
+                }
+            }
+            // GetValues, of static fields
+            6 => {
+                let count = data.int()?;
+                reply.int(count);
+                for _ in 0..count {
+                    let (declaring, field) = self.field(data.long()?)?;
+                    if !field.is_static() {
+                        return Err(INVALID_FIELDID);
+                    }
+                    let statics = declaring.static_fields.borrow();
+                    write_value(reply, env, &statics[field.slot..field.slot + field.slot_count()], &field.descriptor)?;
+                }
+            }
+            // SourceFile
+            7 => {
+                reply.string(class.source_file.as_deref().ok_or(ABSENT_INFORMATION)?);
+            }
+            // Status
+            9 => {
+                reply.int(status(&class));
+            }
+            // Interfaces
+            10 => {
+                reply.int(class.interfaces.len() as i32);
+                for interface in &class.interfaces {
+                    reply.long(self.class_id(interface));
+                }
+            }
+            // ClassObject
+            11 => {
+                let mirror = env.mirror(&class).map_err(|_| INVALID_CLASS)?;
+                reply.long(object_id(Some(mirror)));
+            }
+            _ => return Err(NOT_IMPLEMENTED),
+        }
+        Ok(())
+    }
+}
+
+impl Agent {
+    // a field ID is the ID of the class declaring the field shifted left 16 bits, plus its index there plus one
+    fn field(&self, id: u64) -> Result<(Rc<Class>, Rc<Field>), u16> {
+        let class = self.class(id >> 16).map_err(|_| INVALID_FIELDID)?;
+        let index = ((id & 0xffff) as usize).checked_sub(1).ok_or(INVALID_FIELDID)?;
+        let field = class.fields.get(index).ok_or(INVALID_FIELDID)?;
+        Ok((class.clone(), field.clone()))
+    }
+
+    fn method(&self, command: u8, data: &mut Reader, reply: &mut Writer) -> Result<(), u16> {
+        let class = self.class(data.long()?)?;
+        let method = method_of(class, data.long()?)?;
+        match command {
+            // LineTable
+            1 => {
+                let Some(code) = &method.code else {
+                    reply.long(u64::MAX).long(u64::MAX).int(0);
+                    return Ok(());
+                };
+                reply.long(0).long(code.length().saturating_sub(1) as u64).int(method.line_numbers.len() as i32);
+                for entry in &method.line_numbers {
+                    reply.long(entry.start_pc as u64).int(entry.line_number as i32);
+                }
+            }
+            // VariableTable, VariableTableWithGeneric
+            2 | 5 => {
+                if method.local_variables.is_empty() {
+                    return Err(ABSENT_INFORMATION);
+                }
+                reply.int(method.arg_slot_count() as i32).int(method.local_variables.len() as i32);
+                for variable in &method.local_variables {
+                    reply.long(variable.start_pc as u64).string(&variable.name).string(&variable.descriptor);
+                    if command == 5 {
+                        reply.string("");
+                    }
+                    reply.int(variable.length as i32).int(variable.index as i32);
+                }
+            }
+            // IsObsolete: classes are never redefined
+            4 => {
+                reply.boolean(false);
+            }
+            _ => return Err(NOT_IMPLEMENTED),
+        }
+        Ok(())
+    }
+
+    fn object_reference(
+        &mut self,
+        env: &mut Env,
+        command: u8,
+        data: &mut Reader,
+        reply: &mut Writer,
+    ) -> Result<(), u16> {
+        let id = data.long()?;
+        // IsCollected
+        if command == 9 {
+            reply.boolean(find_object(env, id) == Err(INVALID_OBJECT));
+            return Ok(());
+        }
+        // ReferenceType of the thread group, and of threads that have no Thread object yet
+        if command == 1 && (id == THREAD_GROUP || id & THREAD_TAG != 0 && find_thread(env, id)?.object.is_none()) {
+            let class_name = if id == THREAD_GROUP { "java/lang/ThreadGroup" } else { "java/lang/Thread" };
+            // the built-in class library has no ThreadGroup
+            let class = env.load(class_name).or_else(|_| env.load("java/lang/Object")).map_err(|_| INVALID_OBJECT)?;
+            self.refresh(env);
+            reply.byte(type_tag(&class)).long(self.class_id(&class));
+            return Ok(());
+        }
+        let object = find_object(env, id)?.ok_or(INVALID_OBJECT)?;
+        match command {
+            // ReferenceType
+            1 => {
+                let class = &env.heap[object].class;
+                reply.byte(type_tag(class)).long(self.class_id(class));
+            }
+            // GetValues
+            2 => {
+                let count = data.int()?;
+                reply.int(count);
+                for _ in 0..count {
+                    let (_, field) = self.field(data.long()?)?;
+                    let fields = env.heap[object].fields.borrow();
+                    let slots = match field.is_static() {
+                        true => return Err(INVALID_FIELDID),
+                        false => fields.get(field.slot..field.slot + field.slot_count()).ok_or(INVALID_FIELDID)?,
+                    };
+                    write_value(reply, env, slots, &field.descriptor)?;
+                }
+            }
+            _ => return Err(NOT_IMPLEMENTED),
+        }
+        Ok(())
+    }
+
+    fn thread_reference(
+        &mut self,
+        env: &mut Env,
+        command: u8,
+        data: &mut Reader,
+        reply: &mut Writer,
+    ) -> Result<(), u16> {
+        let thread = find_thread(env, data.long()?)?;
+        match command {
+            // Name
+            1 => {
+                reply.string(&thread.name);
+            }
+            // Suspend, Resume: threads only ever stop together
+            2 => self.suspended += 1,
+            3 => self.suspended = self.suspended.saturating_sub(1),
+            // Status: the thread status and whether it is suspended
+            4 => {
+                let status = match thread.state {
+                    ThreadState::Terminated => 0,
+                    ThreadState::Runnable => 1,
+                    ThreadState::Sleeping { .. } => 2,
+                    ThreadState::Blocked => 3,
+                    ThreadState::Joining { .. } | ThreadState::Waiting { .. } => 4,
+                };
+                reply.int(status).int((self.suspended > 0) as i32);
+            }
+            // ThreadGroup
+            5 => {
+                reply.long(THREAD_GROUP);
+            }
+            // Frames: a frame ID is the place of the frame from the bottom of the stack, counting from one
+            6 => {
+                let (start, length) = (data.int()?, data.int()?);
+                let depth = thread.depth();
+                let start = usize::try_from(start).ok().filter(|start| *start <= depth).ok_or(INVALID_INDEX)?;
+                let length = match length {
+                    -1 => depth - start,
+                    length => {
+                        let length = usize::try_from(length).ok().filter(|length| start + length <= depth);
+                        length.ok_or(INVALID_LENGTH)?
+                    }
+                };
+                reply.int(length as i32);
+                for (index, frame) in thread.frames().enumerate().skip(start).take(length) {
+                    reply.long((depth - index) as u64);
+                    self.write_location(reply, frame);
+                }
+            }
+            // FrameCount
+            7 => {
+                reply.int(thread.depth() as i32);
+            }
+            // SuspendCount
+            12 => {
+                reply.int(self.suspended as i32);
+            }
+            _ => return Err(NOT_IMPLEMENTED),
+        }
+        Ok(())
+    }
+
+    fn stack_frame(&mut self, env: &mut Env, command: u8, data: &mut Reader, reply: &mut Writer) -> Result<(), u16> {
+        let thread = find_thread(env, data.long()?)?;
+        let id = data.long()? as usize;
+        let index = thread.depth().checked_sub(id).filter(|_| id > 0).ok_or(INVALID_FRAMEID)?;
+        let frame = thread.frames().nth(index).ok_or(INVALID_FRAMEID)?;
+        let locals = &frame.local_vars.0;
+        match command {
+            // GetValues: of the slots asked for, each with the tag of its type
+            1 => {
+                let count = data.int()?;
+                reply.int(count);
+                for _ in 0..count {
+                    let slot = usize::try_from(data.int()?).map_err(|_| INVALID_SLOT)?;
+                    let tag = data.byte()?;
+                    let width = if tag == b'J' || tag == b'D' { 2 } else { 1 };
+                    let slots = locals.get(slot..slot + width).ok_or(INVALID_SLOT)?;
+                    write_value(reply, env, slots, &char::from(tag).to_string())?;
+                }
+            }
+            // ThisObject
+            3 => {
+                let this = match frame.method.is_static() {
+                    true => None,
+                    false => locals.first().and_then(|slot| match slot {
+                        Slot::Reference(object) => *object,
+                        _ => None,
+                    }),
+                };
+                match this {
+                    Some(this) => reply.byte(object_tag(env, this)).long(object_id(Some(this))),
+                    None => reply.byte(b'L').long(0),
+                };
+            }
+            _ => return Err(NOT_IMPLEMENTED),
+        }
+        Ok(())
+    }
+
+    fn event_request(&mut self, env: &mut Env, command: u8, data: &mut Reader, reply: &mut Writer) -> Result<(), u16> {
+        match command {
+            // Set
+            1 => {
+                let (kind, suspend_policy) = (data.byte()?, data.byte()?);
+                let mut modifiers = Vec::new();
+                let mut step = None;
+                for _ in 0..data.int()? {
+                    modifiers.push(match data.byte()? {
+                        1 => Modifier::Count(data.int()?),
+                        2 => {
+                            data.int()?;
+                            Modifier::Ignored
+                        }
+                        3 => Modifier::ThreadOnly(find_thread(env, data.long()?)?.id),
+                        4 => Modifier::ClassOnly(self.class(data.long()?)?.clone()),
+                        5 => Modifier::ClassMatch(data.string()?),
+                        6 => Modifier::ClassExclude(data.string()?),
+                        7 => {
+                            let (method, pc) = self.resolve(data.location()?)?;
+                            Modifier::LocationOnly(method, pc)
+                        }
+                        8 => {
+                            let class = match data.long()? {
+                                0 => None,
+                                id => Some(self.class(id)?.clone()),
+                            };
+                            Modifier::ExceptionOnly(class, data.boolean()?, data.boolean()?)
+                        }
+                        9 => {
+                            data.long()?;
+                            data.long()?;
+                            Modifier::Ignored
+                        }
+                        10 => {
+                            let thread = find_thread(env, data.long()?)?;
+                            let kind = step_kind(data.int()?, data.int()?)?;
+                            step = Some(Step::new(kind, thread).ok_or(INVALID_FRAMEID)?);
+                            Modifier::Step
+                        }
+                        11 => {
+                            data.long()?;
+                            Modifier::Ignored
+                        }
+                        12 => Modifier::SourceNameMatch(data.string()?),
+                        // PlatformThreadsOnly: every thread is one
+                        13 => Modifier::Ignored,
+                        _ => return Err(ILLEGAL_ARGUMENT),
+                    });
+                }
+                let id = self.next_request;
+                self.next_request += 1;
+                self.requests.push(Request { id, kind, suspend_policy, modifiers, step });
+                reply.int(id);
+            }
+            // Clear
+            2 => {
+                let (kind, id) = (data.byte()?, data.int()?);
+                self.requests.retain(|request| (request.kind, request.id) != (kind, id));
+            }
+            // ClearAllBreakpoints
+            3 => self.requests.retain(|request| request.kind != BREAKPOINT),
+            _ => return Err(NOT_IMPLEMENTED),
+        }
+        Ok(())
+    }
+}
+
+// the IDs of the live threads
+fn all_threads(env: &Env) -> Vec<u64> {
+    let threads = std::iter::once(&*env.thread).chain(env.scheduler.threads());
+    threads.filter(|thread| thread.state != ThreadState::Terminated).map(thread_id).collect()
+}
+
+fn field_id(class_id: u64, index: usize) -> u64 {
+    class_id << 16 | (index as u64 + 1)
+}
+
+fn generic_signature(class: &Class) -> String {
+    signature(&class.classfile.constant_pool, &class.classfile.attributes_info).unwrap_or_default()
+}
+
+// a value with its tag, from its slots and the descriptor of its type
+fn write_value(reply: &mut Writer, env: &Env, slots: &[Slot], descriptor: &str) -> Result<(), u16> {
+    let tag = descriptor.as_bytes().first().copied().ok_or(INVALID_SLOT)?;
+    match (tag, slots) {
+        (b'L' | b'[', [Slot::Reference(Some(object))]) => {
+            reply.byte(object_tag(env, *object)).long(object_id(Some(*object)));
+        }
+        (b'L' | b'[', [Slot::Reference(None)]) => {
+            reply.byte(tag).long(0);
+        }
+        (_, slots) => {
+            reply.byte(tag);
+            write_primitive(reply, tag, slots)?;
+        }
+    }
+    Ok(())
+}
+
+// a primitive value without its tag
+fn write_primitive(reply: &mut Writer, tag: u8, slots: &[Slot]) -> Result<(), u16> {
+    match (tag, slots) {
+        (b'Z', [Slot::Int(value)]) => reply.boolean(*value != 0),
+        (b'B', [Slot::Int(value)]) => reply.byte(*value as u8),
+        (b'C' | b'S', [Slot::Int(value)]) => reply.short(*value as i16),
+        (b'I', [Slot::Int(value)]) => reply.int(*value),
+        (b'F', [Slot::Float(value)]) => reply.int(value.to_bits() as i32),
+        (b'J', [first @ Slot::Long { .. }, second]) => reply.long(Slot::join(first, second, false)),
+        (b'D', [first @ Slot::Double { .. }, second]) => reply.long(Slot::join(first, second, true)),
+        _ => return Err(INVALID_SLOT),
+    };
+    Ok(())
+}
+
+// the elements of `array` in `range` as an array region: the tag of the elements, how many there are, and the
+// values, with their tags when they are objects
+fn write_elements(reply: &mut Writer, env: &Env, array: ObjectRef, range: std::ops::Range<usize>) {
+    let object = &env.heap[array];
+    let descriptor = &object.class.name[1..];
+    let width = Slot::zero(descriptor).len();
+    let tag = descriptor.as_bytes()[0];
+    reply.byte(tag).int(range.len() as i32);
+    let elements = object.fields.borrow();
+    for index in range {
+        let slots = &elements[index * width..(index + 1) * width];
+        let _ = match tag {
+            b'L' | b'[' => write_value(reply, env, slots, descriptor),
+            _ => write_primitive(reply, tag, slots),
+        };
+    }
+}
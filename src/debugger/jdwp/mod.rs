@@ -0,0 +1,548 @@
+// the JDWP agent, for jdb and IDEs to debug the VM over a socket: a debugger attaches by connecting and sending the
+// handshake, then sends commands, which are answered one at a time, and asks for events with EventRequest.Set. The
+// VM only ever runs one thread at a time, so suspending a thread suspends the VM, and it stays suspended, answering
+// commands, until the debugger resumes it as many times as it was suspended. While it runs, the agent looks for
+// commands every POLL_INTERVAL instructions
+mod commands;
+mod packet;
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::log::log;
+use crate::native::Env;
+use crate::runtime::class::{Class, InitState, Method};
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{Frame, Thread};
+
+use super::{Debugger, Event, Step, StepKind};
+use packet::{
+    read_command, write_command, write_reply, Command, Reader, Writer, ARRAY, BREAKPOINT, CLASS, CLASS_PREPARE,
+    COMPOSITE, ERROR, EVENT_COMMAND_SET, EXCEPTION, HANDSHAKE, ILLEGAL_ARGUMENT, INITIALIZED, INTERFACE, INVALID_CLASS,
+    INVALID_LOCATION, INVALID_METHODID, INVALID_OBJECT, INVALID_THREAD, PREPARED, SINGLE_STEP, SUSPEND_ALL,
+    SUSPEND_NONE, VERIFIED, VM_DEATH, VM_START,
+};
+
+const POLL_INTERVAL: u32 = 1000;
+
+// the suspend policy of events and their kinds, requests and data
+type Events = (u8, Vec<(u8, i32, Vec<u8>)>);
+
+// the IDs of threads and of the one thread group are set apart from those of objects, which are below 1 << 32
+const THREAD_TAG: u64 = 1 << 40;
+const THREAD_GROUP: u64 = 1 << 41;
+
+pub struct Agent {
+    listener: TcpListener,
+    // whether the first instruction waits for a debugger to attach, like suspend=y
+    suspend: bool,
+    connection: Option<TcpStream>,
+    // once a debugger has gone away nothing stops any more
+    detached: bool,
+    started: bool,
+    // the classes by their ID less one, in the order they were loaded
+    classes: Vec<Rc<Class>>,
+    class_ids: HashMap<*const Class, u64>,
+    requests: Vec<Request>,
+    next_request: i32,
+    // how many times the VM has been suspended and not yet resumed
+    suspended: u32,
+    // instructions since the connection was last looked at
+    polled: u32,
+    // set by VirtualMachine.Exit
+    quit: bool,
+    // the thread, depth and pc of the last stop, for an instruction that runs again after the class initialization
+    // it started not to stop twice
+    stopped_at: Option<(u64, usize, i32)>,
+}
+
+// what EventRequest.Set asked for
+struct Request {
+    id: i32,
+    kind: u8,
+    suspend_policy: u8,
+    modifiers: Vec<Modifier>,
+    // where the step of a single step request goes from
+    step: Option<Step>,
+}
+
+enum Modifier {
+    // the event is reported the count-th time it happens, and then the request goes away
+    Count(i32),
+    ThreadOnly(u64),
+    ClassOnly(Rc<Class>),
+    // a class name like java.lang.String, which may start or end with *
+    ClassMatch(String),
+    ClassExclude(String),
+    LocationOnly(Rc<Method>, i32),
+    // the exception class, None for any, and whether caught and uncaught ones are reported
+    ExceptionOnly(Option<Rc<Class>>, bool, bool),
+    SourceNameMatch(String),
+    // the step itself is kept by the request
+    Step,
+    // conditions, field and instance filters, which nothing here reports events for
+    Ignored,
+}
+
+// what an event happened to, for the modifiers of the requests to filter it
+struct Occurrence<'a> {
+    thread: &'a Thread,
+    // the class of the location, or the one prepared
+    class: &'a Class,
+    location: Option<(&'a Rc<Method>, i32)>,
+    // the class of the exception thrown and whether something catches it
+    exception: Option<(&'a Class, bool)>,
+}
+
+impl Agent {
+    pub fn new(listener: TcpListener, suspend: bool) -> Agent {
+        Agent {
+            listener,
+            suspend,
+            connection: None,
+            detached: false,
+            started: false,
+            classes: Vec::new(),
+            class_ids: HashMap::new(),
+            requests: Vec::new(),
+            next_request: 1,
+            suspended: 0,
+            polled: 0,
+            quit: false,
+            stopped_at: None,
+        }
+    }
+
+    // takes the debugger on `stream` once it has sent the handshake, and tells it the VM has started
+    fn attach(&mut self, env: &mut Env, mut stream: TcpStream) -> Result<(), Error> {
+        stream.set_nonblocking(false)?;
+        let mut handshake = [0; HANDSHAKE.len()];
+        io::Read::read_exact(&mut stream, &mut handshake)?;
+        if handshake != HANDSHAKE {
+            return Ok(());
+        }
+        io::Write::write_all(&mut stream, HANDSHAKE)?;
+        let _ = stream.set_nodelay(true);
+        log!(env.logger, Interp, Info, "debugger attached from {}", stream.peer_addr()?);
+        self.connection = Some(stream);
+        self.refresh(env);
+        let mut data = Writer::default();
+        data.long(thread_id(env.thread));
+        let policy = if self.suspend { SUSPEND_ALL } else { SUSPEND_NONE };
+        self.send(env, policy, vec![(VM_START, 0, data.0)])
+    }
+
+    // accepts a debugger that has connected, and answers the commands it has sent
+    fn poll(&mut self, env: &mut Env) -> Result<(), Error> {
+        let Some(stream) = &self.connection else {
+            self.listener.set_nonblocking(true)?;
+            return match self.listener.accept() {
+                Ok((stream, _)) => self.attach(env, stream),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => Ok(()),
+                Err(error) => Err(error.into()),
+            };
+        };
+        stream.set_nonblocking(true)?;
+        let waiting = stream.peek(&mut [0]);
+        stream.set_nonblocking(false)?;
+        match waiting {
+            Ok(0) => self.detach(env),
+            Ok(_) => self.serve(env)?,
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {}
+            Err(_) => self.detach(env),
+        }
+        self.wait(env)
+    }
+
+    // answers commands for as long as the VM is suspended
+    fn wait(&mut self, env: &mut Env) -> Result<(), Error> {
+        while self.suspended > 0 && self.connection.is_some() {
+            self.serve(env)?;
+        }
+        Ok(())
+    }
+
+    // reads a command and answers it
+    fn serve(&mut self, env: &mut Env) -> Result<(), Error> {
+        let Some(stream) = &mut self.connection else {
+            return Ok(());
+        };
+        let Ok(Command { id, set, command, data }) = read_command(stream) else {
+            self.detach(env);
+            return Ok(());
+        };
+        self.refresh(env);
+        let reply = self.command(env, set, command, &mut Reader(&data));
+        if let Err(error) = reply {
+            log!(env.logger, Interp, Debug, "JDWP command {}/{} failed with error {}", set, command, error);
+        }
+        if let Some(stream) = &mut self.connection {
+            if write_reply(stream, id, reply).is_err() {
+                self.detach(env);
+            }
+        }
+        match self.quit {
+            true => Err(Error::DebuggerQuit),
+            false => Ok(()),
+        }
+    }
+
+    // lets the VM go on without a debugger
+    fn detach(&mut self, env: &Env) {
+        log!(env.logger, Interp, Info, "debugger detached");
+        self.connection = None;
+        self.detached = true;
+        self.requests.clear();
+        self.suspended = 0;
+    }
+
+    // gives the classes loaded since the last call IDs, and returns them
+    fn refresh(&mut self, env: &Env) -> Vec<Rc<Class>> {
+        let loaded = env.loader.loaded_since(self.classes.len());
+        for class in &loaded {
+            self.classes.push(class.clone());
+            self.class_ids.insert(Rc::as_ptr(class), self.classes.len() as u64);
+        }
+        loaded
+    }
+
+    // sends the events of a composite event packet, each with the kind, request and data, and suspends the VM when
+    // `policy` says to
+    fn send(&mut self, env: &mut Env, policy: u8, events: Vec<(u8, i32, Vec<u8>)>) -> Result<(), Error> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let mut data = Writer::default();
+        data.byte(policy).int(events.len() as i32);
+        for (kind, request, event) in events {
+            data.byte(kind).int(request).0.extend(event);
+        }
+        let Some(stream) = &mut self.connection else {
+            return Ok(());
+        };
+        if write_command(stream, 0, EVENT_COMMAND_SET, COMPOSITE, &data.0).is_err() {
+            self.detach(env);
+            return Ok(());
+        }
+        if policy != SUSPEND_NONE {
+            self.suspended += 1;
+        }
+        self.wait(env)
+    }
+
+    // the requests of `kind` `occurrence` is reported to, with their suspend policies, leaving out the ones whose
+    // count is not yet up and dropping those whose count is
+    fn matching(&mut self, kind: u8, occurrence: &Occurrence) -> Vec<(i32, u8)> {
+        let mut matching = Vec::new();
+        self.requests.retain_mut(|request| {
+            if request.kind != kind || !request.modifiers.iter().all(|modifier| modifier.admits(occurrence)) {
+                return true;
+            }
+            if kind == SINGLE_STEP {
+                let thread = occurrence.thread;
+                let frame = thread.frame().expect("a step without a frame");
+                let Some(step) = request.step.as_ref().filter(|step| step.is_done(thread, frame)) else {
+                    return true;
+                };
+                // the step goes on from here once it is reported
+                request.step = Step::new(step.kind, thread);
+            }
+            let count = request.modifiers.iter_mut().find_map(|modifier| match modifier {
+                Modifier::Count(count) => Some(count),
+                _ => None,
+            });
+            if let Some(count) = count {
+                *count -= 1;
+                if *count > 0 {
+                    return true;
+                }
+                matching.push((request.id, request.suspend_policy));
+                return false;
+            }
+            matching.push((request.id, request.suspend_policy));
+            true
+        });
+        matching
+    }
+
+    fn class_id(&self, class: &Class) -> u64 {
+        self.class_ids.get(&(class as *const Class)).copied().unwrap_or_default()
+    }
+
+    fn class(&self, id: u64) -> Result<&Rc<Class>, u16> {
+        let index = (id as usize).checked_sub(1).ok_or(INVALID_CLASS)?;
+        self.classes.get(index).ok_or(INVALID_CLASS)
+    }
+
+    // where `frame` is: its type tag, class, method and pc
+    fn location(&self, frame: &Frame) -> (u8, u64, u64, i32) {
+        let method = frame.class.methods.iter().position(|method| Rc::ptr_eq(method, &frame.method));
+        let method = method.map_or(0, |index| index as u64 + 1);
+        (type_tag(&frame.class), self.class_id(&frame.class), method, frame.pc)
+    }
+
+    fn write_location(&self, data: &mut Writer, frame: &Frame) {
+        let (tag, class, method, pc) = self.location(frame);
+        data.location(tag, class, method, pc);
+    }
+
+    // the method and pc of a location read from a command
+    fn resolve(&self, (_, class, method, pc): (u8, u64, u64, u64)) -> Result<(Rc<Method>, i32), u16> {
+        let method = method_of(self.class(class)?, method)?;
+        match method.code.as_ref().is_some_and(|code| code.has_instruction_at(pc as i32)) {
+            true => Ok((method.clone(), pc as i32)),
+            false => Err(INVALID_LOCATION),
+        }
+    }
+
+    // the reports for the events at the instruction about to run
+    fn instruction_events(&mut self, env: &Env) -> Events {
+        let thread = &*env.thread;
+        let frame = thread.frame().expect("an instruction without a frame");
+        if let Some((_, depth, pc)) = self.stopped_at.filter(|(id, ..)| *id == thread.id) {
+            if (thread.depth(), frame.pc) == (depth, pc) {
+                return (SUSPEND_NONE, Vec::new());
+            }
+            if thread.depth() <= depth {
+                self.stopped_at = None;
+            }
+        }
+        let occurrence =
+            Occurrence { thread, class: &frame.class, location: Some((&frame.method, frame.pc)), exception: None };
+        let mut matching = Vec::new();
+        for kind in [BREAKPOINT, SINGLE_STEP] {
+            matching.extend(self.matching(kind, &occurrence).into_iter().map(|request| (kind, request)));
+        }
+        let mut data = Writer::default();
+        data.long(thread_id(thread));
+        self.write_location(&mut data, frame);
+        let policy = matching.iter().map(|(_, (_, policy))| *policy).max().unwrap_or(SUSPEND_NONE);
+        if policy != SUSPEND_NONE {
+            self.stopped_at = Some((thread.id, thread.depth(), frame.pc));
+        }
+        let events = matching.into_iter().map(|(kind, (id, _))| (kind, id, data.0.clone())).collect();
+        (policy, events)
+    }
+
+    // the reports for the classes loaded since the last instruction
+    fn class_prepare_events(&mut self, env: &Env) -> Events {
+        let mut policy = SUSPEND_NONE;
+        let mut events = Vec::new();
+        for class in self.refresh(env) {
+            // the JVM does not prepare array classes
+            if class.is_array() {
+                continue;
+            }
+            let occurrence = Occurrence { thread: env.thread, class: &class, location: None, exception: None };
+            let matching = self.matching(CLASS_PREPARE, &occurrence);
+            let mut data = Writer::default();
+            data.long(thread_id(env.thread)).byte(type_tag(&class)).long(self.class_id(&class));
+            data.string(&class.descriptor()).int(status(&class));
+            for (id, request_policy) in matching {
+                policy = policy.max(request_policy);
+                events.push((CLASS_PREPARE, id, data.0.clone()));
+            }
+        }
+        (policy, events)
+    }
+
+    fn exception_events(&mut self, env: &Env, exception: ObjectRef, catch: Option<(usize, i32)>) -> Events {
+        let thread = &*env.thread;
+        let Some(frame) = thread.frame() else {
+            return (SUSPEND_NONE, Vec::new());
+        };
+        let class = &env.heap[exception].class;
+        let occurrence = Occurrence {
+            thread,
+            class: &frame.class,
+            location: Some((&frame.method, frame.pc)),
+            exception: Some((class, catch.is_some())),
+        };
+        let matching = self.matching(EXCEPTION, &occurrence);
+        let mut data = Writer::default();
+        data.long(thread_id(thread));
+        self.write_location(&mut data, frame);
+        data.byte(object_tag(env, exception)).long(object_id(Some(exception)));
+        match catch.and_then(|(depth, pc)| Some((thread.frames().nth(depth)?, pc))) {
+            Some((frame, pc)) => {
+                let (tag, class, method, _) = self.location(frame);
+                data.location(tag, class, method, pc);
+            }
+            None => {
+                data.location(CLASS, 0, 0, 0);
+            }
+        }
+        let policy = matching.iter().map(|(_, policy)| *policy).max().unwrap_or(SUSPEND_NONE);
+        (policy, matching.into_iter().map(|(id, _)| (EXCEPTION, id, data.0.clone())).collect())
+    }
+}
+
+impl Debugger for Agent {
+    fn check(&mut self, env: &mut Env, event: Event) -> Result<(), Error> {
+        if self.detached {
+            return Ok(());
+        }
+        if !std::mem::replace(&mut self.started, true) && self.suspend {
+            log!(env.logger, Interp, Info, "waiting for a debugger on {}", self.listener.local_addr()?);
+            let (stream, _) = self.listener.accept()?;
+            self.attach(env, stream)?;
+        }
+        if matches!(event, Event::Instruction) {
+            self.polled += 1;
+            if self.polled >= POLL_INTERVAL {
+                self.polled = 0;
+                self.poll(env)?;
+            }
+        }
+        if self.connection.is_none() || env.thread.frame().is_none() {
+            return Ok(());
+        }
+        let (policy, events) = self.class_prepare_events(env);
+        self.send(env, policy, events)?;
+        let (policy, events) = match event {
+            Event::Instruction => self.instruction_events(env),
+            Event::Exception { exception, catch } => self.exception_events(env, exception, catch),
+            Event::FieldWrite { .. } => return Ok(()),
+        };
+        self.send(env, policy, events)
+    }
+
+    fn exit(&mut self) {
+        let Some(stream) = &mut self.connection else {
+            return;
+        };
+        let mut events = vec![(VM_DEATH, 0)];
+        let requests = self.requests.iter().filter(|request| request.kind == VM_DEATH);
+        events.extend(requests.map(|request| (VM_DEATH, request.id)));
+        let mut data = Writer::default();
+        data.byte(SUSPEND_NONE).int(events.len() as i32);
+        for (kind, request) in events {
+            data.byte(kind).int(request);
+        }
+        let _ = write_command(stream, 0, EVENT_COMMAND_SET, COMPOSITE, &data.0);
+        self.connection = None;
+    }
+}
+
+impl Modifier {
+    fn admits(&self, occurrence: &Occurrence) -> bool {
+        match self {
+            Modifier::Count(_) | Modifier::Step | Modifier::Ignored => true,
+            Modifier::ThreadOnly(id) => occurrence.thread.id == *id,
+            Modifier::ClassOnly(class) => occurrence.class.is_subclass_of(&class.name),
+            Modifier::ClassMatch(pattern) => matches_pattern(pattern, &occurrence.class.java_name()),
+            Modifier::ClassExclude(pattern) => !matches_pattern(pattern, &occurrence.class.java_name()),
+            Modifier::LocationOnly(method, pc) => {
+                occurrence.location.is_some_and(|(at, at_pc)| Rc::ptr_eq(method, at) && *pc == at_pc)
+            }
+            Modifier::ExceptionOnly(class, caught, uncaught) => {
+                occurrence.exception.is_some_and(|(exception, is_caught)| {
+                    class.as_ref().is_none_or(|class| exception.is_subclass_of(&class.name))
+                        && if is_caught { *caught } else { *uncaught }
+                })
+            }
+            Modifier::SourceNameMatch(pattern) => {
+                occurrence.class.source_file.as_ref().is_some_and(|source| matches_pattern(pattern, source))
+            }
+        }
+    }
+}
+
+// whether `name` matches `pattern`, which is a name or one starting or ending with *
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match (pattern.strip_prefix('*'), pattern.strip_suffix('*')) {
+        (Some(suffix), _) => name.ends_with(suffix),
+        (_, Some(prefix)) => name.starts_with(prefix),
+        _ => name == pattern,
+    }
+}
+
+// a method ID is the index of the method in its class plus one
+fn method_of(class: &Class, id: u64) -> Result<&Rc<Method>, u16> {
+    let index = (id as usize).checked_sub(1).ok_or(INVALID_METHODID)?;
+    class.methods.get(index).ok_or(INVALID_METHODID)
+}
+
+fn type_tag(class: &Class) -> u8 {
+    if class.is_array() {
+        ARRAY
+    } else if class.is_interface() {
+        INTERFACE
+    } else {
+        CLASS
+    }
+}
+
+fn status(class: &Class) -> i32 {
+    match class.init_state.get() {
+        InitState::Initialized => VERIFIED | PREPARED | INITIALIZED,
+        InitState::Erroneous => VERIFIED | PREPARED | ERROR,
+        InitState::Uninitialized | InitState::BeingInitialized(_) => VERIFIED | PREPARED,
+    }
+}
+
+fn thread_id(thread: &Thread) -> u64 {
+    THREAD_TAG | thread.id
+}
+
+// the thread with ID `id`, the current one or one of those waiting for their turn
+fn find_thread<'e>(env: &'e Env, id: u64) -> Result<&'e Thread, u16> {
+    if id & THREAD_TAG == 0 {
+        return Err(INVALID_THREAD);
+    }
+    let id = id & !THREAD_TAG;
+    match env.thread.id == id {
+        true => Ok(env.thread),
+        false => env.scheduler.threads().find(|thread| thread.id == id).ok_or(INVALID_THREAD),
+    }
+}
+
+// an object ID is the id of the reference plus one, 0 being null
+fn object_id(object: Option<ObjectRef>) -> u64 {
+    object.map_or(0, |object| object.id() as u64 + 1)
+}
+
+// the object with ID `id`, None for null; a thread ID stands for the Thread object of the thread
+fn find_object(env: &Env, id: u64) -> Result<Option<ObjectRef>, u16> {
+    if id & THREAD_TAG != 0 {
+        return find_thread(env, id)?.object.map(Some).ok_or(INVALID_OBJECT);
+    }
+    let Some(id) = id.checked_sub(1) else {
+        return Ok(None);
+    };
+    let object = ObjectRef::from_id(u32::try_from(id).map_err(|_| INVALID_OBJECT)?);
+    match env.heap.get(object) {
+        Some(_) => Ok(Some(object)),
+        None => Err(INVALID_OBJECT),
+    }
+}
+
+// the tag of a value that is `object`
+fn object_tag(env: &Env, object: ObjectRef) -> u8 {
+    let object = &env.heap[object];
+    if object.class.name == "java/lang/String" {
+        b's'
+    } else if object.class.is_array() {
+        b'['
+    } else if object.mirror_of.is_some() {
+        b'c'
+    } else {
+        b'L'
+    }
+}
+
+// the step a Step modifier asks for, from the size and depth it gives
+fn step_kind(size: i32, depth: i32) -> Result<StepKind, u16> {
+    match (size, depth) {
+        (0, 0) => Ok(StepKind::Instruction),
+        (_, 0) => Ok(StepKind::Into),
+        (_, 1) => Ok(StepKind::Over),
+        (_, 2) => Ok(StepKind::Out),
+        _ => Err(ILLEGAL_ARGUMENT),
+    }
+}
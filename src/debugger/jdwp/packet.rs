@@ -0,0 +1,174 @@
+// the wire format of JDWP: command and reply packets, the values in them and the constants of the protocol
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+
+pub(super) const HANDSHAKE: &[u8] = b"JDWP-Handshake";
+
+// the flag of reply packets
+const REPLY: u8 = 0x80;
+
+// error codes
+pub(super) const INVALID_THREAD: u16 = 10;
+pub(super) const INVALID_OBJECT: u16 = 20;
+pub(super) const INVALID_CLASS: u16 = 21;
+pub(super) const INVALID_METHODID: u16 = 23;
+pub(super) const INVALID_LOCATION: u16 = 24;
+pub(super) const INVALID_FIELDID: u16 = 25;
+pub(super) const INVALID_FRAMEID: u16 = 30;
+pub(super) const INVALID_SLOT: u16 = 35;
+pub(super) const NOT_IMPLEMENTED: u16 = 99;
+pub(super) const ABSENT_INFORMATION: u16 = 101;
+pub(super) const ILLEGAL_ARGUMENT: u16 = 103;
+pub(super) const INVALID_INDEX: u16 = 503;
+pub(super) const INVALID_LENGTH: u16 = 504;
+
+// event kinds
+pub(super) const SINGLE_STEP: u8 = 1;
+pub(super) const BREAKPOINT: u8 = 2;
+pub(super) const EXCEPTION: u8 = 4;
+pub(super) const CLASS_PREPARE: u8 = 8;
+pub(super) const VM_START: u8 = 90;
+pub(super) const VM_DEATH: u8 = 99;
+
+// suspend policies
+pub(super) const SUSPEND_NONE: u8 = 0;
+pub(super) const SUSPEND_ALL: u8 = 2;
+
+// reference type tags
+pub(super) const CLASS: u8 = 1;
+pub(super) const INTERFACE: u8 = 2;
+pub(super) const ARRAY: u8 = 3;
+
+// class statuses
+pub(super) const VERIFIED: i32 = 1;
+pub(super) const PREPARED: i32 = 2;
+pub(super) const INITIALIZED: i32 = 4;
+pub(super) const ERROR: i32 = 8;
+
+// the composite event command, the only command the VM sends
+pub(super) const EVENT_COMMAND_SET: u8 = 64;
+pub(super) const COMPOSITE: u8 = 100;
+
+// a command packet from the debugger
+pub(super) struct Command {
+    pub id: u32,
+    pub set: u8,
+    pub command: u8,
+    pub data: Vec<u8>,
+}
+
+pub(super) fn read_command(input: &mut impl Read) -> io::Result<Command> {
+    let length = input.read_u32::<BE>()?;
+    let id = input.read_u32::<BE>()?;
+    let flags = input.read_u8()?;
+    let set = input.read_u8()?;
+    let command = input.read_u8()?;
+    let mut data = vec![0; (length as usize).saturating_sub(11)];
+    input.read_exact(&mut data)?;
+    if flags & REPLY != 0 {
+        // the VM never sends commands for the debugger to answer
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "a reply packet from the debugger"));
+    }
+    Ok(Command { id, set, command, data })
+}
+
+// the reply to command `id`: its data, or the error code when it failed
+pub(super) fn write_reply(output: &mut impl Write, id: u32, reply: Result<Vec<u8>, u16>) -> io::Result<()> {
+    let (error, data) = match reply {
+        Ok(data) => (0, data),
+        Err(error) => (error, Vec::new()),
+    };
+    output.write_u32::<BE>(11 + data.len() as u32)?;
+    output.write_u32::<BE>(id)?;
+    output.write_u8(REPLY)?;
+    output.write_u16::<BE>(error)?;
+    output.write_all(&data)?;
+    output.flush()
+}
+
+pub(super) fn write_command(output: &mut impl Write, id: u32, set: u8, command: u8, data: &[u8]) -> io::Result<()> {
+    output.write_u32::<BE>(11 + data.len() as u32)?;
+    output.write_u32::<BE>(id)?;
+    output.write_u8(0)?;
+    output.write_u8(set)?;
+    output.write_u8(command)?;
+    output.write_all(data)?;
+    output.flush()
+}
+
+// reads the data of a command; running out of it is an ILLEGAL_ARGUMENT error
+pub(super) struct Reader<'a>(pub &'a [u8]);
+
+impl Reader<'_> {
+    pub fn byte(&mut self) -> Result<u8, u16> {
+        self.0.read_u8().map_err(|_| ILLEGAL_ARGUMENT)
+    }
+
+    pub fn boolean(&mut self) -> Result<bool, u16> {
+        Ok(self.byte()? != 0)
+    }
+
+    pub fn int(&mut self) -> Result<i32, u16> {
+        self.0.read_i32::<BE>().map_err(|_| ILLEGAL_ARGUMENT)
+    }
+
+    pub fn long(&mut self) -> Result<u64, u16> {
+        self.0.read_u64::<BE>().map_err(|_| ILLEGAL_ARGUMENT)
+    }
+
+    pub fn string(&mut self) -> Result<String, u16> {
+        let length = self.int()? as usize;
+        if self.0.len() < length {
+            return Err(ILLEGAL_ARGUMENT);
+        }
+        let (string, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(String::from_utf8_lossy(string).into_owned())
+    }
+
+    // the type tag, class, method and pc of a location
+    pub fn location(&mut self) -> Result<(u8, u64, u64, u64), u16> {
+        Ok((self.byte()?, self.long()?, self.long()?, self.long()?))
+    }
+}
+
+// builds the data of a reply or an event; every ID is 8 bytes
+#[derive(Default)]
+pub(super) struct Writer(pub Vec<u8>);
+
+impl Writer {
+    pub fn byte(&mut self, value: u8) -> &mut Writer {
+        self.0.push(value);
+        self
+    }
+
+    pub fn boolean(&mut self, value: bool) -> &mut Writer {
+        self.byte(value as u8)
+    }
+
+    pub fn short(&mut self, value: i16) -> &mut Writer {
+        self.0.extend(value.to_be_bytes());
+        self
+    }
+
+    pub fn int(&mut self, value: i32) -> &mut Writer {
+        self.0.extend(value.to_be_bytes());
+        self
+    }
+
+    pub fn long(&mut self, value: u64) -> &mut Writer {
+        self.0.extend(value.to_be_bytes());
+        self
+    }
+
+    pub fn string(&mut self, value: &str) -> &mut Writer {
+        self.int(value.len() as i32);
+        self.0.extend(value.as_bytes());
+        self
+    }
+
+    pub fn location(&mut self, tag: u8, class: u64, method: u64, pc: i32) -> &mut Writer {
+        self.byte(tag).long(class).long(method).long(pc as u64)
+    }
+}
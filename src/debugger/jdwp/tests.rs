@@ -0,0 +1,205 @@
+use crate::classfile::class_reader::{ACC_PUBLIC, ACC_STATIC};
+use crate::classfile::writer::{ClassDef, Constant, FieldDef, MethodDef, Op};
+use crate::vm::TestClasspath;
+
+// a JDWP debugger for a test to script: it sends commands and reads the replies and events
+struct JdwpClient {
+    stream: std::net::TcpStream,
+    next_id: u32,
+}
+
+impl JdwpClient {
+    fn read_packet(&mut self) -> (u8, [u8; 2], Vec<u8>) {
+        use byteorder::{ReadBytesExt, BE};
+        use std::io::Read;
+        let length = self.stream.read_u32::<BE>().unwrap();
+        self.stream.read_u32::<BE>().unwrap();
+        let flags = self.stream.read_u8().unwrap();
+        let mut code = [0; 2];
+        self.stream.read_exact(&mut code).unwrap();
+        let mut data = vec![0; length as usize - 11];
+        self.stream.read_exact(&mut data).unwrap();
+        (flags, code, data)
+    }
+
+    // the data of the reply to the command, which has to succeed
+    fn command(&mut self, set: u8, command: u8, data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        let mut packet = (11 + data.len() as u32).to_be_bytes().to_vec();
+        packet.extend(self.next_id.to_be_bytes());
+        packet.extend([0, set, command]);
+        packet.extend(data);
+        self.next_id += 1;
+        self.stream.write_all(&packet).unwrap();
+        let (flags, error, data) = self.read_packet();
+        assert_eq!((flags, error), (0x80, [0, 0]), "command {}/{}", set, command);
+        data
+    }
+
+    // the suspend policy and the kind, request and rest of the only event of the next composite event
+    fn event(&mut self) -> (u8, u8, i32, Vec<u8>) {
+        let (flags, code, data) = self.read_packet();
+        assert_eq!((flags, code), (0, [64, 100]));
+        assert_eq!(&data[1..5], &1i32.to_be_bytes());
+        (data[0], data[5], i32::from_be_bytes(data[6..10].try_into().unwrap()), data[10..].to_vec())
+    }
+}
+
+// the longs, ints and strings of JDWP data, one after the other
+fn jdwp_long(data: &mut &[u8]) -> u64 {
+    let (value, rest) = data.split_at(8);
+    *data = rest;
+    u64::from_be_bytes(value.try_into().unwrap())
+}
+
+fn jdwp_int(data: &mut &[u8]) -> i32 {
+    let (value, rest) = data.split_at(4);
+    *data = rest;
+    i32::from_be_bytes(value.try_into().unwrap())
+}
+
+fn jdwp_string(data: &mut &[u8]) -> String {
+    let length = jdwp_int(data) as usize;
+    let (value, rest) = data.split_at(length);
+    *data = rest;
+    String::from_utf8(value.to_vec()).unwrap()
+}
+
+#[test]
+fn a_jdwp_debugger_gets_class_prepare_breakpoint_and_step_events_and_reads_frames() {
+    let add = [
+        Op::ILoad(0),
+        Op::ILoad(1),
+        Op::IAdd,
+        Op::IStore(2),
+        Op::ILoad(2),
+        Op::PutStatic("RemoteMath", "total", "I"),
+        Op::ILoad(2),
+        Op::IReturn,
+    ];
+    let add = [MethodDef {
+        lines: &[(0, 10), (4, 11), (6, 12)],
+        locals: &[("a", "I", 0), ("b", "I", 1), ("sum", "I", 2)],
+        ..MethodDef::code("add", "(II)I", ACC_PUBLIC | ACC_STATIC, 2, 3, &add)
+    }];
+    let main = [
+        Op::LdcConstant(Constant::Int(2)),
+        Op::LdcConstant(Constant::Int(3)),
+        Op::InvokeStatic("RemoteMath", "add", "(II)I"),
+        Op::IStore(1),
+        Op::Return,
+    ];
+    let main = [MethodDef::code("main", "([Ljava/lang/String;)V", ACC_PUBLIC | ACC_STATIC, 2, 2, &main)];
+    let classpath = TestClasspath::new(&[
+        ClassDef::new("Remote", &main),
+        ClassDef { fields: &[FieldDef::new("total", "I", ACC_STATIC)], ..ClassDef::new("RemoteMath", &add) },
+    ]);
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let debugger = std::thread::spawn(move || {
+        use std::io::{Read, Write};
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        stream.write_all(b"JDWP-Handshake").unwrap();
+        let mut handshake = [0; 14];
+        stream.read_exact(&mut handshake).unwrap();
+        assert_eq!(&handshake, b"JDWP-Handshake");
+        let mut client = JdwpClient { stream, next_id: 1 };
+        // VM_START, suspending everything
+        let (policy, kind, _, _) = client.event();
+        assert_eq!((policy, kind), (2, 90));
+
+        // a class prepare request for RemoteMath, and a resume
+        let mut request = vec![8, 2, 0, 0, 0, 1, 5];
+        request.extend(10u32.to_be_bytes());
+        request.extend(b"RemoteMath");
+        client.command(15, 1, &request);
+        client.command(1, 9, &[]);
+        let (_, kind, _, data) = client.event();
+        assert_eq!(kind, 8);
+        let mut data = &data[..];
+        let thread = jdwp_long(&mut data);
+        assert_eq!(data[0], 1);
+        data = &data[1..];
+        let class = jdwp_long(&mut data);
+        assert_eq!(jdwp_string(&mut data), "LRemoteMath;");
+
+        // the breakpoint goes at the first instruction of line 11 of add
+        let mut data = &client.command(2, 5, &class.to_be_bytes())[..];
+        assert_eq!(jdwp_int(&mut data), 1);
+        let method = jdwp_long(&mut data);
+        assert_eq!((jdwp_string(&mut data), jdwp_string(&mut data)), (String::from("add"), String::from("(II)I")));
+        let mut line_table = class.to_be_bytes().to_vec();
+        line_table.extend(method.to_be_bytes());
+        let mut data = &client.command(6, 1, &line_table)[..];
+        assert_eq!((jdwp_long(&mut data), jdwp_long(&mut data), jdwp_int(&mut data)), (0, 9, 3));
+        let lines: Vec<_> = (0..3).map(|_| (jdwp_long(&mut data), jdwp_int(&mut data))).collect();
+        assert_eq!(lines, [(0, 10), (4, 11), (8, 12)]);
+        let mut location = vec![1];
+        location.extend(class.to_be_bytes());
+        location.extend(method.to_be_bytes());
+        location.extend(4u64.to_be_bytes());
+        let mut request = vec![2, 2, 0, 0, 0, 1, 7];
+        request.extend(&location);
+        client.command(15, 1, &request);
+        client.command(1, 9, &[]);
+        let (_, kind, _, data) = client.event();
+        assert_eq!(kind, 2);
+        assert_eq!(data[..8], thread.to_be_bytes());
+        assert_eq!(data[8..], location);
+
+        // the frames of add and main, and the locals of add
+        let mut frames = thread.to_be_bytes().to_vec();
+        frames.extend(0u32.to_be_bytes());
+        frames.extend((-1i32).to_be_bytes());
+        let mut data = &client.command(11, 6, &frames)[..];
+        assert_eq!(jdwp_int(&mut data), 2);
+        let frame = jdwp_long(&mut data);
+        let mut values = thread.to_be_bytes().to_vec();
+        values.extend(frame.to_be_bytes());
+        values.extend(3u32.to_be_bytes());
+        for slot in 0..3u32 {
+            values.extend(slot.to_be_bytes());
+            values.push(b'I');
+        }
+        let data = client.command(16, 1, &values);
+        assert_eq!(data, [&[0, 0, 0, 3][..], &[b'I', 0, 0, 0, 2], &[b'I', 0, 0, 0, 3], &[b'I', 0, 0, 0, 5]].concat());
+        let mut data = &client.command(6, 2, &line_table)[..];
+        assert_eq!((jdwp_int(&mut data), jdwp_int(&mut data)), (2, 3));
+        let names: Vec<_> = (0..3)
+            .map(|_| {
+                jdwp_long(&mut data);
+                let name = jdwp_string(&mut data);
+                jdwp_string(&mut data);
+                jdwp_int(&mut data);
+                (name, jdwp_int(&mut data))
+            })
+            .collect();
+        assert_eq!(names, [(String::from("a"), 0), (String::from("b"), 1), (String::from("sum"), 2)]);
+
+        // a step over to line 12, after total has been set
+        let mut request = vec![1, 2, 0, 0, 0, 2, 1, 0, 0, 0, 1, 10];
+        request.extend(thread.to_be_bytes());
+        request.extend([0, 0, 0, 1, 0, 0, 0, 1]);
+        client.command(15, 1, &request);
+        client.command(1, 9, &[]);
+        let (_, kind, _, data) = client.event();
+        assert_eq!(kind, 1);
+        assert_eq!(data[data.len() - 8..], 8u64.to_be_bytes());
+        let mut data = &client.command(2, 4, &class.to_be_bytes())[..];
+        assert_eq!(jdwp_int(&mut data), 1);
+        let field = jdwp_long(&mut data);
+        let mut values = class.to_be_bytes().to_vec();
+        values.extend(1u32.to_be_bytes());
+        values.extend(field.to_be_bytes());
+        assert_eq!(client.command(2, 6, &values), [0, 0, 0, 1, b'I', 0, 0, 0, 5]);
+
+        // the VM runs to the end
+        client.command(1, 9, &[]);
+        let (policy, kind, request, _) = client.event();
+        assert_eq!((policy, kind, request), (0, 99, 0));
+    });
+    let mut vm = classpath.builder().jdwp(listener, true).build().unwrap();
+    let result = vm.run_main("Remote", &[]);
+    debugger.join().unwrap();
+    result.unwrap();
+}
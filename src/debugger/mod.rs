@@ -0,0 +1,86 @@
+// the debuggers, which stop a thread at the events they are asked to and inspect it until they let it go on: the
+// --debug command loop, reading commands from an input, and the JDWP agent, taking them from jdb or an IDE over a
+// socket
+mod jdwp;
+mod repl;
+#[cfg(test)]
+mod tests;
+
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::native::Env;
+use crate::runtime::class::{Class, Field, Method};
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{Frame, Thread};
+
+pub use jdwp::Agent;
+pub use repl::Repl;
+
+pub trait Debugger {
+    // called with the thread `event` happens on, which it stops when the event calls for it;
+    // Err(Error::DebuggerQuit) ends the VM
+    fn check(&mut self, env: &mut Env, event: Event) -> Result<(), Error>;
+
+    // the VM is about to exit
+    fn exit(&mut self) {}
+}
+
+// what the interpreter asks the debugger about
+pub enum Event<'a> {
+    // the current frame is about to run the instruction at its pc
+    Instruction,
+    // putfield or putstatic is about to set `field` of `class` to the value on top of the stack
+    FieldWrite { class: &'a Class, field: &'a Rc<Field> },
+    // `exception` is thrown at the pc of the current frame; `catch` is the frame, counted from the innermost, and
+    // the pc of the handler catching it, None when nothing does
+    Exception { exception: ObjectRef, catch: Option<(usize, i32)> },
+}
+
+#[derive(Clone, Copy)]
+enum StepKind {
+    // to the next line, of the method or of one it calls
+    Into,
+    // to the next line of the method, or to where it returns
+    Over,
+    // to where the method returns
+    Out,
+    // to the next instruction
+    Instruction,
+}
+
+// a step of a thread, from the frame `depth` frames deep running `method` at `pc`
+struct Step {
+    kind: StepKind,
+    thread: u64,
+    depth: usize,
+    method: Rc<Method>,
+    pc: i32,
+}
+
+impl Step {
+    // a step from where `thread` is now
+    fn new(kind: StepKind, thread: &Thread) -> Option<Step> {
+        let frame = thread.frame()?;
+        Some(Step { kind, thread: thread.id, depth: thread.depth(), method: frame.method.clone(), pc: frame.pc })
+    }
+
+    fn is_done(&self, thread: &Thread, frame: &Frame) -> bool {
+        if thread.id != self.thread {
+            return false;
+        }
+        let depth = thread.depth();
+        // methods without line numbers step an instruction at a time
+        let moved = !Rc::ptr_eq(&frame.method, &self.method)
+            || match self.method.line_number(self.pc) {
+                Some(line) => frame.method.line_number(frame.pc) != Some(line),
+                None => frame.pc != self.pc,
+            };
+        match self.kind {
+            StepKind::Into => depth != self.depth || moved,
+            StepKind::Over => depth < self.depth || depth == self.depth && moved,
+            StepKind::Out => depth < self.depth,
+            StepKind::Instruction => true,
+        }
+    }
+}
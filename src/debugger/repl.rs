@@ -0,0 +1,541 @@
+// the --debug debugger: a thread stops before the instruction a breakpoint is at or a step ends at, and before it
+// writes a watched field, and then the commands read from the debugger's input, which go by those of jdb, inspect it
+// until one lets it go on
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::native::Env;
+use crate::runtime::class::{primitive_name, Class, Field, Method};
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{Frame, Slot};
+
+use super::{Debugger, Event, Step, StepKind};
+
+pub struct Repl {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    // the number the next breakpoint or watchpoint gets
+    next_id: usize,
+    step: Option<Step>,
+    // the thread, depth and pc of the last stop, for an instruction that runs again after the class initialization
+    // it started not to stop twice
+    stopped_at: Option<(u64, usize, i32)>,
+    // whether the first instruction has stopped, for the breakpoints to be set before anything runs
+    started: bool,
+    // once the input has ended nothing stops any more
+    detached: bool,
+}
+
+struct Breakpoint {
+    id: usize,
+    // the command that set it, e.g. stop at Main:12
+    command: String,
+    locations: Vec<(Rc<Method>, i32)>,
+}
+
+struct Watchpoint {
+    id: usize,
+    // the field as it was given, e.g. Main.count
+    name: String,
+    field: Rc<Field>,
+}
+
+// why a thread stopped
+enum Stop {
+    Started,
+    Breakpoint(usize),
+    Step,
+    // the watchpoint, the value the field has and the one it is set to
+    FieldWrite(usize, String, String),
+}
+
+const HELP: &str = "\
+cont                          -- lets the thread go on
+stop at <class>:<line>        -- sets a breakpoint at the start of a line
+stop in <class>.<method>[(<descriptor>)][@<pc>]
+                              -- sets a breakpoint at the start of a method, or at instruction <pc> of it
+watch <class>.<field>         -- stops before the field is written
+clear                         -- lists the breakpoints and watchpoints
+clear <number>                -- removes a breakpoint or watchpoint
+step                          -- runs to the next line, stepping into calls
+next                          -- runs to the next line, stepping over calls
+step up                       -- runs until the method returns
+stepi                         -- runs one instruction
+where                         -- lists the frames of the thread
+up [n], down [n]              -- selects a frame further out or in
+locals                        -- prints the local variables of the frame
+stack                         -- prints the operand stack of the frame, the top first
+print <expression>            -- prints a local variable, this or Class.field, followed by any .field
+dump <expression>             -- prints the fields or elements of an object
+quit                          -- ends the VM";
+
+// what a command leaves the stopped thread to do
+enum Action {
+    Prompt,
+    Resume,
+    Quit,
+}
+
+impl Repl {
+    pub fn new(input: Box<dyn BufRead>, output: Box<dyn Write>) -> Repl {
+        Repl {
+            input,
+            output,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            next_id: 1,
+            step: None,
+            stopped_at: None,
+            started: false,
+            detached: false,
+        }
+    }
+
+    // reports why the thread of `env` stopped and takes commands until one lets it go on
+    fn stop(&mut self, env: &Env, stop: Stop) -> Result<(), Error> {
+        let thread = &*env.thread;
+        let frame = thread.frame().expect("a stop without a frame");
+        if !matches!(stop, Stop::Started) {
+            self.stopped_at = Some((thread.id, thread.depth(), frame.pc));
+        }
+        self.step = None;
+        let message = match stop {
+            Stop::Started => String::from("VM started"),
+            Stop::Breakpoint(id) => format!("Breakpoint {} hit", id),
+            Stop::Step => String::from("Step completed"),
+            Stop::FieldWrite(id, old, value) => {
+                let watchpoint = self.watchpoints.iter().find(|watchpoint| watchpoint.id == id);
+                let name = watchpoint.map_or("", |watchpoint| watchpoint.name.as_str());
+                format!("Field {} is set from {} to {}", name, old, value)
+            }
+        };
+        self.say(format!("{}: \"thread={}\", {}", message, thread.name, location(frame)));
+        let mut selected = 0;
+        loop {
+            let _ = write!(self.output, "{}[{}] ", thread.name, selected + 1);
+            let _ = self.output.flush();
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                // nobody is left to take commands
+                self.detached = true;
+                return Ok(());
+            }
+            match self.command(env, line.trim(), &mut selected) {
+                Action::Prompt => {}
+                Action::Resume => return Ok(()),
+                Action::Quit => return Err(Error::DebuggerQuit),
+            }
+        }
+    }
+
+    fn command(&mut self, env: &Env, line: &str, selected: &mut usize) -> Action {
+        let thread = &*env.thread;
+        let frames: Vec<&Frame> = thread.frames().collect();
+        let frame = frames[*selected];
+        let (name, argument) = line.split_once(' ').map_or((line, ""), |(name, argument)| (name, argument.trim()));
+        match (name, argument) {
+            ("", _) => {}
+            ("cont" | "run", _) => return Action::Resume,
+            ("quit" | "exit", _) => return Action::Quit,
+            ("help" | "?", _) => self.say(HELP),
+            ("step", "") => return self.start_step(env, StepKind::Into),
+            ("step", "up") => return self.start_step(env, StepKind::Out),
+            ("next", _) => return self.start_step(env, StepKind::Over),
+            ("stepi", _) => return self.start_step(env, StepKind::Instruction),
+            ("stop", argument) => {
+                let locations = match argument.split_once(' ') {
+                    Some(("at", spec)) => line_locations(env, spec.trim()),
+                    Some(("in", spec)) => method_locations(env, spec.trim()),
+                    _ => Err(String::from("Usage: stop at <class>:<line> or stop in <class>.<method>")),
+                };
+                match locations {
+                    Ok(locations) => {
+                        let id = self.next_id();
+                        self.say(format!("Set breakpoint {}: {}", id, argument));
+                        self.breakpoints.push(Breakpoint { id, command: format!("stop {}", argument), locations });
+                    }
+                    Err(error) => self.say(error),
+                }
+            }
+            ("watch", name) => match watched_field(env, name) {
+                Ok(field) => {
+                    let id = self.next_id();
+                    self.say(format!("Set watchpoint {}: {}", id, name));
+                    self.watchpoints.push(Watchpoint { id, name: String::from(name), field });
+                }
+                Err(error) => self.say(error),
+            },
+            ("clear", "") => {
+                let mut set: Vec<_> = self.breakpoints.iter().map(|point| (point.id, point.command.clone())).collect();
+                set.extend(self.watchpoints.iter().map(|point| (point.id, format!("watch {}", point.name))));
+                set.sort();
+                match set.is_empty() {
+                    true => self.say("No breakpoints or watchpoints set"),
+                    false => {
+                        let set: Vec<_> = set.iter().map(|(id, command)| format!("  {}: {}", id, command)).collect();
+                        self.say(format!("Breakpoints and watchpoints set:\n{}", set.join("\n")));
+                    }
+                }
+            }
+            ("clear", id) => {
+                let count = self.breakpoints.len() + self.watchpoints.len();
+                self.breakpoints.retain(|point| point.id.to_string() != id);
+                self.watchpoints.retain(|point| point.id.to_string() != id);
+                match self.breakpoints.len() + self.watchpoints.len() < count {
+                    true => self.say(format!("Removed {}", id)),
+                    false => self.say(format!("No breakpoint or watchpoint {}", id)),
+                }
+            }
+            ("where", _) => {
+                let elements = thread.stack_trace();
+                for (index, (element, frame)) in elements.iter().zip(&frames).enumerate() {
+                    self.say(format!("  [{}] {}, pc = {}", index + 1, element, frame.pc));
+                }
+            }
+            ("up" | "down", count) => match count.parse::<usize>().or(if count.is_empty() { Ok(1) } else { Err(()) }) {
+                Ok(count) => {
+                    *selected = match name {
+                        "up" => (*selected + count).min(frames.len() - 1),
+                        _ => selected.saturating_sub(count),
+                    };
+                    self.say(format!("  [{}] {}", *selected + 1, location(frames[*selected])));
+                }
+                Err(()) => self.say(format!("Invalid frame count: {}", count)),
+            },
+            ("locals", _) => {
+                let variables = frame.method.local_variables_at(frame.pc);
+                if variables.is_empty() {
+                    self.say("Local variable information not available");
+                    for (index, slots) in values(&frame.local_vars.0) {
+                        self.say(format!("  slot {} = {}", index, format_value(env, slots, "")));
+                    }
+                }
+                for variable in variables {
+                    let value = local(frame, variable.index as usize, &variable.descriptor);
+                    self.say(format!("  {} = {}", variable.name, format_value(env, value, &variable.descriptor)));
+                }
+            }
+            ("stack", _) => {
+                let slots = frame.operand_stack.slots();
+                if slots.is_empty() {
+                    self.say("The operand stack is empty");
+                }
+                for (_, slots) in values(slots).into_iter().rev() {
+                    self.say(format!("  {}", format_value(env, slots, "")));
+                }
+            }
+            ("print", expression) => match evaluate(env, frame, expression) {
+                Ok((value, descriptor)) => {
+                    self.say(format!(" {} = {}", expression, format_value(env, &value, &descriptor)))
+                }
+                Err(error) => self.say(error),
+            },
+            ("dump", expression) => match evaluate(env, frame, expression) {
+                Ok((value, _)) => match value.as_slice() {
+                    [Slot::Reference(Some(object))] => self.say(format!(" {} = {}", expression, dump(env, *object))),
+                    _ => self.say(format!("{} is not an object", expression)),
+                },
+                Err(error) => self.say(error),
+            },
+            _ => self.say(format!("Unrecognized command: '{}'. Try help for a list of commands.", line)),
+        }
+        Action::Prompt
+    }
+
+    fn start_step(&mut self, env: &Env, kind: StepKind) -> Action {
+        self.step = Step::new(kind, env.thread);
+        Action::Resume
+    }
+
+    fn next_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    fn say(&mut self, message: impl AsRef<str>) {
+        let _ = writeln!(self.output, "{}", message.as_ref());
+    }
+}
+
+impl Debugger for Repl {
+    // stops and takes commands when `event` is the first instruction, hits a breakpoint or watchpoint or ends a step
+    fn check(&mut self, env: &mut Env, event: Event) -> Result<(), Error> {
+        if self.detached {
+            return Ok(());
+        }
+        let thread = &*env.thread;
+        let Some(frame) = thread.frame() else {
+            return Ok(());
+        };
+        let stop = match event {
+            Event::Instruction => {
+                if !std::mem::replace(&mut self.started, true) {
+                    return self.stop(env, Stop::Started);
+                }
+                if let Some((_, depth, pc)) = self.stopped_at.filter(|(id, ..)| *id == thread.id) {
+                    if (thread.depth(), frame.pc) == (depth, pc) {
+                        return Ok(());
+                    }
+                    if thread.depth() <= depth {
+                        self.stopped_at = None;
+                    }
+                }
+                let at = |(method, pc): &(Rc<Method>, i32)| Rc::ptr_eq(method, &frame.method) && *pc == frame.pc;
+                match self.breakpoints.iter().find(|breakpoint| breakpoint.locations.iter().any(at)) {
+                    Some(breakpoint) => Stop::Breakpoint(breakpoint.id),
+                    None if self.step.as_ref().is_some_and(|step| step.is_done(thread, frame)) => Stop::Step,
+                    None => return Ok(()),
+                }
+            }
+            Event::FieldWrite { class, field } => {
+                let Some(watchpoint) = self.watchpoints.iter().find(|watchpoint| Rc::ptr_eq(&watchpoint.field, field))
+                else {
+                    return Ok(());
+                };
+                let count = field.slot_count();
+                let stack = &frame.operand_stack;
+                let value: Vec<Slot> = (0..count).rev().map(|depth| stack.peek_slot(depth).clone()).collect();
+                let old = match field.is_static() {
+                    true => class.static_fields.borrow()[field.slot..field.slot + count].to_vec(),
+                    false => match stack.peek_slot(count).reference() {
+                        Some(object) => env.heap[object].fields.borrow()[field.slot..field.slot + count].to_vec(),
+                        // the write throws a NullPointerException instead
+                        None => return Ok(()),
+                    },
+                };
+                let old = format_value(env, &old, &field.descriptor);
+                Stop::FieldWrite(watchpoint.id, old, format_value(env, &value, &field.descriptor))
+            }
+            Event::Exception { .. } => return Ok(()),
+        };
+        self.stop(env, stop)
+    }
+}
+
+// the method, line, pc and instruction `frame` is at, e.g. Main.run()V, line=12 pc=3: iload_1
+fn location(frame: &Frame) -> String {
+    let line = frame.method.line_number(frame.pc).map(|line| format!("line={} ", line)).unwrap_or_default();
+    let code = frame.method.code.as_ref();
+    let instruction = code.map(|code| code.instruction_at(frame.pc).instruction.to_string()).unwrap_or_default();
+    let method = &frame.method;
+    let name = format!("{}.{}{}", frame.class.java_name(), method.name, method.descriptor);
+    format!("{}, {}pc={}: {}", name, line, frame.pc, instruction)
+}
+
+fn load(env: &Env, class_name: &str) -> Result<Rc<Class>, String> {
+    env.loader.load_class(&class_name.replace('.', "/")).map_err(|_| format!("No class {}", class_name))
+}
+
+// the start of line `spec`, e.g. Main:12, in every method of the class that has code on it
+fn line_locations(env: &Env, spec: &str) -> Result<Vec<(Rc<Method>, i32)>, String> {
+    let (class_name, line) = spec.rsplit_once(':').ok_or_else(|| format!("Invalid line: {}", spec))?;
+    let line: u16 = line.parse().map_err(|_| format!("Invalid line number: {}", line))?;
+    let class = load(env, class_name)?;
+    let locations: Vec<_> = class
+        .methods
+        .iter()
+        .flat_map(|method| {
+            let starts = method.line_numbers.iter().filter(|entry| entry.line_number == line);
+            starts.map(|entry| (method.clone(), entry.start_pc as i32))
+        })
+        .collect();
+    match locations.is_empty() {
+        true => Err(format!("No code at line {} of {}", line, class.java_name())),
+        false => Ok(locations),
+    }
+}
+
+// instruction `pc`, 0 when not given, of the methods `spec` names, e.g. Main.run, Main.run(I)V or Main.run@3
+fn method_locations(env: &Env, spec: &str) -> Result<Vec<(Rc<Method>, i32)>, String> {
+    let (spec, pc) = match spec.split_once('@') {
+        Some((spec, pc)) => (spec, pc.parse::<i32>().map_err(|_| format!("Invalid pc: {}", pc))?),
+        None => (spec, 0),
+    };
+    let (name, descriptor) = match spec.find('(') {
+        Some(at) => (&spec[..at], Some(&spec[at..])),
+        None => (spec, None),
+    };
+    let (class_name, method_name) = name.rsplit_once('.').ok_or_else(|| format!("Invalid method: {}", spec))?;
+    let class = load(env, class_name)?;
+    let methods: Vec<_> = class
+        .methods
+        .iter()
+        .filter(|method| method.name == method_name)
+        .filter(|method| descriptor.is_none_or(|descriptor| method.descriptor == descriptor))
+        .filter(|method| method.code.as_ref().is_some_and(|code| code.has_instruction_at(pc)))
+        .map(|method| (method.clone(), pc))
+        .collect();
+    match methods.is_empty() {
+        true => Err(format!("No method {} with an instruction at pc {} in {}", spec, pc, class.java_name())),
+        false => Ok(methods),
+    }
+}
+
+// the field `name` names, e.g. Main.count
+fn watched_field(env: &Env, name: &str) -> Result<Rc<Field>, String> {
+    let (class_name, field_name) = name.rsplit_once('.').ok_or_else(|| format!("Invalid field: {}", name))?;
+    let class = load(env, class_name)?;
+    match field_named(&class, field_name) {
+        Some((_, field)) => Ok(field),
+        None => Err(format!("No field {} in {}", field_name, class_name)),
+    }
+}
+
+// the field `name` of `class` or a super class, and the class declaring it
+fn field_named(class: &Rc<Class>, name: &str) -> Option<(Rc<Class>, Rc<Field>)> {
+    let mut class = Some(class);
+    while let Some(current) = class {
+        if let Some(field) = current.fields.iter().find(|field| field.name == name) {
+            return Some((current.clone(), field.clone()));
+        }
+        class = current.super_class.as_ref();
+    }
+    None
+}
+
+// the value and descriptor of `expression`: a local variable, this or a static field like Main.count, followed by
+// any fields of the objects they refer to, like this.next.value
+fn evaluate(env: &Env, frame: &Frame, expression: &str) -> Result<(Vec<Slot>, String), String> {
+    let names: Vec<&str> = expression.split('.').collect();
+    let (mut value, mut descriptor, fields) = match names[0] {
+        "this" if !frame.method.is_static() => {
+            (frame.local_vars.0[..1].to_vec(), format!("L{};", frame.class.name), &names[1..])
+        }
+        name => match frame.method.local_variables_at(frame.pc).into_iter().find(|variable| variable.name == name) {
+            Some(variable) => {
+                let value = local(frame, variable.index as usize, &variable.descriptor).to_vec();
+                (value, variable.descriptor.clone(), &names[1..])
+            }
+            None => static_field(env, &names).ok_or_else(|| format!("{} is not a local variable or field", name))?,
+        },
+    };
+    for (index, name) in fields.iter().enumerate() {
+        let object = match value.as_slice() {
+            [Slot::Reference(Some(object))] => &env.heap[*object],
+            [Slot::Reference(None)] => return Err(format!("Cannot get {} of null", name)),
+            _ => {
+                let owner = names[..names.len() - fields.len() + index].join(".");
+                return Err(format!("Cannot get {} of {}, which is not an object", name, owner));
+            }
+        };
+        let (class, field) = field_named(&object.class, name)
+            .ok_or_else(|| format!("No field {} in {}", name, object.class.java_name()))?;
+        let slots = field.slot..field.slot + field.slot_count();
+        value = match field.is_static() {
+            true => class.static_fields.borrow()[slots].to_vec(),
+            false => object.fields.borrow()[slots].to_vec(),
+        };
+        descriptor = field.descriptor.clone();
+    }
+    Ok((value, descriptor))
+}
+
+// the static field the longest run of `names` that is a class is followed by, with the names after it
+fn static_field<'n>(env: &Env, names: &'n [&'n str]) -> Option<(Vec<Slot>, String, &'n [&'n str])> {
+    (1..names.len()).rev().find_map(|at| {
+        let class = env.loader.load_class(&names[..at].join("/")).ok()?;
+        let (class, field) = field_named(&class, names[at]).filter(|(_, field)| field.is_static())?;
+        let value = class.static_fields.borrow()[field.slot..field.slot + field.slot_count()].to_vec();
+        Some((value, field.descriptor.clone(), &names[at + 1..]))
+    })
+}
+
+// the slots of local variable `index` of type `descriptor`
+fn local<'f>(frame: &'f Frame, index: usize, descriptor: &str) -> &'f [Slot] {
+    let count = if descriptor == "J" || descriptor == "D" { 2 } else { 1 };
+    &frame.local_vars.0[index..(index + count).min(frame.local_vars.0.len())]
+}
+
+// `slots` grouped into values, the two halves of a long or double together, each with the index it starts at
+fn values(slots: &[Slot]) -> Vec<(usize, &[Slot])> {
+    let mut values = Vec::new();
+    let mut index = 0;
+    while index < slots.len() {
+        let count = match slots[index] {
+            Slot::Long { first: true, .. } | Slot::Double { first: true, .. } if index + 1 < slots.len() => 2,
+            _ => 1,
+        };
+        values.push((index, &slots[index..index + count]));
+        index += count;
+    }
+    values
+}
+
+// a value the way jdb prints it; `descriptor` tells booleans and chars from ints, and may be empty
+fn format_value(env: &Env, slots: &[Slot], descriptor: &str) -> String {
+    match slots {
+        [Slot::Int(value)] => match descriptor {
+            "Z" => (*value != 0).to_string(),
+            "C" => format!("'{}'", char::from_u32(*value as u32).unwrap_or(char::REPLACEMENT_CHARACTER)),
+            _ => value.to_string(),
+        },
+        [Slot::Float(value)] => value.to_string(),
+        [first @ Slot::Long { .. }, second] => (Slot::join(first, second, false) as i64).to_string(),
+        [first @ Slot::Double { .. }, second] => f64::from_bits(Slot::join(first, second, true)).to_string(),
+        [Slot::Reference(None)] => String::from("null"),
+        [Slot::Reference(Some(object))] => describe(env, *object),
+        _ => String::from("<uninitialized>"),
+    }
+}
+
+// an object as jdb prints it, e.g. instance of Main (id=12), with the contents of strings
+fn describe(env: &Env, object: ObjectRef) -> String {
+    let class = &env.heap[object].class;
+    if class.name == "java/lang/String" && has_value(env, object) {
+        return format!("{:?}", env.rust_string(object));
+    }
+    let mut name = type_name(&class.name);
+    if class.is_array() {
+        name = name.replacen("[]", &format!("[{}]", env.heap[object].array_length()), 1);
+    }
+    match &env.heap[object].mirror_of {
+        Some(class) => format!("class {} (id={})", type_name(&class.name), object.id()),
+        None => format!("instance of {} (id={})", name, object.id()),
+    }
+}
+
+// whether string `object` has been given its contents
+fn has_value(env: &Env, string: ObjectRef) -> bool {
+    let object = &env.heap[string];
+    let value = object.class.find_field("value", "[B").or_else(|| object.class.find_field("value", "[C"));
+    value.is_some_and(|(_, field)| matches!(object.fields.borrow()[field.slot], Slot::Reference(Some(_))))
+}
+
+// the fields of `object`, the ones its super classes declare first, or the elements of an array
+fn dump(env: &Env, object: ObjectRef) -> String {
+    let contents = &env.heap[object];
+    let fields = contents.fields.borrow();
+    if contents.class.is_array() {
+        let descriptor = &contents.class.name[1..];
+        let elements: Vec<_> = values(&fields).iter().map(|(_, slots)| format_value(env, slots, descriptor)).collect();
+        return format!("{} {{ {} }}", describe(env, object), elements.join(", "));
+    }
+    let mut classes = Vec::new();
+    let mut class = Some(&contents.class);
+    while let Some(current) = class {
+        classes.push(current);
+        class = current.super_class.as_ref();
+    }
+    let mut lines = vec![format!("{} {{", describe(env, object))];
+    for field in classes.iter().rev().flat_map(|class| class.fields.iter()).filter(|field| !field.is_static()) {
+        let value = &fields[field.slot..field.slot + field.slot_count()];
+        lines.push(format!("    {}: {}", field.name, format_value(env, value, &field.descriptor)));
+    }
+    lines.push(String::from("}"));
+    lines.join("\n")
+}
+
+// a class name in the Java language, e.g. int[] for [I
+fn type_name(class_name: &str) -> String {
+    let dimensions = class_name.bytes().take_while(|byte| *byte == b'[').count();
+    let element = &class_name[dimensions..];
+    let element = match element.strip_prefix('L').and_then(|element| element.strip_suffix(';')) {
+        Some(element) => element.replace('/', "."),
+        None if dimensions > 0 => String::from(primitive_name(element)),
+        None => element.replace('/', "."),
+    };
+    format!("{}{}", element, "[]".repeat(dimensions))
+}
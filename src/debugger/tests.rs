@@ -0,0 +1,72 @@
+use crate::classfile::class_reader::{ACC_PUBLIC, ACC_STATIC};
+use crate::classfile::writer::{ClassDef, Constant, FieldDef, MethodDef, Op};
+use crate::native::Sink;
+use crate::vm::TestClasspath;
+
+#[test]
+fn the_debugger_stops_at_breakpoints_watchpoints_and_steps() {
+    let add = [
+        Op::ILoad(0),
+        Op::ILoad(1),
+        Op::IAdd,
+        Op::IStore(2),
+        Op::ILoad(2),
+        Op::PutStatic("Debugged", "total", "I"),
+        Op::ILoad(2),
+        Op::IReturn,
+    ];
+    let main = [
+        Op::LdcConstant(Constant::Int(2)),
+        Op::LdcConstant(Constant::Int(3)),
+        Op::InvokeStatic("Debugged", "add", "(II)I"),
+        Op::IStore(1),
+        Op::Return,
+    ];
+    let methods = [
+        MethodDef {
+            lines: &[(0, 10), (4, 11), (6, 12)],
+            locals: &[("a", "I", 0), ("b", "I", 1), ("sum", "I", 2)],
+            ..MethodDef::code("add", "(II)I", ACC_PUBLIC | ACC_STATIC, 2, 3, &add)
+        },
+        MethodDef {
+            lines: &[(0, 4), (3, 5), (4, 6)],
+            ..MethodDef::code("main", "([Ljava/lang/String;)V", ACC_PUBLIC | ACC_STATIC, 2, 2, &main)
+        },
+    ];
+    let class = ClassDef { fields: &[FieldDef::new("total", "I", ACC_STATIC)], ..ClassDef::new("Debugged", &methods) };
+    let classpath = TestClasspath::new(&[class]);
+    let script = "stop at Debugged:11\nwatch Debugged.total\nstop at Debugged:99\ncont\nlocals\nwhere\nnext\nstack\n\
+        next\nprint Debugged.total\nstep up\nstack\nlocals\ncont\n";
+    let output = Sink::default();
+    let mut vm = classpath
+        .builder()
+        .debugger(script.as_bytes(), output.clone())
+        .build()
+        .unwrap();
+    vm.run_main("Debugged", &[]).unwrap();
+
+    let output = String::from_utf8(output.0.borrow().clone()).unwrap();
+    let expected = [
+        "VM started: \"thread=main\", Debugged.main([Ljava/lang/String;)V, line=4 pc=0: ldc_w #19",
+        "main[1] Set breakpoint 1: at Debugged:11",
+        "main[1] Set watchpoint 2: Debugged.total",
+        "main[1] No code at line 99 of Debugged",
+        "main[1] Breakpoint 1 hit: \"thread=main\", Debugged.add(II)I, line=11 pc=4: iload_2",
+        "main[1]   a = 2",
+        "  b = 3",
+        "  sum = 5",
+        "main[1]   [1] Debugged.add(Unknown Source), pc = 4",
+        "  [2] Debugged.main(Unknown Source), pc = 6",
+        "main[1] Field Debugged.total is set from 0 to 5: \"thread=main\", Debugged.add(II)I, line=11 pc=5: putstatic #10",
+        "main[1]   5",
+        "main[1] Step completed: \"thread=main\", Debugged.add(II)I, line=12 pc=8: iload_2",
+        "main[1]  Debugged.total = 5",
+        "main[1] Step completed: \"thread=main\", Debugged.main([Ljava/lang/String;)V, line=5 pc=9: istore_1",
+        "main[1]   5",
+        "main[1] Local variable information not available",
+        "  slot 0 = instance of java.lang.String[0] (id=0)",
+        "  slot 1 = <uninitialized>",
+        "main[1] ",
+    ];
+    assert_eq!(output.split('\n').collect::<Vec<_>>(), expected, "{}", output);
+}
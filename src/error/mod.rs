@@ -1,12 +1,14 @@
 use std::io;
 use thiserror::Error as ThisError;
 use zip::result::ZipError;
+use crate::classfile::format_check::FormatError;
+use crate::verifier::VerifyError;
 
 
 #[derive(ThisError, Debug)]
 pub enum Error {
     #[error(transparent)]
-    ZipEntryError(#[from] ZipError),
+    Zip(#[from] ZipError),
 
     #[error(transparent)]
     IO(#[from] io::Error),
@@ -14,9 +16,75 @@ pub enum Error {
     #[error("cannot find class: {0}")]
     ClassNotFound(String),
 
+    // the class file found for a class names another class
+    #[error("{0} (wrong name: {1})")]
+    WrongName(String, String),
+
+    // a class is its own super class or interface, through the classes between
+    #[error("class circularity: {0}")]
+    ClassCircularity(String),
+
+    #[error("cannot read the jimage {0}: {1}")]
+    Jimage(String, &'static str),
+
     #[error("unknown constant type: {0}")]
     UnKnownConstantType(u8),
 
     #[error("classpath not set!")]
     ClasspathNotSet(),
+
+    #[error("truncated class file: need {0} bytes at offset {1}")]
+    TruncatedClassFile(usize, usize),
+
+    #[error("truncated bytecode at offset {0}")]
+    TruncatedBytecode(usize),
+
+    #[error("illegal opcode {0:#x} at offset {1}")]
+    IllegalOpcode(u8, usize),
+
+    #[error("illegal newarray type {0} at offset {1}")]
+    InvalidArrayType(u8, usize),
+
+    #[error("malformed switch at offset {0}")]
+    InvalidSwitch(usize),
+
+    #[error("invalid constant pool index: {0}")]
+    InvalidConstantIndex(u16),
+
+    #[error("malformed utf8 constant at offset {0}")]
+    MalformedUtf8(usize),
+
+    #[error("unknown stack map frame type: {0}")]
+    UnknownStackMapFrame(u8),
+
+    #[error("unknown verification type: {0}")]
+    UnknownVerificationType(u8),
+
+    #[error("unknown annotation element value tag: {0}")]
+    UnknownElementValueTag(u8),
+
+    #[error("class format error:\n{}", .0.iter().map(|e| format!("  {}", e)).collect::<Vec<_>>().join("\n"))]
+    ClassFormat(Vec<FormatError>),
+
+    #[error(transparent)]
+    Verify(#[from] VerifyError),
+
+    #[error("thread stack is full")]
+    StackOverflow,
+
+    #[error("Java-level deadlock")]
+    Deadlock,
+
+    // the main thread of a program ended with an exception of this class that nothing caught
+    #[error("exception in thread \"main\" {0}")]
+    UncaughtException(String),
+
+    #[error("no main method in class {0}")]
+    MainNotFound(String),
+
+    #[error("no method {1} in class {0}")]
+    MethodNotFound(String, String),
+
+    #[error("the debugger quit the VM")]
+    DebuggerQuit,
 }
@@ -0,0 +1,543 @@
+use std::cell::Cell;
+
+use crate::error::Error;
+
+use super::bitcode_reader::BytecodeReader;
+use super::Instruction::{self, *};
+use super::{ArrayType, Local};
+
+// decodes a whole Code attribute into (pc, instruction) pairs
+pub fn decode_code(code: &[u8]) -> Result<Vec<(usize, Instruction)>, Error> {
+    let reader = BytecodeReader {
+        content: code.to_vec(),
+        cursor: Cell::new(0),
+    };
+    let mut instructions = Vec::new();
+    while (reader.cursor.get() as usize) < code.len() {
+        let pc = reader.cursor.get() as usize;
+        instructions.push((pc, Instruction::decode(&reader)?));
+    }
+    Ok(instructions)
+}
+
+// the inverse of decode_code, only the round-trip test re-encodes whole methods
+#[cfg(test)]
+pub fn encode_code(instructions: &[Instruction]) -> Vec<u8> {
+    let mut code = Vec::new();
+    for instruction in instructions {
+        instruction.encode(&mut code);
+    }
+    code
+}
+
+impl Instruction {
+    // decodes the instruction at the reader's cursor and leaves the cursor after it
+    pub fn decode(reader: &BytecodeReader) -> Result<Instruction, Error> {
+        let pc = reader.cursor.get() as usize;
+        let opcode = reader.read_u8()?;
+        let instruction = match opcode {
+            0x00 => Nop,
+            0x01 => AConstNull,
+            0x02..=0x08 => IConst(opcode as i32 - 0x03),
+            0x09 | 0x0a => LConst((opcode - 0x09) as i64),
+            0x0b..=0x0d => FConst((opcode - 0x0b) as f32),
+            0x0e | 0x0f => DConst((opcode - 0x0e) as f64),
+            0x10 => BIPush(reader.read_i8()?),
+            0x11 => SIPush(reader.read_i16()?),
+            0x12 => Ldc(reader.read_u8()?),
+            0x13 => LdcW(reader.read_u16()?),
+            0x14 => Ldc2W(reader.read_u16()?),
+            0x15..=0x19 => load(opcode - 0x15, Local::Byte(reader.read_u8()?)),
+            0x1a..=0x2d => load((opcode - 0x1a) / 4, Local::Implicit((opcode - 0x1a) % 4)),
+            0x2e => IALoad,
+            0x2f => LALoad,
+            0x30 => FALoad,
+            0x31 => DALoad,
+            0x32 => AALoad,
+            0x33 => BALoad,
+            0x34 => CALoad,
+            0x35 => SALoad,
+            0x36..=0x3a => store(opcode - 0x36, Local::Byte(reader.read_u8()?)),
+            0x3b..=0x4e => store((opcode - 0x3b) / 4, Local::Implicit((opcode - 0x3b) % 4)),
+            0x4f => IAStore,
+            0x50 => LAStore,
+            0x51 => FAStore,
+            0x52 => DAStore,
+            0x53 => AAStore,
+            0x54 => BAStore,
+            0x55 => CAStore,
+            0x56 => SAStore,
+            0x57 => Pop,
+            0x58 => Pop2,
+            0x59 => Dup,
+            0x5a => DupX1,
+            0x5b => DupX2,
+            0x5c => Dup2,
+            0x5d => Dup2X1,
+            0x5e => Dup2X2,
+            0x5f => Swap,
+            0x60 => IAdd,
+            0x61 => LAdd,
+            0x62 => FAdd,
+            0x63 => DAdd,
+            0x64 => ISub,
+            0x65 => LSub,
+            0x66 => FSub,
+            0x67 => DSub,
+            0x68 => IMul,
+            0x69 => LMul,
+            0x6a => FMul,
+            0x6b => DMul,
+            0x6c => IDiv,
+            0x6d => LDiv,
+            0x6e => FDiv,
+            0x6f => DDiv,
+            0x70 => IRem,
+            0x71 => LRem,
+            0x72 => FRem,
+            0x73 => DRem,
+            0x74 => INeg,
+            0x75 => LNeg,
+            0x76 => FNeg,
+            0x77 => DNeg,
+            0x78 => IShl,
+            0x79 => LShl,
+            0x7a => IShr,
+            0x7b => LShr,
+            0x7c => IUShr,
+            0x7d => LUShr,
+            0x7e => IAnd,
+            0x7f => LAnd,
+            0x80 => IOr,
+            0x81 => LOr,
+            0x82 => IXor,
+            0x83 => LXor,
+            0x84 => IInc {
+                local: Local::Byte(reader.read_u8()?),
+                value: reader.read_i8()? as i16,
+            },
+            0x85 => I2L,
+            0x86 => I2F,
+            0x87 => I2D,
+            0x88 => L2I,
+            0x89 => L2F,
+            0x8a => L2D,
+            0x8b => F2I,
+            0x8c => F2L,
+            0x8d => F2D,
+            0x8e => D2I,
+            0x8f => D2L,
+            0x90 => D2F,
+            0x91 => I2B,
+            0x92 => I2C,
+            0x93 => I2S,
+            0x94 => LCmp,
+            0x95 => FCmpL,
+            0x96 => FCmpG,
+            0x97 => DCmpL,
+            0x98 => DCmpG,
+            0x99 => IfEq(reader.read_i16()?),
+            0x9a => IfNe(reader.read_i16()?),
+            0x9b => IfLt(reader.read_i16()?),
+            0x9c => IfGe(reader.read_i16()?),
+            0x9d => IfGt(reader.read_i16()?),
+            0x9e => IfLe(reader.read_i16()?),
+            0x9f => IfICmpEq(reader.read_i16()?),
+            0xa0 => IfICmpNe(reader.read_i16()?),
+            0xa1 => IfICmpLt(reader.read_i16()?),
+            0xa2 => IfICmpGe(reader.read_i16()?),
+            0xa3 => IfICmpGt(reader.read_i16()?),
+            0xa4 => IfICmpLe(reader.read_i16()?),
+            0xa5 => IfACmpEq(reader.read_i16()?),
+            0xa6 => IfACmpNe(reader.read_i16()?),
+            0xa7 => Goto(reader.read_i16()?),
+            0xa8 => Jsr(reader.read_i16()?),
+            0xa9 => Ret(Local::Byte(reader.read_u8()?)),
+            0xaa => {
+                reader.skip_padding();
+                let default = reader.read_i32()?;
+                let low = reader.read_i32()?;
+                let high = reader.read_i32()?;
+                if low > high {
+                    return Err(Error::InvalidSwitch(pc));
+                }
+                let mut offsets = Vec::new();
+                for _ in low..=high {
+                    offsets.push(reader.read_i32()?);
+                }
+                TableSwitch { default, low, high, offsets }
+            }
+            0xab => {
+                reader.skip_padding();
+                let default = reader.read_i32()?;
+                let count = reader.read_i32()?;
+                if count < 0 {
+                    return Err(Error::InvalidSwitch(pc));
+                }
+                let mut pairs = Vec::new();
+                for _ in 0..count {
+                    pairs.push((reader.read_i32()?, reader.read_i32()?));
+                }
+                LookupSwitch { default, pairs }
+            }
+            0xac => IReturn,
+            0xad => LReturn,
+            0xae => FReturn,
+            0xaf => DReturn,
+            0xb0 => AReturn,
+            0xb1 => Return,
+            0xb2 => GetStatic(reader.read_u16()?),
+            0xb3 => PutStatic(reader.read_u16()?),
+            0xb4 => GetField(reader.read_u16()?),
+            0xb5 => PutField(reader.read_u16()?),
+            0xb6 => InvokeVirtual(reader.read_u16()?),
+            0xb7 => InvokeSpecial(reader.read_u16()?),
+            0xb8 => InvokeStatic(reader.read_u16()?),
+            0xb9 => {
+                let index = reader.read_u16()?;
+                let count = reader.read_u8()?;
+                reader.read_u8()?;
+                InvokeInterface { index, count }
+            }
+            0xba => {
+                let index = reader.read_u16()?;
+                reader.read_u16()?;
+                InvokeDynamic(index)
+            }
+            0xbb => New(reader.read_u16()?),
+            0xbc => {
+                let code = reader.read_u8()?;
+                NewArray(ArrayType::from_code(code).ok_or(Error::InvalidArrayType(code, pc))?)
+            }
+            0xbd => ANewArray(reader.read_u16()?),
+            0xbe => ArrayLength,
+            0xbf => AThrow,
+            0xc0 => CheckCast(reader.read_u16()?),
+            0xc1 => InstanceOf(reader.read_u16()?),
+            0xc2 => MonitorEnter,
+            0xc3 => MonitorExit,
+            0xc4 => {
+                let modified = reader.read_u8()?;
+                match modified {
+                    0x15..=0x19 => load(modified - 0x15, Local::Wide(reader.read_u16()?)),
+                    0x36..=0x3a => store(modified - 0x36, Local::Wide(reader.read_u16()?)),
+                    0xa9 => Ret(Local::Wide(reader.read_u16()?)),
+                    0x84 => IInc {
+                        local: Local::Wide(reader.read_u16()?),
+                        value: reader.read_i16()?,
+                    },
+                    _ => return Err(Error::IllegalOpcode(modified, pc)),
+                }
+            }
+            0xc5 => MultiANewArray {
+                index: reader.read_u16()?,
+                dimensions: reader.read_u8()?,
+            },
+            0xc6 => IfNull(reader.read_i16()?),
+            0xc7 => IfNonNull(reader.read_i16()?),
+            0xc8 => GotoW(reader.read_i32()?),
+            0xc9 => JsrW(reader.read_i32()?),
+            _ => return Err(Error::IllegalOpcode(opcode, pc)),
+        };
+        Ok(instruction)
+    }
+
+    // appends the instruction to `code`, which holds the method's code before it so switch padding comes out right
+    pub fn encode(&self, code: &mut Vec<u8>) {
+        let pc = code.len();
+        match self {
+            IConst(value) => code.push((value + 0x03) as u8),
+            LConst(value) => code.push(0x09 + *value as u8),
+            FConst(value) => code.push(0x0b + *value as u8),
+            DConst(value) => code.push(0x0e + *value as u8),
+            BIPush(value) => {
+                code.push(0x10);
+                code.push(*value as u8);
+            }
+            SIPush(value) => {
+                code.push(0x11);
+                code.extend_from_slice(&value.to_be_bytes());
+            }
+            Ldc(index) => code.extend_from_slice(&[0x12, *index]),
+            LdcW(index) | Ldc2W(index) | GetStatic(index) | PutStatic(index) | GetField(index) | PutField(index)
+            | InvokeVirtual(index) | InvokeSpecial(index) | InvokeStatic(index) | New(index) | ANewArray(index)
+            | CheckCast(index) | InstanceOf(index) => {
+                code.push(self.opcode());
+                code.extend_from_slice(&index.to_be_bytes());
+            }
+            ILoad(local) | LLoad(local) | FLoad(local) | DLoad(local) | ALoad(local) | IStore(local) | LStore(local)
+            | FStore(local) | DStore(local) | AStore(local) | Ret(local) => {
+                let (byte_opcode, implicit_opcode) = self.local_opcodes();
+                match local {
+                    Local::Implicit(index) if implicit_opcode != byte_opcode => code.push(implicit_opcode + index),
+                    Local::Implicit(index) | Local::Byte(index) => code.extend_from_slice(&[byte_opcode, *index]),
+                    Local::Wide(index) => {
+                        code.extend_from_slice(&[0xc4, byte_opcode]);
+                        code.extend_from_slice(&index.to_be_bytes());
+                    }
+                }
+            }
+            IInc { local, value } => match local {
+                Local::Implicit(index) | Local::Byte(index) => code.extend_from_slice(&[0x84, *index, *value as i8 as u8]),
+                Local::Wide(index) => {
+                    code.extend_from_slice(&[0xc4, 0x84]);
+                    code.extend_from_slice(&index.to_be_bytes());
+                    code.extend_from_slice(&value.to_be_bytes());
+                }
+            },
+            IfEq(offset) | IfNe(offset) | IfLt(offset) | IfGe(offset) | IfGt(offset) | IfLe(offset) | IfICmpEq(offset)
+            | IfICmpNe(offset) | IfICmpLt(offset) | IfICmpGe(offset) | IfICmpGt(offset) | IfICmpLe(offset)
+            | IfACmpEq(offset) | IfACmpNe(offset) | Goto(offset) | Jsr(offset) | IfNull(offset) | IfNonNull(offset) => {
+                code.push(self.opcode());
+                code.extend_from_slice(&offset.to_be_bytes());
+            }
+            GotoW(offset) | JsrW(offset) => {
+                code.push(self.opcode());
+                code.extend_from_slice(&offset.to_be_bytes());
+            }
+            TableSwitch { default, low, high, offsets } => {
+                code.push(0xaa);
+                code.resize(pc + 1 + padding(pc), 0);
+                for value in [default, low, high].into_iter().chain(offsets) {
+                    code.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            LookupSwitch { default, pairs } => {
+                code.push(0xab);
+                code.resize(pc + 1 + padding(pc), 0);
+                code.extend_from_slice(&default.to_be_bytes());
+                code.extend_from_slice(&(pairs.len() as i32).to_be_bytes());
+                for (key, offset) in pairs {
+                    code.extend_from_slice(&key.to_be_bytes());
+                    code.extend_from_slice(&offset.to_be_bytes());
+                }
+            }
+            InvokeInterface { index, count } => {
+                code.push(0xb9);
+                code.extend_from_slice(&index.to_be_bytes());
+                code.extend_from_slice(&[*count, 0]);
+            }
+            InvokeDynamic(index) => {
+                code.push(0xba);
+                code.extend_from_slice(&index.to_be_bytes());
+                code.extend_from_slice(&[0, 0]);
+            }
+            NewArray(array_type) => code.extend_from_slice(&[0xbc, array_type.code()]),
+            MultiANewArray { index, dimensions } => {
+                code.push(0xc5);
+                code.extend_from_slice(&index.to_be_bytes());
+                code.push(*dimensions);
+            }
+            _ => code.push(self.opcode()),
+        }
+    }
+
+    // the number of bytes the instruction takes when it starts at `pc`
+    pub fn length(&self, pc: usize) -> usize {
+        // only the switch padding depends on the pc, and only on its alignment
+        let start = pc % 4;
+        let mut code = vec![0; start];
+        self.encode(&mut code);
+        code.len() - start
+    }
+
+    // the first byte of the encoded instruction, 0xc4 for wide forms
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Nop => 0x00,
+            AConstNull => 0x01,
+            IConst(value) => (value + 0x03) as u8,
+            LConst(value) => 0x09 + *value as u8,
+            FConst(value) => 0x0b + *value as u8,
+            DConst(value) => 0x0e + *value as u8,
+            BIPush(_) => 0x10,
+            SIPush(_) => 0x11,
+            Ldc(_) => 0x12,
+            LdcW(_) => 0x13,
+            Ldc2W(_) => 0x14,
+            ILoad(local) | LLoad(local) | FLoad(local) | DLoad(local) | ALoad(local) | IStore(local) | LStore(local)
+            | FStore(local) | DStore(local) | AStore(local) | Ret(local) => {
+                let (byte_opcode, implicit_opcode) = self.local_opcodes();
+                match local {
+                    Local::Implicit(index) if implicit_opcode != byte_opcode => implicit_opcode + index,
+                    Local::Implicit(_) | Local::Byte(_) => byte_opcode,
+                    Local::Wide(_) => 0xc4,
+                }
+            }
+            IALoad => 0x2e,
+            LALoad => 0x2f,
+            FALoad => 0x30,
+            DALoad => 0x31,
+            AALoad => 0x32,
+            BALoad => 0x33,
+            CALoad => 0x34,
+            SALoad => 0x35,
+            IAStore => 0x4f,
+            LAStore => 0x50,
+            FAStore => 0x51,
+            DAStore => 0x52,
+            AAStore => 0x53,
+            BAStore => 0x54,
+            CAStore => 0x55,
+            SAStore => 0x56,
+            Pop => 0x57,
+            Pop2 => 0x58,
+            Dup => 0x59,
+            DupX1 => 0x5a,
+            DupX2 => 0x5b,
+            Dup2 => 0x5c,
+            Dup2X1 => 0x5d,
+            Dup2X2 => 0x5e,
+            Swap => 0x5f,
+            IAdd => 0x60,
+            LAdd => 0x61,
+            FAdd => 0x62,
+            DAdd => 0x63,
+            ISub => 0x64,
+            LSub => 0x65,
+            FSub => 0x66,
+            DSub => 0x67,
+            IMul => 0x68,
+            LMul => 0x69,
+            FMul => 0x6a,
+            DMul => 0x6b,
+            IDiv => 0x6c,
+            LDiv => 0x6d,
+            FDiv => 0x6e,
+            DDiv => 0x6f,
+            IRem => 0x70,
+            LRem => 0x71,
+            FRem => 0x72,
+            DRem => 0x73,
+            INeg => 0x74,
+            LNeg => 0x75,
+            FNeg => 0x76,
+            DNeg => 0x77,
+            IShl => 0x78,
+            LShl => 0x79,
+            IShr => 0x7a,
+            LShr => 0x7b,
+            IUShr => 0x7c,
+            LUShr => 0x7d,
+            IAnd => 0x7e,
+            LAnd => 0x7f,
+            IOr => 0x80,
+            LOr => 0x81,
+            IXor => 0x82,
+            LXor => 0x83,
+            IInc { local: Local::Wide(_), .. } => 0xc4,
+            IInc { .. } => 0x84,
+            I2L => 0x85,
+            I2F => 0x86,
+            I2D => 0x87,
+            L2I => 0x88,
+            L2F => 0x89,
+            L2D => 0x8a,
+            F2I => 0x8b,
+            F2L => 0x8c,
+            F2D => 0x8d,
+            D2I => 0x8e,
+            D2L => 0x8f,
+            D2F => 0x90,
+            I2B => 0x91,
+            I2C => 0x92,
+            I2S => 0x93,
+            LCmp => 0x94,
+            FCmpL => 0x95,
+            FCmpG => 0x96,
+            DCmpL => 0x97,
+            DCmpG => 0x98,
+            IfEq(_) => 0x99,
+            IfNe(_) => 0x9a,
+            IfLt(_) => 0x9b,
+            IfGe(_) => 0x9c,
+            IfGt(_) => 0x9d,
+            IfLe(_) => 0x9e,
+            IfICmpEq(_) => 0x9f,
+            IfICmpNe(_) => 0xa0,
+            IfICmpLt(_) => 0xa1,
+            IfICmpGe(_) => 0xa2,
+            IfICmpGt(_) => 0xa3,
+            IfICmpLe(_) => 0xa4,
+            IfACmpEq(_) => 0xa5,
+            IfACmpNe(_) => 0xa6,
+            Goto(_) => 0xa7,
+            Jsr(_) => 0xa8,
+            TableSwitch { .. } => 0xaa,
+            LookupSwitch { .. } => 0xab,
+            IReturn => 0xac,
+            LReturn => 0xad,
+            FReturn => 0xae,
+            DReturn => 0xaf,
+            AReturn => 0xb0,
+            Return => 0xb1,
+            GetStatic(_) => 0xb2,
+            PutStatic(_) => 0xb3,
+            GetField(_) => 0xb4,
+            PutField(_) => 0xb5,
+            InvokeVirtual(_) => 0xb6,
+            InvokeSpecial(_) => 0xb7,
+            InvokeStatic(_) => 0xb8,
+            InvokeInterface { .. } => 0xb9,
+            InvokeDynamic(_) => 0xba,
+            New(_) => 0xbb,
+            NewArray(_) => 0xbc,
+            ANewArray(_) => 0xbd,
+            ArrayLength => 0xbe,
+            AThrow => 0xbf,
+            CheckCast(_) => 0xc0,
+            InstanceOf(_) => 0xc1,
+            MonitorEnter => 0xc2,
+            MonitorExit => 0xc3,
+            MultiANewArray { .. } => 0xc5,
+            IfNull(_) => 0xc6,
+            IfNonNull(_) => 0xc7,
+            GotoW(_) => 0xc8,
+            JsrW(_) => 0xc9,
+        }
+    }
+
+    // (opcode taking an index byte, opcode of the _0 form) of instructions with a local variable operand;
+    // both are the same for ret, which has no _n forms
+    fn local_opcodes(&self) -> (u8, u8) {
+        match self {
+            ILoad(_) => (0x15, 0x1a),
+            LLoad(_) => (0x16, 0x1e),
+            FLoad(_) => (0x17, 0x22),
+            DLoad(_) => (0x18, 0x26),
+            ALoad(_) => (0x19, 0x2a),
+            IStore(_) => (0x36, 0x3b),
+            LStore(_) => (0x37, 0x3f),
+            FStore(_) => (0x38, 0x43),
+            DStore(_) => (0x39, 0x47),
+            AStore(_) => (0x3a, 0x4b),
+            _ => (0xa9, 0xa9),
+        }
+    }
+}
+
+// iload, lload, fload, dload, aload order
+fn load(kind: u8, local: Local) -> Instruction {
+    match kind {
+        0 => ILoad(local),
+        1 => LLoad(local),
+        2 => FLoad(local),
+        3 => DLoad(local),
+        _ => ALoad(local),
+    }
+}
+
+fn store(kind: u8, local: Local) -> Instruction {
+    match kind {
+        0 => IStore(local),
+        1 => LStore(local),
+        2 => FStore(local),
+        3 => DStore(local),
+        _ => AStore(local),
+    }
+}
+
+// zero bytes after a switch opcode at `pc` so its operands start at a multiple of 4
+fn padding(pc: usize) -> usize {
+    (4 - (pc + 1) % 4) % 4
+}
@@ -0,0 +1,236 @@
+use std::fmt;
+
+use crate::error::Error;
+
+use super::codec::decode_code;
+use super::Instruction::{self, *};
+use super::{ArrayType, Local};
+
+// one instruction per line, prefixed with its pc
+pub fn disassemble(code: &[u8]) -> Result<String, Error> {
+    let lines: Vec<String> = decode_code(code)?
+        .iter()
+        .map(|(pc, instruction)| format!("{:>5}: {}", pc, instruction))
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.mnemonic();
+        match self {
+            IConst(-1) => write!(f, "iconst_m1"),
+            IConst(value) => write!(f, "iconst_{}", value),
+            LConst(value) => write!(f, "lconst_{}", value),
+            FConst(value) => write!(f, "fconst_{}", value),
+            DConst(value) => write!(f, "dconst_{}", value),
+            BIPush(value) => write!(f, "{} {}", name, value),
+            SIPush(value) => write!(f, "{} {}", name, value),
+            Ldc(index) => write!(f, "{} #{}", name, index),
+            LdcW(index) | Ldc2W(index) | GetStatic(index) | PutStatic(index) | GetField(index) | PutField(index)
+            | InvokeVirtual(index) | InvokeSpecial(index) | InvokeStatic(index) | InvokeDynamic(index) | New(index)
+            | ANewArray(index) | CheckCast(index) | InstanceOf(index) => write!(f, "{} #{}", name, index),
+            ILoad(local) | LLoad(local) | FLoad(local) | DLoad(local) | ALoad(local) | IStore(local) | LStore(local)
+            | FStore(local) | DStore(local) | AStore(local) | Ret(local) => match local {
+                Local::Implicit(index) if !matches!(self, Ret(_)) => write!(f, "{}_{}", name, index),
+                Local::Implicit(index) | Local::Byte(index) => write!(f, "{} {}", name, index),
+                Local::Wide(index) => write!(f, "wide {} {}", name, index),
+            },
+            IInc { local: Local::Wide(index), value } => write!(f, "wide {} {}, {}", name, index, value),
+            IInc { local, value } => write!(f, "{} {}, {}", name, local.index(), value),
+            IfEq(offset) | IfNe(offset) | IfLt(offset) | IfGe(offset) | IfGt(offset) | IfLe(offset) | IfICmpEq(offset)
+            | IfICmpNe(offset) | IfICmpLt(offset) | IfICmpGe(offset) | IfICmpGt(offset) | IfICmpLe(offset)
+            | IfACmpEq(offset) | IfACmpNe(offset) | Goto(offset) | Jsr(offset) | IfNull(offset) | IfNonNull(offset) => {
+                write!(f, "{} {:+}", name, offset)
+            }
+            GotoW(offset) | JsrW(offset) => write!(f, "{} {:+}", name, offset),
+            TableSwitch { default, low, high, offsets } => {
+                let offsets: Vec<String> = offsets.iter().map(|offset| format!("{:+}", offset)).collect();
+                write!(f, "{} {}..{} [{}] default {:+}", name, low, high, offsets.join(", "), default)
+            }
+            LookupSwitch { default, pairs } => {
+                let pairs: Vec<String> = pairs.iter().map(|(key, offset)| format!("{}: {:+}", key, offset)).collect();
+                write!(f, "{} {{{}}} default {:+}", name, pairs.join(", "), default)
+            }
+            InvokeInterface { index, count } => write!(f, "{} #{}, {}", name, index, count),
+            NewArray(array_type) => write!(f, "{} {}", name, array_type),
+            MultiANewArray { index, dimensions } => write!(f, "{} #{}, {}", name, index, dimensions),
+            _ => write!(f, "{}", name),
+        }
+    }
+}
+
+impl fmt::Display for ArrayType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ArrayType::Boolean => "boolean",
+            ArrayType::Char => "char",
+            ArrayType::Float => "float",
+            ArrayType::Double => "double",
+            ArrayType::Byte => "byte",
+            ArrayType::Short => "short",
+            ArrayType::Int => "int",
+            ArrayType::Long => "long",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Instruction {
+    // the name used by the JVM specification, without the _n suffix of the short forms
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Nop => "nop",
+            AConstNull => "aconst_null",
+            IConst(_) => "iconst",
+            LConst(_) => "lconst",
+            FConst(_) => "fconst",
+            DConst(_) => "dconst",
+            BIPush(_) => "bipush",
+            SIPush(_) => "sipush",
+            Ldc(_) => "ldc",
+            LdcW(_) => "ldc_w",
+            Ldc2W(_) => "ldc2_w",
+            ILoad(_) => "iload",
+            LLoad(_) => "lload",
+            FLoad(_) => "fload",
+            DLoad(_) => "dload",
+            ALoad(_) => "aload",
+            IALoad => "iaload",
+            LALoad => "laload",
+            FALoad => "faload",
+            DALoad => "daload",
+            AALoad => "aaload",
+            BALoad => "baload",
+            CALoad => "caload",
+            SALoad => "saload",
+            IStore(_) => "istore",
+            LStore(_) => "lstore",
+            FStore(_) => "fstore",
+            DStore(_) => "dstore",
+            AStore(_) => "astore",
+            IAStore => "iastore",
+            LAStore => "lastore",
+            FAStore => "fastore",
+            DAStore => "dastore",
+            AAStore => "aastore",
+            BAStore => "bastore",
+            CAStore => "castore",
+            SAStore => "sastore",
+            Pop => "pop",
+            Pop2 => "pop2",
+            Dup => "dup",
+            DupX1 => "dup_x1",
+            DupX2 => "dup_x2",
+            Dup2 => "dup2",
+            Dup2X1 => "dup2_x1",
+            Dup2X2 => "dup2_x2",
+            Swap => "swap",
+            IAdd => "iadd",
+            LAdd => "ladd",
+            FAdd => "fadd",
+            DAdd => "dadd",
+            ISub => "isub",
+            LSub => "lsub",
+            FSub => "fsub",
+            DSub => "dsub",
+            IMul => "imul",
+            LMul => "lmul",
+            FMul => "fmul",
+            DMul => "dmul",
+            IDiv => "idiv",
+            LDiv => "ldiv",
+            FDiv => "fdiv",
+            DDiv => "ddiv",
+            IRem => "irem",
+            LRem => "lrem",
+            FRem => "frem",
+            DRem => "drem",
+            INeg => "ineg",
+            LNeg => "lneg",
+            FNeg => "fneg",
+            DNeg => "dneg",
+            IShl => "ishl",
+            LShl => "lshl",
+            IShr => "ishr",
+            LShr => "lshr",
+            IUShr => "iushr",
+            LUShr => "lushr",
+            IAnd => "iand",
+            LAnd => "land",
+            IOr => "ior",
+            LOr => "lor",
+            IXor => "ixor",
+            LXor => "lxor",
+            IInc { .. } => "iinc",
+            I2L => "i2l",
+            I2F => "i2f",
+            I2D => "i2d",
+            L2I => "l2i",
+            L2F => "l2f",
+            L2D => "l2d",
+            F2I => "f2i",
+            F2L => "f2l",
+            F2D => "f2d",
+            D2I => "d2i",
+            D2L => "d2l",
+            D2F => "d2f",
+            I2B => "i2b",
+            I2C => "i2c",
+            I2S => "i2s",
+            LCmp => "lcmp",
+            FCmpL => "fcmpl",
+            FCmpG => "fcmpg",
+            DCmpL => "dcmpl",
+            DCmpG => "dcmpg",
+            IfEq(_) => "ifeq",
+            IfNe(_) => "ifne",
+            IfLt(_) => "iflt",
+            IfGe(_) => "ifge",
+            IfGt(_) => "ifgt",
+            IfLe(_) => "ifle",
+            IfICmpEq(_) => "if_icmpeq",
+            IfICmpNe(_) => "if_icmpne",
+            IfICmpLt(_) => "if_icmplt",
+            IfICmpGe(_) => "if_icmpge",
+            IfICmpGt(_) => "if_icmpgt",
+            IfICmpLe(_) => "if_icmple",
+            IfACmpEq(_) => "if_acmpeq",
+            IfACmpNe(_) => "if_acmpne",
+            Goto(_) => "goto",
+            Jsr(_) => "jsr",
+            Ret(_) => "ret",
+            TableSwitch { .. } => "tableswitch",
+            LookupSwitch { .. } => "lookupswitch",
+            IReturn => "ireturn",
+            LReturn => "lreturn",
+            FReturn => "freturn",
+            DReturn => "dreturn",
+            AReturn => "areturn",
+            Return => "return",
+            GetStatic(_) => "getstatic",
+            PutStatic(_) => "putstatic",
+            GetField(_) => "getfield",
+            PutField(_) => "putfield",
+            InvokeVirtual(_) => "invokevirtual",
+            InvokeSpecial(_) => "invokespecial",
+            InvokeStatic(_) => "invokestatic",
+            InvokeInterface { .. } => "invokeinterface",
+            InvokeDynamic(_) => "invokedynamic",
+            New(_) => "new",
+            NewArray(_) => "newarray",
+            ANewArray(_) => "anewarray",
+            ArrayLength => "arraylength",
+            AThrow => "athrow",
+            CheckCast(_) => "checkcast",
+            InstanceOf(_) => "instanceof",
+            MonitorEnter => "monitorenter",
+            MonitorExit => "monitorexit",
+            MultiANewArray { .. } => "multianewarray",
+            IfNull(_) => "ifnull",
+            IfNonNull(_) => "ifnonnull",
+            GotoW(_) => "goto_w",
+            JsrW(_) => "jsr_w",
+        }
+    }
+}
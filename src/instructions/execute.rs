@@ -0,0 +1,464 @@
+use std::cmp::Ordering;
+
+use crate::classfile::constant_pool::ConstantInfo;
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{Frame, OperandStack, Slot};
+
+use super::Instruction::{self, *};
+
+// what the interpreter loop does after an instruction; everything that touches other frames,
+// the class loader or the heap is left to it
+pub enum Flow {
+    Next,
+    Invoke(InvokeKind, u16),
+    // the slots of the return value, none for a void return
+    Return(Vec<Slot>),
+    // athrow, None when the reference thrown is null
+    Throw(Option<ObjectRef>),
+    // an exception the instruction itself raises, e.g. java/lang/ArithmeticException
+    Raise { class_name: &'static str, message: Option<String> },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvokeKind {
+    Virtual,
+    Special,
+    Static,
+    Interface,
+}
+
+impl Instruction {
+    pub fn execute(&self, frame: &mut Frame) -> Flow {
+        let stack = &mut frame.operand_stack;
+        let locals = &mut frame.local_vars;
+        let mut branch_offset = None;
+        match self {
+            Nop => {}
+            AConstNull => stack.push_ref(None),
+            IConst(value) => stack.push_int(*value),
+            LConst(value) => stack.push_long(*value),
+            FConst(value) => stack.push_float(*value),
+            DConst(value) => stack.push_double(*value),
+            BIPush(value) => stack.push_int(*value as i32),
+            SIPush(value) => stack.push_int(*value as i32),
+            // numeric constants only, the interpreter loads strings; classes need the heap
+            Ldc(index) => push_constant(stack, &frame.class.classfile.constant_pool, *index as u16, self),
+            LdcW(index) | Ldc2W(index) => push_constant(stack, &frame.class.classfile.constant_pool, *index, self),
+
+            ILoad(local) => stack.push_int(locals.get_int(local.index())),
+            LLoad(local) => stack.push_long(locals.get_long(local.index())),
+            FLoad(local) => stack.push_float(locals.get_float(local.index())),
+            DLoad(local) => stack.push_double(locals.get_double(local.index())),
+            ALoad(local) => stack.push_ref(locals.get_ref(local.index())),
+            IStore(local) => locals.set_int(local.index(), stack.pop_int()),
+            LStore(local) => locals.set_long(local.index(), stack.pop_long()),
+            FStore(local) => locals.set_float(local.index(), stack.pop_float()),
+            DStore(local) => locals.set_double(local.index(), stack.pop_double()),
+            AStore(local) => locals.set_ref(local.index(), stack.pop_ref()),
+
+            Pop => {
+                stack.pop_slot();
+            }
+            Pop2 => {
+                stack.pop_slot();
+                stack.pop_slot();
+            }
+            Dup => {
+                let slot1 = stack.pop_slot();
+                stack.push_slot(slot1.clone());
+                stack.push_slot(slot1);
+            }
+            DupX1 => {
+                let slot1 = stack.pop_slot();
+                let slot2 = stack.pop_slot();
+                stack.push_slot(slot1.clone());
+                stack.push_slot(slot2);
+                stack.push_slot(slot1);
+            }
+            DupX2 => {
+                let slot1 = stack.pop_slot();
+                let slot2 = stack.pop_slot();
+                let slot3 = stack.pop_slot();
+                stack.push_slot(slot1.clone());
+                stack.push_slot(slot3);
+                stack.push_slot(slot2);
+                stack.push_slot(slot1);
+            }
+            Dup2 => {
+                let slot1 = stack.pop_slot();
+                let slot2 = stack.pop_slot();
+                stack.push_slot(slot2.clone());
+                stack.push_slot(slot1.clone());
+                stack.push_slot(slot2);
+                stack.push_slot(slot1);
+            }
+            Dup2X1 => {
+                let slot1 = stack.pop_slot();
+                let slot2 = stack.pop_slot();
+                let slot3 = stack.pop_slot();
+                stack.push_slot(slot2.clone());
+                stack.push_slot(slot1.clone());
+                stack.push_slot(slot3);
+                stack.push_slot(slot2);
+                stack.push_slot(slot1);
+            }
+            Dup2X2 => {
+                let slot1 = stack.pop_slot();
+                let slot2 = stack.pop_slot();
+                let slot3 = stack.pop_slot();
+                let slot4 = stack.pop_slot();
+                stack.push_slot(slot2.clone());
+                stack.push_slot(slot1.clone());
+                stack.push_slot(slot4);
+                stack.push_slot(slot3);
+                stack.push_slot(slot2);
+                stack.push_slot(slot1);
+            }
+            Swap => {
+                let slot1 = stack.pop_slot();
+                let slot2 = stack.pop_slot();
+                stack.push_slot(slot1);
+                stack.push_slot(slot2);
+            }
+
+            // the second operand is on top of the stack
+            IAdd => {
+                let (a, b) = (stack.pop_int(), stack.pop_int());
+                stack.push_int(b.wrapping_add(a))
+            }
+            LAdd => {
+                let (a, b) = (stack.pop_long(), stack.pop_long());
+                stack.push_long(b.wrapping_add(a))
+            }
+            FAdd => {
+                let (a, b) = (stack.pop_float(), stack.pop_float());
+                stack.push_float(b + a)
+            }
+            DAdd => {
+                let (a, b) = (stack.pop_double(), stack.pop_double());
+                stack.push_double(b + a)
+            }
+            ISub => {
+                let (a, b) = (stack.pop_int(), stack.pop_int());
+                stack.push_int(b.wrapping_sub(a))
+            }
+            LSub => {
+                let (a, b) = (stack.pop_long(), stack.pop_long());
+                stack.push_long(b.wrapping_sub(a))
+            }
+            FSub => {
+                let (a, b) = (stack.pop_float(), stack.pop_float());
+                stack.push_float(b - a)
+            }
+            DSub => {
+                let (a, b) = (stack.pop_double(), stack.pop_double());
+                stack.push_double(b - a)
+            }
+            IMul => {
+                let (a, b) = (stack.pop_int(), stack.pop_int());
+                stack.push_int(b.wrapping_mul(a))
+            }
+            LMul => {
+                let (a, b) = (stack.pop_long(), stack.pop_long());
+                stack.push_long(b.wrapping_mul(a))
+            }
+            FMul => {
+                let (a, b) = (stack.pop_float(), stack.pop_float());
+                stack.push_float(b * a)
+            }
+            DMul => {
+                let (a, b) = (stack.pop_double(), stack.pop_double());
+                stack.push_double(b * a)
+            }
+            IDiv => {
+                let (a, b) = (stack.pop_int(), stack.pop_int());
+                if a == 0 {
+                    return divide_by_zero();
+                }
+                stack.push_int(b.wrapping_div(a))
+            }
+            LDiv => {
+                let (a, b) = (stack.pop_long(), stack.pop_long());
+                if a == 0 {
+                    return divide_by_zero();
+                }
+                stack.push_long(b.wrapping_div(a))
+            }
+            FDiv => {
+                let (a, b) = (stack.pop_float(), stack.pop_float());
+                stack.push_float(b / a)
+            }
+            DDiv => {
+                let (a, b) = (stack.pop_double(), stack.pop_double());
+                stack.push_double(b / a)
+            }
+            IRem => {
+                let (a, b) = (stack.pop_int(), stack.pop_int());
+                if a == 0 {
+                    return divide_by_zero();
+                }
+                stack.push_int(b.wrapping_rem(a))
+            }
+            LRem => {
+                let (a, b) = (stack.pop_long(), stack.pop_long());
+                if a == 0 {
+                    return divide_by_zero();
+                }
+                stack.push_long(b.wrapping_rem(a))
+            }
+            FRem => {
+                let (a, b) = (stack.pop_float(), stack.pop_float());
+                stack.push_float(b % a)
+            }
+            DRem => {
+                let (a, b) = (stack.pop_double(), stack.pop_double());
+                stack.push_double(b % a)
+            }
+            INeg => {
+                let value = stack.pop_int();
+                stack.push_int(value.wrapping_neg())
+            }
+            LNeg => {
+                let value = stack.pop_long();
+                stack.push_long(value.wrapping_neg())
+            }
+            FNeg => {
+                let value = stack.pop_float();
+                stack.push_float(-value)
+            }
+            DNeg => {
+                let value = stack.pop_double();
+                stack.push_double(-value)
+            }
+            // shift distances only use the low 5 (int) or 6 (long) bits
+            IShl => {
+                let (shift, value) = (stack.pop_int(), stack.pop_int());
+                stack.push_int(value.wrapping_shl(shift as u32))
+            }
+            LShl => {
+                let (shift, value) = (stack.pop_int(), stack.pop_long());
+                stack.push_long(value.wrapping_shl(shift as u32))
+            }
+            IShr => {
+                let (shift, value) = (stack.pop_int(), stack.pop_int());
+                stack.push_int(value.wrapping_shr(shift as u32))
+            }
+            LShr => {
+                let (shift, value) = (stack.pop_int(), stack.pop_long());
+                stack.push_long(value.wrapping_shr(shift as u32))
+            }
+            IUShr => {
+                let (shift, value) = (stack.pop_int(), stack.pop_int());
+                stack.push_int((value as u32).wrapping_shr(shift as u32) as i32)
+            }
+            LUShr => {
+                let (shift, value) = (stack.pop_int(), stack.pop_long());
+                stack.push_long((value as u64).wrapping_shr(shift as u32) as i64)
+            }
+            IAnd => {
+                let (a, b) = (stack.pop_int(), stack.pop_int());
+                stack.push_int(b & a)
+            }
+            LAnd => {
+                let (a, b) = (stack.pop_long(), stack.pop_long());
+                stack.push_long(b & a)
+            }
+            IOr => {
+                let (a, b) = (stack.pop_int(), stack.pop_int());
+                stack.push_int(b | a)
+            }
+            LOr => {
+                let (a, b) = (stack.pop_long(), stack.pop_long());
+                stack.push_long(b | a)
+            }
+            IXor => {
+                let (a, b) = (stack.pop_int(), stack.pop_int());
+                stack.push_int(b ^ a)
+            }
+            LXor => {
+                let (a, b) = (stack.pop_long(), stack.pop_long());
+                stack.push_long(b ^ a)
+            }
+            IInc { local, value } => {
+                let index = local.index();
+                let current = locals.get_int(index);
+                locals.set_int(index, current.wrapping_add(*value as i32))
+            }
+
+            // `as` saturates and maps NaN to 0, which is what the JVM does for float to integer conversions
+            I2L => {
+                let value = stack.pop_int();
+                stack.push_long(value as i64)
+            }
+            I2F => {
+                let value = stack.pop_int();
+                stack.push_float(value as f32)
+            }
+            I2D => {
+                let value = stack.pop_int();
+                stack.push_double(value as f64)
+            }
+            L2I => {
+                let value = stack.pop_long();
+                stack.push_int(value as i32)
+            }
+            L2F => {
+                let value = stack.pop_long();
+                stack.push_float(value as f32)
+            }
+            L2D => {
+                let value = stack.pop_long();
+                stack.push_double(value as f64)
+            }
+            F2I => {
+                let value = stack.pop_float();
+                stack.push_int(value as i32)
+            }
+            F2L => {
+                let value = stack.pop_float();
+                stack.push_long(value as i64)
+            }
+            F2D => {
+                let value = stack.pop_float();
+                stack.push_double(value as f64)
+            }
+            D2I => {
+                let value = stack.pop_double();
+                stack.push_int(value as i32)
+            }
+            D2L => {
+                let value = stack.pop_double();
+                stack.push_long(value as i64)
+            }
+            D2F => {
+                let value = stack.pop_double();
+                stack.push_float(value as f32)
+            }
+            I2B => {
+                let value = stack.pop_int();
+                stack.push_int(value as i8 as i32)
+            }
+            I2C => {
+                let value = stack.pop_int();
+                stack.push_int(value as u16 as i32)
+            }
+            I2S => {
+                let value = stack.pop_int();
+                stack.push_int(value as i16 as i32)
+            }
+
+            LCmp => {
+                let (a, b) = (stack.pop_long(), stack.pop_long());
+                stack.push_int(compare(b.partial_cmp(&a), 0))
+            }
+            FCmpL | FCmpG => {
+                let (a, b) = (stack.pop_float(), stack.pop_float());
+                stack.push_int(compare(b.partial_cmp(&a), if matches!(self, FCmpG) { 1 } else { -1 }))
+            }
+            DCmpL | DCmpG => {
+                let (a, b) = (stack.pop_double(), stack.pop_double());
+                stack.push_int(compare(b.partial_cmp(&a), if matches!(self, DCmpG) { 1 } else { -1 }))
+            }
+            IfEq(offset) | IfNe(offset) | IfLt(offset) | IfGe(offset) | IfGt(offset) | IfLe(offset) => {
+                let value = stack.pop_int();
+                let taken = match self {
+                    IfEq(_) => value == 0,
+                    IfNe(_) => value != 0,
+                    IfLt(_) => value < 0,
+                    IfGe(_) => value >= 0,
+                    IfGt(_) => value > 0,
+                    _ => value <= 0,
+                };
+                if taken {
+                    branch_offset = Some(*offset as i32);
+                }
+            }
+            IfICmpEq(offset) | IfICmpNe(offset) | IfICmpLt(offset) | IfICmpGe(offset) | IfICmpGt(offset)
+            | IfICmpLe(offset) => {
+                let (a, b) = (stack.pop_int(), stack.pop_int());
+                let taken = match self {
+                    IfICmpEq(_) => b == a,
+                    IfICmpNe(_) => b != a,
+                    IfICmpLt(_) => b < a,
+                    IfICmpGe(_) => b >= a,
+                    IfICmpGt(_) => b > a,
+                    _ => b <= a,
+                };
+                if taken {
+                    branch_offset = Some(*offset as i32);
+                }
+            }
+            IfACmpEq(offset) | IfACmpNe(offset) => {
+                let same = stack.pop_ref() == stack.pop_ref();
+                if same == matches!(self, IfACmpEq(_)) {
+                    branch_offset = Some(*offset as i32);
+                }
+            }
+            IfNull(offset) | IfNonNull(offset) => {
+                let reference = stack.pop_ref();
+                if reference.is_none() == matches!(self, IfNull(_)) {
+                    branch_offset = Some(*offset as i32);
+                }
+            }
+
+            Goto(offset) => branch_offset = Some(*offset as i32),
+            GotoW(offset) => branch_offset = Some(*offset),
+            TableSwitch { default, low, high, offsets } => {
+                let index = stack.pop_int();
+                let offset = if (*low..=*high).contains(&index) {
+                    offsets[(index as i64 - *low as i64) as usize]
+                } else {
+                    *default
+                };
+                branch_offset = Some(offset);
+            }
+            LookupSwitch { default, pairs } => {
+                let key = stack.pop_int();
+                let offset = pairs.iter().find(|(k, _)| *k == key).map(|(_, offset)| *offset).unwrap_or(*default);
+                branch_offset = Some(offset);
+            }
+
+            IReturn | FReturn | AReturn => return Flow::Return(stack.pop_slots(1)),
+            LReturn | DReturn => return Flow::Return(stack.pop_slots(2)),
+            Return => return Flow::Return(Vec::new()),
+            InvokeVirtual(index) => return Flow::Invoke(InvokeKind::Virtual, *index),
+            InvokeSpecial(index) => return Flow::Invoke(InvokeKind::Special, *index),
+            InvokeStatic(index) => return Flow::Invoke(InvokeKind::Static, *index),
+            InvokeInterface { index, .. } => return Flow::Invoke(InvokeKind::Interface, *index),
+            AThrow => return Flow::Throw(stack.pop_ref()),
+
+            _ => panic!("unsupported instruction: {}", self),
+        }
+        if let Some(offset) = branch_offset {
+            frame.next_pc = frame.pc + offset;
+        }
+        Flow::Next
+    }
+}
+
+fn push_constant(stack: &mut OperandStack, constant_pool: &[ConstantInfo], index: u16, instruction: &Instruction) {
+    match constant_pool.get(index as usize) {
+        Some(ConstantInfo::ConstantInteger { value }) => stack.push_int(*value as i32),
+        Some(ConstantInfo::ConstantFloat { value }) => stack.push_float(f32::from_bits(*value)),
+        Some(ConstantInfo::ConstantLong { value }) => stack.push_long(*value as i64),
+        Some(ConstantInfo::ConstantDouble { value }) => stack.push_double(f64::from_bits(*value)),
+        _ => panic!("unsupported instruction: {}", instruction),
+    }
+}
+
+fn divide_by_zero() -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/ArithmeticException",
+        message: Some(String::from("/ by zero")),
+    }
+}
+
+// -1, 0 or 1 like the JVM compare instructions, `unordered` is the result when a NaN is involved
+fn compare(ordering: Option<Ordering>, unordered: i32) -> i32 {
+    match ordering {
+        Some(Ordering::Less) => -1,
+        Some(Ordering::Equal) => 0,
+        Some(Ordering::Greater) => 1,
+        None => unordered,
+    }
+}
@@ -0,0 +1,91 @@
+use super::codec::{decode_code, encode_code};
+use super::Instruction::{self, *};
+use super::Local;
+
+// decodes `code`, checks every instruction's pc and length and that encoding gives back the same bytes
+fn round_trip(code: &[u8]) -> Vec<Instruction> {
+    let decoded = decode_code(code).unwrap();
+    let mut pc = 0;
+    for (start, instruction) in &decoded {
+        assert_eq!(*start, pc, "{}", instruction);
+        pc += instruction.length(pc);
+    }
+    assert_eq!(pc, code.len());
+    let instructions: Vec<Instruction> = decoded.into_iter().map(|(_, instruction)| instruction).collect();
+    assert_eq!(encode_code(&instructions), code);
+    instructions
+}
+
+#[test]
+fn wide_forms_survive_a_round_trip() {
+    let code = [
+        0xc4, 0x15, 0x01, 0x00, // wide iload 256
+        0xc4, 0x39, 0x00, 0x02, // wide dstore 2, a wide form of an index that fits a byte
+        0xc4, 0x84, 0x01, 0x2c, 0xfe, 0x0c, // wide iinc 300, -500
+        0x84, 0x03, 0xff, // iinc 3, -1
+        0x15, 0x02, // iload 2, not iload_2
+        0x1c, // iload_2
+        0xc4, 0xa9, 0xff, 0xff, // wide ret 65535
+    ];
+    let instructions = round_trip(&code);
+    assert_eq!(instructions, vec![
+        ILoad(Local::Wide(256)),
+        DStore(Local::Wide(2)),
+        IInc { local: Local::Wide(300), value: -500 },
+        IInc { local: Local::Byte(3), value: -1 },
+        ILoad(Local::Byte(2)),
+        ILoad(Local::Implicit(2)),
+        Ret(Local::Wide(65535)),
+    ]);
+}
+
+#[test]
+fn switches_are_padded_to_a_multiple_of_four_at_every_alignment() {
+    for start in 0..4 {
+        // `start` nops put the switch opcode at pc `start`
+        let mut code = vec![0x00; start];
+        code.push(0xaa);
+        code.resize(code.len() + (4 - (start + 1) % 4) % 4, 0);
+        for value in [40, -1, 1, 20, 24, 28] {
+            code.extend_from_slice(&i32::to_be_bytes(value));
+        }
+        let lookup = code.len();
+        code.push(0xab);
+        code.resize(code.len() + (4 - (lookup + 1) % 4) % 4, 0);
+        for value in [-(lookup as i32), 2, -7, 12, 1000, 16] {
+            code.extend_from_slice(&i32::to_be_bytes(value));
+        }
+        code.push(0xb1);
+        let instructions = round_trip(&code);
+        assert_eq!(instructions[start], TableSwitch { default: 40, low: -1, high: 1, offsets: vec![20, 24, 28] });
+        let pairs = vec![(-7, 12), (1000, 16)];
+        assert_eq!(instructions[start + 1], LookupSwitch { default: -(lookup as i32), pairs });
+        assert_eq!(instructions[start].length(start), 1 + (4 - (start + 1) % 4) % 4 + 24);
+        // the length of a switch depends only on the alignment of its pc
+        assert_eq!(instructions[start].length(start + 4), instructions[start].length(start));
+    }
+}
+
+#[test]
+fn wide_branches_and_multi_byte_operands_survive_a_round_trip() {
+    let code = [
+        0xc8, 0x00, 0x01, 0x00, 0x00, // goto_w 65536
+        0xc9, 0xff, 0xff, 0xff, 0xfb, // jsr_w -5
+        0xa7, 0xff, 0xf6, // goto -10
+        0x10, 0x80, // bipush -128
+        0x11, 0x80, 0x00, // sipush -32768
+        0x12, 0x07, // ldc #7
+        0x14, 0x01, 0x02, // ldc2_w #258
+        0xb9, 0x00, 0x09, 0x02, 0x00, // invokeinterface #9, 2
+        0xba, 0x00, 0x0a, 0x00, 0x00, // invokedynamic #10
+        0xbc, 0x0a, // newarray int
+        0xc5, 0x00, 0x0b, 0x03, // multianewarray #11, 3
+        0x02, 0xb1, // iconst_m1; return
+    ];
+    let instructions = round_trip(&code);
+    assert_eq!(instructions[0], GotoW(65536));
+    assert_eq!(instructions[1], JsrW(-5));
+    assert_eq!(instructions[2], Goto(-10));
+    assert_eq!(&instructions[3..5], &[BIPush(-128), SIPush(-32768)]);
+    assert_eq!(instructions[7], InvokeInterface { index: 9, count: 2 });
+}
@@ -0,0 +1,1677 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::classfile::class_reader::{get_class_name, get_member_ref, get_utf8, ACC_VARARGS};
+use crate::classfile::constant_pool::ConstantInfo;
+use crate::classfile::descriptor::parse_method_descriptor;
+use crate::debugger::{Debugger, Event};
+use crate::error::Error;
+use crate::instructions::{Flow, Instruction, InvokeKind};
+use crate::jni::{self, Jni};
+use crate::log::{log, Logger};
+use crate::native::{Env, NativeRegistry, Output};
+use crate::runtime::call_site::{
+    spin_adapter, spin_invoker, AccessMode, Bootstrap, CallSite, MethodHandleRef, VarHandleRef, INVOKER,
+    REF_GET_FIELD, REF_GET_STATIC, REF_INVOKE_INTERFACE, REF_INVOKE_SPECIAL, REF_INVOKE_STATIC, REF_INVOKE_VIRTUAL,
+    REF_NEW_INVOKE_SPECIAL, REF_PUT_FIELD, REF_PUT_STATIC,
+};
+use crate::runtime::class::{Class, Field, InitState, Method};
+use crate::runtime::class_loader::ClassLoader;
+use crate::runtime::heap::{Heap, ObjectRef};
+use crate::runtime::scheduler::{Next, Scheduler, QUANTUM};
+use crate::runtime::string::StringTable;
+use crate::runtime::{Frame, LocalVars, Object, Slot, Thread, ThreadState};
+use crate::trace::Tracer;
+
+// the outcome of resolving a symbolic reference: Ok(Err(flow)) throws a linkage error into the program,
+// Err is a failure of the VM itself
+type Resolved<T> = Result<Result<T, Flow>, Error>;
+
+const METHOD_HANDLE: &str = "java/lang/invoke/MethodHandle";
+const VAR_HANDLE: &str = "java/lang/invoke/VarHandle";
+const WRONG_METHOD_TYPE: &str = "java/lang/invoke/WrongMethodTypeException";
+
+pub struct Interpreter {
+    loader: ClassLoader,
+    natives: NativeRegistry,
+    scheduler: Scheduler,
+    heap: Box<dyn Heap>,
+    strings: StringTable,
+    // the system properties the VM defines itself, like java.home
+    properties: Vec<(String, String)>,
+    output: Output,
+    // whether the JDK has been initialized, which happens once, on the first thread that runs
+    booted: bool,
+    // the adapters made for calls of signature polymorphic methods of another type than the handle's,
+    // by handle class, method and the two types
+    adapters: HashMap<String, (Rc<Class>, Rc<Method>)>,
+    // the invokers made for calls from outside of Java, by kind and method
+    invokers: HashMap<String, (Rc<Class>, Rc<Method>)>,
+    // the native libraries loaded and what their natives refer to
+    jni: Jni,
+    // -Xtrace and -Xprof
+    tracer: Tracer,
+    // --debug
+    debugger: Option<Box<dyn Debugger>>,
+    // the thread running the program's main method, and the class of the exception it ended with if it did
+    main_thread: Option<u64>,
+    uncaught_in_main: Option<String>,
+    // -Xlog, which the loader, the heap and the scheduler log to as well
+    logger: Rc<Logger>,
+}
+
+impl Interpreter {
+    pub fn new(
+        loader: ClassLoader,
+        stack_size: usize,
+        heap: Box<dyn Heap>,
+        properties: Vec<(String, String)>,
+        output: Output,
+        tracer: Tracer,
+        debugger: Option<Box<dyn Debugger>>,
+    ) -> Interpreter {
+        let logger = loader.logger().clone();
+        Interpreter {
+            natives: NativeRegistry::new(loader.is_builtin()),
+            loader,
+            scheduler: Scheduler::new(stack_size, logger.clone()),
+            heap,
+            strings: StringTable::default(),
+            properties,
+            output,
+            booted: false,
+            adapters: HashMap::new(),
+            invokers: HashMap::new(),
+            jni: Jni::default(),
+            tracer,
+            debugger,
+            main_thread: None,
+            uncaught_in_main: None,
+            logger,
+        }
+    }
+
+    pub fn loader(&self) -> &ClassLoader {
+        &self.loader
+    }
+
+    pub(crate) fn logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    pub(crate) fn jni(&mut self) -> &mut Jni {
+        &mut self.jni
+    }
+
+    // runs `method` on the main thread, with `args` as its String[] argument, until every non-daemon
+    // thread has terminated; it fails like the java launcher does when main ends with an exception or the threads
+    // deadlock
+    pub fn interpret(&mut self, class: Rc<Class>, method: Rc<Method>, args: &[String]) -> Result<(), Error> {
+        let main_class = class.clone();
+        self.scheduler.spawn(String::from("main"), false, None, Frame::new(class, method))?;
+        let Next::Run(mut main) = self.scheduler.next() else {
+            unreachable!("the main thread was just spawned");
+        };
+        self.main_thread = Some(main.id);
+        let mut env = self.env(&mut main);
+        let args = env.new_string_array(args).and_then(|args| {
+            env.thread.current_frame().expect("main without a frame").local_vars.set_ref(0, Some(args));
+            // like the java launcher, the main class is initialized before main runs, and after the JDK is
+            initialize_first(&mut env, &main_class)
+        });
+        match args {
+            Ok(()) => self.boot(&mut main)?,
+            Err(flow) => self.fail(&mut main, flow)?,
+        }
+        self.scheduler.park(main, 0);
+        let mut deadlocked = false;
+        while !deadlocked {
+            match self.scheduler.next() {
+                Next::Run(mut thread) => {
+                    let executed = self.run(&mut thread)?;
+                    self.scheduler.park(thread, executed);
+                }
+                Next::Shutdown => break,
+                Next::Deadlock(threads) => {
+                    self.report_deadlock(&threads);
+                    deadlocked = true;
+                }
+            }
+        }
+        self.heap.log_stats(&mut *self.output.stdout);
+        self.tracer.report(&mut *self.output.stderr);
+        if let Some(debugger) = &mut self.debugger {
+            debugger.exit();
+        }
+        if deadlocked {
+            return Err(Error::Deadlock);
+        }
+        self.uncaught_in_main.take().map_or(Ok(()), |class_name| Err(Error::UncaughtException(class_name)))
+    }
+
+    // a thread for the embedding API to call into Java on, see Thread::embedded
+    pub fn new_embedded_thread(&mut self, name: &str) -> Thread {
+        let mut thread = self.scheduler.new_thread(String::from(name));
+        thread.embedded = true;
+        thread
+    }
+
+    // has `thread` initialize the JDK first thing when nothing has yet, by pushing the frames that do it
+    pub fn boot(&mut self, thread: &mut Thread) -> Result<(), Error> {
+        if std::mem::replace(&mut self.booted, true) {
+            return Ok(());
+        }
+        log!(self.logger, Interp, Debug, "booting the class library on thread {}", thread.name);
+        match boot(&mut self.env(thread)) {
+            Ok(()) => Ok(()),
+            Err(flow) => self.fail(thread, flow),
+        }
+    }
+
+    // runs embedded `thread` until the frames pushed onto it have returned or thrown, the other threads
+    // taking their turns meanwhile, and gives it back; the error of a deadlock takes the threads with it
+    pub fn complete(&mut self, thread: Thread) -> Result<Thread, Error> {
+        let id = thread.id;
+        self.scheduler.resume(thread);
+        loop {
+            match self.scheduler.next() {
+                Next::Run(mut thread) => {
+                    let executed = self.run(&mut thread)?;
+                    let done = thread.id == id && thread.current_frame().is_none();
+                    self.scheduler.park(thread, executed);
+                    if done {
+                        return Ok(self.scheduler.detach(id).expect("the embedded thread is live"));
+                    }
+                }
+                Next::Shutdown => unreachable!("the embedded thread is no daemon"),
+                Next::Deadlock(threads) => {
+                    self.report_deadlock(&threads);
+                    return Err(Error::Deadlock);
+                }
+            }
+        }
+    }
+
+    // throws what a native's `flow` raised or threw into `thread`
+    pub fn fail(&mut self, thread: &mut Thread, flow: Flow) -> Result<(), Error> {
+        let exception = self.exception(thread, flow)?;
+        self.throw(thread, exception)
+    }
+
+    // the exception a native's `flow` raised or threw, made without throwing it
+    pub(crate) fn exception(&mut self, thread: &mut Thread, flow: Flow) -> Result<ObjectRef, Error> {
+        match flow {
+            Flow::Throw(Some(exception)) => Ok(exception),
+            Flow::Throw(None) => self.new_exception(thread, "java/lang/NullPointerException", None),
+            Flow::Raise { class_name, message } => self.new_exception(thread, class_name, message),
+            Flow::Next | Flow::Return(_) | Flow::Invoke(..) => unreachable!("not a failure"),
+        }
+    }
+
+    // the invoker for calls of `kind`, a REF_INVOKE kind, to `method` of `class`, made the first time;
+    // see spin_invoker
+    pub(crate) fn invoker(
+        &mut self,
+        kind: u8,
+        class: &Class,
+        method: &Method,
+    ) -> Result<(Rc<Class>, Rc<Method>), Error> {
+        let key = format!("{}:{}.{}{}", kind, class.name, method.name, method.descriptor);
+        if let Some(invoker) = self.invokers.get(&key) {
+            return Ok(invoker.clone());
+        }
+        let name = self.loader.unique_name(&format!("{}$$Invoker$", class.name));
+        let content = spin_invoker(&name, kind, &class.name, &method.name, &method.descriptor)
+            .expect("a loaded method has a valid descriptor");
+        let class = self.loader.define_class(&name, content)?;
+        let method = class.methods.iter().find(|method| method.name == INVOKER).cloned();
+        let invoker = (class.clone(), method.expect("an invoker without its method"));
+        self.invokers.insert(key, invoker.clone());
+        Ok(invoker)
+    }
+
+    // calls `method` of `class` the way `kind`, a REF_INVOKE kind, says for native code running on `thread`,
+    // with the receiver and arguments in `args`: the call runs to its end on top of the native's caller, the
+    // other threads waiting meanwhile, and gives what the method returned or threw
+    pub(crate) fn call_from_native(
+        &mut self,
+        thread: &mut Thread,
+        kind: u8,
+        class: &Class,
+        method: &Method,
+        args: Vec<Slot>,
+    ) -> Result<Result<Vec<Slot>, ObjectRef>, Error> {
+        let (invoker, invoke) = self.invoker(kind, class, method)?;
+        let mut frame = Frame::new(invoker, invoke);
+        for (index, slot) in args.into_iter().enumerate() {
+            frame.local_vars.0[index] = slot;
+        }
+        frame.returns_to_native = true;
+        if thread.push_frame(frame).is_err() {
+            return self.exception(thread, stack_overflow()).map(Err);
+        }
+        self.run_from_native(thread)
+    }
+
+    // has the initializers of `class` run for native code, like call_from_native
+    pub(crate) fn initialize_from_native(
+        &mut self,
+        thread: &mut Thread,
+        class: &Rc<Class>,
+    ) -> Result<Result<(), ObjectRef>, Error> {
+        match push_initializers(thread, class, true) {
+            Ok(Some(0)) => Ok(Ok(())),
+            Ok(Some(_)) => Ok(self.run_from_native(thread)?.map(|_| ())),
+            Ok(None) => {
+                let message = String::from("a call from native code cannot wait for another thread");
+                self.exception(thread, raise("java/lang/InternalError", message)).map(Err)
+            }
+            Err(flow) => self.exception(thread, flow).map(Err),
+        }
+    }
+
+    // runs `thread` until the frame native code called returns or throws
+    fn run_from_native(&mut self, thread: &mut Thread) -> Result<Result<Vec<Slot>, ObjectRef>, Error> {
+        loop {
+            self.run(thread)?;
+            if let Some(outcome) = thread.outcome.take() {
+                return Ok(outcome);
+            }
+            match thread.state {
+                ThreadState::Runnable => {}
+                // with the other threads held up until the native returns and time standing still, nothing but
+                // a spurious wakeup can end a sleep or a wait
+                ThreadState::Sleeping { .. } | ThreadState::Waiting { .. } => thread.state = ThreadState::Runnable,
+                _ => {
+                    thread.state = ThreadState::Runnable;
+                    let message = String::from("a call from native code cannot wait for another thread");
+                    self.raise(thread, "java/lang/InternalError", Some(message))?;
+                }
+            }
+        }
+    }
+
+    // -Xtrace:call and -Xprof of a call of native `method` of `class`
+    fn trace_native(&mut self, thread: &Thread, class: &Class, method: &Rc<Method>) {
+        if self.tracer.is_enabled() {
+            let out = &mut *self.output.stderr;
+            self.tracer.call(out, &thread.name, &class.java_name(), method, thread.depth() + 1, true);
+        }
+    }
+
+    fn report_deadlock(&mut self, threads: &[Thread]) {
+        let _ = writeln!(self.output.stderr, "Found a Java-level deadlock:");
+        for thread in threads {
+            let _ = writeln!(self.output.stderr, "\"{}\": {:?}", thread.name, thread.state);
+        }
+    }
+
+    // runs `thread` until its time slice is used up, it blocks or it terminates;
+    // returns the number of instructions executed
+    fn run(&mut self, thread: &mut Thread) -> Result<usize, Error> {
+        if let Some((object, count)) = thread.reacquire.take() {
+            let monitor = &self.heap[object].monitor;
+            monitor.leave_wait_set(thread.id);
+            if !monitor.try_enter(thread.id, count) {
+                thread.state = ThreadState::Blocked;
+                thread.reacquire = Some((object, count));
+                return Ok(0);
+            }
+        }
+        for executed in 0..QUANTUM {
+            // stops when the thread cannot go on, or when a frame native code called has returned or thrown
+            if thread.state != ThreadState::Runnable || thread.outcome.is_some() {
+                return Ok(executed);
+            }
+            let Some(frame) = thread.current_frame() else {
+                if thread.embedded {
+                    return Ok(executed);
+                }
+                thread.terminate(&*self.heap);
+                log!(self.logger, Interp, Info, "thread {} ({}) terminated", thread.id, thread.name);
+                // joining a thread waits on its Thread object, so everyone waiting there is done
+                if let Some(object) = thread.object {
+                    for id in self.heap[object].monitor.notify_unowned(true) {
+                        self.scheduler.wake(id);
+                    }
+                }
+                return Ok(executed);
+            };
+            let code = frame.method.code.clone().expect("a method without code on the stack");
+            let entered = !std::mem::replace(&mut frame.started, true);
+            frame.pc = frame.next_pc;
+            let decoded = code.instruction_at(frame.pc);
+            frame.next_pc = decoded.next_pc;
+            if self.debugger.is_some() {
+                self.debug(thread, Event::Instruction)?;
+            }
+            let timing = match self.tracer.is_enabled() {
+                true => self.tracer.start(&mut *self.output.stderr, thread, entered, &decoded.instruction),
+                false => None,
+            };
+            let frame = thread.current_frame().expect("the frame just decoded from");
+            let flow = match &decoded.instruction {
+                Instruction::New(index) => self.new_object(thread, *index)?,
+                Instruction::InvokeDynamic(index) => self.invoke_dynamic(thread, *index)?,
+                Instruction::Ldc(index) => self.load_constant(thread, &decoded.instruction, *index as u16)?,
+                Instruction::LdcW(index) => self.load_constant(thread, &decoded.instruction, *index)?,
+                Instruction::NewArray(array_type) => self.new_array(thread, array_type.descriptor(), 1)?,
+                Instruction::ANewArray(index) => match constant_class_name(thread, *index)? {
+                    name if name.starts_with('[') => self.new_array(thread, &format!("[{}", name), 1)?,
+                    name => self.new_array(thread, &format!("[L{};", name), 1)?,
+                },
+                Instruction::MultiANewArray { index, dimensions } => {
+                    let class_name = constant_class_name(thread, *index)?;
+                    self.new_array(thread, &class_name, *dimensions as usize)?
+                }
+                Instruction::CheckCast(index) | Instruction::InstanceOf(index) => {
+                    self.check_type(thread, &decoded.instruction, *index)?
+                }
+                Instruction::ArrayLength => match frame.operand_stack.pop_ref() {
+                    None => Flow::Throw(None),
+                    Some(array) => {
+                        frame.operand_stack.push_int(self.heap[array].array_length() as i32);
+                        Flow::Next
+                    }
+                },
+                Instruction::IALoad
+                | Instruction::LALoad
+                | Instruction::FALoad
+                | Instruction::DALoad
+                | Instruction::AALoad
+                | Instruction::BALoad
+                | Instruction::CALoad
+                | Instruction::SALoad => self.load_element(thread, &decoded.instruction),
+                Instruction::IAStore
+                | Instruction::LAStore
+                | Instruction::FAStore
+                | Instruction::DAStore
+                | Instruction::AAStore
+                | Instruction::BAStore
+                | Instruction::CAStore
+                | Instruction::SAStore => self.store_element(thread, &decoded.instruction),
+                Instruction::GetStatic(index) | Instruction::PutStatic(index) => {
+                    self.static_field(thread, &decoded.instruction, *index)?
+                }
+                Instruction::GetField(index) | Instruction::PutField(index) => {
+                    self.instance_field(thread, &decoded.instruction, *index)?
+                }
+                Instruction::MonitorEnter => match frame.operand_stack.peek_slot(0).reference() {
+                    None => Flow::Throw(None),
+                    Some(object) => {
+                        if enter_monitor(thread, &self.heap[object]) {
+                            thread.current_frame().expect("monitorenter without a frame").operand_stack.pop_slot();
+                        }
+                        Flow::Next
+                    }
+                },
+                Instruction::MonitorExit => match frame.operand_stack.pop_ref() {
+                    None => Flow::Throw(None),
+                    Some(object) if self.exit_monitor(thread.id, object) => Flow::Next,
+                    Some(_) => illegal_monitor_state(),
+                },
+                instruction => instruction.execute(frame),
+            };
+            let flow = match flow {
+                Flow::Invoke(kind, index) => self.invoke(thread, kind, index)?,
+                flow => flow,
+            };
+            if let Some(timing) = timing {
+                self.tracer.end(timing);
+            }
+            match flow {
+                Flow::Next | Flow::Invoke(..) => {}
+                Flow::Return(value) => {
+                    let frame = thread.pop_frame().expect("return without a frame");
+                    if self.tracer.traces_calls() {
+                        self.tracer.exit(&mut *self.output.stderr, thread, &frame, None);
+                    }
+                    let unlocked = frame.monitor.is_none_or(|object| self.exit_monitor(thread.id, object));
+                    self.finish_initialization(&frame, InitState::Initialized);
+                    if frame.returns_to_native {
+                        thread.outcome = Some(Ok(value));
+                    } else if let Some(pc) = frame.links_call_site {
+                        self.link_call_site(thread, value.first().and_then(Slot::reference), pc)?;
+                    } else if let Some(caller) = thread.current_frame() {
+                        for slot in value {
+                            caller.operand_stack.push_slot(slot);
+                        }
+                    } else if thread.embedded {
+                        thread.outcome = Some(Ok(value));
+                    }
+                    if !unlocked && !frame.returns_to_native && thread.current_frame().is_some() {
+                        self.raise(thread, "java/lang/IllegalMonitorStateException", None)?;
+                    }
+                }
+                Flow::Throw(Some(exception)) => self.throw(thread, exception)?,
+                Flow::Throw(None) => self.raise(thread, "java/lang/NullPointerException", None)?,
+                Flow::Raise { class_name, message } => self.raise(thread, class_name, message)?,
+            }
+        }
+        Ok(QUANTUM)
+    }
+
+    // resolves the method referenced by constant `index` of the current class and invokes it
+    fn invoke(&mut self, thread: &mut Thread, kind: InvokeKind, index: u16) -> Result<Flow, Error> {
+        let caller = thread.current_frame().expect("invoke without a frame");
+        let (class_name, name, descriptor) =
+            get_member_ref(&caller.class.classfile.constant_pool, &index).ok_or(Error::InvalidConstantIndex(index))?;
+        let class = match self.load(&class_name)? {
+            Ok(class) => class,
+            Err(flow) => return Ok(flow),
+        };
+        if is_signature_polymorphic(&class, &name) {
+            let parsed = parse_method_descriptor(&descriptor).ok_or(Error::InvalidConstantIndex(index))?;
+            return self.invoke_polymorphic(thread, &class.name, &name, &descriptor, parsed.arg_slot_count());
+        }
+        let Some((class, method)) = class.find_method(&name, &descriptor) else {
+            let message = format!("{}.{}{}", class.java_name(), name, descriptor);
+            return Ok(raise("java/lang/NoSuchMethodError", message));
+        };
+        self.invoke_method(thread, kind, class, method)
+    }
+
+    // pushes a frame for `method`, or the one overriding it in the class of the receiver unless `kind` is
+    // Static or Special, moving the arguments off the caller's operand stack
+    fn invoke_method(
+        &mut self,
+        thread: &mut Thread,
+        kind: InvokeKind,
+        mut class: Rc<Class>,
+        mut method: Rc<Method>,
+    ) -> Result<Flow, Error> {
+        if kind == InvokeKind::Static {
+            if let Some(flow) = initialize(thread, &class) {
+                return Ok(flow);
+            }
+        }
+        let caller = thread.current_frame().expect("invoke without a frame");
+        let arg_slots = method.arg_slot_count();
+        let mut lock = None;
+        if kind != InvokeKind::Static {
+            let Some(receiver) = caller.operand_stack.peek_slot(arg_slots - 1).reference() else {
+                return Ok(Flow::Throw(None));
+            };
+            if kind != InvokeKind::Special {
+                if let Some(found) = self.heap[receiver].class.find_method(&method.name, &method.descriptor) {
+                    (class, method) = found;
+                }
+            }
+            lock = Some(receiver);
+        }
+        // a synchronized method locks its receiver, or its class when it is static
+        let lock = match lock {
+            _ if !method.is_synchronized() => None,
+            Some(receiver) => Some(receiver),
+            None => match self.env(thread).mirror(&class) {
+                Ok(mirror) => Some(mirror),
+                Err(flow) => return Ok(flow),
+            },
+        };
+        if let Some(object) = lock {
+            if !enter_monitor(thread, &self.heap[object]) {
+                return Ok(Flow::Next);
+            }
+        }
+        // loading a library runs its JNI_OnLoad, which may call back into Java
+        if let Some(by_name) = jni::loads_library(&class.name, &method.name, &method.descriptor) {
+            let caller = thread.current_frame().expect("invoke without a frame");
+            let name = caller.operand_stack.pop_slots(arg_slots).pop().and_then(|slot| slot.reference());
+            let flow = match name {
+                Some(name) => {
+                    let name = self.env(thread).rust_string(name);
+                    jni::load_library(self, thread, &name, by_name)?
+                }
+                None => Flow::Throw(None),
+            };
+            if let Some(object) = lock {
+                self.exit_monitor(thread.id, object);
+            }
+            return Ok(flow);
+        }
+        if let Some(native) = self.natives.find(&class.name, &method.name, &method.descriptor) {
+            // the arguments stay on the caller's operand stack while the native runs, where the collector finds them
+            let caller = thread.current_frame().expect("invoke without a frame");
+            let args = LocalVars(caller.operand_stack.pop_slots(arg_slots));
+            for slot in &args.0 {
+                caller.operand_stack.push_slot(slot.clone());
+            }
+            let flow = native(&mut self.env(thread), &args);
+            if let Some(object) = lock {
+                self.exit_monitor(thread.id, object);
+            }
+            // the native pushed frames to run first, class initializers, and is called again after them
+            if let Flow::Next = flow {
+                return Ok(Flow::Next);
+            }
+            self.trace_native(thread, &class, &method);
+            let caller = thread.current_frame().expect("native without a caller");
+            caller.operand_stack.pop_slots(arg_slots);
+            return Ok(match flow {
+                Flow::Return(value) => {
+                    for slot in value {
+                        caller.operand_stack.push_slot(slot);
+                    }
+                    Flow::Next
+                }
+                flow => flow,
+            });
+        }
+        if let Some(function) = method.is_native().then(|| self.jni.link(&class, &method)).flatten() {
+            self.trace_native(thread, &class, &method);
+            let flow = jni::call(self, thread, &class, &method, function)?;
+            if let Some(object) = lock {
+                self.exit_monitor(thread.id, object);
+            }
+            return Ok(flow);
+        }
+        let message = format!("{}.{}{}", class.java_name(), method.name, method.descriptor);
+        let error = if method.is_native() {
+            Some("java/lang/UnsatisfiedLinkError")
+        } else if method.code.is_none() {
+            Some("java/lang/AbstractMethodError")
+        } else {
+            None
+        };
+        let caller = thread.current_frame().expect("invoke without a frame");
+        let args = caller.operand_stack.pop_slots(arg_slots);
+        let flow = match error {
+            Some(class_name) => raise(class_name, message),
+            None => {
+                let mut frame = Frame::new(class, method);
+                for (index, slot) in args.into_iter().enumerate() {
+                    frame.local_vars.0[index] = slot;
+                }
+                frame.monitor = lock;
+                match thread.push_frame(frame) {
+                    Err(Error::StackOverflow) => stack_overflow(),
+                    result => result.map(|_| Flow::Next)?,
+                }
+            }
+        };
+        if let (Some(object), false) = (lock, matches!(flow, Flow::Next)) {
+            self.exit_monitor(thread.id, object);
+        }
+        Ok(flow)
+    }
+
+    // links the invokedynamic at the current pc the first time it runs and calls what it is linked to.
+    // LambdaMetafactory and StringConcatFactory call sites get a static method of a class the VM writes,
+    // any other bootstrap method runs and the target of the CallSite it returns is kept
+    fn invoke_dynamic(&mut self, thread: &mut Thread, index: u16) -> Result<Flow, Error> {
+        let frame = thread.current_frame().expect("invokedynamic without a frame");
+        let (caller, method, pc) = (frame.class.clone(), frame.method.clone(), frame.pc);
+        let call_site = method.call_sites.borrow().get(&pc).cloned();
+        match call_site {
+            Some(CallSite::Method(class, linked)) => {
+                return self.invoke_method(thread, InvokeKind::Static, class, linked);
+            }
+            Some(CallSite::Handle(target)) => {
+                // what invokeExact of the target does, with no handle on the stack
+                let descriptor = Bootstrap::descriptor(&caller.classfile, index)?;
+                let handle = &self.heap[target];
+                let Some(reference) = handle.handle_of.clone() else {
+                    return Ok(raise("java/lang/UnsupportedOperationException", handle.class.java_name()));
+                };
+                let target = reference.type_descriptor();
+                if target != descriptor {
+                    return Ok(raise(WRONG_METHOD_TYPE, format!("expected {} but found {}", target, descriptor)));
+                }
+                return self.invoke_handle(thread, &reference, false);
+            }
+            None => {}
+        }
+        let bootstrap = Bootstrap::read(&caller.classfile, index)?;
+        let Some(fast_path) = bootstrap.fast_path() else {
+            return self.bootstrap(thread, &caller, &bootstrap);
+        };
+        let name = self.loader.unique_name(&fast_path.class_prefix(&caller.name));
+        let content = match fast_path.spin(&bootstrap, &name) {
+            Ok(content) => content,
+            Err(message) => return Ok(raise("java/lang/BootstrapMethodError", message)),
+        };
+        let class = match self.loader.define_class(&name, content) {
+            Ok(class) => class,
+            Err(Error::ClassNotFound(missing)) => return Ok(raise("java/lang/NoClassDefFoundError", missing)),
+            Err(error) => return Err(error),
+        };
+        let (class, linked) = class
+            .find_method(fast_path.method_name(), &bootstrap.descriptor)
+            .expect("a made up class without its method");
+        method.call_sites.borrow_mut().insert(pc, CallSite::Method(class.clone(), linked.clone()));
+        self.invoke_method(thread, InvokeKind::Static, class, linked)
+    }
+
+    // pushes the frame of the bootstrap method of the current invokedynamic, which runs again once
+    // the CallSite the bootstrap method returns has been linked
+    fn bootstrap(&mut self, thread: &mut Thread, caller: &Rc<Class>, bootstrap: &Bootstrap) -> Result<Flow, Error> {
+        let reference = &bootstrap.method;
+        let name = format!("{}.{}{}", reference.class_name.replace('/', "."), reference.name, reference.descriptor);
+        if reference.kind != REF_INVOKE_STATIC {
+            return Ok(raise("java/lang/BootstrapMethodError", format!("{} is not a static method", name)));
+        }
+        let class = match self.load(&reference.class_name)? {
+            Ok(class) => class,
+            Err(flow) => return Ok(flow),
+        };
+        let (class, method) = match class.find_method(&reference.name, &reference.descriptor) {
+            Some((class, method)) if method.is_static() && method.code.is_some() => (class, method),
+            _ => return Ok(raise("java/lang/NoSuchMethodError", name)),
+        };
+        if let Some(flow) = initialize(thread, &class) {
+            return Ok(flow);
+        }
+        let frame = thread.current_frame().expect("invokedynamic without a frame");
+        frame.next_pc = frame.pc;
+        let mut bootstrap_frame = Frame::new(class, method.clone());
+        bootstrap_frame.links_call_site = Some(frame.pc);
+        match thread.push_frame(bootstrap_frame) {
+            Err(Error::StackOverflow) => return Ok(stack_overflow()),
+            result => result?,
+        }
+        if let Err(flow) = self.env(thread).bootstrap_arguments(caller, bootstrap, &method) {
+            thread.pop_frame();
+            return Ok(flow);
+        }
+        Ok(Flow::Next)
+    }
+
+    // keeps the target of the CallSite a bootstrap method returned for the invokedynamic at `pc`
+    // of the current frame
+    fn link_call_site(&mut self, thread: &mut Thread, call_site: Option<ObjectRef>, pc: i32) -> Result<(), Error> {
+        let call_site_class = self.loader.load_class("java/lang/invoke/CallSite")?;
+        let target = call_site
+            .filter(|call_site| self.heap[*call_site].class.is_assignable_to(&call_site_class))
+            .and_then(|call_site| self.heap[call_site].get_ref_field("target", "Ljava/lang/invoke/MethodHandle;"));
+        let Some(target) = target else {
+            let message = String::from("the bootstrap method returned no CallSite with a target");
+            return self.raise(thread, "java/lang/BootstrapMethodError", Some(message));
+        };
+        let frame = thread.current_frame().expect("a bootstrap method without a caller");
+        frame.method.call_sites.borrow_mut().insert(pc, CallSite::Handle(target));
+        Ok(())
+    }
+
+    // invokes signature polymorphic method `name` of `owner` (JVMS §2.9.3) with the handle below the
+    // `arg_slots` slots of arguments on the operand stack: MethodHandle.invokeExact and invoke call what the
+    // handle refers to, the access modes of VarHandle get or change the variable it refers to. A call of
+    // another type than the handle's goes through an adapter converting the arguments and the result,
+    // except with invokeExact, which throws a WrongMethodTypeException
+    fn invoke_polymorphic(
+        &mut self,
+        thread: &mut Thread,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+        arg_slots: usize,
+    ) -> Result<Flow, Error> {
+        let caller = thread.current_frame().expect("invoke without a frame");
+        let Some(handle) = caller.operand_stack.peek_slot(arg_slots).reference() else {
+            return Ok(Flow::Throw(None));
+        };
+        let handle = &self.heap[handle];
+        let unsupported = format!("{} of a {}", name, handle.class.java_name());
+        let (target, method_name) = if owner == METHOD_HANDLE {
+            let (Some(reference), "invokeExact" | "invoke") = (handle.handle_of.clone(), name) else {
+                return Ok(raise("java/lang/UnsupportedOperationException", unsupported));
+            };
+            let target = reference.type_descriptor();
+            if target == descriptor {
+                return self.invoke_handle(thread, &reference, true);
+            }
+            if name == "invokeExact" {
+                return Ok(raise(WRONG_METHOD_TYPE, format!("expected {} but found {}", target, descriptor)));
+            }
+            (target, "invokeExact")
+        } else {
+            let (Some(reference), Some(mode)) = (handle.var_handle_of.clone(), AccessMode::from_name(name)) else {
+                return Ok(raise("java/lang/UnsupportedOperationException", unsupported));
+            };
+            let target = reference.access_descriptor(mode);
+            if target == descriptor {
+                return self.access_variable(thread, &reference, mode, arg_slots);
+            }
+            (target, name)
+        };
+        self.adapt(thread, owner, method_name, descriptor, &target)
+    }
+
+    // calls the adapter for calling `method_name` of handle class `owner`, whose type is `target`, with
+    // type `site`, making it the first time
+    fn adapt(
+        &mut self,
+        thread: &mut Thread,
+        owner: &str,
+        method_name: &str,
+        site: &str,
+        target: &str,
+    ) -> Result<Flow, Error> {
+        let key = format!("{}.{}{}{}", owner, method_name, site, target);
+        let adapter = self.adapters.get(&key).cloned();
+        let (class, method) = match adapter {
+            Some(adapter) => adapter,
+            None => {
+                let name = self.loader.unique_name(&format!("{}$$Adapter$", owner));
+                let content = match spin_adapter(&name, owner, method_name, site, target) {
+                    Ok(content) => content,
+                    Err(message) => return Ok(raise(WRONG_METHOD_TYPE, message)),
+                };
+                let class = self.loader.define_class(&name, content)?;
+                let method = class.methods.iter().find(|method| method.name == "adapt").cloned();
+                let adapter = (class.clone(), method.expect("an adapter without its method"));
+                self.adapters.insert(key, adapter.clone());
+                adapter
+            }
+        };
+        self.invoke_method(thread, InvokeKind::Static, class, method)
+    }
+
+    // calls the method, or gets or sets the field, a method handle made by the VM refers to, with its
+    // arguments on the operand stack and the handle below them when `on_stack`; a constructor handle gets
+    // a new object to initialize, which it leaves on the stack
+    fn invoke_handle(
+        &mut self,
+        thread: &mut Thread,
+        reference: &MethodHandleRef,
+        on_stack: bool,
+    ) -> Result<Flow, Error> {
+        let parsed = parse_method_descriptor(&reference.type_descriptor());
+        let arg_slots = parsed.map_or(0, |parsed| parsed.arg_slot_count());
+        let class = match self.load(&reference.class_name)? {
+            Ok(class) => class,
+            Err(flow) => return Ok(flow),
+        };
+        let kind = match reference.kind {
+            REF_GET_FIELD | REF_GET_STATIC | REF_PUT_FIELD | REF_PUT_STATIC => {
+                let is_static = matches!(reference.kind, REF_GET_STATIC | REF_PUT_STATIC);
+                let (class, field) = match find_field(&class, &reference.name, &reference.descriptor, is_static) {
+                    Ok(found) => found,
+                    Err(flow) => return Ok(flow),
+                };
+                if let Some(flow) = initialize(thread, &class).filter(|_| is_static) {
+                    return Ok(flow);
+                }
+                let stack = &mut thread.current_frame().expect("invoke without a frame").operand_stack;
+                stack.splice_below(arg_slots, on_stack as usize, Vec::new());
+                let get = matches!(reference.kind, REF_GET_FIELD | REF_GET_STATIC);
+                return Ok(if is_static {
+                    self.access_static(thread, &class, &field, get)
+                } else {
+                    self.access_instance(thread, &field, get)
+                });
+            }
+            REF_INVOKE_VIRTUAL => InvokeKind::Virtual,
+            REF_INVOKE_STATIC => InvokeKind::Static,
+            REF_INVOKE_SPECIAL | REF_NEW_INVOKE_SPECIAL => InvokeKind::Special,
+            REF_INVOKE_INTERFACE => InvokeKind::Interface,
+            kind => {
+                let message = format!("cannot invoke a method handle of kind {}", kind);
+                return Ok(raise("java/lang/UnsupportedOperationException", message));
+            }
+        };
+        let Some((class, method)) = class.find_method(&reference.name, &reference.descriptor) else {
+            let message = format!("{}.{}{}", class.java_name(), reference.name, reference.descriptor);
+            return Ok(raise("java/lang/NoSuchMethodError", message));
+        };
+        // the initializers run before the stack changes, since the call starts over after them
+        if matches!(reference.kind, REF_INVOKE_STATIC | REF_NEW_INVOKE_SPECIAL) {
+            if let Some(flow) = initialize(thread, &class) {
+                return Ok(flow);
+            }
+        }
+        let mut receiver = Vec::new();
+        if reference.kind == REF_NEW_INVOKE_SPECIAL {
+            let object = match self.env(thread).allocate(Object::new(class.clone())) {
+                Ok(object) => object,
+                Err(flow) => return Ok(flow),
+            };
+            receiver = vec![Slot::Reference(Some(object)); 2];
+        }
+        let stack = &mut thread.current_frame().expect("invoke without a frame").operand_stack;
+        stack.splice_below(arg_slots, on_stack as usize, receiver);
+        self.invoke_method(thread, kind, class, method)
+    }
+
+    // does access mode `mode` of a var handle made by the VM with the handle, the coordinates of the variable
+    // and the values the mode takes, `arg_slots` slots past the handle, on the operand stack
+    fn access_variable(
+        &mut self,
+        thread: &mut Thread,
+        reference: &VarHandleRef,
+        mode: AccessMode,
+        arg_slots: usize,
+    ) -> Result<Flow, Error> {
+        let value = reference.value_descriptor();
+        if mode == AccessMode::GetAndAdd && !matches!(value, "I" | "J" | "F" | "D") {
+            return Ok(raise("java/lang/UnsupportedOperationException", format!("getAndAdd of {}", value)));
+        }
+        let field = match reference {
+            VarHandleRef::Field { class_name, name, descriptor, is_static } => {
+                let class = match self.load(class_name)? {
+                    Ok(class) => class,
+                    Err(flow) => return Ok(flow),
+                };
+                let (class, field) = match find_field(&class, name, descriptor, *is_static) {
+                    Ok(found) => found,
+                    Err(flow) => return Ok(flow),
+                };
+                if let Some(flow) = initialize(thread, &class).filter(|_| *is_static) {
+                    return Ok(flow);
+                }
+                Some((class, field))
+            }
+            VarHandleRef::Array(_) => None,
+        };
+        let width = if matches!(value, "J" | "D") { 2 } else { 1 };
+        let operands = match mode {
+            AccessMode::Get => 0,
+            AccessMode::Set | AccessMode::GetAndSet | AccessMode::GetAndAdd => 1,
+            AccessMode::CompareAndSet | AccessMode::CompareAndExchange => 2,
+        };
+        let stack = &mut thread.current_frame().expect("invoke without a frame").operand_stack;
+        let operands: Vec<Vec<Slot>> = stack.pop_slots(operands * width).chunks(width).map(<[Slot]>::to_vec).collect();
+        let coordinates = stack.pop_slots(arg_slots - operands.len() * width);
+        stack.pop_slot();
+        let (object, start) = match &field {
+            Some((_, field)) if field.is_static() => (None, field.slot),
+            Some((_, field)) => match coordinates[0].reference() {
+                Some(object) => (Some(object), field.slot),
+                None => return Ok(Flow::Throw(None)),
+            },
+            None => {
+                let Some(array) = coordinates[0].reference() else {
+                    return Ok(Flow::Throw(None));
+                };
+                let index = coordinates[1].int();
+                if let Some(flow) = check_index(&self.heap[array], index) {
+                    return Ok(flow);
+                }
+                (Some(array), index as usize * width)
+            }
+        };
+        let slots = match (object, &field) {
+            (Some(object), _) => &self.heap[object].fields,
+            (None, Some((class, _))) => &class.static_fields,
+            (None, None) => unreachable!("an array element without an array"),
+        };
+        let current = slots.borrow()[start..start + width].to_vec();
+        let (result, update) = match mode {
+            AccessMode::Get => (current, None),
+            AccessMode::Set => (Vec::new(), Some(operands[0].clone())),
+            AccessMode::CompareAndSet => {
+                let swap = same(&current, &operands[0]);
+                (vec![Slot::Int(swap as i32)], swap.then(|| operands[1].clone()))
+            }
+            AccessMode::CompareAndExchange => {
+                let swap = same(&current, &operands[0]);
+                (current, swap.then(|| operands[1].clone()))
+            }
+            AccessMode::GetAndSet => (current, Some(operands[0].clone())),
+            AccessMode::GetAndAdd => {
+                let sum = add(&current, &operands[0], value);
+                (current, Some(sum))
+            }
+        };
+        if let Some(update) = update {
+            slots.borrow_mut()[start..start + width].clone_from_slice(&update);
+            if let Some(object) = object {
+                self.heap.write_barrier(object);
+            }
+        }
+        let stack = &mut thread.current_frame().expect("invoke without a frame").operand_stack;
+        result.into_iter().for_each(|slot| stack.push_slot(slot));
+        Ok(Flow::Next)
+    }
+
+    fn new_object(&mut self, thread: &mut Thread, index: u16) -> Result<Flow, Error> {
+        let frame = thread.current_frame().expect("new without a frame");
+        let class_name =
+            get_class_name(&frame.class.classfile.constant_pool, &index).ok_or(Error::InvalidConstantIndex(index))?;
+        let class = match self.load(&class_name)? {
+            Ok(class) => class,
+            Err(flow) => return Ok(flow),
+        };
+        if class.is_interface() || class.is_abstract() {
+            return Ok(raise("java/lang/InstantiationError", class.java_name()));
+        }
+        if let Some(flow) = initialize(thread, &class) {
+            return Ok(flow);
+        }
+        let object = match self.env(thread).allocate(Object::new(class)) {
+            Ok(object) => object,
+            Err(flow) => return Ok(flow),
+        };
+        let frame = thread.current_frame().expect("new without a frame");
+        frame.operand_stack.push_ref(Some(object));
+        Ok(Flow::Next)
+    }
+
+    // ldc of a string pushes the interned String, ldc of a class its Class object and ldc of a method type
+    // or method handle a MethodType or MethodHandle; the numeric constants need nothing from the interpreter
+    fn load_constant(&mut self, thread: &mut Thread, instruction: &Instruction, index: u16) -> Result<Flow, Error> {
+        let frame = thread.current_frame().expect("ldc without a frame");
+        let class = frame.class.clone();
+        let constant_pool = &class.classfile.constant_pool;
+        let object = match constant_pool.get(index as usize) {
+            Some(ConstantInfo::ConstantString { index }) => {
+                let value = get_utf8(constant_pool, index).ok_or(Error::InvalidConstantIndex(*index))?;
+                self.env(thread).intern(&value)
+            }
+            Some(ConstantInfo::ConstantClass { .. }) => match self.load(&constant_class_name(thread, index)?)? {
+                Ok(class) => self.env(thread).mirror(&class),
+                Err(flow) => Err(flow),
+            },
+            Some(ConstantInfo::ConstantMethodType { descriptor_index }) => {
+                let descriptor =
+                    get_utf8(constant_pool, descriptor_index).ok_or(Error::InvalidConstantIndex(*descriptor_index))?;
+                self.env(thread).method_type(&descriptor)
+            }
+            Some(ConstantInfo::ConstantMethodHandle { .. }) => {
+                let reference = MethodHandleRef::read(constant_pool, index).ok_or(Error::InvalidConstantIndex(index))?;
+                self.env(thread).method_handle(&reference)
+            }
+            _ => return Ok(instruction.execute(frame)),
+        };
+        match object {
+            Ok(object) => {
+                thread.current_frame().expect("ldc without a frame").operand_stack.push_ref(Some(object));
+                Ok(Flow::Next)
+            }
+            Err(flow) => Ok(flow),
+        }
+    }
+
+    // checkcast and instanceof; neither of them loads the class for null
+    fn check_type(&mut self, thread: &mut Thread, instruction: &Instruction, index: u16) -> Result<Flow, Error> {
+        let frame = thread.current_frame().expect("checkcast without a frame");
+        let object = frame.operand_stack.peek_slot(0).reference();
+        let Some(object) = object else {
+            if let Instruction::InstanceOf(_) = instruction {
+                frame.operand_stack.pop_ref();
+                frame.operand_stack.push_int(0);
+            }
+            return Ok(Flow::Next);
+        };
+        let class = match self.load(&constant_class_name(thread, index)?)? {
+            Ok(class) => class,
+            Err(flow) => return Ok(flow),
+        };
+        let object_class = &self.heap[object].class;
+        let assignable = object_class.is_assignable_to(&class);
+        let stack = &mut thread.current_frame().expect("checkcast without a frame").operand_stack;
+        match instruction {
+            Instruction::InstanceOf(_) => {
+                stack.pop_ref();
+                stack.push_int(assignable as i32);
+            }
+            _ if !assignable => {
+                let message =
+                    format!("class {} cannot be cast to class {}", object_class.java_name(), class.java_name());
+                return Ok(raise("java/lang/ClassCastException", message));
+            }
+            _ => {}
+        }
+        Ok(Flow::Next)
+    }
+
+    // newarray, anewarray and multianewarray: an array of class `class_name` with lengths
+    // for its first `dimensions` dimensions popped off the operand stack
+    fn new_array(&mut self, thread: &mut Thread, class_name: &str, dimensions: usize) -> Result<Flow, Error> {
+        if let Err(flow) = self.load(class_name)? {
+            return Ok(flow);
+        }
+        let stack = &mut thread.current_frame().expect("newarray without a frame").operand_stack;
+        let counts: Vec<i32> = stack.pop_slots(dimensions).iter().map(Slot::int).collect();
+        if let Some(count) = counts.iter().find(|count| **count < 0) {
+            return Ok(raise("java/lang/NegativeArraySizeException", count.to_string()));
+        }
+        let lengths: Vec<usize> = counts.into_iter().map(|count| count as usize).collect();
+        let array = match self.env(thread).new_array(class_name, &lengths) {
+            Ok(array) => array,
+            Err(flow) => return Ok(flow),
+        };
+        thread.current_frame().expect("newarray without a frame").operand_stack.push_ref(Some(array));
+        Ok(Flow::Next)
+    }
+
+    fn load_element(&mut self, thread: &mut Thread, instruction: &Instruction) -> Flow {
+        let width = element_width(instruction);
+        let stack = &mut thread.current_frame().expect("array access without a frame").operand_stack;
+        let index = stack.pop_int();
+        let Some(array) = stack.pop_ref() else {
+            return Flow::Throw(None);
+        };
+        let array = &self.heap[array];
+        if let Some(flow) = check_index(array, index) {
+            return flow;
+        }
+        let start = index as usize * width;
+        for slot in &array.fields.borrow()[start..start + width] {
+            stack.push_slot(slot.clone());
+        }
+        Flow::Next
+    }
+
+    fn store_element(&mut self, thread: &mut Thread, instruction: &Instruction) -> Flow {
+        let width = element_width(instruction);
+        let stack = &mut thread.current_frame().expect("array access without a frame").operand_stack;
+        let mut value = stack.pop_slots(width);
+        let index = stack.pop_int();
+        let Some(array_ref) = stack.pop_ref() else {
+            return Flow::Throw(None);
+        };
+        let array = &self.heap[array_ref];
+        if let Some(flow) = check_index(array, index) {
+            return flow;
+        }
+        // int values are narrowed to the element type, booleans to their lowest bit
+        value[0] = match (instruction, &value[0]) {
+            (Instruction::BAStore, Slot::Int(int)) if array.class.name == "[Z" => Slot::Int(int & 1),
+            (Instruction::BAStore, Slot::Int(int)) => Slot::Int(*int as i8 as i32),
+            (Instruction::CAStore, Slot::Int(int)) => Slot::Int(*int as u16 as i32),
+            (Instruction::SAStore, Slot::Int(int)) => Slot::Int(*int as i16 as i32),
+            (Instruction::AAStore, Slot::Reference(Some(element))) => {
+                let class = &self.heap[*element].class;
+                let component = array.class.component.as_ref().expect("aastore into an array of primitives");
+                if !class.is_assignable_to(component) {
+                    return raise("java/lang/ArrayStoreException", class.java_name());
+                }
+                value[0].clone()
+            }
+            (_, slot) => slot.clone(),
+        };
+        let start = index as usize * width;
+        array.fields.borrow_mut()[start..start + width].clone_from_slice(&value);
+        if let Instruction::AAStore = instruction {
+            self.heap.write_barrier(array_ref);
+        }
+        Flow::Next
+    }
+
+    fn static_field(&mut self, thread: &mut Thread, instruction: &Instruction, index: u16) -> Result<Flow, Error> {
+        let (class, field) = match self.resolve_field(thread, index, true)? {
+            Ok(resolved) => resolved,
+            Err(flow) => return Ok(flow),
+        };
+        if let Some(flow) = initialize(thread, &class) {
+            return Ok(flow);
+        }
+        if self.debugger.is_some() && matches!(instruction, Instruction::PutStatic(_)) {
+            self.debug(thread, Event::FieldWrite { class: &class, field: &field })?;
+        }
+        Ok(self.access_static(thread, &class, &field, matches!(instruction, Instruction::GetStatic(_))))
+    }
+
+    // pushes static field `field` of `class` when `get`, or else sets it to the value on top of the stack
+    fn access_static(&mut self, thread: &mut Thread, class: &Class, field: &Field, get: bool) -> Flow {
+        let stack = &mut thread.current_frame().expect("field access without a frame").operand_stack;
+        let mut statics = class.static_fields.borrow_mut();
+        let slots = &mut statics[field.slot..field.slot + field.slot_count()];
+        if get {
+            for slot in slots.iter() {
+                stack.push_slot(slot.clone());
+            }
+        } else {
+            slots.clone_from_slice(&stack.pop_slots(field.slot_count()));
+        }
+        Flow::Next
+    }
+
+    fn instance_field(&mut self, thread: &mut Thread, instruction: &Instruction, index: u16) -> Result<Flow, Error> {
+        let (class, field) = match self.resolve_field(thread, index, false)? {
+            Ok(resolved) => resolved,
+            Err(flow) => return Ok(flow),
+        };
+        if self.debugger.is_some() && matches!(instruction, Instruction::PutField(_)) {
+            self.debug(thread, Event::FieldWrite { class: &class, field: &field })?;
+        }
+        Ok(self.access_instance(thread, &field, matches!(instruction, Instruction::GetField(_))))
+    }
+
+    // replaces the object on top of the stack with the value of its field `field` when `get`, or else sets
+    // the field of the object below the value on top to that value
+    fn access_instance(&mut self, thread: &mut Thread, field: &Field, get: bool) -> Flow {
+        let stack = &mut thread.current_frame().expect("field access without a frame").operand_stack;
+        if get {
+            let Some(object) = stack.pop_ref() else {
+                return Flow::Throw(None);
+            };
+            for slot in &self.heap[object].fields.borrow()[field.slot..field.slot + field.slot_count()] {
+                stack.push_slot(slot.clone());
+            }
+        } else {
+            let value = stack.pop_slots(field.slot_count());
+            let Some(object) = stack.pop_ref() else {
+                return Flow::Throw(None);
+            };
+            self.heap[object].fields.borrow_mut()[field.slot..field.slot + field.slot_count()].clone_from_slice(&value);
+            self.heap.write_barrier(object);
+        }
+        Flow::Next
+    }
+
+    // the field referenced by constant `index` of the current class, along with the class declaring it
+    fn resolve_field(
+        &mut self,
+        thread: &mut Thread,
+        index: u16,
+        is_static: bool,
+    ) -> Resolved<(Rc<Class>, Rc<Field>)> {
+        let frame = thread.current_frame().expect("field access without a frame");
+        let (class_name, name, descriptor) =
+            get_member_ref(&frame.class.classfile.constant_pool, &index).ok_or(Error::InvalidConstantIndex(index))?;
+        let class = match self.load(&class_name)? {
+            Ok(class) => class,
+            Err(flow) => return Ok(Err(flow)),
+        };
+        Ok(find_field(&class, &name, &descriptor, is_static))
+    }
+
+    // a class that cannot be loaded becomes the LinkageError for the program to handle
+    fn load(&self, class_name: &str) -> Resolved<Rc<Class>> {
+        match self.loader.load_class(class_name) {
+            Ok(class) => Ok(Ok(class)),
+            Err(error) => linkage_error(error).map(|(class_name, message)| Err(raise(class_name, message))),
+        }
+    }
+
+    // has the debugger stop `thread` when `event` calls for it
+    fn debug(&mut self, thread: &mut Thread, event: Event) -> Result<(), Error> {
+        let Some(mut debugger) = self.debugger.take() else {
+            return Ok(());
+        };
+        let result = debugger.check(&mut self.env(thread), event);
+        self.debugger = Some(debugger);
+        result
+    }
+
+    pub fn env<'b>(&'b mut self, thread: &'b mut Thread) -> Env<'b> {
+        Env {
+            loader: &self.loader,
+            scheduler: &mut self.scheduler,
+            heap: &mut *self.heap,
+            strings: &mut self.strings,
+            properties: &self.properties,
+            global_refs: &self.jni.global_refs,
+            output: &mut self.output,
+            logger: &self.logger,
+            thread,
+        }
+    }
+
+    // leaves the monitor of `object`, waking the next thread blocked on it; false when the thread does not own it
+    fn exit_monitor(&mut self, thread: u64, object: ObjectRef) -> bool {
+        match self.heap[object].monitor.exit(thread) {
+            Ok(next) => {
+                if let Some(next) = next {
+                    self.scheduler.wake(next);
+                }
+                true
+            }
+            Err(()) => false,
+        }
+    }
+
+    // unwinds to the nearest handler for `exception`, unlocking what synchronized methods locked on the way;
+    // when there is none the stack ends up empty and the exception is reported like the JVM does for an uncaught one,
+    // or left to the embedder on an embedded thread
+    fn throw(&mut self, thread: &mut Thread, exception: ObjectRef) -> Result<(), Error> {
+        if self.debugger.is_some() {
+            let class = self.heap[exception].class.clone();
+            let mut catch = None;
+            for (depth, frame) in thread.frames().enumerate() {
+                if let Some(handler_pc) = frame.started.then(|| find_handler(frame, &class)).flatten() {
+                    catch = Some((depth, handler_pc));
+                    break;
+                }
+                // what the native code it returns to does with the exception is not known
+                if frame.returns_to_native {
+                    break;
+                }
+            }
+            self.debug(thread, Event::Exception { exception, catch })?;
+        }
+        let mut exception = exception;
+        while let Some(frame) = thread.current_frame() {
+            // a frame that has not run yet, under <clinit> frames pushed on top of it, catches nothing
+            let handler = frame.started.then(|| find_handler(frame, &self.heap[exception].class)).flatten();
+            if let Some(handler_pc) = handler {
+                frame.operand_stack.clear();
+                frame.operand_stack.push_ref(Some(exception));
+                frame.next_pc = handler_pc;
+                return Ok(());
+            }
+            let frame = thread.pop_frame().expect("the frame just looked at");
+            if self.tracer.traces_calls() {
+                let class_name = self.heap[exception].class.java_name();
+                self.tracer.exit(&mut *self.output.stderr, thread, &frame, Some(&class_name));
+            }
+            if let Some(object) = frame.monitor {
+                self.exit_monitor(thread.id, object);
+            }
+            if !frame.initializes.is_empty() {
+                self.finish_initialization(&frame, InitState::Erroneous);
+                if !self.heap[exception].class.is_subclass_of("java/lang/Error") {
+                    exception = self.exception_in_initializer(thread, exception)?;
+                }
+            }
+            if frame.returns_to_native {
+                thread.outcome = Some(Err(exception));
+                return Ok(());
+            }
+        }
+        if thread.embedded {
+            thread.outcome = Some(Err(exception));
+            return Ok(());
+        }
+        if self.main_thread == Some(thread.id) {
+            self.uncaught_in_main = Some(self.heap[exception].class.java_name());
+        }
+        self.describe(thread, exception);
+        Ok(())
+    }
+
+    // ends the initialization of the classes the <clinit> of `frame` initializes, waking the threads waiting for it
+    fn finish_initialization(&mut self, frame: &Frame, state: InitState) {
+        for class in &frame.initializes {
+            class.init_state.set(state);
+            for id in class.init_waiters.take() {
+                self.scheduler.wake(id);
+            }
+        }
+    }
+
+    // the ExceptionInInitializerError a <clinit> throwing `exception` ends with; JDK 8 keeps the exception
+    // in a field of its own besides the cause
+    fn exception_in_initializer(&mut self, thread: &mut Thread, exception: ObjectRef) -> Result<ObjectRef, Error> {
+        // on no frame any more, the exception has to be kept alive through a collection some other way
+        thread.handles.push(exception);
+        let error = self.new_exception(thread, "java/lang/ExceptionInInitializerError", None);
+        thread.handles.pop();
+        let error = error?;
+        let object = &self.heap[error];
+        object.set_ref_field("cause", "Ljava/lang/Throwable;", Some(exception));
+        if object.class.find_field("exception", "Ljava/lang/Throwable;").is_some() {
+            object.set_ref_field("exception", "Ljava/lang/Throwable;", Some(exception));
+        }
+        self.heap.write_barrier(error);
+        Ok(error)
+    }
+
+    // reports `exception` on standard error the way the JVM does one nothing caught, with its stack trace
+    // and those of its causes
+    pub(crate) fn describe(&mut self, thread: &mut Thread, exception: ObjectRef) {
+        let mut heading = format!("Exception in thread \"{}\"", thread.name);
+        let mut described = Vec::new();
+        let mut next = Some(exception);
+        while let Some(exception) = next.filter(|exception| !described.contains(exception)) {
+            described.push(exception);
+            let message = self.heap[exception].get_ref_field("detailMessage", "Ljava/lang/String;");
+            let message = message.map(|message| self.env(thread).rust_string(message));
+            let object = &self.heap[exception];
+            let stderr = &mut self.output.stderr;
+            let _ = match message {
+                Some(message) => writeln!(stderr, "{} {}: {}", heading, object.class.java_name(), message),
+                None => writeln!(stderr, "{} {}", heading, object.class.java_name()),
+            };
+            for element in object.stack_trace.borrow().iter() {
+                let _ = writeln!(stderr, "\tat {}", element);
+            }
+            // a Throwable whose cause was never set has itself as the cause
+            next = object.get_ref_field("cause", "Ljava/lang/Throwable;");
+            heading = String::from("Caused by:");
+        }
+    }
+
+    // creates an exception the VM itself throws, filled in with the current stack trace, and throws it
+    pub fn raise(&mut self, thread: &mut Thread, class_name: &str, message: Option<String>) -> Result<(), Error> {
+        let exception = self.new_exception(thread, class_name, message)?;
+        self.throw(thread, exception)
+    }
+
+    // an exception for the VM to throw, filled in with the current stack trace; the message is left out when the
+    // heap has no room for it
+    pub(crate) fn new_exception(
+        &mut self,
+        thread: &mut Thread,
+        class_name: &str,
+        message: Option<String>,
+    ) -> Result<ObjectRef, Error> {
+        let class = self.loader.load_class(class_name)?;
+        let message = message.and_then(|message| self.env(thread).new_string(&message).ok());
+        let new_exception = |thread: &Thread| {
+            let exception = Object::new(class.clone());
+            *exception.stack_trace.borrow_mut() = thread.stack_trace();
+            exception
+        };
+        let exception = new_exception(thread);
+        let exception = match self.env(thread).allocate_keeping(exception, &Vec::from_iter(message)) {
+            Ok(exception) => exception,
+            Err(_) => self.heap.allocate_past_limit(new_exception(thread)),
+        };
+        self.heap[exception].set_ref_field("detailMessage", "Ljava/lang/String;", message);
+        self.heap.write_barrier(exception);
+        Ok(exception)
+    }
+}
+
+// sets up the first thread the way the JDK expects to find it: a Thread object in the main thread group, and
+// ahead of what the thread runs the JDK's own initialization, System.initPhase1 from JDK 9 on and
+// System.initializeSystemClass before, which leaves System.out ready to use. The built-in class library
+// only needs System.out and System.err
+fn boot(env: &mut Env) -> Result<(), Flow> {
+    if env.loader.is_builtin() {
+        return env.open_standard_streams();
+    }
+    let system = env.load("java/lang/System")?;
+    let initialize_system = match system.find_method("initPhase1", "()V") {
+        Some(_) => "initPhase1",
+        None => "initializeSystemClass",
+    };
+    inject_unsafe_constants(env);
+    // HotSpot creates the system and main thread groups and the main thread object with the constructors it
+    // runs for that first; the frames run in the opposite order to the one they are pushed in
+    let name = env.intern("main")?;
+    let thread = env.thread_object()?;
+    let group_class = env.load("java/lang/ThreadGroup")?;
+    let system_group = env.allocate(Object::new(group_class.clone()))?;
+    let main_group = env.allocate_keeping(Object::new(group_class.clone()), &[system_group])?;
+    let thread_class = env.load("java/lang/Thread")?;
+    let group_and_name = "(Ljava/lang/ThreadGroup;Ljava/lang/String;)V";
+    let calls = [
+        (&system, initialize_system, "()V", Vec::new()),
+        (&thread_class, "<init>", group_and_name, vec![thread, main_group, name]),
+        (&group_class, "<init>", group_and_name, vec![main_group, system_group, name]),
+        (&group_class, "<init>", "()V", vec![system_group]),
+    ];
+    for (class, name, descriptor, args) in calls {
+        let (class, method) = class.find_method(name, descriptor).expect("the JDK declares the methods it boots with");
+        let mut frame = Frame::new(class, method);
+        for (index, object) in args.into_iter().enumerate() {
+            frame.local_vars.set_ref(index, Some(object));
+        }
+        env.thread.push_frame(frame).map_err(|_| stack_overflow())?;
+        // like HotSpot, Method is initialized once the main thread exists and before the system is, its
+        // super class AccessibleObject handing the JDK's reflection its access to java.lang.reflect
+        if name == initialize_system {
+            let method_class = env.load("java/lang/reflect/Method")?;
+            initialize_first(env, &method_class)?;
+        }
+    }
+    // the classes the frames above need are initialized before any of them runs
+    for class in [system, thread_class, group_class] {
+        initialize_first(env, &class)?;
+    }
+    Ok(())
+}
+
+fn initialize_first(env: &mut Env, class: &Rc<Class>) -> Result<(), Flow> {
+    match initialize(env.thread, class) {
+        None | Some(Flow::Next) => Ok(()),
+        Some(flow) => Err(flow),
+    }
+}
+
+// the constants JDK 9 on leave to the VM to fill in, for Unsafe to describe the machine with
+fn inject_unsafe_constants(env: &mut Env) {
+    let Ok(class) = env.loader.load_class("jdk/internal/misc/UnsafeConstants") else {
+        return;
+    };
+    class.init_state.set(InitState::Initialized);
+    let constants = [
+        ("ADDRESS_SIZE0", "I", 8),
+        ("PAGE_SIZE", "I", 4096),
+        ("BIG_ENDIAN", "Z", cfg!(target_endian = "big") as i32),
+        ("UNALIGNED_ACCESS", "Z", 1),
+        ("DATA_CACHE_LINE_FLUSH_SIZE", "I", 0),
+    ];
+    for (name, descriptor, value) in constants {
+        if let Some((_, field)) = class.find_field(name, descriptor) {
+            class.static_fields.borrow_mut()[field.slot] = Slot::Int(value);
+        }
+    }
+}
+
+// the LinkageError the JVM throws when a class fails to load, and its message: the missing class, which may be a
+// super class of the one asked for, or what is wrong with the class file; any other error is the VM's own
+pub(crate) fn linkage_error(error: Error) -> Result<(&'static str, String), Error> {
+    match error {
+        Error::ClassNotFound(missing) => Ok(("java/lang/NoClassDefFoundError", missing)),
+        Error::WrongName(..) => Ok(("java/lang/NoClassDefFoundError", error.to_string())),
+        Error::ClassCircularity(name) => Ok(("java/lang/ClassCircularityError", name)),
+        Error::ClassFormat(errors) => {
+            Ok(("java/lang/ClassFormatError", errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")))
+        }
+        Error::TruncatedClassFile(..)
+        | Error::UnKnownConstantType(_)
+        | Error::InvalidConstantIndex(_)
+        | Error::MalformedUtf8(_)
+        | Error::UnknownStackMapFrame(_)
+        | Error::UnknownVerificationType(_)
+        | Error::UnknownElementValueTag(_)
+        | Error::TruncatedBytecode(_)
+        | Error::IllegalOpcode(..)
+        | Error::InvalidArrayType(..)
+        | Error::InvalidSwitch(_) => Ok(("java/lang/ClassFormatError", error.to_string())),
+        Error::Verify(error) => Ok(("java/lang/VerifyError", error.to_string())),
+        error => Err(error),
+    }
+}
+
+fn raise(class_name: &'static str, message: String) -> Flow {
+    Flow::Raise {
+        class_name,
+        message: Some(message),
+    }
+}
+
+// field `name` of `class` or a super class, along with the class declaring it, when it is static or not
+// as the access expects
+fn find_field(
+    class: &Rc<Class>,
+    name: &str,
+    descriptor: &str,
+    is_static: bool,
+) -> Result<(Rc<Class>, Rc<Field>), Flow> {
+    match class.find_field(name, descriptor) {
+        Some((class, field)) if field.is_static() == is_static => Ok((class, field)),
+        Some(_) => Err(raise("java/lang/IncompatibleClassChangeError", format!("{}.{}", class.java_name(), name))),
+        None => Err(raise("java/lang/NoSuchFieldError", String::from(name))),
+    }
+}
+
+// whether `name` is a signature polymorphic method of `class` (JVMS §2.9.3): a native variable arity method
+// of MethodHandle or VarHandle taking an Object[], which takes and returns whatever its call site says
+fn is_signature_polymorphic(class: &Class, name: &str) -> bool {
+    (class.name == METHOD_HANDLE || class.name == VAR_HANDLE)
+        && class.methods.iter().any(|method| {
+            method.name == name
+                && method.is_native()
+                && method.access_flags & ACC_VARARGS != 0
+                && method.descriptor.starts_with("([Ljava/lang/Object;)")
+        })
+}
+
+// whether two values of a type are the same, the way compareAndSet compares them: references by identity
+// and numbers by their bits
+fn same(current: &[Slot], expected: &[Slot]) -> bool {
+    current.iter().zip(expected).all(|pair| match pair {
+        (Slot::Int(a), Slot::Int(b)) => a == b,
+        (Slot::Float(a), Slot::Float(b)) => a.to_bits() == b.to_bits(),
+        (Slot::Long { bits: a, .. }, Slot::Long { bits: b, .. }) => a == b,
+        (Slot::Double { bits: a, .. }, Slot::Double { bits: b, .. }) => a == b,
+        (Slot::Reference(a), Slot::Reference(b)) => a == b,
+        _ => false,
+    })
+}
+
+// what getAndAdd stores for a value of type `descriptor`, one of int, long, float and double
+fn add(current: &[Slot], delta: &[Slot], descriptor: &str) -> Vec<Slot> {
+    match descriptor {
+        "I" => vec![Slot::Int(current[0].int().wrapping_add(delta[0].int()))],
+        "F" => vec![Slot::Float(current[0].float() + delta[0].float())],
+        "J" => {
+            let sum = Slot::join(&current[0], &current[1], false).wrapping_add(Slot::join(&delta[0], &delta[1], false));
+            Slot::wide(sum, false).to_vec()
+        }
+        _ => {
+            let value = |slots: &[Slot]| f64::from_bits(Slot::join(&slots[0], &slots[1], true));
+            Slot::wide((value(current) + value(delta)).to_bits(), true).to_vec()
+        }
+    }
+}
+
+// schedules <clinit> of `class` and its uninitialized super classes, super classes first, and rewinds
+// the current instruction so that it runs again once they are done, or once the thread initializing one of
+// them is; None when there is nothing to do
+pub(crate) fn initialize(thread: &mut Thread, class: &Rc<Class>) -> Option<Flow> {
+    if class.is_initialized() {
+        return None;
+    }
+    let frame = thread.current_frame().expect("class initialization without a frame");
+    let next_pc = std::mem::replace(&mut frame.next_pc, frame.pc);
+    match push_initializers(thread, class, false) {
+        Ok(Some(0)) => {
+            thread.current_frame().expect("the frame just rewound").next_pc = next_pc;
+            None
+        }
+        Ok(Some(_)) => Some(Flow::Next),
+        Ok(None) => {
+            thread.state = ThreadState::Blocked;
+            Some(Flow::Next)
+        }
+        Err(flow) => Some(flow),
+    }
+}
+
+// pushes the frames of the <clinit> of `class` and its uninitialized super classes, the super classes' on top,
+// marking the classes as being initialized by the thread; the first frame pushed returns to native code when
+// `from_native`. Gives how many frames there are, or None when another thread is initializing one of the classes,
+// which wakes the thread when done
+fn push_initializers(thread: &mut Thread, class: &Rc<Class>, from_native: bool) -> Result<Option<usize>, Flow> {
+    let mut uninitialized: Vec<Rc<Class>> = Vec::new();
+    let mut current = Some(class.clone());
+    while let Some(class) = current {
+        match class.init_state.get() {
+            InitState::Uninitialized => {}
+            InitState::Initialized => break,
+            // a class initializing itself uses what it has initialized so far
+            InitState::BeingInitialized(id) if id == thread.id => break,
+            InitState::BeingInitialized(_) => {
+                class.init_waiters.borrow_mut().push(thread.id);
+                return Ok(None);
+            }
+            InitState::Erroneous => {
+                for subclass in &uninitialized {
+                    subclass.init_state.set(InitState::Erroneous);
+                }
+                let message = format!("Could not initialize class {}", class.java_name());
+                return Err(raise("java/lang/NoClassDefFoundError", message));
+            }
+        }
+        current = class.super_class.clone();
+        uninitialized.push(class);
+    }
+    let mut pushed = 0;
+    // a class without a <clinit> is initialized along with the closest super class that has one
+    let mut without_clinit = Vec::new();
+    for class in uninitialized {
+        let Some(clinit) = class.methods.iter().find(|method| method.name == "<clinit>").cloned() else {
+            without_clinit.push(class);
+            continue;
+        };
+        let mut frame = Frame::new(class.clone(), clinit);
+        frame.returns_to_native = from_native && pushed == 0;
+        frame.initializes = std::iter::once(class).chain(without_clinit.drain(..)).collect();
+        let initializes = frame.initializes.clone();
+        if thread.push_frame(frame).is_err() {
+            // leaves every class as it was for the StackOverflowError
+            for _ in 0..pushed {
+                let frame = thread.pop_frame().expect("a frame just pushed");
+                frame.initializes.iter().for_each(|class| class.init_state.set(InitState::Uninitialized));
+            }
+            return Err(stack_overflow());
+        }
+        for class in initializes {
+            class.init_state.set(InitState::BeingInitialized(thread.id));
+        }
+        pushed += 1;
+    }
+    for class in without_clinit {
+        class.init_state.set(InitState::Initialized);
+    }
+    Ok(Some(pushed))
+}
+
+// enters the monitor of `object`, or blocks the thread and rewinds the current instruction
+// so that it tries again when woken
+fn enter_monitor(thread: &mut Thread, object: &Object) -> bool {
+    if object.monitor.try_enter(thread.id, 1) {
+        return true;
+    }
+    thread.state = ThreadState::Blocked;
+    let frame = thread.current_frame().expect("monitor enter without a frame");
+    frame.next_pc = frame.pc;
+    false
+}
+
+fn illegal_monitor_state() -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/IllegalMonitorStateException",
+        message: Some(String::from("current thread is not owner")),
+    }
+}
+
+pub(crate) fn out_of_memory() -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/OutOfMemoryError",
+        message: Some(String::from("Java heap space")),
+    }
+}
+
+pub(crate) fn stack_overflow() -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/StackOverflowError",
+        message: None,
+    }
+}
+
+// how many slots an element of the array an x-aload or x-astore accesses takes
+fn element_width(instruction: &Instruction) -> usize {
+    match instruction {
+        Instruction::LALoad | Instruction::DALoad | Instruction::LAStore | Instruction::DAStore => 2,
+        _ => 1,
+    }
+}
+
+fn check_index(array: &Object, index: i32) -> Option<Flow> {
+    let length = array.array_length();
+    if index < 0 || index as usize >= length {
+        return Some(raise(
+            "java/lang/ArrayIndexOutOfBoundsException",
+            format!("Index {} out of bounds for length {}", index, length),
+        ));
+    }
+    None
+}
+
+// the name of the class referenced by constant `index` of the current class
+fn constant_class_name(thread: &mut Thread, index: u16) -> Result<String, Error> {
+    let frame = thread.current_frame().expect("class reference without a frame");
+    get_class_name(&frame.class.classfile.constant_pool, &index).ok_or(Error::InvalidConstantIndex(index))
+}
+
+fn find_handler(frame: &Frame, class: &Class) -> Option<i32> {
+    let constant_pool = &frame.class.classfile.constant_pool;
+    frame
+        .method
+        .exception_table
+        .iter()
+        .find(|entry| {
+            (entry.start_pc as i32..entry.end_pc as i32).contains(&frame.pc)
+                && (entry.catch_type == 0
+                    || get_class_name(constant_pool, &entry.catch_type).is_some_and(|name| class.is_subclass_of(&name)))
+        })
+        .map(|entry| entry.handle_pc as i32)
+}
@@ -0,0 +1,315 @@
+// calls between Rust and C functions whose signatures are only known at run time, without libffi. A native is
+// called as a function taking as many integers and floating point numbers as the calling convention passes in
+// registers, followed by enough integers to fill the stack slots the rest of its arguments take: a function only
+// reads the registers and stack slots its parameters are in, so what the others hold does it no harm. The
+// variadic JNI functions are the same trick the other way around, see variadic!
+
+// whether natives can be called on this platform: the System V x86-64 and the AArch64 calling conventions are the
+// ones laid out here
+pub const SUPPORTED: bool =
+    cfg!(all(unix, not(target_vendor = "apple"), any(target_arch = "x86_64", target_arch = "aarch64")));
+
+#[cfg(target_arch = "aarch64")]
+pub const INT_REGISTERS: usize = 8;
+#[cfg(not(target_arch = "aarch64"))]
+pub const INT_REGISTERS: usize = 6;
+pub const FLOAT_REGISTERS: usize = 8;
+// what the stack arguments of a native may add up to, in 8 byte slots
+pub const STACK_SLOTS: usize = 16;
+
+// the arguments of a call to a native, in order
+#[derive(Default)]
+pub struct Arguments {
+    ints: Vec<u64>,
+    floats: Vec<f64>,
+    stack: Vec<u64>,
+}
+
+impl Arguments {
+    // an integer or pointer, extended to 64 bits
+    pub fn int(&mut self, value: u64) {
+        if self.ints.len() < INT_REGISTERS {
+            self.ints.push(value);
+        } else {
+            self.stack.push(value);
+        }
+    }
+
+    // a float goes in the low half, its bits, a double takes all of it
+    pub fn float(&mut self, bits: u64) {
+        if self.floats.len() < FLOAT_REGISTERS {
+            self.floats.push(f64::from_bits(bits));
+        } else {
+            self.stack.push(bits);
+        }
+    }
+
+    // calls the C function at `function` with the arguments, giving the bits of what it returns, in the floating
+    // point register when `float`; None when the arguments take more stack than a call passes
+    //
+    // Safety: `function` has to be a C function taking the arguments and returning a value of the kind asked for
+    pub unsafe fn call(self, function: usize, float: bool) -> Option<u64> {
+        if self.stack.len() > STACK_SLOTS {
+            return None;
+        }
+        let mut ints = [0; INT_REGISTERS];
+        ints[..self.ints.len()].copy_from_slice(&self.ints);
+        let mut floats = [0.0; FLOAT_REGISTERS];
+        floats[..self.floats.len()].copy_from_slice(&self.floats);
+        let mut stack = [0; STACK_SLOTS];
+        stack[..self.stack.len()].copy_from_slice(&self.stack);
+        Some(match float {
+            true => call_float(function, &ints, &floats, &stack).to_bits(),
+            false => call_int(function, &ints, &floats, &stack),
+        })
+    }
+}
+
+macro_rules! universal_call {
+    ($name:ident -> $result:ty, [$($int:ident),*]) => {
+        unsafe fn $name(
+            function: usize,
+            ints: &[u64; INT_REGISTERS],
+            floats: &[f64; FLOAT_REGISTERS],
+            stack: &[u64; STACK_SLOTS],
+        ) -> $result {
+            type Universal = unsafe extern "C" fn(
+                $($int: u64,)*
+                f64, f64, f64, f64, f64, f64, f64, f64,
+                u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64,
+            ) -> $result;
+            let function: Universal = std::mem::transmute::<usize, Universal>(function);
+            let [$($int),*] = *ints;
+            let [f0, f1, f2, f3, f4, f5, f6, f7] = *floats;
+            let [s0, s1, s2, s3, s4, s5, s6, s7, s8, s9, s10, s11, s12, s13, s14, s15] = *stack;
+            function(
+                $($int,)* f0, f1, f2, f3, f4, f5, f6, f7,
+                s0, s1, s2, s3, s4, s5, s6, s7, s8, s9, s10, s11, s12, s13, s14, s15,
+            )
+        }
+    };
+}
+
+#[cfg(target_arch = "aarch64")]
+universal_call!(call_int -> u64, [x0, x1, x2, x3, x4, x5, x6, x7]);
+#[cfg(target_arch = "aarch64")]
+universal_call!(call_float -> f64, [x0, x1, x2, x3, x4, x5, x6, x7]);
+#[cfg(not(target_arch = "aarch64"))]
+universal_call!(call_int -> u64, [rdi, rsi, rdx, rcx, r8, r9]);
+#[cfg(not(target_arch = "aarch64"))]
+universal_call!(call_float -> f64, [rdi, rsi, rdx, rcx, r8, r9]);
+
+// defines extern "C" function `$name`, which stands for a variadic one taking a JNIEnv* and `$fixed` more
+// arguments before the variable ones: C passes those like any other arguments, so the function takes all the
+// registers and stack slots they can be in, the integer registers past the JNIEnv* first. `$body` gets the
+// JNIEnv*, the fixed arguments and the variable ones
+macro_rules! variadic {
+    ($name:ident$(<$generic:ident: $bound:path>)?, $fixed:literal -> $result:ty, $body:expr) => {
+        #[cfg(target_arch = "aarch64")]
+        $crate::jni::abi::variadic!(
+            @define $name$(<$generic: $bound>)?, $fixed -> $result, $body, [x1, x2, x3, x4, x5, x6, x7]
+        );
+        #[cfg(not(target_arch = "aarch64"))]
+        $crate::jni::abi::variadic!(
+            @define $name$(<$generic: $bound>)?, $fixed -> $result, $body, [rsi, rdx, rcx, r8, r9]
+        );
+    };
+    (
+        @define $name:ident$(<$generic:ident: $bound:path>)?, $fixed:literal -> $result:ty, $body:expr,
+        [$($int:ident),*]
+    ) => {
+        #[allow(clippy::too_many_arguments)]
+        unsafe extern "C" fn $name$(<$generic: $bound>)?(
+            env: *mut $crate::jni::env::JniEnv,
+            $($int: u64,)*
+            f0: f64, f1: f64, f2: f64, f3: f64, f4: f64, f5: f64, f6: f64, f7: f64,
+            s0: u64, s1: u64, s2: u64, s3: u64, s4: u64, s5: u64, s6: u64, s7: u64,
+            s8: u64, s9: u64, s10: u64, s11: u64, s12: u64, s13: u64, s14: u64, s15: u64,
+        ) -> $result {
+            let ints = [$($int),*];
+            let floats = [f0, f1, f2, f3, f4, f5, f6, f7];
+            let stack = [s0, s1, s2, s3, s4, s5, s6, s7, s8, s9, s10, s11, s12, s13, s14, s15];
+            let args = $crate::jni::abi::Args::Spread($crate::jni::abi::Spread::new(&ints[$fixed..], floats, stack));
+            $body(env, &ints[..$fixed], args)
+        }
+    };
+}
+
+pub(crate) use variadic;
+
+// the variable arguments of a variadic function, in what is left of the registers and on the stack
+pub struct Spread {
+    ints: Vec<u64>,
+    floats: [f64; FLOAT_REGISTERS],
+    stack: [u64; STACK_SLOTS],
+    next_float: usize,
+    next_stack: usize,
+}
+
+impl Spread {
+    pub fn new(ints: &[u64], floats: [f64; FLOAT_REGISTERS], stack: [u64; STACK_SLOTS]) -> Spread {
+        let mut ints = ints.to_vec();
+        ints.reverse();
+        Spread { ints, floats, stack, next_float: 0, next_stack: 0 }
+    }
+
+    fn next_int(&mut self) -> u64 {
+        self.ints.pop().unwrap_or_else(|| self.next_on_stack())
+    }
+
+    fn next_double(&mut self) -> f64 {
+        match self.floats.get(self.next_float) {
+            Some(value) => {
+                self.next_float += 1;
+                *value
+            }
+            None => f64::from_bits(self.next_on_stack()),
+        }
+    }
+
+    // past the last slot the call has passed, zeros
+    fn next_on_stack(&mut self) -> u64 {
+        let value = self.stack.get(self.next_stack).copied().unwrap_or(0);
+        self.next_stack += 1;
+        value
+    }
+}
+
+// C's va_list, as a function taking one gets it: a pointer to the state of where the next argument is,
+// in the registers the caller saved or on its stack
+#[cfg(target_arch = "aarch64")]
+#[repr(C)]
+pub struct VaList {
+    stack: *mut u64,
+    gr_top: *mut u8,
+    vr_top: *mut u8,
+    gr_offs: i32,
+    vr_offs: i32,
+}
+
+#[cfg(target_arch = "aarch64")]
+impl VaList {
+    unsafe fn next_int(&mut self) -> u64 {
+        if self.gr_offs < 0 {
+            let value = *(self.gr_top.offset(self.gr_offs as isize) as *const u64);
+            self.gr_offs += 8;
+            return value;
+        }
+        let value = *self.stack;
+        self.stack = self.stack.add(1);
+        value
+    }
+
+    unsafe fn next_double(&mut self) -> f64 {
+        if self.vr_offs < 0 {
+            let value = *(self.vr_top.offset(self.vr_offs as isize) as *const f64);
+            self.vr_offs += 16;
+            return value;
+        }
+        f64::from_bits(self.next_on_stack())
+    }
+
+    unsafe fn next_on_stack(&mut self) -> u64 {
+        let value = *self.stack;
+        self.stack = self.stack.add(1);
+        value
+    }
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+#[repr(C)]
+pub struct VaList {
+    gp_offset: u32,
+    fp_offset: u32,
+    overflow_arg_area: *mut u64,
+    reg_save_area: *mut u8,
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+impl VaList {
+    // where the integer registers end in the save area, and the floating point ones, 16 bytes each
+    const INT_AREA: u32 = 48;
+    const FLOAT_AREA: u32 = 176;
+
+    unsafe fn next_int(&mut self) -> u64 {
+        if self.gp_offset < Self::INT_AREA {
+            let value = *(self.reg_save_area.add(self.gp_offset as usize) as *const u64);
+            self.gp_offset += 8;
+            return value;
+        }
+        self.next_on_stack()
+    }
+
+    unsafe fn next_double(&mut self) -> f64 {
+        if self.fp_offset < Self::FLOAT_AREA {
+            let value = *(self.reg_save_area.add(self.fp_offset as usize) as *const f64);
+            self.fp_offset += 16;
+            return value;
+        }
+        f64::from_bits(self.next_on_stack())
+    }
+
+    unsafe fn next_on_stack(&mut self) -> u64 {
+        let value = *self.overflow_arg_area;
+        self.overflow_arg_area = self.overflow_arg_area.add(1);
+        value
+    }
+}
+
+// the arguments of a Java method native code calls, in one of the three ways JNI passes them
+// only ever on the stack of one call, so a Spread is not boxed
+#[allow(clippy::large_enum_variant)]
+pub enum Args {
+    // the variable arguments of Call<Type>Method and the like, ints and doubles
+    Spread(Spread),
+    // a va_list, for Call<Type>MethodV
+    VaList(*mut VaList),
+    // an array of jvalue unions, for Call<Type>MethodA
+    Array(*const u64),
+}
+
+impl Args {
+    // the next argument, of the type descriptor `kind` starts with: a float as its bits, a double or long as
+    // its 64 bits, any other primitive as its value, extended from its width, and a reference as the handle
+    //
+    // Safety: the arguments have to hold one more of that type
+    pub unsafe fn next(&mut self, kind: u8) -> u64 {
+        match self {
+            Args::Spread(spread) => match kind {
+                // C promotes float arguments of variadic functions to double
+                b'F' => (spread.next_double() as f32).to_bits() as u64,
+                b'D' => spread.next_double().to_bits(),
+                _ => narrow(spread.next_int(), kind),
+            },
+            Args::VaList(list) => match kind {
+                b'F' => ((**list).next_double() as f32).to_bits() as u64,
+                b'D' => (**list).next_double().to_bits(),
+                _ => narrow((**list).next_int(), kind),
+            },
+            Args::Array(array) => {
+                let value = *array as *const u8;
+                *array = array.add(1);
+                match kind {
+                    b'Z' => *value as u64,
+                    b'B' => *(value as *const i8) as i32 as u32 as u64,
+                    b'C' => *(value as *const u16) as u64,
+                    b'S' => *(value as *const i16) as i32 as u32 as u64,
+                    b'I' | b'F' => *(value as *const u32) as u64,
+                    _ => *(value as *const u64),
+                }
+            }
+        }
+    }
+}
+
+// a value C passed in a 64 bit register or stack slot, cut to what a parameter of type `kind` takes
+pub(super) fn narrow(value: u64, kind: u8) -> u64 {
+    match kind {
+        b'Z' => value as u8 as u64,
+        b'B' => value as i8 as i32 as u32 as u64,
+        b'C' => value as u16 as u64,
+        b'S' => value as i16 as i32 as u32 as u64,
+        b'I' => value as u32 as u64,
+        _ => value,
+    }
+}
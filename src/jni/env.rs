@@ -0,0 +1,1471 @@
+// the JNIEnv natives get, a pointer to the table of the JNI functions, which are implemented here over the
+// interpreter and the thread the native runs on, and the JavaVM natives can get it back from. References are
+// handles: local ones index the thread's local_refs and go away when the native returns, global ones index
+// Jni::global_refs; jmethodIDs and jfieldIDs index the methods and fields of Jni
+use std::cell::Cell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::io::Write;
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+use crate::classfile::constant_pool::{encode_modified_utf8, modified_utf8_chars};
+use crate::error::Error;
+use crate::instructions::Flow;
+use crate::interpreter::Interpreter;
+use crate::native::{load_failure, Env};
+use crate::runtime::call_site::{REF_INVOKE_INTERFACE, REF_INVOKE_SPECIAL, REF_INVOKE_STATIC, REF_INVOKE_VIRTUAL};
+use crate::runtime::class::{Class, Method};
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{Object, Slot, Thread};
+
+use super::abi::{variadic, Args, VaList};
+use super::{kind, value_bits, Jni};
+
+// a jobject, jclass, jstring or any other reference: 0 for null, odd for a global reference
+type Handle = usize;
+
+const JNI_VERSION_1_8: i32 = 0x0001_0008;
+const JNI_OK: i32 = 0;
+const JNI_ERR: i32 = -1;
+const JNI_EDETACHED: i32 = -2;
+const JNI_EVERSION: i32 = -3;
+// the modes of Release<Type>ArrayElements
+const JNI_COMMIT: i32 = 1;
+const JNI_ABORT: i32 = 2;
+const FUNCTION_COUNT: usize = 235;
+
+#[repr(C)]
+pub struct JniEnv {
+    // what C sees of it, a JNIEnv being a pointer to the function table
+    functions: *const *const c_void,
+    interpreter: *mut Interpreter,
+    thread: *mut Thread,
+    // where the local references of the native start, and those of each PushLocalFrame after
+    frames: Vec<usize>,
+    // the exception the native's caller throws when it returns, unless the native clears it
+    pending: Option<ObjectRef>,
+    // a failure of the VM itself while the native was calling into it, reported once it returns
+    error: Option<Error>,
+}
+
+#[repr(C)]
+struct JavaVm {
+    functions: *const *const c_void,
+}
+
+#[repr(C)]
+struct NativeMethod {
+    name: *const c_char,
+    signature: *const c_char,
+    function: *const c_void,
+}
+
+// the function tables, which only ever hold function pointers, shared by every thread
+struct Shared<T>(T);
+
+unsafe impl<T> Sync for Shared<T> {}
+unsafe impl<T> Send for Shared<T> {}
+
+static FUNCTIONS: OnceLock<Shared<[*const c_void; FUNCTION_COUNT]>> = OnceLock::new();
+static INVOKE_FUNCTIONS: OnceLock<Shared<[*const c_void; 8]>> = OnceLock::new();
+static JAVA_VM: OnceLock<Shared<JavaVm>> = OnceLock::new();
+
+thread_local! {
+    // the JNIEnv of the native running on this thread, for GetEnv
+    static CURRENT: Cell<*mut JniEnv> = const { Cell::new(std::ptr::null_mut()) };
+}
+
+// runs `action` with a JNIEnv for a native running on `thread`, and gives what it returns along with the exception
+// it left pending; the local references made meanwhile are gone afterwards
+pub(super) fn with_env<T>(
+    interpreter: &mut Interpreter,
+    thread: &mut Thread,
+    action: impl FnOnce(&mut JniEnv) -> T,
+) -> Result<(T, Option<ObjectRef>), Error> {
+    let base = thread.local_refs.len();
+    let mut env = Box::new(JniEnv {
+        functions: functions(),
+        interpreter,
+        thread,
+        frames: vec![base],
+        pending: None,
+        error: None,
+    });
+    let outer = CURRENT.replace(&mut *env);
+    let value = action(&mut env);
+    CURRENT.set(outer);
+    thread.local_refs.truncate(base);
+    match env.error.take() {
+        Some(error) => Err(error),
+        None => Ok((value, env.pending)),
+    }
+}
+
+// the JavaVM natives get from GetJavaVM and JNI_OnLoad
+pub(super) fn java_vm() -> *const c_void {
+    let vm = JAVA_VM.get_or_init(|| Shared(JavaVm { functions: invoke_functions() }));
+    &vm.0 as *const JavaVm as *const c_void
+}
+
+// whether a library may ask for JNI `version` from JNI_OnLoad: 1.1, 1.2, 1.4, 1.6 and 1.8, then 9 on
+pub(super) fn is_supported(version: i32) -> bool {
+    matches!(version, 0x0001_0001 | 0x0001_0002 | 0x0001_0004 | 0x0001_0006 | 0x0001_0008)
+        || (0x0009_0000..=0x0015_0000).contains(&version) && version & 0xffff == 0
+}
+
+impl JniEnv {
+    // the VM, which natives only call into while the thread waits for them
+    fn vm(&mut self) -> (&mut Interpreter, &mut Thread) {
+        unsafe { (&mut *self.interpreter, &mut *self.thread) }
+    }
+
+    fn java(&mut self) -> Env<'_> {
+        let (interpreter, thread) = self.vm();
+        interpreter.env(thread)
+    }
+
+    fn jni(&mut self) -> &mut Jni {
+        self.vm().0.jni()
+    }
+
+    pub(super) fn local(&mut self, object: Option<ObjectRef>) -> Handle {
+        let Some(object) = object else {
+            return 0;
+        };
+        let local_refs = &mut self.vm().1.local_refs;
+        local_refs.push(Some(object));
+        local_refs.len() << 1
+    }
+
+    fn global(&mut self, object: Option<ObjectRef>, weak: bool) -> Handle {
+        match object {
+            Some(object) => self.jni().new_global_ref(object, weak) << 1 | 1,
+            None => 0,
+        }
+    }
+
+    pub(super) fn resolve(&mut self, handle: Handle) -> Option<ObjectRef> {
+        match handle {
+            0 => None,
+            _ if handle & 1 == 1 => self.jni().global_refs.get(handle >> 1).copied().flatten(),
+            _ => self.vm().1.local_refs.get((handle >> 1) - 1).copied().flatten(),
+        }
+    }
+
+    // what `handle` refers to, or a NullPointerException pending when that is nothing
+    fn object(&mut self, handle: Handle) -> Option<ObjectRef> {
+        let object = self.resolve(handle);
+        if object.is_none() {
+            self.fail::<()>(Flow::Throw(None));
+        }
+        object
+    }
+
+    // the class the Class object `handle` refers to stands for
+    fn class(&mut self, handle: Handle) -> Option<Rc<Class>> {
+        let mirror = self.object(handle)?;
+        Some(self.java().class_of_mirror(mirror))
+    }
+
+    fn mirror(&mut self, class: &Rc<Class>) -> Handle {
+        match self.java().mirror(class) {
+            Ok(mirror) => self.local(Some(mirror)),
+            Err(flow) => self.fail(flow),
+        }
+    }
+
+    fn method(&mut self, id: usize) -> Option<(Rc<Class>, Rc<Method>)> {
+        let method = self.jni().method(id);
+        if method.is_none() {
+            self.fail::<()>(raise("java/lang/InternalError", format!("invalid jmethodID {}", id)));
+        }
+        method
+    }
+
+    // makes what a native's `flow` raised or threw pending; 0 for a JNI function to return with
+    fn fail<T: Default>(&mut self, flow: Flow) -> T {
+        let (interpreter, thread) = self.vm();
+        match interpreter.exception(thread, flow) {
+            Ok(exception) => self.throw(exception),
+            Err(error) => self.failed(error),
+        }
+        T::default()
+    }
+
+    fn throw(&mut self, exception: ObjectRef) {
+        // the local reference keeps it alive
+        self.local(Some(exception));
+        self.pending = Some(exception);
+    }
+
+    fn failed(&mut self, error: Error) {
+        self.error.get_or_insert(error);
+    }
+
+    // what a call from native code gave, with what it threw pending
+    fn outcome<T>(&mut self, outcome: Result<Result<T, ObjectRef>, Error>) -> Option<T> {
+        match outcome {
+            Ok(Ok(value)) => Some(value),
+            Ok(Err(exception)) => {
+                self.throw(exception);
+                None
+            }
+            Err(error) => {
+                self.failed(error);
+                None
+            }
+        }
+    }
+
+    fn initialize(&mut self, class: &Rc<Class>) -> Option<()> {
+        let (interpreter, thread) = self.vm();
+        let outcome = interpreter.initialize_from_native(thread, class);
+        self.outcome(outcome)
+    }
+
+    fn call(&mut self, kind: u8, class: &Class, method: &Method, args: Vec<Slot>) -> Option<Vec<Slot>> {
+        let (interpreter, thread) = self.vm();
+        let outcome = interpreter.call_from_native(thread, kind, class, method, args);
+        self.outcome(outcome)
+    }
+
+    fn allocate(&mut self, class: &Rc<Class>) -> Option<ObjectRef> {
+        if class.is_abstract() || class.is_interface() || class.is_array() || class.is_primitive() {
+            return self.fail(raise("java/lang/InstantiationException", class.java_name()));
+        }
+        self.initialize(class)?;
+        match self.java().allocate(Object::new(class.clone())) {
+            Ok(object) => Some(object),
+            Err(flow) => self.fail(flow),
+        }
+    }
+
+    // the slots of a value of type `kind`, V for void, that C passed as `bits`, see Args::next
+    pub(super) fn slots(&mut self, kind: u8, bits: u64) -> Vec<Slot> {
+        let bits = super::abi::narrow(bits, kind);
+        match kind {
+            b'V' => Vec::new(),
+            b'J' => Slot::wide(bits, false).to_vec(),
+            b'D' => Slot::wide(bits, true).to_vec(),
+            b'F' => vec![Slot::Float(f32::from_bits(bits as u32))],
+            b'Z' => vec![Slot::Int((bits != 0) as i32)],
+            b'L' | b'[' => vec![Slot::Reference(self.resolve(bits as Handle))],
+            _ => vec![Slot::Int(bits as u32 as i32)],
+        }
+    }
+
+    // the bits C gets for the value of type `kind` in `slots`, a new local reference for an object
+    pub(super) fn bits(&mut self, kind: u8, slots: &[Slot]) -> u64 {
+        match kind {
+            b'V' => 0,
+            b'L' | b'[' => self.local(slots[0].reference()) as u64,
+            _ => value_bits(kind, slots),
+        }
+    }
+
+    // the value of field `id` of what `handle` refers to, or of the class when `is_static`
+    fn field(&mut self, handle: Handle, id: usize, is_static: bool) -> u64 {
+        let Some((class, field)) = self.jni().field(id) else {
+            return self.fail(raise("java/lang/InternalError", format!("invalid jfieldID {}", id)));
+        };
+        let range = field.slot..field.slot + field.slot_count();
+        let slots = match is_static {
+            true => class.static_fields.borrow()[range].to_vec(),
+            false => match self.object(handle) {
+                Some(object) => self.java().heap[object].fields.borrow()[range].to_vec(),
+                None => return 0,
+            },
+        };
+        self.bits(field.descriptor.as_bytes()[0], &slots)
+    }
+
+    fn set_field(&mut self, handle: Handle, id: usize, is_static: bool, bits: u64) {
+        let Some((class, field)) = self.jni().field(id) else {
+            return self.fail(raise("java/lang/InternalError", format!("invalid jfieldID {}", id)));
+        };
+        let slots = self.slots(field.descriptor.as_bytes()[0], bits);
+        let range = field.slot..field.slot + field.slot_count();
+        if is_static {
+            class.static_fields.borrow_mut()[range].clone_from_slice(&slots);
+        } else if let Some(object) = self.object(handle) {
+            let env = self.java();
+            env.heap[object].fields.borrow_mut()[range].clone_from_slice(&slots);
+            env.heap.write_barrier(object);
+        }
+    }
+
+    // the array `handle` refers to when it has elements of type `kind`
+    fn array(&mut self, handle: Handle, kind: u8) -> Option<ObjectRef> {
+        let array = self.object(handle)?;
+        let class_name = &self.java().heap[array].class.name;
+        if class_name.as_bytes() != [b'[', kind] {
+            let message = format!("{} is no array of {}", class_name, kind as char);
+            return self.fail(raise("java/lang/IllegalArgumentException", message));
+        }
+        Some(array)
+    }
+
+    // elements `start` to `start + length` of `array`, when they are all in it
+    fn region(&mut self, array: ObjectRef, start: i32, length: i32) -> Option<(usize, usize)> {
+        let array_length = self.java().heap[array].array_length();
+        match (usize::try_from(start), usize::try_from(length)) {
+            (Ok(start), Ok(length)) if start + length <= array_length => Some((start, length)),
+            _ => {
+                let end = start + length;
+                let message = format!("Array region {}..{} out of bounds for length {}", start, end, array_length);
+                self.fail(raise("java/lang/ArrayIndexOutOfBoundsException", message))
+            }
+        }
+    }
+
+    fn elements<T: Value>(&mut self, array: ObjectRef, start: usize, length: usize) -> Vec<T> {
+        let width = T::width();
+        let fields = self.java().heap[array].fields.borrow()[start * width..(start + length) * width].to_vec();
+        fields.chunks(width).map(|slots| T::from_bits(value_bits(T::KIND, slots))).collect()
+    }
+
+    fn set_elements<T: Value>(&mut self, array: ObjectRef, start: usize, values: &[T]) {
+        let width = T::width();
+        let slots: Vec<Slot> = values.iter().flat_map(|value| self.slots(T::KIND, value.to_bits())).collect();
+        let env = self.java();
+        env.heap[array].fields.borrow_mut()[start * width..start * width + slots.len()].clone_from_slice(&slots);
+    }
+
+    fn string_chars(&mut self, handle: Handle) -> Option<Vec<u16>> {
+        let string = self.object(handle)?;
+        Some(self.java().string_chars(string))
+    }
+
+    // chars `start` to `start + length` of `chars`, or a StringIndexOutOfBoundsException pending
+    fn string_region(&mut self, chars: &[u16], start: i32, length: i32) -> Option<(usize, usize)> {
+        match (usize::try_from(start), usize::try_from(length)) {
+            (Ok(start), Ok(length)) if start + length <= chars.len() => Some((start, start + length)),
+            _ => {
+                let message = format!("begin {}, end {}, length {}", start, start + length, chars.len());
+                self.fail(raise("java/lang/StringIndexOutOfBoundsException", message))
+            }
+        }
+    }
+
+    fn new_string(&mut self, chars: &[u16]) -> Handle {
+        match self.java().new_string_keeping(chars, &[]) {
+            Ok(string) => self.local(Some(string)),
+            Err(flow) => self.fail(flow),
+        }
+    }
+}
+
+// a JNI type by the descriptor letter of the Java one: jboolean to jdouble, jobject as a handle, and void;
+// their bits are the ones Args::next gives
+trait Value: Copy {
+    const KIND: u8;
+
+    fn from_bits(bits: u64) -> Self;
+
+    fn to_bits(self) -> u64;
+
+    // how many slots a value takes
+    fn width() -> usize {
+        match Self::KIND {
+            b'J' | b'D' => 2,
+            _ => 1,
+        }
+    }
+}
+
+macro_rules! value {
+    ($($type:ty = $kind:literal),*) => {
+        $(impl Value for $type {
+            const KIND: u8 = $kind;
+
+            fn from_bits(bits: u64) -> $type {
+                bits as $type
+            }
+
+            fn to_bits(self) -> u64 {
+                self as u64
+            }
+        })*
+    };
+}
+
+value!(u8 = b'Z', i8 = b'B', u16 = b'C', i16 = b'S', i32 = b'I', i64 = b'J', Handle = b'L');
+
+impl Value for f32 {
+    const KIND: u8 = b'F';
+
+    fn from_bits(bits: u64) -> f32 {
+        f32::from_bits(bits as u32)
+    }
+
+    fn to_bits(self) -> u64 {
+        f32::to_bits(self) as u64
+    }
+}
+
+impl Value for f64 {
+    const KIND: u8 = b'D';
+
+    fn from_bits(bits: u64) -> f64 {
+        f64::from_bits(bits)
+    }
+
+    fn to_bits(self) -> u64 {
+        f64::to_bits(self)
+    }
+}
+
+impl Value for () {
+    const KIND: u8 = b'V';
+
+    fn from_bits(_: u64) {}
+
+    fn to_bits(self) -> u64 {
+        0
+    }
+}
+
+fn raise(class_name: &'static str, message: String) -> Flow {
+    Flow::Raise {
+        class_name,
+        message: Some(message),
+    }
+}
+
+// a NUL-terminated modified UTF-8 string C passed, None for NULL
+unsafe fn utf(chars: *const c_char) -> Option<String> {
+    if chars.is_null() {
+        return None;
+    }
+    let bytes = CStr::from_ptr(chars).to_bytes();
+    let chars = modified_utf8_chars(bytes).unwrap_or_else(|| String::from_utf8_lossy(bytes).encode_utf16().collect());
+    Some(String::from_utf16_lossy(&chars))
+}
+
+// a slice C passed as a pointer and a length, which may be NULL when that is 0
+unsafe fn slice<'a, T>(pointer: *const T, length: usize) -> &'a [T] {
+    match length {
+        0 => &[],
+        _ => std::slice::from_raw_parts(pointer, length),
+    }
+}
+
+// hands `values` over to C, for it to give back to release
+fn leak<T>(values: Vec<T>) -> *mut T {
+    Box::into_raw(values.into_boxed_slice()) as *mut T
+}
+
+// takes back `length` values leak handed over
+unsafe fn release<T>(values: *mut T, length: usize) -> Vec<T> {
+    Box::from_raw(std::ptr::slice_from_raw_parts_mut(values, length)).into_vec()
+}
+
+unsafe fn set_is_copy(is_copy: *mut u8) {
+    if !is_copy.is_null() {
+        *is_copy = 1;
+    }
+}
+
+unsafe extern "C" fn get_version(_: *mut JniEnv) -> i32 {
+    JNI_VERSION_1_8
+}
+
+unsafe extern "C" fn define_class(
+    env: *mut JniEnv,
+    name: *const c_char,
+    _: Handle,
+    bytes: *const u8,
+    length: i32,
+) -> Handle {
+    let env = &mut *env;
+    let Some(name) = utf(name) else {
+        return env.fail(raise("java/lang/NoClassDefFoundError", String::from("DefineClass needs the class name")));
+    };
+    let content = slice(bytes, length.max(0) as usize).to_vec();
+    match env.vm().0.loader().define_class(&name, content) {
+        Ok(class) => env.mirror(&class),
+        Err(error) => env.fail(load_failure(error)),
+    }
+}
+
+// like HotSpot's, it initializes the class
+unsafe extern "C" fn find_class(env: *mut JniEnv, name: *const c_char) -> Handle {
+    let env = &mut *env;
+    let Some(name) = utf(name) else {
+        return env.fail(Flow::Throw(None));
+    };
+    let class = match env.java().load(&name) {
+        Ok(class) => class,
+        Err(flow) => return env.fail(flow),
+    };
+    match env.initialize(&class) {
+        Some(()) => env.mirror(&class),
+        None => 0,
+    }
+}
+
+unsafe extern "C" fn from_reflected_method(env: *mut JniEnv, method: Handle) -> usize {
+    let env = &mut *env;
+    let Some(method) = env.object(method) else {
+        return 0;
+    };
+    let (class, method) = env.java().reflected_method(method);
+    env.jni().method_id(&class, &method)
+}
+
+unsafe extern "C" fn from_reflected_field(env: *mut JniEnv, field: Handle) -> usize {
+    let env = &mut *env;
+    let Some(field) = env.object(field) else {
+        return 0;
+    };
+    let (class, field) = env.java().reflected_field(field);
+    env.jni().field_id(&class, &field)
+}
+
+unsafe extern "C" fn to_reflected_method(env: *mut JniEnv, _: Handle, method: usize, _: u8) -> Handle {
+    let env = &mut *env;
+    let Some((class, method)) = env.method(method) else {
+        return 0;
+    };
+    let slot = class.methods.iter().position(|declared| Rc::ptr_eq(declared, &method));
+    match env.java().reflect_method(&class, slot.expect("a method of its class"), &[]) {
+        Ok(method) => env.local(Some(method)),
+        Err(flow) => env.fail(flow),
+    }
+}
+
+unsafe extern "C" fn get_superclass(env: *mut JniEnv, class: Handle) -> Handle {
+    let env = &mut *env;
+    match env.class(class) {
+        Some(class) if !class.is_interface() => match &class.super_class {
+            Some(super_class) => env.mirror(super_class),
+            None => 0,
+        },
+        _ => 0,
+    }
+}
+
+unsafe extern "C" fn is_assignable_from(env: *mut JniEnv, from: Handle, to: Handle) -> u8 {
+    let env = &mut *env;
+    match (env.class(from), env.class(to)) {
+        (Some(from), Some(to)) => from.is_assignable_to(&to) as u8,
+        _ => 0,
+    }
+}
+
+unsafe extern "C" fn to_reflected_field(env: *mut JniEnv, _: Handle, field: usize, _: u8) -> Handle {
+    let env = &mut *env;
+    let Some((class, field)) = env.jni().field(field) else {
+        return env.fail(raise("java/lang/InternalError", format!("invalid jfieldID {}", field)));
+    };
+    let slot = class.fields.iter().position(|declared| Rc::ptr_eq(declared, &field));
+    match env.java().reflect_field(&class, slot.expect("a field of its class"), &[]) {
+        Ok(field) => env.local(Some(field)),
+        Err(flow) => env.fail(flow),
+    }
+}
+
+unsafe extern "C" fn throw(env: *mut JniEnv, exception: Handle) -> i32 {
+    let env = &mut *env;
+    match env.object(exception) {
+        Some(exception) => {
+            env.throw(exception);
+            JNI_OK
+        }
+        None => JNI_ERR,
+    }
+}
+
+// like the exceptions the VM raises, the new one gets its message without a constructor running
+unsafe extern "C" fn throw_new(env: *mut JniEnv, class: Handle, message: *const c_char) -> i32 {
+    let env = &mut *env;
+    let Some(class) = env.class(class) else {
+        return JNI_ERR;
+    };
+    let (interpreter, thread) = env.vm();
+    match interpreter.new_exception(thread, &class.name, utf(message)) {
+        Ok(exception) => {
+            env.throw(exception);
+            JNI_OK
+        }
+        Err(error) => {
+            env.failed(error);
+            JNI_ERR
+        }
+    }
+}
+
+unsafe extern "C" fn exception_occurred(env: *mut JniEnv) -> Handle {
+    let env = &mut *env;
+    env.local(env.pending)
+}
+
+unsafe extern "C" fn exception_describe(env: *mut JniEnv) {
+    let env = &mut *env;
+    if let Some(exception) = env.pending.take() {
+        let (interpreter, thread) = env.vm();
+        interpreter.describe(thread, exception);
+    }
+}
+
+unsafe extern "C" fn exception_clear(env: *mut JniEnv) {
+    (*env).pending = None;
+}
+
+unsafe extern "C" fn fatal_error(env: *mut JniEnv, message: *const c_char) {
+    let env = &mut *env;
+    let message = utf(message).unwrap_or_default();
+    let stderr = &mut env.java().output.stderr;
+    let _ = writeln!(stderr, "FATAL ERROR in native method: {}", message);
+    let _ = stderr.flush();
+    std::process::abort();
+}
+
+unsafe extern "C" fn push_local_frame(env: *mut JniEnv, _: i32) -> i32 {
+    let env = &mut *env;
+    let start = env.vm().1.local_refs.len();
+    env.frames.push(start);
+    JNI_OK
+}
+
+unsafe extern "C" fn pop_local_frame(env: *mut JniEnv, result: Handle) -> Handle {
+    let env = &mut *env;
+    let result = env.resolve(result);
+    if env.frames.len() > 1 {
+        let start = env.frames.pop().expect("a frame");
+        env.vm().1.local_refs.truncate(start);
+    }
+    env.local(result)
+}
+
+unsafe extern "C" fn new_global_ref(env: *mut JniEnv, object: Handle) -> Handle {
+    let env = &mut *env;
+    let object = env.resolve(object);
+    env.global(object, false)
+}
+
+unsafe extern "C" fn delete_global_ref(env: *mut JniEnv, global_ref: Handle) {
+    if global_ref & 1 == 1 {
+        (*env).jni().delete_global_ref(global_ref >> 1);
+    }
+}
+
+unsafe extern "C" fn delete_local_ref(env: *mut JniEnv, local_ref: Handle) {
+    if local_ref != 0 && local_ref & 1 == 0 {
+        if let Some(local_ref) = (*env).vm().1.local_refs.get_mut((local_ref >> 1) - 1) {
+            *local_ref = None;
+        }
+    }
+}
+
+unsafe extern "C" fn is_same_object(env: *mut JniEnv, a: Handle, b: Handle) -> u8 {
+    let env = &mut *env;
+    (env.resolve(a) == env.resolve(b)) as u8
+}
+
+unsafe extern "C" fn new_local_ref(env: *mut JniEnv, object: Handle) -> Handle {
+    let env = &mut *env;
+    let object = env.resolve(object);
+    env.local(object)
+}
+
+unsafe extern "C" fn ensure_local_capacity(_: *mut JniEnv, _: i32) -> i32 {
+    JNI_OK
+}
+
+unsafe extern "C" fn alloc_object(env: *mut JniEnv, class: Handle) -> Handle {
+    let env = &mut *env;
+    let Some(class) = env.class(class) else {
+        return 0;
+    };
+    let object = env.allocate(&class);
+    env.local(object)
+}
+
+// how a method is called: on the receiver's class, on the class declaring the method, or without a receiver
+#[derive(Clone, Copy, PartialEq)]
+enum Dispatch {
+    Virtual,
+    Nonvirtual,
+    Static,
+}
+
+// calls method `method` on what `object` refers to, or without a receiver when static, with `args`, and gives the
+// bits of what it returns
+unsafe fn invoke(env: *mut JniEnv, dispatch: Dispatch, object: Handle, method: usize, mut args: Args) -> u64 {
+    let env = &mut *env;
+    let Some((class, method)) = env.method(method) else {
+        return 0;
+    };
+    let mut slots = Vec::new();
+    if dispatch != Dispatch::Static {
+        let Some(object) = env.object(object) else {
+            return 0;
+        };
+        slots.push(Slot::Reference(Some(object)));
+    }
+    let parsed = method.parsed_descriptor.as_ref().expect("a method with a malformed descriptor");
+    for parameter in &parsed.parameters {
+        let kind = kind(parameter);
+        let bits = args.next(kind);
+        slots.extend(env.slots(kind, bits));
+    }
+    let reference_kind = match dispatch {
+        Dispatch::Static => REF_INVOKE_STATIC,
+        Dispatch::Nonvirtual => REF_INVOKE_SPECIAL,
+        Dispatch::Virtual if class.is_interface() => REF_INVOKE_INTERFACE,
+        Dispatch::Virtual => REF_INVOKE_VIRTUAL,
+    };
+    match env.call(reference_kind, &class, &method, slots) {
+        Some(value) => env.bits(parsed.return_type.as_ref().map_or(b'V', kind), &value),
+        None => 0,
+    }
+}
+
+// allocates an object of the class `class` refers to and calls constructor `method` on it
+unsafe fn new_object(env: *mut JniEnv, class: Handle, method: usize, args: Args) -> Handle {
+    let Some(class) = (*env).class(class) else {
+        return 0;
+    };
+    let object = (*env).allocate(&class);
+    let object = (*env).local(object);
+    if object != 0 {
+        invoke(env, Dispatch::Nonvirtual, object, method, args);
+    }
+    match (*env).pending {
+        Some(_) => 0,
+        None => object,
+    }
+}
+
+variadic!(new_object_spread, 2 -> Handle, |env, fixed: &[u64], args| new_object(
+    env,
+    fixed[0] as Handle,
+    fixed[1] as usize,
+    args
+));
+
+unsafe extern "C" fn new_object_v(env: *mut JniEnv, class: Handle, method: usize, args: *mut VaList) -> Handle {
+    new_object(env, class, method, Args::VaList(args))
+}
+
+unsafe extern "C" fn new_object_a(env: *mut JniEnv, class: Handle, method: usize, args: *const u64) -> Handle {
+    new_object(env, class, method, Args::Array(args))
+}
+
+unsafe extern "C" fn get_object_class(env: *mut JniEnv, object: Handle) -> Handle {
+    let env = &mut *env;
+    let Some(object) = env.object(object) else {
+        return 0;
+    };
+    let class = env.java().heap[object].class.clone();
+    env.mirror(&class)
+}
+
+unsafe extern "C" fn is_instance_of(env: *mut JniEnv, object: Handle, class: Handle) -> u8 {
+    let env = &mut *env;
+    let Some(class) = env.class(class) else {
+        return 0;
+    };
+    match env.resolve(object) {
+        Some(object) => env.java().heap[object].class.is_assignable_to(&class) as u8,
+        None => 1,
+    }
+}
+
+// the jmethodID of the method of the class `class` refers to, or one it inherits, with the given name and
+// descriptor, which is static when `is_static`; the class is initialized first
+unsafe fn method_id(
+    env: *mut JniEnv,
+    class: Handle,
+    name: *const c_char,
+    descriptor: *const c_char,
+    is_static: bool,
+) -> usize {
+    let env = &mut *env;
+    let Some(class) = env.class(class) else {
+        return 0;
+    };
+    let (name, descriptor) = (utf(name).unwrap_or_default(), utf(descriptor).unwrap_or_default());
+    env.initialize(&class);
+    if env.pending.is_some() {
+        return 0;
+    }
+    let found = match name.as_str() {
+        "<init>" | "<clinit>" => {
+            let method = class.methods.iter().find(|method| method.name == name && method.descriptor == descriptor);
+            method.map(|method| (class.clone(), method.clone()))
+        }
+        _ => class.find_method(&name, &descriptor),
+    };
+    match found {
+        Some((class, method)) if method.is_static() == is_static => env.jni().method_id(&class, &method),
+        _ => env.fail(raise("java/lang/NoSuchMethodError", name)),
+    }
+}
+
+unsafe extern "C" fn get_method_id(
+    env: *mut JniEnv,
+    class: Handle,
+    name: *const c_char,
+    descriptor: *const c_char,
+) -> usize {
+    method_id(env, class, name, descriptor, false)
+}
+
+unsafe extern "C" fn get_static_method_id(
+    env: *mut JniEnv,
+    class: Handle,
+    name: *const c_char,
+    descriptor: *const c_char,
+) -> usize {
+    method_id(env, class, name, descriptor, true)
+}
+
+variadic!(call_method<T: Value>, 2 -> T, |env, fixed: &[u64], args| T::from_bits(invoke(
+    env,
+    Dispatch::Virtual,
+    fixed[0] as Handle,
+    fixed[1] as usize,
+    args
+)));
+
+unsafe extern "C" fn call_method_v<T: Value>(env: *mut JniEnv, object: Handle, method: usize, args: *mut VaList) -> T {
+    T::from_bits(invoke(env, Dispatch::Virtual, object, method, Args::VaList(args)))
+}
+
+unsafe extern "C" fn call_method_a<T: Value>(env: *mut JniEnv, object: Handle, method: usize, args: *const u64) -> T {
+    T::from_bits(invoke(env, Dispatch::Virtual, object, method, Args::Array(args)))
+}
+
+variadic!(call_nonvirtual_method<T: Value>, 3 -> T, |env, fixed: &[u64], args| T::from_bits(invoke(
+    env,
+    Dispatch::Nonvirtual,
+    fixed[0] as Handle,
+    fixed[2] as usize,
+    args
+)));
+
+unsafe extern "C" fn call_nonvirtual_method_v<T: Value>(
+    env: *mut JniEnv,
+    object: Handle,
+    _: Handle,
+    method: usize,
+    args: *mut VaList,
+) -> T {
+    T::from_bits(invoke(env, Dispatch::Nonvirtual, object, method, Args::VaList(args)))
+}
+
+unsafe extern "C" fn call_nonvirtual_method_a<T: Value>(
+    env: *mut JniEnv,
+    object: Handle,
+    _: Handle,
+    method: usize,
+    args: *const u64,
+) -> T {
+    T::from_bits(invoke(env, Dispatch::Nonvirtual, object, method, Args::Array(args)))
+}
+
+variadic!(call_static_method<T: Value>, 2 -> T, |env, fixed: &[u64], args| T::from_bits(invoke(
+    env,
+    Dispatch::Static,
+    0,
+    fixed[1] as usize,
+    args
+)));
+
+unsafe extern "C" fn call_static_method_v<T: Value>(
+    env: *mut JniEnv,
+    _: Handle,
+    method: usize,
+    args: *mut VaList,
+) -> T {
+    T::from_bits(invoke(env, Dispatch::Static, 0, method, Args::VaList(args)))
+}
+
+unsafe extern "C" fn call_static_method_a<T: Value>(env: *mut JniEnv, _: Handle, method: usize, args: *const u64) -> T {
+    T::from_bits(invoke(env, Dispatch::Static, 0, method, Args::Array(args)))
+}
+
+// the jfieldID of field of the class `class` refers to, or of a super class, with the given name and descriptor,
+// which is static when `is_static`; the class is initialized first
+unsafe fn field_id(
+    env: *mut JniEnv,
+    class: Handle,
+    name: *const c_char,
+    descriptor: *const c_char,
+    is_static: bool,
+) -> usize {
+    let env = &mut *env;
+    let Some(class) = env.class(class) else {
+        return 0;
+    };
+    let (name, descriptor) = (utf(name).unwrap_or_default(), utf(descriptor).unwrap_or_default());
+    env.initialize(&class);
+    if env.pending.is_some() {
+        return 0;
+    }
+    match class.find_field(&name, &descriptor) {
+        Some((class, field)) if field.is_static() == is_static => env.jni().field_id(&class, &field),
+        _ => env.fail(raise("java/lang/NoSuchFieldError", name)),
+    }
+}
+
+unsafe extern "C" fn get_field_id(
+    env: *mut JniEnv,
+    class: Handle,
+    name: *const c_char,
+    descriptor: *const c_char,
+) -> usize {
+    field_id(env, class, name, descriptor, false)
+}
+
+unsafe extern "C" fn get_static_field_id(
+    env: *mut JniEnv,
+    class: Handle,
+    name: *const c_char,
+    descriptor: *const c_char,
+) -> usize {
+    field_id(env, class, name, descriptor, true)
+}
+
+unsafe extern "C" fn get_field<T: Value>(env: *mut JniEnv, object: Handle, field: usize) -> T {
+    T::from_bits((*env).field(object, field, false))
+}
+
+unsafe extern "C" fn set_field<T: Value>(env: *mut JniEnv, object: Handle, field: usize, value: T) {
+    (*env).set_field(object, field, false, value.to_bits())
+}
+
+unsafe extern "C" fn get_static_field<T: Value>(env: *mut JniEnv, _: Handle, field: usize) -> T {
+    T::from_bits((*env).field(0, field, true))
+}
+
+unsafe extern "C" fn set_static_field<T: Value>(env: *mut JniEnv, _: Handle, field: usize, value: T) {
+    (*env).set_field(0, field, true, value.to_bits())
+}
+
+unsafe extern "C" fn new_string(env: *mut JniEnv, chars: *const u16, length: i32) -> Handle {
+    let env = &mut *env;
+    env.new_string(slice(chars, length.max(0) as usize))
+}
+
+unsafe extern "C" fn get_string_length(env: *mut JniEnv, string: Handle) -> i32 {
+    (*env).string_chars(string).map_or(0, |chars| chars.len() as i32)
+}
+
+unsafe extern "C" fn get_string_chars(env: *mut JniEnv, string: Handle, is_copy: *mut u8) -> *const u16 {
+    let Some(chars) = (*env).string_chars(string) else {
+        return std::ptr::null();
+    };
+    set_is_copy(is_copy);
+    leak(chars)
+}
+
+unsafe extern "C" fn release_string_chars(env: *mut JniEnv, string: Handle, chars: *const u16) {
+    if let Some(length) = (*env).string_chars(string).map(|chars| chars.len()) {
+        release(chars as *mut u16, length);
+    }
+}
+
+unsafe extern "C" fn new_string_utf(env: *mut JniEnv, chars: *const c_char) -> Handle {
+    let env = &mut *env;
+    if chars.is_null() {
+        return 0;
+    }
+    let bytes = CStr::from_ptr(chars).to_bytes();
+    let chars = modified_utf8_chars(bytes).unwrap_or_else(|| String::from_utf8_lossy(bytes).encode_utf16().collect());
+    env.new_string(&chars)
+}
+
+unsafe extern "C" fn get_string_utf_length(env: *mut JniEnv, string: Handle) -> i32 {
+    (*env).string_chars(string).map_or(0, |chars| encode_modified_utf8(&chars).len() as i32)
+}
+
+unsafe extern "C" fn get_string_utf_chars(env: *mut JniEnv, string: Handle, is_copy: *mut u8) -> *const c_char {
+    let Some(chars) = (*env).string_chars(string) else {
+        return std::ptr::null();
+    };
+    set_is_copy(is_copy);
+    CString::new(encode_modified_utf8(&chars)).expect("modified UTF-8 has no NUL").into_raw()
+}
+
+unsafe extern "C" fn release_string_utf_chars(_: *mut JniEnv, _: Handle, chars: *const c_char) {
+    if !chars.is_null() {
+        drop(CString::from_raw(chars as *mut c_char));
+    }
+}
+
+unsafe extern "C" fn get_string_region(env: *mut JniEnv, string: Handle, start: i32, length: i32, buffer: *mut u16) {
+    let env = &mut *env;
+    let Some(chars) = env.string_chars(string) else {
+        return;
+    };
+    if let Some((start, end)) = env.string_region(&chars, start, length) {
+        std::ptr::copy_nonoverlapping(chars[start..end].as_ptr(), buffer, end - start);
+    }
+}
+
+unsafe extern "C" fn get_string_utf_region(
+    env: *mut JniEnv,
+    string: Handle,
+    start: i32,
+    length: i32,
+    buffer: *mut c_char,
+) {
+    let env = &mut *env;
+    let Some(chars) = env.string_chars(string) else {
+        return;
+    };
+    if let Some((start, end)) = env.string_region(&chars, start, length) {
+        let bytes = encode_modified_utf8(&chars[start..end]);
+        std::ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, buffer, bytes.len());
+        *buffer.add(bytes.len()) = 0;
+    }
+}
+
+unsafe extern "C" fn get_array_length(env: *mut JniEnv, array: Handle) -> i32 {
+    let env = &mut *env;
+    match env.object(array) {
+        Some(array) => env.java().heap[array].array_length() as i32,
+        None => 0,
+    }
+}
+
+unsafe extern "C" fn new_object_array(env: *mut JniEnv, length: i32, class: Handle, initial: Handle) -> Handle {
+    let env = &mut *env;
+    let Some(class) = env.class(class) else {
+        return 0;
+    };
+    let Ok(length) = usize::try_from(length) else {
+        return env.fail(raise("java/lang/NegativeArraySizeException", length.to_string()));
+    };
+    let class_name = match class.is_array() {
+        true => format!("[{}", class.name),
+        false => format!("[L{};", class.name),
+    };
+    let initial = env.resolve(initial);
+    let mut java = env.java();
+    match java.new_array(&class_name, &[length]) {
+        Ok(array) => {
+            java.heap[array].fields.borrow_mut().fill(Slot::Reference(initial));
+            java.heap.write_barrier(array);
+            env.local(Some(array))
+        }
+        Err(flow) => env.fail(flow),
+    }
+}
+
+unsafe extern "C" fn get_object_array_element(env: *mut JniEnv, array: Handle, index: i32) -> Handle {
+    let env = &mut *env;
+    let Some(array) = env.object(array) else {
+        return 0;
+    };
+    let Some((index, _)) = env.region(array, index, 1) else {
+        return 0;
+    };
+    let element = env.java().heap[array].fields.borrow()[index].reference();
+    env.local(element)
+}
+
+unsafe extern "C" fn set_object_array_element(env: *mut JniEnv, array: Handle, index: i32, value: Handle) {
+    let env = &mut *env;
+    let Some(array) = env.object(array) else {
+        return;
+    };
+    let Some((index, _)) = env.region(array, index, 1) else {
+        return;
+    };
+    let value = env.resolve(value);
+    let java = env.java();
+    if let (Some(value), Some(component)) = (value, &java.heap[array].class.component) {
+        if !java.heap[value].class.is_assignable_to(component) {
+            let message = java.heap[value].class.java_name();
+            return env.fail(raise("java/lang/ArrayStoreException", message));
+        }
+    }
+    java.heap[array].fields.borrow_mut()[index] = Slot::Reference(value);
+    java.heap.write_barrier(array);
+}
+
+unsafe extern "C" fn new_array<T: Value>(env: *mut JniEnv, length: i32) -> Handle {
+    let env = &mut *env;
+    let Ok(length) = usize::try_from(length) else {
+        return env.fail(raise("java/lang/NegativeArraySizeException", length.to_string()));
+    };
+    match env.java().new_array(&format!("[{}", T::KIND as char), &[length]) {
+        Ok(array) => env.local(Some(array)),
+        Err(flow) => env.fail(flow),
+    }
+}
+
+// the elements are copied out, and copied back on release
+unsafe extern "C" fn get_array_elements<T: Value>(env: *mut JniEnv, array: Handle, is_copy: *mut u8) -> *mut T {
+    let env = &mut *env;
+    let Some(array) = env.array(array, T::KIND) else {
+        return std::ptr::null_mut();
+    };
+    set_is_copy(is_copy);
+    let length = env.java().heap[array].array_length();
+    leak(env.elements::<T>(array, 0, length))
+}
+
+unsafe extern "C" fn release_array_elements<T: Value>(env: *mut JniEnv, array: Handle, elements: *mut T, mode: i32) {
+    let env = &mut *env;
+    let Some(array) = env.array(array, T::KIND) else {
+        return;
+    };
+    let length = env.java().heap[array].array_length();
+    let values = std::slice::from_raw_parts(elements, length);
+    if mode != JNI_ABORT {
+        env.set_elements(array, 0, values);
+    }
+    if mode != JNI_COMMIT {
+        release(elements, length);
+    }
+}
+
+unsafe extern "C" fn get_array_region<T: Value>(
+    env: *mut JniEnv,
+    array: Handle,
+    start: i32,
+    length: i32,
+    buffer: *mut T,
+) {
+    let env = &mut *env;
+    let Some(array) = env.array(array, T::KIND) else {
+        return;
+    };
+    if let Some((start, length)) = env.region(array, start, length) {
+        let values = env.elements::<T>(array, start, length);
+        std::ptr::copy_nonoverlapping(values.as_ptr(), buffer, length);
+    }
+}
+
+unsafe extern "C" fn set_array_region<T: Value>(
+    env: *mut JniEnv,
+    array: Handle,
+    start: i32,
+    length: i32,
+    buffer: *const T,
+) {
+    let env = &mut *env;
+    let Some(array) = env.array(array, T::KIND) else {
+        return;
+    };
+    if let Some((start, length)) = env.region(array, start, length) {
+        env.set_elements(array, start, slice(buffer, length));
+    }
+}
+
+unsafe extern "C" fn register_natives(
+    env: *mut JniEnv,
+    class: Handle,
+    methods: *const NativeMethod,
+    count: i32,
+) -> i32 {
+    let env = &mut *env;
+    let Some(class) = env.class(class) else {
+        return JNI_ERR;
+    };
+    for method in slice(methods, count.max(0) as usize) {
+        let (name, descriptor) = (utf(method.name).unwrap_or_default(), utf(method.signature).unwrap_or_default());
+        let declared = class.methods.iter().any(|declared| {
+            declared.name == name && declared.descriptor == descriptor && declared.is_native()
+        });
+        if !declared {
+            let message = format!("Method '{}{}' name or signature does not match", name, descriptor);
+            env.fail::<()>(raise("java/lang/NoSuchMethodError", message));
+            return JNI_ERR;
+        }
+        env.jni().register(&class, &name, &descriptor, method.function as usize);
+    }
+    JNI_OK
+}
+
+unsafe extern "C" fn unregister_natives(env: *mut JniEnv, class: Handle) -> i32 {
+    let env = &mut *env;
+    let Some(class) = env.class(class) else {
+        return JNI_ERR;
+    };
+    env.jni().unregister(&class);
+    JNI_OK
+}
+
+// the other threads do not run while a native does, so a monitor one of them owns cannot be entered
+unsafe extern "C" fn monitor_enter(env: *mut JniEnv, object: Handle) -> i32 {
+    let env = &mut *env;
+    let Some(object) = env.object(object) else {
+        return JNI_ERR;
+    };
+    let thread = env.vm().1.id;
+    match env.java().heap[object].monitor.try_enter(thread, 1) {
+        true => JNI_OK,
+        false => JNI_ERR,
+    }
+}
+
+unsafe extern "C" fn monitor_exit(env: *mut JniEnv, object: Handle) -> i32 {
+    let env = &mut *env;
+    let Some(object) = env.object(object) else {
+        return JNI_ERR;
+    };
+    let thread = env.vm().1.id;
+    let java = env.java();
+    match java.heap[object].monitor.exit(thread) {
+        Ok(next) => {
+            if let Some(next) = next {
+                java.scheduler.wake(next);
+            }
+            JNI_OK
+        }
+        Err(()) => {
+            let message = String::from("current thread is not owner");
+            env.fail::<()>(raise("java/lang/IllegalMonitorStateException", message));
+            JNI_ERR
+        }
+    }
+}
+
+unsafe extern "C" fn get_java_vm(_: *mut JniEnv, vm: *mut *const c_void) -> i32 {
+    *vm = java_vm();
+    JNI_OK
+}
+
+// arrays have their elements copied out like Get<Type>ArrayElements does
+unsafe extern "C" fn get_primitive_array_critical(env: *mut JniEnv, array: Handle, is_copy: *mut u8) -> *mut c_void {
+    let Some(object) = (*env).object(array) else {
+        return std::ptr::null_mut();
+    };
+    let kind = (*env).java().heap[object].class.name.as_bytes().get(1).copied();
+    match kind {
+        Some(b'Z') => get_array_elements::<u8>(env, array, is_copy) as *mut c_void,
+        Some(b'B') => get_array_elements::<i8>(env, array, is_copy) as *mut c_void,
+        Some(b'C') => get_array_elements::<u16>(env, array, is_copy) as *mut c_void,
+        Some(b'S') => get_array_elements::<i16>(env, array, is_copy) as *mut c_void,
+        Some(b'I') => get_array_elements::<i32>(env, array, is_copy) as *mut c_void,
+        Some(b'J') => get_array_elements::<i64>(env, array, is_copy) as *mut c_void,
+        Some(b'F') => get_array_elements::<f32>(env, array, is_copy) as *mut c_void,
+        Some(b'D') => get_array_elements::<f64>(env, array, is_copy) as *mut c_void,
+        _ => std::ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn release_primitive_array_critical(
+    env: *mut JniEnv,
+    array: Handle,
+    elements: *mut c_void,
+    mode: i32,
+) {
+    let Some(object) = (*env).object(array) else {
+        return;
+    };
+    match (*env).java().heap[object].class.name.as_bytes().get(1) {
+        Some(b'Z') => release_array_elements::<u8>(env, array, elements as *mut u8, mode),
+        Some(b'B') => release_array_elements::<i8>(env, array, elements as *mut i8, mode),
+        Some(b'C') => release_array_elements::<u16>(env, array, elements as *mut u16, mode),
+        Some(b'S') => release_array_elements::<i16>(env, array, elements as *mut i16, mode),
+        Some(b'I') => release_array_elements::<i32>(env, array, elements as *mut i32, mode),
+        Some(b'J') => release_array_elements::<i64>(env, array, elements as *mut i64, mode),
+        Some(b'F') => release_array_elements::<f32>(env, array, elements as *mut f32, mode),
+        Some(b'D') => release_array_elements::<f64>(env, array, elements as *mut f64, mode),
+        _ => {}
+    }
+}
+
+unsafe extern "C" fn new_weak_global_ref(env: *mut JniEnv, object: Handle) -> Handle {
+    let env = &mut *env;
+    let object = env.resolve(object);
+    env.global(object, true)
+}
+
+unsafe extern "C" fn exception_check(env: *mut JniEnv) -> u8 {
+    (*env).pending.is_some() as u8
+}
+
+// there are no direct buffers, which JNI allows for
+unsafe extern "C" fn new_direct_byte_buffer(_: *mut JniEnv, _: *mut c_void, _: i64) -> Handle {
+    0
+}
+
+unsafe extern "C" fn get_direct_buffer_address(_: *mut JniEnv, _: Handle) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+unsafe extern "C" fn get_direct_buffer_capacity(_: *mut JniEnv, _: Handle) -> i64 {
+    -1
+}
+
+unsafe extern "C" fn get_object_ref_type(env: *mut JniEnv, handle: Handle) -> i32 {
+    let env = &mut *env;
+    match handle {
+        _ if env.resolve(handle).is_none() => 0,
+        _ if handle & 1 == 0 => 1,
+        _ if env.jni().weak[handle >> 1] => 3,
+        _ => 2,
+    }
+}
+
+unsafe extern "C" fn get_module(env: *mut JniEnv, class: Handle) -> Handle {
+    let env = &mut *env;
+    let Some(mirror) = env.object(class) else {
+        return 0;
+    };
+    let object = &env.java().heap[mirror];
+    let module = match object.class.find_field("module", "Ljava/lang/Module;") {
+        Some(_) => object.get_ref_field("module", "Ljava/lang/Module;"),
+        None => None,
+    };
+    env.local(module)
+}
+
+unsafe extern "C" fn is_virtual_thread(_: *mut JniEnv, _: Handle) -> u8 {
+    0
+}
+
+// fills in `table` from `start` on, every `stride` entries, with `function` for each of the types
+macro_rules! typed {
+    ($table:ident[$start:literal, $stride:literal] = $function:ident::<$($type:ty),*>) => {
+        let functions = [$($function::<$type> as *const c_void),*];
+        for (index, function) in functions.into_iter().enumerate() {
+            $table[$start + index * $stride] = function;
+        }
+    };
+}
+
+fn functions() -> *const *const c_void {
+    let functions = FUNCTIONS.get_or_init(|| {
+        let mut table = [std::ptr::null(); FUNCTION_COUNT];
+        table[4] = get_version as *const c_void;
+        table[5] = define_class as *const c_void;
+        table[6] = find_class as *const c_void;
+        table[7] = from_reflected_method as *const c_void;
+        table[8] = from_reflected_field as *const c_void;
+        table[9] = to_reflected_method as *const c_void;
+        table[10] = get_superclass as *const c_void;
+        table[11] = is_assignable_from as *const c_void;
+        table[12] = to_reflected_field as *const c_void;
+        table[13] = throw as *const c_void;
+        table[14] = throw_new as *const c_void;
+        table[15] = exception_occurred as *const c_void;
+        table[16] = exception_describe as *const c_void;
+        table[17] = exception_clear as *const c_void;
+        table[18] = fatal_error as *const c_void;
+        table[19] = push_local_frame as *const c_void;
+        table[20] = pop_local_frame as *const c_void;
+        table[21] = new_global_ref as *const c_void;
+        table[22] = delete_global_ref as *const c_void;
+        table[23] = delete_local_ref as *const c_void;
+        table[24] = is_same_object as *const c_void;
+        table[25] = new_local_ref as *const c_void;
+        table[26] = ensure_local_capacity as *const c_void;
+        table[27] = alloc_object as *const c_void;
+        table[28] = new_object_spread as *const c_void;
+        table[29] = new_object_v as *const c_void;
+        table[30] = new_object_a as *const c_void;
+        table[31] = get_object_class as *const c_void;
+        table[32] = is_instance_of as *const c_void;
+        table[33] = get_method_id as *const c_void;
+        typed!(table[34, 3] = call_method::<Handle, u8, i8, u16, i16, i32, i64, f32, f64, ()>);
+        typed!(table[35, 3] = call_method_v::<Handle, u8, i8, u16, i16, i32, i64, f32, f64, ()>);
+        typed!(table[36, 3] = call_method_a::<Handle, u8, i8, u16, i16, i32, i64, f32, f64, ()>);
+        typed!(table[64, 3] = call_nonvirtual_method::<Handle, u8, i8, u16, i16, i32, i64, f32, f64, ()>);
+        typed!(table[65, 3] = call_nonvirtual_method_v::<Handle, u8, i8, u16, i16, i32, i64, f32, f64, ()>);
+        typed!(table[66, 3] = call_nonvirtual_method_a::<Handle, u8, i8, u16, i16, i32, i64, f32, f64, ()>);
+        table[94] = get_field_id as *const c_void;
+        typed!(table[95, 1] = get_field::<Handle, u8, i8, u16, i16, i32, i64, f32, f64>);
+        typed!(table[104, 1] = set_field::<Handle, u8, i8, u16, i16, i32, i64, f32, f64>);
+        table[113] = get_static_method_id as *const c_void;
+        typed!(table[114, 3] = call_static_method::<Handle, u8, i8, u16, i16, i32, i64, f32, f64, ()>);
+        typed!(table[115, 3] = call_static_method_v::<Handle, u8, i8, u16, i16, i32, i64, f32, f64, ()>);
+        typed!(table[116, 3] = call_static_method_a::<Handle, u8, i8, u16, i16, i32, i64, f32, f64, ()>);
+        table[144] = get_static_field_id as *const c_void;
+        typed!(table[145, 1] = get_static_field::<Handle, u8, i8, u16, i16, i32, i64, f32, f64>);
+        typed!(table[154, 1] = set_static_field::<Handle, u8, i8, u16, i16, i32, i64, f32, f64>);
+        table[163] = new_string as *const c_void;
+        table[164] = get_string_length as *const c_void;
+        table[165] = get_string_chars as *const c_void;
+        table[166] = release_string_chars as *const c_void;
+        table[167] = new_string_utf as *const c_void;
+        table[168] = get_string_utf_length as *const c_void;
+        table[169] = get_string_utf_chars as *const c_void;
+        table[170] = release_string_utf_chars as *const c_void;
+        table[171] = get_array_length as *const c_void;
+        table[172] = new_object_array as *const c_void;
+        table[173] = get_object_array_element as *const c_void;
+        table[174] = set_object_array_element as *const c_void;
+        typed!(table[175, 1] = new_array::<u8, i8, u16, i16, i32, i64, f32, f64>);
+        typed!(table[183, 1] = get_array_elements::<u8, i8, u16, i16, i32, i64, f32, f64>);
+        typed!(table[191, 1] = release_array_elements::<u8, i8, u16, i16, i32, i64, f32, f64>);
+        typed!(table[199, 1] = get_array_region::<u8, i8, u16, i16, i32, i64, f32, f64>);
+        typed!(table[207, 1] = set_array_region::<u8, i8, u16, i16, i32, i64, f32, f64>);
+        table[215] = register_natives as *const c_void;
+        table[216] = unregister_natives as *const c_void;
+        table[217] = monitor_enter as *const c_void;
+        table[218] = monitor_exit as *const c_void;
+        table[219] = get_java_vm as *const c_void;
+        table[220] = get_string_region as *const c_void;
+        table[221] = get_string_utf_region as *const c_void;
+        table[222] = get_primitive_array_critical as *const c_void;
+        table[223] = release_primitive_array_critical as *const c_void;
+        table[224] = get_string_chars as *const c_void;
+        table[225] = release_string_chars as *const c_void;
+        table[226] = new_weak_global_ref as *const c_void;
+        table[227] = delete_global_ref as *const c_void;
+        table[228] = exception_check as *const c_void;
+        table[229] = new_direct_byte_buffer as *const c_void;
+        table[230] = get_direct_buffer_address as *const c_void;
+        table[231] = get_direct_buffer_capacity as *const c_void;
+        table[232] = get_object_ref_type as *const c_void;
+        table[233] = get_module as *const c_void;
+        table[234] = is_virtual_thread as *const c_void;
+        Shared(table)
+    });
+    functions.0.as_ptr()
+}
+
+// the invocation interface: threads cannot be attached, natives get the JNIEnv of the thread that called them
+unsafe extern "C" fn destroy_java_vm(_: *const JavaVm) -> i32 {
+    JNI_ERR
+}
+
+unsafe extern "C" fn attach_current_thread(_: *const JavaVm, env: *mut *mut JniEnv, _: *mut c_void) -> i32 {
+    let current = CURRENT.get();
+    if current.is_null() {
+        return JNI_ERR;
+    }
+    *env = current;
+    JNI_OK
+}
+
+unsafe extern "C" fn detach_current_thread(_: *const JavaVm) -> i32 {
+    match CURRENT.get().is_null() {
+        true => JNI_OK,
+        false => JNI_ERR,
+    }
+}
+
+unsafe extern "C" fn get_env(_: *const JavaVm, env: *mut *mut JniEnv, version: i32) -> i32 {
+    if !is_supported(version) {
+        *env = std::ptr::null_mut();
+        return JNI_EVERSION;
+    }
+    *env = CURRENT.get();
+    match (*env).is_null() {
+        true => JNI_EDETACHED,
+        false => JNI_OK,
+    }
+}
+
+fn invoke_functions() -> *const *const c_void {
+    let functions = INVOKE_FUNCTIONS.get_or_init(|| {
+        let mut table = [std::ptr::null(); 8];
+        table[3] = destroy_java_vm as *const c_void;
+        table[4] = attach_current_thread as *const c_void;
+        table[5] = detach_current_thread as *const c_void;
+        table[6] = get_env as *const c_void;
+        table[7] = attach_current_thread as *const c_void;
+        Shared(table)
+    });
+    functions.0.as_ptr()
+}
@@ -0,0 +1,330 @@
+// the Java Native Interface: natives in the shared libraries System.load and System.loadLibrary load, found by the
+// names JNI gives them or registered by the library, and called with a JNIEnv whose functions env implements
+pub(crate) mod abi;
+pub(crate) mod env;
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::ffi::{c_void, CStr, CString};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::classfile::descriptor::FieldType;
+use crate::error::Error;
+use crate::instructions::Flow;
+use crate::interpreter::Interpreter;
+use crate::log::log;
+use crate::runtime::class::{Class, Field, Method};
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{Slot, Thread};
+
+use abi::Arguments;
+use env::{with_env, JniEnv};
+
+// where HotSpot looks for libraries on Linux after LD_LIBRARY_PATH
+const DEFAULT_LIBRARY_PATH: &str = "/usr/java/packages/lib:/usr/lib64:/lib64:/lib:/usr/lib";
+
+#[derive(Default)]
+pub(crate) struct Jni {
+    libraries: Vec<Library>,
+    // the functions of the natives linked so far and of the ones libraries registered, by class, name and
+    // descriptor
+    linked: HashMap<String, usize>,
+    // what the global references of native code refer to, by index; None once deleted. Weak ones are
+    // kept alive like the others
+    pub global_refs: Vec<Option<ObjectRef>>,
+    weak: Vec<bool>,
+    // what the jmethodIDs and jfieldIDs handed out stand for, by index, and the index of each
+    methods: Vec<(Rc<Class>, Rc<Method>)>,
+    fields: Vec<(Rc<Class>, Rc<Field>)>,
+    ids: HashMap<String, usize>,
+}
+
+struct Library {
+    path: String,
+    handle: *mut c_void,
+}
+
+impl Jni {
+    // the function of native `method` of `class`: the one a library registered for it, or else the one a loaded
+    // library exports under the short name JNI gives the native or the long one, which adds the argument types
+    pub(crate) fn link(&mut self, class: &Class, method: &Method) -> Option<usize> {
+        let key = key(&class.name, &method.name, &method.descriptor);
+        if let Some(function) = self.linked.get(&key) {
+            return Some(*function);
+        }
+        let short = format!("Java_{}_{}", mangle(&class.name), mangle(&method.name));
+        let arguments = &method.descriptor[1..method.descriptor.find(')')?];
+        let long = format!("{}__{}", short, mangle(arguments));
+        let function = (self.libraries.iter().find_map(|library| library.symbol(&short)))
+            .or_else(|| self.libraries.iter().find_map(|library| library.symbol(&long)))?;
+        self.linked.insert(key, function);
+        Some(function)
+    }
+
+    fn register(&mut self, class: &Class, name: &str, descriptor: &str, function: usize) {
+        self.linked.insert(key(&class.name, name, descriptor), function);
+    }
+
+    // forgets what was linked for the natives of `class`, which are looked up by name again
+    fn unregister(&mut self, class: &Class) {
+        let prefix = format!("{}.", class.name);
+        self.linked.retain(|key, _| !key.starts_with(&prefix));
+    }
+
+    fn new_global_ref(&mut self, object: ObjectRef, weak: bool) -> usize {
+        let index = match self.global_refs.iter().position(Option::is_none) {
+            Some(index) => index,
+            None => {
+                self.global_refs.push(None);
+                self.weak.push(false);
+                self.global_refs.len() - 1
+            }
+        };
+        self.global_refs[index] = Some(object);
+        self.weak[index] = weak;
+        index
+    }
+
+    fn delete_global_ref(&mut self, index: usize) {
+        if let Some(global_ref) = self.global_refs.get_mut(index) {
+            *global_ref = None;
+        }
+    }
+
+    // the jmethodID of `method` of `class`, never 0
+    fn method_id(&mut self, class: &Rc<Class>, method: &Rc<Method>) -> usize {
+        let key = format!("method {}", key(&class.name, &method.name, &method.descriptor));
+        if let Some(id) = self.ids.get(&key) {
+            return *id;
+        }
+        self.methods.push((class.clone(), method.clone()));
+        self.ids.insert(key, self.methods.len());
+        self.methods.len()
+    }
+
+    fn method(&self, id: usize) -> Option<(Rc<Class>, Rc<Method>)> {
+        self.methods.get(id.checked_sub(1)?).cloned()
+    }
+
+    // the jfieldID of `field` of `class`, never 0
+    fn field_id(&mut self, class: &Rc<Class>, field: &Rc<Field>) -> usize {
+        let key = format!("field {}", key(&class.name, &field.name, &field.descriptor));
+        if let Some(id) = self.ids.get(&key) {
+            return *id;
+        }
+        self.fields.push((class.clone(), field.clone()));
+        self.ids.insert(key, self.fields.len());
+        self.fields.len()
+    }
+
+    fn field(&self, id: usize) -> Option<(Rc<Class>, Rc<Field>)> {
+        self.fields.get(id.checked_sub(1)?).cloned()
+    }
+}
+
+impl Library {
+    fn symbol(&self, name: &str) -> Option<usize> {
+        let name = CString::new(name).ok()?;
+        let address = unsafe { libc::dlsym(self.handle, name.as_ptr()) };
+        (!address.is_null()).then_some(address as usize)
+    }
+}
+
+// whether method `name` of class `class_name` loads a library, and then whether it takes the name of the library,
+// like System.loadLibrary, rather than the path of its file, like System.load. The VM does it in their place,
+// for the JNI_OnLoad of the library to run
+pub(crate) fn loads_library(class_name: &str, name: &str, descriptor: &str) -> Option<bool> {
+    match (class_name, name, descriptor) {
+        ("java/lang/System", "load", "(Ljava/lang/String;)V")
+        | ("java/lang/Runtime", "load0", "(Ljava/lang/Class;Ljava/lang/String;)V") => Some(false),
+        ("java/lang/System", "loadLibrary", "(Ljava/lang/String;)V")
+        | ("java/lang/Runtime", "loadLibrary0", "(Ljava/lang/Class;Ljava/lang/String;)V") => Some(true),
+        _ => None,
+    }
+}
+
+// the directories System.loadLibrary looks in: java.library.path, by default LD_LIBRARY_PATH and then
+// where HotSpot looks on Linux
+pub(crate) fn library_path(properties: &[(String, String)]) -> String {
+    if let Some((_, path)) = properties.iter().find(|(key, _)| key == "java.library.path") {
+        return path.clone();
+    }
+    let mut directories: Vec<String> =
+        std::env::var("LD_LIBRARY_PATH").into_iter().filter(|path| !path.is_empty()).collect();
+    directories.push(String::from(DEFAULT_LIBRARY_PATH));
+    directories.join(":")
+}
+
+// loads the library at path `name`, or named `name` when `by_name`, and runs its JNI_OnLoad; loading a library
+// a second time does nothing
+pub(crate) fn load_library(
+    interpreter: &mut Interpreter,
+    thread: &mut Thread,
+    name: &str,
+    by_name: bool,
+) -> Result<Flow, Error> {
+    if !abi::SUPPORTED {
+        return Ok(unsatisfied_link(format!("cannot load {}: JNI is not supported on this platform", name)));
+    }
+    let path = if by_name {
+        let search = library_path(interpreter.env(thread).properties);
+        let file = format!("lib{}.so", name);
+        let found = search.split(':').filter(|directory| !directory.is_empty());
+        match found.map(|directory| Path::new(directory).join(&file)).find(|path| path.is_file()) {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => return Ok(unsatisfied_link(format!("no {} in java.library.path: {}", name, search))),
+        }
+    } else if !Path::new(name).is_absolute() {
+        return Ok(unsatisfied_link(format!("Expecting an absolute path of the library: {}", name)));
+    } else {
+        String::from(name)
+    };
+    if interpreter.jni().libraries.iter().any(|library| library.path == path) {
+        return Ok(Flow::Next);
+    }
+    let file = match CString::new(path.as_str()) {
+        Ok(file) if Path::new(&path).exists() => file,
+        _ => return Ok(unsatisfied_link(format!("Can't load library: {}", path))),
+    };
+    let handle = unsafe { libc::dlopen(file.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if handle.is_null() {
+        let message = unsafe { CStr::from_ptr(libc::dlerror()) }.to_string_lossy().into_owned();
+        return Ok(unsatisfied_link(message));
+    }
+    log!(interpreter.logger(), Interp, Info, "loaded native library {}", path);
+    let library = Library { path: path.clone(), handle };
+    let on_load = library.symbol("JNI_OnLoad");
+    interpreter.jni().libraries.push(library);
+    let Some(on_load) = on_load else {
+        return Ok(Flow::Next);
+    };
+    let (version, exception) = with_env(interpreter, thread, |_| {
+        let mut arguments = Arguments::default();
+        arguments.int(env::java_vm() as u64);
+        arguments.int(0);
+        unsafe { arguments.call(on_load, false) }.map_or(0, |version| version as i32)
+    })?;
+    if let Some(exception) = exception {
+        return Ok(Flow::Throw(Some(exception)));
+    }
+    if !env::is_supported(version) {
+        interpreter.jni().libraries.pop();
+        return Ok(unsatisfied_link(format!("unsupported JNI version 0x{:x} required by {}", version, path)));
+    }
+    Ok(Flow::Next)
+}
+
+// calls native `method` of `class`, found at `function`, with the arguments on the caller's operand stack,
+// leaving what it returns there in their place
+pub(crate) fn call(
+    interpreter: &mut Interpreter,
+    thread: &mut Thread,
+    class: &Rc<Class>,
+    method: &Method,
+    function: usize,
+) -> Result<Flow, Error> {
+    // a static native gets its class where others get `this`
+    let mirror = match method.is_static() {
+        true => match interpreter.env(thread).mirror(class) {
+            Ok(mirror) => Some(mirror),
+            Err(flow) => return Ok(flow),
+        },
+        false => None,
+    };
+    let caller = thread.current_frame().expect("invoke without a frame");
+    let args = caller.operand_stack.pop_slots(method.arg_slot_count());
+    let parsed = method.parsed_descriptor.as_ref().expect("a method with a malformed descriptor");
+    let returned = parsed.return_type.as_ref().map_or(b'V', kind);
+    let (value, exception) = with_env(interpreter, thread, |env| {
+        let mut arguments = Arguments::default();
+        arguments.int(env as *mut JniEnv as u64);
+        let mut slot = 0;
+        let receiver = mirror.or_else(|| {
+            slot = 1;
+            args[0].reference()
+        });
+        arguments.int(env.local(receiver) as u64);
+        for parameter in &parsed.parameters {
+            let kind = kind(parameter);
+            let width = parameter.slot_size();
+            let bits = env.bits(kind, &args[slot..slot + width]);
+            match kind {
+                b'F' | b'D' => arguments.float(bits),
+                _ => arguments.int(bits),
+            }
+            slot += width;
+        }
+        let bits = unsafe { arguments.call(function, matches!(returned, b'F' | b'D')) }?;
+        Some(env.slots(returned, bits))
+    })?;
+    if let Some(exception) = exception {
+        return Ok(Flow::Throw(Some(exception)));
+    }
+    let Some(value) = value else {
+        let message = format!("{}.{}{}: too many arguments", class.java_name(), method.name, method.descriptor);
+        return Ok(unsatisfied_link(message));
+    };
+    let caller = thread.current_frame().expect("native without a caller");
+    for slot in value {
+        caller.operand_stack.push_slot(slot);
+    }
+    Ok(Flow::Next)
+}
+
+// the letter a type's descriptor starts with, which is all JNI goes by
+fn kind(field_type: &FieldType) -> u8 {
+    match field_type {
+        FieldType::Byte => b'B',
+        FieldType::Char => b'C',
+        FieldType::Double => b'D',
+        FieldType::Float => b'F',
+        FieldType::Int => b'I',
+        FieldType::Long => b'J',
+        FieldType::Short => b'S',
+        FieldType::Boolean => b'Z',
+        FieldType::Object(_) => b'L',
+        FieldType::Array(_) => b'[',
+    }
+}
+
+fn key(class_name: &str, name: &str, descriptor: &str) -> String {
+    format!("{}.{}{}", class_name, name, descriptor)
+}
+
+// a name the way JNI writes it in a symbol: / becomes _, and _, ; and [ are escaped as _1, _2 and _3,
+// any other character that is not an ASCII letter or digit as _0 and its UTF-16 code in hex
+fn mangle(name: &str) -> String {
+    let mut mangled = String::new();
+    for c in name.encode_utf16() {
+        match char::from_u32(c as u32) {
+            Some('/') => mangled.push('_'),
+            Some('_') => mangled.push_str("_1"),
+            Some(';') => mangled.push_str("_2"),
+            Some('[') => mangled.push_str("_3"),
+            Some(c) if c.is_ascii_alphanumeric() => mangled.push(c),
+            _ => mangled.push_str(&format!("_0{:04x}", c)),
+        }
+    }
+    mangled
+}
+
+fn unsatisfied_link(message: String) -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/UnsatisfiedLinkError",
+        message: Some(message),
+    }
+}
+
+// the value of `slots`, the bits of a jvalue holding one of type `kind`, for when natives take it as an argument
+fn value_bits(kind: u8, slots: &[Slot]) -> u64 {
+    match kind {
+        b'J' => Slot::join(&slots[0], &slots[1], false),
+        b'D' => Slot::join(&slots[0], &slots[1], true),
+        b'F' => slots[0].float().to_bits() as u64,
+        b'Z' => slots[0].int() as u8 as u64,
+        b'C' => slots[0].int() as u16 as u64,
+        _ => slots[0].int() as u32 as u64,
+    }
+}
@@ -0,0 +1,146 @@
+use crate::classfile::class_reader::{ACC_PUBLIC, ACC_STATIC};
+use crate::classfile::writer::{ClassDef, FieldDef, MethodDef, Op};
+use crate::vm::{JValue, TestClasspath};
+
+// a JNI library in C, which reaches the JNIEnv and JavaVM functions by their index in the tables
+const JNI_LIBRARY: &str = r#"
+#include <stdarg.h>
+#include <stdio.h>
+
+typedef void *jobject;
+typedef void **JNIEnv;
+typedef void **JavaVM;
+typedef struct { const char *name; const char *signature; void *function; } JNINativeMethod;
+
+#define JNI(env, index, type, ...) ((type) (*(env))[index])(env, __VA_ARGS__)
+
+static jobject native_class;
+
+static double call_double(JNIEnv *env, jobject class, void *method, ...) {
+    va_list args;
+    va_start(args, method);
+    double value = JNI(env, 139, double (*)(JNIEnv *, jobject, void *, va_list), class, method, args);
+    va_end(args);
+    return value;
+}
+
+long long Java_Native_mix(JNIEnv *env, jobject class, int a, double b, long long c) {
+    jobject found = JNI(env, 6, jobject (*)(JNIEnv *, const char *), "Native");
+    void *first = JNI(env, 113, void *(*)(JNIEnv *, jobject, const char *, const char *), found, "first", "(ID)I");
+    void *second = JNI(env, 113, void *(*)(JNIEnv *, jobject, const char *, const char *), found, "second", "(ID)D");
+    void *third = JNI(env, 113, void *(*)(JNIEnv *, jobject, const char *, const char *), found, "third", "(DIJ)J");
+    int x = JNI(env, 129, int (*)(JNIEnv *, jobject, void *, ...), class, first, a, b);
+    double y = call_double(env, class, second, a, b);
+    long long z = JNI(env, 132, long long (*)(JNIEnv *, jobject, void *, ...), class, third, b, a, c);
+    return x + (long long) y + z;
+}
+
+int Java_Native_sum(JNIEnv *env, jobject class, int length) {
+    int values[16];
+    for (int i = 0; i < length; i++) {
+        values[i] = i + 1;
+    }
+    jobject array = JNI(env, 179, jobject (*)(JNIEnv *, int), length);
+    JNI(env, 211, void (*)(JNIEnv *, jobject, int, int, const int *), array, 0, length, values);
+    int *elements = JNI(env, 187, int *(*)(JNIEnv *, jobject, unsigned char *), array, 0);
+    int sum = 0;
+    for (int i = 0; i < length; i++) {
+        sum += elements[i];
+    }
+    elements[0] = 100;
+    JNI(env, 195, void (*)(JNIEnv *, jobject, int *, int), array, elements, 0);
+    int first;
+    JNI(env, 203, void (*)(JNIEnv *, jobject, int, int, int *), array, 0, 1, &first);
+    return sum + first;
+}
+
+int Java_Native_bump(JNIEnv *env, jobject class) {
+    void *count = JNI(env, 144, void *(*)(JNIEnv *, jobject, const char *, const char *), native_class, "count", "I");
+    int value = JNI(env, 150, int (*)(JNIEnv *, jobject, void *), native_class, count) + 1;
+    JNI(env, 159, void (*)(JNIEnv *, jobject, void *, int), native_class, count, value);
+    return value;
+}
+
+void Java_Native_fail(JNIEnv *env, jobject class, jobject message) {
+    const char *chars = JNI(env, 169, const char *(*)(JNIEnv *, jobject, unsigned char *), message, 0);
+    jobject exception = JNI(env, 6, jobject (*)(JNIEnv *, const char *), "java/lang/IllegalStateException");
+    JNI(env, 14, int (*)(JNIEnv *, jobject, const char *), exception, chars);
+    JNI(env, 170, void (*)(JNIEnv *, jobject, const char *), message, chars);
+}
+
+static jobject greet(JNIEnv *env, jobject class, jobject name) {
+    const char *chars = JNI(env, 169, const char *(*)(JNIEnv *, jobject, unsigned char *), name, 0);
+    char greeting[64];
+    snprintf(greeting, sizeof greeting, "hello, %s", chars);
+    JNI(env, 170, void (*)(JNIEnv *, jobject, const char *), name, chars);
+    return JNI(env, 167, jobject (*)(JNIEnv *, const char *), greeting);
+}
+
+int JNI_OnLoad(JavaVM *vm, void *reserved) {
+    JNIEnv *env;
+    if (JNI(vm, 6, int (*)(JavaVM *, void **, int), (void **) &env, 0x00010008) != 0) {
+        return -1;
+    }
+    jobject class = JNI(env, 6, jobject (*)(JNIEnv *, const char *), "Native");
+    native_class = JNI(env, 21, jobject (*)(JNIEnv *, jobject), class);
+    JNINativeMethod methods[] = {{"greet", "(Ljava/lang/String;)Ljava/lang/String;", (void *) greet}};
+    JNI(env, 215, int (*)(JNIEnv *, jobject, const JNINativeMethod *, int), class, methods, 1);
+    return 0x00010008;
+}
+"#;
+
+#[test]
+fn natives_in_a_jni_library_call_back_into_java() {
+    let classpath = TestClasspath::new(&[]);
+    classpath.add("native.c", JNI_LIBRARY.as_bytes());
+    let compiled = std::process::Command::new("cc")
+        .args(["-shared", "-fPIC", "-o", "libnative.so", "native.c"])
+        .current_dir(&classpath.path)
+        .status();
+    assert!(compiled.is_ok_and(|status| status.success()), "this test needs a C compiler, cc, to build its library");
+    let methods = [
+        MethodDef::code("first", "(ID)I", ACC_PUBLIC | ACC_STATIC, 1, 3, &[Op::ILoad(0), Op::IReturn]),
+        MethodDef::code("second", "(ID)D", ACC_PUBLIC | ACC_STATIC, 2, 3, &[Op::DLoad(1), Op::DReturn]),
+        MethodDef::code("third", "(DIJ)J", ACC_PUBLIC | ACC_STATIC, 2, 5, &[Op::LLoad(3), Op::LReturn]),
+        MethodDef::code(
+            "init",
+            "(Ljava/lang/String;)V",
+            ACC_PUBLIC | ACC_STATIC,
+            1,
+            1,
+            &[Op::ALoad(0), Op::InvokeStatic("java/lang/System", "loadLibrary", "(Ljava/lang/String;)V"), Op::Return],
+        ),
+        MethodDef::static_native("mix", "(IDJ)J"),
+        MethodDef::static_native("sum", "(I)I"),
+        MethodDef::static_native("bump", "()I"),
+        MethodDef::static_native("fail", "(Ljava/lang/String;)V"),
+        MethodDef::static_native("greet", "(Ljava/lang/String;)Ljava/lang/String;"),
+    ];
+    let class = ClassDef { fields: &[FieldDef::new("count", "I", ACC_STATIC)], ..ClassDef::new("Native", &methods) };
+    classpath.add("Native.class", &class.write());
+    let path = classpath.path.to_string_lossy();
+    let mut vm = classpath.builder().property("java.library.path", &path).build().unwrap();
+
+    // natives are only linked once their library is loaded
+    let unlinked = vm.call_static("Native", "sum", "(I)I", &[JValue::Int(4)]).unwrap_err();
+    assert_eq!(unlinked.class_name, "java.lang.UnsatisfiedLinkError");
+    let missing = JValue::Object(Some(vm.new_string("missing").unwrap()));
+    let missing = vm.call_static("Native", "init", "(Ljava/lang/String;)V", &[missing]).unwrap_err();
+    assert_eq!(missing.class_name, "java.lang.UnsatisfiedLinkError");
+    let name = JValue::Object(Some(vm.new_string("native").unwrap()));
+    assert_eq!(vm.call_static("Native", "init", "(Ljava/lang/String;)V", &[name]), Ok(JValue::Void));
+
+    let args = [JValue::Int(3), JValue::Double(2.5), JValue::Long(1 << 40)];
+    assert_eq!(vm.call_static("Native", "mix", "(IDJ)J", &args), Ok(JValue::Long(3 + 2 + (1 << 40))));
+    assert_eq!(vm.call_static("Native", "sum", "(I)I", &[JValue::Int(4)]), Ok(JValue::Int(110)));
+    assert_eq!(vm.call_static("Native", "bump", "()I", &[]), Ok(JValue::Int(1)));
+    assert_eq!(vm.call_static("Native", "bump", "()I", &[]), Ok(JValue::Int(2)));
+    // registered by JNI_OnLoad rather than found by its name
+    let greeting = vm.call_static("Native", "greet", "(Ljava/lang/String;)Ljava/lang/String;", &[name]);
+    let Ok(JValue::Object(Some(greeting))) = greeting else {
+        panic!("greet returned {:?}", greeting);
+    };
+    assert_eq!(vm.string_value(greeting).as_deref(), Some("hello, native"));
+    let exception = vm.call_static("Native", "fail", "(Ljava/lang/String;)V", &[name]).unwrap_err();
+    assert_eq!(exception.to_string(), "java.lang.IllegalStateException: native");
+}
@@ -0,0 +1,22 @@
+// a JVM to embed: Vm runs Java code in-process, the learn_jvm launcher is built on it
+mod classpath;
+mod error;
+mod log;
+mod classfile;
+mod debugger;
+mod runtime;
+mod trace;
+mod instructions;
+mod interpreter;
+mod jni;
+mod native;
+mod verifier;
+mod vm;
+
+pub use error::Error;
+pub use instructions::disassemble;
+pub use log::{LogCategory, LogLevel};
+pub use runtime::heap::GcKind;
+pub use trace::TraceKind;
+pub use verifier::{infer_method_types, ClassHierarchy, Frame, VType, VerifyError, VerifyMode};
+pub use vm::{JObject, JValue, JavaException, Vm, VmBuilder};
@@ -0,0 +1,202 @@
+// the diagnostics of the VM itself, kept apart from what the Java program writes: a message has a category and a
+// level, and goes to standard error, or wherever the embedder points it, when its category is enabled at that level
+// or a more detailed one. Nothing is logged by default. Each VM has settings of its own, which the parts of it that
+// log share
+use std::cell::RefCell;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+// how much detail a message is about, from the least to the most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogCategory {
+    // looking for class files on the classpath
+    Classpath,
+    // reading class files
+    Parse,
+    // loading and defining classes
+    Load,
+    // running threads and natives
+    Interp,
+    Gc,
+}
+
+impl LogCategory {
+    pub const ALL: [LogCategory; 5] =
+        [LogCategory::Classpath, LogCategory::Parse, LogCategory::Load, LogCategory::Interp, LogCategory::Gc];
+}
+
+// the log settings of one VM, shared by the parts of it that log
+pub(crate) struct Logger {
+    // by category
+    levels: [LogLevel; LogCategory::ALL.len()],
+    output: RefCell<Box<dyn Write>>,
+}
+
+impl Logger {
+    // logs the categories in `levels` at the level given, and the others not at all, to `output`, standard error
+    // when None
+    pub(crate) fn new(levels: &[(LogCategory, LogLevel)], output: Option<Box<dyn Write>>) -> Logger {
+        let mut logger = Logger::default();
+        for (category, level) in levels {
+            logger.levels[*category as usize] = *level;
+        }
+        if let Some(output) = output {
+            logger.output = RefCell::new(output);
+        }
+        logger
+    }
+
+    pub(crate) fn enabled(&self, category: LogCategory, level: LogLevel) -> bool {
+        level != LogLevel::Off && level <= self.levels[category as usize]
+    }
+
+    pub(crate) fn write(&self, category: LogCategory, level: LogLevel, message: fmt::Arguments) {
+        let _ = writeln!(self.output.borrow_mut(), "[{}][{}] {}", level, category, message);
+    }
+}
+
+// logs nothing
+impl Default for Logger {
+    fn default() -> Logger {
+        Logger { levels: [LogLevel::Off; LogCategory::ALL.len()], output: RefCell::new(Box::new(std::io::stderr())) }
+    }
+}
+
+// logs a message made like format! makes a String to `$logger`, when category `$category` is enabled there at
+// level `$level`, e.g. log!(self.logger, Load, Debug, "loaded {}", name)
+macro_rules! log {
+    ($logger:expr, $category:ident, $level:ident, $($arg:tt)+) => {{
+        let logger: &$crate::log::Logger = &$logger;
+        if logger.enabled($crate::log::LogCategory::$category, $crate::log::LogLevel::$level) {
+            logger.write($crate::log::LogCategory::$category, $crate::log::LogLevel::$level, format_args!($($arg)+));
+        }
+    }};
+}
+
+pub(crate) use log;
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warning",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        })
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(LogLevel::Off),
+            "error" => Ok(LogLevel::Error),
+            "warning" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            "trace" => Ok(LogLevel::Trace),
+            _ => Err(format!("unknown log level {}, expected off|error|warning|info|debug|trace", s)),
+        }
+    }
+}
+
+impl fmt::Display for LogCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            LogCategory::Classpath => "classpath",
+            LogCategory::Parse => "parse",
+            LogCategory::Load => "load",
+            LogCategory::Interp => "interp",
+            LogCategory::Gc => "gc",
+        })
+    }
+}
+
+impl FromStr for LogCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LogCategory::ALL
+            .into_iter()
+            .find(|category| category.to_string() == s)
+            .ok_or_else(|| format!("unknown log category {}, expected classpath|parse|load|interp|gc", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::classfile::class_reader::{ACC_PUBLIC, ACC_STATIC};
+    use crate::classfile::writer::{ClassDef, MethodDef, Op};
+    use crate::native::Sink;
+    use crate::vm::{TestClasspath, VmBuilder};
+
+    use super::{LogCategory, LogLevel};
+
+    #[test]
+    fn logs_are_off_by_default_and_kept_from_standard_output() {
+        let methods =
+            [MethodDef::code("main", "([Ljava/lang/String;)V", ACC_PUBLIC | ACC_STATIC, 0, 1, &[Op::Return])];
+        let class = ClassDef::new("Logged", &methods);
+        let content = class.write();
+        let classpath = TestClasspath::new(&[class]);
+        let run = |configure: fn(VmBuilder) -> VmBuilder| {
+            let (stdout, log) = (Sink::default(), Sink::default());
+            let builder = classpath.builder().stdout(stdout.clone());
+            let mut vm = configure(builder.log_output(log.clone())).build().unwrap();
+            vm.run_main("Logged", &[]).unwrap();
+            let stdout = String::from_utf8(stdout.0.borrow().clone()).unwrap();
+            let log = String::from_utf8(log.0.borrow().clone()).unwrap();
+            (stdout, log)
+        };
+
+        let (stdout, log) = run(|builder| builder);
+        assert_eq!((stdout.as_str(), log.as_str()), ("", ""));
+
+        let (stdout, log) = run(|builder| {
+            builder
+                .log(LogCategory::Classpath, LogLevel::Debug)
+                .log(LogCategory::Load, LogLevel::Info)
+                .log(LogCategory::Parse, LogLevel::Trace)
+                .log(LogCategory::Parse, LogLevel::Off)
+        });
+        assert_eq!(stdout, "");
+        let found = format!("[debug][classpath] found Logged in the user classpath, {} bytes", content.len());
+        assert!(log.lines().any(|line| line == found), "{}", log);
+        assert!(log.lines().any(|line| line == "[info][load] loaded Logged, verified"), "{}", log);
+        // below the levels asked for, or in categories not asked for at all
+        assert!(!log.contains("[trace]") && !log.contains("[parse]") && !log.contains("[debug][load]"), "{}", log);
+    }
+
+    #[test]
+    fn each_vm_logs_to_its_own_output_at_its_own_levels() {
+        let methods =
+            [MethodDef::code("main", "([Ljava/lang/String;)V", ACC_PUBLIC | ACC_STATIC, 0, 1, &[Op::Return])];
+        let classpath = TestClasspath::new(&[ClassDef::new("Logged", &methods)]);
+        let (loads, quiet) = (Sink::default(), Sink::default());
+        // built one after the other on this thread, then run in the order they were built in
+        let loading = classpath.builder().log(LogCategory::Load, LogLevel::Info).log_output(loads.clone());
+        let mut loading = loading.build().unwrap();
+        let mut silent = classpath.builder().log_output(quiet.clone()).build().unwrap();
+        loading.run_main("Logged", &[]).unwrap();
+        silent.run_main("Logged", &[]).unwrap();
+
+        let loads = String::from_utf8(loads.0.borrow().clone()).unwrap();
+        assert!(loads.lines().any(|line| line == "[info][load] loaded Logged, verified"), "{}", loads);
+        assert!(quiet.0.borrow().is_empty(), "{}", String::from_utf8_lossy(&quiet.0.borrow()));
+    }
+}
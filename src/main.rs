@@ -1,18 +1,8 @@
-mod classpath;
-mod error;
-mod classfile;
-mod runtime;
-mod instructions;
+use std::fmt::Display;
+use std::net::TcpListener;
 
-
-use std::cell::Cell;
-use std::path::PathBuf;
-use std::rc::Rc;
-use classfile::ClassFile;
-use classpath::Classpath;
+use learn_jvm::{Error, LogCategory, LogLevel, Vm};
 use structopt::StructOpt;
-use crate::classfile::class_reader::{get_class_name, get_utf8,Reader};
-use crate::runtime::{interpret, Frame, LocalVars, OperandStack, Thread};
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "LearnJVM", usage = "Usage: LearnJVM [-options] class [args...]")]
@@ -23,128 +13,176 @@ pub struct Options {
     classpath: Option<String>,
     #[structopt(long = "cp", help = "classpath", takes_value = true)]
     cp: Option<String>,
-    #[structopt(long = "jre", help = "jre path", takes_value = true)]
+    #[structopt(long = "jre", help = "jre path; without one the built-in class library is used", takes_value = true)]
     jre: Option<String>,
+    #[structopt(short = "X", number_of_values = 1, help = "non-standard options, e.g. -Xverify:none|remote|all, -Xss<size>, -Xmx<size>, -Xmn<size>, -Xgc:marksweep|generational, -Xtrace:inst|call, -Xprof, -Xlog:<categories|all>[=level]")]
+    x_options: Vec<String>,
+    #[structopt(short = "D", number_of_values = 1, help = "set a system property, e.g. -Djava.library.path=<dirs>")]
+    properties: Vec<String>,
+    #[structopt(long = "verbose", number_of_values = 1, help = "enable verbose output, e.g. -verbose:gc")]
+    verbose: Vec<String>,
+    #[structopt(long = "debug", help = "stop before running and take debugger commands from standard input")]
+    debug: bool,
+    #[structopt(long = "agentlib", number_of_values = 1, help = "load an agent, e.g. -agentlib:jdwp=transport=dt_socket,server=y,suspend=y,address=8000")]
+    agents: Vec<String>,
     #[structopt(takes_value = true)]
     class: Option<String>,
     #[structopt(takes_value = true, multiple = true)]
-    _args: Vec<String>,
+    args: Vec<String>,
 }
 
 fn main() {
-    let options = Options::from_args();
+    // java spells them -verbose:gc and -agentlib:jdwp=..., which clap would take for clusters of short flags
+    let args = std::env::args().map(|arg| {
+        if let Some(kind) = arg.strip_prefix("-verbose:") {
+            format!("--verbose={}", kind)
+        } else if let Some(agent) = arg.strip_prefix("-agentlib:") {
+            format!("--agentlib={}", agent)
+        } else {
+            arg
+        }
+    });
+    let options = Options::from_iter(args);
     if options.version_flag {
         println!("version: 0.0.1");
-    } else if options.jre.is_some() && options.class.is_some() {
-        println!("parse input:\n {:?}", options);
-        parse_internal(options);
+    } else if options.class.is_some() {
+        run(options);
     } else {
-        println!("Usage: LearnJVM [-options] class [args...]")
+        fail("Usage: LearnJVM [-options] class [args...]")
     }
 }
 
-fn parse_internal(options: Options) {
-    let jre_lib_dir = PathBuf::from(options.jre.unwrap()).join("lib");
-    if options.cp.is_none() && options.classpath.is_none() {
-        println!("{}", error::Error::ClasspathNotSet());
-        return;
-    }
-    let user_classpath =  if options.cp.is_none() {
-        options.classpath.unwrap()
-    } else {
-        options.cp.unwrap()
+fn run(options: Options) {
+    let Some(classpath) = options.cp.or(options.classpath) else {
+        fail(Error::ClasspathNotSet())
     };
-    let classpath = Classpath::init_classpath(jre_lib_dir, PathBuf::from(user_classpath));
-    let class = classpath.load_class(options.class.unwrap());
-    let reader = Reader {
-        content: class.unwrap(),
-        cursor: Cell::new(0),
-    };
-    test_ch_04();
-    let result = reader.parse_classfile();
-    match result {
-        Err(error) => {
-            println!("failed {}", error)
-        }
-
-        Ok(classfile) => {
-            let this_class = get_class_name(&classfile.constant_pool, &classfile.this_class);
-            let super_class = get_class_name(&classfile.constant_pool, &classfile.super_class);
-            println!("parse classfile successful");
-            println!("version: {}", classfile.major_version);
-            println!("constants count: {}", classfile.constant_pool_count);
-            println!("access_flags: {:#x}", classfile.access_flags);
-            println!("this class: {:?}", this_class);
-            println!("super class: {:?}", super_class);
-            println!("interfaces: {:?}", classfile.interfaces);
-            println!("fields count: {}", classfile.fields_count);
-            for field_info in &classfile.fields_info {
-                println!("  {}", field_info.name);
+    let mut builder = Vm::builder().classpath(&classpath);
+    if let Some(jre) = options.jre {
+        builder = builder.jre(jre);
+    }
+    for property in &options.properties {
+        let (key, value) = property.split_once('=').unwrap_or((property, ""));
+        builder = builder.property(key, value);
+    }
+    for x_option in &options.x_options {
+        if let Some(mode) = x_option.strip_prefix("verify:") {
+            match mode.parse() {
+                Ok(mode) => builder = builder.verify_mode(mode),
+                Err(error) => fail(error),
+            }
+        } else if let Some(size) = x_option.strip_prefix("ss") {
+            match parse_size(size) {
+                Some(size) => builder = builder.stack_size(size),
+                None => fail(format!("Invalid thread stack size: -X{}", x_option)),
+            }
+        } else if let Some(size) = x_option.strip_prefix("mx") {
+            match parse_size(size) {
+                Some(size) => builder = builder.heap_size(size),
+                None => fail(format!("Invalid maximum heap size: -X{}", x_option)),
+            }
+        } else if let Some(size) = x_option.strip_prefix("mn") {
+            match parse_size(size) {
+                Some(size) => builder = builder.young_size(size),
+                None => fail(format!("Invalid maximum new generation size: -X{}", x_option)),
+            }
+        } else if let Some(kind) = x_option.strip_prefix("gc:") {
+            match kind.parse() {
+                Ok(kind) => builder = builder.gc(kind),
+                Err(error) => fail(error),
             }
-            println!("methods count: {}", classfile.methods_count);
-            for method_info in &classfile.methods_info {
-                println!("  {}", method_info.name);
+        } else if let Some(kind) = x_option.strip_prefix("trace:") {
+            match kind.parse() {
+                Ok(kind) => builder = builder.trace(kind),
+                Err(error) => fail(error),
             }
-            println!("attributes count: {}", classfile.attributes_count);
-            test_ch_05(&classfile);
+        } else if x_option == "prof" {
+            builder = builder.profile(true);
+        } else if let Some(log) = x_option.strip_prefix("log:") {
+            match parse_log(log) {
+                Ok(logs) => {
+                    for (category, level) in logs {
+                        builder = builder.log(category, level);
+                    }
+                }
+                Err(error) => fail(error),
+            }
+        } else {
+            fail(format!("Unrecognized option: -X{}", x_option));
+        }
+    }
+    let mut builder = builder.verbose_gc(options.verbose.iter().any(|kind| kind == "gc"));
+    if options.debug {
+        builder = builder.debugger(std::io::stdin().lock(), std::io::stdout());
+    }
+    for agent in &options.agents {
+        let Some(jdwp) = agent.strip_prefix("jdwp=") else {
+            fail(format!("Could not find agent library {}", agent.split('=').next().unwrap_or_default()))
+        };
+        match listen(jdwp) {
+            Ok((listener, suspend)) => builder = builder.jdwp(listener, suspend),
+            Err(error) => fail(error),
         }
     }
+    let result = builder.build().and_then(|mut vm| vm.run_main(&options.class.unwrap(), &options.args));
+    match result {
+        Ok(()) | Err(Error::DebuggerQuit) => {}
+        // the VM has reported these on stderr already
+        Err(Error::UncaughtException(_) | Error::Deadlock) => std::process::exit(1),
+        Err(error) => fail(format!("Error: {}", error)),
+    }
 }
 
-fn test_ch_05(classfile:&ClassFile) {
-   for method in &classfile.methods_info {
-       if method.name == "main" {
-           let descriptor_name = get_utf8(&classfile.constant_pool, &method.descriptor_index).unwrap();
-           if descriptor_name == "([Ljava/lang/String;)V" {
-               interpret(method);
-               return;
-           }
-       }
-   }
-   println!("not found main function!!");
+// reports why the launcher cannot go on and exits with status 1, as java does
+fn fail(message: impl Display) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1)
 }
 
-fn test_ch_04() {
-    let mut thread = Thread::new_thread();
-    let frame = Frame::new_frame(1024, 1024);
-    test_local_vars(frame.local_vars);
-    test_operand_stack(frame.operand_stack);
+// the socket -agentlib:jdwp options like transport=dt_socket,server=y,suspend=n,address=8000 say to listen on, and
+// whether to wait for a debugger before running; only a server on a socket is supported, on localhost unless the
+// address names a host
+fn listen(options: &str) -> Result<(TcpListener, bool), String> {
+    let mut suspend = true;
+    let mut address = None;
+    for option in options.split(',') {
+        match option.split_once('=') {
+            Some(("transport", "dt_socket")) | Some(("server", "y")) => {}
+            Some(("transport", _)) => return Err(String::from("JDWP only supports transport=dt_socket")),
+            Some(("server", _)) => return Err(String::from("JDWP only supports server=y")),
+            Some(("suspend", value)) => suspend = value == "y",
+            Some(("address", value)) => address = Some(value),
+            _ => return Err(format!("unknown JDWP option {}", option)),
+        }
+    }
+    let address = address.ok_or("JDWP needs an address")?;
+    let address = match address.contains(':') {
+        true => address.replacen('*', "0.0.0.0", 1),
+        false => format!("127.0.0.1:{}", address),
+    };
+    let listener =
+        TcpListener::bind(&address).map_err(|error| format!("JDWP cannot listen on {}: {}", address, error))?;
+    let port = listener.local_addr().map_err(|error| error.to_string())?.port();
+    println!("Listening for transport dt_socket at address: {}", port);
+    Ok((listener, suspend))
 }
 
-fn test_operand_stack(mut ops: OperandStack) {
-    ops.push_int(100);
-    ops.push_int(-100);
-    ops.push_long(2997924580);
-    ops.push_long(-2997924580);
-    ops.push_float(3.1415926);
-    ops.push_double(2.71828182845);
-    ops.push_ref(None);
-    println!("{:?}", ops.pop_ref());
-    println!("{}", ops.pop_double());
-    println!("{}", ops.pop_float());
-    println!("{}", ops.pop_long());
-    println!("{}", ops.pop_long());
-    println!("{}", ops.pop_int());
-    println!("{}", ops.pop_int());
+// the categories and level of -Xlog, e.g. classpath,load=debug or all; the level is info when not given
+fn parse_log(log: &str) -> Result<Vec<(LogCategory, LogLevel)>, String> {
+    let (categories, level) = log.split_once('=').unwrap_or((log, "info"));
+    let level = level.parse::<LogLevel>()?;
+    if categories == "all" {
+        return Ok(LogCategory::ALL.into_iter().map(|category| (category, level)).collect());
+    }
+    categories.split(',').map(|category| Ok((category.parse()?, level))).collect()
 }
 
-fn test_local_vars(mut vars: LocalVars) {
-    vars.set_int(0, 100);
-    vars.set_int(1, -100);
-    vars.set_long(2, 2997924580);
-    vars.set_long(4, -2997924580);
-    vars.set_float(6, 3.1415926);
-    vars.set_double(7, 2.71828182845);
-    vars.set_ref(9, None);
-    println!("{}", vars.get_int(0));
-    println!("{}", vars.get_int(1));
-    println!("{}", vars.get_long(2));
-    println!("{}", vars.get_long(4));
-    println!("{}", vars.get_float(6));
-    println!("{:?}", vars.get_ref(9));
-    println!("{}", vars.get_double(7));
-
-    let test = -10;
-    println!("signed-extend = {:016x}, zero extend = {:016x}", (test as i32) as i64, (test as u32) as i64);
-
+// a byte count with an optional k, m or g suffix, as -Xss and -Xmx take it
+fn parse_size(size: &str) -> Option<usize> {
+    let (digits, unit) = match size.char_indices().last()? {
+        (at, 'k' | 'K') => (&size[..at], 1 << 10),
+        (at, 'm' | 'M') => (&size[..at], 1 << 20),
+        (at, 'g' | 'G') => (&size[..at], 1 << 30),
+        _ => (size, 1),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(unit)
 }
@@ -0,0 +1,704 @@
+use std::rc::Rc;
+
+use crate::classfile::attribute::{Annotation, AttributeInfo, ElementValue};
+use crate::classfile::class_reader::get_utf8;
+use crate::classfile::constant_pool::ConstantInfo;
+use crate::classfile::constant_pool::ConstantInfo::{ConstantDouble, ConstantFloat, ConstantInteger, ConstantLong};
+use crate::classfile::descriptor::parse_field_descriptor;
+use crate::instructions::Flow;
+use crate::runtime::class::{annotation_default, annotations, parameter_annotations, Class, Method};
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{LocalVars, Object, Slot};
+
+use super::super::invoke::type_class_name;
+use super::number::{self, bits, box_class, box_type, format_value, hash_code, set_value, width};
+use super::{return_bool, return_string, this, Env, NativeRegistry};
+
+const CLASS: &str = "java/lang/Class";
+const STRING: &str = "java/lang/String";
+const ACCESSIBLE_OBJECT: &str = "java/lang/reflect/AccessibleObject";
+const FIELD: &str = "java/lang/reflect/Field";
+const METHOD: &str = "java/lang/reflect/Method";
+const CONSTRUCTOR: &str = "java/lang/reflect/Constructor";
+const HANDLER: &str = "sun/reflect/annotation/AnnotationInvocationHandler";
+const ANNOTATIONS: &str = "[Ljava/lang/annotation/Annotation;";
+const INHERITED: &str = "Ljava/lang/annotation/Inherited;";
+
+// the JDK parses the annotations reflection hands out from the bytes of their attributes in Java; here
+// natives make them from the parsed attributes, as proxies of their types whose handler holds the values
+pub(super) fn register(registry: &mut NativeRegistry) {
+    for class in [CLASS, ACCESSIBLE_OBJECT] {
+        registry.register(
+            class,
+            "getAnnotation",
+            "(Ljava/lang/Class;)Ljava/lang/annotation/Annotation;",
+            |env, args| match find_annotation(env, args) {
+                Ok(Some((element, index))) => {
+                    let annotation = env.annotation(element.pool(), &element.annotations()[index], &mut Vec::new());
+                    match annotation {
+                        Ok(annotation) => Flow::Return(vec![Slot::Reference(annotation)]),
+                        Err(flow) => flow,
+                    }
+                }
+                Ok(None) => Flow::Return(vec![Slot::Reference(None)]),
+                Err(flow) => flow,
+            },
+        );
+        registry.register(class, "isAnnotationPresent", "(Ljava/lang/Class;)Z", |env, args| {
+            match find_annotation(env, args) {
+                Ok(found) => return_bool(found.is_some()),
+                Err(flow) => flow,
+            }
+        });
+        registry.register(class, "getAnnotations", "()[Ljava/lang/annotation/Annotation;", |env, args| {
+            get_annotations(env, args, true)
+        });
+        registry.register(class, "getDeclaredAnnotations", "()[Ljava/lang/annotation/Annotation;", |env, args| {
+            get_annotations(env, args, false)
+        });
+    }
+    for class in [METHOD, CONSTRUCTOR] {
+        let descriptor = "()[[Ljava/lang/annotation/Annotation;";
+        registry.register(class, "getParameterAnnotations", descriptor, get_parameter_annotations);
+    }
+    registry.register(METHOD, "getDefaultValue", "()Ljava/lang/Object;", get_default_value);
+    registry.register(
+        HANDLER,
+        "invoke",
+        "(Ljava/lang/Object;Ljava/lang/reflect/Method;[Ljava/lang/Object;)Ljava/lang/Object;",
+        invoke,
+    );
+}
+
+// what a Class, Field, Method or Constructor object stands for, the member by its slot in its class
+#[derive(Clone)]
+enum Element {
+    Class(Rc<Class>),
+    Field(Rc<Class>, usize),
+    Method(Rc<Class>, usize),
+}
+
+impl Element {
+    fn of(env: &Env, object: ObjectRef) -> Element {
+        match env.heap[object].class.name.as_str() {
+            CLASS => Element::Class(env.class_of_mirror(object)),
+            FIELD => {
+                let (class, slot) = env.reflected(object);
+                Element::Field(class, slot)
+            }
+            _ => {
+                let (class, slot) = env.reflected(object);
+                Element::Method(class, slot)
+            }
+        }
+    }
+
+    fn class(&self) -> &Rc<Class> {
+        match self {
+            Element::Class(class) | Element::Field(class, _) | Element::Method(class, _) => class,
+        }
+    }
+
+    fn pool(&self) -> &[ConstantInfo] {
+        &self.class().classfile.constant_pool
+    }
+
+    fn attributes(&self) -> &[AttributeInfo] {
+        match self {
+            Element::Class(class) => &class.classfile.attributes_info,
+            Element::Field(class, slot) => &class.classfile.fields_info[*slot].attribute_info,
+            Element::Method(class, slot) => &class.classfile.methods_info[*slot].attribute_info,
+        }
+    }
+
+    fn annotations(&self) -> &[Annotation] {
+        annotations(self.attributes())
+    }
+}
+
+// the annotations of an element by their index among its own: for a class with those of its super classes
+// whose type is @Inherited after them, unless it has one of the type itself
+fn candidates(env: &Env, element: Element, inherited: bool) -> Vec<(Element, usize)> {
+    let mut candidates: Vec<(Element, usize)> =
+        (0..element.annotations().len()).map(|index| (element.clone(), index)).collect();
+    let Element::Class(class) = element else {
+        return candidates;
+    };
+    let mut types: Vec<String> = candidates
+        .iter()
+        .map(|(element, index)| annotation_type(element.pool(), &element.annotations()[*index]))
+        .collect();
+    let mut super_class = class.super_class.clone().filter(|_| inherited);
+    while let Some(class) = super_class {
+        for (index, annotation) in annotations(&class.classfile.attributes_info).iter().enumerate() {
+            let name = annotation_type(&class.classfile.constant_pool, annotation);
+            if !types.contains(&name) && env.loader.load_class(&name).is_ok_and(|type_| is_inherited(&type_)) {
+                candidates.push((Element::Class(class.clone()), index));
+                types.push(name);
+            }
+        }
+        super_class = class.super_class.clone();
+    }
+    candidates
+}
+
+// the annotation of the type given getAnnotation and isAnnotationPresent have
+fn find_annotation(env: &Env, args: &LocalVars) -> Result<Option<(Element, usize)>, Flow> {
+    let Some(type_) = args.get_ref(1) else {
+        return Err(Flow::Raise {
+            class_name: "java/lang/NullPointerException",
+            message: None,
+        });
+    };
+    let type_name = env.class_of_mirror(type_).name.clone();
+    let candidates = candidates(env, Element::of(env, this(args)), true);
+    Ok(candidates
+        .into_iter()
+        .find(|(element, index)| annotation_type(element.pool(), &element.annotations()[*index]) == type_name))
+}
+
+fn get_annotations(env: &mut Env, args: &LocalVars, inherited: bool) -> Flow {
+    let candidates = candidates(env, Element::of(env, this(args)), inherited);
+    let annotations: Vec<(&[ConstantInfo], &Annotation)> = candidates
+        .iter()
+        .map(|(element, index)| (element.pool(), &element.annotations()[*index]))
+        .collect();
+    match env.annotation_array(&annotations, &mut Vec::new()) {
+        Ok(array) => Flow::Return(vec![Slot::Reference(Some(array))]),
+        Err(flow) => flow,
+    }
+}
+
+// an array of the annotations of each parameter, empty for those without any
+fn get_parameter_annotations(env: &mut Env, args: &LocalVars) -> Flow {
+    let element = Element::of(env, this(args));
+    let Element::Method(class, slot) = &element else {
+        unreachable!("parameter annotations of a field");
+    };
+    let parameters = class.methods[*slot].parsed_descriptor.as_ref().map_or(0, |parsed| parsed.parameters.len());
+    let lists = parameter_annotations(element.attributes());
+    let mut live = Vec::new();
+    let array = match env.new_array_keeping(&format!("[{}", ANNOTATIONS), &[parameters], &mut live) {
+        Ok(array) => array,
+        Err(flow) => return flow,
+    };
+    live.push(array);
+    for index in 0..parameters {
+        let annotations: Vec<(&[ConstantInfo], &Annotation)> = lists
+            .get(index)
+            .map_or(&[][..], |list| &list[..])
+            .iter()
+            .map(|annotation| (element.pool(), annotation))
+            .collect();
+        match env.annotation_array(&annotations, &mut live) {
+            Ok(annotations) => {
+                env.heap[array].fields.borrow_mut()[index] = Slot::Reference(Some(annotations));
+                env.heap.write_barrier(array);
+            }
+            Err(flow) => return flow,
+        }
+    }
+    Flow::Return(vec![Slot::Reference(Some(array))])
+}
+
+// the default of the member of an annotation type, boxed when it is of a primitive type
+fn get_default_value(env: &mut Env, args: &LocalVars) -> Flow {
+    let element = Element::of(env, this(args));
+    let Element::Method(class, slot) = &element else {
+        unreachable!("the default value of a field");
+    };
+    let Some(default) = annotation_default(element.attributes()) else {
+        return Flow::Return(vec![Slot::Reference(None)]);
+    };
+    match env.element_value(element.pool(), default, return_descriptor(&class.methods[*slot]), &mut Vec::new()) {
+        Ok(value) => Flow::Return(vec![Slot::Reference(value)]),
+        Err(flow) => flow,
+    }
+}
+
+// a call of a proxy of an annotation: equals, hashCode, toString and annotationType, or a member, of which an
+// array comes as a copy
+fn invoke(env: &mut Env, args: &LocalVars) -> Flow {
+    let handler = this(args);
+    let (class, slot) = env.reflected(args.get_ref(2).expect("a proxy call without a method"));
+    let method = class.methods[slot].clone();
+    match (method.name.as_str(), method.descriptor.as_str()) {
+        ("equals", "(Ljava/lang/Object;)Z") => {
+            let other = env.heap[args.get_ref(3).expect("equals without arguments")].fields.borrow()[0].reference();
+            let equal = env.values_equal(args.get_ref(1), other);
+            return_boxed(env.boxed('Z', &[Slot::Int(equal as i32)], &[]))
+        }
+        ("hashCode", "()I") => {
+            let hash = env.annotation_hash(handler);
+            return_boxed(env.boxed('I', &[Slot::Int(hash)], &[]))
+        }
+        ("toString", "()Ljava/lang/String;") => {
+            let string = env.annotation_string(handler);
+            return_string(env.new_string(&string))
+        }
+        ("annotationType", "()Ljava/lang/Class;") => {
+            Flow::Return(vec![Slot::Reference(env.heap[handler].get_ref_field("type", "Ljava/lang/Class;"))])
+        }
+        (name, _) => {
+            let (names, values) = env.members(handler);
+            let Some(Some(value)) = names.iter().position(|member| member == name).map(|index| values[index]) else {
+                let type_ = env.heap[handler].get_ref_field("type", "Ljava/lang/Class;");
+                let type_ = type_.expect("a handler without a type");
+                return Flow::Raise {
+                    class_name: "java/lang/annotation/IncompleteAnnotationException",
+                    message: Some(format!("{} missing element {}", env.class_of_mirror(type_).java_name(), name)),
+                };
+            };
+            let class = env.heap[value].class.clone();
+            if !class.is_array() {
+                return Flow::Return(vec![Slot::Reference(Some(value))]);
+            }
+            let length = env.heap[value].array_length();
+            match env.allocate_keeping(Object::new_array(class, length), &[value]) {
+                Ok(copy) => {
+                    let elements = env.heap[value].fields.borrow().clone();
+                    *env.heap[copy].fields.borrow_mut() = elements;
+                    env.heap.write_barrier(copy);
+                    Flow::Return(vec![Slot::Reference(Some(copy))])
+                }
+                Err(flow) => flow,
+            }
+        }
+    }
+}
+
+impl Env<'_> {
+    // an Annotation[] of `annotations`, those whose type is not there left out
+    fn annotation_array(
+        &mut self,
+        annotations: &[(&[ConstantInfo], &Annotation)],
+        live: &mut Vec<ObjectRef>,
+    ) -> Result<ObjectRef, Flow> {
+        let depth = live.len();
+        let mut objects = Vec::new();
+        for (pool, annotation) in annotations {
+            if let Some(object) = self.annotation(pool, annotation, live)? {
+                live.push(object);
+                objects.push(Slot::Reference(Some(object)));
+            }
+        }
+        let array = self.new_array_keeping(ANNOTATIONS, &[objects.len()], live);
+        live.truncate(depth);
+        let array = array?;
+        *self.heap[array].fields.borrow_mut() = objects;
+        self.heap.write_barrier(array);
+        Ok(array)
+    }
+
+    // `annotation` as an instance of its type, keeping the `live` objects like allocate_keeping; None when the
+    // type is not there
+    fn annotation(
+        &mut self,
+        pool: &[ConstantInfo],
+        annotation: &Annotation,
+        live: &mut Vec<ObjectRef>,
+    ) -> Result<Option<ObjectRef>, Flow> {
+        let Ok(type_) = self.loader.load_class(&annotation_type(pool, annotation)) else {
+            return Ok(None);
+        };
+        let depth = live.len();
+        let annotation = self.annotation_of(&type_, pool, annotation, live);
+        live.truncate(depth);
+        annotation.map(Some)
+    }
+
+    fn annotation_of(
+        &mut self,
+        type_: &Rc<Class>,
+        pool: &[ConstantInfo],
+        annotation: &Annotation,
+        live: &mut Vec<ObjectRef>,
+    ) -> Result<ObjectRef, Flow> {
+        let given: Vec<(String, &ElementValue)> = annotation
+            .element_value_pairs
+            .iter()
+            .map(|pair| (get_utf8(pool, &pair.element_name_index).unwrap_or_default(), &pair.value))
+            .collect();
+        // the members with a default first, in the order the JDK's HashMap of the defaults has them, then the
+        // others given in the order they are given
+        let members: Vec<(usize, &Method)> = type_
+            .methods
+            .iter()
+            .enumerate()
+            .filter(|(_, method)| !method.is_static() && !method.name.starts_with('<'))
+            .map(|(slot, method)| (slot, &**method))
+            .collect();
+        let default = |slot: usize| annotation_default(&type_.classfile.methods_info[slot].attribute_info);
+        let mut names: Vec<String> = members
+            .iter()
+            .filter(|(slot, _)| default(*slot).is_some())
+            .map(|(_, method)| method.name.clone())
+            .collect();
+        hash_map_order(&mut names);
+        for (name, _) in &given {
+            if !names.contains(name) && members.iter().any(|(_, method)| method.name == *name) {
+                names.push(name.clone());
+            }
+        }
+        let mut values = Vec::new();
+        for name in &names {
+            let (slot, method) = members.iter().find(|(_, method)| method.name == *name).expect("a member");
+            let value = match given.iter().find(|(given, _)| given == name) {
+                Some((_, value)) => self.element_value(pool, value, return_descriptor(method), live)?,
+                None => {
+                    let default = default(*slot).expect("a default");
+                    self.element_value(&type_.classfile.constant_pool, default, return_descriptor(method), live)?
+                }
+            };
+            live.extend(value);
+            values.push(Slot::Reference(value));
+        }
+        let mut strings = Vec::new();
+        for name in &names {
+            let string = self.new_string_keeping(&name.encode_utf16().collect::<Vec<_>>(), live)?;
+            live.push(string);
+            strings.push(Slot::Reference(Some(string)));
+        }
+        let names = self.new_array_keeping("[Ljava/lang/String;", &[strings.len()], live)?;
+        *self.heap[names].fields.borrow_mut() = strings;
+        live.push(names);
+        let values_array = self.new_array_keeping("[Ljava/lang/Object;", &[values.len()], live)?;
+        *self.heap[values_array].fields.borrow_mut() = values;
+        live.push(values_array);
+        let mirror = self.mirror_keeping(type_, live)?;
+        live.push(mirror);
+        let handler = self.allocate_keeping(Object::new(self.load(HANDLER)?), live)?;
+        let handler_ref = &self.heap[handler];
+        handler_ref.set_ref_field("type", "Ljava/lang/Class;", Some(mirror));
+        handler_ref.set_ref_field("names", "[Ljava/lang/String;", Some(names));
+        handler_ref.set_ref_field("values", "[Ljava/lang/Object;", Some(values_array));
+        self.heap.write_barrier(handler);
+        self.new_proxy(std::slice::from_ref(type_), handler, live)
+    }
+
+    // an element value as the member of type `descriptor` returns it, boxed when of a primitive type
+    fn element_value(
+        &mut self,
+        pool: &[ConstantInfo],
+        value: &ElementValue,
+        descriptor: &str,
+        live: &mut Vec<ObjectRef>,
+    ) -> Result<Option<ObjectRef>, Flow> {
+        match value {
+            ElementValue::Const { tag: b's', const_value_index } => {
+                let string = get_utf8(pool, const_value_index).unwrap_or_default();
+                self.new_string_keeping(&string.encode_utf16().collect::<Vec<_>>(), live).map(Some)
+            }
+            ElementValue::Const { tag, const_value_index } => {
+                self.boxed(*tag as char, &constant_slots(pool, *const_value_index), live).map(Some)
+            }
+            ElementValue::Enum { type_name_index, const_name_index } => {
+                let type_ = get_utf8(pool, type_name_index).unwrap_or_default();
+                let class = self.load(&type_class_name(parse_field_descriptor(&type_).as_ref()))?;
+                if let Some(flow) = self.initialize(&class) {
+                    return Err(flow);
+                }
+                let name = get_utf8(pool, const_name_index).unwrap_or_default();
+                Ok(class.find_field(&name, &type_).and_then(|(owner, field)| {
+                    owner.static_fields.borrow()[field.slot].reference()
+                }))
+            }
+            ElementValue::Class { class_info_index } => {
+                let descriptor = get_utf8(pool, class_info_index).unwrap_or_default();
+                let class = self.load(&type_class_name(parse_field_descriptor(&descriptor).as_ref()))?;
+                self.mirror_keeping(&class, live).map(Some)
+            }
+            ElementValue::Annotation(annotation) => self.annotation(pool, annotation, live),
+            ElementValue::Array(values) => {
+                let component = descriptor.strip_prefix('[').unwrap_or("Ljava/lang/Object;");
+                let array = self.new_array_keeping(&format!("[{}", component), &[values.len()], live)?;
+                live.push(array);
+                let mut offset = 0;
+                for value in values {
+                    match value {
+                        ElementValue::Const { tag, const_value_index } if *tag != b's' => {
+                            let slots = constant_slots(pool, *const_value_index);
+                            let length = slots.len();
+                            self.heap[array].fields.borrow_mut()[offset..offset + length].clone_from_slice(&slots);
+                            offset += length;
+                        }
+                        value => {
+                            let element = self.element_value(pool, value, component, live)?;
+                            self.heap[array].fields.borrow_mut()[offset] = Slot::Reference(element);
+                            self.heap.write_barrier(array);
+                            offset += 1;
+                        }
+                    }
+                }
+                live.pop();
+                Ok(Some(array))
+            }
+        }
+    }
+
+    // a box of primitive type `type_` holding `value`
+    fn boxed(&mut self, type_: char, value: &[Slot], live: &[ObjectRef]) -> Result<ObjectRef, Flow> {
+        let class = self.load(box_class(type_))?;
+        if let Some(flow) = self.initialize(&class) {
+            return Err(flow);
+        }
+        let object = self.allocate_keeping(Object::new(class), live)?;
+        set_value(self, object, value);
+        Ok(object)
+    }
+
+    // the names and values of the members of the annotation a handler stands for
+    fn members(&self, handler: ObjectRef) -> (Vec<String>, Vec<Option<ObjectRef>>) {
+        let handler = &self.heap[handler];
+        let names = handler.get_ref_field("names", "[Ljava/lang/String;").expect("a handler without names");
+        let values = handler.get_ref_field("values", "[Ljava/lang/Object;").expect("a handler without values");
+        let names =
+            self.heap[names].fields.borrow().iter().map(|name| self.rust_string(name.reference().unwrap())).collect();
+        let values = self.heap[values].fields.borrow().iter().map(Slot::reference).collect();
+        (names, values)
+    }
+
+    // the handler of `object` when it is an annotation made here
+    fn annotation_handler(&self, object: ObjectRef) -> Option<ObjectRef> {
+        if !self.is_proxy_class(&self.heap[object].class) {
+            return None;
+        }
+        let handler = self.heap[object].get_ref_field("h", "Ljava/lang/reflect/InvocationHandler;")?;
+        Some(handler).filter(|handler| self.heap[*handler].class.name == HANDLER)
+    }
+
+    // the sum over the members of 127 times the hash of the name xor that of the value
+    fn annotation_hash(&mut self, handler: ObjectRef) -> i32 {
+        let (names, values) = self.members(handler);
+        let mut hash = 0i32;
+        for (name, value) in names.iter().zip(values) {
+            let name_hash = string_hash(&name.encode_utf16().collect::<Vec<_>>());
+            hash = hash.wrapping_add(127i32.wrapping_mul(name_hash) ^ self.value_hash(value));
+        }
+        hash
+    }
+
+    // the hashCode of a member value, arrays hashed like java.util.Arrays does
+    fn value_hash(&mut self, value: Option<ObjectRef>) -> i32 {
+        let Some(object) = value else {
+            return 0;
+        };
+        let class = self.heap[object].class.clone();
+        if class.name == STRING {
+            return string_hash(&self.string_chars(object));
+        }
+        if let Some(type_) = box_type(&class.name) {
+            return hash_code(type_, &number::value(self, object));
+        }
+        if let Some(component) = class.name.strip_prefix('[') {
+            let elements = self.heap[object].fields.borrow().clone();
+            return match component.as_bytes()[0] {
+                b'L' | b'[' => elements.iter().fold(1i32, |hash, element| {
+                    hash.wrapping_mul(31).wrapping_add(self.value_hash(element.reference()))
+                }),
+                type_ => elements.chunks(width(type_ as char)).fold(1i32, |hash, element| {
+                    hash.wrapping_mul(31).wrapping_add(hash_code(type_ as char, element))
+                }),
+            };
+        }
+        match self.annotation_handler(object) {
+            Some(handler) => self.annotation_hash(handler),
+            None => self.heap[object].identity_hash(self.thread),
+        }
+    }
+
+    // whether two member values are equal, arrays compared like java.util.Arrays does
+    fn values_equal(&self, a: Option<ObjectRef>, b: Option<ObjectRef>) -> bool {
+        let (Some(a), Some(b)) = (a, b) else {
+            return a.is_none() && b.is_none();
+        };
+        let class = &self.heap[a].class;
+        if a == b {
+            return true;
+        }
+        if class.name != self.heap[b].class.name {
+            return false;
+        }
+        if class.name == STRING {
+            return self.string_chars(a) == self.string_chars(b);
+        }
+        if let Some(type_) = box_type(&class.name) {
+            return bits(type_, &number::value(self, a)) == bits(type_, &number::value(self, b));
+        }
+        if let Some(component) = class.name.strip_prefix('[') {
+            let (a, b) = (self.heap[a].fields.borrow(), self.heap[b].fields.borrow());
+            return a.len() == b.len()
+                && match component.as_bytes()[0] {
+                    b'L' | b'[' => a.iter().zip(b.iter()).all(|(a, b)| self.values_equal(a.reference(), b.reference())),
+                    type_ => a.chunks(width(type_ as char)).zip(b.chunks(width(type_ as char))).all(|(a, b)| {
+                        bits(type_ as char, a) == bits(type_ as char, b)
+                    }),
+                };
+        }
+        let (Some(a), Some(b)) = (self.annotation_handler(a), self.annotation_handler(b)) else {
+            return false;
+        };
+        let type_ = |handler: ObjectRef| self.heap[handler].get_ref_field("type", "Ljava/lang/Class;");
+        let ((names, values), (other_names, other_values)) = (self.members(a), self.members(b));
+        type_(a) == type_(b)
+            && names.len() == other_names.len()
+            && names.iter().zip(values).all(|(name, value)| {
+                let other = other_names.iter().position(|other| other == name).map(|index| other_values[index]);
+                other.is_some_and(|other| self.values_equal(value, other))
+            })
+    }
+
+    // @ and the name of the type, then the members with their values in parentheses, just the value of a lone
+    // member named value
+    fn annotation_string(&self, handler: ObjectRef) -> String {
+        let type_ = self.heap[handler].get_ref_field("type", "Ljava/lang/Class;").expect("a handler without a type");
+        let (names, values) = self.members(handler);
+        let members: Vec<String> = match &names[..] {
+            [name] if name == "value" => vec![self.value_string(values[0])],
+            _ => names
+                .iter()
+                .zip(values)
+                .map(|(name, value)| format!("{}={}", name, self.value_string(value)))
+                .collect(),
+        };
+        format!("@{}({})", self.class_of_mirror(type_).java_name(), members.join(", "))
+    }
+
+    // a member value the way it would be written in source
+    fn value_string(&self, value: Option<ObjectRef>) -> String {
+        let Some(object) = value else {
+            return String::from("null");
+        };
+        let class = &self.heap[object].class;
+        if class.name == STRING {
+            let chars = self.string_chars(object);
+            let quoted: String =
+                chars.iter().map(|char| if *char == b'\'' as u16 { String::from("'") } else { quote(*char) }).collect();
+            return format!("\"{}\"", quoted);
+        }
+        if let Some(type_) = box_type(&class.name) {
+            return primitive_string(type_, &number::value(self, object));
+        }
+        if let Some(component) = class.name.strip_prefix('[') {
+            let fields = self.heap[object].fields.borrow();
+            let elements: Vec<String> = match component.as_bytes()[0] {
+                b'L' | b'[' => fields.iter().map(|element| self.value_string(element.reference())).collect(),
+                type_ => {
+                    let type_ = type_ as char;
+                    fields.chunks(width(type_)).map(|element| primitive_string(type_, element)).collect()
+                }
+            };
+            return format!("{{{}}}", elements.join(", "));
+        }
+        if class.name == CLASS {
+            return format!("{}.class", source_name(&self.class_of_mirror(object).name));
+        }
+        if let Some(handler) = self.annotation_handler(object) {
+            return self.annotation_string(handler);
+        }
+        // an enum constant, by its name
+        match class.find_field("name", "Ljava/lang/String;") {
+            Some(_) => {
+                let name = self.heap[object].get_ref_field("name", "Ljava/lang/String;");
+                name.map_or_else(String::new, |name| self.rust_string(name))
+            }
+            None => class.java_name(),
+        }
+    }
+}
+
+fn return_boxed(object: Result<ObjectRef, Flow>) -> Flow {
+    match object {
+        Ok(object) => Flow::Return(vec![Slot::Reference(Some(object))]),
+        Err(flow) => flow,
+    }
+}
+
+// the class an annotation is an instance of
+fn annotation_type(pool: &[ConstantInfo], annotation: &Annotation) -> String {
+    let descriptor = get_utf8(pool, &annotation.type_index).unwrap_or_default();
+    type_class_name(parse_field_descriptor(&descriptor).as_ref())
+}
+
+fn is_inherited(type_: &Class) -> bool {
+    let pool = &type_.classfile.constant_pool;
+    annotations(&type_.classfile.attributes_info)
+        .iter()
+        .any(|annotation| get_utf8(pool, &annotation.type_index).as_deref() == Some(INHERITED))
+}
+
+fn return_descriptor(method: &Method) -> &str {
+    method.descriptor.rsplit_once(')').map_or("", |(_, descriptor)| descriptor)
+}
+
+// the slots of the integer, float, long or double constant at `index`
+fn constant_slots(pool: &[ConstantInfo], index: u16) -> Vec<Slot> {
+    match pool.get(index as usize) {
+        Some(ConstantInteger { value }) => vec![Slot::Int(*value as i32)],
+        Some(ConstantFloat { value }) => vec![Slot::Float(f32::from_bits(*value))],
+        Some(ConstantLong { value }) => Slot::wide(*value, false).to_vec(),
+        Some(ConstantDouble { value }) => Slot::wide(*value, true).to_vec(),
+        _ => vec![Slot::Int(0)],
+    }
+}
+
+// reorders `names` the way a java.util.HashMap iterates them once they have been put into it one by one: by
+// bucket of their spread hash, its table doubling from one bucket whenever it is more than three quarters full
+fn hash_map_order(names: &mut [String]) {
+    let mut buckets = 1;
+    while names.len() * 4 > buckets * 3 {
+        buckets *= 2;
+    }
+    names.sort_by_key(|name| {
+        let hash = string_hash(&name.encode_utf16().collect::<Vec<_>>()) as u32;
+        (hash ^ (hash >> 16)) as usize & (buckets - 1)
+    });
+}
+
+fn string_hash(chars: &[u16]) -> i32 {
+    chars.iter().fold(0i32, |hash, char| hash.wrapping_mul(31).wrapping_add(*char as i32))
+}
+
+// a primitive value the way it would be written in source
+fn primitive_string(type_: char, value: &[Slot]) -> String {
+    match type_ {
+        'B' => format!("(byte)0x{:02x}", value[0].int() as u8),
+        'C' => format!("'{}'", quote(value[0].int() as u16)),
+        'J' => format!("{}L", format_value(type_, value)),
+        'F' => match value[0].float() {
+            float if float.is_nan() => String::from("0.0f/0.0f"),
+            float if float.is_infinite() => format!("{}1.0f/0.0f", if float < 0.0 { "-" } else { "" }),
+            _ => format!("{}f", format_value(type_, value)),
+        },
+        'D' => match f64::from_bits(Slot::join(&value[0], &value[1], true)) {
+            double if double.is_nan() => String::from("0.0/0.0"),
+            double if double.is_infinite() => format!("{}1.0/0.0", if double < 0.0 { "-" } else { "" }),
+            _ => format_value(type_, value),
+        },
+        _ => format_value(type_, value),
+    }
+}
+
+// a char escaped the way it would be in a char literal
+fn quote(char: u16) -> String {
+    match char {
+        0x08 => String::from("\\b"),
+        0x0c => String::from("\\f"),
+        0x0a => String::from("\\n"),
+        0x0d => String::from("\\r"),
+        0x09 => String::from("\\t"),
+        0x27 => String::from("\\'"),
+        0x22 => String::from("\\\""),
+        0x5c => String::from("\\\\"),
+        0x20..=0x7e => String::from(char as u8 as char),
+        _ => format!("\\u{:04x}", char),
+    }
+}
+
+// the name of a class as source writes it, java.lang.String[] for [Ljava/lang/String;
+fn source_name(name: &str) -> String {
+    match name.strip_prefix('[') {
+        Some(component) => format!("{}[]", source_name(&type_class_name(parse_field_descriptor(component).as_ref()))),
+        None => name.replace('/', "."),
+    }
+}
@@ -0,0 +1,62 @@
+use crate::instructions::Flow;
+use crate::runtime::{LocalVars, Slot};
+
+use super::{Env, NativeRegistry};
+
+const METHOD_TYPE: &str = "java/lang/invoke/MethodType";
+
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(METHOD_TYPE, "methodType", "(Ljava/lang/Class;)Ljava/lang/invoke/MethodType;", method_type);
+    registry.register(
+        METHOD_TYPE,
+        "methodType",
+        "(Ljava/lang/Class;Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        method_type,
+    );
+    registry.register(
+        METHOD_TYPE,
+        "methodType",
+        "(Ljava/lang/Class;[Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        method_type,
+    );
+    registry.register(
+        METHOD_TYPE,
+        "methodType",
+        "(Ljava/lang/Class;Ljava/lang/Class;[Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        method_type,
+    );
+}
+
+// MethodType.methodType: the return type, then the parameter types one by one, in an array, or both
+fn method_type(env: &mut Env, args: &LocalVars) -> Flow {
+    let mut classes = Vec::new();
+    for slot in &args.0 {
+        let Some(object) = slot.reference() else {
+            return Flow::Throw(None);
+        };
+        if env.heap[object].class.is_array() {
+            let elements = env.heap[object].fields.borrow().iter().map(Slot::reference).collect::<Vec<_>>();
+            classes.extend(elements);
+        } else {
+            classes.push(Some(object));
+        }
+    }
+    let mut descriptors = Vec::new();
+    for class in classes {
+        let Some(class) = class else {
+            return Flow::Throw(None);
+        };
+        descriptors.push(env.class_of_mirror(class).descriptor());
+    }
+    if descriptors[1..].iter().any(|descriptor| descriptor == "V") {
+        return Flow::Raise {
+            class_name: "java/lang/IllegalArgumentException",
+            message: Some(String::from("void parameter type")),
+        };
+    }
+    let descriptor = format!("({}){}", descriptors[1..].concat(), descriptors[0]);
+    match env.method_type(&descriptor) {
+        Ok(method_type) => Flow::Return(vec![Slot::Reference(Some(method_type))]),
+        Err(flow) => flow,
+    }
+}
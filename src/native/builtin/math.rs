@@ -0,0 +1,158 @@
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::instructions::Flow;
+use crate::runtime::Slot;
+
+use super::NativeRegistry;
+
+const MATH: &str = "java/lang/Math";
+
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(MATH, "floor", "(D)D", |_, args| return_double(args.get_double(0).floor()));
+    registry.register(MATH, "ceil", "(D)D", |_, args| return_double(args.get_double(0).ceil()));
+    registry.register(MATH, "rint", "(D)D", |_, args| return_double(args.get_double(0).round_ties_even()));
+    registry.register(MATH, "sqrt", "(D)D", |_, args| return_double(args.get_double(0).sqrt()));
+    registry.register(MATH, "cbrt", "(D)D", |_, args| return_double(args.get_double(0).cbrt()));
+    registry.register(MATH, "exp", "(D)D", |_, args| return_double(args.get_double(0).exp()));
+    registry.register(MATH, "log", "(D)D", |_, args| return_double(args.get_double(0).ln()));
+    registry.register(MATH, "log10", "(D)D", |_, args| return_double(args.get_double(0).log10()));
+    registry.register(MATH, "sin", "(D)D", |_, args| return_double(args.get_double(0).sin()));
+    registry.register(MATH, "cos", "(D)D", |_, args| return_double(args.get_double(0).cos()));
+    registry.register(MATH, "tan", "(D)D", |_, args| return_double(args.get_double(0).tan()));
+    registry.register(MATH, "asin", "(D)D", |_, args| return_double(args.get_double(0).asin()));
+    registry.register(MATH, "acos", "(D)D", |_, args| return_double(args.get_double(0).acos()));
+    registry.register(MATH, "atan", "(D)D", |_, args| return_double(args.get_double(0).atan()));
+    registry.register(MATH, "toRadians", "(D)D", |_, args| return_double(args.get_double(0).to_radians()));
+    registry.register(MATH, "signum", "(D)D", |_, args| return_double(signum(args.get_double(0))));
+    registry.register(MATH, "toDegrees", "(D)D", |_, args| return_double(args.get_double(0).to_degrees()));
+    registry.register(MATH, "pow", "(DD)D", |_, args| return_double(pow(args.get_double(0), args.get_double(2))));
+    registry.register(MATH, "hypot", "(DD)D", |_, args| return_double(args.get_double(0).hypot(args.get_double(2))));
+    registry.register(MATH, "atan2", "(DD)D", |_, args| return_double(args.get_double(0).atan2(args.get_double(2))));
+    registry.register(MATH, "abs", "(I)I", |_, args| return_int(args.get_int(0).wrapping_abs()));
+    registry.register(MATH, "abs", "(J)J", |_, args| return_long(args.get_long(0).wrapping_abs()));
+    registry.register(MATH, "abs", "(F)F", |_, args| return_float(args.get_float(0).abs()));
+    registry.register(MATH, "abs", "(D)D", |_, args| return_double(args.get_double(0).abs()));
+    registry.register(MATH, "max", "(II)I", |_, args| return_int(args.get_int(0).max(args.get_int(1))));
+    registry.register(MATH, "max", "(JJ)J", |_, args| return_long(args.get_long(0).max(args.get_long(2))));
+    registry.register(MATH, "max", "(FF)F", |_, args| {
+        return_float(max(args.get_float(0) as f64, args.get_float(1) as f64) as f32)
+    });
+    registry.register(MATH, "max", "(DD)D", |_, args| return_double(max(args.get_double(0), args.get_double(2))));
+    registry.register(MATH, "min", "(II)I", |_, args| return_int(args.get_int(0).min(args.get_int(1))));
+    registry.register(MATH, "min", "(JJ)J", |_, args| return_long(args.get_long(0).min(args.get_long(2))));
+    registry.register(MATH, "min", "(FF)F", |_, args| {
+        return_float(min(args.get_float(0) as f64, args.get_float(1) as f64) as f32)
+    });
+    registry.register(MATH, "min", "(DD)D", |_, args| return_double(min(args.get_double(0), args.get_double(2))));
+    registry.register(MATH, "floorDiv", "(II)I", |_, args| match floor_div(args.get_int(0), args.get_int(1)) {
+        Some(quotient) => return_int(quotient),
+        None => divide_by_zero(),
+    });
+    registry.register(MATH, "floorMod", "(II)I", |_, args| {
+        let (x, y) = (args.get_int(0), args.get_int(1));
+        match floor_div(x, y) {
+            Some(quotient) => return_int(x.wrapping_sub(quotient.wrapping_mul(y))),
+            None => divide_by_zero(),
+        }
+    });
+    registry.register(MATH, "round", "(F)I", |_, args| return_int(round(args.get_float(0) as f64) as i32));
+    registry.register(MATH, "round", "(D)J", |_, args| return_long(round(args.get_double(0)) as i64));
+    registry.register(MATH, "random", "()D", |_, _| return_double(random()));
+}
+
+// NaN when either is NaN, and 0.0 is larger than -0.0
+fn max(a: f64, b: f64) -> f64 {
+    match (a.is_nan() || b.is_nan(), a == b) {
+        (true, _) => f64::NAN,
+        (false, true) if a.is_sign_negative() => b,
+        (false, true) => a,
+        (false, false) => a.max(b),
+    }
+}
+
+fn min(a: f64, b: f64) -> f64 {
+    match (a.is_nan() || b.is_nan(), a == b) {
+        (true, _) => f64::NAN,
+        (false, true) if a.is_sign_negative() => a,
+        (false, true) => b,
+        (false, false) => a.min(b),
+    }
+}
+
+// ±0.0 and NaN are their own signum
+fn signum(value: f64) -> f64 {
+    match value == 0.0 || value.is_nan() {
+        true => value,
+        false => value.signum(),
+    }
+}
+
+// unlike powf, pow(1.0, NaN) and pow(-1.0, ±Infinity) are NaN in Java
+fn pow(base: f64, exponent: f64) -> f64 {
+    match (base, exponent) {
+        (_, exponent) if exponent.is_nan() => f64::NAN,
+        (base, exponent) if base.abs() == 1.0 && exponent.is_infinite() => f64::NAN,
+        _ => base.powf(exponent),
+    }
+}
+
+// rounds half up, the conversion to int or long saturating and taking NaN to 0
+fn round(value: f64) -> f64 {
+    let floor = value.floor();
+    match value - floor >= 0.5 {
+        true => floor + 1.0,
+        false => floor,
+    }
+}
+
+// None for a division by zero
+fn floor_div(x: i32, y: i32) -> Option<i32> {
+    if y == 0 {
+        return None;
+    }
+    let quotient = x.wrapping_div(y);
+    match x.wrapping_rem(y) != 0 && (x ^ y) < 0 {
+        true => Some(quotient - 1),
+        false => Some(quotient),
+    }
+}
+
+thread_local! {
+    static RANDOM_STATE: Cell<u64> = const { Cell::new(0) };
+}
+
+// xorshift64*, seeded from the clock on first use; good enough for the teaching examples it is for
+fn random() -> f64 {
+    RANDOM_STATE.with(|state| {
+        let mut x = state.get();
+        if x == 0 {
+            x = SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |time| time.as_nanos() as u64) | 1;
+        }
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        (x.wrapping_mul(0x2545F4914F6CDD1D) >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
+
+fn divide_by_zero() -> Flow {
+    Flow::Raise { class_name: "java/lang/ArithmeticException", message: Some(String::from("/ by zero")) }
+}
+
+fn return_int(value: i32) -> Flow {
+    Flow::Return(vec![Slot::Int(value)])
+}
+
+fn return_long(value: i64) -> Flow {
+    Flow::Return(Slot::wide(value as u64, false).to_vec())
+}
+
+fn return_float(value: f32) -> Flow {
+    Flow::Return(vec![Slot::Float(value)])
+}
+
+fn return_double(value: f64) -> Flow {
+    Flow::Return(Slot::wide(value.to_bits(), true).to_vec())
+}
@@ -0,0 +1,300 @@
+// the natives of the built-in class library, classpath::builtin, which stands in for the JDK when there
+// is no JRE; they are only registered then, so they never shadow the bytecode of a JDK class
+mod annotation;
+mod invoke;
+mod math;
+mod number;
+mod reflect;
+mod string;
+
+use std::io::Write;
+
+use crate::classpath::builtin::THROWABLES;
+use crate::instructions::Flow;
+use crate::runtime::class::primitive_name;
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{LocalVars, Object, Slot};
+
+use super::{io, system, this, throwable, Env, NativeMethod, NativeRegistry};
+
+const OBJECT: &str = "java/lang/Object";
+const CLASS: &str = "java/lang/Class";
+const SYSTEM: &str = "java/lang/System";
+const PRINT_STREAM: &str = "java/io/PrintStream";
+const OBJECTS: &str = "java/util/Objects";
+
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(OBJECT, "equals", "(Ljava/lang/Object;)Z", object_equals);
+    registry.register(CLASS, "getName", "()Ljava/lang/String;", class_name);
+    registry.register(CLASS, "getSimpleName", "()Ljava/lang/String;", class_simple_name);
+    registry.register(CLASS, "toString", "()Ljava/lang/String;", class_to_string);
+    registry.register(CLASS, "desiredAssertionStatus", "()Z", |_, _| Flow::Return(vec![Slot::Int(0)]));
+    registry.register(SYSTEM, "getProperty", "(Ljava/lang/String;)Ljava/lang/String;", get_property);
+    registry.register(SYSTEM, "lineSeparator", "()Ljava/lang/String;", |env, _| return_string(env.intern("\n")));
+    registry.register(SYSTEM, "gc", "()V", system::gc);
+    registry.register(SYSTEM, "exit", "(I)V", system::halt);
+    for (descriptor, print) in PRINTS {
+        registry.register(PRINT_STREAM, "print", descriptor, print);
+    }
+    for (descriptor, println) in PRINTLNS {
+        registry.register(PRINT_STREAM, "println", descriptor, println);
+    }
+    registry.register(PRINT_STREAM, "println", "()V", |env, args| print(env, args, String::new(), true));
+    registry.register(PRINT_STREAM, "write", "(I)V", write);
+    registry.register(PRINT_STREAM, "flush", "()V", flush);
+    for (class, _) in THROWABLES {
+        registry.register(class, "<init>", "()V", throwable_init);
+        registry.register(class, "<init>", "(Ljava/lang/String;)V", throwable_init_message);
+        registry.register(class, "<init>", "(Ljava/lang/String;Ljava/lang/Throwable;)V", throwable_init_message_cause);
+        registry.register(class, "<init>", "(Ljava/lang/Throwable;)V", throwable_init_cause);
+    }
+    registry.register(THROWABLE, "getMessage", "()Ljava/lang/String;", get_message);
+    registry.register(THROWABLE, "getCause", "()Ljava/lang/Throwable;", get_cause);
+    registry.register(THROWABLE, "initCause", "(Ljava/lang/Throwable;)Ljava/lang/Throwable;", init_cause);
+    registry.register(THROWABLE, "fillInStackTrace", "()Ljava/lang/Throwable;", fill_in_stack_trace);
+    registry.register(THROWABLE, "printStackTrace", "()V", print_stack_trace);
+    registry.register(OBJECTS, "requireNonNull", "(Ljava/lang/Object;)Ljava/lang/Object;", require_non_null);
+    registry.register(
+        OBJECTS,
+        "requireNonNull",
+        "(Ljava/lang/Object;Ljava/lang/String;)Ljava/lang/Object;",
+        require_non_null_message,
+    );
+    annotation::register(registry);
+    invoke::register(registry);
+    math::register(registry);
+    number::register(registry);
+    reflect::register(registry);
+    string::register(registry);
+}
+
+impl Env<'_> {
+    // System.out and System.err, which the JDK sets up in System.initPhase1
+    pub fn open_standard_streams(&mut self) -> Result<(), Flow> {
+        let system = self.load(SYSTEM)?;
+        let print_stream = self.load(PRINT_STREAM)?;
+        for (name, fd) in [("out", 1), ("err", 2)] {
+            let stream = self.allocate(Object::new(print_stream.clone()))?;
+            self.heap[stream].set_int_field("fd", "I", fd);
+            let (_, field) = system.find_field(name, "Ljava/io/PrintStream;").expect("System without standard streams");
+            system.static_fields.borrow_mut()[field.slot] = Slot::Reference(Some(stream));
+        }
+        Ok(())
+    }
+}
+
+fn object_equals(_: &mut Env, args: &LocalVars) -> Flow {
+    return_bool(args.get_ref(1) == Some(this(args)))
+}
+
+fn class_name(env: &mut Env, args: &LocalVars) -> Flow {
+    let name = env.class_of_mirror(this(args)).java_name();
+    return_string(env.intern(&name))
+}
+
+// the name in the source, which an array class gets from its element class; none for anonymous classes
+fn class_simple_name(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(this(args));
+    let dimensions = class.name.chars().take_while(|char| *char == '[').count();
+    let element = &class.name[dimensions..];
+    let name = match element.strip_prefix('L').and_then(|name| name.strip_suffix(';')) {
+        Some(name) => name,
+        None if dimensions > 0 => primitive_name(element),
+        None => element,
+    };
+    let simple_name = name.rsplit(['/', '$']).next().unwrap_or_default();
+    let simple_name = simple_name.trim_start_matches(|char: char| char.is_ascii_digit());
+    return_string(env.new_string(&format!("{}{}", simple_name, "[]".repeat(dimensions))))
+}
+
+fn class_to_string(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(this(args));
+    let name = match class.is_primitive() {
+        true => class.java_name(),
+        false if class.is_interface() => format!("interface {}", class.java_name()),
+        false => format!("class {}", class.java_name()),
+    };
+    return_string(env.new_string(&name))
+}
+
+// the properties the VM defines, see Interpreter::new
+fn get_property(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(key) = args.get_ref(0) else {
+        return Flow::Throw(None);
+    };
+    let key = env.rust_string(key);
+    match env.properties.iter().find(|(name, _)| *name == key) {
+        Some((_, value)) => return_string(env.new_string(&value.clone())),
+        None => Flow::Return(vec![Slot::Reference(None)]),
+    }
+}
+
+const PRINTS: [(&str, NativeMethod); 8] = [
+    ("(Ljava/lang/String;)V", |env, args| print_string(env, args, false)),
+    ("(Z)V", |env, args| print(env, args, (args.get_int(1) != 0).to_string(), false)),
+    ("(C)V", |env, args| print(env, args, String::from_utf16_lossy(&[args.get_int(1) as u16]), false)),
+    ("(I)V", |env, args| print(env, args, args.get_int(1).to_string(), false)),
+    ("(J)V", |env, args| print(env, args, args.get_long(1).to_string(), false)),
+    ("(F)V", |env, args| print(env, args, number::float_string(args.get_float(1)), false)),
+    ("(D)V", |env, args| print(env, args, number::double_string(args.get_double(1)), false)),
+    ("([C)V", |env, args| print_chars(env, args, false)),
+];
+
+const PRINTLNS: [(&str, NativeMethod); 8] = [
+    ("(Ljava/lang/String;)V", |env, args| print_string(env, args, true)),
+    ("(Z)V", |env, args| print(env, args, (args.get_int(1) != 0).to_string(), true)),
+    ("(C)V", |env, args| print(env, args, String::from_utf16_lossy(&[args.get_int(1) as u16]), true)),
+    ("(I)V", |env, args| print(env, args, args.get_int(1).to_string(), true)),
+    ("(J)V", |env, args| print(env, args, args.get_long(1).to_string(), true)),
+    ("(F)V", |env, args| print(env, args, number::float_string(args.get_float(1)), true)),
+    ("(D)V", |env, args| print(env, args, number::double_string(args.get_double(1)), true)),
+    ("([C)V", |env, args| print_chars(env, args, true)),
+];
+
+fn print_string(env: &mut Env, args: &LocalVars, newline: bool) -> Flow {
+    let text = args.get_ref(1).map_or_else(|| String::from("null"), |string| env.rust_string(string));
+    print(env, args, text, newline)
+}
+
+fn print_chars(env: &mut Env, args: &LocalVars, newline: bool) -> Flow {
+    let Some(chars) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let text = String::from_utf16_lossy(&string::array_chars(env, chars));
+    print(env, args, text, newline)
+}
+
+// text goes out as UTF-8, whatever the platform encoding
+fn print(env: &mut Env, args: &LocalVars, mut text: String, newline: bool) -> Flow {
+    if newline {
+        text.push('\n');
+    }
+    let fd = env.heap[this(args)].get_int_field("fd", "I");
+    io::write_fd(env, fd, text.as_bytes())
+}
+
+fn write(env: &mut Env, args: &LocalVars) -> Flow {
+    let fd = env.heap[this(args)].get_int_field("fd", "I");
+    io::write_fd(env, fd, &[args.get_int(1) as u8])
+}
+
+fn flush(env: &mut Env, _: &LocalVars) -> Flow {
+    let _ = env.output.stdout.flush();
+    Flow::Return(Vec::new())
+}
+
+const THROWABLE: &str = "java/lang/Throwable";
+
+fn throwable_init(env: &mut Env, args: &LocalVars) -> Flow {
+    init_throwable(env, this(args), None, None)
+}
+
+fn throwable_init_message(env: &mut Env, args: &LocalVars) -> Flow {
+    init_throwable(env, this(args), args.get_ref(1), None)
+}
+
+fn throwable_init_message_cause(env: &mut Env, args: &LocalVars) -> Flow {
+    init_throwable(env, this(args), args.get_ref(1), args.get_ref(2))
+}
+
+// the message is what cause.toString() gives, unless a subclass of the cause overrides it
+fn throwable_init_cause(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(cause) = args.get_ref(1) else {
+        return init_throwable(env, this(args), None, None);
+    };
+    let message = match env.new_string(&describe(env, cause)) {
+        Ok(message) => message,
+        Err(flow) => return flow,
+    };
+    init_throwable(env, this(args), Some(message), Some(cause))
+}
+
+fn init_throwable(env: &mut Env, this: ObjectRef, message: Option<ObjectRef>, cause: Option<ObjectRef>) -> Flow {
+    let object = &env.heap[this];
+    object.set_ref_field("detailMessage", "Ljava/lang/String;", message);
+    object.set_ref_field("cause", "Ljava/lang/Throwable;", cause);
+    env.heap.write_barrier(this);
+    throwable::fill_in(env, this);
+    Flow::Return(Vec::new())
+}
+
+fn get_message(env: &mut Env, args: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Reference(env.heap[this(args)].get_ref_field("detailMessage", "Ljava/lang/String;"))])
+}
+
+fn get_cause(env: &mut Env, args: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Reference(env.heap[this(args)].get_ref_field("cause", "Ljava/lang/Throwable;"))])
+}
+
+fn init_cause(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    if args.get_ref(1) == Some(this) {
+        return Flow::Raise {
+            class_name: "java/lang/IllegalArgumentException",
+            message: Some(String::from("Self-causation not permitted")),
+        };
+    }
+    env.heap[this].set_ref_field("cause", "Ljava/lang/Throwable;", args.get_ref(1));
+    env.heap.write_barrier(this);
+    Flow::Return(vec![Slot::Reference(Some(this))])
+}
+
+fn fill_in_stack_trace(env: &mut Env, args: &LocalVars) -> Flow {
+    throwable::fill_in(env, this(args));
+    Flow::Return(vec![Slot::Reference(Some(this(args)))])
+}
+
+// to stderr, in the format of the JDK, with the causes after the throwable
+fn print_stack_trace(env: &mut Env, args: &LocalVars) -> Flow {
+    let mut text = String::new();
+    let mut throwable = Some(this(args));
+    while let Some(current) = throwable {
+        if current != this(args) {
+            text.push_str("Caused by: ");
+        }
+        text.push_str(&describe(env, current));
+        text.push('\n');
+        for element in env.heap[current].stack_trace.borrow().iter() {
+            text.push_str(&format!("\tat {}\n", element));
+        }
+        throwable = env.heap[current].get_ref_field("cause", "Ljava/lang/Throwable;").filter(|cause| *cause != current);
+    }
+    io::write_fd(env, 2, text.as_bytes())
+}
+
+// what Throwable.toString gives without overrides
+fn describe(env: &Env, throwable: ObjectRef) -> String {
+    let object = &env.heap[throwable];
+    match object.get_ref_field("detailMessage", "Ljava/lang/String;") {
+        Some(message) => format!("{}: {}", object.class.java_name(), env.rust_string(message)),
+        None => object.class.java_name(),
+    }
+}
+
+fn require_non_null(_: &mut Env, args: &LocalVars) -> Flow {
+    match args.get_ref(0) {
+        Some(object) => Flow::Return(vec![Slot::Reference(Some(object))]),
+        None => Flow::Throw(None),
+    }
+}
+
+fn require_non_null_message(env: &mut Env, args: &LocalVars) -> Flow {
+    match args.get_ref(0) {
+        Some(object) => Flow::Return(vec![Slot::Reference(Some(object))]),
+        None => Flow::Raise {
+            class_name: "java/lang/NullPointerException",
+            message: args.get_ref(1).map(|message| env.rust_string(message)),
+        },
+    }
+}
+
+fn return_bool(value: bool) -> Flow {
+    Flow::Return(vec![Slot::Int(value as i32)])
+}
+
+fn return_string(string: Result<ObjectRef, Flow>) -> Flow {
+    match string {
+        Ok(string) => Flow::Return(vec![Slot::Reference(Some(string))]),
+        Err(flow) => flow,
+    }
+}
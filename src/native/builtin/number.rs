@@ -0,0 +1,449 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use crate::instructions::Flow;
+use crate::runtime::class::Class;
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{LocalVars, Object, Slot};
+
+use super::string::map_char;
+use super::{return_bool, return_string, this, Env, NativeRegistry};
+
+// the box classes with the types of their values
+const BOXES: [(&str, char); 8] = [
+    ("java/lang/Integer", 'I'),
+    ("java/lang/Long", 'J'),
+    ("java/lang/Short", 'S'),
+    ("java/lang/Byte", 'B'),
+    ("java/lang/Float", 'F'),
+    ("java/lang/Double", 'D'),
+    ("java/lang/Boolean", 'Z'),
+    ("java/lang/Character", 'C'),
+];
+const FLOAT: &str = "java/lang/Float";
+const DOUBLE: &str = "java/lang/Double";
+const CHARACTER: &str = "java/lang/Character";
+
+// the boxes keep their value in a `value` field like the JDK's; their natives go by the type of that field
+pub(super) fn register(registry: &mut NativeRegistry) {
+    register_box::<'I'>(registry);
+    register_box::<'J'>(registry);
+    register_box::<'S'>(registry);
+    register_box::<'B'>(registry);
+    register_box::<'F'>(registry);
+    register_box::<'D'>(registry);
+    register_box::<'Z'>(registry);
+    register_box::<'C'>(registry);
+    for (class, _) in &BOXES[..6] {
+        registry.register(class, "byteValue", "()B", |env, args| return_int(number(env, this(args)).int() as i8 as i32));
+        registry.register(class, "shortValue", "()S", |env, args| return_int(number(env, this(args)).int() as i16 as i32));
+        registry.register(class, "intValue", "()I", |env, args| return_int(number(env, this(args)).int()));
+        registry.register(class, "longValue", "()J", |env, args| return_long(number(env, this(args)).long()));
+        registry.register(class, "floatValue", "()F", |env, args| {
+            Flow::Return(vec![Slot::Float(number(env, this(args)).float())])
+        });
+        registry.register(class, "doubleValue", "()D", |env, args| {
+            Flow::Return(Slot::wide(number(env, this(args)).double().to_bits(), true).to_vec())
+        });
+    }
+    registry.register("java/lang/Boolean", "booleanValue", "()Z", |env, args| return_int(value(env, this(args))[0].int()));
+    registry.register(CHARACTER, "charValue", "()C", |env, args| return_int(value(env, this(args))[0].int()));
+    registry.register("java/lang/Integer", "parseInt", "(Ljava/lang/String;)I", |env, args| {
+        parse_integer(env, args, i32::MIN as i64, i32::MAX as i64).map_or_else(|flow| flow, |value| return_int(value as i32))
+    });
+    registry.register("java/lang/Long", "parseLong", "(Ljava/lang/String;)J", |env, args| {
+        parse_integer(env, args, i64::MIN, i64::MAX).map_or_else(|flow| flow, return_long)
+    });
+    registry.register("java/lang/Short", "parseShort", "(Ljava/lang/String;)S", |env, args| {
+        parse_integer(env, args, i16::MIN as i64, i16::MAX as i64).map_or_else(|flow| flow, |value| return_int(value as i32))
+    });
+    registry.register("java/lang/Byte", "parseByte", "(Ljava/lang/String;)B", |env, args| {
+        parse_integer(env, args, i8::MIN as i64, i8::MAX as i64).map_or_else(|flow| flow, |value| return_int(value as i32))
+    });
+    registry.register(FLOAT, "parseFloat", "(Ljava/lang/String;)F", |env, args| {
+        parse_decimal(env, args).map_or_else(|flow| flow, |value| Flow::Return(vec![Slot::Float(value as f32)]))
+    });
+    registry.register(DOUBLE, "parseDouble", "(Ljava/lang/String;)D", |env, args| {
+        parse_decimal(env, args).map_or_else(|flow| flow, |value| Flow::Return(Slot::wide(value.to_bits(), true).to_vec()))
+    });
+    registry.register("java/lang/Boolean", "parseBoolean", "(Ljava/lang/String;)Z", |env, args| {
+        return_bool(args.get_ref(0).is_some_and(|string| env.rust_string(string).eq_ignore_ascii_case("true")))
+    });
+    registry.register("java/lang/Integer", "toHexString", "(I)Ljava/lang/String;", |env, args| {
+        return_string(env.new_string(&format!("{:x}", args.get_int(0))))
+    });
+    registry.register("java/lang/Integer", "toBinaryString", "(I)Ljava/lang/String;", |env, args| {
+        return_string(env.new_string(&format!("{:b}", args.get_int(0))))
+    });
+    registry.register("java/lang/Long", "toHexString", "(J)Ljava/lang/String;", |env, args| {
+        return_string(env.new_string(&format!("{:x}", args.get_long(0))))
+    });
+    registry.register("java/lang/Long", "toBinaryString", "(J)Ljava/lang/String;", |env, args| {
+        return_string(env.new_string(&format!("{:b}", args.get_long(0))))
+    });
+    registry.register(FLOAT, "isNaN", "(F)Z", |_, args| return_bool(args.get_float(0).is_nan()));
+    registry.register(FLOAT, "isInfinite", "(F)Z", |_, args| return_bool(args.get_float(0).is_infinite()));
+    registry.register(FLOAT, "floatToIntBits", "(F)I", |_, args| return_int(float_bits(args.get_float(0)) as i32));
+    registry.register(DOUBLE, "isNaN", "(D)Z", |_, args| return_bool(args.get_double(0).is_nan()));
+    registry.register(DOUBLE, "isInfinite", "(D)Z", |_, args| return_bool(args.get_double(0).is_infinite()));
+    registry.register(DOUBLE, "doubleToLongBits", "(D)J", |_, args| return_long(double_bits(args.get_double(0)) as i64));
+    registry.register(CHARACTER, "isDigit", "(C)Z", |_, args| return_bool(test_char(args, char::is_numeric)));
+    registry.register(CHARACTER, "isLetter", "(C)Z", |_, args| return_bool(test_char(args, char::is_alphabetic)));
+    registry.register(CHARACTER, "isLetterOrDigit", "(C)Z", |_, args| {
+        return_bool(test_char(args, char::is_alphanumeric))
+    });
+    registry.register(CHARACTER, "isWhitespace", "(C)Z", |_, args| return_bool(test_char(args, is_whitespace)));
+    registry.register(CHARACTER, "isUpperCase", "(C)Z", |_, args| return_bool(test_char(args, char::is_uppercase)));
+    registry.register(CHARACTER, "isLowerCase", "(C)Z", |_, args| return_bool(test_char(args, char::is_lowercase)));
+    registry.register(CHARACTER, "toUpperCase", "(C)C", |_, args| {
+        return_int(map_char(args.get_int(0) as u16, char::to_uppercase) as i32)
+    });
+    registry.register(CHARACTER, "toLowerCase", "(C)C", |_, args| {
+        return_int(map_char(args.get_int(0) as u16, char::to_lowercase) as i32)
+    });
+}
+
+// what every box has: a constructor and valueOf taking the value, toString, hashCode, equals, compareTo,
+// and the static toString and compare
+fn register_box<const TYPE: char>(registry: &mut NativeRegistry) {
+    let class = box_class(TYPE);
+    let (class_descriptor, type_descriptor) = (format!("L{};", class), TYPE.to_string());
+    registry.register(class, "<init>", &format!("({})V", type_descriptor), init::<TYPE>);
+    registry.register(class, "valueOf", &format!("({}){}", type_descriptor, class_descriptor), value_of::<TYPE>);
+    registry.register(class, "toString", &format!("({})Ljava/lang/String;", type_descriptor), |env, args| {
+        let value = format_value(TYPE, &args.0[..width(TYPE)]);
+        return_string(env.new_string(&value))
+    });
+    registry.register(class, "compare", &format!("({}{})I", type_descriptor, type_descriptor), |_, args| {
+        let width = width(TYPE);
+        return_int(compare(TYPE, &args.0[..width], &args.0[width..2 * width]))
+    });
+    registry.register(class, "toString", "()Ljava/lang/String;", |env, args| {
+        let value = format_value(TYPE, &value(env, this(args)));
+        return_string(env.new_string(&value))
+    });
+    registry.register(class, "hashCode", "()I", |env, args| return_int(hash_code(TYPE, &value(env, this(args)))));
+    registry.register(class, "equals", "(Ljava/lang/Object;)Z", |env, args| {
+        let other = args.get_ref(1).filter(|other| env.heap[*other].class.name == box_class(TYPE));
+        return_bool(other.is_some_and(|other| bits(TYPE, &value(env, this(args))) == bits(TYPE, &value(env, other))))
+    });
+    registry.register(class, "compareTo", &format!("({})I", class_descriptor), |env, args| {
+        let Some(other) = args.get_ref(1) else {
+            return Flow::Throw(None);
+        };
+        return_int(compare(TYPE, &value(env, this(args)), &value(env, other)))
+    });
+}
+
+// the primitive type a box is for, by the name of its class
+pub(super) fn box_type(class_name: &str) -> Option<char> {
+    BOXES.iter().find(|(class, _)| *class == class_name).map(|(_, type_)| *type_)
+}
+
+pub(super) fn box_class(type_: char) -> &'static str {
+    BOXES.iter().find(|(_, box_type)| *box_type == type_).expect("a box for every primitive type").0
+}
+
+pub(super) fn width(type_: char) -> usize {
+    match type_ {
+        'J' | 'D' => 2,
+        _ => 1,
+    }
+}
+
+fn init<const TYPE: char>(env: &mut Env, args: &LocalVars) -> Flow {
+    set_value(env, this(args), &args.0[1..1 + width(TYPE)]);
+    Flow::Return(Vec::new())
+}
+
+// boxes the same value in the same box where JLS §5.1.7 says to: booleans, -128 to 127 and chars up to 127.
+// Boolean has its TRUE and FALSE, the other boxes keep what they box in their static array `cache`, made and
+// filled as they are asked for
+fn value_of<const TYPE: char>(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = match env.load(box_class(TYPE)) {
+        Ok(class) => class,
+        Err(flow) => return flow,
+    };
+    if let Some(flow) = env.initialize(&class) {
+        return flow;
+    }
+    let cache_index = match TYPE {
+        'Z' => {
+            let name = if args.get_int(0) != 0 { "TRUE" } else { "FALSE" };
+            let (_, field) = class.find_field(name, "Ljava/lang/Boolean;").expect("Boolean without its constants");
+            return Flow::Return(vec![class.static_fields.borrow()[field.slot].clone()]);
+        }
+        'B' | 'S' | 'I' => Some(args.get_int(0) as i64 + 128).filter(|index| (0..256).contains(index)),
+        'J' => args.get_long(0).checked_add(128).filter(|index| (0..256).contains(index)),
+        'C' => Some(args.get_int(0) as i64).filter(|index| *index < 128),
+        _ => None,
+    };
+    let Some(index) = cache_index else {
+        return new_box::<TYPE>(env, &class, args);
+    };
+    let descriptor = format!("[L{};", box_class(TYPE));
+    let (_, field) = class.find_field("cache", &descriptor).expect("a box without its cache");
+    let cache = class.static_fields.borrow()[field.slot].reference();
+    let cache = match cache {
+        Some(cache) => cache,
+        None => match env.new_array(&descriptor, &[if TYPE == 'C' { 128 } else { 256 }]) {
+            Ok(cache) => {
+                class.static_fields.borrow_mut()[field.slot] = Slot::Reference(Some(cache));
+                cache
+            }
+            Err(flow) => return flow,
+        },
+    };
+    let cached = env.heap[cache].fields.borrow()[index as usize].clone();
+    if let Slot::Reference(Some(_)) = cached {
+        return Flow::Return(vec![cached]);
+    }
+    // the cache is kept alive by the class through the allocation
+    let object = new_box::<TYPE>(env, &class, args);
+    if let Flow::Return(slots) = &object {
+        env.heap[cache].fields.borrow_mut()[index as usize] = slots[0].clone();
+        env.heap.write_barrier(cache);
+    }
+    object
+}
+
+fn new_box<const TYPE: char>(env: &mut Env, class: &Rc<Class>, args: &LocalVars) -> Flow {
+    match env.allocate(Object::new(class.clone())) {
+        Ok(object) => {
+            set_value(env, object, &args.0[..width(TYPE)]);
+            Flow::Return(vec![Slot::Reference(Some(object))])
+        }
+        Err(flow) => flow,
+    }
+}
+
+// the slots of the value of a box
+pub(super) fn value(env: &Env, object: ObjectRef) -> Vec<Slot> {
+    let object = &env.heap[object];
+    let field = object.class.fields.iter().find(|field| field.name == "value").expect("a box without a value");
+    object.fields.borrow()[field.slot..field.slot + width(field.descriptor.chars().next().unwrap_or('I'))].to_vec()
+}
+
+pub(super) fn set_value(env: &Env, object: ObjectRef, value: &[Slot]) {
+    let object = &env.heap[object];
+    let field = object.class.fields.iter().find(|field| field.name == "value").expect("a box without a value");
+    object.fields.borrow_mut()[field.slot..field.slot + value.len()].clone_from_slice(value);
+}
+
+// the value of a numeric box, converted the way the JVM's conversion instructions do
+enum Number {
+    Integral(i64),
+    Decimal(f64),
+}
+
+impl Number {
+    fn int(&self) -> i32 {
+        match self {
+            Number::Integral(value) => *value as i32,
+            Number::Decimal(value) => *value as i32,
+        }
+    }
+
+    fn long(&self) -> i64 {
+        match self {
+            Number::Integral(value) => *value,
+            Number::Decimal(value) => *value as i64,
+        }
+    }
+
+    fn float(&self) -> f32 {
+        match self {
+            Number::Integral(value) => *value as f32,
+            Number::Decimal(value) => *value as f32,
+        }
+    }
+
+    fn double(&self) -> f64 {
+        match self {
+            Number::Integral(value) => *value as f64,
+            Number::Decimal(value) => *value,
+        }
+    }
+}
+
+fn number(env: &Env, object: ObjectRef) -> Number {
+    let value = value(env, object);
+    match &value[..] {
+        [Slot::Float(value)] => Number::Decimal(*value as f64),
+        [Slot::Int(value)] => Number::Integral(*value as i64),
+        [first, second] => match first {
+            Slot::Double { .. } => Number::Decimal(f64::from_bits(Slot::join(first, second, true))),
+            _ => Number::Integral(Slot::join(first, second, false) as i64),
+        },
+        _ => unreachable!("a box of an unknown type"),
+    }
+}
+
+// the value as the bits equals compares, with every NaN the same
+pub(super) fn bits(type_: char, value: &[Slot]) -> u64 {
+    match type_ {
+        'F' => float_bits(value[0].float()) as u64,
+        'D' => double_bits(f64::from_bits(Slot::join(&value[0], &value[1], true))),
+        'J' => Slot::join(&value[0], &value[1], false),
+        _ => value[0].int() as u64,
+    }
+}
+
+fn float_bits(value: f32) -> u32 {
+    match value.is_nan() {
+        true => 0x7fc00000,
+        false => value.to_bits(),
+    }
+}
+
+fn double_bits(value: f64) -> u64 {
+    match value.is_nan() {
+        true => 0x7ff8000000000000,
+        false => value.to_bits(),
+    }
+}
+
+pub(super) fn hash_code(type_: char, value: &[Slot]) -> i32 {
+    let bits = bits(type_, value);
+    match type_ {
+        'Z' if bits != 0 => 1231,
+        'Z' => 1237,
+        'J' | 'D' => (bits ^ (bits >> 32)) as i32,
+        _ => bits as i32,
+    }
+}
+
+// Character, Short and Byte give the difference, the others -1, 0 or 1; decimals are ordered like
+// their bits, with -0.0 below 0.0 and NaN above everything
+fn compare(type_: char, a: &[Slot], b: &[Slot]) -> i32 {
+    let ordering = match type_ {
+        'C' | 'S' | 'B' => return a[0].int() - b[0].int(),
+        'F' => {
+            let (a, b) = (a[0].float(), b[0].float());
+            a.partial_cmp(&b).filter(|ordering| ordering.is_ne()).unwrap_or_else(|| {
+                (float_bits(a) as i32).cmp(&(float_bits(b) as i32))
+            })
+        }
+        'D' => {
+            let a = f64::from_bits(Slot::join(&a[0], &a[1], true));
+            let b = f64::from_bits(Slot::join(&b[0], &b[1], true));
+            a.partial_cmp(&b).filter(|ordering| ordering.is_ne()).unwrap_or_else(|| {
+                (double_bits(a) as i64).cmp(&(double_bits(b) as i64))
+            })
+        }
+        'J' => (Slot::join(&a[0], &a[1], false) as i64).cmp(&(Slot::join(&b[0], &b[1], false) as i64)),
+        _ => a[0].int().cmp(&b[0].int()),
+    };
+    match ordering {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+pub(super) fn format_value(type_: char, value: &[Slot]) -> String {
+    match type_ {
+        'Z' => (value[0].int() != 0).to_string(),
+        'C' => String::from_utf16_lossy(&[value[0].int() as u16]),
+        'F' => float_string(value[0].float()),
+        'D' => double_string(f64::from_bits(Slot::join(&value[0], &value[1], true))),
+        'J' => (Slot::join(&value[0], &value[1], false) as i64).to_string(),
+        _ => value[0].int().to_string(),
+    }
+}
+
+// Float.toString: the shortest decimal that reads back as the same float, with at least one digit after
+// the point, in scientific notation below 10^-3 and from 10^7 on
+pub(super) fn float_string(value: f32) -> String {
+    java_decimal(value as f64, value.to_string(), format!("{:e}", value))
+}
+
+// Double.toString, like float_string
+pub(super) fn double_string(value: f64) -> String {
+    java_decimal(value, value.to_string(), format!("{:e}", value))
+}
+
+fn java_decimal(value: f64, plain: String, scientific: String) -> String {
+    if value.is_nan() {
+        return String::from("NaN");
+    }
+    if value.is_infinite() {
+        return String::from(if value > 0.0 { "Infinity" } else { "-Infinity" });
+    }
+    if value == 0.0 || (1e-3..1e7).contains(&value.abs()) {
+        return match plain.contains('.') {
+            true => plain,
+            false => plain + ".0",
+        };
+    }
+    let (mantissa, exponent) = scientific.split_once('e').expect("scientific notation has an exponent");
+    match mantissa.contains('.') {
+        true => format!("{}E{}", mantissa, exponent),
+        false => format!("{}.0E{}", mantissa, exponent),
+    }
+}
+
+// Integer.parseInt and the like, in base 10 with an optional sign
+fn parse_integer(env: &Env, args: &LocalVars, min: i64, max: i64) -> Result<i64, Flow> {
+    let Some(string) = args.get_ref(0) else {
+        return Err(number_format(String::from("Cannot parse null string: null")));
+    };
+    let string = env.rust_string(string);
+    match string.parse::<i64>() {
+        Ok(value) if (min..=max).contains(&value) => Ok(value),
+        // Short and Byte tell apart a number they have no room for from one that is no int
+        Ok(value) if min > i32::MIN as i64 && i32::try_from(value).is_ok() => {
+            Err(number_format(format!("Value out of range. Value:\"{}\" Radix:10", string)))
+        }
+        _ => Err(number_format(format!("For input string: \"{}\"", string))),
+    }
+}
+
+// Double.parseDouble and Float.parseFloat, which allow white space around the number and a type suffix
+fn parse_decimal(env: &Env, args: &LocalVars) -> Result<f64, Flow> {
+    let Some(string) = args.get_ref(0) else {
+        return Err(Flow::Throw(None));
+    };
+    let string = env.rust_string(string);
+    let trimmed = string.trim_matches(|char: char| char <= ' ');
+    if trimmed.is_empty() {
+        return Err(number_format(String::from("empty String")));
+    }
+    let number = trimmed.strip_suffix(['f', 'F', 'd', 'D']).unwrap_or(trimmed);
+    let (sign, unsigned) = match number.strip_prefix(['+', '-']) {
+        Some(unsigned) => (&number[..1], unsigned),
+        None => ("", number),
+    };
+    // Rust reads inf and nan in any case, Java only these
+    let value = match unsigned {
+        "Infinity" | "NaN" if number.len() == trimmed.len() => format!("{}{}", sign, unsigned).parse().ok(),
+        _ if unsigned.chars().all(|char| char.is_ascii_digit() || matches!(char, '.' | 'e' | 'E' | '+' | '-')) => {
+            number.parse().ok()
+        }
+        _ => None,
+    };
+    value.ok_or_else(|| number_format(format!("For input string: \"{}\"", string)))
+}
+
+fn number_format(message: String) -> Flow {
+    Flow::Raise { class_name: "java/lang/NumberFormatException", message: Some(message) }
+}
+
+fn test_char(args: &LocalVars, test: fn(char) -> bool) -> bool {
+    char::from_u32(args.get_int(0) as u32).is_some_and(test)
+}
+
+// Character.isWhitespace: white space other than the non-breaking spaces, plus the control chars
+// for separators
+fn is_whitespace(char: char) -> bool {
+    matches!(char, '\t'..='\r' | '\u{1c}'..='\u{1f}')
+        || (char.is_whitespace() && !matches!(char, '\u{85}' | '\u{a0}' | '\u{2007}' | '\u{202f}'))
+}
+
+fn return_int(value: i32) -> Flow {
+    Flow::Return(vec![Slot::Int(value)])
+}
+
+fn return_long(value: i64) -> Flow {
+    Flow::Return(Slot::wide(value as u64, false).to_vec())
+}
@@ -0,0 +1,75 @@
+use crate::classfile::class_reader::{
+    ACC_ABSTRACT, ACC_FINAL, ACC_INTERFACE, ACC_NATIVE, ACC_PRIVATE, ACC_PROTECTED, ACC_PUBLIC, ACC_STATIC,
+    ACC_SYNCHRONIZED, ACC_TRANSIENT, ACC_VOLATILE,
+};
+use crate::instructions::Flow;
+use crate::runtime::{LocalVars, Slot};
+
+use super::super::illegal_argument;
+use super::super::reflect::{get_field, invoke, new_instance, set_field};
+use super::{this, Env, NativeMethod, NativeRegistry};
+
+const MODIFIER: &str = "java/lang/reflect/Modifier";
+const FIELD: &str = "java/lang/reflect/Field";
+const METHOD: &str = "java/lang/reflect/Method";
+const CONSTRUCTOR: &str = "java/lang/reflect/Constructor";
+const PROXY: &str = "java/lang/reflect/Proxy";
+
+const MODIFIER_TESTS: [(&str, NativeMethod); 11] = [
+    ("isPublic", has_modifier::<ACC_PUBLIC>),
+    ("isPrivate", has_modifier::<ACC_PRIVATE>),
+    ("isProtected", has_modifier::<ACC_PROTECTED>),
+    ("isStatic", has_modifier::<ACC_STATIC>),
+    ("isFinal", has_modifier::<ACC_FINAL>),
+    ("isSynchronized", has_modifier::<ACC_SYNCHRONIZED>),
+    ("isVolatile", has_modifier::<ACC_VOLATILE>),
+    ("isTransient", has_modifier::<ACC_TRANSIENT>),
+    ("isNative", has_modifier::<ACC_NATIVE>),
+    ("isInterface", has_modifier::<ACC_INTERFACE>),
+    ("isAbstract", has_modifier::<ACC_ABSTRACT>),
+];
+
+pub(super) fn register(registry: &mut NativeRegistry) {
+    for (name, test) in MODIFIER_TESTS {
+        registry.register(MODIFIER, name, "(I)Z", test);
+    }
+    // the JDK has Field.get and Field.set go through Unsafe, and Method.invoke and Constructor.newInstance
+    // through the natives of its accessors; here they are natives themselves
+    registry.register(FIELD, "get", "(Ljava/lang/Object;)Ljava/lang/Object;", get_field);
+    registry.register(FIELD, "set", "(Ljava/lang/Object;Ljava/lang/Object;)V", set_field);
+    registry.register(
+        METHOD,
+        "invoke",
+        "(Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;",
+        |env, args| invoke(env, Some(this(args)), args),
+    );
+    registry.register(
+        CONSTRUCTOR,
+        "newInstance",
+        "([Ljava/lang/Object;)Ljava/lang/Object;",
+        |env, args| new_instance(env, Some(this(args)), args),
+    );
+    registry.register(
+        PROXY,
+        "getInvocationHandler",
+        "(Ljava/lang/Object;)Ljava/lang/reflect/InvocationHandler;",
+        get_invocation_handler,
+    );
+}
+
+fn get_invocation_handler(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(proxy) = args.get_ref(0) else {
+        return Flow::Raise {
+            class_name: "java/lang/NullPointerException",
+            message: None,
+        };
+    };
+    if !env.is_proxy_class(&env.heap[proxy].class) {
+        return illegal_argument("not a proxy instance");
+    }
+    Flow::Return(vec![Slot::Reference(env.heap[proxy].get_ref_field("h", "Ljava/lang/reflect/InvocationHandler;"))])
+}
+
+fn has_modifier<const FLAG: u16>(_: &mut Env, args: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int((args.get_int(0) & FLAG as i32 != 0) as i32)])
+}
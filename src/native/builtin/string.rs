@@ -0,0 +1,532 @@
+use crate::instructions::Flow;
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{LocalVars, Slot};
+
+use super::number::{double_string, float_string};
+use super::{return_bool, return_string, this, Env, NativeRegistry};
+
+const STRING: &str = "java/lang/String";
+const STRING_BUILDER: &str = "java/lang/StringBuilder";
+// the capacity of a new StringBuilder past its initial contents
+const BUILDER_CAPACITY: usize = 16;
+
+// strings keep their chars in a char[] the way JDK 8 does; a StringBuilder keeps them in the first
+// `count` elements of a char[] it replaces with a larger one when they no longer fit
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(STRING, "<init>", "()V", |env, args| init_string(env, this(args), &[]));
+    registry.register(STRING, "<init>", "(Ljava/lang/String;)V", init_string_copy);
+    registry.register(STRING, "<init>", "([C)V", init_string_chars);
+    registry.register(STRING, "<init>", "([CII)V", init_string_range);
+    registry.register(STRING, "length", "()I", |env, args| return_int(env.string_chars(this(args)).len() as i32));
+    registry.register(STRING, "isEmpty", "()Z", |env, args| return_bool(env.string_chars(this(args)).is_empty()));
+    registry.register(STRING, "charAt", "(I)C", |env, args| char_at(&env.string_chars(this(args)), args.get_int(1)));
+    registry.register(STRING, "equals", "(Ljava/lang/Object;)Z", equals);
+    registry.register(STRING, "equalsIgnoreCase", "(Ljava/lang/String;)Z", equals_ignore_case);
+    registry.register(STRING, "hashCode", "()I", hash_code);
+    registry.register(STRING, "compareTo", "(Ljava/lang/String;)I", compare_to);
+    registry.register(STRING, "concat", "(Ljava/lang/String;)Ljava/lang/String;", concat);
+    registry.register(STRING, "substring", "(I)Ljava/lang/String;", substring);
+    registry.register(STRING, "substring", "(II)Ljava/lang/String;", substring_range);
+    registry.register(STRING, "indexOf", "(I)I", index_of_char);
+    registry.register(STRING, "indexOf", "(Ljava/lang/String;)I", index_of);
+    registry.register(STRING, "lastIndexOf", "(I)I", last_index_of_char);
+    registry.register(STRING, "contains", "(Ljava/lang/CharSequence;)Z", contains);
+    registry.register(STRING, "startsWith", "(Ljava/lang/String;)Z", starts_with);
+    registry.register(STRING, "endsWith", "(Ljava/lang/String;)Z", ends_with);
+    registry.register(STRING, "trim", "()Ljava/lang/String;", trim);
+    registry.register(STRING, "toUpperCase", "()Ljava/lang/String;", to_upper_case);
+    registry.register(STRING, "toLowerCase", "()Ljava/lang/String;", to_lower_case);
+    registry.register(STRING, "replace", "(CC)Ljava/lang/String;", replace);
+    registry.register(STRING, "repeat", "(I)Ljava/lang/String;", repeat);
+    registry.register(STRING, "toCharArray", "()[C", to_char_array);
+    registry.register(STRING, "valueOf", "(Z)Ljava/lang/String;", |env, args| {
+        return_string(env.new_string(&(args.get_int(0) != 0).to_string()))
+    });
+    registry.register(STRING, "valueOf", "(C)Ljava/lang/String;", |env, args| {
+        return_string(env.new_string_keeping(&[args.get_int(0) as u16], &[]))
+    });
+    registry.register(STRING, "valueOf", "(I)Ljava/lang/String;", |env, args| {
+        return_string(env.new_string(&args.get_int(0).to_string()))
+    });
+    registry.register(STRING, "valueOf", "(J)Ljava/lang/String;", |env, args| {
+        return_string(env.new_string(&args.get_long(0).to_string()))
+    });
+    registry.register(STRING, "valueOf", "(F)Ljava/lang/String;", |env, args| {
+        return_string(env.new_string(&float_string(args.get_float(0))))
+    });
+    registry.register(STRING, "valueOf", "(D)Ljava/lang/String;", |env, args| {
+        return_string(env.new_string(&double_string(args.get_double(0))))
+    });
+    registry.register(STRING, "valueOf", "([C)Ljava/lang/String;", value_of_chars);
+    registry.register(STRING_BUILDER, "<init>", "()V", |env, args| init_builder(env, this(args), &[], BUILDER_CAPACITY));
+    registry.register(STRING_BUILDER, "<init>", "(I)V", init_builder_capacity);
+    registry.register(STRING_BUILDER, "<init>", "(Ljava/lang/String;)V", init_builder_string);
+    registry.register(STRING_BUILDER, "append", "(Ljava/lang/String;)Ljava/lang/StringBuilder;", append_string);
+    registry.register(STRING_BUILDER, "append", "(Z)Ljava/lang/StringBuilder;", |env, args| {
+        append(env, args, (args.get_int(1) != 0).to_string().encode_utf16().collect())
+    });
+    registry.register(STRING_BUILDER, "append", "(C)Ljava/lang/StringBuilder;", |env, args| {
+        append(env, args, vec![args.get_int(1) as u16])
+    });
+    registry.register(STRING_BUILDER, "append", "(I)Ljava/lang/StringBuilder;", |env, args| {
+        append(env, args, args.get_int(1).to_string().encode_utf16().collect())
+    });
+    registry.register(STRING_BUILDER, "append", "(J)Ljava/lang/StringBuilder;", |env, args| {
+        append(env, args, args.get_long(1).to_string().encode_utf16().collect())
+    });
+    registry.register(STRING_BUILDER, "append", "(F)Ljava/lang/StringBuilder;", |env, args| {
+        append(env, args, float_string(args.get_float(1)).encode_utf16().collect())
+    });
+    registry.register(STRING_BUILDER, "append", "(D)Ljava/lang/StringBuilder;", |env, args| {
+        append(env, args, double_string(args.get_double(1)).encode_utf16().collect())
+    });
+    registry.register(STRING_BUILDER, "append", "([C)Ljava/lang/StringBuilder;", append_chars);
+    registry.register(STRING_BUILDER, "insert", "(ILjava/lang/String;)Ljava/lang/StringBuilder;", insert);
+    registry.register(STRING_BUILDER, "deleteCharAt", "(I)Ljava/lang/StringBuilder;", delete_char_at);
+    registry.register(STRING_BUILDER, "reverse", "()Ljava/lang/StringBuilder;", reverse);
+    registry.register(STRING_BUILDER, "length", "()I", |env, args| return_int(builder_chars(env, this(args)).len() as i32));
+    registry.register(STRING_BUILDER, "charAt", "(I)C", |env, args| {
+        char_at(&builder_chars(env, this(args)), args.get_int(1))
+    });
+    registry.register(STRING_BUILDER, "setCharAt", "(IC)V", set_char_at);
+    registry.register(STRING_BUILDER, "setLength", "(I)V", set_length);
+    registry.register(STRING_BUILDER, "indexOf", "(Ljava/lang/String;)I", builder_index_of);
+    registry.register(STRING_BUILDER, "toString", "()Ljava/lang/String;", |env, args| {
+        let chars = builder_chars(env, this(args));
+        return_string(env.new_string_keeping(&chars, &[]))
+    });
+}
+
+// the elements of a char[]
+pub(super) fn array_chars(env: &Env, array: ObjectRef) -> Vec<u16> {
+    env.heap[array].fields.borrow().iter().map(|char| char.int() as u16).collect()
+}
+
+fn new_char_array(env: &mut Env, chars: &[u16]) -> Result<ObjectRef, Flow> {
+    let array = env.new_array("[C", &[chars.len()])?;
+    *env.heap[array].fields.borrow_mut() = chars.iter().map(|char| Slot::Int(*char as i32)).collect();
+    Ok(array)
+}
+
+fn init_string(env: &mut Env, this: ObjectRef, chars: &[u16]) -> Flow {
+    match new_char_array(env, chars) {
+        Ok(array) => set_value(env, this, array),
+        Err(flow) => flow,
+    }
+}
+
+// strings never change, so they can share the array
+fn init_string_copy(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(original) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let array = env.heap[original].get_ref_field("value", "[C").expect("a String without a value");
+    set_value(env, this(args), array)
+}
+
+fn init_string_chars(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(array) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let chars = array_chars(env, array);
+    init_string(env, this(args), &chars)
+}
+
+fn init_string_range(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(array) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let chars = array_chars(env, array);
+    let (offset, count) = (args.get_int(2), args.get_int(3));
+    if offset < 0 || count < 0 || offset as usize + count as usize > chars.len() {
+        return out_of_bounds(format!("offset {}, count {}, length {}", offset, count, chars.len()));
+    }
+    init_string(env, this(args), &chars[offset as usize..offset as usize + count as usize])
+}
+
+fn set_value(env: &mut Env, this: ObjectRef, array: ObjectRef) -> Flow {
+    env.heap[this].set_ref_field("value", "[C", Some(array));
+    env.heap.write_barrier(this);
+    Flow::Return(Vec::new())
+}
+
+fn char_at(chars: &[u16], index: i32) -> Flow {
+    match usize::try_from(index).ok().and_then(|index| chars.get(index)) {
+        Some(char) => return_int(*char as i32),
+        None => out_of_bounds(format!("index {}, length {}", index, chars.len())),
+    }
+}
+
+fn equals(env: &mut Env, args: &LocalVars) -> Flow {
+    let other = args.get_ref(1).filter(|other| env.heap[*other].class.name == STRING);
+    return_bool(other.is_some_and(|other| env.string_chars(this(args)) == env.string_chars(other)))
+}
+
+fn equals_ignore_case(env: &mut Env, args: &LocalVars) -> Flow {
+    let equal = args.get_ref(1).is_some_and(|other| {
+        let (chars, other) = (env.string_chars(this(args)), env.string_chars(other));
+        chars.len() == other.len() && chars.iter().zip(&other).all(|(a, b)| fold_case(*a) == fold_case(*b))
+    });
+    return_bool(equal)
+}
+
+// how equalsIgnoreCase compares chars: upper case first, then lower case
+fn fold_case(char: u16) -> u16 {
+    map_char(map_char(char, char::to_uppercase), char::to_lowercase)
+}
+
+// the char with `mapping` applied, or itself when the mapping takes more than one char
+pub(super) fn map_char<I: Iterator<Item = char>>(char: u16, mapping: fn(char) -> I) -> u16 {
+    let Some(value) = char::from_u32(char as u32) else {
+        return char;
+    };
+    let mut mapped = mapping(value);
+    match (mapped.next(), mapped.next()) {
+        (Some(mapped), None) if (mapped as u32) <= u16::MAX as u32 => mapped as u16,
+        _ => char,
+    }
+}
+
+// s[0]*31^(n-1) + s[1]*31^(n-2) + ... + s[n-1], in int arithmetic
+fn hash_code(env: &mut Env, args: &LocalVars) -> Flow {
+    let hash = env.string_chars(this(args)).iter().fold(0i32, |hash, char| hash.wrapping_mul(31).wrapping_add(*char as i32));
+    return_int(hash)
+}
+
+// the difference of the first chars that differ, else of the lengths
+fn compare_to(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(other) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let (chars, other) = (env.string_chars(this(args)), env.string_chars(other));
+    let difference = chars.iter().zip(&other).find(|(a, b)| a != b);
+    match difference {
+        Some((a, b)) => return_int(*a as i32 - *b as i32),
+        None => return_int(chars.len() as i32 - other.len() as i32),
+    }
+}
+
+fn concat(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(other) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let other = env.string_chars(other);
+    if other.is_empty() {
+        return return_string(Ok(this(args)));
+    }
+    let chars = [env.string_chars(this(args)), other].concat();
+    return_string(env.new_string_keeping(&chars, &[]))
+}
+
+fn substring(env: &mut Env, args: &LocalVars) -> Flow {
+    let chars = env.string_chars(this(args));
+    sub_string(env, &chars, args.get_int(1), chars.len() as i32)
+}
+
+fn substring_range(env: &mut Env, args: &LocalVars) -> Flow {
+    let chars = env.string_chars(this(args));
+    sub_string(env, &chars, args.get_int(1), args.get_int(2))
+}
+
+fn sub_string(env: &mut Env, chars: &[u16], begin: i32, end: i32) -> Flow {
+    if begin < 0 || begin > end || end as usize > chars.len() {
+        return out_of_bounds(format!("begin {}, end {}, length {}", begin, end, chars.len()));
+    }
+    return_string(env.new_string_keeping(&chars[begin as usize..end as usize], &[]))
+}
+
+// the char at `index` for a char, and the surrogate pair at `index` for a supplementary code point
+fn index_of_char(env: &mut Env, args: &LocalVars) -> Flow {
+    let needle = code_point_chars(args.get_int(1));
+    return_int(find(&env.string_chars(this(args)), &needle))
+}
+
+fn last_index_of_char(env: &mut Env, args: &LocalVars) -> Flow {
+    let (chars, needle) = (env.string_chars(this(args)), code_point_chars(args.get_int(1)));
+    let index = match needle.is_empty() {
+        true => None,
+        false => chars.windows(needle.len()).rposition(|window| window == needle),
+    };
+    return_int(index.map_or(-1, |index| index as i32))
+}
+
+fn code_point_chars(code_point: i32) -> Vec<u16> {
+    match char::from_u32(code_point as u32) {
+        Some(char) => char.encode_utf16(&mut [0; 2]).to_vec(),
+        None if (0..=u16::MAX as i32).contains(&code_point) => vec![code_point as u16],
+        None => Vec::new(),
+    }
+}
+
+fn index_of(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(other) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    return_int(find(&env.string_chars(this(args)), &env.string_chars(other)))
+}
+
+// the index of the first occurrence of `needle`, -1 without one; the empty string is found at 0
+fn find(chars: &[u16], needle: &[u16]) -> i32 {
+    if needle.is_empty() {
+        return 0;
+    }
+    chars.windows(needle.len()).position(|window| window == needle).map_or(-1, |index| index as i32)
+}
+
+fn contains(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(sequence) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let sequence = sequence_chars(env, sequence);
+    return_bool(find(&env.string_chars(this(args)), &sequence) >= 0)
+}
+
+// the chars of a String or StringBuilder, the CharSequences of the library
+fn sequence_chars(env: &Env, sequence: ObjectRef) -> Vec<u16> {
+    match env.heap[sequence].class.name.as_str() {
+        STRING_BUILDER => builder_chars(env, sequence),
+        _ => env.string_chars(sequence),
+    }
+}
+
+fn starts_with(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(prefix) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    return_bool(env.string_chars(this(args)).starts_with(&env.string_chars(prefix)))
+}
+
+fn ends_with(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(suffix) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    return_bool(env.string_chars(this(args)).ends_with(&env.string_chars(suffix)))
+}
+
+// without the chars up to and including the space at either end
+fn trim(env: &mut Env, args: &LocalVars) -> Flow {
+    let chars = env.string_chars(this(args));
+    let start = chars.iter().position(|char| *char > b' ' as u16).unwrap_or(chars.len());
+    let end = chars.iter().rposition(|char| *char > b' ' as u16).map_or(start, |end| end + 1);
+    if start == 0 && end == chars.len() {
+        return return_string(Ok(this(args)));
+    }
+    return_string(env.new_string_keeping(&chars[start..end], &[]))
+}
+
+fn to_upper_case(env: &mut Env, args: &LocalVars) -> Flow {
+    let value = env.rust_string(this(args)).to_uppercase();
+    return_string(env.new_string(&value))
+}
+
+fn to_lower_case(env: &mut Env, args: &LocalVars) -> Flow {
+    let value = env.rust_string(this(args)).to_lowercase();
+    return_string(env.new_string(&value))
+}
+
+fn replace(env: &mut Env, args: &LocalVars) -> Flow {
+    let (old, new) = (args.get_int(1) as u16, args.get_int(2) as u16);
+    let chars: Vec<u16> =
+        env.string_chars(this(args)).into_iter().map(|char| if char == old { new } else { char }).collect();
+    return_string(env.new_string_keeping(&chars, &[]))
+}
+
+fn repeat(env: &mut Env, args: &LocalVars) -> Flow {
+    let count = args.get_int(1);
+    if count < 0 {
+        return Flow::Raise {
+            class_name: "java/lang/IllegalArgumentException",
+            message: Some(format!("count is negative: {}", count)),
+        };
+    }
+    let chars = env.string_chars(this(args));
+    if chars.len().saturating_mul(count as usize) > i32::MAX as usize {
+        return Flow::Raise { class_name: "java/lang/OutOfMemoryError", message: Some(String::from("Repeating too long")) };
+    }
+    return_string(env.new_string_keeping(&chars.repeat(count as usize), &[]))
+}
+
+fn to_char_array(env: &mut Env, args: &LocalVars) -> Flow {
+    let chars = env.string_chars(this(args));
+    match new_char_array(env, &chars) {
+        Ok(array) => Flow::Return(vec![Slot::Reference(Some(array))]),
+        Err(flow) => flow,
+    }
+}
+
+fn value_of_chars(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(array) = args.get_ref(0) else {
+        return Flow::Throw(None);
+    };
+    let chars = array_chars(env, array);
+    return_string(env.new_string_keeping(&chars, &[]))
+}
+
+fn builder_chars(env: &Env, builder: ObjectRef) -> Vec<u16> {
+    let object = &env.heap[builder];
+    let array = object.get_ref_field("value", "[C").expect("a StringBuilder without a value");
+    let count = object.get_int_field("count", "I") as usize;
+    env.heap[array].fields.borrow()[..count].iter().map(|char| char.int() as u16).collect()
+}
+
+// makes `chars` the contents of the builder, in a larger array when they do not fit
+fn set_builder_chars(env: &mut Env, builder: ObjectRef, chars: &[u16]) -> Result<(), Flow> {
+    let array = env.heap[builder].get_ref_field("value", "[C").expect("a StringBuilder without a value");
+    let capacity = env.heap[array].array_length();
+    let array = match chars.len() > capacity {
+        true => {
+            let array = env.new_array("[C", &[chars.len().max(capacity * 2 + 2)])?;
+            env.heap[builder].set_ref_field("value", "[C", Some(array));
+            env.heap.write_barrier(builder);
+            array
+        }
+        false => array,
+    };
+    let mut elements = env.heap[array].fields.borrow_mut();
+    for (index, char) in chars.iter().enumerate() {
+        elements[index] = Slot::Int(*char as i32);
+    }
+    drop(elements);
+    env.heap[builder].set_int_field("count", "I", chars.len() as i32);
+    Ok(())
+}
+
+fn init_builder(env: &mut Env, this: ObjectRef, chars: &[u16], capacity: usize) -> Flow {
+    let array = match env.new_array("[C", &[capacity]) {
+        Ok(array) => array,
+        Err(flow) => return flow,
+    };
+    env.heap[this].set_ref_field("value", "[C", Some(array));
+    env.heap.write_barrier(this);
+    match set_builder_chars(env, this, chars) {
+        Ok(()) => Flow::Return(Vec::new()),
+        Err(flow) => flow,
+    }
+}
+
+fn init_builder_capacity(env: &mut Env, args: &LocalVars) -> Flow {
+    match usize::try_from(args.get_int(1)) {
+        Ok(capacity) => init_builder(env, this(args), &[], capacity),
+        Err(_) => Flow::Raise { class_name: "java/lang/NegativeArraySizeException", message: Some(args.get_int(1).to_string()) },
+    }
+}
+
+fn init_builder_string(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(string) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let chars = env.string_chars(string);
+    init_builder(env, this(args), &chars, chars.len() + BUILDER_CAPACITY)
+}
+
+fn append(env: &mut Env, args: &LocalVars, chars: Vec<u16>) -> Flow {
+    let this = this(args);
+    let chars = [builder_chars(env, this), chars].concat();
+    return_builder(set_builder_chars(env, this, &chars), this)
+}
+
+fn append_string(env: &mut Env, args: &LocalVars) -> Flow {
+    let chars = match args.get_ref(1) {
+        Some(string) => env.string_chars(string),
+        None => "null".encode_utf16().collect(),
+    };
+    append(env, args, chars)
+}
+
+fn append_chars(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(array) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let chars = array_chars(env, array);
+    append(env, args, chars)
+}
+
+fn insert(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    let mut chars = builder_chars(env, this);
+    let offset = args.get_int(1);
+    if offset < 0 || offset as usize > chars.len() {
+        return out_of_bounds(format!("offset {}, length {}", offset, chars.len()));
+    }
+    let inserted = match args.get_ref(2) {
+        Some(string) => env.string_chars(string),
+        None => "null".encode_utf16().collect(),
+    };
+    chars.splice(offset as usize..offset as usize, inserted);
+    return_builder(set_builder_chars(env, this, &chars), this)
+}
+
+fn delete_char_at(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    let mut chars = builder_chars(env, this);
+    let index = args.get_int(1);
+    if index < 0 || index as usize >= chars.len() {
+        return out_of_bounds(format!("index {},length {}", index, chars.len()));
+    }
+    chars.remove(index as usize);
+    return_builder(set_builder_chars(env, this, &chars), this)
+}
+
+// surrogate pairs stay in order, as the code points they stand for
+fn reverse(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    let mut chars = builder_chars(env, this);
+    chars.reverse();
+    for index in 1..chars.len() {
+        let (low, high) = (chars[index - 1], chars[index]);
+        if (0xDC00..0xE000).contains(&low) && (0xD800..0xDC00).contains(&high) {
+            chars.swap(index - 1, index);
+        }
+    }
+    return_builder(set_builder_chars(env, this, &chars), this)
+}
+
+fn set_char_at(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    let mut chars = builder_chars(env, this);
+    let index = args.get_int(1);
+    match usize::try_from(index).ok().and_then(|index| chars.get_mut(index)) {
+        Some(char) => *char = args.get_int(2) as u16,
+        None => return out_of_bounds(format!("index {},length {}", index, chars.len())),
+    }
+    match set_builder_chars(env, this, &chars) {
+        Ok(()) => Flow::Return(Vec::new()),
+        Err(flow) => flow,
+    }
+}
+
+// the chars past the new length are dropped, and the ones up to it that are new are '\0'
+fn set_length(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    let mut chars = builder_chars(env, this);
+    let Ok(length) = usize::try_from(args.get_int(1)) else {
+        return out_of_bounds(format!("String index out of range: {}", args.get_int(1)));
+    };
+    chars.resize(length, 0);
+    match set_builder_chars(env, this, &chars) {
+        Ok(()) => Flow::Return(Vec::new()),
+        Err(flow) => flow,
+    }
+}
+
+fn builder_index_of(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(string) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    return_int(find(&builder_chars(env, this(args)), &env.string_chars(string)))
+}
+
+fn return_builder(result: Result<(), Flow>, builder: ObjectRef) -> Flow {
+    match result {
+        Ok(()) => Flow::Return(vec![Slot::Reference(Some(builder))]),
+        Err(flow) => flow,
+    }
+}
+
+fn return_int(value: i32) -> Flow {
+    Flow::Return(vec![Slot::Int(value)])
+}
+
+fn out_of_bounds(message: String) -> Flow {
+    Flow::Raise { class_name: "java/lang/StringIndexOutOfBoundsException", message: Some(message) }
+}
@@ -0,0 +1,281 @@
+use crate::classfile::class_reader::{ACC_ABSTRACT, ACC_FINAL, ACC_PUBLIC, ACC_SUPER};
+use std::rc::Rc;
+
+use crate::instructions::Flow;
+use crate::runtime::class::{primitive_name, raw_annotations, signature, Class};
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{LocalVars, Object, Slot};
+
+use super::{null, return_object, this, Env, NativeRegistry};
+
+const CLASS: &str = "java/lang/Class";
+
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(CLASS, "desiredAssertionStatus0", "(Ljava/lang/Class;)Z", desired_assertion_status);
+    registry.register(CLASS, "getPrimitiveClass", "(Ljava/lang/String;)Ljava/lang/Class;", get_primitive_class);
+    registry.register(
+        CLASS,
+        "forName0",
+        "(Ljava/lang/String;ZLjava/lang/ClassLoader;Ljava/lang/Class;)Ljava/lang/Class;",
+        for_name,
+    );
+    registry.register(CLASS, "initClassName", "()Ljava/lang/String;", init_class_name);
+    registry.register(CLASS, "isArray", "()Z", is_array);
+    registry.register(CLASS, "isPrimitive", "()Z", is_primitive);
+    registry.register(CLASS, "isInterface", "()Z", is_interface);
+    registry.register(CLASS, "isHidden", "()Z", is_hidden);
+    registry.register(CLASS, "isInstance", "(Ljava/lang/Object;)Z", is_instance);
+    registry.register(CLASS, "isAssignableFrom", "(Ljava/lang/Class;)Z", is_assignable_from);
+    registry.register(CLASS, "getSuperclass", "()Ljava/lang/Class;", get_superclass);
+    registry.register(CLASS, "getInterfaces0", "()[Ljava/lang/Class;", get_interfaces);
+    registry.register(CLASS, "getComponentType", "()Ljava/lang/Class;", get_component_type);
+    registry.register(CLASS, "getModifiers", "()I", get_modifiers);
+    registry.register(CLASS, "getNestHost0", "()Ljava/lang/Class;", get_nest_host);
+    registry.register(CLASS, "getGenericSignature0", "()Ljava/lang/String;", get_generic_signature);
+    registry.register(CLASS, "getDeclaredFields0", "(Z)[Ljava/lang/reflect/Field;", get_declared_fields);
+    registry.register(
+        CLASS,
+        "getDeclaredMethods0",
+        "(Z)[Ljava/lang/reflect/Method;",
+        |env, args| get_declared_methods(env, args, false),
+    );
+    registry.register(
+        CLASS,
+        "getDeclaredConstructors0",
+        "(Z)[Ljava/lang/reflect/Constructor;",
+        |env, args| get_declared_methods(env, args, true),
+    );
+    registry.register(CLASS, "getRawAnnotations", "()[B", get_raw_annotations);
+    registry.register(CLASS, "getConstantPool", "()Ljdk/internal/reflect/ConstantPool;", get_constant_pool);
+    // what the VM has no class file attributes for yet
+    registry.register(CLASS, "getDeclaringClass0", "()Ljava/lang/Class;", null);
+    registry.register(CLASS, "getEnclosingMethod0", "()[Ljava/lang/Object;", null);
+    registry.register(CLASS, "getSimpleBinaryName0", "()Ljava/lang/String;", null);
+    registry.register(CLASS, "getProtectionDomain0", "()Ljava/security/ProtectionDomain;", null);
+}
+
+// assertions stay disabled, like without -ea
+fn desired_assertion_status(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int(0)])
+}
+
+fn get_primitive_class(env: &mut Env, args: &LocalVars) -> Flow {
+    let name = env.rust_string(args.get_ref(0).expect("getPrimitiveClass(null)"));
+    let mirror = env.load(&name).and_then(|class| env.mirror(&class));
+    return_object(mirror)
+}
+
+// every class comes from the one class loader, so the loader argument makes no difference
+fn for_name(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(name) = args.get_ref(0) else {
+        return Flow::Throw(None);
+    };
+    let name = env.rust_string(name);
+    let class = match env.loader.load_class(&name.replace('.', "/")) {
+        Ok(class) if !name.contains('/') => class,
+        _ => return Flow::Raise { class_name: "java/lang/ClassNotFoundException", message: Some(name) },
+    };
+    if args.get_int(1) != 0 {
+        if let Some(flow) = env.initialize(&class) {
+            return flow;
+        }
+    }
+    return_object(env.mirror(&class))
+}
+
+fn init_class_name(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    let name = env.intern(&env.class_of_mirror(this).java_name());
+    if let Ok(name) = name {
+        env.heap[this].set_ref_field("name", "Ljava/lang/String;", Some(name));
+        env.heap.write_barrier(this);
+    }
+    return_object(name)
+}
+
+fn is_array(env: &mut Env, args: &LocalVars) -> Flow {
+    return_bool(env.class_of_mirror(this(args)).is_array())
+}
+
+fn is_primitive(env: &mut Env, args: &LocalVars) -> Flow {
+    return_bool(env.class_of_mirror(this(args)).is_primitive())
+}
+
+fn is_interface(env: &mut Env, args: &LocalVars) -> Flow {
+    return_bool(env.class_of_mirror(this(args)).is_interface())
+}
+
+fn is_hidden(_: &mut Env, _: &LocalVars) -> Flow {
+    return_bool(false)
+}
+
+fn is_instance(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(this(args));
+    return_bool(args.get_ref(1).is_some_and(|object| env.heap[object].class.is_assignable_to(&class)))
+}
+
+fn is_assignable_from(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(other) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let (class, other) = (env.class_of_mirror(this(args)), env.class_of_mirror(other));
+    // primitive types are only assignable to themselves
+    let assignable = if class.is_primitive() || other.is_primitive() {
+        class.name == other.name
+    } else {
+        other.is_assignable_to(&class)
+    };
+    return_bool(assignable)
+}
+
+// interfaces and primitive types have none, even though their class files name Object
+fn get_superclass(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(this(args));
+    match &class.super_class {
+        Some(super_class) if !class.is_interface() => return_object(env.mirror(&super_class.clone())),
+        _ => Flow::Return(vec![Slot::Reference(None)]),
+    }
+}
+
+fn get_interfaces(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(this(args));
+    let array = match env.new_array("[Ljava/lang/Class;", &[class.interfaces.len()]) {
+        Ok(array) => array,
+        Err(flow) => return flow,
+    };
+    for (index, interface) in class.interfaces.iter().enumerate() {
+        let mirror = match env.mirror_keeping(interface, &[array]) {
+            Ok(mirror) => mirror,
+            Err(flow) => return flow,
+        };
+        env.heap[array].fields.borrow_mut()[index] = Slot::Reference(Some(mirror));
+        env.heap.write_barrier(array);
+    }
+    Flow::Return(vec![Slot::Reference(Some(array))])
+}
+
+// JDK 8 asks the VM, later JDKs read the componentType field
+fn get_component_type(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    if !env.class_of_mirror(this).is_array() {
+        return Flow::Return(vec![Slot::Reference(None)]);
+    }
+    let class = env.class_of_mirror(this);
+    let component = match &class.component {
+        Some(component) => component.clone(),
+        None => match env.load(primitive_name(&class.name[1..])) {
+            Ok(component) => component,
+            Err(flow) => return flow,
+        },
+    };
+    return_object(env.mirror(&component))
+}
+
+fn get_modifiers(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(this(args));
+    let modifiers = if class.is_primitive() || class.is_array() {
+        ACC_PUBLIC | ACC_FINAL | ACC_ABSTRACT
+    } else {
+        class.classfile.access_flags & !ACC_SUPER
+    };
+    Flow::Return(vec![Slot::Int(modifiers as i32)])
+}
+
+// without NestHost attributes every class is its own nest
+fn get_nest_host(_: &mut Env, args: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Reference(Some(this(args)))])
+}
+
+fn get_generic_signature(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(this(args));
+    match signature(&class.classfile.constant_pool, &class.classfile.attributes_info) {
+        Some(signature) => return_object(env.intern(&signature)),
+        None => Flow::Return(vec![Slot::Reference(None)]),
+    }
+}
+
+fn get_raw_annotations(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(this(args));
+    match raw_annotations(&class.classfile.attributes_info) {
+        [Some(annotations), _, _] => return_object(env.byte_array_keeping(annotations, &[])),
+        _ => Flow::Return(vec![Slot::Reference(None)]),
+    }
+}
+
+// the ConstantPool the JDK parses annotations with, which like HotSpot's refers to the class by its mirror
+fn get_constant_pool(env: &mut Env, args: &LocalVars) -> Flow {
+    let mirror = this(args);
+    let class = match env.load("jdk/internal/reflect/ConstantPool") {
+        Ok(class) => class,
+        Err(flow) => return flow,
+    };
+    let pool = match env.allocate(Object::new(class)) {
+        Ok(pool) => pool,
+        Err(flow) => return flow,
+    };
+    env.heap[pool].set_ref_field("constantPoolOop", "Ljava/lang/Object;", Some(mirror));
+    Flow::Return(vec![Slot::Reference(Some(pool))])
+}
+
+// the fields the class declares, only the public ones when the argument says so
+fn get_declared_fields(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(this(args));
+    let public_only = args.get_int(1) != 0;
+    let slots: Vec<usize> = (0..class.fields.len())
+        .filter(|slot| !public_only || class.fields[*slot].access_flags & ACC_PUBLIC != 0)
+        .collect();
+    let reflect = |env: &mut Env, class: &Rc<Class>, slot, live: &[ObjectRef]| env.reflect_field(class, slot, live);
+    members(env, &class, "[Ljava/lang/reflect/Field;", &slots, reflect)
+}
+
+// the methods the class declares, or its constructors; neither includes the class initializer
+fn get_declared_methods(env: &mut Env, args: &LocalVars, constructors: bool) -> Flow {
+    let class = env.class_of_mirror(this(args));
+    let public_only = args.get_int(1) != 0;
+    let slots: Vec<usize> = (0..class.methods.len())
+        .filter(|slot| {
+            let method = &class.methods[*slot];
+            let wanted = match constructors {
+                true => method.name == "<init>",
+                false => !method.name.starts_with('<'),
+            };
+            wanted && (!public_only || method.access_flags & ACC_PUBLIC != 0)
+        })
+        .collect();
+    let array_class = match constructors {
+        true => "[Ljava/lang/reflect/Constructor;",
+        false => "[Ljava/lang/reflect/Method;",
+    };
+    let reflect = |env: &mut Env, class: &Rc<Class>, slot, live: &[ObjectRef]| env.reflect_method(class, slot, live);
+    members(env, &class, array_class, &slots, reflect)
+}
+
+// makes the reflective object for the member of a class at a slot, keeping the objects given alive
+type Reflect = fn(&mut Env, &Rc<Class>, usize, &[ObjectRef]) -> Result<ObjectRef, Flow>;
+
+// an array of class `array_class` with the reflective objects `reflect` makes for the members at `slots`
+fn members(
+    env: &mut Env,
+    class: &Rc<Class>,
+    array_class: &str,
+    slots: &[usize],
+    reflect: Reflect,
+) -> Flow {
+    let array = match env.new_array(array_class, &[slots.len()]) {
+        Ok(array) => array,
+        Err(flow) => return flow,
+    };
+    for (index, slot) in slots.iter().enumerate() {
+        let member = match reflect(env, class, *slot, &[array]) {
+            Ok(member) => member,
+            Err(flow) => return flow,
+        };
+        env.heap[array].fields.borrow_mut()[index] = Slot::Reference(Some(member));
+        env.heap.write_barrier(array);
+    }
+    Flow::Return(vec![Slot::Reference(Some(array))])
+}
+
+fn return_bool(value: bool) -> Flow {
+    Flow::Return(vec![Slot::Int(value as i32)])
+}
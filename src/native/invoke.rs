@@ -0,0 +1,363 @@
+use std::rc::Rc;
+
+use crate::classfile::class_reader::ACC_VARARGS;
+use crate::classfile::descriptor::{parse_method_descriptor, FieldType};
+use crate::instructions::Flow;
+use crate::runtime::call_site::{
+    Bootstrap, MethodHandleRef, StaticArgument, VarHandleRef, REF_GET_FIELD, REF_GET_STATIC, REF_INVOKE_INTERFACE,
+    REF_INVOKE_SPECIAL, REF_INVOKE_STATIC, REF_INVOKE_VIRTUAL, REF_NEW_INVOKE_SPECIAL, REF_PUT_FIELD, REF_PUT_STATIC,
+};
+use crate::runtime::class::{primitive_name, Class, Method};
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{LocalVars, Object, Slot};
+
+use super::{Env, NativeMethod, NativeRegistry};
+
+const LOOKUP: &str = "java/lang/invoke/MethodHandles$Lookup";
+const METHOD_HANDLES: &str = "java/lang/invoke/MethodHandles";
+const METHOD_TYPE: &str = "java/lang/invoke/MethodType";
+const METHOD_HANDLE: &str = "java/lang/invoke/MethodHandle";
+const VAR_HANDLE: &str = "java/lang/invoke/VarHandle";
+// MethodHandles.Lookup.FULL_POWER_MODES: public, private, protected, package, module and original access
+const FULL_POWER_MODES: i32 = 0x5f;
+
+// the Lookup methods making handles, which the VM makes itself so that it knows what they refer to
+const FINDERS: [(&str, &str, NativeMethod); 10] = [
+    (
+        "findStatic",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/MethodHandle;",
+        |env, args| find_method(env, args, REF_INVOKE_STATIC),
+    ),
+    (
+        "findVirtual",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/MethodHandle;",
+        |env, args| find_method(env, args, REF_INVOKE_VIRTUAL),
+    ),
+    (
+        "findSpecial",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/Class;)\
+         Ljava/lang/invoke/MethodHandle;",
+        |env, args| find_method(env, args, REF_INVOKE_SPECIAL),
+    ),
+    (
+        "findConstructor",
+        "(Ljava/lang/Class;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/MethodHandle;",
+        find_constructor,
+    ),
+    (
+        "findGetter",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, args| find_field(env, args, REF_GET_FIELD),
+    ),
+    (
+        "findSetter",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, args| find_field(env, args, REF_PUT_FIELD),
+    ),
+    (
+        "findStaticGetter",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, args| find_field(env, args, REF_GET_STATIC),
+    ),
+    (
+        "findStaticSetter",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, args| find_field(env, args, REF_PUT_STATIC),
+    ),
+    (
+        "findVarHandle",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;",
+        |env, args| find_var_handle(env, args, false),
+    ),
+    (
+        "findStaticVarHandle",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;",
+        |env, args| find_var_handle(env, args, true),
+    ),
+];
+
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(METHOD_HANDLES, "lookup", "()Ljava/lang/invoke/MethodHandles$Lookup;", lookup);
+    registry.register(
+        METHOD_HANDLES,
+        "arrayElementVarHandle",
+        "(Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;",
+        array_element_var_handle,
+    );
+    for (name, descriptor, find) in FINDERS {
+        registry.register(LOOKUP, name, descriptor, find);
+    }
+}
+
+impl Env<'_> {
+    // the lookup object of `class` with full access to it, like MethodHandles.lookup() called from it
+    pub fn lookup(&mut self, class: &Rc<Class>) -> Result<ObjectRef, Flow> {
+        let mirror = self.mirror(class)?;
+        let lookup = self.allocate(Object::new(self.load(LOOKUP)?))?;
+        let object = &self.heap[lookup];
+        object.set_ref_field("lookupClass", "Ljava/lang/Class;", Some(mirror));
+        object.set_int_field("allowedModes", "I", FULL_POWER_MODES);
+        self.heap.write_barrier(lookup);
+        Ok(lookup)
+    }
+
+    // the MethodType of method descriptor `descriptor`, with the classes it names loaded
+    pub fn method_type(&mut self, descriptor: &str) -> Result<ObjectRef, Flow> {
+        let Some(parsed) = parse_method_descriptor(descriptor) else {
+            return Err(illegal_argument(format!("bad method descriptor {}", descriptor)));
+        };
+        let ptypes = self.new_array("[Ljava/lang/Class;", &[parsed.parameters.len()])?;
+        for (index, parameter) in parsed.parameters.iter().enumerate() {
+            let class = self.load(&type_class_name(Some(parameter)))?;
+            let mirror = self.mirror_keeping(&class, &[ptypes])?;
+            self.heap[ptypes].fields.borrow_mut()[index] = Slot::Reference(Some(mirror));
+            self.heap.write_barrier(ptypes);
+        }
+        let rtype = self.load(&type_class_name(parsed.return_type.as_ref()))?;
+        let rtype = self.mirror_keeping(&rtype, &[ptypes])?;
+        let method_type = self.allocate_keeping(Object::new(self.load(METHOD_TYPE)?), &[ptypes])?;
+        let object = &self.heap[method_type];
+        object.set_ref_field("rtype", "Ljava/lang/Class;", Some(rtype));
+        object.set_ref_field("ptypes", "[Ljava/lang/Class;", Some(ptypes));
+        self.heap.write_barrier(method_type);
+        Ok(method_type)
+    }
+
+    // a method handle for the field or method `reference` refers to; what it refers to is resolved
+    // when it is invoked
+    pub fn method_handle(&mut self, reference: &MethodHandleRef) -> Result<ObjectRef, Flow> {
+        let method_type = self.method_type(&reference.type_descriptor())?;
+        let mut handle = Object::new(self.load(METHOD_HANDLE)?);
+        handle.handle_of = Some(Rc::new(reference.clone()));
+        let handle = self.allocate_keeping(handle, &[method_type])?;
+        self.heap[handle].set_ref_field("type", "Ljava/lang/invoke/MethodType;", Some(method_type));
+        self.heap.write_barrier(handle);
+        Ok(handle)
+    }
+
+    // a var handle for the field or the array elements `reference` refers to
+    pub fn var_handle(&mut self, reference: VarHandleRef) -> Result<ObjectRef, Flow> {
+        let mut handle = Object::new(self.load(VAR_HANDLE)?);
+        handle.var_handle_of = Some(Rc::new(reference));
+        self.allocate(handle)
+    }
+
+    // the method descriptor of MethodType `method_type`
+    pub fn method_type_descriptor(&self, method_type: ObjectRef) -> String {
+        let object = &self.heap[method_type];
+        let descriptor = |mirror: Option<ObjectRef>| {
+            self.class_of_mirror(mirror.expect("a MethodType with a null type")).descriptor()
+        };
+        let parameters: String = match object.get_ref_field("ptypes", "[Ljava/lang/Class;") {
+            Some(ptypes) => self.heap[ptypes].fields.borrow().iter().map(|slot| descriptor(slot.reference())).collect(),
+            None => String::new(),
+        };
+        format!("({}){}", parameters, descriptor(object.get_ref_field("rtype", "Ljava/lang/Class;")))
+    }
+
+    // fills in the arguments of bootstrap method `method`, whose frame is the current one: the lookup of
+    // `caller`, the name and type of the call site and the static arguments, converted to the parameter
+    // types; a variable arity bootstrap method gets the static arguments past its fixed ones in an array.
+    // Every object goes into the frame as soon as it is made, which keeps it through a collection
+    pub fn bootstrap_arguments(
+        &mut self,
+        caller: &Rc<Class>,
+        bootstrap: &Bootstrap,
+        method: &Method,
+    ) -> Result<(), Flow> {
+        let parameters = method.parsed_descriptor.as_ref().map_or(&[][..], |descriptor| &descriptor.parameters);
+        let varargs = method.access_flags & ACC_VARARGS != 0
+            && matches!(parameters.last(), Some(FieldType::Array(_)))
+            && parameters.len() > 3;
+        let fixed = if varargs { parameters.len() - 1 } else { parameters.len() };
+        let arguments = &bootstrap.arguments;
+        if fixed < 3 || arguments.len() < fixed - 3 || (!varargs && arguments.len() != fixed - 3) {
+            let message = format!("{} cannot take the static arguments of {}", method.name, bootstrap.name);
+            return Err(bootstrap_method_error(message));
+        }
+        let lookup = self.lookup(caller)?;
+        self.set_local(0, vec![Slot::Reference(Some(lookup))]);
+        let name = self.intern(&bootstrap.name)?;
+        self.set_local(1, vec![Slot::Reference(Some(name))]);
+        let method_type = self.method_type(&bootstrap.descriptor)?;
+        self.set_local(2, vec![Slot::Reference(Some(method_type))]);
+        let mut slot = 3;
+        for (argument, parameter) in arguments.iter().zip(&parameters[3..fixed]) {
+            let value = self.static_argument(argument, parameter)?;
+            let width = value.len();
+            self.set_local(slot, value);
+            slot += width;
+        }
+        if let Some(FieldType::Array(component)) = parameters.last().filter(|_| varargs) {
+            let rest = &arguments[fixed - 3..];
+            let array = self.new_array(&parameters[fixed].descriptor(), &[rest.len()])?;
+            self.set_local(slot, vec![Slot::Reference(Some(array))]);
+            let width = component.slot_size();
+            for (index, argument) in rest.iter().enumerate() {
+                let value = self.static_argument(argument, component)?;
+                self.heap[array].fields.borrow_mut()[index * width..(index + 1) * width].clone_from_slice(&value);
+                self.heap.write_barrier(array);
+            }
+        }
+        Ok(())
+    }
+
+    // a static argument as a value of type `parameter`: numbers are boxed for reference types,
+    // and the others become the String, Class, MethodType or MethodHandle objects they stand for
+    fn static_argument(&mut self, argument: &StaticArgument, parameter: &FieldType) -> Result<Vec<Slot>, Flow> {
+        let wide = |bits: u64, double| Vec::from(Slot::wide(bits, double));
+        let primitive = match argument {
+            StaticArgument::Int(value) => Some(("java/lang/Integer", "I", vec![Slot::Int(*value)])),
+            StaticArgument::Float(value) => Some(("java/lang/Float", "F", vec![Slot::Float(*value)])),
+            StaticArgument::Long(value) => Some(("java/lang/Long", "J", wide(*value as u64, false))),
+            StaticArgument::Double(value) => Some(("java/lang/Double", "D", wide(value.to_bits(), true))),
+            _ => None,
+        };
+        let object = match (argument, primitive) {
+            (_, Some((_, descriptor, value))) if parameter.descriptor() == descriptor => return Ok(value),
+            (_, Some((class_name, descriptor, value))) => {
+                let class = self.load(class_name)?;
+                let (_, field) = class.find_field("value", descriptor).expect("a box without a value field");
+                let object = Object::new(class);
+                object.fields.borrow_mut()[field.slot..field.slot + value.len()].clone_from_slice(&value);
+                self.allocate(object)?
+            }
+            (StaticArgument::String(value), _) => self.intern(value)?,
+            (StaticArgument::Class(name), _) => {
+                let class = self.load(name)?;
+                self.mirror(&class)?
+            }
+            (StaticArgument::MethodType(descriptor), _) => self.method_type(descriptor)?,
+            (StaticArgument::MethodHandle(reference), _) => self.method_handle(reference)?,
+            _ => unreachable!("numeric static arguments are handled above"),
+        };
+        Ok(vec![Slot::Reference(Some(object))])
+    }
+
+    fn set_local(&mut self, index: usize, value: Vec<Slot>) {
+        let frame = self.thread.current_frame().expect("bootstrap method without a frame");
+        frame.local_vars.0[index..index + value.len()].clone_from_slice(&value);
+    }
+}
+
+// MethodHandles.lookup(), the lookup of the class calling it
+fn lookup(env: &mut Env, _: &LocalVars) -> Flow {
+    let caller = env.thread.current_frame().expect("a native without a caller").class.clone();
+    return_object(env.lookup(&caller))
+}
+
+fn array_element_var_handle(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(class) = args.get_ref(0) else {
+        return Flow::Throw(None);
+    };
+    let class = env.class_of_mirror(class);
+    if !class.is_array() {
+        return illegal_argument(format!("not an array class: {}", class.java_name()));
+    }
+    return_object(env.var_handle(VarHandleRef::Array(class.name.clone())))
+}
+
+// Lookup.findStatic, findVirtual and findSpecial: a handle of `kind` for the method named by the second
+// argument with the MethodType of the third in the class the first stands for. Access is not checked
+fn find_method(env: &mut Env, args: &LocalVars, kind: u8) -> Flow {
+    let (Some(class), Some(name), Some(method_type)) = (args.get_ref(1), args.get_ref(2), args.get_ref(3)) else {
+        return Flow::Throw(None);
+    };
+    let class = env.class_of_mirror(class);
+    let name = env.rust_string(name);
+    let descriptor = env.method_type_descriptor(method_type);
+    let kind = if kind == REF_INVOKE_VIRTUAL && class.is_interface() { REF_INVOKE_INTERFACE } else { kind };
+    match class.find_method(&name, &descriptor) {
+        Some((_, method)) if method.is_static() == (kind == REF_INVOKE_STATIC) && !name.starts_with('<') => {}
+        _ => return no_such_member("java/lang/NoSuchMethodException", &class, &name, &descriptor),
+    }
+    let class_name = class.name.clone();
+    return_object(env.method_handle(&MethodHandleRef { kind, class_name, name, descriptor }))
+}
+
+fn find_constructor(env: &mut Env, args: &LocalVars) -> Flow {
+    let (Some(class), Some(method_type)) = (args.get_ref(1), args.get_ref(2)) else {
+        return Flow::Throw(None);
+    };
+    let class = env.class_of_mirror(class);
+    let descriptor = env.method_type_descriptor(method_type);
+    let name = String::from("<init>");
+    let declared = class.methods.iter().any(|method| method.name == name && method.descriptor == descriptor);
+    if class.is_abstract() || !declared {
+        return no_such_member("java/lang/NoSuchMethodException", &class, &name, &descriptor);
+    }
+    let class_name = class.name.clone();
+    return_object(env.method_handle(&MethodHandleRef { kind: REF_NEW_INVOKE_SPECIAL, class_name, name, descriptor }))
+}
+
+// Lookup.findGetter, findSetter, findStaticGetter and findStaticSetter
+fn find_field(env: &mut Env, args: &LocalVars, kind: u8) -> Flow {
+    match field(env, args, matches!(kind, REF_GET_STATIC | REF_PUT_STATIC)) {
+        Ok((class_name, name, descriptor)) => {
+            return_object(env.method_handle(&MethodHandleRef { kind, class_name, name, descriptor }))
+        }
+        Err(flow) => flow,
+    }
+}
+
+fn find_var_handle(env: &mut Env, args: &LocalVars, is_static: bool) -> Flow {
+    match field(env, args, is_static) {
+        Ok((class_name, name, descriptor)) => {
+            return_object(env.var_handle(VarHandleRef::Field { class_name, name, descriptor, is_static }))
+        }
+        Err(flow) => flow,
+    }
+}
+
+// the class, name and descriptor of the field a Lookup.find method with the class, the name and the type
+// of the field as arguments asks for
+fn field(env: &mut Env, args: &LocalVars, is_static: bool) -> Result<(String, String, String), Flow> {
+    let (Some(class), Some(name), Some(field_type)) = (args.get_ref(1), args.get_ref(2), args.get_ref(3)) else {
+        return Err(Flow::Throw(None));
+    };
+    let class = env.class_of_mirror(class);
+    let name = env.rust_string(name);
+    let descriptor = env.class_of_mirror(field_type).descriptor();
+    match class.find_field(&name, &descriptor) {
+        Some((_, field)) if field.is_static() == is_static => Ok((class.name.clone(), name, descriptor)),
+        _ => Err(no_such_member("java/lang/NoSuchFieldException", &class, &name, &descriptor)),
+    }
+}
+
+fn return_object(object: Result<ObjectRef, Flow>) -> Flow {
+    match object {
+        Ok(object) => Flow::Return(vec![Slot::Reference(Some(object))]),
+        Err(flow) => flow,
+    }
+}
+
+fn no_such_member(class_name: &'static str, class: &Class, name: &str, descriptor: &str) -> Flow {
+    Flow::Raise {
+        class_name,
+        message: Some(format!("no such member: {}.{}{}", class.java_name(), name, descriptor)),
+    }
+}
+
+// the name of the class of a type, which is the name of the primitive type for one, void for None
+pub(super) fn type_class_name(field_type: Option<&FieldType>) -> String {
+    match field_type {
+        None => String::from("void"),
+        Some(FieldType::Object(name)) => name.clone(),
+        Some(array @ FieldType::Array(_)) => array.descriptor(),
+        Some(primitive) => String::from(primitive_name(&primitive.descriptor())),
+    }
+}
+
+fn bootstrap_method_error(message: String) -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/BootstrapMethodError",
+        message: Some(message),
+    }
+}
+
+fn illegal_argument(message: String) -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/IllegalArgumentException",
+        message: Some(message),
+    }
+}
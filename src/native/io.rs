@@ -0,0 +1,169 @@
+use std::io::{Read, Write};
+
+use crate::instructions::Flow;
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{LocalVars, Slot};
+
+use super::{empty, this, Env, NativeRegistry};
+
+const FILE_DESCRIPTOR: &str = "java/io/FileDescriptor";
+const FILE_OUTPUT_STREAM: &str = "java/io/FileOutputStream";
+const FILE_INPUT_STREAM: &str = "java/io/FileInputStream";
+
+// the streams behind System.in, out and err, on the file descriptors 0, 1 and 2 of the process;
+// opening files is left for later
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(FILE_DESCRIPTOR, "getHandle", "(I)J", get_handle);
+    registry.register(FILE_DESCRIPTOR, "getAppend", "(I)Z", get_append);
+    registry.register(FILE_DESCRIPTOR, "close0", "()V", empty);
+    registry.register(FILE_OUTPUT_STREAM, "writeBytes", "([BIIZ)V", write_bytes);
+    registry.register(FILE_OUTPUT_STREAM, "write", "(IZ)V", write);
+    registry.register(FILE_INPUT_STREAM, "readBytes", "([BII)I", read_bytes);
+    registry.register(FILE_INPUT_STREAM, "read0", "()I", read);
+    registry.register(FILE_INPUT_STREAM, "available0", "()I", available);
+}
+
+// Windows handles, which Linux has none of
+fn get_handle(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(Slot::wide(-1i64 as u64, false).to_vec())
+}
+
+fn get_append(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int(0)])
+}
+
+fn write_bytes(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(bytes) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let (offset, length) = (args.get_int(2), args.get_int(3));
+    let elements = env.heap[bytes].fields.borrow();
+    if offset < 0 || length < 0 || offset as usize + length as usize > elements.len() {
+        return out_of_bounds(offset, length, elements.len());
+    }
+    let bytes: Vec<u8> =
+        elements[offset as usize..offset as usize + length as usize].iter().map(|byte| byte.int() as u8).collect();
+    drop(elements);
+    output(env, this(args), &bytes)
+}
+
+fn write(env: &mut Env, args: &LocalVars) -> Flow {
+    output(env, this(args), &[args.get_int(1) as u8])
+}
+
+// where what the program writes to file descriptors 1 and 2 goes, the process's stdout and stderr unless an
+// embedder has the VM write elsewhere
+pub struct Output {
+    pub stdout: Box<dyn Write>,
+    pub stderr: Box<dyn Write>,
+}
+
+impl Default for Output {
+    fn default() -> Output {
+        Output {
+            stdout: Box::new(std::io::stdout()),
+            stderr: Box::new(std::io::stderr()),
+        }
+    }
+}
+
+// a Write that a test can read back what went into
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct Sink(pub(crate) std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for Sink {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// stdout is flushed on every write, so it interleaves with stderr the way it would unbuffered
+fn output(env: &mut Env, stream: ObjectRef, bytes: &[u8]) -> Flow {
+    let fd = file_descriptor(env, stream);
+    write_fd(env, fd, bytes)
+}
+
+pub(super) fn write_fd(env: &mut Env, fd: i32, bytes: &[u8]) -> Flow {
+    let written = match fd {
+        1 => env.output.stdout.write_all(bytes).and_then(|_| env.output.stdout.flush()),
+        2 => env.output.stderr.write_all(bytes),
+        _ => return closed(),
+    };
+    match written {
+        Ok(()) => Flow::Return(Vec::new()),
+        Err(error) => io_exception(error),
+    }
+}
+
+// -1 at the end of the input
+fn read_bytes(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(bytes) = args.get_ref(1) else {
+        return Flow::Throw(None);
+    };
+    let (offset, length) = (args.get_int(2), args.get_int(3));
+    let array_length = env.heap[bytes].array_length();
+    if offset < 0 || length < 0 || offset as usize + length as usize > array_length {
+        return out_of_bounds(offset, length, array_length);
+    }
+    if file_descriptor(env, this(args)) != 0 {
+        return closed();
+    }
+    let mut buffer = vec![0; length as usize];
+    match std::io::stdin().read(&mut buffer) {
+        Ok(0) if length > 0 => Flow::Return(vec![Slot::Int(-1)]),
+        Ok(read) => {
+            let mut elements = env.heap[bytes].fields.borrow_mut();
+            for (index, byte) in buffer[..read].iter().enumerate() {
+                elements[offset as usize + index] = Slot::Int(*byte as i8 as i32);
+            }
+            Flow::Return(vec![Slot::Int(read as i32)])
+        }
+        Err(error) => io_exception(error),
+    }
+}
+
+fn read(env: &mut Env, args: &LocalVars) -> Flow {
+    if file_descriptor(env, this(args)) != 0 {
+        return closed();
+    }
+    let mut byte = [0];
+    match std::io::stdin().read(&mut byte) {
+        Ok(0) => Flow::Return(vec![Slot::Int(-1)]),
+        Ok(_) => Flow::Return(vec![Slot::Int(byte[0] as i32)]),
+        Err(error) => io_exception(error),
+    }
+}
+
+// how much can be read without blocking is not known for a pipe or terminal
+fn available(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int(0)])
+}
+
+// the fd of the FileDescriptor of a FileInputStream or FileOutputStream, -1 once closed
+fn file_descriptor(env: &Env, stream: ObjectRef) -> i32 {
+    let object = &env.heap[stream];
+    let fd = object.get_ref_field("fd", "Ljava/io/FileDescriptor;").expect("a stream without a FileDescriptor");
+    env.heap[fd].get_int_field("fd", "I")
+}
+
+fn out_of_bounds(offset: i32, length: i32, array_length: usize) -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/IndexOutOfBoundsException",
+        message: Some(format!("Range [{}, {} + {}) out of bounds for length {}", offset, offset, length, array_length)),
+    }
+}
+
+fn closed() -> Flow {
+    Flow::Raise { class_name: "java/io/IOException", message: Some(String::from("Stream Closed")) }
+}
+
+fn io_exception(error: std::io::Error) -> Flow {
+    Flow::Raise { class_name: "java/io/IOException", message: Some(error.to_string()) }
+}
@@ -0,0 +1,348 @@
+use crate::instructions::Flow;
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{LocalVars, Object, Slot};
+
+use super::{empty, null, Env, NativeRegistry};
+
+const UNSAFE: &str = "jdk/internal/misc/Unsafe";
+const VM: &str = "jdk/internal/misc/VM";
+const CDS: &str = "jdk/internal/misc/CDS";
+const SIGNAL: &str = "jdk/internal/misc/Signal";
+
+// where the elements of an array start, as if it had HotSpot's 16 byte header; elements are as wide as
+// in HotSpot, references taking 4 bytes like compressed oops
+const ARRAY_BASE_OFFSET: i64 = 16;
+const REFERENCE_SIZE: i64 = 4;
+// static field offsets are their slot past this, telling them apart from the fields of the Class object
+// Unsafe gets them through
+const STATIC_FIELD_OFFSET: i64 = 1 << 20;
+
+// Unsafe on top of the slots of objects: an offset is a field slot for an object, a byte offset for
+// an array, so that a long can be read out of a byte[] the way java.util.zip and ArraysSupport do
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(UNSAFE, "arrayBaseOffset0", "(Ljava/lang/Class;)I", array_base_offset);
+    registry.register(UNSAFE, "arrayIndexScale0", "(Ljava/lang/Class;)I", array_index_scale);
+    registry.register(UNSAFE, "objectFieldOffset1", "(Ljava/lang/Class;Ljava/lang/String;)J", object_field_offset);
+    // what the JDK's Field.get and Field.set go through
+    registry.register(UNSAFE, "objectFieldOffset0", "(Ljava/lang/reflect/Field;)J", reflected_field_offset);
+    registry.register(UNSAFE, "staticFieldOffset0", "(Ljava/lang/reflect/Field;)J", reflected_field_offset);
+    registry.register(UNSAFE, "staticFieldBase0", "(Ljava/lang/reflect/Field;)Ljava/lang/Object;", static_field_base);
+    register_access::<b'Z'>(registry, "Boolean", "Z");
+    register_access::<b'B'>(registry, "Byte", "B");
+    register_access::<b'S'>(registry, "Short", "S");
+    register_access::<b'C'>(registry, "Char", "C");
+    register_access::<b'I'>(registry, "Int", "I");
+    register_access::<b'J'>(registry, "Long", "J");
+    register_access::<b'F'>(registry, "Float", "F");
+    register_access::<b'D'>(registry, "Double", "D");
+    register_access::<b'L'>(registry, "Reference", "Ljava/lang/Object;");
+    registry.register(UNSAFE, "compareAndSetInt", "(Ljava/lang/Object;JII)Z", compare_and_set::<b'I'>);
+    registry.register(UNSAFE, "compareAndSetLong", "(Ljava/lang/Object;JJJ)Z", compare_and_set::<b'J'>);
+    registry.register(
+        UNSAFE,
+        "compareAndSetReference",
+        "(Ljava/lang/Object;JLjava/lang/Object;Ljava/lang/Object;)Z",
+        compare_and_set::<b'L'>,
+    );
+    registry.register(UNSAFE, "compareAndExchangeInt", "(Ljava/lang/Object;JII)I", compare_and_exchange::<b'I'>);
+    registry.register(UNSAFE, "compareAndExchangeLong", "(Ljava/lang/Object;JJJ)J", compare_and_exchange::<b'J'>);
+    registry.register(
+        UNSAFE,
+        "compareAndExchangeReference",
+        "(Ljava/lang/Object;JLjava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+        compare_and_exchange::<b'L'>,
+    );
+    // one thread runs at a time, so memory is always in order
+    registry.register(UNSAFE, "fullFence", "()V", empty);
+    registry.register(UNSAFE, "loadFence", "()V", empty);
+    registry.register(UNSAFE, "storeFence", "()V", empty);
+    registry.register(UNSAFE, "ensureClassInitialized0", "(Ljava/lang/Class;)V", ensure_class_initialized);
+    registry.register(UNSAFE, "shouldBeInitialized0", "(Ljava/lang/Class;)Z", should_be_initialized);
+    registry.register(UNSAFE, "allocateInstance", "(Ljava/lang/Class;)Ljava/lang/Object;", allocate_instance);
+
+    registry.register(VM, "initialize", "()V", empty);
+    registry.register(VM, "initializeFromArchive", "(Ljava/lang/Class;)V", empty);
+    registry.register(VM, "latestUserDefinedLoader0", "()Ljava/lang/ClassLoader;", null);
+    // there is no class data sharing archive to dump or map
+    registry.register(CDS, "isDumpingClassList0", "()Z", no);
+    registry.register(CDS, "isDumpingArchive0", "()Z", no);
+    registry.register(CDS, "isSharingEnabled0", "()Z", no);
+    registry.register(CDS, "getRandomSeedForDumping", "()J", random_seed_for_dumping);
+    registry.register(CDS, "initializeFromArchive", "(Ljava/lang/Class;)V", empty);
+    registry.register(SIGNAL, "findSignal0", "(Ljava/lang/String;)I", find_signal);
+    registry.register(SIGNAL, "handle0", "(IJ)J", handle_signal);
+}
+
+// getX and putX of a type, plus their volatile variants that the acquire, release and opaque ones go through
+fn register_access<const KIND: u8>(registry: &mut NativeRegistry, name: &str, descriptor: &str) {
+    let get = format!("(Ljava/lang/Object;J){}", descriptor);
+    let put = format!("(Ljava/lang/Object;J{})V", descriptor);
+    registry.register(UNSAFE, &format!("get{}", name), &get, get_value::<KIND>);
+    registry.register(UNSAFE, &format!("get{}Volatile", name), &get, get_value::<KIND>);
+    registry.register(UNSAFE, &format!("put{}", name), &put, put_value::<KIND>);
+    registry.register(UNSAFE, &format!("put{}Volatile", name), &put, put_value::<KIND>);
+}
+
+fn array_base_offset(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int(ARRAY_BASE_OFFSET as i32)])
+}
+
+fn array_index_scale(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(args.get_ref(1).expect("arrayIndexScale0(null)"));
+    Flow::Return(vec![Slot::Int(element_size(class.name.as_bytes()[1]) as i32)])
+}
+
+fn object_field_offset(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(args.get_ref(1).expect("objectFieldOffset1(null, ..)"));
+    let name = env.rust_string(args.get_ref(2).expect("objectFieldOffset1(.., null)"));
+    match class.fields.iter().find(|field| field.name == name) {
+        Some(field) if field.is_static() => {
+            Flow::Return(Slot::wide((STATIC_FIELD_OFFSET + field.slot as i64) as u64, false).to_vec())
+        }
+        Some(field) => Flow::Return(Slot::wide(field.slot as u64, false).to_vec()),
+        None => Flow::Raise { class_name: "java/lang/InternalError", message: Some(name) },
+    }
+}
+
+fn reflected_field_offset(env: &mut Env, args: &LocalVars) -> Flow {
+    let (_, field) = env.reflected_field(args.get_ref(1).expect("objectFieldOffset0(null)"));
+    match field.is_static() {
+        true => Flow::Return(Slot::wide((STATIC_FIELD_OFFSET + field.slot as i64) as u64, false).to_vec()),
+        false => Flow::Return(Slot::wide(field.slot as u64, false).to_vec()),
+    }
+}
+
+// static fields are read through the Class object
+fn static_field_base(env: &mut Env, args: &LocalVars) -> Flow {
+    let (class, _) = env.reflected_field(args.get_ref(1).expect("staticFieldBase0(null)"));
+    match env.mirror(&class) {
+        Ok(mirror) => Flow::Return(vec![Slot::Reference(Some(mirror))]),
+        Err(flow) => flow,
+    }
+}
+
+fn get_value<const KIND: u8>(env: &mut Env, args: &LocalVars) -> Flow {
+    match read(env, args.get_ref(1), args.get_long(2), KIND) {
+        Ok(value) => Flow::Return(value),
+        Err(flow) => flow,
+    }
+}
+
+fn put_value<const KIND: u8>(env: &mut Env, args: &LocalVars) -> Flow {
+    let value = args.0[4..4 + slot_count(KIND)].to_vec();
+    match write(env, args.get_ref(1), args.get_long(2), KIND, value) {
+        Ok(()) => Flow::Return(Vec::new()),
+        Err(flow) => flow,
+    }
+}
+
+fn compare_and_set<const KIND: u8>(env: &mut Env, args: &LocalVars) -> Flow {
+    match compare_and_swap(env, args, KIND) {
+        Ok((_, swapped)) => Flow::Return(vec![Slot::Int(swapped as i32)]),
+        Err(flow) => flow,
+    }
+}
+
+fn compare_and_exchange<const KIND: u8>(env: &mut Env, args: &LocalVars) -> Flow {
+    match compare_and_swap(env, args, KIND) {
+        Ok((witness, _)) => Flow::Return(witness),
+        Err(flow) => flow,
+    }
+}
+
+// stores the new value when the current one is the expected one; gives the value found and whether it was stored
+fn compare_and_swap(env: &mut Env, args: &LocalVars, kind: u8) -> Result<(Vec<Slot>, bool), Flow> {
+    let (object, offset, width) = (args.get_ref(1), args.get_long(2), slot_count(kind));
+    let expected = &args.0[4..4 + width];
+    let current = read(env, object, offset, kind)?;
+    let swapped = match kind {
+        b'L' => current[0].reference() == expected[0].reference(),
+        b'J' => Slot::join(&current[0], &current[1], false) == Slot::join(&expected[0], &expected[1], false),
+        _ => current[0].int() == expected[0].int(),
+    };
+    if swapped {
+        write(env, object, offset, kind, args.0[4 + width..4 + 2 * width].to_vec())?;
+    }
+    Ok((current, swapped))
+}
+
+// the value of type `kind` at `offset` into `object`
+fn read(env: &Env, object: Option<ObjectRef>, offset: i64, kind: u8) -> Result<Vec<Slot>, Flow> {
+    let object = &env.heap[object.ok_or_else(off_heap)?];
+    match &object.mirror_of {
+        Some(class) if offset >= STATIC_FIELD_OFFSET => {
+            let slot = (offset - STATIC_FIELD_OFFSET) as usize;
+            return Ok(class.static_fields.borrow()[slot..slot + slot_count(kind)].to_vec());
+        }
+        _ => {}
+    }
+    let fields = object.fields.borrow();
+    let Some(element) = element_type(object) else {
+        let slot = offset as usize;
+        return Ok(fields[slot..slot + slot_count(kind)].to_vec());
+    };
+    if kind == b'L' {
+        return Ok(vec![fields[reference_index(offset)].clone()]);
+    }
+    let start = (offset - ARRAY_BASE_OFFSET) as usize;
+    let size = element_size(element);
+    let mut bits = 0;
+    for (shift, byte) in (start..start + element_size(kind)).enumerate() {
+        bits |= (element_bits(&fields, element, byte / size) >> (8 * (byte % size)) & 0xff) << (8 * shift);
+    }
+    Ok(slots(kind, bits))
+}
+
+// stores `value`, the slots of a value of type `kind`, at `offset` into `object`
+fn write(env: &mut Env, object: Option<ObjectRef>, offset: i64, kind: u8, value: Vec<Slot>) -> Result<(), Flow> {
+    let object_ref = object.ok_or_else(off_heap)?;
+    let object = &env.heap[object_ref];
+    match &object.mirror_of {
+        Some(class) if offset >= STATIC_FIELD_OFFSET => {
+            let slot = (offset - STATIC_FIELD_OFFSET) as usize;
+            class.static_fields.borrow_mut().splice(slot..slot + value.len(), value);
+            return Ok(());
+        }
+        _ => {}
+    }
+    let element = element_type(object);
+    let mut fields = object.fields.borrow_mut();
+    match element {
+        None => {
+            let slot = offset as usize;
+            fields.splice(slot..slot + value.len(), value);
+        }
+        Some(_) if kind == b'L' => fields[reference_index(offset)] = value[0].clone(),
+        Some(element) => {
+            let bits = value_bits(kind, &value);
+            let start = (offset - ARRAY_BASE_OFFSET) as usize;
+            let size = element_size(element);
+            for (shift, byte) in (start..start + element_size(kind)).enumerate() {
+                let (index, position) = (byte / size, 8 * (byte % size));
+                let old = element_bits(&fields, element, index) & !(0xff << position);
+                let new = old | (bits >> (8 * shift) & 0xff) << position;
+                let width = slot_count(element);
+                fields.splice(index * width..(index + 1) * width, slots(element, new));
+            }
+        }
+    }
+    drop(fields);
+    env.heap.write_barrier(object_ref);
+    Ok(())
+}
+
+// the element type of a primitive array as its descriptor, L for reference arrays; None for other objects
+fn element_type(object: &Object) -> Option<u8> {
+    match object.class.name.as_bytes() {
+        [b'[', b'L' | b'[', ..] => Some(b'L'),
+        [b'[', element, ..] => Some(*element),
+        _ => None,
+    }
+}
+
+fn reference_index(offset: i64) -> usize {
+    ((offset - ARRAY_BASE_OFFSET) / REFERENCE_SIZE) as usize
+}
+
+// the bytes of a value of type `descriptor`, as laid out in an array
+fn element_size(descriptor: u8) -> usize {
+    match descriptor {
+        b'Z' | b'B' => 1,
+        b'S' | b'C' => 2,
+        b'I' | b'F' => 4,
+        b'J' | b'D' => 8,
+        _ => REFERENCE_SIZE as usize,
+    }
+}
+
+fn slot_count(descriptor: u8) -> usize {
+    match descriptor {
+        b'J' | b'D' => 2,
+        _ => 1,
+    }
+}
+
+// element `index` of a primitive array as raw bits
+fn element_bits(fields: &[Slot], element: u8, index: usize) -> u64 {
+    let width = slot_count(element);
+    value_bits(element, &fields[index * width..(index + 1) * width])
+}
+
+fn value_bits(descriptor: u8, value: &[Slot]) -> u64 {
+    match descriptor {
+        b'J' => Slot::join(&value[0], &value[1], false),
+        b'D' => Slot::join(&value[0], &value[1], true),
+        b'F' => value[0].float().to_bits() as u64,
+        _ => value[0].int() as u32 as u64,
+    }
+}
+
+// the slots of a value of type `descriptor` with the given raw bits, narrowed like a store to an array
+fn slots(descriptor: u8, bits: u64) -> Vec<Slot> {
+    match descriptor {
+        b'J' => Slot::wide(bits, false).to_vec(),
+        b'D' => Slot::wide(bits, true).to_vec(),
+        b'F' => vec![Slot::Float(f32::from_bits(bits as u32))],
+        b'Z' => vec![Slot::Int((bits as u8 != 0) as i32)],
+        b'B' => vec![Slot::Int(bits as i8 as i32)],
+        b'S' => vec![Slot::Int(bits as i16 as i32)],
+        b'C' => vec![Slot::Int(bits as u16 as i32)],
+        _ => vec![Slot::Int(bits as i32)],
+    }
+}
+
+// the VM has no memory outside the heap for Unsafe to reach with a null base
+fn off_heap() -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/InternalError",
+        message: Some(String::from("off-heap memory access is not supported")),
+    }
+}
+
+fn ensure_class_initialized(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(args.get_ref(1).expect("ensureClassInitialized0(null)"));
+    env.initialize(&class).unwrap_or(Flow::Return(Vec::new()))
+}
+
+fn should_be_initialized(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(args.get_ref(1).expect("shouldBeInitialized0(null)"));
+    Flow::Return(vec![Slot::Int(!class.is_initialized() as i32)])
+}
+
+fn allocate_instance(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(args.get_ref(1).expect("allocateInstance(null)"));
+    if class.is_interface() || class.is_abstract() || class.is_array() || class.is_primitive() {
+        return Flow::Raise { class_name: "java/lang/InstantiationException", message: Some(class.java_name()) };
+    }
+    if let Some(flow) = env.initialize(&class) {
+        return flow;
+    }
+    match env.allocate(Object::new(class)) {
+        Ok(object) => Flow::Return(vec![Slot::Reference(Some(object))]),
+        Err(flow) => flow,
+    }
+}
+
+fn no(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int(0)])
+}
+
+fn random_seed_for_dumping(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(Slot::wide(0, false).to_vec())
+}
+
+// the signal numbers of Linux for the signals the JDK installs handlers for; the handlers never run
+fn find_signal(env: &mut Env, args: &LocalVars) -> Flow {
+    let name = env.rust_string(args.get_ref(0).expect("findSignal0(null)"));
+    let number = match name.as_str() {
+        "HUP" => 1,
+        "INT" => 2,
+        "TERM" => 15,
+        _ => -1,
+    };
+    Flow::Return(vec![Slot::Int(number)])
+}
+
+fn handle_signal(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(Slot::wide(0, false).to_vec())
+}
@@ -0,0 +1,297 @@
+// methods the VM implements in Rust: the natives of JDK classes, plus JDK methods whose bytecode
+// needs more of the JDK than the VM can run yet
+mod builtin;
+mod class;
+mod invoke;
+mod io;
+mod misc;
+mod object;
+mod proxy;
+mod reference;
+mod reflect;
+mod string;
+mod system;
+mod thread;
+mod throwable;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub use io::Output;
+#[cfg(test)]
+pub(crate) use io::Sink;
+
+use crate::error::Error;
+use crate::instructions::Flow;
+use crate::interpreter::{initialize, linkage_error, out_of_memory, stack_overflow};
+use crate::log::Logger;
+use crate::runtime::class::{primitive_name, Class, Method};
+use crate::runtime::class_loader::ClassLoader;
+use crate::runtime::heap::{roots, Heap, ObjectRef};
+use crate::runtime::scheduler::Scheduler;
+use crate::runtime::string::StringTable;
+use crate::runtime::{Frame, LocalVars, Object, Slot, Thread};
+
+// what a native gets to work with besides its arguments
+pub struct Env<'a> {
+    pub loader: &'a ClassLoader,
+    pub scheduler: &'a mut Scheduler,
+    pub heap: &'a mut dyn Heap,
+    pub strings: &'a mut StringTable,
+    pub properties: &'a [(String, String)],
+    // what the global references of native code refer to
+    pub global_refs: &'a [Option<ObjectRef>],
+    pub output: &'a mut Output,
+    pub logger: &'a Logger,
+    pub thread: &'a mut Thread,
+}
+
+impl Env<'_> {
+    // puts `object` on the heap, collecting garbage when it is full, or gives the OutOfMemoryError to throw
+    pub fn allocate(&mut self, object: Object) -> Result<ObjectRef, Flow> {
+        self.allocate_keeping(object, &[])
+    }
+
+    // allocates like allocate, keeping the `live` objects through a collection although nothing
+    // refers to them yet, for objects built out of several allocations
+    pub fn allocate_keeping(&mut self, object: Object, live: &[ObjectRef]) -> Result<ObjectRef, Flow> {
+        let (loader, scheduler, strings, thread) = (self.loader, &*self.scheduler, &*self.strings, &*self.thread);
+        let global_refs = self.global_refs;
+        let roots = || {
+            let threads = scheduler.threads().chain([thread]);
+            let mut roots = roots(&loader.loaded_classes(), strings, global_refs, threads);
+            roots.extend_from_slice(live);
+            roots
+        };
+        let object = self.heap.allocate(object, &roots).ok_or_else(out_of_memory);
+        self.write_gc_log();
+        object
+    }
+
+    // a full collection, what System.gc asks for
+    pub fn collect(&mut self) {
+        let threads = self.scheduler.threads().chain([&*self.thread]);
+        let roots = roots(&self.loader.loaded_classes(), self.strings, self.global_refs, threads);
+        self.heap.collect(roots);
+        self.write_gc_log();
+    }
+
+    // -verbose:gc goes where System.out does, as on HotSpot
+    fn write_gc_log(&mut self) {
+        for line in self.heap.take_log() {
+            let _ = writeln!(self.output.stdout, "{}", line);
+        }
+    }
+
+    pub fn load(&self, class_name: &str) -> Result<Rc<Class>, Flow> {
+        self.loader.load_class(class_name).map_err(load_failure)
+    }
+
+    // a new array of class `class_name`, e.g. [I or [[Ljava/lang/String;, with the given lengths
+    // along its first dimensions; the arrays of the dimensions past those are left null
+    pub fn new_array(&mut self, class_name: &str, lengths: &[usize]) -> Result<ObjectRef, Flow> {
+        self.new_array_keeping(class_name, lengths, &mut Vec::new())
+    }
+
+    fn new_array_keeping(
+        &mut self,
+        class_name: &str,
+        lengths: &[usize],
+        live: &mut Vec<ObjectRef>,
+    ) -> Result<ObjectRef, Flow> {
+        let class = self.load(class_name)?;
+        // every element takes at least a byte, so this can never fit
+        if lengths[0] > self.heap.capacity() {
+            return Err(out_of_memory());
+        }
+        let array = self.allocate_keeping(Object::new_array(class, lengths[0]), live)?;
+        if lengths.len() > 1 {
+            live.push(array);
+            let mut elements = Ok(());
+            for index in 0..lengths[0] {
+                match self.new_array_keeping(&class_name[1..], &lengths[1..], live) {
+                    Ok(element) => {
+                        self.heap[array].fields.borrow_mut()[index] = Slot::Reference(Some(element));
+                        self.heap.write_barrier(array);
+                    }
+                    Err(flow) => {
+                        elements = Err(flow);
+                        break;
+                    }
+                }
+            }
+            live.pop();
+            elements?;
+        }
+        Ok(array)
+    }
+
+    // the java.lang.Class object standing for `class`, created on first use
+    pub fn mirror(&mut self, class: &Rc<Class>) -> Result<ObjectRef, Flow> {
+        self.mirror_keeping(class, &[])
+    }
+
+    // mirror for natives that hold on to objects nothing refers to yet, like allocate_keeping
+    pub fn mirror_keeping(&mut self, class: &Rc<Class>, live: &[ObjectRef]) -> Result<ObjectRef, Flow> {
+        if let Some(mirror) = class.mirror.get() {
+            return Ok(mirror);
+        }
+        let component = match &class.component {
+            Some(component) => Some(self.mirror_keeping(&component.clone(), live)?),
+            None if class.is_array() => {
+                Some(self.mirror_keeping(&self.load(primitive_name(&class.name[1..]))?, live)?)
+            }
+            None => None,
+        };
+        let mut object = Object::new(self.load("java/lang/Class")?);
+        object.mirror_of = Some(class.clone());
+        let mirror = self.allocate_keeping(object, &[live, &Vec::from_iter(component)].concat())?;
+        // JDK 9 on keep the element class of an array class in a field
+        let object = &self.heap[mirror];
+        if component.is_some() && object.class.find_field("componentType", "Ljava/lang/Class;").is_some() {
+            object.set_ref_field("componentType", "Ljava/lang/Class;", component);
+        }
+        class.mirror.set(Some(mirror));
+        Ok(mirror)
+    }
+
+    // has the initializers of `class` run before the native goes on: the Flow to return with when they
+    // have to run first, the native being called again after them
+    pub fn initialize(&mut self, class: &Rc<Class>) -> Option<Flow> {
+        initialize(self.thread, class)
+    }
+
+    // has static `method` called in place of the running native, which returns: it takes the native's
+    // arguments and its result is what the native returns
+    pub fn tail_call(&mut self, class: Rc<Class>, method: Rc<Method>, args: &LocalVars) -> Flow {
+        let caller = self.thread.current_frame().expect("a native without a caller");
+        let mut frame = Frame::new(class, method);
+        for (index, slot) in caller.operand_stack.pop_slots(args.0.len()).into_iter().enumerate() {
+            frame.local_vars.0[index] = slot;
+        }
+        match self.thread.push_frame(frame) {
+            Ok(()) => Flow::Next,
+            Err(_) => stack_overflow(),
+        }
+    }
+
+    // the class a java.lang.Class object stands for
+    pub fn class_of_mirror(&self, mirror: ObjectRef) -> Rc<Class> {
+        self.heap[mirror].mirror_of.clone().expect("a Class object that stands for no class")
+    }
+}
+
+// natives get their arguments laid out like a frame's locals, `this` first, and return
+// Flow::Return with the return value or an exception to throw. Flow::Next calls the native again
+// once the frames it pushed have returned, which is how natives have classes initialized, unless the
+// native has handed its call over to a method with Env::tail_call
+pub type NativeMethod = fn(&mut Env, &LocalVars) -> Flow;
+
+pub struct NativeRegistry {
+    methods: HashMap<String, NativeMethod>,
+}
+
+impl NativeRegistry {
+    // with the natives of the built-in class library when that stands in for the JDK
+    pub fn new(builtin: bool) -> NativeRegistry {
+        let mut registry = NativeRegistry {
+            methods: HashMap::new(),
+        };
+        class::register(&mut registry);
+        invoke::register(&mut registry);
+        io::register(&mut registry);
+        misc::register(&mut registry);
+        object::register(&mut registry);
+        proxy::register(&mut registry);
+        reference::register(&mut registry);
+        reflect::register(&mut registry);
+        string::register(&mut registry);
+        system::register(&mut registry);
+        thread::register(&mut registry);
+        throwable::register(&mut registry);
+        if builtin {
+            builtin::register(&mut registry);
+        }
+        registry
+    }
+
+    pub fn register(&mut self, class_name: &str, method_name: &str, descriptor: &str, method: NativeMethod) {
+        self.methods.insert(key(class_name, method_name, descriptor), method);
+    }
+
+    pub fn find(&self, class_name: &str, method_name: &str, descriptor: &str) -> Option<NativeMethod> {
+        // the JDK's classes register their natives from <clinit>, there is nothing to do for that here
+        if descriptor == "()V" && (method_name == "registerNatives" || method_name == "initIDs") {
+            return Some(empty);
+        }
+        self.methods.get(&key(class_name, method_name, descriptor)).copied()
+    }
+}
+
+fn key(class_name: &str, method_name: &str, descriptor: &str) -> String {
+    format!("{}.{}{}", class_name, method_name, descriptor)
+}
+
+fn empty(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(Vec::new())
+}
+
+fn null(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Reference(None)])
+}
+
+fn return_object(object: Result<ObjectRef, Flow>) -> Flow {
+    match object {
+        Ok(object) => Flow::Return(vec![Slot::Reference(Some(object))]),
+        Err(flow) => flow,
+    }
+}
+
+fn this(args: &LocalVars) -> ObjectRef {
+    args.get_ref(0).expect("native instance method called on null")
+}
+
+// a timeout in milliseconds as the virtual time it ends at, 0 meaning none; None when negative
+fn deadline(scheduler: &Scheduler, millis: i64) -> Option<Option<u64>> {
+    let millis = u64::try_from(millis).ok()?;
+    Some((millis > 0).then(|| scheduler.now() + millis * NANOS_PER_MILLI))
+}
+
+const NANOS_PER_MILLI: u64 = 1_000_000;
+
+fn negative_timeout() -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/IllegalArgumentException",
+        message: Some(String::from("timeout value is negative")),
+    }
+}
+
+// the LinkageError for a class that failed to load; a failure of the VM itself is a NoClassDefFoundError too
+pub(crate) fn load_failure(error: Error) -> Flow {
+    let (class_name, message) = match linkage_error(error) {
+        Ok((class_name, message)) => (class_name, message),
+        Err(error) => ("java/lang/NoClassDefFoundError", error.to_string()),
+    };
+    Flow::Raise { class_name, message: Some(message) }
+}
+
+fn not_owner() -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/IllegalMonitorStateException",
+        message: Some(String::from("current thread is not owner")),
+    }
+}
+
+fn illegal_argument(message: &str) -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/IllegalArgumentException",
+        message: Some(String::from(message)),
+    }
+}
+
+fn internal_error(message: String) -> Flow {
+    Flow::Raise {
+        class_name: "java/lang/InternalError",
+        message: Some(message),
+    }
+}
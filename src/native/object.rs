@@ -0,0 +1,78 @@
+use crate::instructions::Flow;
+use crate::runtime::{LocalVars, Slot, ThreadState};
+
+use super::{deadline, negative_timeout, not_owner, this, Env, NativeRegistry};
+
+const OBJECT: &str = "java/lang/Object";
+
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(OBJECT, "hashCode", "()I", hash_code);
+    registry.register(OBJECT, "getClass", "()Ljava/lang/Class;", get_class);
+    registry.register(OBJECT, "clone", "()Ljava/lang/Object;", clone);
+    registry.register(OBJECT, "wait", "(J)V", wait);
+    registry.register(OBJECT, "notify", "()V", notify);
+    registry.register(OBJECT, "notifyAll", "()V", notify_all);
+}
+
+fn hash_code(env: &mut Env, args: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int(env.heap[this(args)].identity_hash(env.thread))])
+}
+
+fn get_class(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.heap[this(args)].class.clone();
+    match env.mirror(&class) {
+        Ok(mirror) => Flow::Return(vec![Slot::Reference(Some(mirror))]),
+        Err(flow) => flow,
+    }
+}
+
+// a shallow copy; arrays can always be cloned, other objects when their class implements Cloneable
+fn clone(env: &mut Env, args: &LocalVars) -> Flow {
+    let object = &env.heap[this(args)];
+    if !object.class.is_array() && !object.class.is_subclass_of("java/lang/Cloneable") {
+        return Flow::Raise {
+            class_name: "java/lang/CloneNotSupportedException",
+            message: Some(object.class.java_name()),
+        };
+    }
+    match env.allocate(object.copy()) {
+        Ok(copy) => Flow::Return(vec![Slot::Reference(Some(copy))]),
+        Err(flow) => flow,
+    }
+}
+
+// the thread lets go of the monitor and takes it back when it runs again after being notified
+// or timing out; wait(0) waits until notified
+fn wait(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    let Some(until) = deadline(env.scheduler, args.get_long(1)) else {
+        return negative_timeout();
+    };
+    let Ok((count, next)) = env.heap[this].monitor.wait(env.thread.id) else {
+        return not_owner();
+    };
+    if let Some(next) = next {
+        env.scheduler.wake(next);
+    }
+    env.thread.state = ThreadState::Waiting { until };
+    env.thread.reacquire = Some((this, count));
+    Flow::Return(Vec::new())
+}
+
+fn notify(env: &mut Env, args: &LocalVars) -> Flow {
+    notify_threads(env, args, false)
+}
+
+fn notify_all(env: &mut Env, args: &LocalVars) -> Flow {
+    notify_threads(env, args, true)
+}
+
+fn notify_threads(env: &mut Env, args: &LocalVars, all: bool) -> Flow {
+    let Ok(waiting) = env.heap[this(args)].monitor.notify(env.thread.id, all) else {
+        return not_owner();
+    };
+    for id in waiting {
+        env.scheduler.wake(id);
+    }
+    Flow::Return(Vec::new())
+}
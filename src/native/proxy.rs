@@ -0,0 +1,135 @@
+use std::rc::Rc;
+
+use crate::instructions::Flow;
+use crate::runtime::call_site::{proxy_method_field, spin_proxy, PROXY};
+use crate::runtime::class::Class;
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{LocalVars, Object, Slot};
+
+use super::{illegal_argument, internal_error, return_object, Env, NativeRegistry};
+
+// what every proxy passes on to its handler besides the methods of its interfaces
+const OBJECT_METHODS: [(&str, &str); 3] =
+    [("equals", "(Ljava/lang/Object;)Z"), ("hashCode", "()I"), ("toString", "()Ljava/lang/String;")];
+
+// the JDK spins proxy classes with its own bytecode library and defines them in modules of their own, which is
+// more of the JDK than the VM runs; the VM spins them itself instead
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(
+        PROXY,
+        "newProxyInstance",
+        "(Ljava/lang/ClassLoader;[Ljava/lang/Class;Ljava/lang/reflect/InvocationHandler;)Ljava/lang/Object;",
+        new_proxy_instance,
+    );
+    registry.register(PROXY, "isProxyClass", "(Ljava/lang/Class;)Z", |env, args| {
+        let class = env.class_of_mirror(args.get_ref(0).expect("isProxyClass of null"));
+        Flow::Return(vec![Slot::Int(env.is_proxy_class(&class) as i32)])
+    });
+}
+
+impl Env<'_> {
+    // whether `class` is a proxy class the VM made
+    pub fn is_proxy_class(&self, class: &Rc<Class>) -> bool {
+        let interfaces: Vec<String> = class.interfaces.iter().map(|interface| interface.name.clone()).collect();
+        self.loader.proxy_class(&interfaces).is_some_and(|proxy| Rc::ptr_eq(&proxy, class))
+    }
+
+    // a proxy implementing `interfaces` that passes its calls on to `handler`, keeping the `live` objects like
+    // allocate_keeping
+    pub(super) fn new_proxy(
+        &mut self,
+        interfaces: &[Rc<Class>],
+        handler: ObjectRef,
+        live: &[ObjectRef],
+    ) -> Result<ObjectRef, Flow> {
+        let class = self.proxy_class(interfaces)?;
+        if let Some(flow) = self.initialize(&class) {
+            return Err(flow);
+        }
+        let proxy = self.allocate_keeping(Object::new(class), &[live, &[handler]].concat())?;
+        self.heap[proxy].set_ref_field("h", "Ljava/lang/reflect/InvocationHandler;", Some(handler));
+        Ok(proxy)
+    }
+
+    // the proxy class implementing `interfaces`, made and defined on first use with the Methods its handler
+    // gets in its static fields
+    fn proxy_class(&mut self, interfaces: &[Rc<Class>]) -> Result<Rc<Class>, Flow> {
+        let names: Vec<String> = interfaces.iter().map(|interface| interface.name.clone()).collect();
+        if let Some(class) = self.loader.proxy_class(&names) {
+            return Ok(class);
+        }
+        let object = self.load("java/lang/Object")?;
+        let mut methods = Vec::new();
+        for (name, descriptor) in OBJECT_METHODS {
+            methods.extend(member_slot(&object, name, descriptor));
+        }
+        for interface in interfaces {
+            proxied_methods(interface, &mut methods);
+        }
+        // in the package of the first interface, which a package private one needs
+        let package = names[0].rsplit_once('/').map_or("", |(package, _)| package);
+        let prefix = match package {
+            "" => String::from("$Proxy"),
+            package => format!("{}/$Proxy", package),
+        };
+        let class_name = self.loader.unique_name(&prefix);
+        let signatures: Vec<(&str, &str)> = methods
+            .iter()
+            .map(|(class, slot)| (class.methods[*slot].name.as_str(), class.methods[*slot].descriptor.as_str()))
+            .collect();
+        let interface_names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let content = spin_proxy(&class_name, &interface_names, &signatures).map_err(internal_error)?;
+        let class = self.loader.define_class(&class_name, content);
+        let class = class.map_err(|error| internal_error(error.to_string()))?;
+        for (index, (declaring, slot)) in methods.iter().enumerate() {
+            let method = self.reflect_method(declaring, *slot, &[])?;
+            let (_, field) = class.find_field(&proxy_method_field(index), "Ljava/lang/reflect/Method;").unwrap();
+            class.static_fields.borrow_mut()[field.slot] = Slot::Reference(Some(method));
+        }
+        self.loader.add_proxy_class(names, class.clone());
+        Ok(class)
+    }
+}
+
+fn new_proxy_instance(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(handler) = args.get_ref(2) else {
+        return Flow::Raise {
+            class_name: "java/lang/NullPointerException",
+            message: None,
+        };
+    };
+    let mirrors = env.heap[args.get_ref(1).expect("newProxyInstance without interfaces")].fields.borrow().clone();
+    let interfaces: Vec<Rc<Class>> =
+        mirrors.iter().map(|mirror| env.class_of_mirror(mirror.reference().expect("a null interface"))).collect();
+    if let Some(class) = interfaces.iter().find(|class| !class.is_interface()) {
+        return illegal_argument(&format!("{} is not an interface", class.java_name()));
+    }
+    if interfaces.is_empty() {
+        return illegal_argument("a proxy needs an interface");
+    }
+    return_object(env.new_proxy(&interfaces, handler, &[]))
+}
+
+// the class and slot of method `name` of `class`
+fn member_slot(class: &Rc<Class>, name: &str, descriptor: &str) -> Option<(Rc<Class>, usize)> {
+    let slot = class.methods.iter().position(|method| method.name == name && method.descriptor == descriptor)?;
+    Some((class.clone(), slot))
+}
+
+// adds the instance methods of `interface` and its super interfaces to `methods`, those of the same name and
+// descriptor as one already there left out
+fn proxied_methods(interface: &Rc<Class>, methods: &mut Vec<(Rc<Class>, usize)>) {
+    for (slot, method) in interface.methods.iter().enumerate() {
+        let proxied = !method.is_static() && !method.is_private() && !method.name.starts_with('<');
+        let known = methods.iter().any(|(class, slot)| {
+            let known = &class.methods[*slot];
+            known.name == method.name && known.descriptor == method.descriptor
+        });
+        if proxied && !known {
+            methods.push((interface.clone(), slot));
+        }
+    }
+    for super_interface in &interface.interfaces {
+        proxied_methods(super_interface, methods);
+    }
+}
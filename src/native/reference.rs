@@ -0,0 +1,36 @@
+use crate::instructions::Flow;
+use crate::runtime::{LocalVars, Slot, ThreadState};
+
+use super::{null, this, Env, NativeRegistry};
+
+const REFERENCE: &str = "java/lang/ref/Reference";
+
+// the collectors treat referents like any other field, so no reference is ever cleared by them or enqueued,
+// and the Reference Handler thread waits for a pending list that never comes
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(REFERENCE, "waitForReferencePendingList", "()V", wait_for_reference_pending_list);
+    registry.register(REFERENCE, "hasReferencePendingList", "()Z", has_reference_pending_list);
+    registry.register(REFERENCE, "getAndClearReferencePendingList", "()Ljava/lang/ref/Reference;", null);
+    registry.register(REFERENCE, "refersTo0", "(Ljava/lang/Object;)Z", refers_to);
+    registry.register("java/lang/ref/PhantomReference", "refersTo0", "(Ljava/lang/Object;)Z", refers_to);
+    registry.register(REFERENCE, "clear0", "()V", clear);
+}
+
+fn wait_for_reference_pending_list(env: &mut Env, _: &LocalVars) -> Flow {
+    env.thread.state = ThreadState::Waiting { until: None };
+    Flow::Return(Vec::new())
+}
+
+fn has_reference_pending_list(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int(0)])
+}
+
+fn refers_to(env: &mut Env, args: &LocalVars) -> Flow {
+    let referent = env.heap[this(args)].get_ref_field("referent", "Ljava/lang/Object;");
+    Flow::Return(vec![Slot::Int((referent == args.get_ref(1)) as i32)])
+}
+
+fn clear(env: &mut Env, args: &LocalVars) -> Flow {
+    env.heap[this(args)].set_ref_field("referent", "Ljava/lang/Object;", None);
+    Flow::Return(Vec::new())
+}
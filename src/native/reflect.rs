@@ -0,0 +1,527 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::classfile::class_reader::{get_class_name, get_utf8};
+use crate::classfile::constant_pool::ConstantInfo::{
+    ConstantDouble, ConstantFloat, ConstantInteger, ConstantLong, ConstantString,
+};
+use crate::classfile::descriptor::parse_field_descriptor;
+use crate::instructions::Flow;
+use crate::runtime::call_site::{
+    box_class, spin_accessor, MethodHandleRef, ACCESSOR, REF_GET_FIELD, REF_GET_STATIC, REF_INVOKE_INTERFACE,
+    REF_INVOKE_SPECIAL, REF_INVOKE_STATIC, REF_INVOKE_VIRTUAL, REF_NEW_INVOKE_SPECIAL, REF_PUT_FIELD, REF_PUT_STATIC,
+};
+use crate::runtime::class::{raw_annotations, Class, Field, Method, PRIMITIVE_TYPES};
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{LocalVars, Object, Slot};
+
+use super::invoke::type_class_name;
+use super::{illegal_argument, internal_error, null, return_object, Env, NativeRegistry};
+
+const REFLECTION: &str = "jdk/internal/reflect/Reflection";
+const ACCESS_CONTROLLER: &str = "java/security/AccessController";
+const ARRAY: &str = "java/lang/reflect/Array";
+const FIELD: &str = "java/lang/reflect/Field";
+const METHOD: &str = "java/lang/reflect/Method";
+const CONSTRUCTOR: &str = "java/lang/reflect/Constructor";
+const CONSTANT_POOL: &str = "jdk/internal/reflect/ConstantPool";
+const METHOD_ACCESSOR: &str = "jdk/internal/reflect/NativeMethodAccessorImpl";
+const CONSTRUCTOR_ACCESSOR: &str = "jdk/internal/reflect/NativeConstructorAccessorImpl";
+// the access flags HotSpot passes on as modifiers, JVM_RECOGNIZED_FIELD_MODIFIERS and
+// JVM_RECOGNIZED_METHOD_MODIFIERS
+const FIELD_MODIFIERS: u16 = 0x50df;
+const METHOD_MODIFIERS: u16 = 0x1dff;
+
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(REFLECTION, "getCallerClass", "()Ljava/lang/Class;", get_caller_class);
+    registry.register(REFLECTION, "getClassAccessFlags", "(Ljava/lang/Class;)I", get_class_access_flags);
+    registry.register(REFLECTION, "areNestMates", "(Ljava/lang/Class;Ljava/lang/Class;)Z", are_nest_mates);
+    registry.register(ARRAY, "newArray", "(Ljava/lang/Class;I)Ljava/lang/Object;", new_array);
+    registry.register(ARRAY, "getLength", "(Ljava/lang/Object;)I", get_length);
+    // there is no security manager, so no access control context either
+    registry.register(
+        ACCESS_CONTROLLER,
+        "getStackAccessControlContext",
+        "()Ljava/security/AccessControlContext;",
+        null,
+    );
+    registry.register(
+        ACCESS_CONTROLLER,
+        "getInheritedAccessControlContext",
+        "()Ljava/security/AccessControlContext;",
+        null,
+    );
+    registry.register(
+        ACCESS_CONTROLLER,
+        "getProtectionDomain",
+        "(Ljava/lang/Class;)Ljava/security/ProtectionDomain;",
+        null,
+    );
+    // what the JDK's annotation parser reads the constant pool of a class with
+    registry.register(CONSTANT_POOL, "getSize0", "(Ljava/lang/Object;)I", |env, args| {
+        let class = env.class_of_mirror(args.get_ref(1).expect("a ConstantPool without a class"));
+        Flow::Return(vec![Slot::Int(class.classfile.constant_pool.len() as i32)])
+    });
+    registry.register(CONSTANT_POOL, "getIntAt0", "(Ljava/lang/Object;I)I", get_int_at);
+    registry.register(CONSTANT_POOL, "getLongAt0", "(Ljava/lang/Object;I)J", get_long_at);
+    registry.register(CONSTANT_POOL, "getFloatAt0", "(Ljava/lang/Object;I)F", get_float_at);
+    registry.register(CONSTANT_POOL, "getDoubleAt0", "(Ljava/lang/Object;I)D", get_double_at);
+    registry.register(CONSTANT_POOL, "getUTF8At0", "(Ljava/lang/Object;I)Ljava/lang/String;", get_utf8_at);
+    registry.register(CONSTANT_POOL, "getStringAt0", "(Ljava/lang/Object;I)Ljava/lang/String;", get_string_at);
+    registry.register(CONSTANT_POOL, "getClassAt0", "(Ljava/lang/Object;I)Ljava/lang/Class;", get_class_at);
+    // the accessors the JDK's reflection calls through; Method.invoke and Constructor.newInstance are natives
+    // throughout, instead of going over to bytecode the JDK would spin after a number of calls
+    registry.register(
+        METHOD_ACCESSOR,
+        "invoke0",
+        "(Ljava/lang/reflect/Method;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;",
+        |env, args| invoke(env, args.get_ref(0), args),
+    );
+    registry.register(
+        METHOD_ACCESSOR,
+        "invoke",
+        "(Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;",
+        |env, args| {
+            let method = env.heap[super::this(args)].get_ref_field("method", "Ljava/lang/reflect/Method;");
+            invoke(env, method, args)
+        },
+    );
+    registry.register(
+        CONSTRUCTOR_ACCESSOR,
+        "newInstance0",
+        "(Ljava/lang/reflect/Constructor;[Ljava/lang/Object;)Ljava/lang/Object;",
+        |env, args| new_instance(env, args.get_ref(0), args),
+    );
+    registry.register(
+        CONSTRUCTOR_ACCESSOR,
+        "newInstance",
+        "([Ljava/lang/Object;)Ljava/lang/Object;",
+        |env, args| {
+            let constructor = env.heap[super::this(args)].get_ref_field("c", "Ljava/lang/reflect/Constructor;");
+            new_instance(env, constructor, args)
+        },
+    );
+}
+
+impl Env<'_> {
+    // the java.lang.reflect.Field for field `slot` of `class`, keeping the `live` objects like allocate_keeping
+    pub fn reflect_field(&mut self, class: &Rc<Class>, slot: usize, live: &[ObjectRef]) -> Result<ObjectRef, Flow> {
+        let field = class.fields[slot].clone();
+        let declaring = self.mirror_keeping(class, live)?;
+        let field_type = self.load(&type_class_name(parse_field_descriptor(&field.descriptor).as_ref()))?;
+        let field_type = self.mirror_keeping(&field_type, live)?;
+        let name = self.intern(&field.name)?;
+        let signature = field.signature.as_deref().map(|signature| self.intern(signature)).transpose()?;
+        let object = self.allocate_keeping(Object::new(self.load(FIELD)?), live)?;
+        let object_ref = &self.heap[object];
+        object_ref.set_ref_field("clazz", "Ljava/lang/Class;", Some(declaring));
+        object_ref.set_int_field("slot", "I", slot as i32);
+        object_ref.set_ref_field("name", "Ljava/lang/String;", Some(name));
+        object_ref.set_ref_field("type", "Ljava/lang/Class;", Some(field_type));
+        object_ref.set_int_field("modifiers", "I", (field.access_flags & FIELD_MODIFIERS) as i32);
+        object_ref.set_int_field("trustedFinal", "Z", (field.is_static() && field.is_final()) as i32);
+        object_ref.set_ref_field("signature", "Ljava/lang/String;", signature);
+        self.heap.write_barrier(object);
+        let [annotations, _, _] = raw_annotations(&class.classfile.fields_info[slot].attribute_info);
+        self.set_bytes_field(object, "annotations", annotations, live)?;
+        Ok(object)
+    }
+
+    // the java.lang.reflect.Method for method `slot` of `class`, or the Constructor when that is <init>
+    pub fn reflect_method(&mut self, class: &Rc<Class>, slot: usize, live: &[ObjectRef]) -> Result<ObjectRef, Flow> {
+        let method = class.methods[slot].clone();
+        let parsed = method.parsed_descriptor.clone().expect("a method with a malformed descriptor");
+        let parameters: Vec<String> =
+            parsed.parameters.iter().map(|parameter| type_class_name(Some(parameter))).collect();
+        let mut live = live.to_vec();
+        let parameters = self.class_array(&parameters, &live)?;
+        live.push(parameters);
+        let exceptions = self.class_array(&method.exceptions, &live)?;
+        live.push(exceptions);
+        let declaring = self.mirror_keeping(class, &live)?;
+        let signature = method.signature.as_deref().map(|signature| self.intern(signature)).transpose()?;
+        let constructor = method.name == "<init>";
+        let object_class = self.load(if constructor { CONSTRUCTOR } else { METHOD })?;
+        let name = self.intern(&method.name)?;
+        let object = self.allocate_keeping(Object::new(object_class), &live)?;
+        if !constructor {
+            let return_type = self.load(&type_class_name(parsed.return_type.as_ref()))?;
+            let return_type = self.mirror_keeping(&return_type, &[&live[..], &[object]].concat())?;
+            self.heap[object].set_ref_field("returnType", "Ljava/lang/Class;", Some(return_type));
+            self.heap[object].set_ref_field("name", "Ljava/lang/String;", Some(name));
+        }
+        let object_ref = &self.heap[object];
+        object_ref.set_ref_field("clazz", "Ljava/lang/Class;", Some(declaring));
+        object_ref.set_int_field("slot", "I", slot as i32);
+        object_ref.set_ref_field("parameterTypes", "[Ljava/lang/Class;", Some(parameters));
+        object_ref.set_ref_field("exceptionTypes", "[Ljava/lang/Class;", Some(exceptions));
+        object_ref.set_int_field("modifiers", "I", (method.access_flags & METHOD_MODIFIERS) as i32);
+        object_ref.set_ref_field("signature", "Ljava/lang/String;", signature);
+        self.heap.write_barrier(object);
+        let attributes = &class.classfile.methods_info[slot].attribute_info;
+        let [annotations, parameter_annotations, default] = raw_annotations(attributes);
+        self.set_bytes_field(object, "annotations", annotations, &live)?;
+        self.set_bytes_field(object, "parameterAnnotations", parameter_annotations, &live)?;
+        if !constructor {
+            self.set_bytes_field(object, "annotationDefault", default, &live)?;
+        }
+        Ok(object)
+    }
+
+    // sets byte[] field `name` of `object` to a copy of `bytes`, leaving it null without them
+    fn set_bytes_field(
+        &mut self,
+        object: ObjectRef,
+        name: &str,
+        bytes: Option<&[u8]>,
+        live: &[ObjectRef],
+    ) -> Result<(), Flow> {
+        if let Some(bytes) = bytes {
+            let array = self.byte_array_keeping(bytes, &[live, &[object]].concat())?;
+            self.heap[object].set_ref_field(name, "[B", Some(array));
+            self.heap.write_barrier(object);
+        }
+        Ok(())
+    }
+
+    // a byte[] holding `bytes`, keeping the `live` objects like allocate_keeping
+    pub fn byte_array_keeping(&mut self, bytes: &[u8], live: &[ObjectRef]) -> Result<ObjectRef, Flow> {
+        let array = self.new_array_keeping("[B", &[bytes.len()], &mut live.to_vec())?;
+        *self.heap[array].fields.borrow_mut() = bytes.iter().map(|byte| Slot::Int(*byte as i8 as i32)).collect();
+        Ok(array)
+    }
+
+    // the class and field a java.lang.reflect.Field refers to
+    pub fn reflected_field(&self, field: ObjectRef) -> (Rc<Class>, Rc<Field>) {
+        let (class, slot) = self.reflected(field);
+        let field = class.fields[slot].clone();
+        (class, field)
+    }
+
+    // the class and method a java.lang.reflect.Method or Constructor refers to
+    pub fn reflected_method(&self, method: ObjectRef) -> (Rc<Class>, Rc<Method>) {
+        let (class, slot) = self.reflected(method);
+        let method = class.methods[slot].clone();
+        (class, method)
+    }
+
+    pub(super) fn reflected(&self, member: ObjectRef) -> (Rc<Class>, usize) {
+        let object = &self.heap[member];
+        let class = object.get_ref_field("clazz", "Ljava/lang/Class;").expect("a member without a class");
+        (self.class_of_mirror(class), object.get_int_field("slot", "I") as usize)
+    }
+
+    // a Class[] of the classes named `names`
+    fn class_array(&mut self, names: &[String], live: &[ObjectRef]) -> Result<ObjectRef, Flow> {
+        let mut mirrors = Vec::new();
+        for name in names {
+            let class = self.load(name)?;
+            mirrors.push(Slot::Reference(Some(self.mirror_keeping(&class, live)?)));
+        }
+        let array = self.new_array_keeping("[Ljava/lang/Class;", &[mirrors.len()], &mut live.to_vec())?;
+        *self.heap[array].fields.borrow_mut() = mirrors;
+        self.heap.write_barrier(array);
+        Ok(array)
+    }
+
+    // hands the call of the native over to the accessor for member `name` of `class` by reference kind `kind`,
+    // spinning it into `cache` the first time
+    fn access(
+        &mut self,
+        class: &Class,
+        (name, descriptor): (&str, &str),
+        kind: u8,
+        cache: &RefCell<Option<(Rc<Class>, Rc<Method>)>>,
+        args: &LocalVars,
+    ) -> Flow {
+        let cached = cache.borrow().clone();
+        let (accessor, method) = match cached {
+            Some(accessor) => accessor,
+            None => {
+                let reference = MethodHandleRef {
+                    kind,
+                    class_name: class.name.clone(),
+                    name: String::from(name),
+                    descriptor: String::from(descriptor),
+                };
+                let accessor_name = self.loader.unique_name(&format!("{}$$Accessor$", class.name));
+                let content = match spin_accessor(&accessor_name, &reference) {
+                    Ok(content) => content,
+                    Err(message) => return internal_error(message),
+                };
+                let accessor = match self.loader.define_class(&accessor_name, content) {
+                    Ok(accessor) => accessor,
+                    Err(error) => return internal_error(error.to_string()),
+                };
+                let method = accessor.methods.iter().find(|method| method.name == ACCESSOR).cloned();
+                let accessor = (accessor.clone(), method.expect("an accessor without its method"));
+                *cache.borrow_mut() = Some(accessor.clone());
+                accessor
+            }
+        };
+        self.tail_call(accessor, method, args)
+    }
+
+    // whether `value` can be stored where type `descriptor` is expected, unboxed for a primitive type;
+    // primitive values are not widened
+    fn fits(&self, descriptor: &str, value: Option<ObjectRef>) -> Result<bool, Flow> {
+        Ok(match (box_class(descriptor), value) {
+            (Some(box_class), Some(value)) => self.heap[value].class.name == box_class,
+            (Some(_), None) => false,
+            (None, Some(value)) => {
+                let class = self.load(&type_class_name(parse_field_descriptor(descriptor).as_ref()))?;
+                self.heap[value].class.is_assignable_to(&class)
+            }
+            (None, None) => true,
+        })
+    }
+
+    // that the elements of Object[] `array`, null for none, are arguments for `method`
+    fn check_arguments(&self, method: &Method, array: Option<ObjectRef>) -> Result<(), Flow> {
+        let arguments: Vec<Option<ObjectRef>> = match array {
+            Some(array) => self.heap[array].fields.borrow().iter().map(Slot::reference).collect(),
+            None => Vec::new(),
+        };
+        let parameters = method.parsed_descriptor.as_ref().map_or(&[][..], |parsed| &parsed.parameters[..]);
+        if arguments.len() != parameters.len() {
+            return Err(illegal_argument("wrong number of arguments"));
+        }
+        for (parameter, argument) in parameters.iter().zip(arguments) {
+            if !self.fits(&parameter.descriptor(), argument)? {
+                return Err(illegal_argument("argument type mismatch"));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Method.invoke of `method`, with the receiver and the Object[] of arguments the second and third arguments
+pub(super) fn invoke(env: &mut Env, method: Option<ObjectRef>, args: &LocalVars) -> Flow {
+    let Some(method) = method else {
+        return Flow::Throw(None);
+    };
+    let (class, method) = env.reflected_method(method);
+    if !method.is_static() {
+        match args.get_ref(1) {
+            None => return Flow::Throw(None),
+            Some(receiver) if !env.heap[receiver].class.is_assignable_to(&class) => {
+                return illegal_argument("object is not an instance of declaring class")
+            }
+            Some(_) => {}
+        }
+    }
+    if let Err(flow) = env.check_arguments(&method, args.get_ref(2)) {
+        return flow;
+    }
+    let kind = if method.is_static() {
+        REF_INVOKE_STATIC
+    } else if method.is_private() {
+        REF_INVOKE_SPECIAL
+    } else if class.is_interface() {
+        REF_INVOKE_INTERFACE
+    } else {
+        REF_INVOKE_VIRTUAL
+    };
+    env.access(&class, (&method.name, &method.descriptor), kind, &method.accessor, args)
+}
+
+// Constructor.newInstance of `constructor`, with the Object[] of arguments the second argument
+pub(super) fn new_instance(env: &mut Env, constructor: Option<ObjectRef>, args: &LocalVars) -> Flow {
+    let Some(constructor) = constructor else {
+        return Flow::Throw(None);
+    };
+    let (class, method) = env.reflected_method(constructor);
+    if class.is_abstract() {
+        return Flow::Raise { class_name: "java/lang/InstantiationException", message: None };
+    }
+    if let Err(flow) = env.check_arguments(&method, args.get_ref(1)) {
+        return flow;
+    }
+    env.access(&class, (&method.name, &method.descriptor), REF_NEW_INVOKE_SPECIAL, &method.accessor, args)
+}
+
+// Field.get of the Field that is the first argument, from the object that is the second
+pub(super) fn get_field(env: &mut Env, args: &LocalVars) -> Flow {
+    let (class, field) = env.reflected_field(super::this(args));
+    if let Some(flow) = check_receiver(env, &class, &field, args.get_ref(1)) {
+        return flow;
+    }
+    let kind = if field.is_static() { REF_GET_STATIC } else { REF_GET_FIELD };
+    env.access(&class, (&field.name, &field.descriptor), kind, &field.getter, args)
+}
+
+// Field.set of the Field that is the first argument, in the object that is the second, to the third
+pub(super) fn set_field(env: &mut Env, args: &LocalVars) -> Flow {
+    let (class, field) = env.reflected_field(super::this(args));
+    if let Some(flow) = check_receiver(env, &class, &field, args.get_ref(1)) {
+        return flow;
+    }
+    if field.is_static() && field.is_final() {
+        let message = format!("Can not set static final field {}.{}", class.java_name(), field.name);
+        return Flow::Raise { class_name: "java/lang/IllegalAccessException", message: Some(message) };
+    }
+    match env.fits(&field.descriptor, args.get_ref(2)) {
+        Ok(true) => {}
+        Ok(false) => return field_mismatch(env, &class, &field, args.get_ref(2)),
+        Err(flow) => return flow,
+    }
+    let kind = if field.is_static() { REF_PUT_STATIC } else { REF_PUT_FIELD };
+    env.access(&class, (&field.name, &field.descriptor), kind, &field.setter, args)
+}
+
+// the NullPointerException or IllegalArgumentException for reading or writing an instance field of `object`
+fn check_receiver(env: &Env, class: &Class, field: &Field, object: Option<ObjectRef>) -> Option<Flow> {
+    match object {
+        _ if field.is_static() => None,
+        None => Some(Flow::Throw(None)),
+        Some(object) if !env.heap[object].class.is_assignable_to(class) => {
+            Some(field_mismatch(env, class, field, Some(object)))
+        }
+        Some(_) => None,
+    }
+}
+
+// the IllegalArgumentException for `value` not going with `field`, as the object or as the value
+fn field_mismatch(env: &Env, class: &Class, field: &Field, value: Option<ObjectRef>) -> Flow {
+    let field_type = type_class_name(parse_field_descriptor(&field.descriptor).as_ref()).replace('/', ".");
+    let value = match value {
+        Some(value) => env.heap[value].class.java_name(),
+        None => String::from("null value"),
+    };
+    let message = format!("Can not set {} field {}.{} to {}", field_type, class.java_name(), field.name, value);
+    illegal_argument(&message)
+}
+
+// the class whose constant pool a ConstantPool native reads, and the index it reads at
+fn constant_at(env: &Env, args: &LocalVars) -> (Rc<Class>, usize) {
+    let class = env.class_of_mirror(args.get_ref(1).expect("a ConstantPool without a class"));
+    (class, args.get_int(2) as usize)
+}
+
+fn get_int_at(env: &mut Env, args: &LocalVars) -> Flow {
+    let (class, index) = constant_at(env, args);
+    match class.classfile.constant_pool.get(index) {
+        Some(ConstantInteger { value }) => Flow::Return(vec![Slot::Int(*value as i32)]),
+        _ => wrong_constant_type(),
+    }
+}
+
+fn get_long_at(env: &mut Env, args: &LocalVars) -> Flow {
+    let (class, index) = constant_at(env, args);
+    match class.classfile.constant_pool.get(index) {
+        Some(ConstantLong { value }) => Flow::Return(Slot::wide(*value, false).to_vec()),
+        _ => wrong_constant_type(),
+    }
+}
+
+fn get_float_at(env: &mut Env, args: &LocalVars) -> Flow {
+    let (class, index) = constant_at(env, args);
+    match class.classfile.constant_pool.get(index) {
+        Some(ConstantFloat { value }) => Flow::Return(vec![Slot::Float(f32::from_bits(*value))]),
+        _ => wrong_constant_type(),
+    }
+}
+
+fn get_double_at(env: &mut Env, args: &LocalVars) -> Flow {
+    let (class, index) = constant_at(env, args);
+    match class.classfile.constant_pool.get(index) {
+        Some(ConstantDouble { value }) => Flow::Return(Slot::wide(*value, true).to_vec()),
+        _ => wrong_constant_type(),
+    }
+}
+
+fn get_utf8_at(env: &mut Env, args: &LocalVars) -> Flow {
+    let (class, index) = constant_at(env, args);
+    match get_utf8(&class.classfile.constant_pool, &(index as u16)) {
+        Some(value) => return_object(env.intern(&value)),
+        None => wrong_constant_type(),
+    }
+}
+
+fn get_string_at(env: &mut Env, args: &LocalVars) -> Flow {
+    let (class, index) = constant_at(env, args);
+    let value = match class.classfile.constant_pool.get(index) {
+        Some(ConstantString { index }) => get_utf8(&class.classfile.constant_pool, index),
+        _ => None,
+    };
+    match value {
+        Some(value) => return_object(env.intern(&value)),
+        None => wrong_constant_type(),
+    }
+}
+
+fn get_class_at(env: &mut Env, args: &LocalVars) -> Flow {
+    let (class, index) = constant_at(env, args);
+    let Some(name) = get_class_name(&class.classfile.constant_pool, &(index as u16)) else {
+        return wrong_constant_type();
+    };
+    match env.load(&name) {
+        Ok(class) => return_object(env.mirror(&class)),
+        Err(flow) => flow,
+    }
+}
+
+fn wrong_constant_type() -> Flow {
+    illegal_argument("Wrong type at constant pool index")
+}
+
+// the class of whoever called the caller-sensitive method that asks, the frame of that method being the
+// innermost one; reflection frames in between do not count
+fn get_caller_class(env: &mut Env, _: &LocalVars) -> Flow {
+    let caller = env
+        .thread
+        .frame_classes()
+        .skip(1)
+        .find(|class| class.name != "java/lang/reflect/Method" && !class.name.starts_with("jdk/internal/reflect/"))
+        .cloned();
+    match caller {
+        Some(class) => match env.mirror(&class) {
+            Ok(mirror) => Flow::Return(vec![Slot::Reference(Some(mirror))]),
+            Err(flow) => flow,
+        },
+        None => Flow::Return(vec![Slot::Reference(None)]),
+    }
+}
+
+fn get_class_access_flags(env: &mut Env, args: &LocalVars) -> Flow {
+    let class = env.class_of_mirror(args.get_ref(0).expect("getClassAccessFlags(null)"));
+    Flow::Return(vec![Slot::Int(class.classfile.access_flags as i32)])
+}
+
+// without NestHost attributes a class is only a nest mate of itself
+fn are_nest_mates(_: &mut Env, args: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int((args.get_ref(0) == args.get_ref(1)) as i32)])
+}
+
+// an array with elements of the class the first argument stands for
+fn new_array(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(component) = args.get_ref(0) else {
+        return Flow::Throw(None);
+    };
+    let component = env.class_of_mirror(component);
+    let length = args.get_int(1);
+    let class_name = match PRIMITIVE_TYPES.iter().find(|(name, _)| *name == component.name) {
+        Some((_, "V")) => return Flow::Raise { class_name: "java/lang/IllegalArgumentException", message: None },
+        Some((_, descriptor)) => format!("[{}", descriptor),
+        None if component.is_array() => format!("[{}", component.name),
+        None => format!("[L{};", component.name),
+    };
+    if length < 0 {
+        return Flow::Raise { class_name: "java/lang/NegativeArraySizeException", message: Some(length.to_string()) };
+    }
+    match env.new_array(&class_name, &[length as usize]) {
+        Ok(array) => Flow::Return(vec![Slot::Reference(Some(array))]),
+        Err(flow) => flow,
+    }
+}
+
+fn get_length(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(array) = args.get_ref(0) else {
+        return Flow::Throw(None);
+    };
+    let array = &env.heap[array];
+    if !array.class.is_array() {
+        return illegal_argument("Argument is not an array");
+    }
+    Flow::Return(vec![Slot::Int(array.array_length() as i32)])
+}
@@ -0,0 +1,104 @@
+use crate::instructions::Flow;
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::string::{decode, encode};
+use crate::runtime::{LocalVars, Object, Slot};
+
+use super::{this, Env, NativeRegistry};
+
+const STRING: &str = "java/lang/String";
+
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(STRING, "intern", "()Ljava/lang/String;", intern);
+}
+
+// strings are laid out the way the String class on the classpath expects: a char[] value up to JDK 8,
+// a byte[] value and a coder saying how the chars are packed into it from JDK 9 on
+impl Env<'_> {
+    // a new java.lang.String with the contents of `value`
+    pub fn new_string(&mut self, value: &str) -> Result<ObjectRef, Flow> {
+        self.new_string_keeping(&value.encode_utf16().collect::<Vec<_>>(), &[])
+    }
+
+    // the one String with the contents of `value` that string constants and String.intern share
+    pub fn intern(&mut self, value: &str) -> Result<ObjectRef, Flow> {
+        let chars: Vec<u16> = value.encode_utf16().collect();
+        if let Some(string) = self.strings.get(&chars) {
+            return Ok(string);
+        }
+        let string = self.new_string_keeping(&chars, &[])?;
+        self.strings.insert(chars, string);
+        Ok(string)
+    }
+
+    // a String[] holding new strings with the given contents, like the arguments main gets
+    pub fn new_string_array(&mut self, values: &[String]) -> Result<ObjectRef, Flow> {
+        let array = self.new_array("[Ljava/lang/String;", &[values.len()])?;
+        for (index, value) in values.iter().enumerate() {
+            let string = self.new_string_keeping(&value.encode_utf16().collect::<Vec<_>>(), &[array])?;
+            self.heap[array].fields.borrow_mut()[index] = Slot::Reference(Some(string));
+            self.heap.write_barrier(array);
+        }
+        Ok(array)
+    }
+
+    // the contents of a String as UTF-16 chars
+    pub fn string_chars(&self, string: ObjectRef) -> Vec<u16> {
+        let object = &self.heap[string];
+        if object.class.find_field("value", "[C").is_some() {
+            let array = object.get_ref_field("value", "[C").expect("a String without a value");
+            return self.heap[array].fields.borrow().iter().map(|char| char.int() as u16).collect();
+        }
+        let array = object.get_ref_field("value", "[B").expect("a String without a value");
+        let bytes: Vec<i8> = self.heap[array].fields.borrow().iter().map(|byte| byte.int() as i8).collect();
+        decode(&bytes, object.get_int_field("coder", "B"))
+    }
+
+    // the contents of a String as a Rust string, with unpaired surrogates replaced
+    pub fn rust_string(&self, string: ObjectRef) -> String {
+        String::from_utf16_lossy(&self.string_chars(string))
+    }
+
+    // new_string for natives holding on to objects nothing refers to yet, like allocate_keeping
+    pub(crate) fn new_string_keeping(&mut self, chars: &[u16], live: &[ObjectRef]) -> Result<ObjectRef, Flow> {
+        let class = self.load(STRING)?;
+        let (array, coder) = if class.find_field("value", "[C").is_some() {
+            let array = self.new_array_keeping("[C", &[chars.len()], &mut live.to_vec())?;
+            *self.heap[array].fields.borrow_mut() = chars.iter().map(|char| Slot::Int(*char as i32)).collect();
+            (array, None)
+        } else {
+            // COMPACT_STRINGS is false until String.<clinit> runs, and String methods go by it too
+            let compact = class
+                .find_field("COMPACT_STRINGS", "Z")
+                .is_some_and(|(class, field)| class.static_fields.borrow()[field.slot].int() != 0);
+            let (bytes, coder) = encode(chars, compact);
+            let array = self.new_array_keeping("[B", &[bytes.len()], &mut live.to_vec())?;
+            *self.heap[array].fields.borrow_mut() = bytes.into_iter().map(|byte| Slot::Int(byte as i32)).collect();
+            (array, Some(coder))
+        };
+        let string = self.allocate_keeping(Object::new(class), &[live, &[array]].concat())?;
+        let object = &self.heap[string];
+        match coder {
+            None => object.set_ref_field("value", "[C", Some(array)),
+            Some(coder) => {
+                object.set_ref_field("value", "[B", Some(array));
+                object.set_int_field("coder", "B", coder);
+            }
+        }
+        self.heap.write_barrier(string);
+        Ok(string)
+    }
+}
+
+// the interned String with the same contents, which is this one when there was none yet
+fn intern(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    let chars = env.string_chars(this);
+    let string = match env.strings.get(&chars) {
+        Some(string) => string,
+        None => {
+            env.strings.insert(chars, this);
+            this
+        }
+    };
+    Flow::Return(vec![Slot::Reference(Some(string))])
+}
@@ -0,0 +1,287 @@
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::instructions::Flow;
+use crate::jni;
+use crate::runtime::{LocalVars, Slot};
+
+use super::{empty, Env, NativeRegistry};
+
+const SYSTEM: &str = "java/lang/System";
+const RUNTIME: &str = "java/lang/Runtime";
+const SHUTDOWN: &str = "java/lang/Shutdown";
+const SYSTEM_PROPS: &str = "jdk/internal/util/SystemProps$Raw";
+const FLOAT: &str = "java/lang/Float";
+const DOUBLE: &str = "java/lang/Double";
+
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(SYSTEM, "identityHashCode", "(Ljava/lang/Object;)I", identity_hash_code);
+    registry.register(SYSTEM, "arraycopy", "(Ljava/lang/Object;ILjava/lang/Object;II)V", arraycopy);
+    registry.register(SYSTEM, "nanoTime", "()J", nano_time);
+    registry.register(SYSTEM, "currentTimeMillis", "()J", current_time_millis);
+    registry.register(SYSTEM, "setIn0", "(Ljava/io/InputStream;)V", set_in);
+    registry.register(SYSTEM, "setOut0", "(Ljava/io/PrintStream;)V", set_out);
+    registry.register(SYSTEM, "setErr0", "(Ljava/io/PrintStream;)V", set_err);
+    registry.register(SYSTEM_PROPS, "vmProperties", "()[Ljava/lang/String;", vm_properties);
+    registry.register(SYSTEM_PROPS, "platformProperties", "()[Ljava/lang/String;", platform_properties);
+    registry.register(RUNTIME, "availableProcessors", "()I", available_processors);
+    registry.register(RUNTIME, "maxMemory", "()J", max_memory);
+    registry.register(RUNTIME, "totalMemory", "()J", max_memory);
+    registry.register(RUNTIME, "freeMemory", "()J", free_memory);
+    registry.register(RUNTIME, "gc", "()V", gc);
+    registry.register(SHUTDOWN, "beforeHalt", "()V", empty);
+    registry.register(SHUTDOWN, "halt0", "(I)V", halt);
+    registry.register(FLOAT, "floatToRawIntBits", "(F)I", float_to_raw_int_bits);
+    registry.register(FLOAT, "intBitsToFloat", "(I)F", int_bits_to_float);
+    registry.register(DOUBLE, "doubleToRawLongBits", "(D)J", double_to_raw_long_bits);
+    registry.register(DOUBLE, "longBitsToDouble", "(J)D", long_bits_to_double);
+    registry.register("java/lang/StringUTF16", "isBigEndian", "()Z", is_big_endian);
+}
+
+// the hash Object.hashCode would return even when the class overrides it, 0 for null
+fn identity_hash_code(env: &mut Env, args: &LocalVars) -> Flow {
+    let hash = args.get_ref(0).map_or(0, |object| env.heap[object].identity_hash(env.thread));
+    Flow::Return(vec![Slot::Int(hash)])
+}
+
+// copies like memmove, so overlapping ranges of the same array work; between reference arrays of different
+// types every element is checked, and the ones before the first that does not fit stay copied
+fn arraycopy(env: &mut Env, args: &LocalVars) -> Flow {
+    let (Some(src), Some(dest)) = (args.get_ref(0), args.get_ref(2)) else {
+        return Flow::Throw(None);
+    };
+    let (src_pos, dest_pos, length) = (args.get_int(1), args.get_int(3), args.get_int(4));
+    let (src_class, dest_class) = (env.heap[src].class.clone(), env.heap[dest].class.clone());
+    for (name, class) in [("source", &src_class), ("destination", &dest_class)] {
+        if !class.is_array() {
+            return array_store(format!("arraycopy: {} type {} is not an array", name, class.java_name()));
+        }
+    }
+    let references = src_class.name[1..].starts_with(['L', '[']);
+    if references != dest_class.name[1..].starts_with(['L', '[']) || !references && src_class.name != dest_class.name {
+        return array_store(format!(
+            "arraycopy: type mismatch: can not copy {} into {}",
+            src_class.java_name(),
+            dest_class.java_name()
+        ));
+    }
+    let (src_length, dest_length) = (env.heap[src].array_length(), env.heap[dest].array_length());
+    for (name, position, array_length) in [("source", src_pos, src_length), ("destination", dest_pos, dest_length)] {
+        let message = if position < 0 {
+            format!("arraycopy: {} index {} out of bounds for length {}", name, position, array_length)
+        } else if length < 0 {
+            format!("arraycopy: length {} is negative", length)
+        } else if position as usize + length as usize > array_length {
+            format!("arraycopy: last {} index {} out of bounds for length {}", name, position + length, array_length)
+        } else {
+            continue;
+        };
+        return Flow::Raise { class_name: "java/lang/ArrayIndexOutOfBoundsException", message: Some(message) };
+    }
+    let width = Slot::zero(&src_class.name[1..]).len();
+    let (src_pos, dest_pos) = (src_pos as usize * width, dest_pos as usize * width);
+    let mut elements = env.heap[src].fields.borrow()[src_pos..src_pos + length as usize * width].to_vec();
+    let mut stored = Ok(());
+    if references && !src_class.is_assignable_to(&dest_class) {
+        let component = dest_class.component.as_ref().expect("a reference array class without a component");
+        let misfit = elements.iter().position(|element| {
+            element.reference().is_some_and(|object| !env.heap[object].class.is_assignable_to(component))
+        });
+        if let Some(index) = misfit {
+            stored = Err(array_store(format!(
+                "arraycopy: element type mismatch: can not cast one of the elements of {} \
+                 to the type of the destination array, {}",
+                src_class.java_name(),
+                component.java_name()
+            )));
+            elements.truncate(index);
+        }
+    }
+    env.heap[dest].fields.borrow_mut().splice(dest_pos..dest_pos + elements.len(), elements);
+    env.heap.write_barrier(dest);
+    match stored {
+        Ok(()) => Flow::Return(Vec::new()),
+        Err(flow) => flow,
+    }
+}
+
+fn array_store(message: String) -> Flow {
+    Flow::Raise { class_name: "java/lang/ArrayStoreException", message: Some(message) }
+}
+
+// the scheduler's virtual time, which Thread.sleep and timed waits go by as well
+fn nano_time(env: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(Slot::wide(env.scheduler.now(), false).to_vec())
+}
+
+fn current_time_millis(_: &mut Env, _: &LocalVars) -> Flow {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_millis() as u64);
+    Flow::Return(Slot::wide(millis, false).to_vec())
+}
+
+// System.in, out and err are static finals that only the VM may set
+fn set_in(env: &mut Env, args: &LocalVars) -> Flow {
+    set_stream(env, "in", "Ljava/io/InputStream;", args)
+}
+
+fn set_out(env: &mut Env, args: &LocalVars) -> Flow {
+    set_stream(env, "out", "Ljava/io/PrintStream;", args)
+}
+
+fn set_err(env: &mut Env, args: &LocalVars) -> Flow {
+    set_stream(env, "err", "Ljava/io/PrintStream;", args)
+}
+
+fn set_stream(env: &mut Env, name: &str, descriptor: &str, args: &LocalVars) -> Flow {
+    let system = match env.load(SYSTEM) {
+        Ok(system) => system,
+        Err(flow) => return flow,
+    };
+    let (_, field) = system.find_field(name, descriptor).expect("java.lang.System declares its streams");
+    system.static_fields.borrow_mut()[field.slot] = Slot::Reference(args.get_ref(0));
+    Flow::Return(Vec::new())
+}
+
+// key, value, key, value, ...: what the command line defined, and what describes the VM
+fn vm_properties(env: &mut Env, _: &LocalVars) -> Flow {
+    let java_home = env.properties.iter().find(|(key, _)| key == "java.home").map(|(_, value)| value.clone());
+    let vm = [
+        ("java.vm.name", String::from("LearnJVM")),
+        ("java.vm.vendor", String::from("LearnJVM")),
+        ("java.vm.version", String::from(env!("CARGO_PKG_VERSION"))),
+        ("java.vm.info", String::from("interpreted mode")),
+        ("java.vm.specification.name", String::from("Java Virtual Machine Specification")),
+        ("java.vm.specification.vendor", String::from("Oracle Corporation")),
+        ("jdk.debug", String::from("release")),
+        ("sun.java.launcher", String::from("SUN_STANDARD")),
+        ("java.library.path", jni::library_path(env.properties)),
+        ("sun.boot.library.path", java_home.map(|home| home + "/lib").unwrap_or_default()),
+    ];
+    let properties: Vec<String> = env
+        .properties
+        .iter()
+        .cloned()
+        .chain(vm.into_iter().map(|(key, value)| (String::from(key), value)))
+        .flat_map(|(key, value)| [key, value])
+        .collect();
+    match env.new_string_array(&properties) {
+        Ok(array) => Flow::Return(vec![Slot::Reference(Some(array))]),
+        Err(flow) => flow,
+    }
+}
+
+// the properties of the platform, each at the index the _<name>_NDX constant of SystemProps$Raw gives it,
+// e.g. _file_encoding_NDX for file.encoding; the ones left null are not set
+fn platform_properties(env: &mut Env, _: &LocalVars) -> Flow {
+    let raw = match env.load(SYSTEM_PROPS) {
+        Ok(raw) => raw,
+        Err(flow) => return flow,
+    };
+    let statics = raw.static_fields.borrow();
+    let (_, length) = raw.find_field("FIXED_LENGTH", "I").expect("SystemProps$Raw declares FIXED_LENGTH");
+    let mut properties = vec![None; statics[length.slot].int() as usize];
+    for field in &raw.fields {
+        let Some(name) = field.name.strip_prefix('_').and_then(|name| name.strip_suffix("_NDX")) else {
+            continue;
+        };
+        properties[statics[field.slot].int() as usize] = platform_property(&name.replace('_', "."));
+    }
+    drop(statics);
+    let array = match env.new_array("[Ljava/lang/String;", &[properties.len()]) {
+        Ok(array) => array,
+        Err(flow) => return flow,
+    };
+    for (index, value) in properties.into_iter().enumerate() {
+        let Some(value) = value else {
+            continue;
+        };
+        match env.new_string_keeping(&value.encode_utf16().collect::<Vec<_>>(), &[array]) {
+            Ok(string) => {
+                env.heap[array].fields.borrow_mut()[index] = Slot::Reference(Some(string));
+                env.heap.write_barrier(array);
+            }
+            Err(flow) => return flow,
+        }
+    }
+    Flow::Return(vec![Slot::Reference(Some(array))])
+}
+
+fn platform_property(name: &str) -> Option<String> {
+    let value = match name {
+        "file.encoding" | "sun.jnu.encoding" | "native.encoding" => "UTF-8",
+        "file.separator" => "/",
+        "line.separator" => "\n",
+        "path.separator" => ":",
+        "os.name" => std::env::consts::OS,
+        "os.arch" => match std::env::consts::ARCH {
+            "x86_64" => "amd64",
+            arch => arch,
+        },
+        "os.version" => {
+            return std::fs::read_to_string("/proc/sys/kernel/osrelease").ok().map(|release| release.trim().to_string())
+        }
+        "java.io.tmpdir" => "/tmp",
+        "user.dir" => return std::env::current_dir().ok().map(|dir| dir.to_string_lossy().into_owned()),
+        // what HotSpot reports when it cannot look the user up
+        "user.home" => return Some(std::env::var("HOME").unwrap_or_else(|_| String::from("?"))),
+        "user.name" => {
+            return Some(
+                std::env::var("USER").or_else(|_| std::env::var("LOGNAME")).unwrap_or_else(|_| String::from("?")),
+            )
+        }
+        "sun.cpu.endian" if cfg!(target_endian = "big") => "big",
+        "sun.cpu.endian" => "little",
+        "sun.io.unicode.encoding" if cfg!(target_endian = "big") => "UnicodeBig",
+        "sun.io.unicode.encoding" => "UnicodeLittle",
+        "sun.arch.data.model" => "64",
+        _ => return None,
+    };
+    Some(String::from(value))
+}
+
+// the scheduler runs every Java thread on one OS thread
+fn available_processors(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int(1)])
+}
+
+fn max_memory(env: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(Slot::wide(env.heap.capacity() as u64, false).to_vec())
+}
+
+fn free_memory(env: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(Slot::wide((env.heap.capacity() - env.heap.used()) as u64, false).to_vec())
+}
+
+pub(super) fn gc(env: &mut Env, _: &LocalVars) -> Flow {
+    env.collect();
+    Flow::Return(Vec::new())
+}
+
+// the end of System.exit and of the last non-daemon thread, once the shutdown hooks have run
+pub(super) fn halt(env: &mut Env, args: &LocalVars) -> Flow {
+    env.heap.log_stats(&mut *env.output.stdout);
+    let _ = env.output.stdout.flush();
+    let _ = env.output.stderr.flush();
+    std::process::exit(args.get_int(0))
+}
+
+fn float_to_raw_int_bits(_: &mut Env, args: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int(args.get_float(0).to_bits() as i32)])
+}
+
+fn int_bits_to_float(_: &mut Env, args: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Float(f32::from_bits(args.get_int(0) as u32))])
+}
+
+fn double_to_raw_long_bits(_: &mut Env, args: &LocalVars) -> Flow {
+    Flow::Return(Slot::wide(args.get_double(0).to_bits(), false).to_vec())
+}
+
+fn long_bits_to_double(_: &mut Env, args: &LocalVars) -> Flow {
+    Flow::Return(Slot::wide(args.get_long(0) as u64, true).to_vec())
+}
+
+// the VM lays out the UTF-16 bytes of strings low byte first, whatever the machine, see runtime::string
+fn is_big_endian(_: &mut Env, _: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int(0)])
+}
@@ -0,0 +1,114 @@
+use crate::instructions::Flow;
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{Frame, LocalVars, Object, Slot, ThreadState, THREAD_STATUS_RUNNABLE};
+
+use super::{deadline, empty, negative_timeout, this, Env, NativeRegistry, NANOS_PER_MILLI};
+
+const THREAD: &str = "java/lang/Thread";
+const NORM_PRIORITY: i32 = 5;
+
+// java.lang.Thread on top of the scheduler; join waits in the scheduler instead of on the Thread object
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(THREAD, "currentThread", "()Ljava/lang/Thread;", current_thread);
+    registry.register(THREAD, "start0", "()V", start);
+    registry.register(THREAD, "isAlive", "()Z", is_alive);
+    registry.register(THREAD, "join", "(J)V", join);
+    registry.register(THREAD, "sleep", "(J)V", sleep);
+    registry.register(THREAD, "yield", "()V", yield_now);
+    registry.register(THREAD, "holdsLock", "(Ljava/lang/Object;)Z", holds_lock);
+    // the scheduler has no priorities, and there are no OS threads to name
+    registry.register(THREAD, "setPriority0", "(I)V", empty);
+    registry.register(THREAD, "setNativeName", "(Ljava/lang/String;)V", empty);
+    registry.register(THREAD, "interrupt0", "()V", interrupt);
+    registry.register(THREAD, "clearInterruptEvent", "()V", empty);
+}
+
+impl Env<'_> {
+    // the java.lang.Thread object of the running thread, created on first use for the main thread
+    pub fn thread_object(&mut self) -> Result<ObjectRef, Flow> {
+        if let Some(object) = self.thread.object {
+            return Ok(object);
+        }
+        let object = Object::new(self.load(THREAD)?);
+        object.set_int_field("daemon", "Z", self.thread.daemon as i32);
+        object.set_int_field("priority", "I", NORM_PRIORITY);
+        object.set_long_field("eetop", self.thread.id as i64);
+        object.set_int_field("threadStatus", "I", THREAD_STATUS_RUNNABLE);
+        let object = self.allocate(object)?;
+        self.thread.object = Some(object);
+        Ok(object)
+    }
+}
+
+fn current_thread(env: &mut Env, _: &LocalVars) -> Flow {
+    match env.thread_object() {
+        Ok(object) => Flow::Return(vec![Slot::Reference(Some(object))]),
+        Err(flow) => flow,
+    }
+}
+
+// Thread.start has checked that the thread was not started before
+fn start(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    let object = &env.heap[this];
+    let (class, run) = object.class.find_method("run", "()V").expect("java.lang.Thread declares run()");
+    let mut frame = Frame::new(class, run);
+    frame.local_vars.set_ref(0, Some(this));
+    let name = object.get_ref_field("name", "Ljava/lang/String;").expect("a Thread without a name");
+    let daemon = object.get_int_field("daemon", "Z") != 0;
+    let name = env.rust_string(name);
+    match env.scheduler.spawn(name, daemon, Some(this), frame) {
+        Ok(id) => {
+            let this = &env.heap[this];
+            this.set_long_field("eetop", id as i64);
+            this.set_int_field("threadStatus", "I", THREAD_STATUS_RUNNABLE);
+            Flow::Return(Vec::new())
+        }
+        Err(error) => Flow::Raise { class_name: "java/lang/OutOfMemoryError", message: Some(error.to_string()) },
+    }
+}
+
+// from start until the last frame of the thread returns
+fn is_alive(env: &mut Env, args: &LocalVars) -> Flow {
+    Flow::Return(vec![Slot::Int((env.heap[this(args)].get_long_field("eetop") != 0) as i32)])
+}
+
+// Thread.interrupt has set the interrupted field; a thread in Object.wait wakes up as if spuriously
+fn interrupt(env: &mut Env, args: &LocalVars) -> Flow {
+    let id = env.heap[this(args)].get_long_field("eetop") as u64;
+    env.scheduler.wake(id);
+    Flow::Return(Vec::new())
+}
+
+// join(0) waits for as long as it takes
+fn join(env: &mut Env, args: &LocalVars) -> Flow {
+    let Some(until) = deadline(env.scheduler, args.get_long(1)) else {
+        return negative_timeout();
+    };
+    let id = env.heap[this(args)].get_long_field("eetop") as u64;
+    if id != 0 {
+        env.thread.state = ThreadState::Joining { thread: id, until };
+    }
+    Flow::Return(Vec::new())
+}
+
+fn sleep(env: &mut Env, args: &LocalVars) -> Flow {
+    let Ok(millis) = u64::try_from(args.get_long(0)) else {
+        return negative_timeout();
+    };
+    env.thread.state = ThreadState::Sleeping { until: env.scheduler.now() + millis * NANOS_PER_MILLI };
+    Flow::Return(Vec::new())
+}
+
+// sleeping until now puts the thread at the back of the run queue
+fn yield_now(env: &mut Env, _: &LocalVars) -> Flow {
+    env.thread.state = ThreadState::Sleeping { until: env.scheduler.now() };
+    Flow::Return(Vec::new())
+}
+
+fn holds_lock(env: &mut Env, args: &LocalVars) -> Flow {
+    match args.get_ref(0) {
+        Some(object) => Flow::Return(vec![Slot::Int(env.heap[object].monitor.is_owned_by(env.thread.id) as i32)]),
+        None => Flow::Throw(None),
+    }
+}
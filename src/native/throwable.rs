@@ -0,0 +1,87 @@
+use crate::instructions::Flow;
+use crate::runtime::heap::ObjectRef;
+use crate::runtime::{LocalVars, Slot};
+
+use super::{this, Env, NativeRegistry};
+
+const THROWABLE: &str = "java/lang/Throwable";
+const STACK_TRACE_ELEMENT: &str = "java/lang/StackTraceElement";
+// StackTraceElement.lineNumber of a native method
+const NATIVE_LINE_NUMBER: i32 = -2;
+
+pub(super) fn register(registry: &mut NativeRegistry) {
+    registry.register(THROWABLE, "fillInStackTrace", "(I)Ljava/lang/Throwable;", fill_in_stack_trace);
+    registry.register(
+        STACK_TRACE_ELEMENT,
+        "initStackTraceElements",
+        "([Ljava/lang/StackTraceElement;Ljava/lang/Throwable;)V",
+        init_stack_trace_elements,
+    );
+}
+
+// the stack where the throwable was created: the frames of fillInStackTrace and of the constructors
+// of the throwable are left out, like HotSpot does
+fn fill_in_stack_trace(env: &mut Env, args: &LocalVars) -> Flow {
+    let this = this(args);
+    fill_in(env, this);
+    Flow::Return(vec![Slot::Reference(Some(this))])
+}
+
+pub(super) fn fill_in(env: &mut Env, this: ObjectRef) {
+    let class = env.heap[this].class.clone();
+    let mut trace = env.thread.stack_trace();
+    let filling_in = trace.iter().take_while(|element| element.method_name == "fillInStackTrace").count();
+    let constructing = trace[filling_in..]
+        .iter()
+        .take_while(|element| {
+            element.method_name == "<init>" && class.is_subclass_of(&element.class_name.replace('.', "/"))
+        })
+        .count();
+    trace.drain(..filling_in + constructing);
+    let object = &env.heap[this];
+    if object.class.find_field("depth", "I").is_some() {
+        object.set_int_field("depth", "I", trace.len() as i32);
+    }
+    *object.stack_trace.borrow_mut() = trace;
+}
+
+// fills in the elements Throwable.getStackTrace made for the throwable's stack trace, one per frame
+fn init_stack_trace_elements(env: &mut Env, args: &LocalVars) -> Flow {
+    let (Some(elements), Some(throwable)) = (args.get_ref(0), args.get_ref(1)) else {
+        return Flow::Throw(None);
+    };
+    let frames: Vec<_> = env.heap[throwable]
+        .stack_trace
+        .borrow()
+        .iter()
+        .map(|frame| {
+            let line_number = match frame.line_number {
+                _ if frame.native => NATIVE_LINE_NUMBER,
+                Some(line_number) => line_number as i32,
+                None => -1,
+            };
+            (frame.class_name.clone(), frame.method_name.clone(), frame.file_name.clone(), line_number)
+        })
+        .collect();
+    let length = env.heap[elements].array_length();
+    for (index, (class_name, method_name, file_name, line_number)) in frames.into_iter().take(length).enumerate() {
+        let element = env.heap[elements].fields.borrow()[index].reference().expect("a null StackTraceElement");
+        let class = match env.load(&class_name.replace('.', "/")).and_then(|class| env.mirror(&class)) {
+            Ok(class) => class,
+            Err(flow) => return flow,
+        };
+        env.heap[element].set_ref_field("declaringClassObject", "Ljava/lang/Class;", Some(class));
+        let strings =
+            [("declaringClass", Some(class_name)), ("methodName", Some(method_name)), ("fileName", file_name)];
+        for (name, value) in strings {
+            let value = match value.map(|value| env.intern(&value)).transpose() {
+                Ok(value) => value,
+                Err(flow) => return flow,
+            };
+            env.heap[element].set_ref_field(name, "Ljava/lang/String;", value);
+        }
+        env.heap[element].set_int_field("lineNumber", "I", line_number);
+        env.heap.write_barrier(element);
+    }
+    Flow::Return(Vec::new())
+}
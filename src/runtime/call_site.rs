@@ -0,0 +1,1011 @@
+use std::rc::Rc;
+
+use crate::classfile::attribute::AttributeInfo::BootstrapMethodsAttribute;
+use crate::classfile::class_reader::{
+    get_class_name, get_member_ref, get_name_and_type, get_utf8, ACC_FINAL, ACC_PRIVATE, ACC_PUBLIC, ACC_STATIC,
+    ACC_SYNTHETIC,
+};
+use crate::classfile::constant_pool::ConstantInfo;
+use crate::classfile::descriptor::{parse_field_descriptor, parse_method_descriptor, FieldType};
+use crate::classfile::writer::{ClassDef, Constant, FieldDef, Handler, MethodDef, Op};
+use crate::classfile::ClassFile;
+use crate::error::Error;
+
+use super::class::{Class, Method};
+use super::heap::ObjectRef;
+
+// what an invokedynamic instruction was linked to (JVMS §5.4.3.6), kept per instruction
+#[derive(Clone)]
+pub enum CallSite {
+    // for the bootstraps the VM knows, a static method of a class it made up, taking the instruction's arguments
+    Method(Rc<Class>, Rc<Method>),
+    // the target of the CallSite object a bootstrap method returned, invoked with MethodHandle.invokeExact
+    Handle(ObjectRef),
+}
+
+pub const REF_GET_FIELD: u8 = 1;
+pub const REF_GET_STATIC: u8 = 2;
+pub const REF_PUT_FIELD: u8 = 3;
+pub const REF_PUT_STATIC: u8 = 4;
+pub const REF_INVOKE_VIRTUAL: u8 = 5;
+pub const REF_INVOKE_STATIC: u8 = 6;
+pub const REF_INVOKE_SPECIAL: u8 = 7;
+pub const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+pub const REF_INVOKE_INTERFACE: u8 = 9;
+
+// a CONSTANT_MethodHandle: the kind of reference and the field or method it refers to
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodHandleRef {
+    pub kind: u8,
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+// what a VarHandle the VM makes refers to
+#[derive(Debug, Clone, PartialEq)]
+pub enum VarHandleRef {
+    // a field of `class_name`, reached through an object unless it is static
+    Field {
+        class_name: String,
+        name: String,
+        descriptor: String,
+        is_static: bool,
+    },
+    // the elements of arrays of class `class_name`, e.g. [I
+    Array(String),
+}
+
+// the access modes of VarHandle by what they do; the plain, opaque, acquire, release and volatile variants
+// of each are the same with one thread running at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessMode {
+    Get,
+    Set,
+    CompareAndSet,
+    CompareAndExchange,
+    GetAndSet,
+    GetAndAdd,
+}
+
+// a static argument of a bootstrap method as the constant pool has it
+#[derive(Debug, Clone, PartialEq)]
+pub enum StaticArgument {
+    Int(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    String(String),
+    Class(String),
+    // the descriptor of a CONSTANT_MethodType
+    MethodType(String),
+    MethodHandle(MethodHandleRef),
+}
+
+// the bootstrap method of an invokedynamic instruction with the name and descriptor of its call site
+// and the static arguments to pass it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bootstrap {
+    pub method: MethodHandleRef,
+    pub name: String,
+    pub descriptor: String,
+    pub arguments: Vec<StaticArgument>,
+}
+
+// the bootstraps the VM links call sites for without running them: it writes the class
+// LambdaMetafactory would spin for a lambda, and one with a StringBuilder for string concatenation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FastPath {
+    Lambda,
+    Concat,
+}
+
+impl MethodHandleRef {
+    pub fn read(constant_pool: &[ConstantInfo], index: u16) -> Option<MethodHandleRef> {
+        let ConstantInfo::ConstantMethodHandle { ref_kind, ref_kind_index } = constant_pool.get(index as usize)? else {
+            return None;
+        };
+        let (class_name, name, descriptor) = get_member_ref(constant_pool, ref_kind_index)?;
+        Some(MethodHandleRef { kind: *ref_kind, class_name, name, descriptor })
+    }
+
+    // the descriptor of the MethodType of the handle: a field access takes the object and the value to store,
+    // a method call takes the receiver first and a constructor returns what it creates
+    pub fn type_descriptor(&self) -> String {
+        let (arguments, returned) = match self.descriptor.strip_prefix('(').and_then(|rest| rest.split_once(')')) {
+            Some((arguments, returned)) => (arguments, returned),
+            None => ("", self.descriptor.as_str()),
+        };
+        let class = format!("L{};", self.class_name);
+        match self.kind {
+            REF_GET_FIELD => format!("({}){}", class, self.descriptor),
+            REF_GET_STATIC => format!("(){}", self.descriptor),
+            REF_PUT_FIELD => format!("({}{})V", class, self.descriptor),
+            REF_PUT_STATIC => format!("({})V", self.descriptor),
+            REF_NEW_INVOKE_SPECIAL => format!("({}){}", arguments, class),
+            REF_INVOKE_STATIC => self.descriptor.clone(),
+            _ => format!("({}{}){}", class, arguments, returned),
+        }
+    }
+}
+
+impl VarHandleRef {
+    // the type of the variable
+    pub fn value_descriptor(&self) -> &str {
+        match self {
+            VarHandleRef::Field { descriptor, .. } => descriptor,
+            VarHandleRef::Array(class_name) => &class_name[1..],
+        }
+    }
+
+    // the descriptors of what locates the variable: the object for an instance field, the array
+    // and the index for an element
+    pub fn coordinates(&self) -> String {
+        match self {
+            VarHandleRef::Field { is_static: true, .. } => String::new(),
+            VarHandleRef::Field { class_name, .. } => format!("L{};", class_name),
+            VarHandleRef::Array(class_name) => format!("{}I", class_name),
+        }
+    }
+
+    // the descriptor a call of access mode `mode` has to have, e.g. (Ljava/lang/Object;II)Z for
+    // compareAndSet of an int field
+    pub fn access_descriptor(&self, mode: AccessMode) -> String {
+        let (coordinates, value) = (self.coordinates(), self.value_descriptor());
+        match mode {
+            AccessMode::Get => format!("({}){}", coordinates, value),
+            AccessMode::Set => format!("({}{})V", coordinates, value),
+            AccessMode::CompareAndSet => format!("({}{}{})Z", coordinates, value, value),
+            AccessMode::CompareAndExchange => format!("({}{}{}){}", coordinates, value, value, value),
+            AccessMode::GetAndSet | AccessMode::GetAndAdd => format!("({}{}){}", coordinates, value, value),
+        }
+    }
+}
+
+impl AccessMode {
+    // the access mode of VarHandle method `name`; None for the bitwise ones, which the VM does not do
+    pub fn from_name(name: &str) -> Option<AccessMode> {
+        let mode = match name {
+            "get" | "getVolatile" | "getOpaque" | "getAcquire" => AccessMode::Get,
+            "set" | "setVolatile" | "setOpaque" | "setRelease" => AccessMode::Set,
+            "compareAndSet"
+            | "weakCompareAndSet"
+            | "weakCompareAndSetPlain"
+            | "weakCompareAndSetAcquire"
+            | "weakCompareAndSetRelease" => AccessMode::CompareAndSet,
+            "compareAndExchange" | "compareAndExchangeAcquire" | "compareAndExchangeRelease" => {
+                AccessMode::CompareAndExchange
+            }
+            "getAndSet" | "getAndSetAcquire" | "getAndSetRelease" => AccessMode::GetAndSet,
+            "getAndAdd" | "getAndAddAcquire" | "getAndAddRelease" => AccessMode::GetAndAdd,
+            _ => return None,
+        };
+        Some(mode)
+    }
+}
+
+impl StaticArgument {
+    fn read(constant_pool: &[ConstantInfo], index: u16) -> Option<StaticArgument> {
+        let argument = match constant_pool.get(index as usize)? {
+            ConstantInfo::ConstantInteger { value } => StaticArgument::Int(*value as i32),
+            ConstantInfo::ConstantFloat { value } => StaticArgument::Float(f32::from_bits(*value)),
+            ConstantInfo::ConstantLong { value } => StaticArgument::Long(*value as i64),
+            ConstantInfo::ConstantDouble { value } => StaticArgument::Double(f64::from_bits(*value)),
+            ConstantInfo::ConstantString { index } => StaticArgument::String(get_utf8(constant_pool, index)?),
+            ConstantInfo::ConstantClass { .. } => StaticArgument::Class(get_class_name(constant_pool, &index)?),
+            ConstantInfo::ConstantMethodType { descriptor_index } => {
+                StaticArgument::MethodType(get_utf8(constant_pool, descriptor_index)?)
+            }
+            ConstantInfo::ConstantMethodHandle { .. } => {
+                StaticArgument::MethodHandle(MethodHandleRef::read(constant_pool, index)?)
+            }
+            _ => return None,
+        };
+        Some(argument)
+    }
+}
+
+impl Bootstrap {
+    // the call site of the CONSTANT_InvokeDynamic at `index` of `classfile`
+    pub fn read(classfile: &ClassFile, index: u16) -> Result<Bootstrap, Error> {
+        let constant_pool = &classfile.constant_pool;
+        let invalid = || Error::InvalidConstantIndex(index);
+        let Some(ConstantInfo::ConstantInvokeDynamic { bootstrap_method_attr_index, name_and_type_index }) =
+            constant_pool.get(index as usize)
+        else {
+            return Err(invalid());
+        };
+        let (name, descriptor) = get_name_and_type(constant_pool, name_and_type_index).ok_or_else(invalid)?;
+        let entry = classfile
+            .attributes_info
+            .iter()
+            .find_map(|attribute| match attribute {
+                BootstrapMethodsAttribute { boostrap_methods } => Some(boostrap_methods),
+                _ => None,
+            })
+            .and_then(|bootstrap_methods| bootstrap_methods.get(*bootstrap_method_attr_index as usize))
+            .ok_or_else(invalid)?;
+        let method = MethodHandleRef::read(constant_pool, entry.bootstrap_method_ref).ok_or_else(invalid)?;
+        let arguments = entry
+            .bootstrap_arguments
+            .iter()
+            .map(|index| StaticArgument::read(constant_pool, *index))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        Ok(Bootstrap { method, name, descriptor, arguments })
+    }
+
+    // the descriptor of the call site of the CONSTANT_InvokeDynamic at `index`, without reading its bootstrap
+    pub fn descriptor(classfile: &ClassFile, index: u16) -> Result<String, Error> {
+        let constant_pool = &classfile.constant_pool;
+        match constant_pool.get(index as usize) {
+            Some(ConstantInfo::ConstantInvokeDynamic { name_and_type_index, .. }) => {
+                get_name_and_type(constant_pool, name_and_type_index).map(|(_, descriptor)| descriptor)
+            }
+            _ => None,
+        }
+        .ok_or(Error::InvalidConstantIndex(index))
+    }
+
+    pub fn fast_path(&self) -> Option<FastPath> {
+        if self.method.kind != REF_INVOKE_STATIC {
+            return None;
+        }
+        match (self.method.class_name.as_str(), self.method.name.as_str()) {
+            ("java/lang/invoke/LambdaMetafactory", "metafactory" | "altMetafactory") => Some(FastPath::Lambda),
+            ("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants" | "makeConcat") => {
+                Some(FastPath::Concat)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FastPath {
+    // what the name of the class made up for a call site of `caller` starts with
+    pub fn class_prefix(self, caller: &str) -> String {
+        match self {
+            FastPath::Lambda => format!("{}$$Lambda$", caller),
+            FastPath::Concat => format!("{}$$StringConcat$", caller),
+        }
+    }
+
+    // the static method of the class the call site is linked to
+    pub fn method_name(self) -> &'static str {
+        match self {
+            FastPath::Lambda => "get$Lambda",
+            FastPath::Concat => "concat",
+        }
+    }
+
+    // the class file of class `class_name` for the call site, or the message of the BootstrapMethodError
+    // the bootstrap method would have thrown for it
+    pub fn spin(self, bootstrap: &Bootstrap, class_name: &str) -> Result<Vec<u8>, String> {
+        match self {
+            FastPath::Lambda => spin_lambda(bootstrap, class_name),
+            FastPath::Concat => spin_concat(bootstrap, class_name),
+        }
+    }
+}
+
+
+const OBJECT: &str = "java/lang/Object";
+const STRING_BUILDER: &str = "java/lang/StringBuilder";
+// the flags of LambdaMetafactory.altMetafactory
+const FLAG_SERIALIZABLE: i32 = 1;
+const FLAG_MARKERS: i32 = 2;
+const FLAG_BRIDGES: i32 = 4;
+
+// a primitive type with its box, the method unboxing it and the descriptor of valueOf
+struct Wrapper {
+    primitive: &'static str,
+    class: &'static str,
+    unbox: &'static str,
+    unbox_descriptor: &'static str,
+    value_of: &'static str,
+}
+
+const WRAPPERS: [Wrapper; 8] = [
+    Wrapper {
+        primitive: "Z",
+        class: "java/lang/Boolean",
+        unbox: "booleanValue",
+        unbox_descriptor: "()Z",
+        value_of: "(Z)Ljava/lang/Boolean;",
+    },
+    Wrapper {
+        primitive: "B",
+        class: "java/lang/Byte",
+        unbox: "byteValue",
+        unbox_descriptor: "()B",
+        value_of: "(B)Ljava/lang/Byte;",
+    },
+    Wrapper {
+        primitive: "C",
+        class: "java/lang/Character",
+        unbox: "charValue",
+        unbox_descriptor: "()C",
+        value_of: "(C)Ljava/lang/Character;",
+    },
+    Wrapper {
+        primitive: "S",
+        class: "java/lang/Short",
+        unbox: "shortValue",
+        unbox_descriptor: "()S",
+        value_of: "(S)Ljava/lang/Short;",
+    },
+    Wrapper {
+        primitive: "I",
+        class: "java/lang/Integer",
+        unbox: "intValue",
+        unbox_descriptor: "()I",
+        value_of: "(I)Ljava/lang/Integer;",
+    },
+    Wrapper {
+        primitive: "J",
+        class: "java/lang/Long",
+        unbox: "longValue",
+        unbox_descriptor: "()J",
+        value_of: "(J)Ljava/lang/Long;",
+    },
+    Wrapper {
+        primitive: "F",
+        class: "java/lang/Float",
+        unbox: "floatValue",
+        unbox_descriptor: "()F",
+        value_of: "(F)Ljava/lang/Float;",
+    },
+    Wrapper {
+        primitive: "D",
+        class: "java/lang/Double",
+        unbox: "doubleValue",
+        unbox_descriptor: "()D",
+        value_of: "(D)Ljava/lang/Double;",
+    },
+];
+
+// the class of the box of primitive type `primitive`, e.g. java/lang/Integer for I
+pub fn box_class(primitive: &str) -> Option<&'static str> {
+    WRAPPERS.iter().find(|wrapper| wrapper.primitive == primitive).map(|wrapper| wrapper.class)
+}
+
+// a type of a descriptor with the name checkcast takes for it, which the ops borrow
+struct Type {
+    descriptor: String,
+    cast: String,
+}
+
+struct Signature {
+    descriptor: String,
+    parameters: Vec<Type>,
+    // None for void
+    returned: Option<Type>,
+}
+
+impl Type {
+    fn new(field_type: &FieldType) -> Type {
+        let descriptor = field_type.descriptor();
+        let cast = match field_type {
+            FieldType::Object(name) => name.clone(),
+            _ => descriptor.clone(),
+        };
+        Type { descriptor, cast }
+    }
+
+    fn is_reference(&self) -> bool {
+        self.descriptor.starts_with(['L', '['])
+    }
+
+    fn is_wide(&self) -> bool {
+        matches!(self.descriptor.as_str(), "J" | "D")
+    }
+
+    fn slots(&self) -> u16 {
+        if self.is_wide() {
+            2
+        } else {
+            1
+        }
+    }
+
+    // the box of a primitive type, or the primitive type a box is for
+    fn wrapper(&self) -> Option<&'static Wrapper> {
+        WRAPPERS.iter().find(|wrapper| wrapper.primitive == self.descriptor || wrapper.class == self.cast)
+    }
+
+    fn load(&self, slot: u16) -> Op<'static> {
+        let slot = slot as u8;
+        match self.descriptor.as_bytes()[0] {
+            b'L' | b'[' => Op::ALoad(slot),
+            b'J' => Op::LLoad(slot),
+            b'F' => Op::FLoad(slot),
+            b'D' => Op::DLoad(slot),
+            _ => Op::ILoad(slot),
+        }
+    }
+}
+
+impl Signature {
+    fn new(descriptor: &str) -> Result<Signature, String> {
+        let parsed = parse_method_descriptor(descriptor).ok_or_else(|| format!("bad method type {}", descriptor))?;
+        Ok(Signature {
+            descriptor: String::from(descriptor),
+            parameters: parsed.parameters.iter().map(Type::new).collect(),
+            returned: parsed.return_type.as_ref().map(Type::new),
+        })
+    }
+
+    fn slots(&self) -> u16 {
+        self.parameters.iter().map(Type::slots).sum()
+    }
+}
+
+fn return_op(returned: Option<&Type>) -> Op<'static> {
+    match returned.map(|returned| returned.descriptor.as_bytes()[0]) {
+        None => Op::Return,
+        Some(b'L' | b'[') => Op::AReturn,
+        Some(b'J') => Op::LReturn,
+        Some(b'F') => Op::FReturn,
+        Some(b'D') => Op::DReturn,
+        Some(_) => Op::IReturn,
+    }
+}
+
+// the ops turning the value on top of the stack from type `from` into type `to` the way
+// LambdaMetafactory adapts arguments and results (casts, boxing, unboxing and primitive widening);
+// `hint` is the instantiated type, which says what box to expect when unboxing a reference
+fn convert<'a>(ops: &mut Vec<Op<'a>>, from: &'a Type, to: &'a Type, hint: Option<&'a Type>) -> Result<(), String> {
+    if from.descriptor == to.descriptor {
+        return Ok(());
+    }
+    match (from.is_reference(), to.is_reference()) {
+        (true, true) => {
+            if to.cast != OBJECT {
+                ops.push(Op::CheckCast(&to.cast));
+            }
+            Ok(())
+        }
+        (false, true) => {
+            let wrapper = from.wrapper().ok_or_else(|| format!("cannot box {}", from.descriptor))?;
+            ops.push(Op::InvokeStatic(wrapper.class, "valueOf", wrapper.value_of));
+            Ok(())
+        }
+        (true, false) => {
+            let wrapper = [hint, Some(from), Some(to)]
+                .into_iter()
+                .flatten()
+                .find_map(|candidate| candidate.is_reference().then(|| candidate.wrapper()).flatten())
+                .or_else(|| to.wrapper())
+                .ok_or_else(|| format!("cannot unbox {} to {}", from.descriptor, to.descriptor))?;
+            if from.cast != wrapper.class {
+                ops.push(Op::CheckCast(wrapper.class));
+            }
+            ops.push(Op::InvokeVirtual(wrapper.class, wrapper.unbox, wrapper.unbox_descriptor));
+            widen(ops, wrapper.primitive, &to.descriptor)
+        }
+        (false, false) => widen(ops, &from.descriptor, &to.descriptor),
+    }
+}
+
+fn widen(ops: &mut Vec<Op>, from: &str, to: &str) -> Result<(), String> {
+    let op = match (from, to) {
+        _ if from == to => return Ok(()),
+        ("B" | "S" | "C" | "I", "I") | ("B", "S") => return Ok(()),
+        ("B" | "S" | "C" | "I", "J") => Op::I2L,
+        ("B" | "S" | "C" | "I", "F") => Op::I2F,
+        ("B" | "S" | "C" | "I", "D") => Op::I2D,
+        ("J", "F") => Op::L2F,
+        ("J", "D") => Op::L2D,
+        ("F", "D") => Op::F2D,
+        _ => return Err(format!("cannot convert {} to {}", from, to)),
+    };
+    ops.push(op);
+    Ok(())
+}
+
+// the class LambdaMetafactory would spin: the captured arguments in fields set by the constructor,
+// a static factory taking them, and the interface method, with its bridges, calling the implementation
+fn spin_lambda(bootstrap: &Bootstrap, class_name: &str) -> Result<Vec<u8>, String> {
+    let invalid = || format!("bad arguments for LambdaMetafactory.{}", bootstrap.method.name);
+    use StaticArgument::{MethodHandle, MethodType};
+    let [MethodType(sam), MethodHandle(implementation), MethodType(instantiated), rest @ ..] = &bootstrap.arguments[..]
+    else {
+        return Err(invalid());
+    };
+    let site = Signature::new(&bootstrap.descriptor)?;
+    let interface = match &site.returned {
+        Some(returned) if returned.descriptor.starts_with('L') => returned.cast.as_str(),
+        _ => return Err(invalid()),
+    };
+    let mut interfaces = vec![interface];
+    let mut sams = vec![Signature::new(sam)?];
+    // altMetafactory's flags, then the marker interfaces and the bridges if the flags say so
+    if let [StaticArgument::Int(flags), rest @ ..] = rest {
+        let mut rest = rest;
+        if flags & FLAG_MARKERS != 0 {
+            let [StaticArgument::Int(count), more @ ..] = rest else { return Err(invalid()) };
+            let (markers, more) = more.split_at((*count as usize).min(more.len()));
+            for marker in markers {
+                let StaticArgument::Class(marker) = marker else { return Err(invalid()) };
+                interfaces.push(marker);
+            }
+            rest = more;
+        }
+        if flags & FLAG_BRIDGES != 0 {
+            let [StaticArgument::Int(count), more @ ..] = rest else { return Err(invalid()) };
+            for bridge in more.iter().take(*count as usize) {
+                let StaticArgument::MethodType(bridge) = bridge else { return Err(invalid()) };
+                if sams.iter().all(|sam| sam.descriptor != *bridge) {
+                    sams.push(Signature::new(bridge)?);
+                }
+            }
+        }
+        if flags & FLAG_SERIALIZABLE != 0 {
+            interfaces.push("java/io/Serializable");
+        }
+    }
+    let target = Signature::new(&implementation.type_descriptor())?;
+    let instantiated = Signature::new(instantiated)?;
+    let captured = &site.parameters;
+    let fields: Vec<String> = (1..=captured.len()).map(|n| format!("arg${}", n)).collect();
+    let init_descriptor = format!("({})V", captured.iter().map(|field| field.descriptor.as_str()).collect::<String>());
+
+    let field_defs: Vec<FieldDef> = fields
+        .iter()
+        .zip(captured)
+        .map(|(name, field)| FieldDef::new(name, &field.descriptor, ACC_PRIVATE | ACC_FINAL))
+        .collect();
+    let mut init = vec![Op::ALoad(0), Op::InvokeSpecial(OBJECT, "<init>", "()V")];
+    let mut factory = vec![Op::New(class_name), Op::Dup];
+    let mut slot = 0;
+    for (name, field) in fields.iter().zip(captured) {
+        init.extend([Op::ALoad(0), field.load(slot + 1), Op::PutField(class_name, name, &field.descriptor)]);
+        factory.push(field.load(slot));
+        slot += field.slots();
+    }
+    init.push(Op::Return);
+    factory.extend([Op::InvokeSpecial(class_name, "<init>", &init_descriptor), Op::AReturn]);
+
+    let mut forwarders = Vec::new();
+    for sam in &sams {
+        if target.parameters.len() != captured.len() + sam.parameters.len() {
+            return Err(format!("{} does not fit {}{}", implementation.name, bootstrap.name, sam.descriptor));
+        }
+        let mut ops = Vec::new();
+        if implementation.kind == REF_NEW_INVOKE_SPECIAL {
+            ops.extend([Op::New(&implementation.class_name), Op::Dup]);
+        }
+        for ((name, field), to) in fields.iter().zip(captured).zip(&target.parameters) {
+            ops.extend([Op::ALoad(0), Op::GetField(class_name, name, &field.descriptor)]);
+            convert(&mut ops, field, to, None)?;
+        }
+        let mut slot = 1;
+        for (n, parameter) in sam.parameters.iter().enumerate() {
+            ops.push(parameter.load(slot));
+            slot += parameter.slots();
+            convert(&mut ops, parameter, &target.parameters[captured.len() + n], instantiated.parameters.get(n))?;
+        }
+        let (class, name, descriptor) =
+            (&implementation.class_name[..], &implementation.name[..], &implementation.descriptor[..]);
+        ops.push(match implementation.kind {
+            REF_INVOKE_VIRTUAL => Op::InvokeVirtual(class, name, descriptor),
+            REF_INVOKE_STATIC => Op::InvokeStatic(class, name, descriptor),
+            REF_INVOKE_SPECIAL | REF_NEW_INVOKE_SPECIAL => Op::InvokeSpecial(class, name, descriptor),
+            REF_INVOKE_INTERFACE => Op::InvokeInterface(class, name, descriptor),
+            _ => return Err(format!("cannot make a lambda of method handle kind {}", implementation.kind)),
+        });
+        // a constructor reference returns what the new and dup above left on the stack
+        let returned = target.returned.as_ref();
+        match (returned, &sam.returned) {
+            (Some(returned), None) => ops.push(if returned.is_wide() { Op::Pop2 } else { Op::Pop }),
+            (Some(returned), Some(to)) => convert(&mut ops, returned, to, instantiated.returned.as_ref())?,
+            (None, Some(_)) => return Err(format!("{} returns nothing", implementation.name)),
+            (None, None) => {}
+        }
+        ops.push(return_op(sam.returned.as_ref()));
+        forwarders.push((sam, ops, 4 + target.slots(), 1 + sam.slots()));
+    }
+
+    let mut methods = vec![
+        MethodDef::code("<init>", &init_descriptor, ACC_PRIVATE, 3, 1 + site.slots(), &init),
+        MethodDef::code(
+            "get$Lambda",
+            &site.descriptor,
+            ACC_PRIVATE | ACC_STATIC,
+            2 + site.slots(),
+            site.slots(),
+            &factory,
+        ),
+    ];
+    for (sam, ops, max_stack, max_locals) in &forwarders {
+        methods.push(MethodDef::code(&bootstrap.name, &sam.descriptor, ACC_PUBLIC, *max_stack, *max_locals, ops));
+    }
+    let class = ClassDef {
+        name: class_name,
+        super_class: Some(OBJECT),
+        interfaces: &interfaces,
+        access_flags: ACC_FINAL | ACC_SYNTHETIC,
+        fields: &field_defs,
+        methods: &methods,
+    };
+    Ok(class.write())
+}
+
+// a piece of the string a concatenation makes
+enum Piece {
+    Literal(String),
+    Argument(usize),
+    Constant(Constant, &'static str),
+}
+
+// a class with a static method appending the arguments and constants of the recipe to a StringBuilder,
+// which is what javac compiled string concatenation to before Java 9
+fn spin_concat(bootstrap: &Bootstrap, class_name: &str) -> Result<Vec<u8>, String> {
+    let site = Signature::new(&bootstrap.descriptor)?;
+    let (recipe, mut constants) = match &bootstrap.arguments[..] {
+        [StaticArgument::String(recipe), constants @ ..] => (recipe.clone(), constants.iter()),
+        [] => ("\u{1}".repeat(site.parameters.len()), [].iter()),
+        _ => return Err(String::from("bad arguments for StringConcatFactory.makeConcatWithConstants")),
+    };
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut arguments = 0;
+    for c in recipe.chars() {
+        let piece = match c {
+            '\u{1}' => Piece::Argument(arguments),
+            '\u{2}' => match constants.next() {
+                Some(StaticArgument::String(value)) => {
+                    literal.push_str(value);
+                    continue;
+                }
+                Some(StaticArgument::Int(value)) => Piece::Constant(Constant::Int(*value), "(I)"),
+                Some(StaticArgument::Long(value)) => Piece::Constant(Constant::Long(*value), "(J)"),
+                Some(StaticArgument::Float(value)) => Piece::Constant(Constant::Float(*value), "(F)"),
+                Some(StaticArgument::Double(value)) => Piece::Constant(Constant::Double(*value), "(D)"),
+                _ => return Err(String::from("missing or unsupported constant in the concatenation recipe")),
+            },
+            c => {
+                literal.push(c);
+                continue;
+            }
+        };
+        if c == '\u{1}' {
+            arguments += 1;
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+        }
+        pieces.push(piece);
+    }
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    if arguments != site.parameters.len() {
+        return Err(format!("the concatenation recipe does not fit {}", bootstrap.descriptor));
+    }
+
+    let slots: Vec<u16> = site
+        .parameters
+        .iter()
+        .scan(0, |slot, parameter| {
+            let current = *slot;
+            *slot += parameter.slots();
+            Some(current)
+        })
+        .collect();
+    let appends: Vec<String> = site
+        .parameters
+        .iter()
+        .map(|parameter| {
+            let appended = match parameter.descriptor.as_str() {
+                "B" | "S" | "I" => "I",
+                "Ljava/lang/String;" => "Ljava/lang/String;",
+                _ if parameter.is_reference() => "Ljava/lang/Object;",
+                descriptor => descriptor,
+            };
+            format!("({})Ljava/lang/StringBuilder;", appended)
+        })
+        .collect();
+    let constant_appends: Vec<String> = pieces
+        .iter()
+        .map(|piece| match piece {
+            Piece::Constant(_, descriptor) => format!("{}Ljava/lang/StringBuilder;", descriptor),
+            _ => String::new(),
+        })
+        .collect();
+    let mut ops = vec![Op::New(STRING_BUILDER), Op::Dup, Op::InvokeSpecial(STRING_BUILDER, "<init>", "()V")];
+    for (piece, constant_append) in pieces.iter().zip(&constant_appends) {
+        match piece {
+            Piece::Literal(value) => ops.extend([
+                Op::Ldc(value),
+                Op::InvokeVirtual(STRING_BUILDER, "append", "(Ljava/lang/String;)Ljava/lang/StringBuilder;"),
+            ]),
+            Piece::Argument(n) => ops.extend([
+                site.parameters[*n].load(slots[*n]),
+                Op::InvokeVirtual(STRING_BUILDER, "append", &appends[*n]),
+            ]),
+            Piece::Constant(value, _) => {
+                ops.extend([Op::LdcConstant(*value), Op::InvokeVirtual(STRING_BUILDER, "append", constant_append)])
+            }
+        }
+    }
+    ops.extend([Op::InvokeVirtual(STRING_BUILDER, "toString", "()Ljava/lang/String;"), Op::AReturn]);
+    let methods = [MethodDef::code("concat", &site.descriptor, ACC_PUBLIC | ACC_STATIC, 4, site.slots(), &ops)];
+    let class = ClassDef {
+        name: class_name,
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_FINAL | ACC_SYNTHETIC,
+        fields: &[],
+        methods: &methods,
+    };
+    Ok(class.write())
+}
+
+// a class with a static method `adapt` for calling `method_name` of `owner`, MethodHandle.invokeExact or an
+// access mode of VarHandle, as if it had type `site` when its type is `target`: it takes the handle and the
+// arguments of the call, converts them the way MethodHandle.asType does, and converts the result back
+pub fn spin_adapter(
+    class_name: &str,
+    owner: &str,
+    method_name: &str,
+    site: &str,
+    target: &str,
+) -> Result<Vec<u8>, String> {
+    let site = Signature::new(site)?;
+    let target = Signature::new(target)?;
+    let mismatch = || format!("cannot convert {} to {}", target.descriptor, site.descriptor);
+    if site.parameters.len() != target.parameters.len() {
+        return Err(mismatch());
+    }
+    let mut ops = vec![Op::ALoad(0)];
+    let mut slot = 1;
+    for (from, to) in site.parameters.iter().zip(&target.parameters) {
+        ops.push(from.load(slot));
+        slot += from.slots();
+        convert(&mut ops, from, to, None).map_err(|_| mismatch())?;
+    }
+    ops.push(Op::InvokeVirtual(owner, method_name, &target.descriptor));
+    match (&target.returned, &site.returned) {
+        (Some(returned), None) => ops.push(if returned.is_wide() { Op::Pop2 } else { Op::Pop }),
+        (Some(returned), Some(to)) => convert(&mut ops, returned, to, None).map_err(|_| mismatch())?,
+        (None, Some(_)) => return Err(mismatch()),
+        (None, None) => {}
+    }
+    ops.push(return_op(site.returned.as_ref()));
+    let descriptor = format!("(L{};{}", owner, &site.descriptor[1..]);
+    let methods =
+        [MethodDef::code("adapt", &descriptor, ACC_PUBLIC | ACC_STATIC, 4 + target.slots(), 1 + site.slots(), &ops)];
+    let class = ClassDef {
+        name: class_name,
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_FINAL | ACC_SYNTHETIC,
+        fields: &[],
+        methods: &methods,
+    };
+    Ok(class.write())
+}
+
+// the static method of an invoker class
+pub const INVOKER: &str = "invoke";
+
+// a class with a static method `invoke` making a call of `kind`, one of the REF_INVOKE kinds, to method `name`
+// of `owner`: it takes the receiver first unless the call is static, then the method's arguments, and returns
+// what the method does. Code outside of Java calls methods through it, leaving class initialization, dispatch
+// and natives to the interpreter
+pub fn spin_invoker(class_name: &str, kind: u8, owner: &str, name: &str, descriptor: &str) -> Result<Vec<u8>, String> {
+    let method = Signature::new(descriptor)?;
+    let mut ops = Vec::new();
+    let mut slot = 0;
+    if kind != REF_INVOKE_STATIC {
+        ops.push(Op::ALoad(0));
+        slot = 1;
+    }
+    for parameter in &method.parameters {
+        ops.push(parameter.load(slot));
+        slot += parameter.slots();
+    }
+    ops.push(match kind {
+        REF_INVOKE_STATIC => Op::InvokeStatic(owner, name, descriptor),
+        REF_INVOKE_SPECIAL => Op::InvokeSpecial(owner, name, descriptor),
+        REF_INVOKE_INTERFACE => Op::InvokeInterface(owner, name, descriptor),
+        _ => Op::InvokeVirtual(owner, name, descriptor),
+    });
+    ops.push(return_op(method.returned.as_ref()));
+    let invoke_descriptor = match kind {
+        REF_INVOKE_STATIC => String::from(descriptor),
+        _ => format!("(L{};{}", owner, &descriptor[1..]),
+    };
+    let methods = [MethodDef::code(INVOKER, &invoke_descriptor, ACC_PUBLIC | ACC_STATIC, slot.max(2), slot, &ops)];
+    let class = ClassDef {
+        name: class_name,
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_FINAL | ACC_SYNTHETIC,
+        fields: &[],
+        methods: &methods,
+    };
+    Ok(class.write())
+}
+
+// the static method of an accessor class
+pub const ACCESSOR: &str = "access";
+
+const INVOCATION_TARGET_EXCEPTION: &str = "java/lang/reflect/InvocationTargetException";
+
+// a class with a static method `access` through which reflection gets at what `target` refers to, taking the
+// arguments of the native it stands in for: Field.get the Field and the object, Field.set those and the value,
+// Method.invoke the Method, the receiver and the arguments, Constructor.newInstance the Constructor and the
+// arguments, the first of them going unused. It unboxes the arguments, whose types the natives have checked,
+// boxes the result, and wraps what the method or constructor throws in an InvocationTargetException
+pub fn spin_accessor(class_name: &str, target: &MethodHandleRef) -> Result<Vec<u8>, String> {
+    let object = Type { descriptor: format!("L{};", OBJECT), cast: String::from(OBJECT) };
+    let owner = target.class_name.as_str();
+    let (name, descriptor) = (target.name.as_str(), target.descriptor.as_str());
+    let is_static = matches!(target.kind, REF_GET_STATIC | REF_PUT_STATIC | REF_INVOKE_STATIC);
+    // the type of a field or the signature of a method or constructor
+    let field = parse_field_descriptor(descriptor).map(|field| Type::new(&field));
+    let method = Signature::new(descriptor).ok();
+    let mut ops = Vec::new();
+    if !is_static && target.kind != REF_NEW_INVOKE_SPECIAL {
+        ops.extend([Op::ALoad(1), Op::CheckCast(owner)]);
+    }
+    let (access_descriptor, max_stack, handled) = match (target.kind, &field, &method) {
+        (REF_GET_FIELD | REF_GET_STATIC, Some(field), _) => {
+            ops.push(match is_static {
+                true => Op::GetStatic(owner, name, descriptor),
+                false => Op::GetField(owner, name, descriptor),
+            });
+            convert(&mut ops, field, &object, None)?;
+            ops.push(Op::AReturn);
+            ("(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;", 2, None)
+        }
+        (REF_PUT_FIELD | REF_PUT_STATIC, Some(field), _) => {
+            ops.push(Op::ALoad(2));
+            convert(&mut ops, &object, field, None)?;
+            ops.push(match is_static {
+                true => Op::PutStatic(owner, name, descriptor),
+                false => Op::PutField(owner, name, descriptor),
+            });
+            ops.push(Op::Return);
+            ("(Ljava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;)V", 3, None)
+        }
+        (REF_INVOKE_VIRTUAL | REF_INVOKE_STATIC | REF_INVOKE_SPECIAL | REF_INVOKE_INTERFACE, _, Some(method)) => {
+            push_arguments(&mut ops, method, 2, &object)?;
+            let invoke = ops.len();
+            ops.push(match target.kind {
+                REF_INVOKE_VIRTUAL => Op::InvokeVirtual(owner, name, descriptor),
+                REF_INVOKE_STATIC => Op::InvokeStatic(owner, name, descriptor),
+                REF_INVOKE_SPECIAL => Op::InvokeSpecial(owner, name, descriptor),
+                _ => Op::InvokeInterface(owner, name, descriptor),
+            });
+            match &method.returned {
+                Some(returned) => convert(&mut ops, returned, &object, None)?,
+                None => ops.push(Op::AConstNull),
+            }
+            ops.push(Op::AReturn);
+            let descriptor = "(Ljava/lang/Object;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;";
+            (descriptor, 4 + method.slots(), Some((invoke, 3)))
+        }
+        (REF_NEW_INVOKE_SPECIAL, _, Some(constructor)) => {
+            ops.extend([Op::New(owner), Op::Dup]);
+            push_arguments(&mut ops, constructor, 1, &object)?;
+            let invoke = ops.len();
+            ops.extend([Op::InvokeSpecial(owner, name, descriptor), Op::AReturn]);
+            ("(Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;", 5 + constructor.slots(), Some((invoke, 2)))
+        }
+        _ => return Err(format!("no accessor for {}.{}:{}", owner, name, descriptor)),
+    };
+    let mut handlers = Vec::new();
+    if let Some((invoke, exception)) = handled {
+        handlers.push(Handler { start: invoke, end: invoke + 1, target: ops.len(), catch_type: None });
+        ops.extend([
+            Op::AStore(exception),
+            Op::New(INVOCATION_TARGET_EXCEPTION),
+            Op::Dup,
+            Op::ALoad(exception),
+            Op::InvokeSpecial(INVOCATION_TARGET_EXCEPTION, "<init>", "(Ljava/lang/Throwable;)V"),
+            Op::AThrow,
+        ]);
+    }
+    let access = MethodDef::code(ACCESSOR, access_descriptor, ACC_PUBLIC | ACC_STATIC, max_stack.max(4), 4, &ops);
+    let methods = [MethodDef { handlers: &handlers, ..access }];
+    let class = ClassDef {
+        name: class_name,
+        super_class: Some(OBJECT),
+        interfaces: &[],
+        access_flags: ACC_FINAL | ACC_SYNTHETIC,
+        fields: &[],
+        methods: &methods,
+    };
+    Ok(class.write())
+}
+
+// the ops pushing the elements of the Object[] in local `array` as the parameters of `method`
+fn push_arguments<'a>(
+    ops: &mut Vec<Op<'a>>,
+    method: &'a Signature,
+    array: u8,
+    object: &'a Type,
+) -> Result<(), String> {
+    for (index, parameter) in method.parameters.iter().enumerate() {
+        ops.extend([Op::ALoad(array), Op::LdcConstant(Constant::Int(index as i32)), Op::AALoad]);
+        convert(ops, object, parameter, None)?;
+    }
+    Ok(())
+}
+
+pub const PROXY: &str = "java/lang/reflect/Proxy";
+const INVOCATION_HANDLER: &str = "java/lang/reflect/InvocationHandler";
+const INVOKE_HANDLER: &str = "(Ljava/lang/Object;Ljava/lang/reflect/Method;[Ljava/lang/Object;)Ljava/lang/Object;";
+
+// the name of the static field of a proxy class holding the java.lang.reflect.Method of its method `index`
+pub fn proxy_method_field(index: usize) -> String {
+    format!("m{}", index)
+}
+
+// a dynamic proxy class implementing `interfaces`: a subclass of java.lang.reflect.Proxy whose `methods`, by name
+// and descriptor, box their arguments and call the InvocationHandler of the proxy with the Method in the static
+// field proxy_method_field names, which whoever defines the class sets, and unbox what it returns. Unlike the JDK's
+// proxies they let checked exceptions the methods do not declare through, without an UndeclaredThrowableException
+pub fn spin_proxy(class_name: &str, interfaces: &[&str], methods: &[(&str, &str)]) -> Result<Vec<u8>, String> {
+    let object = Type { descriptor: format!("L{};", OBJECT), cast: String::from(OBJECT) };
+    let method = "Ljava/lang/reflect/Method;";
+    let handler = "Ljava/lang/reflect/InvocationHandler;";
+    let names: Vec<String> = (0..methods.len()).map(proxy_method_field).collect();
+    let signatures = methods.iter().map(|(_, descriptor)| Signature::new(descriptor)).collect::<Result<Vec<_>, _>>()?;
+    let mut bodies = Vec::new();
+    for (name, signature) in names.iter().zip(&signatures) {
+        let mut ops = vec![Op::ALoad(0), Op::GetField(class_name, "h", handler), Op::ALoad(0)];
+        ops.push(Op::GetStatic(class_name, name, method));
+        if signature.parameters.is_empty() {
+            ops.push(Op::AConstNull);
+        } else {
+            ops.extend([Op::LdcConstant(Constant::Int(signature.parameters.len() as i32)), Op::ANewArray(OBJECT)]);
+            let mut slot = 1;
+            for (index, parameter) in signature.parameters.iter().enumerate() {
+                ops.extend([Op::Dup, Op::LdcConstant(Constant::Int(index as i32)), parameter.load(slot)]);
+                convert(&mut ops, parameter, &object, None)?;
+                ops.push(Op::AAStore);
+                slot += parameter.slots();
+            }
+        }
+        ops.push(Op::InvokeInterface(INVOCATION_HANDLER, "invoke", INVOKE_HANDLER));
+        match &signature.returned {
+            Some(returned) => convert(&mut ops, &object, returned, None)?,
+            None => ops.push(Op::Pop),
+        }
+        ops.push(return_op(signature.returned.as_ref()));
+        bodies.push(ops);
+    }
+    let constructor = [
+        Op::ALoad(0),
+        Op::ALoad(1),
+        Op::InvokeSpecial(PROXY, "<init>", "(Ljava/lang/reflect/InvocationHandler;)V"),
+        Op::Return,
+    ];
+    let mut method_defs = vec![MethodDef::code(
+        "<init>",
+        "(Ljava/lang/reflect/InvocationHandler;)V",
+        ACC_PUBLIC,
+        2,
+        2,
+        &constructor,
+    )];
+    for ((name, _), (signature, ops)) in methods.iter().zip(signatures.iter().zip(&bodies)) {
+        let max_locals = 1 + signature.slots();
+        method_defs.push(MethodDef::code(name, &signature.descriptor, ACC_PUBLIC | ACC_FINAL, 8, max_locals, ops));
+    }
+    let fields: Vec<FieldDef> =
+        names.iter().map(|name| FieldDef::new(name, method, ACC_PRIVATE | ACC_STATIC)).collect();
+    let class = ClassDef {
+        name: class_name,
+        super_class: Some(PROXY),
+        interfaces,
+        access_flags: ACC_PUBLIC | ACC_FINAL | ACC_SYNTHETIC,
+        fields: &fields,
+        methods: &method_defs,
+    };
+    Ok(class.write())
+}
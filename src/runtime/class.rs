@@ -1,7 +1,512 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::classfile::attribute::AttributeInfo::{
+    AnnotationDefaultAttribute, CodeAttribute, ConstantValueAttribute, ExceptionsAttribute, LineNumberTableAttribute,
+    LocalVariableTableAttribute, RuntimeVisibleAnnotationsAttribute, RuntimeVisibleParameterAnnotationsAttribute,
+    SignatureAttribute, SourceFileAttribute,
+};
+use crate::classfile::attribute::{Annotation, AttributeInfo, ElementValue, ExceptionTableEntry, LineNumberEntry};
+use crate::classfile::class_reader::{
+    get_class_name, get_utf8, FieldInfo, MethodInfo, ACC_ABSTRACT, ACC_FINAL, ACC_INTERFACE, ACC_NATIVE, ACC_PRIVATE,
+    ACC_STATIC, ACC_SYNCHRONIZED,
+};
+use crate::classfile::constant_pool::ConstantInfo;
+use crate::classfile::descriptor::{parse_method_descriptor, MethodDescriptor};
+use crate::classfile::ClassFile;
+use crate::error::Error;
+use crate::instructions::{decode_code, Instruction};
+
+use super::call_site::CallSite;
+use super::heap::ObjectRef;
+use super::Slot;
+
+// the primitive types, including void, and their descriptors
+pub const PRIMITIVE_TYPES: [(&str, &str); 9] = [
+    ("boolean", "Z"),
+    ("byte", "B"),
+    ("char", "C"),
+    ("short", "S"),
+    ("int", "I"),
+    ("long", "J"),
+    ("float", "F"),
+    ("double", "D"),
+    ("void", "V"),
+];
+
+// the name of the primitive type with descriptor `descriptor`, e.g. int for I
+pub fn primitive_name(descriptor: &str) -> &'static str {
+    match PRIMITIVE_TYPES.iter().find(|(_, primitive)| *primitive == descriptor) {
+        Some((name, _)) => name,
+        None => panic!("{} is not a primitive type", descriptor),
+    }
+}
+
+// a loaded class: the parsed class file linked to its super class
+#[derive(Default)]
 pub struct Class {
-    access_flag: u16,
-    name: String,
-    super_name: String,
-    interface_name_vec: Vec<String>,
-    constant_pool: 
-}
\ No newline at end of file
+    pub name: String,
+    pub super_class: Option<Rc<Class>>,
+    // the interfaces the class declares, not the ones it inherits
+    pub interfaces: Vec<Rc<Class>>,
+    // the element class of a reference array class; None for every other class
+    pub component: Option<Rc<Class>>,
+    pub classfile: ClassFile,
+    pub methods: Vec<Rc<Method>>,
+    pub fields: Vec<Rc<Field>>,
+    pub source_file: Option<String>,
+    // the field slots of a new instance, inherited fields first, each holding its type's default value
+    pub instance_fields: Vec<Slot>,
+    pub static_fields: RefCell<Vec<Slot>>,
+    // how far the class has come with running its <clinit>, and the threads waiting for another thread to finish it
+    pub init_state: Cell<InitState>,
+    pub init_waiters: RefCell<Vec<u64>>,
+    // the java.lang.Class object, created on first use
+    pub mirror: Cell<Option<ObjectRef>>,
+}
+
+// the initialization states of JVMS §5.5
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InitState {
+    #[default]
+    Uninitialized,
+    // by the thread with that ID, which runs the <clinit> of the class or of a super class it waits on
+    BeingInitialized(u64),
+    Initialized,
+    // its <clinit> threw, or that of a super class did
+    Erroneous,
+}
+
+pub struct Field {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u16,
+    // the first of the field's slots in the instance or static field slots
+    pub slot: usize,
+    // the generic type from the Signature attribute
+    pub signature: Option<String>,
+    // the classes reflection reads and writes the field through, made on first use
+    pub getter: RefCell<Option<(Rc<Class>, Rc<Method>)>>,
+    pub setter: RefCell<Option<(Rc<Class>, Rc<Method>)>>,
+}
+
+#[derive(Default)]
+pub struct Method {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u16,
+    pub parsed_descriptor: Option<MethodDescriptor>,
+    pub max_stack: usize,
+    pub max_locals: usize,
+    // None for native and abstract methods
+    pub code: Option<Rc<Code>>,
+    pub exception_table: Vec<ExceptionTableEntry>,
+    pub line_numbers: Vec<LineNumberEntry>,
+    // the names of the local variables from the LocalVariableTable, for the debugger
+    pub local_variables: Vec<LocalVariable>,
+    // what the invokedynamic instructions of the method were linked to, by pc
+    pub call_sites: RefCell<HashMap<i32, CallSite>>,
+    // the generic signature from the Signature attribute and the classes of the Exceptions attribute
+    pub signature: Option<String>,
+    pub exceptions: Vec<String>,
+    // the class reflection calls the method through, made on first use
+    pub accessor: RefCell<Option<(Rc<Class>, Rc<Method>)>>,
+}
+
+// a local variable of a method, in slot `index` for the `length` bytes of code from `start_pc`
+pub struct LocalVariable {
+    pub name: String,
+    pub descriptor: String,
+    pub start_pc: u16,
+    pub length: u16,
+    pub index: u16,
+}
+
+// the instructions of a method decoded once up front, indexed by pc
+pub struct Code {
+    instructions: Vec<Option<DecodedInstruction>>,
+}
+
+pub struct DecodedInstruction {
+    pub instruction: Instruction,
+    pub next_pc: i32,
+}
+
+impl Class {
+    pub fn new(classfile: ClassFile, super_class: Option<Rc<Class>>, interfaces: Vec<Rc<Class>>) -> Result<Class, Error> {
+        let name = get_class_name(&classfile.constant_pool, &classfile.this_class).unwrap_or_default();
+        let mut methods = Vec::new();
+        for method in &classfile.methods_info {
+            methods.push(Rc::new(Method::new(&classfile, method)?));
+        }
+        let source_file = classfile.attributes_info.iter().find_map(|attribute| match attribute {
+            SourceFileAttribute { source_file } => get_utf8(&classfile.constant_pool, source_file),
+            _ => None,
+        });
+        let mut instance_fields = super_class.as_ref().map(|class| class.instance_fields.clone()).unwrap_or_default();
+        let mut static_fields = Vec::new();
+        let mut fields = Vec::new();
+        for field in &classfile.fields_info {
+            let descriptor = get_utf8(&classfile.constant_pool, &field.descriptor_index).unwrap_or_default();
+            let slots = if field.access_flag & ACC_STATIC != 0 {
+                &mut static_fields
+            } else {
+                &mut instance_fields
+            };
+            let slot = slots.len();
+            slots.extend(constant_value(&classfile.constant_pool, field).unwrap_or_else(|| Slot::zero(&descriptor)));
+            fields.push(Rc::new(Field {
+                name: field.name.clone(),
+                descriptor,
+                access_flags: field.access_flag,
+                slot,
+                signature: signature(&classfile.constant_pool, &field.attribute_info),
+                getter: RefCell::new(None),
+                setter: RefCell::new(None),
+            }));
+        }
+        Ok(Class {
+            name,
+            super_class,
+            interfaces,
+            component: None,
+            classfile,
+            methods,
+            fields,
+            source_file,
+            instance_fields,
+            static_fields: RefCell::new(static_fields),
+            init_state: Cell::new(InitState::Uninitialized),
+            init_waiters: RefCell::new(Vec::new()),
+            mirror: Cell::new(None),
+        })
+    }
+
+    // the class of arrays named `name`, e.g. [I or [Ljava/lang/String;, holding elements of class `component`
+    // when they are references. Arrays have no class file, their only methods are Object's
+    pub fn new_array(name: &str, object_class: Rc<Class>, component: Option<Rc<Class>>) -> Class {
+        Class {
+            name: String::from(name),
+            super_class: Some(object_class),
+            component,
+            init_state: Cell::new(InitState::Initialized),
+            ..Class::default()
+        }
+    }
+
+    // the Class object of a primitive type, e.g. int, which Integer.TYPE holds
+    pub fn new_primitive(name: &str) -> Class {
+        Class {
+            name: String::from(name),
+            init_state: Cell::new(InitState::Initialized),
+            ..Class::default()
+        }
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.init_state.get() == InitState::Initialized
+    }
+
+    pub fn is_primitive(&self) -> bool {
+        PRIMITIVE_TYPES.iter().any(|(name, _)| *name == self.name)
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.name.starts_with('[')
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.classfile.access_flags & ACC_INTERFACE != 0
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.classfile.access_flags & ACC_ABSTRACT != 0
+    }
+
+    // finds a field declared in this class or inherited from a super class, along with the class declaring it
+    pub fn find_field(self: &Rc<Self>, name: &str, descriptor: &str) -> Option<(Rc<Class>, Rc<Field>)> {
+        let mut class = Some(self);
+        while let Some(current) = class {
+            let field = current.fields.iter().find(|field| field.name == name && field.descriptor == descriptor);
+            if let Some(field) = field {
+                return Some((current.clone(), field.clone()));
+            }
+            class = current.super_class.as_ref();
+        }
+        None
+    }
+
+    // finds a method declared in this class or inherited from a super class, along with the class declaring it;
+    // failing that, a default method of one of the interfaces, or else an abstract one
+    pub fn find_method(self: &Rc<Self>, name: &str, descriptor: &str) -> Option<(Rc<Class>, Rc<Method>)> {
+        let mut class = Some(self);
+        let mut interfaces = Vec::new();
+        while let Some(current) = class {
+            let method = current.methods.iter().find(|method| method.name == name && method.descriptor == descriptor);
+            if let Some(method) = method {
+                return Some((current.clone(), method.clone()));
+            }
+            interfaces.extend(current.interfaces.iter().cloned());
+            class = current.super_class.as_ref();
+        }
+        let mut abstract_method = None;
+        while let Some(interface) = interfaces.pop() {
+            let method = interface.methods.iter().find(|method| {
+                method.name == name && method.descriptor == descriptor && !method.is_static() && !method.is_private()
+            });
+            match method {
+                Some(method) if method.code.is_some() => return Some((interface.clone(), method.clone())),
+                Some(method) => abstract_method = abstract_method.or(Some((interface.clone(), method.clone()))),
+                None => {}
+            }
+            interfaces.extend(interface.interfaces.iter().cloned());
+        }
+        abstract_method
+    }
+
+    // whether the class is `class_name`, extends it or implements it
+    pub fn is_subclass_of(&self, class_name: &str) -> bool {
+        self.name == class_name
+            || self.interfaces.iter().any(|interface| interface.is_subclass_of(class_name))
+            || self.super_class.as_ref().is_some_and(|class| class.is_subclass_of(class_name))
+    }
+
+    // whether a reference to an instance of this class can be stored where `target` is expected,
+    // the check aastore does on its elements
+    pub fn is_assignable_to(&self, target: &Class) -> bool {
+        match (&self.component, &target.component) {
+            (Some(component), Some(target_component)) => component.is_assignable_to(target_component),
+            // arrays of primitives only go where the same array type or Object, Cloneable or Serializable does
+            _ if self.is_array() => {
+                self.name == target.name
+                    || ["java/lang/Object", "java/lang/Cloneable", "java/io/Serializable"].contains(&target.name.as_str())
+            }
+            _ => self.is_subclass_of(&target.name),
+        }
+    }
+
+    // the descriptor of the type the class stands for, e.g. I for int or Ljava/lang/String; for String
+    pub fn descriptor(&self) -> String {
+        match PRIMITIVE_TYPES.iter().find(|(name, _)| *name == self.name) {
+            Some((_, descriptor)) => String::from(*descriptor),
+            None if self.is_array() => self.name.clone(),
+            None => format!("L{};", self.name),
+        }
+    }
+
+    // the class name as Java prints it, e.g. java.lang.Object
+    pub fn java_name(&self) -> String {
+        self.name.replace('/', ".")
+    }
+}
+
+impl Field {
+    pub fn is_static(&self) -> bool {
+        self.access_flags & ACC_STATIC != 0
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.access_flags & ACC_FINAL != 0
+    }
+
+    // 2 for long and double fields, 1 for everything else
+    pub fn slot_count(&self) -> usize {
+        if self.descriptor == "J" || self.descriptor == "D" {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+// the initial value of a static final field with a ConstantValue attribute; string constants
+// wait for the VM to have strings
+fn constant_value(constant_pool: &[ConstantInfo], field: &FieldInfo) -> Option<Vec<Slot>> {
+    if field.access_flag & ACC_STATIC == 0 {
+        return None;
+    }
+    field.attribute_info.iter().find_map(|attribute| match attribute {
+        ConstantValueAttribute { value_index } => match constant_pool.get(*value_index as usize)? {
+            ConstantInfo::ConstantInteger { value } => Some(vec![Slot::Int(*value as i32)]),
+            ConstantInfo::ConstantFloat { value } => Some(vec![Slot::Float(f32::from_bits(*value))]),
+            ConstantInfo::ConstantLong { value } => Some(Slot::wide(*value, false).to_vec()),
+            ConstantInfo::ConstantDouble { value } => Some(Slot::wide(*value, true).to_vec()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+// the Signature attribute among `attributes`, which a class, field or method has when it uses generics
+pub fn signature(constant_pool: &[ConstantInfo], attributes: &[AttributeInfo]) -> Option<String> {
+    attributes.iter().find_map(|attribute| match attribute {
+        SignatureAttribute { signature_index } => get_utf8(constant_pool, signature_index),
+        _ => None,
+    })
+}
+
+// the bytes of the RuntimeVisibleAnnotations, RuntimeVisibleParameterAnnotations and AnnotationDefault
+// attributes among `attributes`, in that order, which is what the JDK's reflection parses annotations from
+pub fn raw_annotations(attributes: &[AttributeInfo]) -> [Option<&[u8]>; 3] {
+    let mut raw_annotations = [None; 3];
+    for attribute in attributes {
+        match attribute {
+            RuntimeVisibleAnnotationsAttribute { raw, .. } => raw_annotations[0] = Some(&raw[..]),
+            RuntimeVisibleParameterAnnotationsAttribute { raw, .. } => raw_annotations[1] = Some(&raw[..]),
+            AnnotationDefaultAttribute { raw, .. } => raw_annotations[2] = Some(&raw[..]),
+            _ => {}
+        }
+    }
+    raw_annotations
+}
+
+// the annotations of the RuntimeVisibleAnnotations attribute among `attributes`
+pub fn annotations(attributes: &[AttributeInfo]) -> &[Annotation] {
+    attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            RuntimeVisibleAnnotationsAttribute { annotations, .. } => Some(&annotations[..]),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+// the annotations of each parameter from the RuntimeVisibleParameterAnnotations attribute among `attributes`
+pub fn parameter_annotations(attributes: &[AttributeInfo]) -> &[Vec<Annotation>] {
+    attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            RuntimeVisibleParameterAnnotationsAttribute { parameter_annotations, .. } => {
+                Some(&parameter_annotations[..])
+            }
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+// the default value of an element of an annotation type, from the AnnotationDefault attribute of its method
+pub fn annotation_default(attributes: &[AttributeInfo]) -> Option<&ElementValue> {
+    attributes.iter().find_map(|attribute| match attribute {
+        AnnotationDefaultAttribute { default_value, .. } => Some(default_value),
+        _ => None,
+    })
+}
+
+impl Method {
+    fn new(classfile: &ClassFile, method: &MethodInfo) -> Result<Method, Error> {
+        let descriptor = get_utf8(&classfile.constant_pool, &method.descriptor_index).unwrap_or_default();
+        let mut result = Method {
+            name: method.name.clone(),
+            parsed_descriptor: parse_method_descriptor(&descriptor),
+            descriptor,
+            access_flags: method.access_flag,
+            signature: signature(&classfile.constant_pool, &method.attribute_info),
+            ..Method::default()
+        };
+        for attribute in &method.attribute_info {
+            if let ExceptionsAttribute { index_table } = attribute {
+                result.exceptions =
+                    index_table.iter().filter_map(|index| get_class_name(&classfile.constant_pool, index)).collect();
+            }
+            if let CodeAttribute { max_stacks, max_locals, code, exception_table, attributes, .. } = attribute {
+                result.max_stack = *max_stacks as usize;
+                result.max_locals = *max_locals as usize;
+                result.code = Some(Rc::new(Code::new(code)?));
+                result.exception_table = exception_table.clone();
+                for attribute in attributes {
+                    if let LineNumberTableAttribute { line_number_table } = attribute {
+                        result.line_numbers.extend(line_number_table.iter().cloned());
+                    }
+                    if let LocalVariableTableAttribute { local_variable_table } = attribute {
+                        let constant_pool = &classfile.constant_pool;
+                        result.local_variables.extend(local_variable_table.iter().map(|entry| LocalVariable {
+                            name: get_utf8(constant_pool, &entry.name_index).unwrap_or_default(),
+                            descriptor: get_utf8(constant_pool, &entry.descriptor_index).unwrap_or_default(),
+                            start_pc: entry.start_pc,
+                            length: entry.length,
+                            index: entry.index,
+                        }));
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.access_flags & ACC_STATIC != 0
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.access_flags & ACC_PRIVATE != 0
+    }
+
+    pub fn is_synchronized(&self) -> bool {
+        self.access_flags & ACC_SYNCHRONIZED != 0
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.access_flags & ACC_NATIVE != 0
+    }
+
+    // slots taken by the arguments, including `this` for instance methods
+    pub fn arg_slot_count(&self) -> usize {
+        let args = self.parsed_descriptor.as_ref().map(MethodDescriptor::arg_slot_count).unwrap_or_default();
+        if self.is_static() {
+            args
+        } else {
+            args + 1
+        }
+    }
+
+    // the local variables in scope at `pc`, by slot
+    pub fn local_variables_at(&self, pc: i32) -> Vec<&LocalVariable> {
+        let mut variables: Vec<_> = self
+            .local_variables
+            .iter()
+            .filter(|variable| (0..variable.length as i32).contains(&(pc - variable.start_pc as i32)))
+            .collect();
+        variables.sort_by_key(|variable| variable.index);
+        variables
+    }
+
+    // the source line of the instruction at `pc`, None without a LineNumberTable
+    pub fn line_number(&self, pc: i32) -> Option<u16> {
+        self.line_numbers
+            .iter()
+            .filter(|entry| entry.start_pc as i32 <= pc)
+            .max_by_key(|entry| entry.start_pc)
+            .map(|entry| entry.line_number)
+    }
+}
+
+impl Code {
+    pub fn new(bytecode: &[u8]) -> Result<Code, Error> {
+        let mut instructions = Vec::new();
+        instructions.resize_with(bytecode.len(), || None);
+        let decoded = decode_code(bytecode)?;
+        let mut next_pcs: Vec<i32> = decoded.iter().skip(1).map(|(pc, _)| *pc as i32).collect();
+        next_pcs.push(bytecode.len() as i32);
+        for ((pc, instruction), next_pc) in decoded.into_iter().zip(next_pcs) {
+            instructions[pc] = Some(DecodedInstruction { instruction, next_pc });
+        }
+        Ok(Code { instructions })
+    }
+
+    // the bytes of bytecode
+    pub fn length(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn has_instruction_at(&self, pc: i32) -> bool {
+        matches!(self.instructions.get(pc as usize), Some(Some(_)))
+    }
+
+    pub fn instruction_at(&self, pc: i32) -> &DecodedInstruction {
+        match self.instructions.get(pc as usize) {
+            Some(Some(decoded)) => decoded,
+            _ => panic!("no instruction starts at pc {}", pc),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::classfile::class_reader::{get_class_name, Reader};
+use crate::classfile::format_check::check_format;
+use crate::classpath::Classpath;
+use crate::error::Error;
+use crate::log::{log, Logger};
+use crate::verifier::{verify_class, ClasspathHierarchy, VerifyMode};
+
+use super::class::{Class, PRIMITIVE_TYPES};
+
+// loads classes from the classpath on first use, super classes first, and keeps them for the life of the VM
+pub struct ClassLoader {
+    classpath: Classpath,
+    verify_mode: VerifyMode,
+    classes: RefCell<HashMap<String, Rc<Class>>>,
+    // how many names unique_name has handed out
+    generated: Cell<usize>,
+    // the dynamic proxy classes made so far, by the interfaces they implement
+    proxies: RefCell<HashMap<Vec<String>, Rc<Class>>>,
+    // the classes and array classes in the order they were loaded, for the debugger
+    order: RefCell<Vec<Rc<Class>>>,
+    // the classes whose super classes and interfaces are being loaded; meeting one again is a circularity
+    loading: RefCell<Vec<String>>,
+}
+
+impl ClassLoader {
+    pub fn new(classpath: Classpath, verify_mode: VerifyMode) -> ClassLoader {
+        ClassLoader {
+            classpath,
+            verify_mode,
+            classes: RefCell::new(HashMap::new()),
+            generated: Cell::new(0),
+            proxies: RefCell::new(HashMap::new()),
+            order: RefCell::new(Vec::new()),
+            loading: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn load_class(&self, class_name: &str) -> Result<Rc<Class>, Error> {
+        if let Some(class) = self.classes.borrow().get(class_name) {
+            return Ok(class.clone());
+        }
+        if class_name.starts_with('[') {
+            return self.load_array_class(class_name);
+        }
+        if PRIMITIVE_TYPES.iter().any(|(name, _)| *name == class_name) {
+            let class = Rc::new(Class::new_primitive(class_name));
+            self.classes.borrow_mut().insert(String::from(class_name), class.clone());
+            return Ok(class);
+        }
+        let (content, source) = self.classpath.load_class(String::from(class_name))?;
+        log!(self.logger(), Load, Debug, "defining {}", class_name);
+        self.define(class_name, content, self.verify_mode.should_verify(source))
+    }
+
+    // defines a class the VM made up itself, like the ones linking invokedynamic call sites; the classpath
+    // knows nothing about it, so it is not verified
+    pub fn define_class(&self, class_name: &str, content: Vec<u8>) -> Result<Rc<Class>, Error> {
+        self.define(class_name, content, false)
+    }
+
+    // a name no class has been given yet, `prefix` followed by a number
+    pub fn unique_name(&self, prefix: &str) -> String {
+        self.generated.set(self.generated.get() + 1);
+        format!("{}{}", prefix, self.generated.get())
+    }
+
+    // the proxy class implementing `interfaces`, in that order, if one has been made
+    pub fn proxy_class(&self, interfaces: &[String]) -> Option<Rc<Class>> {
+        self.proxies.borrow().get(interfaces).cloned()
+    }
+
+    pub fn add_proxy_class(&self, interfaces: Vec<String>, class: Rc<Class>) {
+        self.proxies.borrow_mut().insert(interfaces, class);
+    }
+
+    fn define(&self, class_name: &str, content: Vec<u8>, verify: bool) -> Result<Rc<Class>, Error> {
+        let reader = Reader {
+            content,
+            cursor: Cell::new(0),
+            attribute_lengths: RefCell::new(Vec::new()),
+            logger: self.classpath.logger().clone(),
+        };
+        let classfile = reader.parse_classfile()?;
+        check_format(&classfile).map_err(Error::ClassFormat)?;
+        let this_name = get_class_name(&classfile.constant_pool, &classfile.this_class).unwrap_or_default();
+        if this_name != class_name {
+            return Err(Error::WrongName(String::from(class_name), this_name));
+        }
+        if verify {
+            verify_class(&classfile, &ClasspathHierarchy::new(&self.classpath))?;
+        }
+        if self.loading.borrow().iter().any(|name| name == class_name) {
+            return Err(Error::ClassCircularity(String::from(class_name)));
+        }
+        let load_supers = || {
+            let super_class = match get_class_name(&classfile.constant_pool, &classfile.super_class) {
+                Some(super_name) => Some(self.load_class(&super_name)?),
+                None => None,
+            };
+            let interfaces = classfile.interfaces.iter().map(|interface| self.load_class(interface));
+            Ok::<_, Error>((super_class, interfaces.collect::<Result<Vec<_>, Error>>()?))
+        };
+        self.loading.borrow_mut().push(String::from(class_name));
+        let supers = load_supers();
+        self.loading.borrow_mut().pop();
+        let (super_class, interfaces) = supers?;
+        let class = Rc::new(Class::new(classfile, super_class, interfaces)?);
+        self.classes.borrow_mut().insert(String::from(class_name), class.clone());
+        self.order.borrow_mut().push(class.clone());
+        log!(self.logger(), Load, Info, "loaded {}{}", class_name, if verify { ", verified" } else { "" });
+        Ok(class)
+    }
+
+    // array classes are made up on first use; the class of a reference array loads its element class
+    fn load_array_class(&self, class_name: &str) -> Result<Rc<Class>, Error> {
+        let element = &class_name[1..];
+        let component = if element.starts_with('[') {
+            Some(self.load_class(element)?)
+        } else if let Some(element) = element.strip_prefix('L').and_then(|element| element.strip_suffix(';')) {
+            Some(self.load_class(element)?)
+        } else {
+            None
+        };
+        let class = Rc::new(Class::new_array(class_name, self.load_class("java/lang/Object")?, component));
+        self.classes.borrow_mut().insert(String::from(class_name), class.clone());
+        self.order.borrow_mut().push(class.clone());
+        log!(self.logger(), Load, Debug, "made array class {}", class_name);
+        Ok(class)
+    }
+
+    // whether the classes of the JDK are the built-in class library
+    pub fn is_builtin(&self) -> bool {
+        self.classpath.is_builtin()
+    }
+
+    pub(crate) fn logger(&self) -> &Rc<Logger> {
+        self.classpath.logger()
+    }
+
+    pub fn loaded_classes(&self) -> Vec<Rc<Class>> {
+        self.classes.borrow().values().cloned().collect()
+    }
+
+    // the classes and array classes loaded after the first `count` of them, in the order they were; primitive
+    // classes are left out
+    pub fn loaded_since(&self, count: usize) -> Vec<Rc<Class>> {
+        self.order.borrow().get(count..).unwrap_or_default().to_vec()
+    }
+}
@@ -0,0 +1,282 @@
+use std::mem;
+use std::ops::Index;
+use std::rc::Rc;
+use std::time::Instant;
+
+use crate::log::Logger;
+use crate::runtime::Object;
+
+use super::{mark, GcLog, GcStats, Heap, ObjectRef};
+
+// minor collections a young object survives before it is promoted
+const TENURING_THRESHOLD: u8 = 2;
+// old generation entries per card of the card table
+const CARD_SIZE: usize = 16;
+
+// where the object behind a handle lives: the index into eden, survivor space or the old generation
+#[derive(Clone, Copy)]
+enum Location {
+    Free,
+    Eden(u32),
+    Survivor(u32),
+    Old(u32),
+    // copied to survivor space by the minor collection in progress
+    Copied,
+}
+
+struct Entry {
+    handle: ObjectRef,
+    // minor collections survived
+    age: u8,
+    object: Object,
+}
+
+// a young generation of eden and survivor space in front of an old generation. New objects are
+// bump-allocated in eden; a minor collection copies the young objects reachable from the roots and
+// the dirty cards to survivor space, or to the old generation once they are old enough, and leaves
+// everything else behind. The old generation is only collected, by mark-sweep, when the heap is full
+pub struct GenerationalHeap {
+    handles: Vec<Location>,
+    free_handles: Vec<u32>,
+    eden: Vec<Option<Entry>>,
+    survivors: Vec<Option<Entry>>,
+    old: Vec<Option<Entry>>,
+    free_old: Vec<u32>,
+    // one flag per CARD_SIZE old entries, set when a reference is stored into one of them since
+    // the last minor collection, or when one of them still points into the young generation
+    cards: Vec<bool>,
+    // bytes taken by live objects, all of them and in eden and survivor space
+    used: usize,
+    young_used: usize,
+    max_size: usize,
+    young_size: usize,
+    log: GcLog,
+}
+
+impl GenerationalHeap {
+    pub fn new(max_size: usize, young_size: usize, verbose: bool, logger: Rc<Logger>) -> GenerationalHeap {
+        GenerationalHeap {
+            handles: Vec::new(),
+            free_handles: Vec::new(),
+            eden: Vec::new(),
+            survivors: Vec::new(),
+            old: Vec::new(),
+            free_old: Vec::new(),
+            cards: Vec::new(),
+            used: 0,
+            young_used: 0,
+            max_size,
+            young_size: young_size.min(max_size),
+            log: GcLog::new(verbose, logger),
+        }
+    }
+
+    fn new_handle(&mut self) -> ObjectRef {
+        match self.free_handles.pop() {
+            Some(index) => ObjectRef(index),
+            None => {
+                self.handles.push(Location::Free);
+                ObjectRef(self.handles.len() as u32 - 1)
+            }
+        }
+    }
+
+    fn free_handle(&mut self, entry: Entry) {
+        self.used -= entry.object.size();
+        self.handles[entry.handle.0 as usize] = Location::Free;
+        self.free_handles.push(entry.handle.0);
+    }
+
+    fn insert_eden(&mut self, object: Object) -> ObjectRef {
+        let handle = self.new_handle();
+        let size = object.size();
+        self.used += size;
+        self.young_used += size;
+        self.handles[handle.0 as usize] = Location::Eden(self.eden.len() as u32);
+        self.eden.push(Some(Entry { handle, age: 0, object }));
+        handle
+    }
+
+    fn insert_old(&mut self, object: Object) -> ObjectRef {
+        let handle = self.new_handle();
+        self.used += object.size();
+        self.promote(Entry { handle, age: 0, object });
+        handle
+    }
+
+    // moves `entry` into the old generation and returns its index there
+    fn promote(&mut self, entry: Entry) -> usize {
+        let index = match self.free_old.pop() {
+            Some(index) => index as usize,
+            None => {
+                self.old.push(None);
+                self.cards.resize(self.old.len().div_ceil(CARD_SIZE), false);
+                self.old.len() - 1
+            }
+        };
+        self.handles[entry.handle.0 as usize] = Location::Old(index as u32);
+        self.old[index] = Some(entry);
+        index
+    }
+
+    fn is_young(&self, object: ObjectRef) -> bool {
+        matches!(self.handles[object.0 as usize], Location::Eden(_) | Location::Survivor(_))
+    }
+
+    // copies the live young objects out of eden and survivor space; the old generation and the
+    // dirty cards stand in for the roots in it
+    fn minor_collect(&mut self, roots: Vec<ObjectRef>) {
+        let start = Instant::now();
+        let before = self.used;
+        let dirty: Vec<usize> = (0..self.cards.len()).filter(|card| self.cards[*card]).collect();
+        let mut pending = roots;
+        for card in &dirty {
+            for entry in self.old_entries(*card) {
+                pending.extend(entry.object.references());
+            }
+        }
+        let mut eden = mem::take(&mut self.eden);
+        let mut from = mem::take(&mut self.survivors);
+        let mut survivor_used = 0;
+        let mut promoted = Vec::new();
+        while let Some(object) = pending.pop() {
+            let entry = match self.handles[object.0 as usize] {
+                Location::Eden(index) => eden[index as usize].take(),
+                Location::Survivor(index) => from[index as usize].take(),
+                Location::Free | Location::Old(_) | Location::Copied => None,
+            };
+            let Some(mut entry) = entry else {
+                continue;
+            };
+            pending.extend(entry.object.references());
+            entry.age += 1;
+            let size = entry.object.size();
+            // survivor space takes a quarter of the nursery, what does not fit there is promoted early
+            if entry.age >= TENURING_THRESHOLD || survivor_used + size > self.young_size / 4 {
+                promoted.push(self.promote(entry));
+            } else {
+                survivor_used += size;
+                self.handles[entry.handle.0 as usize] = Location::Copied;
+                self.survivors.push(Some(entry));
+            }
+        }
+        for (index, entry) in self.survivors.iter().flatten().enumerate() {
+            self.handles[entry.handle.0 as usize] = Location::Survivor(index as u32);
+        }
+        let mut freed_objects = 0;
+        for entry in eden.into_iter().chain(from).flatten() {
+            self.free_handle(entry);
+            freed_objects += 1;
+        }
+        self.young_used = survivor_used;
+        // the cards to keep dirty are the ones still pointing into survivor space
+        for card in dirty.into_iter().chain(promoted.into_iter().map(|index| index / CARD_SIZE)) {
+            let dirty = self
+                .old_entries(card)
+                .any(|entry| entry.object.references().into_iter().any(|object| self.is_young(object)));
+            self.cards[card] = dirty;
+        }
+        self.log.record(true, start, before, self.used, self.max_size, freed_objects);
+    }
+
+    fn old_entries(&self, card: usize) -> impl Iterator<Item = &Entry> {
+        let end = (card * CARD_SIZE + CARD_SIZE).min(self.old.len());
+        self.old[card * CARD_SIZE..end].iter().flatten()
+    }
+}
+
+impl Heap for GenerationalHeap {
+    fn allocate(&mut self, object: Object, roots: &dyn Fn() -> Vec<ObjectRef>) -> Option<ObjectRef> {
+        let size = object.size();
+        if self.used + size > self.max_size {
+            self.collect(roots());
+            if self.used + size > self.max_size {
+                return None;
+            }
+        } else if size <= self.young_size && self.young_used + size > self.young_size {
+            self.minor_collect(roots());
+        }
+        // objects too big for the nursery, or that do not fit next to the survivors, go straight to the old generation
+        Some(if self.young_used + size <= self.young_size {
+            self.insert_eden(object)
+        } else {
+            self.insert_old(object)
+        })
+    }
+
+    fn allocate_past_limit(&mut self, object: Object) -> ObjectRef {
+        self.insert_old(object)
+    }
+
+    // marks and sweeps both generations, then promotes every young survivor so that the nursery starts out empty
+    fn collect(&mut self, roots: Vec<ObjectRef>) {
+        let start = Instant::now();
+        let before = self.used;
+        let marked = mark(self, self.handles.len(), roots);
+        let young: Vec<Entry> = mem::take(&mut self.eden).into_iter().chain(mem::take(&mut self.survivors)).flatten().collect();
+        let mut freed_objects = 0;
+        for index in 0..self.old.len() {
+            if self.old[index].as_ref().is_some_and(|entry| !marked[entry.handle.0 as usize]) {
+                let entry = self.old[index].take().expect("checked above");
+                self.free_handle(entry);
+                self.free_old.push(index as u32);
+                freed_objects += 1;
+            }
+        }
+        for entry in young {
+            if marked[entry.handle.0 as usize] {
+                self.promote(entry);
+            } else {
+                self.free_handle(entry);
+                freed_objects += 1;
+            }
+        }
+        self.young_used = 0;
+        self.cards.fill(false);
+        self.log.record(false, start, before, self.used, self.max_size, freed_objects);
+    }
+
+    fn write_barrier(&mut self, object: ObjectRef) {
+        if let Location::Old(index) = self.handles[object.0 as usize] {
+            self.cards[index as usize / CARD_SIZE] = true;
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.max_size
+    }
+
+    fn used(&self) -> usize {
+        self.used
+    }
+
+    fn stats(&self) -> GcStats {
+        self.log.stats
+    }
+
+    fn verbose(&self) -> bool {
+        self.log.verbose
+    }
+
+    fn take_log(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.log.lines)
+    }
+
+    fn get(&self, object: ObjectRef) -> Option<&Object> {
+        let entry = match self.handles.get(object.0 as usize)? {
+            Location::Eden(index) => &self.eden[*index as usize],
+            Location::Survivor(index) => &self.survivors[*index as usize],
+            Location::Old(index) => &self.old[*index as usize],
+            Location::Free | Location::Copied => &None,
+        };
+        entry.as_ref().map(|entry| &entry.object)
+    }
+}
+
+impl Index<ObjectRef> for GenerationalHeap {
+    type Output = Object;
+
+    fn index(&self, object: ObjectRef) -> &Object {
+        self.get(object).expect("reference to a collected object")
+    }
+}
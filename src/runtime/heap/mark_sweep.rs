@@ -0,0 +1,112 @@
+use std::ops::Index;
+use std::rc::Rc;
+use std::time::Instant;
+
+use crate::log::Logger;
+use crate::runtime::Object;
+
+use super::{mark, GcLog, GcStats, Heap, ObjectRef};
+
+// a single space collected by mark-sweep as a whole
+pub struct MarkSweepHeap {
+    objects: Vec<Option<Object>>,
+    // handles of swept objects, reused before the heap grows
+    free: Vec<u32>,
+    // bytes taken by live objects, and the -Xmx limit on that
+    used: usize,
+    max_size: usize,
+    log: GcLog,
+}
+
+impl MarkSweepHeap {
+    pub fn new(max_size: usize, verbose: bool, logger: Rc<Logger>) -> MarkSweepHeap {
+        MarkSweepHeap {
+            objects: Vec::new(),
+            free: Vec::new(),
+            used: 0,
+            max_size,
+            log: GcLog::new(verbose, logger),
+        }
+    }
+
+    fn insert(&mut self, object: Object) -> ObjectRef {
+        self.used += object.size();
+        match self.free.pop() {
+            Some(index) => {
+                self.objects[index as usize] = Some(object);
+                ObjectRef(index)
+            }
+            None => {
+                self.objects.push(Some(object));
+                ObjectRef(self.objects.len() as u32 - 1)
+            }
+        }
+    }
+}
+
+impl Heap for MarkSweepHeap {
+    fn allocate(&mut self, object: Object, roots: &dyn Fn() -> Vec<ObjectRef>) -> Option<ObjectRef> {
+        let size = object.size();
+        if self.used + size > self.max_size {
+            self.collect(roots());
+            if self.used + size > self.max_size {
+                return None;
+            }
+        }
+        Some(self.insert(object))
+    }
+
+    fn allocate_past_limit(&mut self, object: Object) -> ObjectRef {
+        self.insert(object)
+    }
+
+    fn collect(&mut self, roots: Vec<ObjectRef>) {
+        let start = Instant::now();
+        let before = self.used;
+        let marked = mark(self, self.objects.len(), roots);
+        let mut freed_objects = 0;
+        for (index, entry) in self.objects.iter_mut().enumerate() {
+            if marked[index] {
+                continue;
+            }
+            if let Some(object) = entry.take() {
+                self.used -= object.size();
+                self.free.push(index as u32);
+                freed_objects += 1;
+            }
+        }
+        self.log.record(false, start, before, self.used, self.max_size, freed_objects);
+    }
+
+    fn capacity(&self) -> usize {
+        self.max_size
+    }
+
+    fn used(&self) -> usize {
+        self.used
+    }
+
+    fn stats(&self) -> GcStats {
+        self.log.stats
+    }
+
+    fn verbose(&self) -> bool {
+        self.log.verbose
+    }
+
+    fn take_log(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.log.lines)
+    }
+
+    fn get(&self, object: ObjectRef) -> Option<&Object> {
+        self.objects.get(object.0 as usize)?.as_ref()
+    }
+}
+
+impl Index<ObjectRef> for MarkSweepHeap {
+    type Output = Object;
+
+    fn index(&self, object: ObjectRef) -> &Object {
+        self.get(object).expect("reference to a collected object")
+    }
+}
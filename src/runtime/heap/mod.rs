@@ -0,0 +1,262 @@
+mod generational;
+mod mark_sweep;
+
+use std::io::Write;
+use std::ops::Index;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::log::{log, Logger};
+
+use super::call_site::CallSite;
+use super::class::Class;
+use super::string::StringTable;
+use super::{Object, Slot, Thread};
+
+pub use self::generational::GenerationalHeap;
+pub use self::mark_sweep::MarkSweepHeap;
+
+// what an object costs on top of its fields when counted against -Xmx
+const OBJECT_HEADER: usize = 16;
+
+// a reference to an object on the heap, valid for as long as the object is reachable from the roots;
+// heaps map it to wherever the object lives, so moving an object does not change its references.
+// Two references are to the same object exactly when they are equal, which is what if_acmpeq compares
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObjectRef(u32);
+
+impl ObjectRef {
+    // a number telling the object apart from the others that are live, for the debugger to show
+    pub fn id(self) -> u32 {
+        self.0
+    }
+
+    // the reference with id `id`, which get tells whether is to a live object
+    pub fn from_id(id: u32) -> ObjectRef {
+        ObjectRef(id)
+    }
+}
+
+// an object heap and its garbage collector
+pub trait Heap: Index<ObjectRef, Output = Object> {
+    // puts `object` on the heap, collecting garbage first when it does not fit; None when even that
+    // does not make enough room. `roots` is only asked for when there is a collection
+    fn allocate(&mut self, object: Object, roots: &dyn Fn() -> Vec<ObjectRef>) -> Option<ObjectRef>;
+
+    // for the errors the VM throws itself when the heap is full, which have to go somewhere
+    fn allocate_past_limit(&mut self, object: Object) -> ObjectRef;
+
+    // a full collection: keeps everything reachable from `roots` and frees the rest
+    fn collect(&mut self, roots: Vec<ObjectRef>);
+
+    // called after a reference is stored into a field or element of `object`
+    fn write_barrier(&mut self, _object: ObjectRef) {}
+
+    // the -Xmx limit, in bytes
+    fn capacity(&self) -> usize;
+
+    // the bytes the objects on the heap take, live or not yet collected
+    fn used(&self) -> usize;
+
+    fn stats(&self) -> GcStats;
+
+    // -verbose:gc
+    fn verbose(&self) -> bool;
+
+    // the -verbose:gc lines of the collections since the last call, for the VM to write where System.out goes
+    fn take_log(&mut self) -> Vec<String>;
+
+    // the object `object` is to, None when it has been collected
+    fn get(&self, object: ObjectRef) -> Option<&Object>;
+
+    // the totals -verbose:gc reports when the VM exits
+    fn log_stats(&mut self, out: &mut dyn Write) {
+        let stats = self.stats();
+        if self.verbose() {
+            for line in self.take_log() {
+                let _ = writeln!(out, "{}", line);
+            }
+            let _ = writeln!(
+                out,
+                "[GC statistics: {} collections ({} minor), {} objects ({}K) freed, {:.7} secs]",
+                stats.collections,
+                stats.minor_collections,
+                stats.freed_objects,
+                stats.freed_bytes / 1024,
+                stats.pause.as_secs_f64()
+            );
+        }
+    }
+}
+
+// which collector manages the heap, picked with -Xgc
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcKind {
+    MarkSweep,
+    Generational,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GcStats {
+    pub collections: u32,
+    // how many of the collections only looked at the young generation
+    pub minor_collections: u32,
+    pub freed_objects: usize,
+    pub freed_bytes: usize,
+    pub pause: Duration,
+}
+
+// the statistics of a heap, and the -verbose:gc log of them
+struct GcLog {
+    verbose: bool,
+    stats: GcStats,
+    // the -verbose:gc lines not written yet
+    lines: Vec<String>,
+    // the VM's log, which gets every collection at the info level of the gc category
+    logger: Rc<Logger>,
+}
+
+impl FromStr for GcKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "marksweep" => Ok(GcKind::MarkSweep),
+            "generational" => Ok(GcKind::Generational),
+            _ => Err(format!("unknown garbage collector {}, expected marksweep|generational", s)),
+        }
+    }
+}
+
+// a heap of at most `max_size` bytes, `young_size` of which are the nursery of a generational heap
+pub fn new_heap(kind: GcKind, max_size: usize, young_size: usize, verbose: bool, logger: Rc<Logger>) -> Box<dyn Heap> {
+    match kind {
+        GcKind::MarkSweep => Box::new(MarkSweepHeap::new(max_size, verbose, logger)),
+        GcKind::Generational => Box::new(GenerationalHeap::new(max_size, young_size, verbose, logger)),
+    }
+}
+
+impl GcLog {
+    fn new(verbose: bool, logger: Rc<Logger>) -> GcLog {
+        GcLog {
+            verbose,
+            logger,
+            stats: GcStats::default(),
+            lines: Vec::new(),
+        }
+    }
+
+    // counts a collection that started at `start` and took the heap from `before` bytes to `after`
+    fn record(&mut self, minor: bool, start: Instant, before: usize, after: usize, capacity: usize, freed_objects: usize) {
+        let pause = start.elapsed();
+        self.stats.collections += 1;
+        self.stats.minor_collections += minor as u32;
+        self.stats.freed_objects += freed_objects;
+        self.stats.freed_bytes += before - after;
+        self.stats.pause += pause;
+        log!(
+            self.logger,
+            Gc,
+            Info,
+            "{} collection: {}K->{}K of {}K, {} objects freed in {:.7} secs",
+            if minor { "minor" } else { "full" },
+            before / 1024,
+            after / 1024,
+            capacity / 1024,
+            freed_objects,
+            pause.as_secs_f64()
+        );
+        if self.verbose {
+            self.lines.push(format!(
+                "[{} (Allocation Failure) {}K->{}K({}K), {:.7} secs]",
+                if minor { "GC" } else { "Full GC" },
+                before / 1024,
+                after / 1024,
+                capacity / 1024,
+                pause.as_secs_f64()
+            ));
+        }
+    }
+}
+
+impl Object {
+    fn size(&self) -> usize {
+        if !self.class.is_array() {
+            return OBJECT_HEADER + 8 * self.fields.borrow().len();
+        }
+        // arrays are counted at the width of their elements, plus the length
+        let element_size = match self.class.name.as_bytes()[1] {
+            b'Z' | b'B' => 1,
+            b'C' | b'S' => 2,
+            b'I' | b'F' => 4,
+            _ => 8,
+        };
+        OBJECT_HEADER + 4 + element_size * self.array_length()
+    }
+
+    fn references(&self) -> Vec<ObjectRef> {
+        self.fields.borrow().iter().filter_map(reference).collect()
+    }
+}
+
+// everything the program can still get at objects through: the static fields and Class objects
+// of loaded classes, the interned strings, the global references of native code, and the frames,
+// Thread objects and monitors of live threads
+pub fn roots<'a>(
+    classes: &[Rc<Class>],
+    strings: &StringTable,
+    global_refs: &[Option<ObjectRef>],
+    threads: impl IntoIterator<Item = &'a Thread>,
+) -> Vec<ObjectRef> {
+    let mut roots: Vec<ObjectRef> = strings.strings().collect();
+    roots.extend(global_refs.iter().flatten());
+    for class in classes {
+        roots.extend(class.static_fields.borrow().iter().filter_map(reference));
+        roots.extend(class.mirror.get());
+        for method in &class.methods {
+            roots.extend(method.call_sites.borrow().values().filter_map(|call_site| match call_site {
+                CallSite::Handle(target) => Some(*target),
+                CallSite::Method(..) => None,
+            }));
+        }
+    }
+    for thread in threads {
+        roots.extend(thread.object);
+        roots.extend(thread.reacquire.map(|(object, _)| object));
+        roots.extend_from_slice(&thread.handles);
+        roots.extend(thread.local_refs.iter().flatten());
+        match &thread.outcome {
+            Some(Ok(value)) => roots.extend(value.iter().filter_map(reference)),
+            Some(Err(exception)) => roots.push(*exception),
+            None => {}
+        }
+        for frame in &thread.stack.frames {
+            roots.extend(frame.local_vars.0.iter().filter_map(reference));
+            roots.extend(frame.operand_stack.slots.iter().filter_map(reference));
+            roots.extend(frame.monitor);
+        }
+    }
+    roots
+}
+
+// which of the `handles` handles of `heap` are reachable from `roots`
+fn mark<H: Index<ObjectRef, Output = Object> + ?Sized>(heap: &H, handles: usize, roots: Vec<ObjectRef>) -> Vec<bool> {
+    let mut marked = vec![false; handles];
+    let mut pending = roots;
+    while let Some(object) = pending.pop() {
+        let index = object.0 as usize;
+        if !marked[index] {
+            marked[index] = true;
+            pending.extend(heap[object].references());
+        }
+    }
+    marked
+}
+
+fn reference(slot: &Slot) -> Option<ObjectRef> {
+    match slot {
+        Slot::Reference(object) => *object,
+        _ => None,
+    }
+}
@@ -1,307 +1,611 @@
+pub mod call_site;
+pub mod class;
+pub mod class_loader;
+pub mod heap;
+pub mod monitor;
+pub mod scheduler;
+pub mod string;
+#[cfg(test)]
+mod tests;
+
 use std::cell::{Cell, RefCell};
+use std::fmt;
 use std::rc::Rc;
-use std::thread::sleep;
-use std::time::Duration;
 
-use crate::classfile::attribute::AttributeInfo;
-use crate::classfile::class_reader::MethodInfo;
-use crate::instructions::bitcode_reader::BytecodeReader;
-use crate::instructions::new_instruction;
+use crate::error::Error;
+
+use self::call_site::{MethodHandleRef, VarHandleRef};
+use self::class::{Class, Method};
+use self::heap::{Heap, ObjectRef};
+use self::monitor::Monitor;
+
+// stack traces are cut off after this many frames, like HotSpot's MaxJavaStackTraceDepth
+const MAX_STACK_TRACE_DEPTH: usize = 1024;
+// what a frame costs on top of its slots when counted against -Xss
+const FRAME_OVERHEAD: usize = 64;
+// java.lang.Thread.threadStatus values, as JVMTI defines them
+pub const THREAD_STATUS_RUNNABLE: i32 = 0x0005;
+pub const THREAD_STATUS_TERMINATED: i32 = 0x0002;
+
+pub struct Object {
+    pub class: Rc<Class>,
+    pub fields: RefCell<Vec<Slot>>,
+    pub monitor: Monitor,
+    // the identity hash, 0 until something asks for it; it moves with the object when a collector copies it
+    hash: Cell<i32>,
+    // for java.lang.Class objects, the class they stand for
+    pub mirror_of: Option<Rc<Class>>,
+    // for the java.lang.invoke.MethodHandle objects the VM makes, the field or method they refer to
+    pub handle_of: Option<Rc<MethodHandleRef>>,
+    // for the java.lang.invoke.VarHandle objects the VM makes, the variable they refer to
+    pub var_handle_of: Option<Rc<VarHandleRef>>,
+    // where a throwable was created, filled in by Throwable.fillInStackTrace
+    pub stack_trace: RefCell<Vec<StackTraceElement>>,
+}
 
-#[derive(Clone, Debug)]
-pub struct Object {}
+pub struct StackTraceElement {
+    pub class_name: String,
+    pub method_name: String,
+    pub file_name: Option<String>,
+    pub line_number: Option<u16>,
+    pub native: bool,
+}
 
+// a Java thread; the scheduler runs all of them on the one OS thread
 pub struct Thread {
-    pub pc: i32,
+    pub id: u64,
+    pub name: String,
+    pub daemon: bool,
+    pub state: ThreadState,
+    // the java.lang.Thread object, created on first use for the main thread
+    pub object: Option<ObjectRef>,
+    // the monitor a thread back from Object.wait has to take again before it goes on,
+    // and how many times it had entered it
+    pub reacquire: Option<(ObjectRef, u32)>,
     pub stack: Stack,
+    // a thread the embedding API calls into Java on: it outlives its frames, waiting for the next call,
+    // and keeps how the last call ended in `outcome` instead of returning to a caller or reporting an exception
+    pub embedded: bool,
+    pub outcome: Option<Result<Vec<Slot>, ObjectRef>>,
+    // the objects the embedder holds on to, alive for as long as they are here
+    pub handles: Vec<ObjectRef>,
+    // what the local references of the natives running on the thread through JNI refer to, by index;
+    // None once deleted
+    pub local_refs: Vec<Option<ObjectRef>>,
+    // the state of the identity hash generator
+    hash_state: [u32; 4],
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThreadState {
+    Runnable,
+    // until a point in the scheduler's virtual time, in nanoseconds
+    Sleeping { until: u64 },
+    // until the thread with id `thread` terminates, or the time is up
+    Joining { thread: u64, until: Option<u64> },
+    // on entering a monitor another thread owns, or on a class another thread is initializing
+    Blocked,
+    // in Object.wait, until notified or the time is up
+    Waiting { until: Option<u64> },
+    Terminated,
+}
+
+// the frames of a thread, innermost last; the size is counted in bytes so -Xss bounds the depth
 pub struct Stack {
     max_size: usize,
     size: usize,
-    frames:  Vec<Frame>
+    frames: Vec<Frame>,
 }
 
-pub struct LocalVars(Vec<Slot>);
+pub struct LocalVars(pub(crate) Vec<Slot>);
 
 pub struct Frame {
+    pub class: Rc<Class>,
+    pub method: Rc<Method>,
     pub local_vars: LocalVars,
     pub operand_stack: OperandStack,
-    pub next_pc: i32
+    // what a synchronized method locked on entry, to unlock on the way out
+    pub monitor: Option<ObjectRef>,
+    // the instruction being executed and the one after it
+    pub pc: i32,
+    pub next_pc: i32,
+    // for a bootstrap method, the pc of the invokedynamic in the caller its CallSite links
+    pub links_call_site: Option<i32>,
+    // for a frame native code called through JNI, that its return or uncaught exception goes to the thread's
+    // outcome for the native to pick up, rather than to the frame below
+    pub returns_to_native: bool,
+    // whether it has run an instruction yet, which -Xtrace:call logs the call at
+    pub started: bool,
+    // for a <clinit> frame, the classes initialized once it returns: its own and the subclasses without a <clinit>
+    // pushed along with it
+    pub initializes: Vec<Rc<Class>>,
 }
 
-// TODO clone option/box
-#[derive(Clone, Debug)]
-pub struct Slot {
-    num: i32,
-    reference: Option<Rc<Object>>,
+// one local variable or operand stack entry, tagged with what was stored in it;
+// a long or double takes two slots, the low word in the first one
+#[derive(Clone, Debug, Default)]
+pub enum Slot {
+    #[default]
+    Uninitialized,
+    Int(i32),
+    Float(f32),
+    Long { bits: u32, first: bool },
+    Double { bits: u32, first: bool },
+    Reference(Option<ObjectRef>),
 }
 
 pub struct OperandStack {
-    size: usize,
+    max_stack: usize,
     slots: Vec<Slot>,
 }
 
-impl Thread{
-    pub fn new_thread() -> Thread{
-        return Thread {
-            pc: 0,
-            stack: Stack::new_stack(1024),
-        };
+impl Object {
+    pub fn new(class: Rc<Class>) -> Object {
+        Object {
+            fields: RefCell::new(class.instance_fields.clone()),
+            monitor: Monitor::default(),
+            hash: Cell::new(0),
+            class,
+            mirror_of: None,
+            handle_of: None,
+            var_handle_of: None,
+            stack_trace: RefCell::new(Vec::new()),
+        }
+    }
+
+    // a new object of the same class with the same field values, what Object.clone makes
+    pub fn copy(&self) -> Object {
+        Object {
+            fields: RefCell::new(self.fields.borrow().clone()),
+            ..Object::new(self.class.clone())
+        }
+    }
+}
+
+impl Object {
+    // an array of `length` elements of `class`, an array class, each holding its type's default value;
+    // the elements are kept in the fields, two slots each for long and double
+    pub fn new_array(class: Rc<Class>, length: usize) -> Object {
+        let element = Slot::zero(&class.name[1..]);
+        let fields = (0..length).flat_map(|_| element.clone()).collect();
+        Object {
+            fields: RefCell::new(fields),
+            ..Object::new(class)
+        }
+    }
+
+    // the number of elements of an array
+    pub fn array_length(&self) -> usize {
+        self.fields.borrow().len() / Slot::zero(&self.class.name[1..]).len()
+    }
+
+    // what Object.hashCode and System.identityHashCode return, assigned on first use
+    pub fn identity_hash(&self, thread: &mut Thread) -> i32 {
+        if self.hash.get() == 0 {
+            self.hash.set(thread.next_hash());
+        }
+        self.hash.get()
+    }
+
+    // instance field access by name, for natives working with fields of JDK classes
+    fn field_slot(&self, name: &str, descriptor: &str) -> usize {
+        match self.class.find_field(name, descriptor) {
+            Some((_, field)) if !field.is_static() => field.slot,
+            _ => panic!("{} has no field {} {}", self.class.java_name(), name, descriptor),
+        }
+    }
+
+    pub fn get_int_field(&self, name: &str, descriptor: &str) -> i32 {
+        self.fields.borrow()[self.field_slot(name, descriptor)].int()
+    }
+
+    pub fn set_int_field(&self, name: &str, descriptor: &str, value: i32) {
+        let slot = self.field_slot(name, descriptor);
+        self.fields.borrow_mut()[slot] = Slot::Int(value);
+    }
+
+    pub fn get_long_field(&self, name: &str) -> i64 {
+        let slot = self.field_slot(name, "J");
+        let fields = self.fields.borrow();
+        Slot::join(&fields[slot], &fields[slot + 1], false) as i64
     }
 
-    pub fn pc(&self) -> i32 {
-        return self.pc;
+    pub fn set_long_field(&self, name: &str, value: i64) {
+        let slot = self.field_slot(name, "J");
+        let [first, second] = Slot::wide(value as u64, false);
+        let mut fields = self.fields.borrow_mut();
+        fields[slot] = first;
+        fields[slot + 1] = second;
     }
 
-    pub fn set_pc(&mut self, pc: i32) {
-        self.pc = pc;
+    pub fn get_ref_field(&self, name: &str, descriptor: &str) -> Option<ObjectRef> {
+        self.fields.borrow()[self.field_slot(name, descriptor)].reference()
     }
 
-    pub fn pop_frame(&mut self) -> Frame {
-        return self.stack.pop();
+    pub fn set_ref_field(&self, name: &str, descriptor: &str, value: Option<ObjectRef>) {
+        let slot = self.field_slot(name, descriptor);
+        self.fields.borrow_mut()[slot] = Slot::Reference(value);
+    }
+}
+
+impl fmt::Debug for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{:p}", self.class.java_name(), self)
+    }
+}
+
+impl fmt::Display for StackTraceElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}(", self.class_name, self.method_name)?;
+        match (&self.file_name, self.line_number) {
+            _ if self.native => write!(f, "Native Method)"),
+            (Some(file_name), Some(line_number)) => write!(f, "{}:{})", file_name, line_number),
+            (Some(file_name), None) => write!(f, "{})", file_name),
+            (None, _) => write!(f, "Unknown Source)"),
+        }
+    }
+}
+
+impl Thread {
+    pub fn new_thread(id: u64, name: String, stack_size: usize) -> Thread {
+        Thread {
+            id,
+            name,
+            daemon: false,
+            state: ThreadState::Runnable,
+            object: None,
+            reacquire: None,
+            stack: Stack::new_stack(stack_size),
+            embedded: false,
+            outcome: None,
+            handles: Vec::new(),
+            local_refs: Vec::new(),
+            // HotSpot's seeds, with the thread id in place of a random number
+            hash_state: [id as u32, 842502087, 0x8767, 273326509],
+        }
+    }
+
+    // HotSpot's default identity hash: Marsaglia's xor-shift on per-thread state, cut to 31 bits and never 0
+    fn next_hash(&mut self) -> i32 {
+        let [x, y, z, w] = self.hash_state;
+        let t = x ^ (x << 11);
+        let v = (w ^ (w >> 19)) ^ (t ^ (t >> 8));
+        self.hash_state = [y, z, w, v];
+        match v & 0x7fff_ffff {
+            0 => 0xbad,
+            hash => hash as i32,
+        }
+    }
+
+    pub fn current_frame(&mut self) -> Option<&mut Frame> {
+        self.stack.frames.last_mut()
     }
 
-    pub fn current_frame(&mut self) -> &mut Frame {
-        return self.stack.top();
+    pub fn frame(&self) -> Option<&Frame> {
+        self.stack.frames.last()
     }
 
-    pub fn push_frame(&mut self, frame:  Frame) {
-        return self.stack.push(frame);
+    // the frames from the innermost one out
+    pub fn frames(&self) -> impl Iterator<Item = &Frame> {
+        self.stack.frames.iter().rev()
+    }
+
+    // how many frames are on the stack
+    pub fn depth(&self) -> usize {
+        self.stack.frames.len()
+    }
+
+    pub fn pop_frame(&mut self) -> Option<Frame> {
+        self.stack.pop()
+    }
+
+    pub fn push_frame(&mut self, frame: Frame) -> Result<(), Error> {
+        self.stack.push(frame)
+    }
+
+    // what the JVM does when a thread's last frame is gone: Thread.isAlive turns false and joiners are released
+    pub fn terminate(&mut self, heap: &dyn Heap) {
+        self.state = ThreadState::Terminated;
+        if let Some(object) = self.object {
+            let object = &heap[object];
+            object.set_long_field("eetop", 0);
+            object.set_int_field("threadStatus", "I", THREAD_STATUS_TERMINATED);
+        }
+    }
+
+    // the classes of the methods on the stack, from the innermost one out
+    pub fn frame_classes(&self) -> impl Iterator<Item = &Rc<Class>> {
+        self.stack.frames.iter().rev().map(|frame| &frame.class)
+    }
+
+    // the frames from the innermost one out, as Throwable.getStackTrace reports them
+    pub fn stack_trace(&self) -> Vec<StackTraceElement> {
+        self.stack
+            .frames
+            .iter()
+            .rev()
+            .take(MAX_STACK_TRACE_DEPTH)
+            .map(|frame| StackTraceElement {
+                class_name: frame.class.java_name(),
+                method_name: frame.method.name.clone(),
+                file_name: frame.class.source_file.clone(),
+                line_number: frame.method.line_number(frame.pc),
+                native: frame.method.is_native(),
+            })
+            .collect()
     }
 }
 
 impl Stack {
     fn new_stack(max_size: usize) -> Stack {
-        return Stack {
+        Stack {
             max_size,
             size: 0,
             frames: Vec::new(),
-        };
-    }
-
-    pub fn push(&mut self, frame: Frame) {
-        if self.size >= self.max_size {
-            panic!("stackOverflow");
         }
-        if self.frames.is_empty() {
-            self.frames.push(frame);
-        }
-        self.size += 1;
     }
 
-    pub fn pop(&mut self) -> Frame {
-        if self.frames.is_empty() {
-            panic!("stack is empty");
+    pub fn push(&mut self, frame: Frame) -> Result<(), Error> {
+        let size = frame.size();
+        if self.size + size > self.max_size {
+            return Err(Error::StackOverflow);
         }
-        self.size -= 1;
-        return self.frames.remove(self.size);
+        self.size += size;
+        self.frames.push(frame);
+        Ok(())
     }
 
-    pub fn top(&mut self) -> &mut Frame {
-        if self.frames.is_empty() {
-            panic!("stack is empty");
-        }
-        return self.frames.get_mut(0).unwrap();
+    pub fn pop(&mut self) -> Option<Frame> {
+        let frame = self.frames.pop()?;
+        self.size -= frame.size();
+        Some(frame)
     }
 }
 
 impl Frame {
-    pub fn new_frame(max_local: usize, max_stack: usize) -> Frame {
-        return Frame {
-            local_vars: LocalVars::new_local_vars(max_local),
-            operand_stack: OperandStack::new_operand_stack(max_stack),
-            next_pc: 0
-        };
+    pub fn new(class: Rc<Class>, method: Rc<Method>) -> Frame {
+        Frame {
+            local_vars: LocalVars::new_local_vars(method.max_locals),
+            operand_stack: OperandStack::new_operand_stack(method.max_stack),
+            class,
+            method,
+            monitor: None,
+            pc: 0,
+            next_pc: 0,
+            links_call_site: None,
+            returns_to_native: false,
+            started: false,
+            initializes: Vec::new(),
+        }
+    }
+
+    fn size(&self) -> usize {
+        FRAME_OVERHEAD + 8 * (self.method.max_locals + self.method.max_stack)
     }
 }
 
 impl OperandStack {
-    fn new_operand_stack(max_stack: usize) -> OperandStack {
-        return OperandStack {
-            size: 0,
-            slots: vec![Slot::new_slot(); max_stack],
-        };
+    pub(crate) fn new_operand_stack(max_stack: usize) -> OperandStack {
+        OperandStack {
+            max_stack,
+            slots: Vec::with_capacity(max_stack),
+        }
     }
 
     pub(crate) fn push_int(&mut self, value: i32) {
-        self.slots[self.size].num = value;
-        // TOOD remove
-        println!("OperandStack push_int[{}] {}", self.size, value);
-        self.size += 1;
+        self.push_slot(Slot::Int(value));
     }
 
     pub(crate) fn pop_int(&mut self) -> i32 {
-        self.size -= 1;
-        println!("OperandStack pop_int[{}] {}", self.size, self.slots[self.size].num);
-        return self.slots[self.size].num;
+        self.pop_slot().int()
     }
 
     pub(crate) fn push_float(&mut self, value: f32) {
-        self.slots[self.size].num = value.to_bits() as i32;
-        self.size += 1;
+        self.push_slot(Slot::Float(value));
     }
 
     pub(crate) fn pop_float(&mut self) -> f32 {
-        self.size -= 1;
-        return f32::from_bits(self.slots[self.size].num as u32);
+        self.pop_slot().float()
     }
 
-    pub(crate) fn push_double(&mut self, value: f64) {
-        self.push_long(value.to_bits() as i64)
+    pub(crate) fn push_long(&mut self, value: i64) {
+        let [first, second] = Slot::wide(value as u64, false);
+        self.push_slot(first);
+        self.push_slot(second);
     }
 
-    pub(crate) fn pop_double(&mut self) -> f64 {
-        return f64::from_bits(self.pop_long() as u64);
+    pub(crate) fn pop_long(&mut self) -> i64 {
+        let second = self.pop_slot();
+        let first = self.pop_slot();
+        Slot::join(&first, &second, false) as i64
     }
 
-    pub(crate) fn push_long(&mut self, value: i64) {
-        self.slots[self.size].num = value as i32;
-        self.slots[self.size + 1].num = (value >> 32) as i32;
-        self.size += 2;
+    pub(crate) fn push_double(&mut self, value: f64) {
+        let [first, second] = Slot::wide(value.to_bits(), true);
+        self.push_slot(first);
+        self.push_slot(second);
     }
 
-    pub(crate) fn pop_long(&mut self) -> i64 {
-        self.size -= 2;
-        let low = self.slots[self.size].num as u32 as i64;
-        let high = self.slots[self.size + 1].num as i64;
-        return high << 32 | low;
+    pub(crate) fn pop_double(&mut self) -> f64 {
+        let second = self.pop_slot();
+        let first = self.pop_slot();
+        f64::from_bits(Slot::join(&first, &second, true))
     }
 
-    pub(crate) fn push_ref(&mut self, value: Option<Rc<Object>>) {
-        self.slots[self.size].reference = value;
-        self.size += 1;
+    pub(crate) fn push_ref(&mut self, value: Option<ObjectRef>) {
+        self.push_slot(Slot::Reference(value));
     }
 
-    pub(crate) fn pop_ref(&mut self) -> Option<Rc<Object>> {
-        self.size -= 1;
-        let slot_removed = self.slots.remove(self.size);
-        self.slots[self.size] = (Slot {
-            num: slot_removed.num,
-            reference: Option::None,
-        });
-        return slot_removed.reference;
+    pub(crate) fn pop_ref(&mut self) -> Option<ObjectRef> {
+        self.pop_slot().reference()
     }
 
     pub(crate) fn push_slot(&mut self, value: Slot) {
-        self.slots[self.size] = value;
-        self.size += 1;
+        if self.slots.len() >= self.max_stack {
+            panic!("operand stack overflow");
+        }
+        self.slots.push(value);
     }
 
     pub(crate) fn pop_slot(&mut self) -> Slot {
-        self.size -= 1;
-        let slot_removed = self.slots.remove(self.size);
-        self.push_slot(Slot {
-            num: 0,
-            reference: Option::None,
-        });
-        return slot_removed;
+        self.slots.pop().expect("operand stack underflow")
+    }
+
+    // the top `count` slots, deepest first
+    pub(crate) fn pop_slots(&mut self, count: usize) -> Vec<Slot> {
+        let len = self.slots.len();
+        if count > len {
+            panic!("operand stack underflow");
+        }
+        self.slots.split_off(len - count)
+    }
+
+    // how many slots are on the stack
+    pub(crate) fn depth(&self) -> usize {
+        self.slots.len()
+    }
+
+    // the slots from the bottom up
+    pub(crate) fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    // the slot `depth` entries below the top, 0 being the top
+    pub(crate) fn peek_slot(&self, depth: usize) -> &Slot {
+        self.slots.iter().rev().nth(depth).expect("operand stack underflow")
+    }
+
+    // replaces the `count` slots lying `depth` slots below the top with `slots`, which is how the VM takes
+    // a method handle from under its arguments; it may leave one slot past max_stack, for the object a
+    // constructor handle passes to <init> and then leaves behind
+    pub(crate) fn splice_below(&mut self, depth: usize, count: usize, slots: Vec<Slot>) {
+        let end = self.slots.len().checked_sub(depth).expect("operand stack underflow");
+        self.slots.splice(end - count..end, slots);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.slots.clear();
     }
 }
 
 impl Slot {
-    fn new_slot() -> Slot {
-        return Slot {
-            num: 0,
-            reference: None,
+    // the default value of a field with the given descriptor, two slots for a long or double
+    pub(crate) fn zero(descriptor: &str) -> Vec<Slot> {
+        match descriptor.as_bytes().first() {
+            Some(b'J') => Slot::wide(0, false).to_vec(),
+            Some(b'D') => Slot::wide(0, true).to_vec(),
+            Some(b'F') => vec![Slot::Float(0.0)],
+            Some(b'L' | b'[') => vec![Slot::Reference(None)],
+            _ => vec![Slot::Int(0)],
+        }
+    }
+
+    // the two slots of a long or double, first slot first
+    pub(crate) fn wide(bits: u64, double: bool) -> [Slot; 2] {
+        let half = |bits: u32, first: bool| if double {
+            Slot::Double { bits, first }
+        } else {
+            Slot::Long { bits, first }
         };
+        [half(bits as u32, true), half((bits >> 32) as u32, false)]
+    }
+
+    pub(crate) fn join(first: &Slot, second: &Slot, double: bool) -> u64 {
+        (second.half(double, false) as u64) << 32 | first.half(double, true) as u64
+    }
+
+    fn half(&self, double: bool, first: bool) -> u32 {
+        match self {
+            Slot::Long { bits, first: f } if !double && *f == first => *bits,
+            Slot::Double { bits, first: f } if double && *f == first => *bits,
+            _ => self.mismatch(if double { "half of a double" } else { "half of a long" }),
+        }
+    }
+
+    pub(crate) fn int(&self) -> i32 {
+        match self {
+            Slot::Int(value) => *value,
+            _ => self.mismatch("int") as i32,
+        }
+    }
+
+    pub(crate) fn float(&self) -> f32 {
+        match self {
+            Slot::Float(value) => *value,
+            _ => f32::from_bits(self.mismatch("float")),
+        }
+    }
+
+    pub(crate) fn reference(&self) -> Option<ObjectRef> {
+        match self {
+            Slot::Reference(value) => *value,
+            _ => {
+                self.mismatch("reference");
+                None
+            }
+        }
+    }
+
+    // debug builds stop at a read of the wrong type, release builds reinterpret the bits like an untyped JVM slot
+    fn mismatch(&self, expected: &str) -> u32 {
+        if cfg!(debug_assertions) {
+            panic!("expected {} but the slot holds {:?}", expected, self);
+        }
+        match self {
+            Slot::Int(value) => *value as u32,
+            Slot::Float(value) => value.to_bits(),
+            Slot::Long { bits, .. } | Slot::Double { bits, .. } => *bits,
+            Slot::Uninitialized | Slot::Reference(_) => 0,
+        }
     }
 }
 
 impl LocalVars {
-    fn new_local_vars(max_size: usize) -> LocalVars {
-        return LocalVars(vec![Slot::new_slot(); max_size]);
+    pub(crate) fn new_local_vars(max_size: usize) -> LocalVars {
+        LocalVars(vec![Slot::Uninitialized; max_size])
     }
 
     pub fn set_int(&mut self, index: usize, value: i32) {
-        self.0[index].num = value;
-        // TODO remove
-        println!("LocalVars set_int[{}] value = {}", index, value);
+        self.0[index] = Slot::Int(value);
     }
 
     pub fn get_int(&self, index: usize) -> i32 {
-        return self.0[index].num;
+        self.0[index].int()
     }
 
     pub(crate) fn get_float(&self, index: usize) -> f32 {
-        return f32::from_bits(self.0[index].num as u32);
+        self.0[index].float()
     }
 
     pub(crate) fn set_float(&mut self, index: usize, value: f32) {
-        self.0[index].num = value.to_bits() as i32;
+        self.0[index] = Slot::Float(value);
     }
 
     pub(crate) fn set_long(&mut self, index: usize, value: i64) {
-        self.0[index].num = value as i32;
-        self.0[index + 1].num = (value >> 32) as i32;
+        let [first, second] = Slot::wide(value as u64, false);
+        self.0[index] = first;
+        self.0[index + 1] = second;
     }
 
     pub(crate) fn get_long(&self, index: usize) -> i64 {
-        let low = self.0[index].num as u32 as i64;
-        let high = self.0[index + 1].num as i64;
-        return high << 32 | low;
+        Slot::join(&self.0[index], &self.0[index + 1], false) as i64
     }
 
     pub(crate) fn set_double(&mut self, index: usize, value: f64) {
-        self.set_long(index, value.to_bits() as i64)
+        let [first, second] = Slot::wide(value.to_bits(), true);
+        self.0[index] = first;
+        self.0[index + 1] = second;
     }
 
     pub(crate) fn get_double(&self, index: usize) -> f64 {
-        return f64::from_bits(self.get_long(index) as u64);
+        f64::from_bits(Slot::join(&self.0[index], &self.0[index + 1], true))
     }
 
-    pub(crate) fn set_ref(&mut self, index: usize, value: Option<Rc<Object>>) {
-        self.0[index].reference = value;
+    pub(crate) fn set_ref(&mut self, index: usize, value: Option<ObjectRef>) {
+        self.0[index] = Slot::Reference(value);
     }
 
-    pub(crate) fn get_ref(&mut self, index: usize) -> Option<Rc<Object>> {
-        let slot_removed = self.0.remove(index);
-        self.0.push(Slot {
-            num: slot_removed.num,
-            reference: slot_removed.reference.clone(),
-        });
-        return slot_removed.reference;
-    }
-}
-
-pub fn interpret(method: &MethodInfo) {
-    let attribute_list = &method.attribute_info;
-    for attribute in attribute_list {
-        if let AttributeInfo::CodeAttribute{max_stacks, max_locals,code_length,code,exception_table,attributes} = attribute {
-            let mut thread = Thread::new_thread();
-            let mut frame = Frame::new_frame(*max_locals as usize, *max_stacks as usize);
-            thread.push_frame(frame);
-            // TODO copy???
-            inner_loop(&mut thread, code.to_owned());
-        }
-    }
-}
-
-pub fn inner_loop(thread: &mut Thread, bytecode: Vec<u8>){
-    let mut frame = thread.pop_frame();
-    let mut reader = BytecodeReader {
-        content: bytecode,
-        cursor: Cell::new(0),
-    };
-    while true {
-        let next_pc = frame.next_pc;
-        thread.set_pc(next_pc);
-        reader.reset( next_pc);
-        let opcode = reader.read_u8().unwrap();
-	    let mut inst =  new_instruction(opcode);
-		inst.fetch_operands(&reader);
-        let pc = reader.cursor.get();
-        unsafe {
-            frame.next_pc = pc;
-		    println!("pc:{} inst:{:?}", pc, inst);
-		    // execute
-		    inst.execute(&mut frame, thread);
-            for local in &frame.local_vars.0 {
-                println!("local vars = {}", local.num);
-            }
-            println!("");
-        }
-        sleep(Duration::from_millis(10))
+    pub(crate) fn get_ref(&self, index: usize) -> Option<ObjectRef> {
+        self.0[index].reference()
     }
-    // todo
 }
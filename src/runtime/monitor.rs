@@ -0,0 +1,93 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+// the lock and condition every object carries; threads are named by id, and waking a blocked
+// or waiting thread is left to the caller, which has the scheduler
+#[derive(Default)]
+pub struct Monitor {
+    owner: Cell<Option<u64>>,
+    // how many times the owner has entered the monitor
+    count: Cell<u32>,
+    // threads blocked on entering, in arrival order
+    entry_queue: RefCell<VecDeque<u64>>,
+    // threads in Object.wait, in arrival order
+    wait_set: RefCell<VecDeque<u64>>,
+}
+
+impl Monitor {
+    // enters the monitor `count` times, or queues `thread` to be woken when it is released
+    pub fn try_enter(&self, thread: u64, count: u32) -> bool {
+        match self.owner.get() {
+            None => {
+                self.owner.set(Some(thread));
+                self.count.set(count);
+                self.entry_queue.borrow_mut().retain(|queued| *queued != thread);
+                true
+            }
+            Some(owner) if owner == thread => {
+                self.count.set(self.count.get() + count);
+                true
+            }
+            Some(_) => {
+                let mut entry_queue = self.entry_queue.borrow_mut();
+                if !entry_queue.contains(&thread) {
+                    entry_queue.push_back(thread);
+                }
+                false
+            }
+        }
+    }
+
+    // leaves the monitor once; Err when `thread` does not own it, otherwise the thread
+    // to wake when this released the monitor and someone is blocked on it
+    pub fn exit(&self, thread: u64) -> Result<Option<u64>, ()> {
+        if !self.is_owned_by(thread) {
+            return Err(());
+        }
+        self.count.set(self.count.get() - 1);
+        if self.count.get() > 0 {
+            return Ok(None);
+        }
+        self.owner.set(None);
+        Ok(self.entry_queue.borrow().front().copied())
+    }
+
+    // releases the monitor however often it was entered and puts `thread` in the wait set; returns
+    // the recursion count to restore once the thread gets the monitor back and the thread to wake
+    pub fn wait(&self, thread: u64) -> Result<(u32, Option<u64>), ()> {
+        if !self.is_owned_by(thread) {
+            return Err(());
+        }
+        let count = self.count.replace(0);
+        self.owner.set(None);
+        self.wait_set.borrow_mut().push_back(thread);
+        Ok((count, self.entry_queue.borrow().front().copied()))
+    }
+
+    // takes `thread` out of the wait set, e.g. after its wait timed out
+    pub fn leave_wait_set(&self, thread: u64) {
+        self.wait_set.borrow_mut().retain(|waiting| *waiting != thread);
+    }
+
+    // the threads to wake: the longest waiting one, or all of them
+    pub fn notify(&self, thread: u64, all: bool) -> Result<Vec<u64>, ()> {
+        if !self.is_owned_by(thread) {
+            return Err(());
+        }
+        Ok(self.notify_unowned(all))
+    }
+
+    // notify without holding the monitor, for the VM itself
+    pub fn notify_unowned(&self, all: bool) -> Vec<u64> {
+        let mut wait_set = self.wait_set.borrow_mut();
+        if all {
+            wait_set.drain(..).collect()
+        } else {
+            wait_set.pop_front().into_iter().collect()
+        }
+    }
+
+    pub fn is_owned_by(&self, thread: u64) -> bool {
+        self.owner.get() == Some(thread)
+    }
+}
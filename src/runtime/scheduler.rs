@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::log::{log, Logger};
+
+use super::heap::ObjectRef;
+use super::{Frame, Thread, ThreadState};
+
+// instructions a thread runs before the next runnable thread gets its turn
+pub const QUANTUM: usize = 1000;
+// how far the virtual clock moves per instruction, roughly what this interpreter manages
+// so that sleeps in a busy program take about as long as on the wall clock
+const NANOS_PER_INSTRUCTION: u64 = 10;
+
+// a deterministic round-robin scheduler for green threads: which thread runs next depends only
+// on the program and the virtual clock, never on the wall clock, so interleavings are reproducible
+pub struct Scheduler {
+    // every live thread except the one running
+    threads: BTreeMap<u64, Thread>,
+    run_queue: VecDeque<u64>,
+    // virtual time in nanoseconds
+    clock: u64,
+    next_id: u64,
+    stack_size: usize,
+    logger: Rc<Logger>,
+}
+
+pub enum Next {
+    Run(Thread),
+    // every non-daemon thread has terminated, daemon threads are abandoned
+    Shutdown,
+    // live non-daemon threads are left but none of them can ever run again
+    Deadlock(Vec<Thread>),
+}
+
+impl Scheduler {
+    pub fn new(stack_size: usize, logger: Rc<Logger>) -> Scheduler {
+        Scheduler {
+            threads: BTreeMap::new(),
+            run_queue: VecDeque::new(),
+            clock: 0,
+            next_id: 1,
+            stack_size,
+            logger,
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.clock
+    }
+
+    // creates a runnable thread whose first frame is `frame` and returns its id
+    pub fn spawn(&mut self, name: String, daemon: bool, object: Option<ObjectRef>, frame: Frame) -> Result<u64, Error> {
+        let mut thread = self.new_thread(name);
+        thread.daemon = daemon;
+        thread.object = object;
+        thread.push_frame(frame)?;
+        let id = thread.id;
+        let daemon = if daemon { ", a daemon" } else { "" };
+        log!(self.logger, Interp, Info, "started thread {} ({}){}", id, thread.name, daemon);
+        self.resume(thread);
+        Ok(id)
+    }
+
+    // a thread with a fresh id that is not scheduled yet
+    pub fn new_thread(&mut self, name: String) -> Thread {
+        let id = self.next_id;
+        self.next_id += 1;
+        Thread::new_thread(id, name, self.stack_size)
+    }
+
+    // schedules a runnable thread that is not live in the scheduler, a new one or one taken out with detach
+    pub fn resume(&mut self, thread: Thread) {
+        self.run_queue.push_back(thread.id);
+        self.threads.insert(thread.id, thread);
+    }
+
+    // takes a parked thread out of the scheduler, for whoever holds it to resume later
+    pub fn detach(&mut self, id: u64) -> Option<Thread> {
+        self.run_queue.retain(|queued| *queued != id);
+        self.threads.remove(&id)
+    }
+
+    // takes back the thread that ran, having executed `instructions` instructions
+    pub fn park(&mut self, thread: Thread, instructions: usize) {
+        self.clock += instructions as u64 * NANOS_PER_INSTRUCTION;
+        match thread.state {
+            ThreadState::Terminated => {
+                for waiting in self.threads.values_mut() {
+                    if matches!(waiting.state, ThreadState::Joining { thread: id, .. } if id == thread.id) {
+                        waiting.state = ThreadState::Runnable;
+                        self.run_queue.push_back(waiting.id);
+                    }
+                }
+            }
+            state => {
+                if state == ThreadState::Runnable {
+                    self.run_queue.push_back(thread.id);
+                }
+                self.threads.insert(thread.id, thread);
+            }
+        }
+    }
+
+    // picks the thread to run next, waiting out sleeps when nothing else can run
+    pub fn next(&mut self) -> Next {
+        loop {
+            if self.threads.values().all(|thread| thread.daemon) {
+                return Next::Shutdown;
+            }
+            self.wake_sleepers(self.clock);
+            if let Some(id) = self.run_queue.pop_front() {
+                let thread = self.threads.remove(&id).expect("queued thread is not live");
+                return Next::Run(thread);
+            }
+            let Some(deadline) = self.threads.values().filter_map(|thread| deadline(thread.state)).min() else {
+                return Next::Deadlock(std::mem::take(&mut self.threads).into_values().collect());
+            };
+            std::thread::sleep(Duration::from_nanos(deadline - self.clock));
+            self.clock = deadline;
+        }
+    }
+
+    // every live thread except the one running
+    pub fn threads(&self) -> impl Iterator<Item = &Thread> {
+        self.threads.values()
+    }
+
+    // makes a thread blocked on or waiting for a monitor runnable again
+    pub fn wake(&mut self, id: u64) {
+        if let Some(thread) = self.threads.get_mut(&id) {
+            if matches!(thread.state, ThreadState::Blocked | ThreadState::Waiting { .. }) {
+                thread.state = ThreadState::Runnable;
+                self.run_queue.push_back(id);
+            }
+        }
+    }
+
+    // makes the threads whose time is up at `now` runnable, earliest deadline first
+    fn wake_sleepers(&mut self, now: u64) {
+        let mut woken: Vec<(u64, u64)> = self
+            .threads
+            .values()
+            .filter_map(|thread| deadline(thread.state).filter(|deadline| *deadline <= now).map(|deadline| (deadline, thread.id)))
+            .collect();
+        woken.sort_unstable();
+        for (_, id) in woken {
+            if let Some(thread) = self.threads.get_mut(&id) {
+                thread.state = ThreadState::Runnable;
+                self.run_queue.push_back(id);
+            }
+        }
+    }
+}
+
+fn deadline(state: ThreadState) -> Option<u64> {
+    match state {
+        ThreadState::Sleeping { until } => Some(until),
+        ThreadState::Joining { until, .. } | ThreadState::Waiting { until } => until,
+        _ => None,
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+use super::heap::ObjectRef;
+
+// java.lang.String.coder values of JDK 9+
+pub const LATIN1: i32 = 0;
+pub const UTF16: i32 = 1;
+
+// the interned strings of the VM, by their UTF-16 contents: every string constant a class loads,
+// plus what String.intern adds. They live as long as the VM, like the classes that use them
+#[derive(Default)]
+pub struct StringTable {
+    strings: HashMap<Vec<u16>, ObjectRef>,
+}
+
+impl StringTable {
+    pub fn get(&self, chars: &[u16]) -> Option<ObjectRef> {
+        self.strings.get(chars).copied()
+    }
+
+    pub fn insert(&mut self, chars: Vec<u16>, string: ObjectRef) {
+        self.strings.insert(chars, string);
+    }
+
+    pub fn strings(&self) -> impl Iterator<Item = ObjectRef> + '_ {
+        self.strings.values().copied()
+    }
+}
+
+// the bytes and coder of a JDK 9+ String value: one byte per char when compact strings are on and every
+// char fits, otherwise two, low byte first like StringUTF16 on a little-endian machine
+pub fn encode(chars: &[u16], compact: bool) -> (Vec<i8>, i32) {
+    if compact && chars.iter().all(|char| *char <= 0xff) {
+        return (chars.iter().map(|char| *char as i8).collect(), LATIN1);
+    }
+    let bytes = chars.iter().flat_map(|char| char.to_le_bytes()).map(|byte| byte as i8).collect();
+    (bytes, UTF16)
+}
+
+// the chars of a JDK 9+ String value
+pub fn decode(bytes: &[i8], coder: i32) -> Vec<u16> {
+    if coder == LATIN1 {
+        return bytes.iter().map(|byte| *byte as u8 as u16).collect();
+    }
+    bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0] as u8, pair[1] as u8])).collect()
+}
@@ -0,0 +1,1262 @@
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use proptest::prelude::*;
+
+use crate::classfile::class_reader::{Reader, ACC_PUBLIC, ACC_STATIC};
+use crate::classfile::format_check::check_format;
+use crate::classfile::writer::{ClassDef, Constant, FieldDef, MethodDef, Op};
+use crate::classpath::builtin::class_names;
+use crate::classpath::Classpath;
+use crate::error::Error;
+use crate::instructions::{Flow, Instruction};
+use crate::interpreter::Interpreter;
+use crate::native::{Output, Sink};
+use crate::trace::Tracer;
+use crate::verifier::{verify_class, ClasspathHierarchy, VerifyMode};
+use crate::vm::{JValue, TestClasspath};
+
+use super::call_site::{
+    proxy_method_field, spin_accessor, spin_adapter, spin_proxy, AccessMode, Bootstrap, MethodHandleRef,
+    StaticArgument, VarHandleRef, ACCESSOR, PROXY, REF_GET_FIELD, REF_INVOKE_STATIC, REF_INVOKE_VIRTUAL,
+    REF_NEW_INVOKE_SPECIAL, REF_PUT_STATIC,
+};
+use super::class_loader::ClassLoader;
+use super::class::{primitive_name, Class, Code, InitState, Method, PRIMITIVE_TYPES};
+use super::heap::{roots, GenerationalHeap, Heap, MarkSweepHeap, ObjectRef};
+use super::monitor::Monitor;
+use super::scheduler::{Next, Scheduler, QUANTUM};
+use super::string::{decode, encode, StringTable, LATIN1, UTF16};
+use super::{Frame, LocalVars, Object, OperandStack, Slot, Thread, ThreadState};
+
+// a typed value as the JVM sees it, references compare by identity
+#[derive(Debug, Clone)]
+enum Value {
+    Int(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    Reference(Option<ObjectRef>),
+}
+
+impl Value {
+    fn category(&self) -> usize {
+        match self {
+            Value::Long(_) | Value::Double(_) => 2,
+            _ => 1,
+        }
+    }
+
+    fn push(&self, stack: &mut OperandStack) {
+        match self {
+            Value::Int(value) => stack.push_int(*value),
+            Value::Float(value) => stack.push_float(*value),
+            Value::Long(value) => stack.push_long(*value),
+            Value::Double(value) => stack.push_double(*value),
+            Value::Reference(value) => stack.push_ref(*value),
+        }
+    }
+
+    // pops a value of the same type as `self`
+    fn pop_like(&self, stack: &mut OperandStack) -> Value {
+        match self {
+            Value::Int(_) => Value::Int(stack.pop_int()),
+            Value::Float(_) => Value::Float(stack.pop_float()),
+            Value::Long(_) => Value::Long(stack.pop_long()),
+            Value::Double(_) => Value::Double(stack.pop_double()),
+            Value::Reference(_) => Value::Reference(stack.pop_ref()),
+        }
+    }
+
+    fn store(&self, locals: &mut LocalVars, index: usize) {
+        match self {
+            Value::Int(value) => locals.set_int(index, *value),
+            Value::Float(value) => locals.set_float(index, *value),
+            Value::Long(value) => locals.set_long(index, *value),
+            Value::Double(value) => locals.set_double(index, *value),
+            Value::Reference(value) => locals.set_ref(index, *value),
+        }
+    }
+
+    fn load_like(&self, locals: &LocalVars, index: usize) -> Value {
+        match self {
+            Value::Int(_) => Value::Int(locals.get_int(index)),
+            Value::Float(_) => Value::Float(locals.get_float(index)),
+            Value::Long(_) => Value::Long(locals.get_long(index)),
+            Value::Double(_) => Value::Double(locals.get_double(index)),
+            Value::Reference(_) => Value::Reference(locals.get_ref(index)),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+            (Value::Long(a), Value::Long(b)) => a == b,
+            (Value::Double(a), Value::Double(b)) => a.to_bits() == b.to_bits(),
+            (Value::Reference(a), Value::Reference(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+thread_local! {
+    // where objects that only need to be told apart live
+    static HEAP: RefCell<MarkSweepHeap> = RefCell::new(MarkSweepHeap::new(usize::MAX, false, Rc::default()));
+}
+
+fn new_object() -> ObjectRef {
+    HEAP.with(|heap| heap.borrow_mut().allocate_past_limit(Object::new(Rc::new(Class::default()))))
+}
+
+fn new_frame(class_name: &str, method_name: &str, max_locals: usize, max_stack: usize) -> Frame {
+    let class = Class {
+        name: String::from(class_name),
+        ..Class::default()
+    };
+    let method = Method {
+        name: String::from(method_name),
+        max_locals,
+        max_stack,
+        ..Method::default()
+    };
+    Frame::new(Rc::new(class), Rc::new(method))
+}
+
+fn category1() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        any::<i32>().prop_map(Value::Int),
+        any::<f32>().prop_map(Value::Float),
+        Just(Value::Reference(None)),
+        Just(()).prop_map(|_| Value::Reference(Some(new_object()))),
+    ]
+}
+
+fn category2() -> impl Strategy<Value = Value> {
+    prop_oneof![any::<i64>().prop_map(Value::Long), any::<f64>().prop_map(Value::Double)]
+}
+
+fn value() -> impl Strategy<Value = Value> {
+    prop_oneof![category1(), category2()]
+}
+
+fn words(values: &[Value]) -> usize {
+    values.iter().map(Value::category).sum()
+}
+
+// pushes `below` and then `operands` (the last one on top), executes `instruction` and checks
+// that the stack then holds `below` followed by `expected`
+fn check_stack_instruction(instruction: Instruction, below: &[Value], operands: &[Value], expected: &[Value]) {
+    let mut frame = new_frame("Test", "test", 0, words(below) + words(operands) + words(expected));
+    for value in below.iter().chain(operands) {
+        value.push(&mut frame.operand_stack);
+    }
+    assert!(matches!(instruction.execute(&mut frame), Flow::Next));
+    assert_eq!(frame.operand_stack.slots.len(), words(below) + words(expected));
+    for value in expected.iter().rev().chain(below.iter().rev()) {
+        assert_eq!(&value.pop_like(&mut frame.operand_stack), value);
+    }
+    assert!(frame.operand_stack.slots.is_empty());
+}
+
+proptest! {
+    #[test]
+    fn pops_return_pushes_in_reverse(values in prop::collection::vec(value(), 0..32)) {
+        let mut stack = OperandStack::new_operand_stack(words(&values));
+        for value in &values {
+            value.push(&mut stack);
+        }
+        prop_assert_eq!(stack.slots.len(), words(&values));
+        for value in values.iter().rev() {
+            prop_assert_eq!(&value.pop_like(&mut stack), value);
+        }
+        prop_assert!(stack.slots.is_empty());
+    }
+
+    #[test]
+    fn locals_read_back_without_disturbing_each_other(values in prop::collection::vec(value(), 1..32), reads in prop::collection::vec(any::<prop::sample::Index>(), 0..64)) {
+        let mut locals = LocalVars::new_local_vars(words(&values));
+        let mut indexes = Vec::new();
+        let mut index = 0;
+        for value in &values {
+            value.store(&mut locals, index);
+            indexes.push(index);
+            index += value.category();
+        }
+        for read in reads {
+            let which = read.index(values.len());
+            prop_assert_eq!(&values[which].load_like(&locals, indexes[which]), &values[which]);
+        }
+        for (value, index) in values.iter().zip(&indexes) {
+            prop_assert_eq!(&value.load_like(&locals, *index), value);
+        }
+    }
+
+    #[test]
+    fn long_and_double_halves_hold_the_low_word_first(long in any::<i64>(), double in any::<f64>()) {
+        let mut stack = OperandStack::new_operand_stack(4);
+        stack.push_long(long);
+        stack.push_double(double);
+        let halves: Vec<(bool, u32, bool)> = stack
+            .slots
+            .iter()
+            .map(|slot| match slot {
+                Slot::Long { bits, first } => (false, *bits, *first),
+                Slot::Double { bits, first } => (true, *bits, *first),
+                other => panic!("{:?} is not half of a long or double", other),
+            })
+            .collect();
+        let double = double.to_bits();
+        prop_assert_eq!(halves, vec![
+            (false, long as u32, true),
+            (false, (long >> 32) as u32, false),
+            (true, double as u32, true),
+            (true, (double >> 32) as u32, false),
+        ]);
+    }
+
+    #[test]
+    fn pop_and_pop2(below in prop::collection::vec(value(), 0..4), value1 in category1(), value2 in category1(), wide in category2()) {
+        check_stack_instruction(Instruction::Pop, &below, std::slice::from_ref(&value1), &[]);
+        check_stack_instruction(Instruction::Pop2, &below, &[value2, value1], &[]);
+        check_stack_instruction(Instruction::Pop2, &below, &[wide], &[]);
+    }
+
+    #[test]
+    fn dup_dup_x1_and_swap(below in prop::collection::vec(value(), 0..4), value1 in category1(), value2 in category1()) {
+        check_stack_instruction(Instruction::Dup, &below, std::slice::from_ref(&value1), &[value1.clone(), value1.clone()]);
+        check_stack_instruction(Instruction::DupX1, &below, &[value2.clone(), value1.clone()], &[value1.clone(), value2.clone(), value1.clone()]);
+        check_stack_instruction(Instruction::Swap, &below, &[value2.clone(), value1.clone()], &[value1, value2]);
+    }
+
+    #[test]
+    fn dup_x2(below in prop::collection::vec(value(), 0..4), value1 in category1(), value2 in category1(), value3 in category1(), wide in category2()) {
+        // form 1: value3, value2, value1 -> value1, value3, value2, value1
+        check_stack_instruction(
+            Instruction::DupX2,
+            &below,
+            &[value3.clone(), value2.clone(), value1.clone()],
+            &[value1.clone(), value3, value2, value1.clone()],
+        );
+        // form 2: value2 (category 2), value1 -> value1, value2, value1
+        check_stack_instruction(Instruction::DupX2, &below, &[wide.clone(), value1.clone()], &[value1.clone(), wide, value1]);
+    }
+
+    #[test]
+    fn dup2_and_dup2_x1(below in prop::collection::vec(value(), 0..4), value1 in category1(), value2 in category1(), value3 in category1(), wide in category2()) {
+        // dup2 form 1 and form 2
+        check_stack_instruction(Instruction::Dup2, &below, &[value2.clone(), value1.clone()], &[value2.clone(), value1.clone(), value2.clone(), value1.clone()]);
+        check_stack_instruction(Instruction::Dup2, &below, std::slice::from_ref(&wide), &[wide.clone(), wide.clone()]);
+        // dup2_x1 form 1: value3, value2, value1 -> value2, value1, value3, value2, value1
+        check_stack_instruction(
+            Instruction::Dup2X1,
+            &below,
+            &[value3.clone(), value2.clone(), value1.clone()],
+            &[value2.clone(), value1.clone(), value3, value2.clone(), value1.clone()],
+        );
+        // dup2_x1 form 2: value2, value1 (category 2) -> value1, value2, value1
+        check_stack_instruction(Instruction::Dup2X1, &below, &[value2.clone(), wide.clone()], &[wide.clone(), value2, wide]);
+    }
+
+    #[test]
+    fn dup2_x2(
+        below in prop::collection::vec(value(), 0..4),
+        values in prop::collection::vec(category1(), 4),
+        wides in prop::collection::vec(category2(), 3),
+    ) {
+        let (value1, value2, value3, value4) = (&values[0], &values[1], &values[2], &values[3]);
+        // form 1: value4, value3, value2, value1 -> value2, value1, value4, value3, value2, value1
+        check_stack_instruction(
+            Instruction::Dup2X2,
+            &below,
+            &[value4.clone(), value3.clone(), value2.clone(), value1.clone()],
+            &[value2.clone(), value1.clone(), value4.clone(), value3.clone(), value2.clone(), value1.clone()],
+        );
+        // form 2: value3, value2, value1 (category 2) -> value1, value3, value2, value1
+        check_stack_instruction(
+            Instruction::Dup2X2,
+            &below,
+            &[value3.clone(), value2.clone(), wides[0].clone()],
+            &[wides[0].clone(), value3.clone(), value2.clone(), wides[0].clone()],
+        );
+        // form 3: value3 (category 2), value2, value1 -> value2, value1, value3, value2, value1
+        check_stack_instruction(
+            Instruction::Dup2X2,
+            &below,
+            &[wides[1].clone(), value2.clone(), value1.clone()],
+            &[value2.clone(), value1.clone(), wides[1].clone(), value2.clone(), value1.clone()],
+        );
+        // form 4: value2 (category 2), value1 (category 2) -> value1, value2, value1
+        check_stack_instruction(
+            Instruction::Dup2X2,
+            &below,
+            &[wides[2].clone(), wides[0].clone()],
+            &[wides[0].clone(), wides[2].clone(), wides[0].clone()],
+        );
+    }
+}
+
+#[test]
+fn reading_a_reference_local_keeps_the_other_locals_in_place() {
+    let objects: Vec<ObjectRef> = (0..4).map(|_| new_object()).collect();
+    let mut locals = LocalVars::new_local_vars(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        locals.set_ref(index, Some(*object));
+    }
+    for _ in 0..2 {
+        for (index, object) in objects.iter().enumerate() {
+            assert_eq!(locals.get_ref(index), Some(*object));
+        }
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "expected int")]
+fn reading_half_of_a_long_as_an_int_is_caught_in_debug_builds() {
+    let mut stack = OperandStack::new_operand_stack(2);
+    stack.push_long(1);
+    stack.pop_int();
+}
+
+#[test]
+#[should_panic(expected = "operand stack overflow")]
+fn pushing_past_max_stack_panics() {
+    let mut stack = OperandStack::new_operand_stack(1);
+    stack.push_int(1);
+    stack.push_int(2);
+}
+
+#[test]
+fn pushing_frames_past_the_stack_size_overflows() {
+    // each of these frames costs 64 + 8 * 4 bytes
+    let mut thread = Thread::new_thread(1, String::from("main"), 96 * 10 + 50);
+    for _ in 0..10 {
+        thread.push_frame(new_frame("Test", "recurse", 2, 2)).unwrap();
+    }
+    assert!(matches!(thread.push_frame(new_frame("Test", "recurse", 2, 2)), Err(Error::StackOverflow)));
+    thread.pop_frame().unwrap();
+    thread.push_frame(new_frame("Test", "recurse", 2, 2)).unwrap();
+}
+
+#[test]
+fn stack_trace_lists_the_innermost_frame_first() {
+    let mut thread = Thread::new_thread(1, String::from("main"), 1 << 20);
+    thread.push_frame(new_frame("org/example/Main", "main", 1, 1)).unwrap();
+    thread.push_frame(new_frame("org/example/Main", "run", 1, 1)).unwrap();
+    let trace: Vec<String> = thread.stack_trace().iter().map(ToString::to_string).collect();
+    assert_eq!(trace, vec!["org.example.Main.run(Unknown Source)", "org.example.Main.main(Unknown Source)"]);
+}
+
+#[test]
+fn dividing_by_zero_raises_arithmetic_exception() {
+    let mut frame = new_frame("Test", "test", 0, 2);
+    frame.operand_stack.push_int(1);
+    frame.operand_stack.push_int(0);
+    match Instruction::IDiv.execute(&mut frame) {
+        Flow::Raise { class_name, message } => {
+            assert_eq!(class_name, "java/lang/ArithmeticException");
+            assert_eq!(message.as_deref(), Some("/ by zero"));
+        }
+        _ => panic!("idiv by zero did not raise"),
+    }
+}
+
+fn spawn(scheduler: &mut Scheduler, name: &str, daemon: bool) -> u64 {
+    scheduler.spawn(String::from(name), daemon, None, new_frame("Test", "run", 1, 1)).unwrap()
+}
+
+// runs the next thread for `instructions` instructions, leaving it in `state`, and returns its name
+fn run_next(scheduler: &mut Scheduler, instructions: usize, state: ThreadState) -> String {
+    let Next::Run(mut thread) = scheduler.next() else {
+        panic!("no thread to run");
+    };
+    let name = thread.name.clone();
+    thread.state = state;
+    scheduler.park(thread, instructions);
+    name
+}
+
+#[test]
+fn runnable_threads_take_turns_in_start_order() {
+    let mut scheduler = Scheduler::new(1 << 20, Rc::default());
+    for name in ["main", "Thread-0", "Thread-1"] {
+        spawn(&mut scheduler, name, false);
+    }
+    let order: Vec<String> = (0..6).map(|_| run_next(&mut scheduler, 1000, ThreadState::Runnable)).collect();
+    assert_eq!(order, ["main", "Thread-0", "Thread-1", "main", "Thread-0", "Thread-1"]);
+}
+
+#[test]
+fn sleepers_wake_on_the_virtual_clock() {
+    let mut scheduler = Scheduler::new(1 << 20, Rc::default());
+    spawn(&mut scheduler, "sleeper", false);
+    spawn(&mut scheduler, "worker", false);
+    // 100 instructions of the worker are not enough to reach the deadline, 1000 more are
+    let until = scheduler.now() + 5_000;
+    assert_eq!(run_next(&mut scheduler, 0, ThreadState::Sleeping { until }), "sleeper");
+    assert_eq!(run_next(&mut scheduler, 100, ThreadState::Runnable), "worker");
+    assert_eq!(run_next(&mut scheduler, 1000, ThreadState::Runnable), "worker");
+    assert_eq!(run_next(&mut scheduler, 0, ThreadState::Terminated), "worker");
+    assert_eq!(run_next(&mut scheduler, 0, ThreadState::Terminated), "sleeper");
+    assert!(matches!(scheduler.next(), Next::Shutdown));
+}
+
+#[test]
+fn an_idle_scheduler_fast_forwards_to_the_next_deadline() {
+    let mut scheduler = Scheduler::new(1 << 20, Rc::default());
+    spawn(&mut scheduler, "main", false);
+    let until = scheduler.now() + 1_000;
+    run_next(&mut scheduler, 0, ThreadState::Sleeping { until });
+    assert!(matches!(scheduler.next(), Next::Run(thread) if thread.name == "main"));
+    assert_eq!(scheduler.now(), until);
+}
+
+#[test]
+fn joining_threads_resume_when_the_thread_terminates() {
+    let mut scheduler = Scheduler::new(1 << 20, Rc::default());
+    spawn(&mut scheduler, "main", false);
+    let worker = spawn(&mut scheduler, "worker", false);
+    run_next(&mut scheduler, 10, ThreadState::Joining { thread: worker, until: None });
+    assert_eq!(run_next(&mut scheduler, 10, ThreadState::Runnable), "worker");
+    assert_eq!(run_next(&mut scheduler, 10, ThreadState::Terminated), "worker");
+    assert_eq!(run_next(&mut scheduler, 10, ThreadState::Terminated), "main");
+    assert!(matches!(scheduler.next(), Next::Shutdown));
+}
+
+#[test]
+fn the_vm_shuts_down_once_only_daemon_threads_are_left() {
+    let mut scheduler = Scheduler::new(1 << 20, Rc::default());
+    spawn(&mut scheduler, "main", false);
+    spawn(&mut scheduler, "daemon", true);
+    run_next(&mut scheduler, 10, ThreadState::Terminated);
+    assert!(matches!(scheduler.next(), Next::Shutdown));
+}
+
+#[test]
+fn threads_joining_each_other_deadlock() {
+    let mut scheduler = Scheduler::new(1 << 20, Rc::default());
+    let first = spawn(&mut scheduler, "first", false);
+    let second = spawn(&mut scheduler, "second", false);
+    run_next(&mut scheduler, 10, ThreadState::Joining { thread: second, until: None });
+    run_next(&mut scheduler, 10, ThreadState::Joining { thread: first, until: None });
+    let Next::Deadlock(threads) = scheduler.next() else {
+        panic!("expected a deadlock");
+    };
+    let names: Vec<String> = threads.into_iter().map(|thread| thread.name).collect();
+    assert_eq!(names, ["first", "second"]);
+}
+
+#[test]
+fn monitors_are_reentrant_and_hand_over_in_arrival_order() {
+    let monitor = Monitor::default();
+    assert!(monitor.try_enter(1, 1));
+    assert!(monitor.try_enter(1, 1));
+    assert!(!monitor.try_enter(2, 1));
+    assert!(!monitor.try_enter(3, 1));
+    assert_eq!(monitor.exit(2), Err(()));
+    assert_eq!(monitor.exit(1), Ok(None));
+    assert_eq!(monitor.exit(1), Ok(Some(2)));
+    assert!(monitor.try_enter(2, 1));
+    assert_eq!(monitor.exit(2), Ok(Some(3)));
+    assert!(monitor.try_enter(3, 1));
+    assert_eq!(monitor.exit(3), Ok(None));
+    assert_eq!(monitor.exit(3), Err(()));
+}
+
+#[test]
+fn waiting_releases_the_monitor_until_notified() {
+    let monitor = Monitor::default();
+    assert!(monitor.try_enter(1, 2));
+    assert_eq!(monitor.notify(2, false), Err(()));
+    assert_eq!(monitor.wait(1), Ok((2, None)));
+    assert!(monitor.try_enter(2, 1));
+    assert_eq!(monitor.wait(2), Ok((1, None)));
+    assert!(monitor.try_enter(3, 1));
+    assert_eq!(monitor.notify(3, false), Ok(vec![1]));
+    assert_eq!(monitor.notify(3, true), Ok(vec![2]));
+    assert_eq!(monitor.notify(3, true), Ok(vec![]));
+    // the notified threads get the monitor back with their recursion count once it is free
+    assert!(!monitor.try_enter(1, 2));
+    assert_eq!(monitor.exit(3), Ok(Some(1)));
+    assert!(monitor.try_enter(1, 2));
+    assert_eq!(monitor.exit(1), Ok(None));
+    assert!(monitor.is_owned_by(1));
+}
+
+// a class whose instances have two reference fields, 32 bytes each
+fn node_class() -> Rc<Class> {
+    Rc::new(Class {
+        name: String::from("Node"),
+        instance_fields: vec![Slot::Reference(None); 2],
+        ..Class::default()
+    })
+}
+
+// one heap of each kind with room for `objects` nodes, a quarter of it nursery
+fn heaps(objects: usize) -> [Box<dyn Heap>; 2] {
+    [
+        Box::new(MarkSweepHeap::new(objects * 32, false, Rc::default())),
+        Box::new(GenerationalHeap::new(objects * 32, objects * 8, false, Rc::default())),
+    ]
+}
+
+fn link(heap: &mut dyn Heap, from: ObjectRef, field: usize, to: ObjectRef) {
+    heap[from].fields.borrow_mut()[field] = Slot::Reference(Some(to));
+    heap.write_barrier(from);
+}
+
+#[test]
+fn collection_frees_unreachable_cycles_and_keeps_what_the_roots_reach() {
+    let class = node_class();
+    for mut heap in heaps(1024) {
+        let mut allocate = || heap.allocate(Object::new(class.clone()), &Vec::new).unwrap();
+        let (live, child, cycle_a, cycle_b) = (allocate(), allocate(), allocate(), allocate());
+        link(&mut *heap, live, 0, child);
+        link(&mut *heap, child, 0, live);
+        link(&mut *heap, cycle_a, 0, cycle_b);
+        link(&mut *heap, cycle_b, 1, cycle_a);
+        let mut thread = Thread::new_thread(1, String::from("main"), 1 << 20);
+        let mut frame = new_frame("Test", "main", 1, 1);
+        frame.local_vars.set_ref(0, Some(live));
+        thread.push_frame(frame).unwrap();
+        heap.collect(roots(&[], &StringTable::default(), &[], [&thread]));
+        assert_eq!(heap[child].fields.borrow()[0].reference(), Some(live));
+        let stats = heap.stats();
+        assert_eq!((stats.collections, stats.freed_objects, stats.freed_bytes), (1, 2, 2 * 32));
+        // the freed handles are reused
+        let reused = [heap.allocate_past_limit(Object::new(class.clone())), heap.allocate_past_limit(Object::new(class.clone()))];
+        assert!(reused.contains(&cycle_a) && reused.contains(&cycle_b));
+    }
+}
+
+#[test]
+fn static_fields_and_class_objects_are_roots() {
+    let class = node_class();
+    for mut heap in heaps(1024) {
+        let (held, mirror, garbage) = (
+            heap.allocate_past_limit(Object::new(class.clone())),
+            heap.allocate_past_limit(Object::new(class.clone())),
+            heap.allocate_past_limit(Object::new(class.clone())),
+        );
+        let holder = Rc::new(Class {
+            static_fields: RefCell::new(vec![Slot::Reference(Some(held))]),
+            ..Class::default()
+        });
+        holder.mirror.set(Some(mirror));
+        heap.collect(roots(&[holder], &StringTable::default(), &[], []));
+        assert_eq!(heap.stats().freed_objects, 1);
+        assert_eq!(heap.allocate_past_limit(Object::new(class.clone())), garbage);
+    }
+}
+
+#[test]
+fn allocating_past_the_limit_collects_first_and_fails_when_everything_is_live() {
+    let class = node_class();
+    for mut heap in heaps(2) {
+        let first = heap.allocate(Object::new(class.clone()), &Vec::new).unwrap();
+        heap.allocate(Object::new(class.clone()), &Vec::new).unwrap();
+        let third = heap.allocate(Object::new(class.clone()), &|| vec![first]).unwrap();
+        assert!(heap.allocate(Object::new(class.clone()), &|| vec![first, third]).is_none());
+        assert_eq!(heap[first].class.name, "Node");
+    }
+}
+
+#[test]
+fn minor_collections_free_young_garbage_and_promote_survivors() {
+    let class = node_class();
+    // a nursery of four nodes, survivor space of one
+    let mut heap = GenerationalHeap::new(1024 * 32, 4 * 32, false, Rc::default());
+    let kept = heap.allocate(Object::new(class.clone()), &Vec::new).unwrap();
+    for _ in 0..20 {
+        heap.allocate(Object::new(class.clone()), &|| vec![kept]).unwrap();
+    }
+    // the first collection frees the three nodes next to `kept`, the second one promotes it,
+    // and from then on every fourth allocation collects the four nodes before it
+    let stats = heap.stats();
+    assert_eq!((stats.collections, stats.minor_collections), (5, 5));
+    assert_eq!(stats.freed_objects, 3 + 3 + 4 + 4 + 4);
+    assert_eq!(heap[kept].class.name, "Node");
+}
+
+#[test]
+fn the_card_table_keeps_young_objects_that_only_old_objects_refer_to() {
+    let class = node_class();
+    let mut heap = GenerationalHeap::new(1024 * 32, 4 * 32, false, Rc::default());
+    let old = heap.allocate_past_limit(Object::new(class.clone()));
+    let young = heap.allocate(Object::new(class.clone()), &Vec::new).unwrap();
+    link(&mut heap, old, 0, young);
+    let grandchild = heap.allocate(Object::new(class.clone()), &Vec::new).unwrap();
+    link(&mut heap, young, 1, grandchild);
+    // fills the nursery until it has been collected a few times, with only the old object as a root
+    for _ in 0..16 {
+        heap.allocate(Object::new(class.clone()), &|| vec![old]).unwrap();
+    }
+    assert!(heap.stats().minor_collections >= 3);
+    assert_eq!(heap[old].fields.borrow()[0].reference(), Some(young));
+    assert_eq!(heap[young].fields.borrow()[1].reference(), Some(grandchild));
+    assert_eq!(heap[grandchild].class.name, "Node");
+}
+
+#[test]
+fn a_young_object_reached_twice_is_copied_once() {
+    let class = node_class();
+    let mut heap = GenerationalHeap::new(1024 * 32, 4 * 32, false, Rc::default());
+    heap.allocate(Object::new(class.clone()), &Vec::new).unwrap();
+    let shared = heap.allocate(Object::new(class.clone()), &Vec::new).unwrap();
+    let parent = heap.allocate(Object::new(class.clone()), &Vec::new).unwrap();
+    link(&mut heap, parent, 0, shared);
+    heap.allocate(Object::new(class.clone()), &Vec::new).unwrap();
+    heap.allocate(Object::new(class.clone()), &|| vec![shared, parent, shared]).unwrap();
+    assert_eq!(heap.stats().freed_objects, 2);
+    assert_eq!(heap[parent].fields.borrow()[0].reference(), Some(shared));
+    assert_eq!(heap[shared].class.name, "Node");
+}
+
+#[test]
+fn identity_hashes_are_assigned_lazily_and_survive_copying() {
+    let class = node_class();
+    let mut heap = GenerationalHeap::new(1024 * 32, 4 * 32, false, Rc::default());
+    let mut thread = Thread::new_thread(1, String::from("main"), 1 << 20);
+    let (first, second) = (
+        heap.allocate(Object::new(class.clone()), &Vec::new).unwrap(),
+        heap.allocate(Object::new(class.clone()), &Vec::new).unwrap(),
+    );
+    let hash = heap[first].identity_hash(&mut thread);
+    assert!(hash > 0);
+    assert_ne!(heap[second].identity_hash(&mut thread), hash);
+    // enough allocations to copy `first` to survivor space and then promote it
+    for _ in 0..8 {
+        heap.allocate(Object::new(class.clone()), &|| vec![first]).unwrap();
+    }
+    assert_eq!(heap.stats().minor_collections, 2);
+    assert_eq!(heap[first].identity_hash(&mut thread), hash);
+}
+
+#[test]
+fn identity_hashes_follow_the_thread_that_asks_first() {
+    let hashes = |id| {
+        let mut thread = Thread::new_thread(id, String::from("main"), 1 << 20);
+        (0..4).map(|_| new_object_hash(&mut thread)).collect::<Vec<i32>>()
+    };
+    assert_eq!(hashes(1), hashes(1));
+    assert_ne!(hashes(1), hashes(2));
+}
+
+fn new_object_hash(thread: &mut Thread) -> i32 {
+    let object = new_object();
+    HEAP.with(|heap| heap.borrow()[object].identity_hash(thread))
+}
+
+fn array_class(name: &str, component: Option<Rc<Class>>) -> Rc<Class> {
+    Rc::new(Class::new_array(name, Rc::new(Class::default()), component))
+}
+
+#[test]
+fn arrays_hold_default_elements_and_are_sized_by_element_type() {
+    let longs = Object::new_array(array_class("[J", None), 3);
+    assert_eq!((longs.array_length(), longs.fields.borrow().len()), (3, 6));
+    let mut heaps = [MarkSweepHeap::new(1024, false, Rc::default()), MarkSweepHeap::new(1024, false, Rc::default())];
+    heaps[0].allocate_past_limit(Object::new_array(array_class("[B", None), 100));
+    heaps[1].allocate_past_limit(Object::new_array(array_class("[Ljava/lang/Object;", None), 100));
+    // the header and length, then one byte against eight per element
+    heaps.iter_mut().for_each(|heap| heap.collect(Vec::new()));
+    let freed: Vec<usize> = heaps.iter().map(|heap| heap.stats().freed_bytes).collect();
+    assert_eq!(freed, vec![20 + 100, 20 + 800]);
+}
+
+#[test]
+fn array_classes_are_assignable_by_their_components() {
+    let object = Rc::new(Class {
+        name: String::from("java/lang/Object"),
+        ..Class::default()
+    });
+    let comparable = Rc::new(Class {
+        name: String::from("java/lang/Comparable"),
+        ..Class::default()
+    });
+    let string = Rc::new(Class {
+        name: String::from("java/lang/String"),
+        super_class: Some(object.clone()),
+        interfaces: vec![comparable.clone()],
+        ..Class::default()
+    });
+    assert!(string.is_assignable_to(&comparable) && string.is_assignable_to(&object));
+    assert!(!object.is_assignable_to(&string));
+    let strings = array_class("[Ljava/lang/String;", Some(string.clone()));
+    let objects = array_class("[Ljava/lang/Object;", Some(object.clone()));
+    let ints = array_class("[I", None);
+    assert!(strings.is_assignable_to(&objects) && strings.is_assignable_to(&object));
+    assert!(!objects.is_assignable_to(&strings));
+    assert!(ints.is_assignable_to(&object) && ints.is_assignable_to(&array_class("[I", None)));
+    assert!(!ints.is_assignable_to(&objects) && !ints.is_assignable_to(&array_class("[J", None)));
+}
+
+#[test]
+fn interned_strings_and_array_elements_are_roots() {
+    let class = node_class();
+    for mut heap in heaps(1024) {
+        let (interned, element, garbage) = (
+            heap.allocate_past_limit(Object::new(class.clone())),
+            heap.allocate_past_limit(Object::new(class.clone())),
+            heap.allocate_past_limit(Object::new(class.clone())),
+        );
+        let array = heap.allocate_past_limit(Object::new_array(array_class("[LNode;", Some(class.clone())), 2));
+        link(&mut *heap, array, 1, element);
+        let mut strings = StringTable::default();
+        strings.insert("interned".encode_utf16().collect(), interned);
+        let holder = Rc::new(Class {
+            static_fields: RefCell::new(vec![Slot::Reference(Some(array))]),
+            ..Class::default()
+        });
+        heap.collect(roots(&[holder], &strings, &[], []));
+        assert_eq!(heap.stats().freed_objects, 1);
+        assert_eq!(heap.allocate_past_limit(Object::new(class.clone())), garbage);
+        assert_eq!(strings.get(&"interned".encode_utf16().collect::<Vec<_>>()), Some(interned));
+    }
+}
+
+#[test]
+fn strings_are_compact_only_when_every_char_fits_in_a_byte() {
+    let chars = |value: &str| value.encode_utf16().collect::<Vec<u16>>();
+    assert_eq!(encode(&chars("hé"), true), (vec![b'h' as i8, 0xe9_u8 as i8], LATIN1));
+    assert_eq!(encode(&chars("hé"), false), (vec![b'h' as i8, 0, 0xe9_u8 as i8, 0], UTF16));
+    assert_eq!(encode(&chars("h中"), true).1, UTF16);
+}
+
+proptest! {
+    #[test]
+    fn string_values_decode_to_what_was_encoded(chars in prop::collection::vec(any::<u16>(), 0..64), compact: bool) {
+        let (bytes, coder) = encode(&chars, compact);
+        prop_assert_eq!(decode(&bytes, coder), chars);
+    }
+}
+
+// a method named `name` taking no arguments, with a body unless it is abstract
+fn method(name: &str, body: bool) -> Rc<Method> {
+    Rc::new(Method {
+        name: String::from(name),
+        descriptor: String::from("()V"),
+        // return
+        code: body.then(|| Rc::new(Code::new(&[0xb1]).unwrap())),
+        ..Method::default()
+    })
+}
+
+#[test]
+fn interface_default_methods_come_after_the_class_hierarchy_and_before_abstract_ones() {
+    let abstract_interface = Rc::new(Class {
+        name: String::from("Named"),
+        methods: vec![method("name", false), method("size", false)],
+        ..Class::default()
+    });
+    let default_interface = Rc::new(Class {
+        name: String::from("Sized"),
+        interfaces: vec![abstract_interface.clone()],
+        methods: vec![method("size", true), method("kind", true)],
+        ..Class::default()
+    });
+    let super_class = Rc::new(Class {
+        name: String::from("Base"),
+        methods: vec![method("kind", true)],
+        ..Class::default()
+    });
+    let class = Rc::new(Class {
+        name: String::from("Box"),
+        super_class: Some(super_class),
+        interfaces: vec![default_interface],
+        ..Class::default()
+    });
+    let declaring = |name: &str| class.find_method(name, "()V").map(|(class, _)| class.name.clone());
+    assert_eq!(declaring("kind").as_deref(), Some("Base"));
+    assert_eq!(declaring("size").as_deref(), Some("Sized"));
+    assert_eq!(declaring("name").as_deref(), Some("Named"));
+    assert_eq!(declaring("missing"), None);
+}
+
+#[test]
+fn copies_get_the_field_values_but_not_the_identity() {
+    let mut thread = Thread::new_thread(1, String::from("main"), 1024);
+    let original = Object::new(node_class());
+    original.fields.borrow_mut()[0] = Slot::Reference(Some(new_object()));
+    original.identity_hash(&mut thread);
+    assert!(original.monitor.try_enter(1, 1));
+    let copy = original.copy();
+    assert_eq!(copy.fields.borrow()[0].reference(), original.fields.borrow()[0].reference());
+    copy.fields.borrow_mut()[1] = Slot::Reference(Some(new_object()));
+    assert_eq!(original.fields.borrow()[1].reference(), None);
+    assert_ne!(copy.identity_hash(&mut thread), original.identity_hash(&mut thread));
+    assert!(copy.monitor.try_enter(2, 1));
+}
+
+#[test]
+fn primitive_classes_are_named_after_their_type() {
+    for (name, descriptor) in PRIMITIVE_TYPES {
+        assert_eq!(primitive_name(descriptor), name);
+        assert!(Class::new_primitive(name).is_primitive());
+    }
+    let int = Class::new_primitive("int");
+    assert!(!int.is_array() && int.super_class.is_none() && int.is_initialized());
+    assert!(!array_class("[I", None).is_primitive());
+}
+
+#[test]
+fn frame_classes_start_at_the_innermost_frame() {
+    let mut thread = Thread::new_thread(1, String::from("main"), 1024);
+    for class_name in ["Main", "Caller", "Callee"] {
+        thread.push_frame(new_frame(class_name, "run", 0, 0)).unwrap();
+    }
+    let names: Vec<&str> = thread.frame_classes().map(|class| class.name.as_str()).collect();
+    assert_eq!(names, ["Callee", "Caller", "Main"]);
+}
+
+#[test]
+fn every_builtin_class_loads_and_verifies() {
+    let loader = ClassLoader::new(Classpath::builtin_classpath(PathBuf::from("."), Rc::default()), VerifyMode::All);
+    for name in class_names() {
+        let class = loader.load_class(name).unwrap_or_else(|error| panic!("{}: {}", name, error));
+        assert_eq!(class.name, name);
+    }
+}
+
+fn static_handle(class_name: &str, name: &str, descriptor: &str) -> MethodHandleRef {
+    MethodHandleRef {
+        kind: REF_INVOKE_STATIC,
+        class_name: String::from(class_name),
+        name: String::from(name),
+        descriptor: String::from(descriptor),
+    }
+}
+
+// checks that a class the VM made up passes the verifier and defines it
+fn define_verified(loader: &ClassLoader, classpath: &Classpath, class_name: &str, content: Vec<u8>) -> Rc<Class> {
+    let reader = Reader {
+        content: content.clone(),
+        cursor: Cell::new(0),
+        attribute_lengths: RefCell::new(Vec::new()),
+        logger: Rc::default(),
+    };
+    let classfile = reader.parse_classfile().unwrap();
+    check_format(&classfile).unwrap();
+    verify_class(&classfile, &ClasspathHierarchy::new(classpath)).unwrap();
+    loader.define_class(class_name, content).unwrap()
+}
+
+// spins the class of `bootstrap`, checks that it passes the verifier and defines it
+fn spin(loader: &ClassLoader, classpath: &Classpath, bootstrap: &Bootstrap, class_name: &str) -> Rc<Class> {
+    let fast_path = bootstrap.fast_path().expect("a bootstrap method without a fast path");
+    let content = fast_path.spin(bootstrap, class_name).unwrap();
+    let class = define_verified(loader, classpath, class_name, content);
+    assert!(class.find_method(fast_path.method_name(), &bootstrap.descriptor).is_some());
+    class
+}
+
+#[test]
+fn lambda_and_string_concatenation_classes_are_spun_valid() {
+    let loader = ClassLoader::new(Classpath::builtin_classpath(PathBuf::from("."), Rc::default()), VerifyMode::All);
+    let classpath = Classpath::builtin_classpath(PathBuf::from("."), Rc::default());
+    // Function<Integer, Integer> f = x -> Math.max(k, x), with k captured
+    let lambda = Bootstrap {
+        method: static_handle("java/lang/invoke/LambdaMetafactory", "metafactory", ""),
+        name: String::from("apply"),
+        descriptor: String::from("(I)Ljava/util/function/Function;"),
+        arguments: vec![
+            StaticArgument::MethodType(String::from("(Ljava/lang/Object;)Ljava/lang/Object;")),
+            StaticArgument::MethodHandle(static_handle("java/lang/Math", "max", "(II)I")),
+            StaticArgument::MethodType(String::from("(Ljava/lang/Integer;)Ljava/lang/Integer;")),
+        ],
+    };
+    let class = spin(&loader, &classpath, &lambda, "Main$$Lambda$1");
+    assert!(class.is_assignable_to(&loader.load_class("java/util/function/Function").unwrap()));
+    assert!(class.find_method("apply", "(Ljava/lang/Object;)Ljava/lang/Object;").is_some());
+    assert!(class.find_field("arg$1", "I").is_some());
+    // "a" + i + "b" + 5 + l + o, the 5 being a constant of the recipe
+    let concat = Bootstrap {
+        method: static_handle("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants", ""),
+        name: String::from("makeConcatWithConstants"),
+        descriptor: String::from("(IJLjava/lang/Object;)Ljava/lang/String;"),
+        arguments: vec![StaticArgument::String(String::from("a\u{1}b\u{2}\u{1}\u{1}")), StaticArgument::Int(5)],
+    };
+    spin(&loader, &classpath, &concat, "Main$$StringConcat$2");
+    let missing = Bootstrap { arguments: vec![StaticArgument::String(String::from("\u{2}"))], ..concat };
+    assert!(missing.fast_path().unwrap().spin(&missing, "Main$$StringConcat$3").is_err());
+}
+
+#[test]
+fn handle_adapters_are_spun_valid() {
+    let loader = ClassLoader::new(Classpath::builtin_classpath(PathBuf::from("."), Rc::default()), VerifyMode::All);
+    let classpath = Classpath::builtin_classpath(PathBuf::from("."), Rc::default());
+    let count = VarHandleRef::Field {
+        class_name: String::from("Main"),
+        name: String::from("count"),
+        descriptor: String::from("I"),
+        is_static: false,
+    };
+    assert_eq!(count.access_descriptor(AccessMode::CompareAndSet), "(LMain;II)Z");
+    assert_eq!(VarHandleRef::Array(String::from("[J")).access_descriptor(AccessMode::Get), "([JI)J");
+    // (int) mh.invoke(a, b) with boxed arguments for a handle of type (II)I, widened to long
+    let method_handle = "java/lang/invoke/MethodHandle";
+    let content =
+        spin_adapter("Adapter$1", method_handle, "invokeExact", "(Ljava/lang/Object;Ljava/lang/Integer;)J", "(II)I");
+    let class = define_verified(&loader, &classpath, "Adapter$1", content.unwrap());
+    let adapt = "(Ljava/lang/invoke/MethodHandle;Ljava/lang/Object;Ljava/lang/Integer;)J";
+    assert!(class.find_method("adapt", adapt).is_some_and(|(_, method)| method.is_static()));
+    // Object element = ints.get(array, index) for int[] elements
+    let var_handle = "java/lang/invoke/VarHandle";
+    let content = spin_adapter("Adapter$2", var_handle, "get", "(Ljava/lang/Object;I)Ljava/lang/Object;", "([II)I");
+    define_verified(&loader, &classpath, "Adapter$2", content.unwrap());
+    assert!(spin_adapter("Adapter$3", method_handle, "invokeExact", "(I)I", "(II)I").is_err());
+    assert!(spin_adapter("Adapter$4", method_handle, "invokeExact", "()I", "()V").is_err());
+}
+
+#[test]
+fn reflection_accessors_are_spun_valid() {
+    let loader = ClassLoader::new(Classpath::builtin_classpath(PathBuf::from("."), Rc::default()), VerifyMode::All);
+    let classpath = Classpath::builtin_classpath(PathBuf::from("."), Rc::default());
+    let object = "Ljava/lang/Object;";
+    // Field.get of an int field, Field.set of a static one, Method.invoke of a virtual and a static method,
+    // Constructor.newInstance
+    let targets = [
+        (REF_GET_FIELD, "java/lang/Integer", "value", "I", format!("({0}{0}){0}", object)),
+        (REF_PUT_STATIC, "java/lang/System", "out", "Ljava/io/PrintStream;", format!("({0}{0}{0})V", object)),
+        (REF_INVOKE_VIRTUAL, "java/lang/Integer", "intValue", "()I", format!("({0}{0}[{0}){0}", object)),
+        (REF_INVOKE_STATIC, "java/lang/Math", "max", "(JJ)J", format!("({0}{0}[{0}){0}", object)),
+        (REF_NEW_INVOKE_SPECIAL, "java/lang/Integer", "<init>", "(I)V", format!("({0}[{0}){0}", object)),
+    ];
+    for (index, (kind, class_name, name, descriptor, access)) in targets.into_iter().enumerate() {
+        let target = MethodHandleRef {
+            kind,
+            class_name: String::from(class_name),
+            name: String::from(name),
+            descriptor: String::from(descriptor),
+        };
+        let accessor = format!("{}$$Accessor${}", class_name, index);
+        let class = define_verified(&loader, &classpath, &accessor, spin_accessor(&accessor, &target).unwrap());
+        assert!(class.find_method(ACCESSOR, &access).is_some_and(|(_, method)| method.is_static()));
+    }
+    let setter = MethodHandleRef { kind: REF_PUT_STATIC, ..static_handle("java/lang/System", "out", "()V") };
+    assert!(spin_accessor("java/lang/System$$Accessor$5", &setter).is_err());
+}
+
+#[test]
+fn proxy_classes_are_spun_valid() {
+    let loader = ClassLoader::new(Classpath::builtin_classpath(PathBuf::from("."), Rc::default()), VerifyMode::All);
+    let classpath = Classpath::builtin_classpath(PathBuf::from("."), Rc::default());
+    // a proxy of an annotation type with an int, a long[] and a void member besides those of Object
+    let methods = [
+        ("equals", "(Ljava/lang/Object;)Z"),
+        ("hashCode", "()I"),
+        ("toString", "()Ljava/lang/String;"),
+        ("count", "()I"),
+        ("ids", "()[J"),
+        ("run", "(JLjava/lang/String;)V"),
+        ("annotationType", "()Ljava/lang/Class;"),
+    ];
+    let content = spin_proxy("$Proxy1", &["java/lang/annotation/Annotation"], &methods).unwrap();
+    let class = define_verified(&loader, &classpath, "$Proxy1", content);
+    assert!(class.is_assignable_to(&loader.load_class(PROXY).unwrap()));
+    assert!(class.find_method("<init>", "(Ljava/lang/reflect/InvocationHandler;)V").is_some());
+    for (index, (name, descriptor)) in methods.into_iter().enumerate() {
+        assert!(class.find_method(name, descriptor).is_some_and(|(owner, _)| Rc::ptr_eq(&owner, &class)));
+        assert!(class.find_field(&proxy_method_field(index), "Ljava/lang/reflect/Method;").is_some());
+    }
+}
+
+#[test]
+fn an_embedded_vm_calls_static_methods_and_reports_what_they_throw() {
+    let methods = [
+        MethodDef::code(
+            "greet",
+            "(Ljava/lang/String;)Ljava/lang/String;",
+            ACC_PUBLIC | ACC_STATIC,
+            2,
+            1,
+            &[
+                Op::GetStatic("java/lang/System", "out", "Ljava/io/PrintStream;"),
+                Op::ALoad(0),
+                Op::InvokeVirtual("java/io/PrintStream", "println", "(Ljava/lang/String;)V"),
+                Op::Ldc("hello, "),
+                Op::ALoad(0),
+                Op::InvokeVirtual("java/lang/String", "concat", "(Ljava/lang/String;)Ljava/lang/String;"),
+                Op::AReturn,
+            ],
+        ),
+        MethodDef::code("widen", "(I)J", ACC_PUBLIC | ACC_STATIC, 2, 1, &[Op::ILoad(0), Op::I2L, Op::LReturn]),
+        MethodDef::code(
+            "fail",
+            "(Ljava/lang/String;)V",
+            ACC_PUBLIC | ACC_STATIC,
+            3,
+            1,
+            &[
+                Op::New("java/lang/IllegalStateException"),
+                Op::Dup,
+                Op::ALoad(0),
+                Op::InvokeSpecial("java/lang/IllegalStateException", "<init>", "(Ljava/lang/String;)V"),
+                Op::AThrow,
+            ],
+        ),
+    ];
+    let classpath = TestClasspath::new(&[ClassDef::new("Plugin", &methods)]);
+    let stdout = Sink::default();
+    let mut vm = classpath
+        .builder()
+        .verify_mode(VerifyMode::All)
+        .stdout(stdout.clone())
+        .build()
+        .unwrap();
+
+    let name = vm.new_string("plugin").unwrap();
+    let name = JValue::Object(Some(name));
+    let greeting = vm.call_static("Plugin", "greet", "(Ljava/lang/String;)Ljava/lang/String;", &[name]);
+    let Ok(JValue::Object(Some(greeting))) = greeting else {
+        panic!("greet returned {:?}", greeting);
+    };
+    assert_eq!(vm.string_value(greeting).as_deref(), Some("hello, plugin"));
+    assert_eq!(stdout.0.borrow().as_slice(), b"plugin\n");
+    assert_eq!(vm.call_static("Plugin", "widen", "(I)J", &[JValue::Int(-7)]), Ok(JValue::Long(-7)));
+
+    let exception = vm.call_static("Plugin", "fail", "(Ljava/lang/String;)V", &[name]).unwrap_err();
+    assert_eq!(exception.to_string(), "java.lang.IllegalStateException: plugin");
+    assert_eq!(exception.stack_trace, ["Plugin.fail(Unknown Source)"]);
+    let mismatch = vm.call_static("Plugin", "widen", "(I)J", &[JValue::Long(1)]).unwrap_err();
+    assert_eq!(mismatch.class_name, "java.lang.IllegalArgumentException");
+    let missing = vm.call_static("Plugin", "greet", "()V", &[]).unwrap_err();
+    assert_eq!(missing.class_name, "java.lang.NoSuchMethodError");
+    // the VM goes on after an exception
+    assert_eq!(vm.call_static("Plugin", "widen", "(I)J", &[JValue::Int(1 << 20)]), Ok(JValue::Long(1 << 20)));
+}
+
+#[test]
+fn a_class_is_only_loaded_from_the_class_file_of_its_own_name() {
+    let answer = [Op::LdcConstant(Constant::Int(42)), Op::IReturn];
+    let answer = [MethodDef::code("answer", "()I", ACC_PUBLIC | ACC_STATIC, 1, 0, &answer)];
+    let call = [Op::InvokeStatic("B", "answer", "()I"), Op::IReturn];
+    let call = [MethodDef::code("call", "()I", ACC_PUBLIC | ACC_STATIC, 1, 0, &call)];
+    let classpath = TestClasspath::new(&[ClassDef::new("XA", &answer), ClassDef::new("Caller", &call)]);
+    classpath.add("B.class", &ClassDef::new("XA", &answer).write());
+    let mut vm = classpath.builder().build().unwrap();
+
+    // XA.class is no A.class, however alike their paths
+    let missing = vm.call_static("A", "answer", "()I", &[]).unwrap_err();
+    assert_eq!(missing.to_string(), "java.lang.NoClassDefFoundError: A");
+    let wrong = vm.call_static("B", "answer", "()I", &[]).unwrap_err();
+    assert_eq!(wrong.to_string(), "java.lang.NoClassDefFoundError: B (wrong name: XA)");
+    let wrong = vm.call_static("Caller", "call", "()I", &[]).unwrap_err();
+    assert_eq!(wrong.to_string(), "java.lang.NoClassDefFoundError: B (wrong name: XA)");
+    assert_eq!(vm.call_static("XA", "answer", "()I", &[]), Ok(JValue::Int(42)));
+}
+
+#[test]
+fn a_class_that_is_its_own_super_class_is_a_class_circularity_error() {
+    let answer = [Op::LdcConstant(Constant::Int(42)), Op::IReturn];
+    let answer = [MethodDef::code("answer", "()I", ACC_PUBLIC | ACC_STATIC, 1, 0, &answer)];
+    let call = [Op::InvokeStatic("A", "answer", "()I"), Op::IReturn];
+    let call = [MethodDef::code("call", "()I", ACC_PUBLIC | ACC_STATIC, 1, 0, &call)];
+    let classpath = TestClasspath::new(&[
+        ClassDef { super_class: Some("B"), ..ClassDef::new("A", &answer) },
+        ClassDef { super_class: Some("A"), ..ClassDef::new("B", &[]) },
+        ClassDef::new("Caller", &call),
+        ClassDef::new("Fine", &answer),
+    ]);
+    let mut vm = classpath.builder().verify_mode(VerifyMode::All).build().unwrap();
+
+    let circular = vm.call_static("A", "answer", "()I", &[]).unwrap_err();
+    assert_eq!(circular.to_string(), "java.lang.ClassCircularityError: A");
+    let circular = vm.call_static("Caller", "call", "()I", &[]).unwrap_err();
+    assert_eq!(circular.to_string(), "java.lang.ClassCircularityError: A");
+    assert_eq!(vm.call_static("Fine", "answer", "()I", &[]), Ok(JValue::Int(42)));
+}
+
+#[test]
+fn a_class_that_fails_to_load_is_a_linkage_error_for_the_program() {
+    let answer = [Op::LdcConstant(Constant::Int(42)), Op::IReturn];
+    let answer = [MethodDef::code("answer", "()I", ACC_PUBLIC | ACC_STATIC, 1, 0, &answer)];
+    let unverifiable = [MethodDef::code("answer", "()I", ACC_PUBLIC | ACC_STATIC, 1, 0, &[Op::Return])];
+    let calls = ["Orphan", "Broken", "Unverifiable"].map(|name| [Op::InvokeStatic(name, "answer", "()I"), Op::IReturn]);
+    let methods = ["orphan", "broken", "unverifiable"]
+        .iter()
+        .zip(&calls)
+        .map(|(name, call)| MethodDef::code(name, "()I", ACC_PUBLIC | ACC_STATIC, 1, 0, call))
+        .collect::<Vec<_>>();
+    let classpath = TestClasspath::new(&[
+        ClassDef { super_class: Some("Missing"), ..ClassDef::new("Orphan", &answer) },
+        ClassDef::new("Unverifiable", &unverifiable),
+        ClassDef::new("Caller", &methods),
+    ]);
+    let mut broken = ClassDef::new("Broken", &answer).write();
+    broken[..4].copy_from_slice(&0xCAFED00Du32.to_be_bytes());
+    classpath.add("Broken.class", &broken);
+    let mut vm = classpath.builder().verify_mode(VerifyMode::All).build().unwrap();
+
+    // the class reported missing is the super class that is, not the class asked for
+    let missing = vm.call_static("Caller", "orphan", "()I", &[]).unwrap_err();
+    assert_eq!(missing.to_string(), "java.lang.NoClassDefFoundError: Missing");
+    let broken = vm.call_static("Caller", "broken", "()I", &[]).unwrap_err();
+    assert_eq!(broken.to_string(), "java.lang.ClassFormatError: bad magic number 0xcafed00d");
+    let unverifiable = vm.call_static("Caller", "unverifiable", "()I", &[]).unwrap_err();
+    assert_eq!(unverifiable.class_name, "java.lang.VerifyError");
+    // these are thrown into the program, so the VM goes on
+    assert_eq!(vm.call_static("Caller", "broken", "()I", &[]).unwrap_err().class_name, "java.lang.ClassFormatError");
+}
+
+#[test]
+fn a_main_method_that_throws_fails_the_run() {
+    let main = [
+        Op::New("java/lang/IllegalStateException"),
+        Op::Dup,
+        Op::InvokeSpecial("java/lang/IllegalStateException", "<init>", "()V"),
+        Op::AThrow,
+    ];
+    let main = [MethodDef::code("main", "([Ljava/lang/String;)V", ACC_PUBLIC | ACC_STATIC, 2, 1, &main)];
+    let classpath = TestClasspath::new(&[ClassDef::new("Failing", &main)]);
+    let stderr = Sink::default();
+    let mut vm = classpath.builder().stderr(stderr.clone()).build().unwrap();
+
+    let result = vm.run_main("Failing", &[]);
+    let Err(Error::UncaughtException(class_name)) = result else {
+        panic!("main ended with {:?}", result);
+    };
+    assert_eq!(class_name, "java.lang.IllegalStateException");
+    let stderr = String::from_utf8(stderr.0.borrow().clone()).unwrap();
+    assert!(stderr.starts_with("Exception in thread \"main\" java.lang.IllegalStateException\n"), "{}", stderr);
+}
+
+#[test]
+fn value_of_gives_the_same_box_for_small_values() {
+    let classpath = TestClasspath::new(&[]);
+    let mut vm = classpath.builder().build().unwrap();
+    let mut box_ = |class_name: &str, descriptor: &str, value: JValue| {
+        let descriptor = format!("({})L{};", descriptor, class_name);
+        vm.call_static(class_name, "valueOf", &descriptor, &[value]).unwrap()
+    };
+    // the two ends of the values cached, and values that are not; every byte and boolean is
+    let cases: [(&str, &str, [JValue; 2], &[JValue]); 6] = [
+        ("java/lang/Integer", "I", [JValue::Int(-128), JValue::Int(127)], &[JValue::Int(-129), JValue::Int(128)]),
+        ("java/lang/Long", "J", [JValue::Long(-128), JValue::Long(127)], &[JValue::Long(i64::MIN), JValue::Long(128)]),
+        ("java/lang/Short", "S", [JValue::Short(-128), JValue::Short(127)], &[JValue::Short(-129), JValue::Short(128)]),
+        ("java/lang/Byte", "B", [JValue::Byte(-128), JValue::Byte(127)], &[]),
+        ("java/lang/Character", "C", [JValue::Char(0), JValue::Char(127)], &[JValue::Char(128), JValue::Char(0xffff)]),
+        ("java/lang/Boolean", "Z", [JValue::Boolean(false), JValue::Boolean(true)], &[]),
+    ];
+    for (class_name, descriptor, cached, uncached) in cases {
+        for value in cached {
+            assert_eq!(box_(class_name, descriptor, value), box_(class_name, descriptor, value), "{:?}", value);
+        }
+        assert_ne!(box_(class_name, descriptor, cached[0]), box_(class_name, descriptor, cached[1]));
+        for &value in uncached {
+            assert_ne!(box_(class_name, descriptor, value), box_(class_name, descriptor, value), "{:?}", value);
+        }
+    }
+}
+
+// Race, whose <clinit> takes more than a time slice and then sets `value` to 42 or, when `fails`, throws a
+// NullPointerException, and Reader, which copies Race.value into a static field of its own named after the thread
+fn race_classes(fails: bool) -> Vec<Vec<u8>> {
+    let mut clinit = [Op::AConstNull, Op::Pop].repeat(QUANTUM);
+    match fails {
+        true => clinit.extend([Op::AConstNull, Op::AThrow]),
+        false => clinit.extend([Op::LdcConstant(Constant::Int(42)), Op::PutStatic("Race", "value", "I"), Op::Return]),
+    }
+    let clinit = [MethodDef::code("<clinit>", "()V", ACC_STATIC, 1, 0, &clinit)];
+    let race = ClassDef { fields: &[FieldDef::new("value", "I", ACC_STATIC)], ..ClassDef::new("Race", &clinit) };
+    let read = |field| [Op::GetStatic("Race", "value", "I"), Op::PutStatic("Reader", field, "I"), Op::Return];
+    let (spawned, embedded) = (read("spawned"), read("embedded"));
+    let methods = [
+        MethodDef::code("spawned", "()V", ACC_PUBLIC | ACC_STATIC, 1, 0, &spawned),
+        MethodDef::code("embedded", "()V", ACC_PUBLIC | ACC_STATIC, 1, 0, &embedded),
+    ];
+    let reader = ClassDef {
+        fields: &[FieldDef::new("spawned", "I", ACC_STATIC), FieldDef::new("embedded", "I", ACC_STATIC)],
+        ..ClassDef::new("Reader", &methods)
+    };
+    vec![race.write(), reader.write()]
+}
+
+// an interpreter on the built-in class library with the race classes, where a thread named reader runs
+// Reader.spawned ahead of the embedded thread it gives, which is to run Reader.embedded
+fn race(fails: bool, stderr: Sink) -> (Interpreter, Thread) {
+    let loader = ClassLoader::new(Classpath::builtin_classpath(PathBuf::from("."), Rc::default()), VerifyMode::All);
+    for (name, content) in ["Race", "Reader"].into_iter().zip(race_classes(fails)) {
+        loader.define_class(name, content).unwrap();
+    }
+    let reader = loader.load_class("Reader").unwrap();
+    let output = Output { stdout: Box::new(Sink::default()), stderr: Box::new(stderr) };
+    let heap = Box::new(MarkSweepHeap::new(usize::MAX, false, Rc::default()));
+    let mut interpreter = Interpreter::new(loader, 1 << 20, heap, Vec::new(), output, Tracer::new(&[], false), None);
+    let mut thread = interpreter.new_embedded_thread("main");
+    interpreter.boot(&mut thread).unwrap();
+    let frame = |name| {
+        let method = reader.methods.iter().find(|method| method.name == name).unwrap().clone();
+        Frame::new(reader.clone(), method)
+    };
+    interpreter.env(&mut thread).scheduler.spawn(String::from("reader"), false, None, frame("spawned")).unwrap();
+    thread.push_frame(frame("embedded")).unwrap();
+    (interpreter, thread)
+}
+
+fn static_int(loader: &ClassLoader, class_name: &str, name: &str) -> i32 {
+    let (class, field) = loader.load_class(class_name).unwrap().find_field(name, "I").unwrap();
+    let value = class.static_fields.borrow()[field.slot].int();
+    value
+}
+
+#[test]
+fn a_thread_using_a_class_another_thread_is_initializing_waits_until_it_is_done() {
+    let (mut interpreter, thread) = race(false, Sink::default());
+    let thread = interpreter.complete(thread).unwrap();
+    assert!(thread.outcome.is_none_or(|outcome| outcome.is_ok()));
+    // the reader thread went first, so the embedded one found Race being initialized
+    assert_eq!(static_int(interpreter.loader(), "Reader", "spawned"), 42);
+    assert_eq!(static_int(interpreter.loader(), "Reader", "embedded"), 42);
+    assert_eq!(interpreter.loader().load_class("Race").unwrap().init_state.get(), InitState::Initialized);
+}
+
+#[test]
+fn a_throwing_initializer_ends_in_exception_in_initializer_error_and_later_uses_in_no_class_def_found_error() {
+    let stderr = Sink::default();
+    let (mut interpreter, thread) = race(true, stderr.clone());
+    let mut thread = interpreter.complete(thread).unwrap();
+    let race = interpreter.loader().load_class("Race").unwrap();
+    assert_eq!(race.init_state.get(), InitState::Erroneous);
+    let stderr = String::from_utf8(stderr.0.borrow().clone()).unwrap();
+    assert!(stderr.starts_with(concat!(
+        "Exception in thread \"reader\" java.lang.ExceptionInInitializerError\n",
+        "\tat Reader.spawned(Unknown Source)\n",
+        "Caused by: java.lang.NullPointerException\n",
+        "\tat Race.<clinit>(Unknown Source)\n",
+    )), "{}", stderr);
+
+    // the embedded thread was waiting for the reader thread, and any use after that fails the same
+    for _ in 0..2 {
+        let Some(Err(exception)) = thread.outcome.take() else {
+            panic!("Reader.embedded did not throw");
+        };
+        let env = interpreter.env(&mut thread);
+        let message = env.heap[exception].get_ref_field("detailMessage", "Ljava/lang/String;").unwrap();
+        assert_eq!(env.heap[exception].class.name, "java/lang/NoClassDefFoundError");
+        assert_eq!(env.rust_string(message), "Could not initialize class Race");
+        let reader = interpreter.loader().load_class("Reader").unwrap();
+        let method = reader.methods.iter().find(|method| method.name == "embedded").unwrap().clone();
+        thread.push_frame(Frame::new(reader, method)).unwrap();
+        thread = interpreter.complete(thread).unwrap();
+    }
+}
+
+#[test]
+fn an_embedded_call_into_a_class_whose_initializer_throws_reports_the_error() {
+    let classpath = TestClasspath::new(&[]);
+    for (name, content) in ["Race", "Reader"].into_iter().zip(race_classes(true)) {
+        classpath.add(&format!("{}.class", name), &content);
+    }
+    let mut vm = classpath.builder().build().unwrap();
+
+    let exception = vm.call_static("Reader", "spawned", "()V", &[]).unwrap_err();
+    assert_eq!(exception.class_name, "java.lang.ExceptionInInitializerError");
+    assert_eq!(exception.stack_trace, ["Reader.spawned(Unknown Source)"]);
+    let exception = vm.call_static("Reader", "embedded", "()V", &[]).unwrap_err();
+    assert_eq!(exception.to_string(), "java.lang.NoClassDefFoundError: Could not initialize class Race");
+}
@@ -0,0 +1,297 @@
+// the reports the interpreter makes on request of what it runs: -Xtrace:inst logs every instruction, -Xtrace:call
+// every call and return, and -Xprof counts and times the instructions by opcode and by method, for a profile when
+// the VM exits
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::instructions::Instruction;
+use crate::runtime::class::Method;
+use crate::runtime::{Frame, Thread};
+
+// what -Xtrace logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceKind {
+    Instructions,
+    Calls,
+}
+
+impl FromStr for TraceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inst" => Ok(TraceKind::Instructions),
+            "call" => Ok(TraceKind::Calls),
+            _ => Err(format!("unknown trace {}, expected inst|call", s)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Tracer {
+    instructions: bool,
+    calls: bool,
+    profile: Option<Profile>,
+}
+
+#[derive(Default)]
+struct Profile {
+    opcodes: HashMap<&'static str, Counter>,
+    // by the address of the method, which lives as long as the VM
+    methods: HashMap<*const Method, MethodCounter>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Counter {
+    count: u64,
+    time: Duration,
+}
+
+struct MethodCounter {
+    name: String,
+    calls: u64,
+    // the instructions of the method itself and the time they took, not counting the methods it calls
+    instructions: Counter,
+}
+
+// how long an instruction took, from when Tracer::start saw it
+pub struct Timing {
+    start: Instant,
+    mnemonic: &'static str,
+    method: *const Method,
+}
+
+impl Tracer {
+    pub fn new(traces: &[TraceKind], profile: bool) -> Tracer {
+        Tracer {
+            instructions: traces.contains(&TraceKind::Instructions),
+            calls: traces.contains(&TraceKind::Calls),
+            profile: profile.then(Profile::default),
+        }
+    }
+
+    // whether anything is traced or profiled, for the interpreter to skip it all otherwise
+    pub fn is_enabled(&self) -> bool {
+        self.instructions || self.calls || self.profile.is_some()
+    }
+
+    pub fn traces_calls(&self) -> bool {
+        self.calls
+    }
+
+    // -Xtrace and -Xprof of `instruction`, which the current frame of `thread` is about to run, and of the call
+    // of the frame when `entered` it is its first; gives the timing to end once it has run
+    pub fn start(
+        &mut self,
+        out: &mut dyn Write,
+        thread: &Thread,
+        entered: bool,
+        instruction: &Instruction,
+    ) -> Option<Timing> {
+        let frame = thread.frame()?;
+        let method = &frame.method;
+        if entered {
+            self.call(out, &thread.name, &frame.class.java_name(), method, thread.depth(), false);
+        }
+        if self.instructions {
+            let _ = writeln!(
+                out,
+                "[inst][{}] {}.{}{} @{}: {} (stack {})",
+                thread.name,
+                frame.class.java_name(),
+                method.name,
+                method.descriptor,
+                frame.pc,
+                instruction,
+                frame.operand_stack.depth()
+            );
+        }
+        self.profile.as_ref()?;
+        Some(Timing {
+            start: Instant::now(),
+            mnemonic: instruction.mnemonic(),
+            method: Rc::as_ptr(method),
+        })
+    }
+
+    pub fn end(&mut self, timing: Timing) {
+        let time = timing.start.elapsed();
+        let Some(profile) = &mut self.profile else {
+            return;
+        };
+        let opcode = profile.opcodes.entry(timing.mnemonic).or_default();
+        opcode.count += 1;
+        opcode.time += time;
+        if let Some(method) = profile.methods.get_mut(&timing.method) {
+            method.instructions.count += 1;
+            method.instructions.time += time;
+        }
+    }
+
+    // -Xtrace:call and -Xprof of a call of `method` of class `class_name` on thread `thread`, `depth` frames deep
+    // with it, which is native when `native`
+    pub fn call(
+        &mut self,
+        out: &mut dyn Write,
+        thread: &str,
+        class_name: &str,
+        method: &Rc<Method>,
+        depth: usize,
+        native: bool,
+    ) {
+        if self.calls {
+            let native = if native { " native" } else { "" };
+            let indent = "  ".repeat(depth.saturating_sub(1));
+            let name = format!("{}.{}{}", class_name, method.name, method.descriptor);
+            let _ = writeln!(out, "[call][{}] {}-> {}{}", thread, indent, name, native);
+        }
+        if let Some(profile) = &mut self.profile {
+            let counter = profile.methods.entry(Rc::as_ptr(method)).or_insert_with(|| MethodCounter {
+                name: format!("{}.{}{}", class_name, method.name, method.descriptor),
+                calls: 0,
+                instructions: Counter::default(),
+            });
+            counter.calls += 1;
+        }
+    }
+
+    // -Xtrace:call of `frame`, just popped off `thread`, returning, or throwing `exception` when there is one
+    pub fn exit(&self, out: &mut dyn Write, thread: &Thread, frame: &Frame, exception: Option<&str>) {
+        if !self.calls {
+            return;
+        }
+        let indent = "  ".repeat(thread.depth());
+        let name = format!("{}.{}{}", frame.class.java_name(), frame.method.name, frame.method.descriptor);
+        let _ = match exception {
+            Some(exception) => writeln!(out, "[call][{}] {}<- {} threw {}", thread.name, indent, name, exception),
+            None => writeln!(out, "[call][{}] {}<- {}", thread.name, indent, name),
+        };
+    }
+
+    // the -Xprof profile: the opcodes and then the methods, the longest running first
+    pub fn report(&self, out: &mut dyn Write) {
+        let Some(profile) = &self.profile else {
+            return;
+        };
+        let mut opcodes: Vec<_> = profile.opcodes.iter().collect();
+        opcodes.sort_by(|(a_name, a), (b_name, b)| b.time.cmp(&a.time).then(a_name.cmp(b_name)));
+        let total = opcodes.iter().fold(Counter::default(), |total, (_, counter)| Counter {
+            count: total.count + counter.count,
+            time: total.time + counter.time,
+        });
+        let _ = writeln!(out, "Profile: {} instructions in {:.6} secs", total.count, total.time.as_secs_f64());
+        let _ = writeln!(out, "{:<20} {:>12} {:>12} {:>7}", "opcode", "count", "secs", "%");
+        for (mnemonic, counter) in opcodes {
+            let _ = writeln!(
+                out,
+                "{:<20} {:>12} {:>12.6} {:>6.2}%",
+                mnemonic,
+                counter.count,
+                counter.time.as_secs_f64(),
+                percent(counter.time, total.time)
+            );
+        }
+        let mut methods: Vec<_> = profile.methods.values().collect();
+        methods.sort_by(|a, b| b.instructions.time.cmp(&a.instructions.time).then(a.name.cmp(&b.name)));
+        let _ = writeln!(out, "{:>12} {:>12} {:>12} {:>7}  method", "calls", "instructions", "secs", "%");
+        for method in methods {
+            let _ = writeln!(
+                out,
+                "{:>12} {:>12} {:>12.6} {:>6.2}%  {}",
+                method.calls,
+                method.instructions.count,
+                method.instructions.time.as_secs_f64(),
+                percent(method.instructions.time, total.time),
+                method.name
+            );
+        }
+    }
+}
+
+fn percent(time: Duration, total: Duration) -> f64 {
+    match total.is_zero() {
+        true => 0.0,
+        false => time.as_secs_f64() * 100.0 / total.as_secs_f64(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::classfile::class_reader::{ACC_PUBLIC, ACC_STATIC};
+    use crate::classfile::writer::{ClassDef, Constant, MethodDef, Op};
+    use crate::native::Sink;
+    use crate::vm::TestClasspath;
+
+    use super::TraceKind;
+
+    #[test]
+    fn traces_and_profiles_go_to_standard_error_and_the_gc_log_to_standard_output() {
+        let methods = [
+            MethodDef::code(
+                "main",
+                "([Ljava/lang/String;)V",
+                ACC_PUBLIC | ACC_STATIC,
+                1,
+                1,
+                &[Op::InvokeStatic("Traced", "answer", "()I"), Op::Pop, Op::Return],
+            ),
+            MethodDef::code("<clinit>", "()V", ACC_STATIC, 0, 0, &[Op::Return]),
+            MethodDef::code(
+                "answer",
+                "()I",
+                ACC_PUBLIC | ACC_STATIC,
+                1,
+                0,
+                &[Op::LdcConstant(Constant::Int(42)), Op::IReturn],
+            ),
+        ];
+        let classpath = TestClasspath::new(&[ClassDef::new("Traced", &methods)]);
+        let (stdout, stderr) = (Sink::default(), Sink::default());
+        let mut vm = classpath
+            .builder()
+            .trace(TraceKind::Instructions)
+            .trace(TraceKind::Calls)
+            .profile(true)
+            .verbose_gc(true)
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .build()
+            .unwrap();
+        vm.run_main("Traced", &[]).unwrap();
+
+        // what the program prints is kept apart from what the VM reports about it
+        let stdout = String::from_utf8(stdout.0.borrow().clone()).unwrap();
+        assert!(stdout.starts_with("[GC statistics: 0 collections (0 minor)"), "{}", stdout);
+        let output = String::from_utf8(stderr.0.borrow().clone()).unwrap();
+        let traced: Vec<&str> =
+            output.lines().filter(|line| line.starts_with('[') && line.contains("Traced.")).collect();
+        assert_eq!(
+            traced,
+            [
+                // the launcher has Traced initialized before main, so invokestatic runs once
+                "[call][main]   -> Traced.<clinit>()V",
+                "[inst][main] Traced.<clinit>()V @0: return (stack 0)",
+                "[call][main]   <- Traced.<clinit>()V",
+                "[call][main] -> Traced.main([Ljava/lang/String;)V",
+                "[inst][main] Traced.main([Ljava/lang/String;)V @0: invokestatic #10 (stack 0)",
+                "[call][main]   -> Traced.answer()I",
+                "[inst][main] Traced.answer()I @0: ldc_w #14 (stack 0)",
+                "[inst][main] Traced.answer()I @3: ireturn (stack 1)",
+                "[call][main]   <- Traced.answer()I",
+                "[inst][main] Traced.main([Ljava/lang/String;)V @3: pop (stack 1)",
+                "[inst][main] Traced.main([Ljava/lang/String;)V @4: return (stack 0)",
+                "[call][main] <- Traced.main([Ljava/lang/String;)V",
+            ]
+        );
+        let profile = output.lines().skip_while(|line| !line.starts_with("Profile: ")).collect::<Vec<_>>();
+        let count = |name: &str| profile.iter().find(|line| line.starts_with(&format!("{} ", name))).map(|line| {
+            line.split_whitespace().nth(1).unwrap().parse::<u64>().unwrap()
+        });
+        assert_eq!((count("invokestatic"), count("ireturn"), count("pop")), (Some(1), Some(1), Some(1)));
+        let answer = profile.iter().find(|line| line.ends_with("  Traced.answer()I")).unwrap();
+        assert_eq!(answer.split_whitespace().take(2).collect::<Vec<_>>(), ["1", "2"]);
+    }
+}
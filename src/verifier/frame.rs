@@ -0,0 +1,617 @@
+use crate::classfile::class_reader::{get_class_name, get_member_ref, get_name_and_type};
+use crate::classfile::constant_pool::ConstantInfo::*;
+use crate::classfile::descriptor::{parse_field_descriptor, parse_method_descriptor};
+use crate::instructions::Instruction::{self, *};
+
+use super::types::{is_assignable, VType};
+use super::MethodContext;
+
+// the types of the local variables and operand stack at one instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub locals: Vec<VType>,
+    pub stack: Vec<VType>,
+    // set while `this` has not been initialized yet in an <init> method
+    pub this_uninit: bool,
+}
+
+// what an instruction does to control flow
+pub struct Step {
+    pub next_pc: usize,
+    pub falls_through: bool,
+    pub targets: Vec<usize>,
+    // jsr: the subroutine called, control comes back to next_pc through its ret
+    pub jsr: Option<usize>,
+    // ret: the subroutine returned from
+    pub ret: Option<usize>,
+}
+
+type VerifyResult<T> = Result<T, String>;
+
+impl Frame {
+    pub(crate) fn is_assignable_to(&self, other: &Frame, ctx: &MethodContext) -> bool {
+        self.stack.len() == other.stack.len()
+            && self.locals.len() == other.locals.len()
+            && (!self.this_uninit || other.this_uninit)
+            && self.locals.iter().zip(&other.locals).all(|(a, b)| is_assignable(a, b, ctx.hierarchy))
+            && self.stack.iter().zip(&other.stack).all(|(a, b)| is_assignable(a, b, ctx.hierarchy))
+    }
+
+    pub(crate) fn push(&mut self, value: VType, ctx: &MethodContext) -> VerifyResult<()> {
+        let size = if value.is_category2() { 2 } else { 1 };
+        if self.stack.len() + size > ctx.max_stack {
+            return Err(format!("operand stack overflow, max_stack is {}", ctx.max_stack));
+        }
+        let category2 = value.is_category2();
+        self.stack.push(value);
+        if category2 {
+            self.stack.push(VType::Top);
+        }
+        Ok(())
+    }
+
+    fn pop_word(&mut self) -> VerifyResult<VType> {
+        self.stack.pop().ok_or_else(|| String::from("operand stack underflow"))
+    }
+
+    // pops a value that must be assignable to `expected`
+    pub(crate) fn pop(&mut self, expected: &VType, ctx: &MethodContext) -> VerifyResult<VType> {
+        if expected.is_category2() {
+            let top = self.pop_word()?;
+            let value = self.pop_word()?;
+            if top != VType::Top || &value != expected {
+                return Err(format!("expected {:?} on the operand stack, found {:?}", expected, value));
+            }
+            return Ok(value);
+        }
+        let value = self.pop_word()?;
+        if !is_assignable(&value, expected, ctx.hierarchy) || value == VType::Top {
+            return Err(format!("expected {:?} on the operand stack, found {:?}", expected, value));
+        }
+        Ok(value)
+    }
+
+    fn pop_reference(&mut self) -> VerifyResult<VType> {
+        let value = self.pop_word()?;
+        if !value.is_reference() {
+            return Err(format!("expected a reference on the operand stack, found {:?}", value));
+        }
+        Ok(value)
+    }
+
+    fn pop_array(&mut self) -> VerifyResult<VType> {
+        let value = self.pop_word()?;
+        if value != VType::Null && !value.is_array() {
+            return Err(format!("expected an array on the operand stack, found {:?}", value));
+        }
+        Ok(value)
+    }
+
+    // removes the top `words` entries without splitting a long or double
+    fn pop_words(&mut self, words: usize) -> VerifyResult<Vec<VType>> {
+        if self.stack.len() < words {
+            return Err(String::from("operand stack underflow"));
+        }
+        let values = self.stack.split_off(self.stack.len() - words);
+        if values.first() == Some(&VType::Top) {
+            return Err(String::from("instruction splits a long or double value"));
+        }
+        Ok(values)
+    }
+
+    // the dup family: copies the top `dup` words below the `skip` words underneath them
+    fn dup_x(&mut self, dup: usize, skip: usize, ctx: &MethodContext) -> VerifyResult<()> {
+        let duplicated = self.pop_words(dup)?;
+        let skipped = self.pop_words(skip)?;
+        if self.stack.len() + skipped.len() + 2 * duplicated.len() > ctx.max_stack {
+            return Err(format!("operand stack overflow, max_stack is {}", ctx.max_stack));
+        }
+        self.stack.extend(duplicated.iter().cloned());
+        self.stack.extend(skipped);
+        self.stack.extend(duplicated);
+        Ok(())
+    }
+
+    pub(crate) fn load(&mut self, index: usize, expected: &VType, ctx: &MethodContext) -> VerifyResult<()> {
+        let value = self.local(index, expected.is_category2())?;
+        let matches = if expected.is_reference() {
+            value.is_reference()
+        } else {
+            &value == expected
+        };
+        if !matches {
+            return Err(format!("expected {:?} in local {}, found {:?}", expected, index, value));
+        }
+        self.push(value, ctx)
+    }
+
+    fn local(&self, index: usize, category2: bool) -> VerifyResult<VType> {
+        let last = if category2 { index + 1 } else { index };
+        if last >= self.locals.len() {
+            return Err(format!("local variable index {} out of range, max_locals is {}", last, self.locals.len()));
+        }
+        Ok(self.locals[index].clone())
+    }
+
+    pub(crate) fn store(&mut self, index: usize, value: VType) -> VerifyResult<()> {
+        let category2 = value.is_category2();
+        self.local(index, category2)?;
+        // overwriting the second half of a long or double invalidates the first half
+        if index > 0 && self.locals[index - 1].is_category2() {
+            self.locals[index - 1] = VType::Top;
+        }
+        self.locals[index] = value;
+        if category2 {
+            self.locals[index + 1] = VType::Top;
+        }
+        Ok(())
+    }
+
+    fn replace_uninitialized(&mut self, uninitialized: &VType, initialized: VType) {
+        for t in self.locals.iter_mut().chain(self.stack.iter_mut()) {
+            if t == uninitialized {
+                *t = initialized.clone();
+            }
+        }
+        if uninitialized == &VType::UninitializedThis {
+            self.this_uninit = false;
+        }
+    }
+
+    fn binary(&mut self, operand: VType, ctx: &MethodContext) -> VerifyResult<()> {
+        self.pop(&operand, ctx)?;
+        self.pop(&operand, ctx)?;
+        self.push(operand, ctx)
+    }
+
+    fn convert(&mut self, from: VType, to: VType, ctx: &MethodContext) -> VerifyResult<()> {
+        self.pop(&from, ctx)?;
+        self.push(to, ctx)
+    }
+
+    fn array_load(&mut self, element: &[&str], result: VType, ctx: &MethodContext) -> VerifyResult<()> {
+        self.pop(&VType::Integer, ctx)?;
+        let array = self.pop_array()?;
+        check_array_type(&array, element)?;
+        self.push(result, ctx)
+    }
+
+    fn array_store(&mut self, element: &[&str], value: VType, ctx: &MethodContext) -> VerifyResult<()> {
+        self.pop(&value, ctx)?;
+        self.pop(&VType::Integer, ctx)?;
+        let array = self.pop_array()?;
+        check_array_type(&array, element)
+    }
+
+    fn return_value(&mut self, expected: VType, ctx: &MethodContext) -> VerifyResult<()> {
+        let matches = match (&ctx.return_type, &expected) {
+            (Some(return_type), VType::Reference(_)) => return_type.is_reference(),
+            (Some(return_type), expected) => return_type == expected,
+            (None, _) => false,
+        };
+        if !matches {
+            return Err(format!("return instruction does not match return type {:?}", ctx.return_type));
+        }
+        let declared = ctx.return_type.clone().unwrap_or(VType::Top);
+        self.pop(&declared, ctx)?;
+        Ok(())
+    }
+
+    // abstractly executes `instruction` at `pc`, leaving the frame in its outgoing state
+    pub(crate) fn execute(&mut self, pc: usize, instruction: &Instruction, ctx: &MethodContext) -> VerifyResult<Step> {
+        let mut falls_through = true;
+        let mut jsr = None;
+        let mut ret = None;
+        let targets = instruction.branch_targets(pc);
+        let object = || VType::Reference(String::from("java/lang/Object"));
+        match instruction {
+            Nop => {}
+            AConstNull => self.push(VType::Null, ctx)?,
+            IConst(_) | BIPush(_) | SIPush(_) => self.push(VType::Integer, ctx)?,
+            LConst(_) => self.push(VType::Long, ctx)?,
+            FConst(_) => self.push(VType::Float, ctx)?,
+            DConst(_) => self.push(VType::Double, ctx)?,
+            Ldc(index) => {
+                let value = ldc_type(*index as u16, false, ctx)?;
+                self.push(value, ctx)?
+            }
+            LdcW(index) => {
+                let value = ldc_type(*index, false, ctx)?;
+                self.push(value, ctx)?
+            }
+            Ldc2W(index) => {
+                let value = ldc_type(*index, true, ctx)?;
+                self.push(value, ctx)?
+            }
+            ILoad(local) => self.load(local.index(), &VType::Integer, ctx)?,
+            LLoad(local) => self.load(local.index(), &VType::Long, ctx)?,
+            FLoad(local) => self.load(local.index(), &VType::Float, ctx)?,
+            DLoad(local) => self.load(local.index(), &VType::Double, ctx)?,
+            ALoad(local) => self.load(local.index(), &object(), ctx)?,
+            IALoad => self.array_load(&["[I"], VType::Integer, ctx)?,
+            LALoad => self.array_load(&["[J"], VType::Long, ctx)?,
+            FALoad => self.array_load(&["[F"], VType::Float, ctx)?,
+            DALoad => self.array_load(&["[D"], VType::Double, ctx)?,
+            AALoad => {
+                self.pop(&VType::Integer, ctx)?;
+                let array = self.pop_array()?;
+                match array.component() {
+                    Some(component) if component.is_reference() => self.push(component, ctx)?,
+                    _ => return Err(format!("aaload on {:?}", array)),
+                }
+            }
+            BALoad => self.array_load(&["[B", "[Z"], VType::Integer, ctx)?,
+            CALoad => self.array_load(&["[C"], VType::Integer, ctx)?,
+            SALoad => self.array_load(&["[S"], VType::Integer, ctx)?,
+            IStore(local) => self.store_typed(local.index(), VType::Integer, ctx)?,
+            LStore(local) => self.store_typed(local.index(), VType::Long, ctx)?,
+            FStore(local) => self.store_typed(local.index(), VType::Float, ctx)?,
+            DStore(local) => self.store_typed(local.index(), VType::Double, ctx)?,
+            AStore(local) => self.store_typed(local.index(), object(), ctx)?,
+            IAStore => self.array_store(&["[I"], VType::Integer, ctx)?,
+            LAStore => self.array_store(&["[J"], VType::Long, ctx)?,
+            FAStore => self.array_store(&["[F"], VType::Float, ctx)?,
+            DAStore => self.array_store(&["[D"], VType::Double, ctx)?,
+            AAStore => {
+                self.pop_reference()?;
+                self.pop(&VType::Integer, ctx)?;
+                let array = self.pop_array()?;
+                if !matches!(array.component(), Some(component) if component.is_reference()) {
+                    return Err(format!("aastore on {:?}", array));
+                }
+            }
+            BAStore => self.array_store(&["[B", "[Z"], VType::Integer, ctx)?,
+            CAStore => self.array_store(&["[C"], VType::Integer, ctx)?,
+            SAStore => self.array_store(&["[S"], VType::Integer, ctx)?,
+            Pop => {
+                self.pop_words(1)?;
+            }
+            Pop2 => {
+                self.pop_words(2)?;
+            }
+            Dup => self.dup_x(1, 0, ctx)?,
+            DupX1 => self.dup_x(1, 1, ctx)?,
+            DupX2 => self.dup_x(1, 2, ctx)?,
+            Dup2 => self.dup_x(2, 0, ctx)?,
+            Dup2X1 => self.dup_x(2, 1, ctx)?,
+            Dup2X2 => self.dup_x(2, 2, ctx)?,
+            Swap => {
+                let top = self.pop_words(1)?;
+                let below = self.pop_words(1)?;
+                self.stack.extend(top);
+                self.stack.extend(below);
+            }
+            IAdd | ISub | IMul | IDiv | IRem | IAnd | IOr | IXor => self.binary(VType::Integer, ctx)?,
+            LAdd | LSub | LMul | LDiv | LRem | LAnd | LOr | LXor => self.binary(VType::Long, ctx)?,
+            FAdd | FSub | FMul | FDiv | FRem => self.binary(VType::Float, ctx)?,
+            DAdd | DSub | DMul | DDiv | DRem => self.binary(VType::Double, ctx)?,
+            INeg => self.convert(VType::Integer, VType::Integer, ctx)?,
+            LNeg => self.convert(VType::Long, VType::Long, ctx)?,
+            FNeg => self.convert(VType::Float, VType::Float, ctx)?,
+            DNeg => self.convert(VType::Double, VType::Double, ctx)?,
+            IShl | IShr | IUShr => self.binary(VType::Integer, ctx)?,
+            LShl | LShr | LUShr => {
+                self.pop(&VType::Integer, ctx)?;
+                self.convert(VType::Long, VType::Long, ctx)?
+            }
+            IInc { local, .. } => {
+                if self.local(local.index(), false)? != VType::Integer {
+                    return Err(format!("iinc on non-int local {}", local.index()));
+                }
+            }
+            I2L => self.convert(VType::Integer, VType::Long, ctx)?,
+            I2F => self.convert(VType::Integer, VType::Float, ctx)?,
+            I2D => self.convert(VType::Integer, VType::Double, ctx)?,
+            L2I => self.convert(VType::Long, VType::Integer, ctx)?,
+            L2F => self.convert(VType::Long, VType::Float, ctx)?,
+            L2D => self.convert(VType::Long, VType::Double, ctx)?,
+            F2I => self.convert(VType::Float, VType::Integer, ctx)?,
+            F2L => self.convert(VType::Float, VType::Long, ctx)?,
+            F2D => self.convert(VType::Float, VType::Double, ctx)?,
+            D2I => self.convert(VType::Double, VType::Integer, ctx)?,
+            D2L => self.convert(VType::Double, VType::Long, ctx)?,
+            D2F => self.convert(VType::Double, VType::Float, ctx)?,
+            I2B | I2C | I2S => self.convert(VType::Integer, VType::Integer, ctx)?,
+            LCmp => {
+                self.pop(&VType::Long, ctx)?;
+                self.convert(VType::Long, VType::Integer, ctx)?
+            }
+            FCmpL | FCmpG => {
+                self.pop(&VType::Float, ctx)?;
+                self.convert(VType::Float, VType::Integer, ctx)?
+            }
+            DCmpL | DCmpG => {
+                self.pop(&VType::Double, ctx)?;
+                self.convert(VType::Double, VType::Integer, ctx)?
+            }
+            IfEq(_) | IfNe(_) | IfLt(_) | IfGe(_) | IfGt(_) | IfLe(_) => {
+                self.pop(&VType::Integer, ctx)?;
+            }
+            IfICmpEq(_) | IfICmpNe(_) | IfICmpLt(_) | IfICmpGe(_) | IfICmpGt(_) | IfICmpLe(_) => {
+                self.pop(&VType::Integer, ctx)?;
+                self.pop(&VType::Integer, ctx)?;
+            }
+            IfACmpEq(_) | IfACmpNe(_) => {
+                self.pop_reference()?;
+                self.pop_reference()?;
+            }
+            IfNull(_) | IfNonNull(_) => {
+                self.pop_reference()?;
+            }
+            Goto(_) | GotoW(_) => falls_through = false,
+            Jsr(_) | JsrW(_) => {
+                let offset = match instruction {
+                    Jsr(offset) => *offset as i32,
+                    JsrW(offset) => *offset,
+                    _ => unreachable!(),
+                };
+                let target = (pc as i64 + offset as i64) as usize;
+                self.push(VType::ReturnAddress(target), ctx)?;
+                jsr = Some(target);
+                falls_through = false;
+            }
+            Ret(local) => {
+                ret = Some(self.return_address(local.index())?);
+                falls_through = false;
+            }
+            TableSwitch { .. } => {
+                self.pop(&VType::Integer, ctx)?;
+                falls_through = false;
+            }
+            LookupSwitch { pairs, .. } => {
+                if pairs.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+                    return Err(String::from("lookupswitch keys are not sorted"));
+                }
+                self.pop(&VType::Integer, ctx)?;
+                falls_through = false;
+            }
+            IReturn => {
+                self.return_value(VType::Integer, ctx)?;
+                falls_through = false;
+            }
+            LReturn => {
+                self.return_value(VType::Long, ctx)?;
+                falls_through = false;
+            }
+            FReturn => {
+                self.return_value(VType::Float, ctx)?;
+                falls_through = false;
+            }
+            DReturn => {
+                self.return_value(VType::Double, ctx)?;
+                falls_through = false;
+            }
+            AReturn => {
+                self.return_value(object(), ctx)?;
+                falls_through = false;
+            }
+            Return => {
+                if ctx.return_type.is_some() {
+                    return Err(String::from("return from a method that returns a value"));
+                }
+                if self.this_uninit {
+                    return Err(String::from("constructor returns before calling super() or this()"));
+                }
+                falls_through = false;
+            }
+            GetStatic(index) | PutStatic(index) | GetField(index) | PutField(index) => {
+                let (class_name, _, descriptor) = member_ref(*index, ctx)?;
+                let field_type = parse_field_descriptor(&descriptor)
+                    .ok_or_else(|| format!("illegal field descriptor {}", descriptor))?;
+                let value = VType::from_field_type(&field_type);
+                let owner = VType::Reference(class_name.clone());
+                match instruction {
+                    GetStatic(_) => self.push(value, ctx)?,
+                    PutStatic(_) => {
+                        self.pop(&value, ctx)?;
+                    }
+                    GetField(_) => {
+                        self.pop(&owner, ctx)?;
+                        self.push(value, ctx)?
+                    }
+                    _ => {
+                        self.pop(&value, ctx)?;
+                        // fields of the class itself may be assigned before super() is called
+                        let receiver = self.pop_word()?;
+                        let initializing = receiver == VType::UninitializedThis && class_name == ctx.class_name;
+                        if !initializing && !is_assignable(&receiver, &owner, ctx.hierarchy) {
+                            return Err(format!("putfield on {:?}", receiver));
+                        }
+                    }
+                }
+            }
+            InvokeVirtual(_) | InvokeSpecial(_) | InvokeStatic(_) | InvokeInterface { .. } | InvokeDynamic(_) => {
+                self.invoke(instruction, pc, ctx)?
+            }
+            New(index) => {
+                class_constant(*index, ctx)?;
+                let value = VType::Uninitialized(pc);
+                if self.stack.contains(&value) {
+                    return Err(String::from("uninitialized object from this new is still on the stack"));
+                }
+                for local in self.locals.iter_mut() {
+                    if local == &value {
+                        *local = VType::Top;
+                    }
+                }
+                self.push(value, ctx)?
+            }
+            NewArray(array_type) => {
+                self.convert(VType::Integer, VType::Reference(String::from(array_type.descriptor())), ctx)?
+            }
+            ANewArray(index) => {
+                let class_name = class_constant(*index, ctx)?;
+                let array = if class_name.starts_with('[') {
+                    format!("[{}", class_name)
+                } else {
+                    format!("[L{};", class_name)
+                };
+                self.convert(VType::Integer, VType::Reference(array), ctx)?
+            }
+            ArrayLength => {
+                self.pop_array()?;
+                self.push(VType::Integer, ctx)?
+            }
+            AThrow => {
+                self.pop(&VType::Reference(String::from("java/lang/Throwable")), ctx)?;
+                falls_through = false;
+            }
+            CheckCast(index) => {
+                let class_name = class_constant(*index, ctx)?;
+                self.pop_reference()?;
+                self.push(VType::Reference(class_name), ctx)?
+            }
+            InstanceOf(index) => {
+                class_constant(*index, ctx)?;
+                self.pop_reference()?;
+                self.push(VType::Integer, ctx)?
+            }
+            MonitorEnter | MonitorExit => {
+                self.pop_reference()?;
+            }
+            MultiANewArray { index, dimensions } => {
+                let class_name = class_constant(*index, ctx)?;
+                let dimensions = *dimensions as usize;
+                if dimensions == 0 || class_name.bytes().take_while(|b| *b == b'[').count() < dimensions {
+                    return Err(format!("multianewarray of {} with {} dimensions", class_name, dimensions));
+                }
+                for _ in 0..dimensions {
+                    self.pop(&VType::Integer, ctx)?;
+                }
+                self.push(VType::Reference(class_name), ctx)?
+            }
+        }
+        Ok(Step {
+            next_pc: pc + instruction.length(pc),
+            falls_through,
+            targets,
+            jsr,
+            ret,
+        })
+    }
+
+    fn return_address(&self, index: usize) -> VerifyResult<usize> {
+        match self.local(index, false)? {
+            VType::ReturnAddress(subroutine) => Ok(subroutine),
+            other => Err(format!("ret on local {} holding {:?}", index, other)),
+        }
+    }
+
+    fn store_typed(&mut self, index: usize, expected: VType, ctx: &MethodContext) -> VerifyResult<()> {
+        let value = if expected.is_reference() {
+            // astore also stores the return address of a subroutine, which aload may not load
+            match self.stack.last() {
+                Some(VType::ReturnAddress(_)) => self.pop_word()?,
+                _ => self.pop_reference()?,
+            }
+        } else {
+            self.pop(&expected, ctx)?
+        };
+        self.store(index, value)
+    }
+
+    fn invoke(&mut self, instruction: &Instruction, pc: usize, ctx: &MethodContext) -> VerifyResult<()> {
+        let index = match instruction {
+            InvokeVirtual(index) | InvokeSpecial(index) | InvokeStatic(index) | InvokeDynamic(index) => *index,
+            InvokeInterface { index, .. } => *index,
+            _ => return Err(format!("{} is not an invoke instruction", instruction)),
+        };
+        let (class_name, name, descriptor) = if let InvokeDynamic(_) = instruction {
+            match ctx.constant_pool.get(index as usize) {
+                Some(ConstantInvokeDynamic { name_and_type_index, .. }) => {
+                    let (name, descriptor) = get_name_and_type(ctx.constant_pool, name_and_type_index)
+                        .ok_or_else(|| format!("bad invokedynamic constant #{}", index))?;
+                    (String::new(), name, descriptor)
+                }
+                _ => return Err(format!("constant #{} is not an invokedynamic constant", index)),
+            }
+        } else {
+            member_ref(index, ctx)?
+        };
+        let method_descriptor = parse_method_descriptor(&descriptor)
+            .ok_or_else(|| format!("illegal method descriptor {}", descriptor))?;
+        let is_special = matches!(instruction, InvokeSpecial(_));
+        if name.starts_with('<') && !(is_special && name == "<init>") {
+            return Err(format!("illegal call to {}", name));
+        }
+        for parameter in method_descriptor.parameters.iter().rev() {
+            self.pop(&VType::from_field_type(parameter), ctx)?;
+        }
+        match instruction {
+            InvokeSpecial(_) if name == "<init>" => {
+                let receiver = self.pop_word()?;
+                let initialized = match &receiver {
+                    VType::UninitializedThis => {
+                        let is_super = Some(class_name.as_str()) == ctx.super_name;
+                        if !ctx.is_init || (class_name != ctx.class_name && !is_super) {
+                            return Err(format!("bad <init> call on uninitialized this at {}", pc));
+                        }
+                        VType::Reference(String::from(ctx.class_name))
+                    }
+                    VType::Uninitialized(new_pc) => {
+                        let created = new_class_at(*new_pc, ctx)?;
+                        if created != class_name {
+                            return Err(format!("<init> of {} called on a new {}", class_name, created));
+                        }
+                        VType::Reference(created)
+                    }
+                    other => return Err(format!("<init> called on {:?}", other)),
+                };
+                self.replace_uninitialized(&receiver, initialized);
+            }
+            InvokeSpecial(_) => {
+                self.pop(&VType::Reference(String::from(ctx.class_name)), ctx)?;
+            }
+            InvokeVirtual(_) => {
+                self.pop(&VType::Reference(class_name), ctx)?;
+            }
+            InvokeInterface { .. } => {
+                self.pop(&VType::Reference(String::from("java/lang/Object")), ctx)?;
+            }
+            _ => {}
+        }
+        if let Some(return_type) = &method_descriptor.return_type {
+            self.push(VType::from_field_type(return_type), ctx)?;
+        }
+        Ok(())
+    }
+}
+
+fn check_array_type(array: &VType, element: &[&str]) -> VerifyResult<()> {
+    match array {
+        VType::Null => Ok(()),
+        VType::Reference(name) if element.contains(&name.as_str()) => Ok(()),
+        _ => Err(format!("expected an array of {:?}, found {:?}", element, array)),
+    }
+}
+
+fn ldc_type(index: u16, wide: bool, ctx: &MethodContext) -> VerifyResult<VType> {
+    let value = match ctx.constant_pool.get(index as usize) {
+        Some(ConstantInteger { .. }) if !wide => VType::Integer,
+        Some(ConstantFloat { .. }) if !wide => VType::Float,
+        Some(ConstantString { .. }) if !wide => VType::Reference(String::from("java/lang/String")),
+        Some(ConstantClass { .. }) if !wide && ctx.major_version >= 49 => VType::Reference(String::from("java/lang/Class")),
+        Some(ConstantMethodType { .. }) if !wide => VType::Reference(String::from("java/lang/invoke/MethodType")),
+        Some(ConstantMethodHandle { .. }) if !wide => VType::Reference(String::from("java/lang/invoke/MethodHandle")),
+        Some(ConstantLong { .. }) if wide => VType::Long,
+        Some(ConstantDouble { .. }) if wide => VType::Double,
+        _ => return Err(format!("constant #{} cannot be loaded by ldc", index)),
+    };
+    Ok(value)
+}
+
+fn member_ref(index: u16, ctx: &MethodContext) -> VerifyResult<(String, String, String)> {
+    get_member_ref(ctx.constant_pool, &index).ok_or_else(|| format!("constant #{} is not a member reference", index))
+}
+
+fn class_constant(index: u16, ctx: &MethodContext) -> VerifyResult<String> {
+    get_class_name(ctx.constant_pool, &index).ok_or_else(|| format!("constant #{} is not a class", index))
+}
+
+// the class instantiated by the `new` instruction at `pc`
+pub fn new_class_at(pc: usize, ctx: &MethodContext) -> VerifyResult<String> {
+    match ctx.instruction_at(pc) {
+        Some(New(index)) => class_constant(*index, ctx),
+        _ => Err(format!("uninitialized type refers to offset {} which is not a new instruction", pc)),
+    }
+}
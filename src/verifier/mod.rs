@@ -0,0 +1,279 @@
+pub(crate) mod frame;
+pub(crate) mod type_checker;
+pub(crate) mod type_inference;
+pub(crate) mod types;
+#[cfg(test)]
+mod tests;
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use thiserror::Error as ThisError;
+
+use crate::classfile::attribute::AttributeInfo::{self, CodeAttribute};
+use crate::classfile::attribute::ExceptionTableEntry;
+use crate::classfile::class_reader::{get_class_name, get_utf8, MethodInfo, Reader, ACC_INTERFACE, ACC_STATIC};
+use crate::classfile::constant_pool::ConstantInfo;
+use crate::classfile::descriptor::{parse_method_descriptor, MethodDescriptor};
+use crate::classfile::format_check::check_format;
+use crate::classfile::ClassFile;
+use crate::classpath::{ClassSource, Classpath};
+use crate::error::Error;
+use crate::instructions::{decode_code, Instruction};
+
+use self::type_checker::type_check;
+use self::type_inference::infer_types;
+use self::types::expand_types;
+
+pub use self::frame::Frame;
+pub use self::types::VType;
+
+// which classes get verified, like HotSpot's -Xverify
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyMode {
+    None,
+    // everything except classes from the boot classpath
+    Remote,
+    All,
+}
+
+#[derive(ThisError, Debug, Clone, PartialEq, Eq)]
+#[error("verify error in {class_name}.{method}{}: {message}", .pc.map(|pc| format!(" at pc {}", pc)).unwrap_or_default())]
+pub struct VerifyError {
+    pub class_name: String,
+    pub method: String,
+    pub pc: Option<usize>,
+    pub message: String,
+}
+
+// the verifier needs the class hierarchy to decide if one reference type is assignable to another
+pub trait ClassHierarchy {
+    fn super_class(&self, class_name: &str) -> Option<String>;
+    fn is_interface(&self, class_name: &str) -> bool;
+}
+
+pub struct MethodContext<'a> {
+    pub constant_pool: &'a Vec<ConstantInfo>,
+    pub class_name: &'a str,
+    pub super_name: Option<&'a str>,
+    pub major_version: u16,
+    pub is_static: bool,
+    pub is_init: bool,
+    pub descriptor: MethodDescriptor,
+    pub return_type: Option<VType>,
+    pub max_stack: usize,
+    pub max_locals: usize,
+    pub code: &'a [u8],
+    pub exception_table: &'a [ExceptionTableEntry],
+    pub code_attributes: &'a [AttributeInfo],
+    // the decoded code, sorted by pc
+    pub instructions: Vec<(usize, Instruction)>,
+    pub hierarchy: &'a dyn ClassHierarchy,
+}
+
+impl VerifyMode {
+    pub fn should_verify(&self, source: ClassSource) -> bool {
+        match self {
+            VerifyMode::None => false,
+            VerifyMode::Remote => source != ClassSource::Boot,
+            VerifyMode::All => true,
+        }
+    }
+}
+
+impl FromStr for VerifyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(VerifyMode::None),
+            "remote" => Ok(VerifyMode::Remote),
+            "all" => Ok(VerifyMode::All),
+            _ => Err(format!("unknown verify mode {}, expected none|remote|all", s)),
+        }
+    }
+}
+
+impl<'a> MethodContext<'a> {
+    // the frame on method entry: `this` followed by the parameters
+    pub fn initial_frame(&self) -> Result<Frame, String> {
+        let mut locals = Vec::new();
+        let mut this_uninit = false;
+        if !self.is_static {
+            if self.is_init && self.class_name != "java/lang/Object" {
+                locals.push(VType::UninitializedThis);
+                this_uninit = true;
+            } else {
+                locals.push(VType::Reference(String::from(self.class_name)));
+            }
+        }
+        for parameter in &self.descriptor.parameters {
+            locals.push(VType::from_field_type(parameter));
+        }
+        let locals = expand_types(&locals, self.max_locals)
+            .ok_or_else(|| format!("parameters need more than max_locals {} slots", self.max_locals))?;
+        Ok(Frame { locals, stack: Vec::new(), this_uninit })
+    }
+
+    // the instruction starting at `pc`, None if `pc` is not an instruction boundary
+    pub fn instruction_at(&self, pc: usize) -> Option<&Instruction> {
+        let index = self.instructions.binary_search_by_key(&pc, |(pc, _)| *pc).ok()?;
+        Some(&self.instructions[index].1)
+    }
+}
+
+pub fn verify_class(classfile: &ClassFile, hierarchy: &dyn ClassHierarchy) -> Result<(), VerifyError> {
+    let class_name = get_class_name(&classfile.constant_pool, &classfile.this_class).unwrap_or_default();
+    let super_name = get_class_name(&classfile.constant_pool, &classfile.super_class);
+    for method in &classfile.methods_info {
+        verify_method(classfile, &class_name, super_name.as_deref(), method, hierarchy)?;
+    }
+    Ok(())
+}
+
+pub fn verify_method(
+    classfile: &ClassFile,
+    class_name: &str,
+    super_name: Option<&str>,
+    method: &MethodInfo,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), VerifyError> {
+    with_method_context(classfile, class_name, super_name, method, hierarchy, |ctx| {
+        if ctx.major_version >= 50 {
+            match type_check(ctx) {
+                Ok(()) => return Ok(()),
+                Err(error) if ctx.major_version > 50 => return Err(error),
+                // like HotSpot, version 50 class files fall back to type inference
+                Err(_) => {}
+            }
+        }
+        infer_types(ctx).map(|_| ())
+    })?;
+    Ok(())
+}
+
+// the inferred types of the locals and operand stack at every reachable instruction of method `name` with
+// `descriptor` in `class_file`, empty for methods without code
+pub fn infer_method_types(
+    class_file: &[u8],
+    name: &str,
+    descriptor: &str,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<BTreeMap<usize, Frame>, Error> {
+    let reader = Reader {
+        content: class_file.to_vec(),
+        cursor: Cell::new(0),
+        attribute_lengths: RefCell::new(Vec::new()),
+        logger: Rc::default(),
+    };
+    let classfile = reader.parse_classfile()?;
+    check_format(&classfile).map_err(Error::ClassFormat)?;
+    let class_name = get_class_name(&classfile.constant_pool, &classfile.this_class).unwrap_or_default();
+    let super_name = get_class_name(&classfile.constant_pool, &classfile.super_class);
+    let method = classfile.methods_info.iter()
+        .find(|method| {
+            method.name == name
+                && get_utf8(&classfile.constant_pool, &method.descriptor_index).as_deref() == Some(descriptor)
+        })
+        .ok_or_else(|| Error::MethodNotFound(class_name.clone(), format!("{}{}", name, descriptor)))?;
+    let frames = with_method_context(&classfile, &class_name, super_name.as_deref(), method, hierarchy, infer_types)?;
+    Ok(frames.unwrap_or_default())
+}
+
+// runs `verify` on the code of `method`, None if the method has no code
+fn with_method_context<T>(
+    classfile: &ClassFile,
+    class_name: &str,
+    super_name: Option<&str>,
+    method: &MethodInfo,
+    hierarchy: &dyn ClassHierarchy,
+    verify: impl FnOnce(&MethodContext) -> Result<T, (Option<usize>, String)>,
+) -> Result<Option<T>, VerifyError> {
+    let descriptor_name = get_utf8(&classfile.constant_pool, &method.descriptor_index).unwrap_or_default();
+    let error = |pc: Option<usize>, message: String| VerifyError {
+        class_name: String::from(class_name),
+        method: format!("{}{}", method.name, descriptor_name),
+        pc,
+        message,
+    };
+    let code_attribute = method.attribute_info.iter().find_map(|attribute| match attribute {
+        CodeAttribute { max_stacks, max_locals, code, exception_table, attributes, .. } => {
+            Some((max_stacks, max_locals, code, exception_table, attributes))
+        }
+        _ => None,
+    });
+    let (max_stacks, max_locals, code, exception_table, attributes) = match code_attribute {
+        Some(code_attribute) => code_attribute,
+        None => return Ok(None),
+    };
+    let descriptor = parse_method_descriptor(&descriptor_name)
+        .ok_or_else(|| error(None, format!("illegal method descriptor {}", descriptor_name)))?;
+    let instructions = decode_code(code).map_err(|e| error(None, e.to_string()))?;
+    let ctx = MethodContext {
+        constant_pool: &classfile.constant_pool,
+        class_name,
+        super_name,
+        major_version: classfile.major_version,
+        is_static: method.access_flag & ACC_STATIC != 0,
+        is_init: method.name == "<init>",
+        return_type: descriptor.return_type.as_ref().map(VType::from_field_type),
+        descriptor,
+        max_stack: *max_stacks as usize,
+        max_locals: *max_locals as usize,
+        code,
+        exception_table,
+        code_attributes: attributes,
+        instructions,
+        hierarchy,
+    };
+    verify(&ctx).map(Some).map_err(|(pc, message)| error(pc, message))
+}
+
+// answers hierarchy questions by reading class files from the classpath
+pub struct ClasspathHierarchy<'a> {
+    classpath: &'a Classpath,
+    // class name -> (super class, is interface), None if the class cannot be found
+    cache: RefCell<HashMap<String, Option<ClassInfo>>>,
+}
+
+type ClassInfo = (Option<String>, bool);
+
+impl<'a> ClasspathHierarchy<'a> {
+    pub fn new(classpath: &'a Classpath) -> ClasspathHierarchy<'a> {
+        ClasspathHierarchy {
+            classpath,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn lookup(&self, class_name: &str) -> Option<ClassInfo> {
+        if let Some(entry) = self.cache.borrow().get(class_name) {
+            return entry.clone();
+        }
+        let entry = self.classpath.load_class(String::from(class_name)).ok().and_then(|(content, _)| {
+            let reader = Reader {
+                content,
+                cursor: Cell::new(0),
+                attribute_lengths: RefCell::new(Vec::new()),
+                logger: self.classpath.logger().clone(),
+            };
+            let classfile = reader.parse_classfile().ok()?;
+            let super_name = get_class_name(&classfile.constant_pool, &classfile.super_class);
+            Some((super_name, classfile.access_flags & ACC_INTERFACE != 0))
+        });
+        self.cache.borrow_mut().insert(String::from(class_name), entry.clone());
+        entry
+    }
+}
+
+impl<'a> ClassHierarchy for ClasspathHierarchy<'a> {
+    fn super_class(&self, class_name: &str) -> Option<String> {
+        self.lookup(class_name)?.0
+    }
+
+    fn is_interface(&self, class_name: &str) -> bool {
+        matches!(self.lookup(class_name), Some((_, true)))
+    }
+}
@@ -0,0 +1,304 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::classfile::attribute::AttributeInfo::{CodeAttribute, StackMapTableAttribute};
+use crate::classfile::attribute::{StackMapFrame, VerificationTypeInfo};
+use crate::classfile::class_reader::{Reader, ACC_PUBLIC, ACC_STATIC};
+use crate::classfile::constant_pool::ConstantInfo;
+use crate::classfile::writer::{ClassDef, Handler, MethodDef, Op};
+use crate::classfile::ClassFile;
+use crate::classpath::Classpath;
+use crate::error::Error;
+
+use super::types::{is_reference_assignable, merge_types};
+use super::{infer_method_types, verify_class, ClassHierarchy, ClasspathHierarchy, Frame, VType, VerifyError};
+
+const RETURN: &[Op] = &[Op::Return];
+// puts a method reference to take(Object) in the constant pool, for the code under test to call
+const CALL_TAKE: &[Op] = &[Op::AConstNull, Op::InvokeStatic("Sample", "take", "(Ljava/lang/Object;)V"), Op::Return];
+
+// a version 52 class whose method run has `descriptor` and the given code and StackMapTable
+fn class_with_code(
+    descriptor: &str,
+    max_stack: u16,
+    max_locals: u16,
+    code: Vec<u8>,
+    entries: Vec<StackMapFrame>,
+) -> ClassFile {
+    let flags = ACC_PUBLIC | ACC_STATIC;
+    let methods = [
+        MethodDef::code("run", descriptor, flags, 0, 0, RETURN),
+        MethodDef::code("call", "()V", flags, 1, 0, CALL_TAKE),
+        MethodDef::code("take", "(Ljava/lang/Object;)V", flags, 0, 1, RETURN),
+    ];
+    let content = ClassDef::new("Sample", &methods).write();
+    let reader = Reader {
+        content,
+        cursor: Cell::new(0),
+        attribute_lengths: RefCell::new(Vec::new()),
+        logger: Rc::default(),
+    };
+    let mut classfile = reader.parse_classfile().unwrap();
+    classfile.major_version = 52;
+    let run = classfile.methods_info.iter_mut().find(|method| method.name == "run").unwrap();
+    if let Some(CodeAttribute { max_stacks, max_locals: locals, code: bytes, attributes, .. }) =
+        run.attribute_info.first_mut()
+    {
+        *max_stacks = max_stack;
+        *locals = max_locals;
+        *bytes = code;
+        *attributes = vec![StackMapTableAttribute { entries }];
+    }
+    classfile
+}
+
+fn verify_error(classfile: &ClassFile) -> VerifyError {
+    let classpath = Classpath::builtin_classpath(PathBuf::from("."), Rc::default());
+    verify_class(classfile, &ClasspathHierarchy::new(&classpath)).expect_err("the class passed the verifier")
+}
+
+fn assert_verify_error(classfile: &ClassFile, pc: Option<usize>, message: &str) {
+    let error = verify_error(classfile);
+    assert!(error.method.starts_with("run("), "{}", error);
+    assert_eq!(error.pc, pc, "{}", error);
+    assert!(error.message.starts_with(message), "{}", error);
+}
+
+// the java/lang/Object class constant, which the super class index points at
+fn object(classfile: &ClassFile) -> VerificationTypeInfo {
+    VerificationTypeInfo::Object { cpool_index: classfile.super_class }
+}
+
+fn method_ref(classfile: &ClassFile) -> [u8; 2] {
+    let index = classfile.constant_pool.iter()
+        .position(|constant| matches!(constant, ConstantInfo::ConstantMethodReference { .. }))
+        .unwrap() as u16;
+    index.to_be_bytes()
+}
+
+// iconst_0; ifeq 5; return; 5: return
+const BRANCH: [u8; 6] = [0x03, 0x99, 0x00, 0x04, 0xb1, 0xb1];
+
+#[test]
+fn a_branch_to_a_declared_frame_passes_the_type_checker() {
+    let classfile = class_with_code("()V", 1, 0, BRANCH.to_vec(), vec![StackMapFrame::SameFrame { offset_delta: 5 }]);
+    let classpath = Classpath::builtin_classpath(PathBuf::from("."), Rc::default());
+    verify_class(&classfile, &ClasspathHierarchy::new(&classpath)).unwrap();
+}
+
+#[test]
+fn a_branch_target_without_a_stack_map_frame_is_a_verify_error() {
+    let classfile = class_with_code("()V", 1, 0, BRANCH.to_vec(), Vec::new());
+    assert_verify_error(&classfile, Some(1), "no stack map frame at branch target 5");
+
+    // the frame must be at the target, not merely somewhere after the branch
+    let classfile = class_with_code("()V", 1, 0, BRANCH.to_vec(), vec![StackMapFrame::SameFrame { offset_delta: 4 }]);
+    assert_verify_error(&classfile, Some(1), "no stack map frame at branch target 5");
+
+    // code after an unconditional return needs a frame too: iconst_0; pop; return; return
+    let classfile = class_with_code("()V", 1, 0, vec![0x03, 0x57, 0xb1, 0xb1], Vec::new());
+    assert_verify_error(&classfile, Some(3), "expected a stack map frame after an unconditional branch");
+}
+
+#[test]
+fn a_stack_deeper_than_max_stack_is_a_verify_error() {
+    // iconst_0; iconst_0; pop; pop; return
+    let classfile = class_with_code("()V", 1, 0, vec![0x03, 0x03, 0x57, 0x57, 0xb1], Vec::new());
+    assert_verify_error(&classfile, Some(1), "operand stack overflow, max_stack is 1");
+
+    // lconst_0 takes two slots: lconst_0; pop2; return
+    let classfile = class_with_code("()V", 1, 0, vec![0x09, 0x58, 0xb1], Vec::new());
+    assert_verify_error(&classfile, Some(0), "operand stack overflow, max_stack is 1");
+
+    // a declared frame may not be deeper either
+    let entries = vec![StackMapFrame::SameLocals1StackItemFrame { offset_delta: 5, stack: VerificationTypeInfo::Long }];
+    let classfile = class_with_code("()V", 1, 0, BRANCH.to_vec(), entries);
+    assert_verify_error(&classfile, None, "stack map frame at 5 has a deeper stack than max_stack");
+}
+
+#[test]
+fn a_local_variable_index_out_of_range_is_a_verify_error() {
+    // iload_3; pop; return
+    let classfile = class_with_code("()V", 1, 1, vec![0x1d, 0x57, 0xb1], Vec::new());
+    assert_verify_error(&classfile, Some(0), "local variable index 3 out of range, max_locals is 1");
+
+    // iconst_0; istore 1; return
+    let classfile = class_with_code("()V", 1, 1, vec![0x03, 0x36, 0x01, 0xb1], Vec::new());
+    assert_verify_error(&classfile, Some(1), "local variable index 1 out of range, max_locals is 1");
+
+    // the second slot of a long is out of range: lconst_0; lstore_0; return
+    let classfile = class_with_code("()V", 2, 1, vec![0x09, 0x3f, 0xb1], Vec::new());
+    assert_verify_error(&classfile, Some(1), "local variable index 1 out of range, max_locals is 1");
+
+    // a declared frame with more locals than max_locals
+    let entries = vec![StackMapFrame::AppendFrame {
+        offset_delta: 5,
+        locals: vec![VerificationTypeInfo::Integer, VerificationTypeInfo::Integer],
+    }];
+    let classfile = class_with_code("()V", 1, 1, BRANCH.to_vec(), entries);
+    assert_verify_error(&classfile, None, "stack map frame at 5 has more locals than max_locals");
+}
+
+const INT_FOR_OBJECT: &str = "expected Reference(\"java/lang/Object\") on the operand stack, found Integer";
+
+#[test]
+fn an_int_where_a_reference_is_expected_is_a_verify_error() {
+    // iconst_0; invokestatic take(Object); return
+    let [high, low] = method_ref(&class_with_code("()V", 1, 0, Vec::new(), Vec::new()));
+    let classfile = class_with_code("()V", 1, 0, vec![0x03, 0xb8, high, low, 0xb1], Vec::new());
+    assert_verify_error(&classfile, Some(1), INT_FOR_OBJECT);
+
+    // iconst_0; areturn
+    let classfile = class_with_code("()Ljava/lang/Object;", 1, 0, vec![0x03, 0xb0], Vec::new());
+    assert_verify_error(&classfile, Some(1), INT_FOR_OBJECT);
+
+    // an int in a local the frame at the branch target declares an Object:
+    // iconst_0; istore_0; iconst_0; ifeq 7; return; 7: return
+    let code = vec![0x03, 0x3b, 0x03, 0x99, 0x00, 0x04, 0xb1, 0xb1];
+    let locals = vec![object(&class_with_code("()V", 1, 1, Vec::new(), Vec::new()))];
+    let entries = vec![StackMapFrame::FullFrame { offset_delta: 7, locals, stack: Vec::new() }];
+    let classfile = class_with_code("()V", 1, 1, code, entries);
+    assert_verify_error(&classfile, Some(3), "branch to 7:");
+}
+
+// a version 49 class file whose static method run has `descriptor` and `code`, for the type inference verifier
+fn class_file_with_code(
+    descriptor: &str,
+    max_stack: u16,
+    max_locals: u16,
+    code: &[u8],
+    handlers: &[Handler],
+) -> Vec<u8> {
+    // one byte per return, so the ops and handler indexes are pcs; the returns are replaced by `code` below
+    let ops = vec![Op::Return; code.len()];
+    let methods = [MethodDef {
+        handlers,
+        ..MethodDef::code("run", descriptor, ACC_PUBLIC | ACC_STATIC, max_stack, max_locals, &ops)
+    }];
+    let mut content = ClassDef::new("Sample", &methods).write();
+    let mut placeholder = (code.len() as u32).to_be_bytes().to_vec();
+    placeholder.extend(&ops.iter().map(|_| 0xb1).collect::<Vec<u8>>());
+    let start = content.windows(placeholder.len()).position(|window| window == placeholder).unwrap() + 4;
+    content[start..start + code.len()].copy_from_slice(code);
+    content
+}
+
+fn infer(content: &[u8], descriptor: &str) -> Result<BTreeMap<usize, Frame>, Error> {
+    let classpath = Classpath::builtin_classpath(PathBuf::from("."), Rc::default());
+    infer_method_types(content, "run", descriptor, &ClasspathHierarchy::new(&classpath))
+}
+
+fn reference(name: &str) -> VType {
+    VType::Reference(String::from(name))
+}
+
+#[test]
+fn types_are_inferred_through_a_finally_subroutine() {
+    // int x = 1; try { x = 2; } finally { x = 3; }, compiled with jsr/ret:
+    //  0: iconst_1; istore_0; iconst_2; istore_0; jsr 14; return
+    //  8: astore_1; jsr 14; aload_1; athrow
+    // 14: astore_2; iconst_3; istore_0; ret 2
+    let code = [
+        0x04, 0x3b, 0x05, 0x3b, 0xa8, 0x00, 0x0a, 0xb1,
+        0x4c, 0xa8, 0x00, 0x05, 0x2b, 0xbf,
+        0x4d, 0x06, 0x3b, 0xa9, 0x02,
+    ];
+    let handlers = [Handler { start: 2, end: 4, target: 8, catch_type: None }];
+    let frames = infer(&class_file_with_code("()V", 1, 3, &code, &handlers), "()V").unwrap();
+    let pcs: Vec<usize> = frames.keys().copied().collect();
+    assert_eq!(pcs, vec![0, 1, 2, 3, 4, 7, 8, 9, 12, 13, 14, 15, 16, 17]);
+
+    let throwable = reference("java/lang/Throwable");
+    assert_eq!(frames[&8].locals, vec![VType::Integer, VType::Top, VType::Top]);
+    assert_eq!(frames[&8].stack, vec![throwable.clone()]);
+    // both calls enter the subroutine, which cannot rely on the exception in local 1
+    assert_eq!(frames[&14].locals, vec![VType::Integer, VType::Top, VType::Top]);
+    assert_eq!(frames[&14].stack, vec![VType::ReturnAddress(14)]);
+    // after each call the locals the subroutine left alone have their types from before the jsr again
+    assert_eq!(frames[&7].locals, vec![VType::Integer, VType::Top, VType::ReturnAddress(14)]);
+    assert_eq!(frames[&12].locals, vec![VType::Integer, throwable, VType::ReturnAddress(14)]);
+    assert!(frames[&12].stack.is_empty());
+}
+
+#[test]
+fn types_merged_at_a_loop_head_are_widened() {
+    // static void run(String s, Integer i) { Object o = s; while (o != null) o = i; }
+    //  0: aload_0; astore_2; 2: aload_2; ifnull 11; aload_1; astore_2; goto 2; 11: return
+    let code = [0x2a, 0x4d, 0x2c, 0xc6, 0x00, 0x08, 0x2b, 0x4d, 0xa7, 0xff, 0xfa, 0xb1];
+    let descriptor = "(Ljava/lang/String;Ljava/lang/Integer;)V";
+    let frames = infer(&class_file_with_code(descriptor, 1, 3, &code, &[]), descriptor).unwrap();
+    let string = reference("java/lang/String");
+    let integer = reference("java/lang/Integer");
+    // the String from before the loop and the Integer from its body meet at their common super class
+    for pc in [2, 6, 11] {
+        assert_eq!(frames[&pc].locals, vec![string.clone(), integer.clone(), reference("java/lang/Object")], "{}", pc);
+    }
+    assert_eq!(frames[&8].locals[2], integer);
+
+    // an int and a float in the same local merge to an unusable Top:
+    //  0: iconst_0; istore_1; 2: iload_0; ifeq 11; fconst_0; fstore_1; goto 2; 11: return
+    let code = [0x03, 0x3c, 0x1a, 0x99, 0x00, 0x08, 0x0b, 0x44, 0xa7, 0xff, 0xfa, 0xb1];
+    let frames = infer(&class_file_with_code("(I)V", 1, 2, &code, &[]), "(I)V").unwrap();
+    assert_eq!(frames[&2].locals, vec![VType::Integer, VType::Top]);
+    assert_eq!(frames[&11].locals, vec![VType::Integer, VType::Top]);
+}
+
+#[test]
+fn an_inconsistent_stack_at_a_join_is_a_verify_error() {
+    //  0: iload_0; ifeq 8; iconst_1; goto 9; 8: nop; 9: return
+    let code = [0x1a, 0x99, 0x00, 0x07, 0x04, 0xa7, 0x00, 0x04, 0x00, 0xb1];
+    match infer(&class_file_with_code("(I)V", 1, 1, &code, &[]), "(I)V") {
+        Err(Error::Verify(error)) => {
+            assert_eq!(error.pc, Some(8));
+            assert_eq!(error.message, "operand stack depth at 9 differs between paths, 1 and 0");
+        }
+        other => panic!("expected a verify error, got {:?}", other.map(|frames| frames.len())),
+    }
+
+    // the same depth, but an int on one path and a float on the other: 8: fconst_0
+    let mut code = code;
+    code[8] = 0x0b;
+    match infer(&class_file_with_code("(I)V", 1, 1, &code, &[]), "(I)V") {
+        Err(Error::Verify(error)) => {
+            assert_eq!(error.pc, Some(8));
+            assert_eq!(error.message, "incompatible types Integer and Float on the operand stack at 9");
+        }
+        other => panic!("expected a verify error, got {:?}", other.map(|frames| frames.len())),
+    }
+
+    // the frames of consistent paths are inferred, and missing methods are reported
+    code[8] = 0x04;
+    let content = class_file_with_code("(I)V", 1, 1, &code, &[]);
+    assert_eq!(infer(&content, "(I)V").unwrap()[&9].stack, vec![VType::Integer]);
+    let missing = infer(&content, "()V");
+    assert!(matches!(missing, Err(Error::MethodNotFound(class, method)) if class == "Sample" && method == "run()V"));
+}
+
+// a broken hierarchy where A and B extend each other and C extends A
+struct Circular;
+
+impl ClassHierarchy for Circular {
+    fn super_class(&self, class_name: &str) -> Option<String> {
+        match class_name {
+            "A" => Some(String::from("B")),
+            "B" => Some(String::from("A")),
+            "C" => Some(String::from("A")),
+            _ => None,
+        }
+    }
+
+    fn is_interface(&self, _: &str) -> bool {
+        false
+    }
+}
+
+#[test]
+fn a_circular_hierarchy_ends_the_walk_up_the_super_classes() {
+    assert!(is_reference_assignable("C", "B", &Circular));
+    assert!(!is_reference_assignable("A", "C", &Circular));
+    assert!(!is_reference_assignable("A", "D", &Circular));
+    assert_eq!(merge_types(&reference("C"), &reference("B"), &Circular), reference("B"));
+    assert_eq!(merge_types(&reference("A"), &reference("D"), &Circular), reference("java/lang/Object"));
+}
@@ -0,0 +1,177 @@
+// verification by type checking (JVMS §4.10.1), for class files version 50 and above
+use std::collections::BTreeMap;
+
+use crate::classfile::attribute::AttributeInfo::StackMapTableAttribute;
+use crate::classfile::attribute::{StackMapFrame, VerificationTypeInfo};
+use crate::classfile::class_reader::get_class_name;
+
+use super::frame::{new_class_at, Frame};
+use super::types::{expand_types, VType};
+use super::MethodContext;
+
+pub fn type_check(ctx: &MethodContext) -> Result<(), (Option<usize>, String)> {
+    let initial = ctx.initial_frame().map_err(|message| (None, message))?;
+    let stack_map = stack_map_frames(ctx, &initial).map_err(|message| (None, message))?;
+    let is_boundary = |pc: usize| ctx.instruction_at(pc).is_some();
+    for pc in stack_map.keys() {
+        if !is_boundary(*pc) {
+            return Err((Some(*pc), String::from("stack map frame is not at an instruction boundary")));
+        }
+    }
+
+    let mut current = Some(initial);
+    for (pc, instruction) in &ctx.instructions {
+        let pc = *pc;
+        let fail = |message: String| (Some(pc), message);
+        let mut frame = match (stack_map.get(&pc), current.take()) {
+            (Some(declared), Some(incoming)) => {
+                if !incoming.is_assignable_to(declared, ctx) {
+                    return Err(fail(format!("incompatible stack map frame, {:?} is not assignable to {:?}", incoming, declared)));
+                }
+                declared.clone()
+            }
+            (Some(declared), None) => declared.clone(),
+            (None, Some(incoming)) => incoming,
+            (None, None) => return Err(fail(String::from("expected a stack map frame after an unconditional branch"))),
+        };
+
+        for entry in ctx.exception_table {
+            if (entry.start_pc as usize..entry.end_pc as usize).contains(&pc) {
+                let handler = entry.handle_pc as usize;
+                let catch_type = if entry.catch_type == 0 {
+                    String::from("java/lang/Throwable")
+                } else {
+                    get_class_name(ctx.constant_pool, &entry.catch_type)
+                        .ok_or_else(|| fail(format!("bad catch type #{}", entry.catch_type)))?
+                };
+                let exception_frame = Frame {
+                    locals: frame.locals.clone(),
+                    stack: vec![VType::Reference(catch_type)],
+                    this_uninit: frame.this_uninit,
+                };
+                match stack_map.get(&handler) {
+                    Some(declared) if exception_frame.is_assignable_to(declared, ctx) => {}
+                    Some(_) => return Err(fail(format!("frame is not assignable to exception handler at {}", handler))),
+                    None => return Err(fail(format!("no stack map frame at exception handler {}", handler))),
+                }
+            }
+        }
+
+        let step = frame.execute(pc, instruction, ctx).map_err(fail)?;
+        if step.jsr.is_some() || step.ret.is_some() {
+            return Err(fail(String::from("jsr/ret are not allowed in class files with a StackMapTable")));
+        }
+        for target in &step.targets {
+            if !is_boundary(*target) {
+                return Err(fail(format!("branch target {} is not an instruction boundary", target)));
+            }
+            match stack_map.get(target) {
+                Some(declared) if frame.is_assignable_to(declared, ctx) => {}
+                Some(declared) => return Err(fail(format!("branch to {}: {:?} is not assignable to {:?}", target, frame, declared))),
+                None => return Err(fail(format!("no stack map frame at branch target {}", target))),
+            }
+        }
+        if step.falls_through {
+            if step.next_pc >= ctx.code.len() {
+                return Err(fail(String::from("execution falls off the end of the code")));
+            }
+            current = Some(frame);
+        }
+    }
+    Ok(())
+}
+
+// decodes the StackMapTable into full frames keyed by offset
+fn stack_map_frames(ctx: &MethodContext, initial: &Frame) -> Result<BTreeMap<usize, Frame>, String> {
+    let mut frames = BTreeMap::new();
+    let entries = ctx.code_attributes.iter().find_map(|attribute| match attribute {
+        StackMapTableAttribute { entries } => Some(entries),
+        _ => None,
+    });
+    let entries = match entries {
+        Some(entries) => entries,
+        None => return Ok(frames),
+    };
+
+    // locals as listed in the table, where long and double take a single entry
+    let mut locals = compress_locals(&initial.locals);
+    let mut offset: Option<usize> = None;
+    for entry in entries {
+        let (offset_delta, stack) = match entry {
+            StackMapFrame::SameFrame { offset_delta } => (*offset_delta, Vec::new()),
+            StackMapFrame::SameLocals1StackItemFrame { offset_delta, stack } => (*offset_delta, vec![verification_type(stack, ctx)?]),
+            StackMapFrame::ChopFrame { offset_delta, chopped } => {
+                if *chopped as usize > locals.len() {
+                    return Err(String::from("chop frame removes more locals than there are"));
+                }
+                locals.truncate(locals.len() - *chopped as usize);
+                (*offset_delta, Vec::new())
+            }
+            StackMapFrame::AppendFrame { offset_delta, locals: appended } => {
+                for local in appended {
+                    locals.push(verification_type(local, ctx)?);
+                }
+                (*offset_delta, Vec::new())
+            }
+            StackMapFrame::FullFrame { offset_delta, locals: full, stack } => {
+                locals = full.iter().map(|local| verification_type(local, ctx)).collect::<Result<_, _>>()?;
+                let stack = stack.iter().map(|item| verification_type(item, ctx)).collect::<Result<_, _>>()?;
+                (*offset_delta, stack)
+            }
+        };
+        let pc = match offset {
+            None => offset_delta as usize,
+            Some(previous) => previous + offset_delta as usize + 1,
+        };
+        offset = Some(pc);
+        let expanded_locals = expand_types(&locals, ctx.max_locals)
+            .ok_or_else(|| format!("stack map frame at {} has more locals than max_locals", pc))?;
+        let mut expanded_stack = Vec::new();
+        for item in stack {
+            let category2 = item.is_category2();
+            expanded_stack.push(item);
+            if category2 {
+                expanded_stack.push(VType::Top);
+            }
+        }
+        if expanded_stack.len() > ctx.max_stack {
+            return Err(format!("stack map frame at {} has a deeper stack than max_stack", pc));
+        }
+        let this_uninit = expanded_locals.contains(&VType::UninitializedThis);
+        frames.insert(pc, Frame { locals: expanded_locals, stack: expanded_stack, this_uninit });
+    }
+    Ok(frames)
+}
+
+fn compress_locals(locals: &[VType]) -> Vec<VType> {
+    let mut result = Vec::new();
+    let mut index = 0;
+    while index < locals.len() {
+        result.push(locals[index].clone());
+        index += if locals[index].is_category2() { 2 } else { 1 };
+    }
+    while result.last() == Some(&VType::Top) {
+        result.pop();
+    }
+    result
+}
+
+fn verification_type(info: &VerificationTypeInfo, ctx: &MethodContext) -> Result<VType, String> {
+    let value = match info {
+        VerificationTypeInfo::Top => VType::Top,
+        VerificationTypeInfo::Integer => VType::Integer,
+        VerificationTypeInfo::Float => VType::Float,
+        VerificationTypeInfo::Long => VType::Long,
+        VerificationTypeInfo::Double => VType::Double,
+        VerificationTypeInfo::Null => VType::Null,
+        VerificationTypeInfo::UninitializedThis => VType::UninitializedThis,
+        VerificationTypeInfo::Object { cpool_index } => VType::Reference(
+            get_class_name(ctx.constant_pool, cpool_index).ok_or_else(|| format!("bad class constant #{} in stack map", cpool_index))?,
+        ),
+        VerificationTypeInfo::Uninitialized { offset } => {
+            new_class_at(*offset as usize, ctx)?;
+            VType::Uninitialized(*offset as usize)
+        }
+    };
+    Ok(value)
+}
@@ -0,0 +1,177 @@
+// verification by type inference (JVMS §4.10.2), for class files older than version 50
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::classfile::class_reader::get_class_name;
+
+use super::frame::Frame;
+use super::types::{merge_types, VType};
+use super::MethodContext;
+
+// the inferred frame at an instruction, plus which locals were written since entering the current subroutine
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct State {
+    frame: Frame,
+    modified: Vec<bool>,
+}
+
+// computes the frame at every reachable instruction by iterating over the control flow graph until nothing changes
+pub fn infer_types(ctx: &MethodContext) -> Result<BTreeMap<usize, Frame>, (Option<usize>, String)> {
+    let initial = ctx.initial_frame().map_err(|message| (None, message))?;
+    let is_boundary = |pc: usize| ctx.instruction_at(pc).is_some();
+
+    let mut handlers = Vec::new();
+    for entry in ctx.exception_table {
+        let handler = entry.handle_pc as usize;
+        if !is_boundary(handler) {
+            return Err((None, format!("exception handler {} is not an instruction boundary", handler)));
+        }
+        let catch_type = if entry.catch_type == 0 {
+            String::from("java/lang/Throwable")
+        } else {
+            get_class_name(ctx.constant_pool, &entry.catch_type)
+                .ok_or_else(|| (None, format!("bad catch type #{}", entry.catch_type)))?
+        };
+        handlers.push((entry.start_pc as usize..entry.end_pc as usize, handler, VType::Reference(catch_type)));
+    }
+
+    let mut states = BTreeMap::new();
+    let mut pending = BTreeSet::new();
+    states.insert(0, State { frame: initial, modified: vec![false; ctx.max_locals] });
+    pending.insert(0);
+    // subroutine -> (jsr offset, return offset) of every call seen so far
+    let mut callers: HashMap<usize, BTreeSet<(usize, usize)>> = HashMap::new();
+    // subroutine -> offsets of the ret instructions returning from it
+    let mut returns: HashMap<usize, BTreeSet<usize>> = HashMap::new();
+
+    while let Some(pc) = pending.pop_first() {
+        let fail = |message: String| (Some(pc), message);
+        let incoming = states[&pc].clone();
+        let mut frame = incoming.frame.clone();
+        let instruction = ctx.instruction_at(pc).ok_or_else(|| fail(String::from("not an instruction boundary")))?;
+        let step = frame.execute(pc, instruction, ctx).map_err(fail)?;
+        let mut modified = incoming.modified.clone();
+        for (index, (before, after)) in incoming.frame.locals.iter().zip(&frame.locals).enumerate() {
+            if before != after {
+                modified[index] = true;
+            }
+        }
+        let outgoing = State { frame, modified };
+
+        // a handler may be entered before or after the instruction has updated the locals
+        for (range, handler, catch_type) in &handlers {
+            if !range.contains(&pc) {
+                continue;
+            }
+            for state in [&incoming, &outgoing] {
+                let exception_state = State {
+                    frame: Frame {
+                        locals: state.frame.locals.clone(),
+                        stack: vec![catch_type.clone()],
+                        this_uninit: state.frame.this_uninit,
+                    },
+                    modified: outgoing.modified.clone(),
+                };
+                merge_into(*handler, exception_state, &mut states, &mut pending, ctx).map_err(fail)?;
+            }
+        }
+
+        for target in &step.targets {
+            if !is_boundary(*target) {
+                return Err(fail(format!("branch target {} is not an instruction boundary", target)));
+            }
+            merge_into(*target, outgoing.clone(), &mut states, &mut pending, ctx).map_err(fail)?;
+        }
+        if step.falls_through {
+            if step.next_pc >= ctx.code.len() {
+                return Err(fail(String::from("execution falls off the end of the code")));
+            }
+            merge_into(step.next_pc, outgoing.clone(), &mut states, &mut pending, ctx).map_err(fail)?;
+        }
+
+        if let Some(subroutine) = step.jsr {
+            if !is_boundary(subroutine) {
+                return Err(fail(format!("jsr target {} is not an instruction boundary", subroutine)));
+            }
+            let entry = State { frame: outgoing.frame.clone(), modified: vec![false; ctx.max_locals] };
+            merge_into(subroutine, entry, &mut states, &mut pending, ctx).map_err(fail)?;
+            callers.entry(subroutine).or_default().insert((pc, step.next_pc));
+            // the frame before this jsr may have changed, so the rets must propagate again
+            if let Some(rets) = returns.get(&subroutine) {
+                pending.extend(rets.iter().copied());
+            }
+        }
+        if let Some(subroutine) = step.ret {
+            returns.entry(subroutine).or_default().insert(pc);
+            for (call, return_pc) in callers.get(&subroutine).cloned().unwrap_or_default() {
+                if return_pc >= ctx.code.len() {
+                    return Err(fail(String::from("subroutine returns past the end of the code")));
+                }
+                // locals the subroutine did not touch keep the types they had at the jsr
+                let before = &states[&call];
+                let mut locals = Vec::with_capacity(ctx.max_locals);
+                let mut modified = Vec::with_capacity(ctx.max_locals);
+                for index in 0..ctx.max_locals {
+                    let written = outgoing.modified[index];
+                    locals.push(if written { outgoing.frame.locals[index].clone() } else { before.frame.locals[index].clone() });
+                    modified.push(written || before.modified[index]);
+                }
+                let returned = State {
+                    frame: Frame { locals, stack: outgoing.frame.stack.clone(), this_uninit: outgoing.frame.this_uninit },
+                    modified,
+                };
+                merge_into(return_pc, returned, &mut states, &mut pending, ctx).map_err(fail)?;
+            }
+        }
+    }
+    Ok(states.into_iter().map(|(pc, state)| (pc, state.frame)).collect())
+}
+
+// merges `incoming` into the state recorded at `pc`, queueing `pc` again if that state changed
+fn merge_into(
+    pc: usize,
+    incoming: State,
+    states: &mut BTreeMap<usize, State>,
+    pending: &mut BTreeSet<usize>,
+    ctx: &MethodContext,
+) -> Result<(), String> {
+    let existing = match states.get_mut(&pc) {
+        Some(existing) => existing,
+        None => {
+            states.insert(pc, incoming);
+            pending.insert(pc);
+            return Ok(());
+        }
+    };
+    if existing.frame.stack.len() != incoming.frame.stack.len() {
+        return Err(format!(
+            "operand stack depth at {} differs between paths, {} and {}",
+            pc,
+            existing.frame.stack.len(),
+            incoming.frame.stack.len()
+        ));
+    }
+    let mut stack = Vec::with_capacity(incoming.frame.stack.len());
+    for (a, b) in existing.frame.stack.iter().zip(&incoming.frame.stack) {
+        let merged = merge_types(a, b, ctx.hierarchy);
+        if merged == VType::Top && a != b {
+            return Err(format!("incompatible types {:?} and {:?} on the operand stack at {}", a, b, pc));
+        }
+        stack.push(merged);
+    }
+    let locals = existing
+        .frame
+        .locals
+        .iter()
+        .zip(&incoming.frame.locals)
+        .map(|(a, b)| merge_types(a, b, ctx.hierarchy))
+        .collect();
+    let merged = State {
+        frame: Frame { locals, stack, this_uninit: existing.frame.this_uninit || incoming.frame.this_uninit },
+        modified: existing.modified.iter().zip(&incoming.modified).map(|(a, b)| *a || *b).collect(),
+    };
+    if &merged != existing {
+        *existing = merged;
+        pending.insert(pc);
+    }
+    Ok(())
+}
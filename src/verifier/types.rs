@@ -0,0 +1,169 @@
+use crate::classfile::descriptor::{parse_field_descriptor, FieldType};
+
+use super::ClassHierarchy;
+
+// verification types (JVMS §4.10.1.2); long and double take two entries, the second one is Top
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VType {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    // offset of the `new` instruction that created the object
+    Uninitialized(usize),
+    // class name in internal form, or an array descriptor like [I
+    Reference(String),
+    // pushed by jsr, the offset of the subroutine it jumps to; only seen by the type inference verifier
+    ReturnAddress(usize),
+}
+
+impl VType {
+    pub(crate) fn from_field_type(field_type: &FieldType) -> VType {
+        match field_type {
+            FieldType::Byte | FieldType::Char | FieldType::Short | FieldType::Boolean | FieldType::Int => VType::Integer,
+            FieldType::Float => VType::Float,
+            FieldType::Long => VType::Long,
+            FieldType::Double => VType::Double,
+            FieldType::Object(name) => VType::Reference(name.clone()),
+            FieldType::Array(_) => VType::Reference(field_type.descriptor()),
+        }
+    }
+
+    pub fn is_category2(&self) -> bool {
+        matches!(self, VType::Long | VType::Double)
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self, VType::Null | VType::UninitializedThis | VType::Uninitialized(_) | VType::Reference(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, VType::Reference(name) if name.starts_with('['))
+    }
+
+    // the element type of an array reference, None if this is not an array
+    pub fn component(&self) -> Option<VType> {
+        match self {
+            VType::Null => Some(VType::Null),
+            VType::Reference(name) if name.starts_with('[') => {
+                parse_field_descriptor(&name[1..]).map(|component| VType::from_field_type(&component))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn is_assignable(from: &VType, to: &VType, hierarchy: &dyn ClassHierarchy) -> bool {
+    match (from, to) {
+        (_, VType::Top) => true,
+        (from, to) if from == to => true,
+        (VType::Null, VType::Reference(_)) => true,
+        (VType::Reference(from), VType::Reference(to)) => is_reference_assignable(from, to, hierarchy),
+        _ => false,
+    }
+}
+
+pub fn is_reference_assignable(from: &str, to: &str, hierarchy: &dyn ClassHierarchy) -> bool {
+    if from == to || to == "java/lang/Object" {
+        return true;
+    }
+    if let Some(from_component) = from.strip_prefix('[') {
+        if let Some(to_component) = to.strip_prefix('[') {
+            return match (parse_field_descriptor(from_component), parse_field_descriptor(to_component)) {
+                (Some(FieldType::Object(from)), Some(FieldType::Object(to))) => is_reference_assignable(&from, &to, hierarchy),
+                (Some(from @ FieldType::Array(_)), Some(FieldType::Object(to))) => is_reference_assignable(&from.descriptor(), &to, hierarchy),
+                (Some(from @ FieldType::Array(_)), Some(to @ FieldType::Array(_))) => {
+                    is_reference_assignable(&from.descriptor(), &to.descriptor(), hierarchy)
+                }
+                _ => false,
+            };
+        }
+        return to == "java/lang/Cloneable" || to == "java/io/Serializable";
+    }
+    if to.starts_with('[') {
+        return false;
+    }
+    // the type checker treats interfaces like java/lang/Object
+    if hierarchy.is_interface(to) {
+        return true;
+    }
+    super_classes(from, hierarchy).iter().any(|name| name == to)
+}
+
+// the super classes of `class_name`, nearest first; the walk ends at a class seen before, since on a broken
+// classpath a class can be its own super class
+fn super_classes(class_name: &str, hierarchy: &dyn ClassHierarchy) -> Vec<String> {
+    let mut supers: Vec<String> = Vec::new();
+    let mut current = hierarchy.super_class(class_name);
+    while let Some(name) = current {
+        if name == class_name || supers.contains(&name) {
+            break;
+        }
+        current = hierarchy.super_class(&name);
+        supers.push(name);
+    }
+    supers
+}
+
+// the type of a slot reached with both `a` and `b`; Top if they have nothing in common
+pub fn merge_types(a: &VType, b: &VType, hierarchy: &dyn ClassHierarchy) -> VType {
+    match (a, b) {
+        (a, b) if a == b => a.clone(),
+        (VType::Null, VType::Reference(_)) => b.clone(),
+        (VType::Reference(_), VType::Null) => a.clone(),
+        (VType::Reference(a), VType::Reference(b)) => VType::Reference(common_super_class(a, b, hierarchy)),
+        _ => VType::Top,
+    }
+}
+
+// the most specific class both `a` and `b` are assignable to, interfaces count as java/lang/Object
+fn common_super_class(a: &str, b: &str, hierarchy: &dyn ClassHierarchy) -> String {
+    if a == b {
+        return String::from(a);
+    }
+    if let (Some(a_component), Some(b_component)) = (a.strip_prefix('['), b.strip_prefix('[')) {
+        let component_name = |component: FieldType| match component {
+            FieldType::Object(name) => Some(name),
+            FieldType::Array(_) => Some(component.descriptor()),
+            _ => None,
+        };
+        let a_name = parse_field_descriptor(a_component).and_then(component_name);
+        let b_name = parse_field_descriptor(b_component).and_then(component_name);
+        if let (Some(a_name), Some(b_name)) = (a_name, b_name) {
+            let common = common_super_class(&a_name, &b_name, hierarchy);
+            return if common.starts_with('[') {
+                format!("[{}", common)
+            } else {
+                format!("[L{};", common)
+            };
+        }
+    }
+    if a.starts_with('[') || b.starts_with('[') || hierarchy.is_interface(a) || hierarchy.is_interface(b) {
+        return String::from("java/lang/Object");
+    }
+    let mut ancestors = vec![String::from(a)];
+    ancestors.extend(super_classes(a, hierarchy));
+    std::iter::once(String::from(b))
+        .chain(super_classes(b, hierarchy))
+        .find(|name| ancestors.contains(name))
+        .unwrap_or_else(|| String::from("java/lang/Object"))
+}
+
+// turns a list of types where long/double count once into slots, padding with Top up to `size`
+pub fn expand_types(types: &[VType], size: usize) -> Option<Vec<VType>> {
+    let mut result = Vec::with_capacity(size);
+    for t in types {
+        result.push(t.clone());
+        if t.is_category2() {
+            result.push(VType::Top);
+        }
+    }
+    if result.len() > size {
+        return None;
+    }
+    result.resize(size, VType::Top);
+    Some(result)
+}
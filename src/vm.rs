@@ -0,0 +1,528 @@
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::classfile::descriptor::{parse_method_descriptor, FieldType};
+use crate::classpath::Classpath;
+use crate::debugger::{Agent, Debugger, Repl};
+use crate::error::Error;
+use crate::instructions::Flow;
+use crate::interpreter::{initialize, linkage_error, stack_overflow, Interpreter};
+use crate::log::{LogCategory, LogLevel, Logger};
+use crate::native::Output;
+use crate::runtime::class::Class;
+use crate::runtime::class_loader::ClassLoader;
+use crate::runtime::heap::{new_heap, GcKind, ObjectRef};
+use crate::runtime::call_site::REF_INVOKE_STATIC;
+use crate::runtime::{Frame, Slot, Thread};
+use crate::trace::{TraceKind, Tracer};
+use crate::verifier::VerifyMode;
+
+// the -Xss default, as on 64-bit HotSpot
+const DEFAULT_STACK_SIZE: usize = 1 << 20;
+// the -Xmx default
+const DEFAULT_HEAP_SIZE: usize = 256 << 20;
+// the default share of the heap that is the nursery of the generational collector, as HotSpot's NewRatio=2
+const DEFAULT_YOUNG_RATIO: usize = 3;
+
+// a Java virtual machine to run Java code in-process, on the thread of the caller: calls run the Java threads
+// they start as well, until the called method returns or throws
+pub struct Vm {
+    interpreter: Interpreter,
+    // the thread calls run on, made and booted on the first call
+    thread: Option<Thread>,
+    // after a deadlock or a JDK that failed to initialize nothing runs any more
+    dead: bool,
+}
+
+// the options of a Vm, with those of the java launcher as defaults
+pub struct VmBuilder {
+    classpath: Option<String>,
+    jre: Option<PathBuf>,
+    verify_mode: VerifyMode,
+    stack_size: usize,
+    heap_size: usize,
+    young_size: Option<usize>,
+    gc_kind: GcKind,
+    verbose_gc: bool,
+    traces: Vec<TraceKind>,
+    profile: bool,
+    properties: Vec<(String, String)>,
+    output: Output,
+    logs: Vec<(LogCategory, LogLevel)>,
+    log_output: Option<Box<dyn Write>>,
+    debugger: Option<Box<dyn Debugger>>,
+}
+
+// a Java value going into or coming out of a call
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JValue {
+    Void,
+    Boolean(bool),
+    Byte(i8),
+    Char(u16),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Object(Option<JObject>),
+}
+
+// a Java object the embedder holds, kept from the collector until it is released with Vm::release
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JObject(ObjectRef);
+
+// an exception a call threw and did not catch, as the JVM reports it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JavaException {
+    // the binary name, e.g. java.lang.IllegalStateException
+    pub class_name: String,
+    pub message: Option<String>,
+    // the frames from the innermost one out, as in a printed stack trace
+    pub stack_trace: Vec<String>,
+}
+
+impl Vm {
+    pub fn builder() -> VmBuilder {
+        VmBuilder {
+            classpath: None,
+            jre: None,
+            verify_mode: VerifyMode::Remote,
+            stack_size: DEFAULT_STACK_SIZE,
+            heap_size: DEFAULT_HEAP_SIZE,
+            young_size: None,
+            gc_kind: GcKind::MarkSweep,
+            verbose_gc: false,
+            traces: Vec::new(),
+            profile: false,
+            properties: Vec::new(),
+            output: Output::default(),
+            logs: Vec::new(),
+            log_output: None,
+            debugger: None,
+        }
+    }
+
+    // runs static void main(String[]) of class `class_name` the way the java launcher does, until every
+    // non-daemon thread has terminated, reporting what they do not catch on stderr
+    pub fn run_main(&mut self, class_name: &str, args: &[String]) -> Result<(), Error> {
+        let class = self.interpreter.loader().load_class(class_name)?;
+        let main = class.methods.iter().find(|method| {
+            method.name == "main" && method.descriptor == "([Ljava/lang/String;)V" && method.is_static()
+        });
+        let main = main.ok_or_else(|| Error::MainNotFound(class.java_name()))?.clone();
+        self.interpreter.interpret(class, main, args)
+    }
+
+    // loads class `class_name`, e.g. com/example/Plugin, giving its java.lang.Class object
+    pub fn load_class(&mut self, class_name: &str) -> Result<JObject, JavaException> {
+        let class = self.class(class_name)?;
+        self.with_thread(|interpreter, thread| {
+            let mirror = interpreter.env(thread).mirror(&class)?;
+            thread.handles.push(mirror);
+            Ok(JObject(mirror))
+        })
+    }
+
+    // calls static method `name` of class `class_name` with the given descriptor, initializing the class first,
+    // and gives what it returns, JValue::Void for a void method
+    pub fn call_static(
+        &mut self,
+        class_name: &str,
+        name: &str,
+        descriptor: &str,
+        args: &[JValue],
+    ) -> Result<JValue, JavaException> {
+        let class = self.class(class_name)?;
+        let signature = format!("{}.{}{}", class.java_name(), name, descriptor);
+        let method = class.methods.iter().find(|method| method.name == name && method.descriptor == descriptor);
+        let mut method = match method {
+            Some(method) if method.is_static() => method.clone(),
+            _ => return Err(JavaException::new("java.lang.NoSuchMethodError", Some(signature))),
+        };
+        // natives are called from Java code, an invoker here, which calls the method like invokestatic would
+        let mut frame_class = class.clone();
+        if method.code.is_none() {
+            let invoker = self.interpreter.invoker(REF_INVOKE_STATIC, &class, &method);
+            (frame_class, method) = invoker.map_err(JavaException::internal)?;
+        }
+        let parsed = parse_method_descriptor(descriptor).expect("a loaded method has a valid descriptor");
+        self.thread()?;
+        let Some(slots) = self.arg_slots(&parsed.parameters, args) else {
+            let message = Some(String::from("argument type mismatch"));
+            return Err(JavaException::new("java.lang.IllegalArgumentException", message));
+        };
+        let mut frame = Frame::new(frame_class, method);
+        for (index, slot) in slots.into_iter().enumerate() {
+            frame.local_vars.0[index] = slot;
+        }
+        self.with_thread(|_, thread| {
+            thread.push_frame(frame).map_err(|_| stack_overflow())?;
+            // the initializers go on top of the method, to run before it
+            match initialize(thread, &class) {
+                None | Some(Flow::Next) => Ok(()),
+                Some(flow) => {
+                    thread.pop_frame();
+                    Err(flow)
+                }
+            }
+        })?;
+        let value = self.run()?;
+        Ok(match parsed.return_type {
+            None => JValue::Void,
+            Some(FieldType::Boolean) => JValue::Boolean(value[0].int() != 0),
+            Some(FieldType::Byte) => JValue::Byte(value[0].int() as i8),
+            Some(FieldType::Char) => JValue::Char(value[0].int() as u16),
+            Some(FieldType::Short) => JValue::Short(value[0].int() as i16),
+            Some(FieldType::Int) => JValue::Int(value[0].int()),
+            Some(FieldType::Float) => JValue::Float(value[0].float()),
+            Some(FieldType::Long) => JValue::Long(Slot::join(&value[0], &value[1], false) as i64),
+            Some(FieldType::Double) => JValue::Double(f64::from_bits(Slot::join(&value[0], &value[1], true))),
+            Some(FieldType::Object(_) | FieldType::Array(_)) => {
+                let object = value[0].reference();
+                self.thread.as_mut().expect("the thread just ran").handles.extend(object);
+                JValue::Object(object.map(JObject))
+            }
+        })
+    }
+
+    // a new java.lang.String with the characters of `value`
+    pub fn new_string(&mut self, value: &str) -> Result<JObject, JavaException> {
+        self.with_thread(|interpreter, thread| {
+            let string = interpreter.env(thread).new_string(value)?;
+            thread.handles.push(string);
+            Ok(JObject(string))
+        })
+    }
+
+    // the characters of `string`, None when it is no java.lang.String
+    pub fn string_value(&mut self, string: JObject) -> Option<String> {
+        let thread = self.thread.as_mut()?;
+        let env = self.interpreter.env(thread);
+        (env.heap[string.0].class.name == "java/lang/String").then(|| env.rust_string(string.0))
+    }
+
+    // lets the collector have `object` once nothing in Java refers to it either
+    pub fn release(&mut self, object: JObject) {
+        if let Some(thread) = &mut self.thread {
+            if let Some(index) = thread.handles.iter().position(|handle| *handle == object.0) {
+                thread.handles.swap_remove(index);
+            }
+        }
+    }
+
+    fn class(&mut self, class_name: &str) -> Result<Rc<Class>, JavaException> {
+        self.interpreter.loader().load_class(class_name).map_err(|error| match linkage_error(error) {
+            Ok((class_name, message)) => JavaException::new(&class_name.replace('/', "."), Some(message)),
+            Err(error) => JavaException::internal(error),
+        })
+    }
+
+    // the slots of `args` when they are of the types of `parameters`
+    fn arg_slots(&mut self, parameters: &[FieldType], args: &[JValue]) -> Option<Vec<Slot>> {
+        if parameters.len() != args.len() {
+            return None;
+        }
+        let mut slots = Vec::new();
+        for (parameter, arg) in parameters.iter().zip(args) {
+            match (parameter, arg) {
+                (FieldType::Boolean, JValue::Boolean(value)) => slots.push(Slot::Int(*value as i32)),
+                (FieldType::Byte, JValue::Byte(value)) => slots.push(Slot::Int(*value as i32)),
+                (FieldType::Char, JValue::Char(value)) => slots.push(Slot::Int(*value as i32)),
+                (FieldType::Short, JValue::Short(value)) => slots.push(Slot::Int(*value as i32)),
+                (FieldType::Int, JValue::Int(value)) => slots.push(Slot::Int(*value)),
+                (FieldType::Float, JValue::Float(value)) => slots.push(Slot::Float(*value)),
+                (FieldType::Long, JValue::Long(value)) => slots.extend(Slot::wide(*value as u64, false)),
+                (FieldType::Double, JValue::Double(value)) => slots.extend(Slot::wide(value.to_bits(), true)),
+                (FieldType::Object(_) | FieldType::Array(_), JValue::Object(None)) => slots.push(Slot::Reference(None)),
+                (FieldType::Object(_) | FieldType::Array(_), JValue::Object(Some(object))) => {
+                    let class_name = match parameter {
+                        FieldType::Object(class_name) => class_name.clone(),
+                        array => array.descriptor(),
+                    };
+                    let type_ = self.interpreter.loader().load_class(&class_name).ok()?;
+                    let thread = self.thread.as_mut()?;
+                    if !self.interpreter.env(thread).heap[object.0].class.is_assignable_to(&type_) {
+                        return None;
+                    }
+                    slots.push(Slot::Reference(Some(object.0)));
+                }
+                _ => return None,
+            }
+        }
+        Some(slots)
+    }
+
+    // the thread calls run on, booting the VM on it the first time
+    fn thread(&mut self) -> Result<&mut Thread, JavaException> {
+        if self.dead {
+            return Err(JavaException::internal(Error::Deadlock));
+        }
+        if self.thread.is_none() {
+            let mut thread = self.interpreter.new_embedded_thread("main");
+            self.interpreter.boot(&mut thread).map_err(JavaException::internal)?;
+            self.thread = Some(thread);
+            // there is no going on with a JDK that failed to initialize
+            if let Err(exception) = self.run() {
+                self.dead = true;
+                return Err(exception);
+            }
+        }
+        Ok(self.thread.as_mut().expect("the thread was just made"))
+    }
+
+    // runs `action` on the thread calls run on, what it raises or throws becoming the JavaException
+    fn with_thread<T>(
+        &mut self,
+        action: impl FnOnce(&mut Interpreter, &mut Thread) -> Result<T, Flow>,
+    ) -> Result<T, JavaException> {
+        self.thread()?;
+        let thread = self.thread.as_mut().expect("the thread was just made");
+        match action(&mut self.interpreter, thread) {
+            Ok(value) => Ok(value),
+            Err(flow) => {
+                // thrown where nothing can catch it, the exception becomes the outcome
+                self.interpreter.fail(thread, flow).map_err(JavaException::internal)?;
+                Err(self.outcome().expect_err("an exception was thrown"))
+            }
+        }
+    }
+
+    // runs the frames on the thread calls run on to the end, giving what the bottom one returned
+    fn run(&mut self) -> Result<Vec<Slot>, JavaException> {
+        let thread = self.thread.take().expect("no thread to run");
+        let thread = self.interpreter.complete(thread).map_err(|error| {
+            self.dead = true;
+            JavaException::internal(error)
+        })?;
+        self.thread = Some(thread);
+        self.outcome()
+    }
+
+    // how the last call on the thread calls run on ended
+    fn outcome(&mut self) -> Result<Vec<Slot>, JavaException> {
+        let thread = self.thread.as_mut().expect("no thread ran");
+        match thread.outcome.take() {
+            None => Ok(Vec::new()),
+            Some(Ok(value)) => Ok(value),
+            Some(Err(exception)) => Err(self.exception(exception)),
+        }
+    }
+
+    fn exception(&mut self, exception: ObjectRef) -> JavaException {
+        let thread = self.thread.as_mut().expect("the exception's thread");
+        let env = self.interpreter.env(thread);
+        let object = &env.heap[exception];
+        let message = object.get_ref_field("detailMessage", "Ljava/lang/String;");
+        JavaException {
+            class_name: object.class.java_name(),
+            message: message.map(|message| env.rust_string(message)),
+            stack_trace: object.stack_trace.borrow().iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+impl VmBuilder {
+    // where the application's classes are, directories and jars separated like the -classpath of java
+    pub fn classpath(mut self, classpath: &str) -> VmBuilder {
+        self.classpath = Some(String::from(classpath));
+        self
+    }
+
+    // the JRE whose class library to run on; without one the VM runs on its built-in class library
+    pub fn jre(mut self, jre: impl Into<PathBuf>) -> VmBuilder {
+        self.jre = Some(jre.into());
+        self
+    }
+
+    // which classes the verifier checks, as -Xverify
+    pub fn verify_mode(mut self, verify_mode: VerifyMode) -> VmBuilder {
+        self.verify_mode = verify_mode;
+        self
+    }
+
+    // the most bytes a thread's stack takes, as -Xss
+    pub fn stack_size(mut self, stack_size: usize) -> VmBuilder {
+        self.stack_size = stack_size;
+        self
+    }
+
+    // the most bytes the heap takes, as -Xmx
+    pub fn heap_size(mut self, heap_size: usize) -> VmBuilder {
+        self.heap_size = heap_size;
+        self
+    }
+
+    // the bytes of the heap that are the generational collector's nursery, as -Xmn
+    pub fn young_size(mut self, young_size: usize) -> VmBuilder {
+        self.young_size = Some(young_size);
+        self
+    }
+
+    pub fn gc(mut self, gc_kind: GcKind) -> VmBuilder {
+        self.gc_kind = gc_kind;
+        self
+    }
+
+    // whether the collector logs what it does to standard output, as -verbose:gc
+    pub fn verbose_gc(mut self, verbose_gc: bool) -> VmBuilder {
+        self.verbose_gc = verbose_gc;
+        self
+    }
+
+    // logs what the interpreter runs to standard error, as -Xtrace:inst or -Xtrace:call
+    pub fn trace(mut self, kind: TraceKind) -> VmBuilder {
+        self.traces.push(kind);
+        self
+    }
+
+    // whether the interpreter counts and times what it runs, to report on standard error when run_main ends, as -Xprof
+    pub fn profile(mut self, profile: bool) -> VmBuilder {
+        self.profile = profile;
+        self
+    }
+
+    // a system property, as -D
+    pub fn property(mut self, key: &str, value: &str) -> VmBuilder {
+        self.properties.push((String::from(key), String::from(value)));
+        self
+    }
+
+    // where System.out and the -verbose:gc log go
+    pub fn stdout(mut self, stdout: impl Write + 'static) -> VmBuilder {
+        self.output.stdout = Box::new(stdout);
+        self
+    }
+
+    // where System.err, the reports of uncaught exceptions and the -Xtrace and -Xprof output go
+    pub fn stderr(mut self, stderr: impl Write + 'static) -> VmBuilder {
+        self.output.stderr = Box::new(stderr);
+        self
+    }
+
+    // logs the VM's own diagnostics of `category` at `level` and the levels above it, as -Xlog
+    pub fn log(mut self, category: LogCategory, level: LogLevel) -> VmBuilder {
+        self.logs.retain(|(logged, _)| *logged != category);
+        self.logs.push((category, level));
+        self
+    }
+
+    // where the VM's own diagnostics go, standard error by default
+    pub fn log_output(mut self, output: impl Write + 'static) -> VmBuilder {
+        self.log_output = Some(Box::new(output));
+        self
+    }
+
+    // stops the first thread to run before its first instruction, and then the threads at the breakpoints,
+    // watchpoints and steps set, for the commands read from `input` to inspect them, as --debug
+    pub fn debugger(mut self, input: impl BufRead + 'static, output: impl Write + 'static) -> VmBuilder {
+        self.debugger = Some(Box::new(Repl::new(Box::new(input), Box::new(output))));
+        self
+    }
+
+    // serves JDWP on `listener` for jdb or an IDE to attach to, like -agentlib:jdwp; with `suspend` the first thread
+    // to run waits for one before its first instruction
+    pub fn jdwp(mut self, listener: TcpListener, suspend: bool) -> VmBuilder {
+        self.debugger = Some(Box::new(Agent::new(listener, suspend)));
+        self
+    }
+
+    pub fn build(self) -> Result<Vm, Error> {
+        let logger = Rc::new(Logger::new(&self.logs, self.log_output));
+        let user_classpath = self.classpath.ok_or(Error::ClasspathNotSet())?;
+        let young_size = self.young_size.unwrap_or(self.heap_size / DEFAULT_YOUNG_RATIO);
+        let heap = new_heap(self.gc_kind, self.heap_size, young_size, self.verbose_gc, logger.clone());
+        let mut properties = vec![(String::from("java.class.path"), user_classpath.clone())];
+        let classpath = match self.jre {
+            Some(java_home) => {
+                properties.push((String::from("java.home"), java_home.to_string_lossy().into_owned()));
+                Classpath::init_classpath(java_home.join("lib"), PathBuf::from(user_classpath), logger)?
+            }
+            None => Classpath::builtin_classpath(PathBuf::from(user_classpath), logger),
+        };
+        properties.extend(self.properties);
+        let loader = ClassLoader::new(classpath, self.verify_mode);
+        Ok(Vm {
+            interpreter: Interpreter::new(
+                loader,
+                self.stack_size,
+                heap,
+                properties,
+                self.output,
+                Tracer::new(&self.traces, self.profile),
+                self.debugger,
+            ),
+            thread: None,
+            dead: false,
+        })
+    }
+}
+
+impl JavaException {
+    fn new(class_name: &str, message: Option<String>) -> JavaException {
+        JavaException {
+            class_name: String::from(class_name),
+            message,
+            stack_trace: Vec::new(),
+        }
+    }
+
+    // a failure of the VM itself, which the JVM would report as an InternalError
+    fn internal(error: Error) -> JavaException {
+        JavaException::new("java.lang.InternalError", Some(error.to_string()))
+    }
+}
+
+impl fmt::Display for JavaException {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {}", self.class_name, message),
+            None => write!(f, "{}", self.class_name),
+        }
+    }
+}
+
+impl std::error::Error for JavaException {}
+
+// a directory of classes for a test to run a VM on, removed again when the test is done with it, passed or not
+#[cfg(test)]
+pub(crate) struct TestClasspath {
+    pub(crate) path: PathBuf,
+}
+
+#[cfg(test)]
+impl TestClasspath {
+    pub(crate) fn new(classes: &[crate::classfile::writer::ClassDef]) -> TestClasspath {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        // tests run side by side, so each gets a directory of its own
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("learn_jvm_{}_{}", std::process::id(), COUNT.fetch_add(1, Ordering::Relaxed));
+        let classpath = TestClasspath { path: std::env::temp_dir().join(name) };
+        std::fs::create_dir_all(&classpath.path).unwrap();
+        for class in classes {
+            classpath.add(&format!("{}.class", class.name), &class.write());
+        }
+        classpath
+    }
+
+    // writes a file other than a class, or a class made some other way
+    pub(crate) fn add(&self, file_name: &str, content: &[u8]) {
+        let path = self.path.join(file_name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    pub(crate) fn builder(&self) -> VmBuilder {
+        Vm::builder().classpath(&self.path.to_string_lossy())
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestClasspath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}